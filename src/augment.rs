@@ -0,0 +1,530 @@
+//! Random data augmentation for image batches, with a record of every
+//! choice made.
+//!
+//! A [`Pipeline`] chains random crops, horizontal flips and mixup. Running
+//! it on a batch `[n, c, h, w]` draws the parameters of each step for each
+//! sample from an [`Rng`] and returns them with the augmented batch, as a
+//! [`BatchRecord`]. Records collected over an epoch form an
+//! [`AugmentLog`], which saves to and loads from a small text file: an
+//! ablation can then [`Pipeline::replay`] the exact augmented stream of an
+//! earlier run, whatever else changed in between.
+//!
+//! The file format is one line per sample, with a line per batch:
+//!
+//! ```text
+//! delta-augment 1
+//! batch 2
+//! crop 3 1 flip 0 mixup 1 0.72
+//! crop 0 4 flip 1 mixup 0 0.28
+//! ```
+
+use std::fmt;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::random::Rng;
+use crate::tensor::Tensor;
+
+const HEADER: &str = "delta-augment 1";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Step {
+    RandomCrop { size: [usize; 2], padding: usize },
+    HorizontalFlip { p: f32 },
+    Mixup { alpha: f32 },
+}
+
+/// One augmentation applied to one sample, with its parameters.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Transform {
+    /// Window of the (zero padded) image kept, by its top left corner.
+    Crop { top: usize, left: usize },
+    /// Whether the image was mirrored left to right.
+    Flip(bool),
+    /// Blended as `lambda · self + (1 - lambda) · batch[partner]`.
+    Mixup { partner: usize, lambda: f32 },
+}
+
+impl fmt::Display for Transform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Transform::Crop { top, left } => write!(f, "crop {} {}", top, left),
+            Transform::Flip(flip) => write!(f, "flip {}", flip as u8),
+            // Display of an f32 round-trips exactly
+            Transform::Mixup { partner, lambda } => write!(f, "mixup {} {}", partner, lambda),
+        }
+    }
+}
+
+/// The transforms applied to each sample of one batch, in pipeline order.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BatchRecord {
+    pub samples: Vec<Vec<Transform>>,
+}
+
+impl BatchRecord {
+    /// Mix per-sample targets `[n, ...]` (one-hot labels, say) as the
+    /// mixup steps mixed the inputs. Targets pass through unchanged when
+    /// there was no mixup.
+    ///
+    /// # Panics
+    /// Panics if `targets` does not have one row per sample.
+    pub fn mix_targets(&self, targets: &Tensor) -> Tensor {
+        let n = self.samples.len();
+        assert!(
+            targets.ndim() >= 1 && targets.shape()[0] == n,
+            "expected targets for {} samples, got {:?}",
+            n,
+            targets.shape()
+        );
+        let mut rows = targets.to_vec();
+        let width = rows.len() / n.max(1);
+        let steps = self.samples.first().map_or(0, Vec::len);
+        for step in 0..steps {
+            let before = rows.clone();
+            for (i, transforms) in self.samples.iter().enumerate() {
+                if let Transform::Mixup { partner, lambda } = transforms[step] {
+                    for k in 0..width {
+                        rows[i * width + k] = lambda * before[i * width + k]
+                            + (1.0 - lambda) * before[partner * width + k];
+                    }
+                }
+            }
+        }
+        Tensor::from_vec(rows, targets.shape())
+    }
+}
+
+/// Records of the batches of a run, in order. See the
+/// [module docs](self).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AugmentLog {
+    pub batches: Vec<BatchRecord>,
+}
+
+impl AugmentLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, record: BatchRecord) {
+        self.batches.push(record);
+    }
+
+    pub fn len(&self) -> usize {
+        self.batches.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.batches.is_empty()
+    }
+
+    /// Write the log to a file in the text format.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        std::fs::write(path, self.to_string())
+    }
+
+    /// Read a log written by [`AugmentLog::save`].
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        std::fs::read_to_string(path)?
+            .parse()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
+
+impl fmt::Display for AugmentLog {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", HEADER)?;
+        for batch in &self.batches {
+            writeln!(f, "batch {}", batch.samples.len())?;
+            for sample in &batch.samples {
+                let parts: Vec<String> = sample.iter().map(|t| t.to_string()).collect();
+                writeln!(f, "{}", parts.join(" "))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn parse_sample(line: &str) -> Result<Vec<Transform>, String> {
+    let bad = || format!("malformed augmentation line {:?}", line);
+    let mut words = line.split_whitespace();
+    let mut arg = || words.next().ok_or_else(bad);
+    let mut transforms = Vec::new();
+    while let Ok(kind) = arg() {
+        let transform = match kind {
+            "crop" => Transform::Crop {
+                top: arg()?.parse().map_err(|_| bad())?,
+                left: arg()?.parse().map_err(|_| bad())?,
+            },
+            "flip" => Transform::Flip(match arg()? {
+                "0" => false,
+                "1" => true,
+                _ => return Err(bad()),
+            }),
+            "mixup" => Transform::Mixup {
+                partner: arg()?.parse().map_err(|_| bad())?,
+                lambda: arg()?.parse().map_err(|_| bad())?,
+            },
+            _ => return Err(bad()),
+        };
+        transforms.push(transform);
+    }
+    Ok(transforms)
+}
+
+impl FromStr for AugmentLog {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        let mut lines = s.lines().filter(|line| !line.trim().is_empty());
+        if lines.next() != Some(HEADER) {
+            return Err(format!(
+                "not an augmentation log: expected {:?} on the first line",
+                HEADER
+            ));
+        }
+        let mut log = AugmentLog::new();
+        while let Some(line) = lines.next() {
+            let n: usize = line
+                .strip_prefix("batch ")
+                .and_then(|n| n.parse().ok())
+                .ok_or_else(|| format!("expected \"batch <samples>\", got {:?}", line))?;
+            let samples = (0..n)
+                .map(|_| {
+                    lines
+                        .next()
+                        .ok_or_else(|| format!("batch of {} samples ends early", n))
+                        .and_then(parse_sample)
+                })
+                .collect::<Result<_, _>>()?;
+            log.push(BatchRecord { samples });
+        }
+        Ok(log)
+    }
+}
+
+/// Gamma(shape, 1) by Marsaglia and Tsang's method.
+fn gamma(shape: f32, rng: &mut Rng) -> f32 {
+    if shape < 1.0 {
+        // Boost to shape + 1, then scale back down
+        let u = 1.0 - rng.uniform();
+        return gamma(shape + 1.0, rng) * u.powf(1.0 / shape);
+    }
+    let d = shape - 1.0 / 3.0;
+    let c = 1.0 / (9.0 * d).sqrt();
+    loop {
+        let x = rng.normal();
+        let v = (1.0 + c * x).powi(3);
+        if v <= 0.0 {
+            continue;
+        }
+        let u = 1.0 - rng.uniform();
+        if u.ln() < 0.5 * x * x + d - d * v + d * v.ln() {
+            return d * v;
+        }
+    }
+}
+
+/// A chain of random augmentations for image batches `[n, c, h, w]`.
+///
+/// # Example
+/// ```
+/// use delta::augment::{AugmentLog, Pipeline};
+/// use delta::random::Rng;
+/// use delta::tensor::Tensor;
+///
+/// let pipeline = Pipeline::new().random_crop([8, 8], 2).horizontal_flip(0.5).mixup(0.4);
+/// let images = Tensor::randn(&[4, 3, 8, 8], &mut Rng::new(0));
+///
+/// // Training run: augment, and keep the log
+/// let mut rng = Rng::new(1);
+/// let mut log = AugmentLog::new();
+/// let (augmented, record) = pipeline.apply(&images, &mut rng);
+/// log.push(record);
+///
+/// // Ablation run: the same stream, from the saved text
+/// let reloaded: AugmentLog = log.to_string().parse().unwrap();
+/// let again = pipeline.replay(&images, &reloaded.batches[0]);
+/// assert_eq!(again.to_vec(), augmented.to_vec());
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Pipeline {
+    steps: Vec<Step>,
+}
+
+impl Pipeline {
+    /// An empty pipeline, which leaves batches unchanged.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Crop a random `size` window out of each image, zero padded by
+    /// `padding` on every side first.
+    pub fn random_crop(mut self, size: [usize; 2], padding: usize) -> Self {
+        self.steps.push(Step::RandomCrop { size, padding });
+        self
+    }
+
+    /// Mirror each image left to right with probability `p`.
+    pub fn horizontal_flip(mut self, p: f32) -> Self {
+        self.steps.push(Step::HorizontalFlip { p });
+        self
+    }
+
+    /// Blend each image with a random other image of the batch, weighted
+    /// by `lambda ~ Beta(alpha, alpha)`. Mix the targets alike with
+    /// [`BatchRecord::mix_targets`].
+    pub fn mixup(mut self, alpha: f32) -> Self {
+        assert!(alpha > 0.0, "mixup needs alpha > 0, got {}", alpha);
+        self.steps.push(Step::Mixup { alpha });
+        self
+    }
+
+    /// Augment `batch`, drawing every parameter from `rng`, and return the
+    /// result with the record of what was applied.
+    ///
+    /// # Panics
+    /// Panics if `batch` is not `[n, c, h, w]`, or a crop does not fit.
+    pub fn apply(&self, batch: &Tensor, rng: &mut Rng) -> (Tensor, BatchRecord) {
+        let n = check_batch(batch);
+        let mut samples = vec![Vec::with_capacity(self.steps.len()); n];
+        let (mut h, mut w) = (batch.shape()[2], batch.shape()[3]);
+        for step in &self.steps {
+            for sample in samples.iter_mut() {
+                let transform = match *step {
+                    Step::RandomCrop { size, padding } => {
+                        let (ph, pw) = (h + 2 * padding, w + 2 * padding);
+                        assert!(
+                            size[0] <= ph && size[1] <= pw,
+                            "crop {:?} does not fit images [{}, {}] padded by {}",
+                            size,
+                            h,
+                            w,
+                            padding
+                        );
+                        Transform::Crop {
+                            top: rng.below(ph - size[0] + 1),
+                            left: rng.below(pw - size[1] + 1),
+                        }
+                    }
+                    Step::HorizontalFlip { p } => Transform::Flip(rng.uniform() < p),
+                    Step::Mixup { alpha } => {
+                        let (a, b) = (gamma(alpha, rng), gamma(alpha, rng));
+                        Transform::Mixup {
+                            partner: rng.below(n),
+                            lambda: a / (a + b),
+                        }
+                    }
+                };
+                sample.push(transform);
+            }
+            if let Step::RandomCrop { size, .. } = *step {
+                [h, w] = size;
+            }
+        }
+        let record = BatchRecord { samples };
+        (self.replay(batch, &record), record)
+    }
+
+    /// Apply exactly the transforms of `record` to `batch`, without
+    /// drawing anything.
+    ///
+    /// # Panics
+    /// Panics if `record` was not made by this pipeline for a batch of
+    /// this shape.
+    pub fn replay(&self, batch: &Tensor, record: &BatchRecord) -> Tensor {
+        let n = check_batch(batch);
+        assert!(
+            record.samples.len() == n && record.samples.iter().all(|s| s.len() == self.steps.len()),
+            "record of {} samples does not match a batch of {} for a pipeline of {} steps",
+            record.samples.len(),
+            n,
+            self.steps.len()
+        );
+        let mut shape: [usize; 4] = batch.shape().try_into().unwrap();
+        let mut data = batch.to_vec();
+        for (s, step) in self.steps.iter().enumerate() {
+            let [_, c, h, w] = shape;
+            let plane = h * w;
+            let next = match *step {
+                Step::RandomCrop { size, padding } => {
+                    let [ch, cw] = size;
+                    let mut out = vec![0.0; n * c * ch * cw];
+                    for (i, sample) in record.samples.iter().enumerate() {
+                        let Transform::Crop { top, left } = sample[s] else {
+                            panic!("record step {} is {}, expected a crop", s, sample[s]);
+                        };
+                        for ci in 0..c {
+                            for y in 0..ch {
+                                for x in 0..cw {
+                                    let (sy, sx) = (top + y, left + x);
+                                    let inside = sy >= padding
+                                        && sx >= padding
+                                        && sy - padding < h
+                                        && sx - padding < w;
+                                    if inside {
+                                        out[((i * c + ci) * ch + y) * cw + x] =
+                                            data[(i * c + ci) * plane + (sy - padding) * w + sx
+                                                - padding];
+                                    }
+                                }
+                            }
+                        }
+                    }
+                    shape = [n, c, ch, cw];
+                    out
+                }
+                Step::HorizontalFlip { .. } => {
+                    let mut out = data.clone();
+                    for (i, sample) in record.samples.iter().enumerate() {
+                        let Transform::Flip(flip) = sample[s] else {
+                            panic!("record step {} is {}, expected a flip", s, sample[s]);
+                        };
+                        if flip {
+                            for row in out[i * c * plane..(i + 1) * c * plane].chunks_mut(w) {
+                                row.reverse();
+                            }
+                        }
+                    }
+                    out
+                }
+                Step::Mixup { .. } => {
+                    let size = c * plane;
+                    let mut out = data.clone();
+                    for (i, sample) in record.samples.iter().enumerate() {
+                        let Transform::Mixup { partner, lambda } = sample[s] else {
+                            panic!("record step {} is {}, expected a mixup", s, sample[s]);
+                        };
+                        assert!(partner < n, "mixup partner {} out of range", partner);
+                        for k in 0..size {
+                            out[i * size + k] = lambda * data[i * size + k]
+                                + (1.0 - lambda) * data[partner * size + k];
+                        }
+                    }
+                    out
+                }
+            };
+            data = next;
+        }
+        Tensor::from_vec(data, &shape)
+    }
+}
+
+fn check_batch(batch: &Tensor) -> usize {
+    assert_eq!(
+        batch.ndim(),
+        4,
+        "augmentation expects image batches [n, c, h, w], got {:?}",
+        batch.shape()
+    );
+    batch.shape()[0]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn images() -> Tensor {
+        Tensor::from_vec(
+            (0..2 * 2 * 3 * 3).map(|v| v as f32).collect(),
+            &[2, 2, 3, 3],
+        )
+    }
+
+    #[test]
+    fn test_replay_reproduces_every_epoch() {
+        let pipeline = Pipeline::new()
+            .random_crop([3, 3], 1)
+            .horizontal_flip(0.5)
+            .mixup(1.0);
+        let mut rng = Rng::new(2);
+        let mut log = AugmentLog::new();
+        let mut outputs = Vec::new();
+        for _ in 0..5 {
+            let (out, record) = pipeline.apply(&images(), &mut rng);
+            outputs.push(out.to_vec());
+            log.push(record);
+        }
+        let path = std::env::temp_dir().join(format!("delta-augment-{}.txt", std::process::id()));
+        log.save(&path).unwrap();
+        let loaded = AugmentLog::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded, log);
+        for (record, expected) in loaded.batches.iter().zip(outputs) {
+            assert_eq!(pipeline.replay(&images(), record).to_vec(), expected);
+        }
+    }
+
+    #[test]
+    fn test_transforms() {
+        let record = |t: Vec<Transform>| BatchRecord {
+            samples: vec![t.clone(), t],
+        };
+        let crop = Pipeline::new().random_crop([2, 2], 1);
+        let out = crop.replay(
+            &images(),
+            &record(vec![Transform::Crop { top: 0, left: 2 }]),
+        );
+        assert_eq!(out.shape(), &[2, 2, 2, 2]);
+        // Padding row on top, then columns 1..3 of row 0
+        assert_eq!(out.to_vec()[..4], [0.0, 0.0, 1.0, 2.0]);
+
+        let flip = Pipeline::new().horizontal_flip(1.0);
+        let out = flip.replay(&images(), &record(vec![Transform::Flip(true)]));
+        assert_eq!(out.to_vec()[..3], [2.0, 1.0, 0.0]);
+
+        let mix = Pipeline::new().mixup(0.2);
+        let r = record(vec![Transform::Mixup {
+            partner: 1,
+            lambda: 0.25,
+        }]);
+        let out = mix.replay(&images(), &r).to_vec();
+        assert_eq!(out[0], 0.75 * 18.0);
+        assert_eq!(out[18], 18.0);
+        let targets = Tensor::from_vec(vec![1.0, 0.0, 0.0, 1.0], &[2, 2]);
+        assert_eq!(r.mix_targets(&targets).to_vec(), vec![0.25, 0.75, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn test_seeds_reproduce_records() {
+        let pipeline = Pipeline::new().random_crop([2, 3], 0).mixup(0.3);
+        let (a, ra) = pipeline.apply(&images(), &mut Rng::new(9));
+        let (b, rb) = pipeline.apply(&images(), &mut Rng::new(9));
+        assert_eq!((a.to_vec(), ra.clone()), (b.to_vec(), rb));
+        for sample in &ra.samples {
+            let Transform::Mixup { lambda, .. } = sample[1] else {
+                panic!("expected mixup");
+            };
+            assert!((0.0..=1.0).contains(&lambda));
+        }
+    }
+
+    #[test]
+    fn test_rejects_malformed_logs() {
+        assert!("batch 1\nflip 1".parse::<AugmentLog>().is_err());
+        let err = "delta-augment 1\nbatch 2\nflip 1"
+            .parse::<AugmentLog>()
+            .unwrap_err();
+        assert_eq!(err, "batch of 2 samples ends early");
+        let err = "delta-augment 1\nbatch 1\nflip 2"
+            .parse::<AugmentLog>()
+            .unwrap_err();
+        assert_eq!(err, "malformed augmentation line \"flip 2\"");
+    }
+
+    #[test]
+    fn test_gamma_mean() {
+        let mut rng = Rng::new(4);
+        for shape in [0.3, 2.5] {
+            let mean = (0..20_000).map(|_| gamma(shape, &mut rng)).sum::<f32>() / 20_000.0;
+            assert!(
+                (mean - shape).abs() < 0.05 * shape.max(1.0),
+                "{} {}",
+                shape,
+                mean
+            );
+        }
+    }
+}
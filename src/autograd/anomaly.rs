@@ -0,0 +1,156 @@
+use std::backtrace::Backtrace;
+use std::cell::Cell;
+
+use crate::autograd::Node;
+use crate::tensor::Tensor;
+
+thread_local! {
+    static ANOMALY_ENABLED: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Whether anomaly detection is currently on.
+pub fn is_anomaly_enabled() -> bool {
+    ANOMALY_ENABLED.with(Cell::get)
+}
+
+/// Turns anomaly detection on until dropped.
+///
+/// While it is alive:
+/// - every recorded op captures a backtrace of where it was called, and
+/// - every backward pass checks the gradients each backward function
+///   returns, and panics at the first NaN or infinity, naming the op and
+///   showing the backtrace of the forward call that created it.
+///
+/// Without it, a non-finite gradient spreads silently through the rest of
+/// the backward pass and only shows up in the parameters. Capturing a
+/// backtrace per op is slow, so turn it on to hunt a bug, not for
+/// training. Guards nest, and the mode is per thread.
+///
+/// # Example
+/// ```should_panic
+/// use delta::autograd::AnomalyGuard;
+/// use delta::tensor::Tensor;
+/// let _guard = AnomalyGuard::new();
+/// let x = Tensor::from_vec(vec![0.0, 1.0], &[2]).requires_grad(true);
+/// // d/dx √x is infinite at 0: panics, pointing at the sqrt
+/// x.sqrt().sum().backward();
+/// ```
+pub struct AnomalyGuard {
+    prev: bool,
+}
+
+impl AnomalyGuard {
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        let prev = ANOMALY_ENABLED.with(|flag| flag.replace(true));
+        Self { prev }
+    }
+}
+
+impl Drop for AnomalyGuard {
+    fn drop(&mut self) {
+        ANOMALY_ENABLED.with(|flag| flag.set(self.prev));
+    }
+}
+
+/// Run `f` with anomaly detection on and return its result.
+///
+/// The closure form of [`AnomalyGuard`]. Both the forward computation and
+/// the backward pass should run inside it: the forward to record where
+/// each op came from, the backward to check the gradients.
+pub fn detect_anomaly<R>(f: impl FnOnce() -> R) -> R {
+    let _guard = AnomalyGuard::new();
+    f()
+}
+
+/// Where a node was created, if anomaly detection was on at the time.
+pub(crate) fn capture_trace() -> Option<Backtrace> {
+    is_anomaly_enabled().then(Backtrace::force_capture)
+}
+
+/// Panic if any gradient the backward function of `node` produced for a
+/// graph input is NaN or infinite.
+pub(crate) fn check_gradients(node: &Node, grads: &[Tensor]) {
+    for (i, (input, g)) in node.inputs().iter().zip(grads).enumerate() {
+        if input.is_none() {
+            continue;
+        }
+        let Some(bad) = g.to_vec().into_iter().find(|v| !v.is_finite()) else {
+            continue;
+        };
+        let origin = match node.trace() {
+            Some(trace) => format!("{} was created at:\n{}", node.op(), trace),
+            None => format!(
+                "{} was created outside anomaly mode, so no trace was recorded",
+                node.op()
+            ),
+        };
+        panic!(
+            "Anomaly detected: backward of {} produced a gradient containing {} for input {} (shape {:?})\n{}",
+            node.op(),
+            bad,
+            i,
+            g.shape(),
+            origin
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_finite_gradients_pass() {
+        let x = Tensor::from_vec(vec![1.0, 4.0], &[2]).requires_grad(true);
+        detect_anomaly(|| x.sqrt().sum().backward());
+        assert_eq!(x.grad().unwrap().to_vec(), vec![0.5, 0.25]);
+        assert!(!is_anomaly_enabled());
+    }
+
+    #[test]
+    fn test_records_provenance_only_in_anomaly_mode() {
+        let x = Tensor::from_vec(vec![1.0], &[1]).requires_grad(true);
+        assert!(x.exp().node().unwrap().trace().is_none());
+        let y = detect_anomaly(|| x.exp());
+        assert!(y.node().unwrap().trace().is_some());
+    }
+
+    #[test]
+    fn test_reports_first_bad_op() {
+        let result = std::panic::catch_unwind(|| {
+            let x = Tensor::from_vec(vec![0.0, 1.0], &[2]).requires_grad(true);
+            detect_anomaly(|| x.sqrt().exp().scalar_mul(2.0).sum().backward())
+        });
+        let message = *result.unwrap_err().downcast::<String>().unwrap();
+        // The ops after the sqrt have finite derivatives; √x' is infinite at 0
+        assert!(
+            message.starts_with(
+                "Anomaly detected: backward of sqrt produced a gradient containing inf for input 0"
+            ),
+            "{}",
+            message
+        );
+        assert!(message.contains("sqrt was created at:"));
+    }
+
+    #[test]
+    #[should_panic(expected = "created outside anomaly mode")]
+    fn test_checks_graphs_recorded_without_it() {
+        let x = Tensor::from_vec(vec![0.0], &[1]).requires_grad(true);
+        let y = x.sqrt().sum();
+        detect_anomaly(|| y.backward());
+    }
+
+    #[test]
+    fn test_guards_nest() {
+        {
+            let _outer = AnomalyGuard::new();
+            {
+                let _inner = AnomalyGuard::new();
+            }
+            assert!(is_anomaly_enabled());
+        }
+        assert!(!is_anomaly_enabled());
+    }
+}
@@ -0,0 +1,273 @@
+use std::rc::Rc;
+
+use crate::autograd::grad_mode::GradModeGuard;
+use crate::autograd::offload::{ScratchFile, Spilled};
+use crate::autograd::{gradients, record_first_order};
+use crate::tensor::Tensor;
+
+/// An input of a checkpointed segment, as kept for the backward pass.
+enum Saved {
+    Kept(Tensor),
+    Spilled(Spilled),
+}
+
+impl Saved {
+    fn load(&self) -> Tensor {
+        match self {
+            Saved::Kept(t) => t.detach(),
+            Saved::Spilled(s) => s.load(),
+        }
+    }
+}
+
+/// Run `f` on `inputs` without keeping its intermediate results, and
+/// recompute them when the backward pass reaches it.
+///
+/// Normally every op inside `f` records a node, and each node keeps its
+/// inputs alive until the graph is dropped: for a deep model that is most
+/// of the memory. A checkpointed segment records a single node instead,
+/// holding only `inputs`. The backward pass runs `f` a second time, with
+/// recording on, to get the gradients, so memory is traded for one extra
+/// forward per segment. Checkpointing every few layers of a deep stack
+/// bounds memory by the size of one segment.
+///
+/// Gradients only flow to `inputs`: pass the weights of the segment as
+/// inputs rather than capturing them in `f`, or they get no gradient.
+/// `f` must compute the same thing both times, so it should not draw
+/// random numbers or otherwise depend on outside state.
+///
+/// The recomputation is not itself part of the graph: gradients through a
+/// checkpoint cannot be differentiated again, and
+/// [`grad`](crate::autograd::grad) with `create_graph` panics on them.
+///
+/// # Example
+/// ```
+/// use delta::autograd::checkpoint;
+/// use delta::tensor::Tensor;
+///
+/// let x = Tensor::from_vec(vec![1.0, -1.0], &[1, 2]);
+/// let w = Tensor::from_vec(vec![0.5, 0.1, -0.3, 0.2], &[2, 2]).requires_grad(true);
+/// let block = |t: &[Tensor]| t[0].matmul(&t[1]).tanh().matmul(&t[1]).tanh();
+///
+/// let y = checkpoint(block, &[x.clone(), w.clone()]);
+/// y.sum().backward();
+/// let checkpointed = w.grad().unwrap().to_vec();
+///
+/// w.zero_grad();
+/// block(&[x, w.clone()]).sum().backward();
+/// assert_eq!(checkpointed, w.grad().unwrap().to_vec());
+/// ```
+pub fn checkpoint(f: impl Fn(&[Tensor]) -> Tensor + 'static, inputs: &[Tensor]) -> Tensor {
+    let saved = inputs.iter().map(|t| Saved::Kept(t.clone())).collect();
+    segment(f, inputs, saved, "checkpoint")
+}
+
+/// [`checkpoint`], with the segment's activations spilled to `scratch`
+/// rather than kept in memory until the backward pass.
+///
+/// A checkpointed segment still holds on to its inputs: in a deep stack,
+/// the activation entering every segment stays in memory for the whole
+/// pass. Here those inputs are written to the scratch file as soon as the
+/// segment has run and read back when the backward pass reaches it, so
+/// resident memory is bounded by a single segment whatever the depth.
+/// Leaf inputs that require gradients (the weights) are not spilled: the
+/// model keeps them anyway.
+///
+/// Choose per layer: offload the segments whose inputs are large, keep
+/// the cheap ones with [`checkpoint`]. Spilled regions of the file are
+/// released when the graph is dropped. The segment is recomputed during
+/// backward as with [`checkpoint`]; what changes is where its inputs wait.
+///
+/// # Panics
+/// Panics if writing or reading the scratch file fails.
+///
+/// # Example
+/// ```
+/// use std::rc::Rc;
+///
+/// use delta::autograd::{ScratchFile, checkpoint_offload};
+/// use delta::tensor::Tensor;
+///
+/// let scratch = Rc::new(ScratchFile::temp().unwrap());
+/// let w = Tensor::from_vec(vec![0.5, 0.1, -0.3, 0.2], &[2, 2]).requires_grad(true);
+/// let layer = |t: &[Tensor]| t[0].matmul(&t[1]).tanh();
+///
+/// let mut h = Tensor::from_vec(vec![1.0, -1.0], &[1, 2]);
+/// for _ in 0..3 {
+///     h = checkpoint_offload(layer, &[h, w.clone()], &scratch);
+/// }
+/// // The activations between layers wait on disk
+/// assert_eq!(scratch.live_regions(), 3);
+/// h.sum().backward();
+/// assert!(w.grad().is_some());
+/// drop(h);
+/// assert_eq!(scratch.bytes_in_use(), 0);
+/// ```
+pub fn checkpoint_offload(
+    f: impl Fn(&[Tensor]) -> Tensor + 'static,
+    inputs: &[Tensor],
+    scratch: &Rc<ScratchFile>,
+) -> Tensor {
+    let saved = inputs
+        .iter()
+        .map(|t| {
+            if t.is_leaf() && t.node().is_some() {
+                Saved::Kept(t.clone())
+            } else {
+                Saved::Spilled(Spilled::new(t, scratch))
+            }
+        })
+        .collect();
+    segment(f, inputs, saved, "checkpoint_offload")
+}
+
+/// Run `f` without recording and record one node that recomputes it from
+/// `saved` during backward.
+fn segment(
+    f: impl Fn(&[Tensor]) -> Tensor + 'static,
+    inputs: &[Tensor],
+    saved: Vec<Saved>,
+    op: &'static str,
+) -> Tensor {
+    let output = {
+        let _guard = GradModeGuard::new(false);
+        f(inputs)
+    };
+
+    let refs: Vec<&Tensor> = inputs.iter().collect();
+    record_first_order(output, op, &refs, move |g| {
+        let leaves: Vec<Tensor> = saved.iter().map(|t| t.load().requires_grad(true)).collect();
+        let recomputed = {
+            let _guard = GradModeGuard::new(true);
+            f(&leaves)
+        };
+        gradients(&recomputed, g.clone(), &leaves, false)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    use super::*;
+    use crate::autograd::grad;
+    use crate::autograd::testing::check_grad;
+
+    fn layer(x: &Tensor, w: &Tensor) -> Tensor {
+        x.matmul(w).tanh()
+    }
+
+    fn inputs() -> [Tensor; 3] {
+        [
+            Tensor::from_vec(vec![0.5, -1.0, 2.0, 0.1, 0.3, -0.7], &[2, 3]),
+            Tensor::from_vec(
+                vec![0.2, -0.4, 0.1, 0.5, 0.3, -0.2, 0.6, 0.1, -0.3],
+                &[3, 3],
+            ),
+            Tensor::from_vec(vec![-0.1, 0.3, 0.2, 0.4, -0.5, 0.2], &[3, 2]),
+        ]
+    }
+
+    #[test]
+    fn test_records_one_node() {
+        let [x, w1, w2] = inputs();
+        let (w1, w2) = (w1.requires_grad(true), w2.requires_grad(true));
+        let y = checkpoint(|t| layer(&layer(&t[0], &t[1]), &t[2]), &[x, w1.clone(), w2]);
+        let node = y.node().unwrap();
+        assert_eq!(node.op(), "checkpoint");
+        // Straight back to the inputs, no intermediate nodes in between
+        assert!(node.inputs()[0].is_none());
+        assert!(node.inputs()[1].as_ref().unwrap().is_leaf());
+    }
+
+    #[test]
+    fn test_gradients() {
+        check_grad(
+            |t| {
+                let block = |s: &[Tensor]| layer(&layer(&s[0], &s[1]), &s[2]);
+                checkpoint(block, t).scalar_mul(2.0)
+            },
+            &inputs(),
+        );
+    }
+
+    #[test]
+    fn test_nested_segments() {
+        let [x, w1, w2] = inputs();
+        let (w1, w2) = (w1.requires_grad(true), w2.requires_grad(true));
+        let h = checkpoint(|t| layer(&t[0], &t[1]), &[x.clone(), w1.clone()]);
+        let y = checkpoint(|t| layer(&t[0], &t[1]), &[h, w2.clone()]);
+        y.sum().backward();
+        let direct = layer(&layer(&x, &w1), &w2).sum();
+        let expected = grad(&direct, &[w1.clone(), w2.clone()], false);
+        for (got, want) in [w1.grad().unwrap(), w2.grad().unwrap()]
+            .iter()
+            .zip(expected)
+        {
+            for (a, b) in got.to_vec().iter().zip(want.to_vec()) {
+                assert!((a - b).abs() < 1e-6);
+            }
+        }
+    }
+
+    #[test]
+    fn test_recomputes_during_backward() {
+        let calls = Rc::new(Cell::new(0));
+        let counter = Rc::clone(&calls);
+        let x = Tensor::from_vec(vec![1.0, 2.0], &[2]).requires_grad(true);
+        let y = checkpoint(
+            move |t| {
+                counter.set(counter.get() + 1);
+                t[0].exp()
+            },
+            std::slice::from_ref(&x),
+        );
+        assert_eq!(calls.get(), 1);
+        y.sum().backward();
+        assert_eq!(calls.get(), 2);
+        assert_eq!(x.grad().unwrap().to_vec(), y.to_vec());
+    }
+
+    #[test]
+    fn test_offload_matches_checkpoint() {
+        let scratch = Rc::new(ScratchFile::temp().unwrap());
+        let [x, w1, w2] = inputs();
+        let (w1, w2) = (w1.requires_grad(true), w2.requires_grad(true));
+        let run = |offload: bool| {
+            w1.zero_grad();
+            w2.zero_grad();
+            let seg = |t: &[Tensor]| layer(&t[0], &t[1]);
+            let mut h = x.clone();
+            for w in [&w1, &w2] {
+                let args = [h, w.clone()];
+                h = if offload {
+                    checkpoint_offload(seg, &args, &scratch)
+                } else {
+                    checkpoint(seg, &args)
+                };
+            }
+            if offload {
+                // x and the hidden activation; the weights stay in memory
+                assert_eq!(scratch.live_regions(), 2);
+                assert_eq!(scratch.bytes_in_use(), 4 * (6 + 6));
+            }
+            h.sum().backward();
+            [w1.grad().unwrap().to_vec(), w2.grad().unwrap().to_vec()]
+        };
+        assert_eq!(run(true), run(false));
+        assert_eq!(scratch.live_regions(), 0);
+    }
+
+    #[test]
+    fn test_offload_gradients() {
+        let scratch = Rc::new(ScratchFile::temp().unwrap());
+        check_grad(
+            move |t| {
+                let h = t[0].scalar_mul(2.0);
+                checkpoint_offload(|s| layer(&s[0], &s[1]), &[h, t[1].clone()], &scratch)
+            },
+            &inputs()[..2],
+        );
+    }
+}
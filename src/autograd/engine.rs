@@ -0,0 +1,699 @@
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+use crate::autograd::Node;
+use crate::autograd::anomaly::{check_gradients, is_anomaly_enabled};
+use crate::autograd::grad_mode::GradModeGuard;
+use crate::tensor::Tensor;
+
+impl Tensor {
+    /// Compute the gradient of this tensor with respect to every tensor it
+    /// was computed from.
+    ///
+    /// Afterwards [`Tensor::grad`] of each tensor in the graph (leaves and
+    /// intermediates) returns d(self)/d(tensor), shaped like that tensor.
+    ///
+    /// Gradients accumulate: running backward again (on this or another
+    /// output) adds to the stored gradients, so gradients of several losses
+    /// or mini-batches sum up. Call [`Tensor::zero_grad`] to reset them.
+    ///
+    /// The backward pass itself is not recorded, so gradients are plain
+    /// tensors.
+    ///
+    /// # Panics
+    /// - Panics if this tensor is not part of a graph
+    /// - Panics if this tensor has more than one element (e.g. a loss
+    ///   that was not reduced)
+    pub fn backward(&self) {
+        let root = self
+            .node()
+            .expect("backward called on a tensor that is not part of a graph");
+        assert_eq!(
+            self.nelems(),
+            1,
+            "backward requires a single-element tensor, got shape {:?}",
+            self.shape()
+        );
+
+        let seed = Tensor::from_vec(vec![1.0], self.shape());
+        run_backward(root, seed);
+    }
+
+    /// Call `hook` with the gradient of this tensor whenever a backward
+    /// pass (or [`grad`](crate::autograd::grad)) computes it.
+    ///
+    /// The hook sees the complete gradient, summed over every use of the
+    /// tensor, before it flows on to the inputs. Returning `None` leaves it
+    /// unchanged; returning a tensor replaces it, both for what is stored
+    /// in [`Tensor::grad`] and for everything upstream. Hooks run in the
+    /// order they were registered, each seeing the result of the previous.
+    ///
+    /// Hooks belong to the graph node, which clones of a tensor share, and
+    /// live as long as it does. A hook that captures its own tensor keeps
+    /// the node alive forever.
+    ///
+    /// # Panics
+    /// - Panics if this tensor is not part of a graph
+    /// - The backward pass panics if a hook returns a gradient of a
+    ///   different shape
+    ///
+    /// # Example
+    /// ```
+    /// use delta::tensor::Tensor;
+    /// let x = Tensor::from_vec(vec![1.0, 2.0], &[2]).requires_grad(true);
+    /// let h = x.scalar_mul(3.0);
+    /// // Clip the gradient flowing through h to [-1, 1]
+    /// h.register_hook(|g| Some(g.map(|v| v.clamp(-1.0, 1.0))));
+    /// h.powi(2).sum().backward();
+    /// assert_eq!(h.grad().unwrap().to_vec(), vec![1.0, 1.0]);
+    /// assert_eq!(x.grad().unwrap().to_vec(), vec![3.0, 3.0]);
+    /// ```
+    pub fn register_hook(&self, hook: impl Fn(&Tensor) -> Option<Tensor> + 'static) {
+        let node = self
+            .node()
+            .expect("register_hook called on a tensor that is not part of a graph");
+        node.add_hook(Box::new(hook));
+    }
+}
+
+/// Evaluate `f` at `params` and differentiate the result with respect to
+/// each of them.
+///
+/// The params are handed to `f` as fresh leaves, detached from whatever
+/// graph they came from, and recording is switched on for the call. A
+/// param the result does not depend on gets a zero gradient.
+///
+/// # Panics
+/// Panics if `f` returns more than one element.
+pub(crate) fn value_and_grad(
+    f: impl FnOnce(&[Tensor]) -> Tensor,
+    params: &[Tensor],
+) -> (Tensor, Vec<Tensor>) {
+    let leaves: Vec<Tensor> = params
+        .iter()
+        .map(|p| p.detach().requires_grad(true))
+        .collect();
+
+    let value = {
+        let _guard = GradModeGuard::new(true);
+        f(&leaves)
+    };
+    assert_eq!(
+        value.nelems(),
+        1,
+        "expected a single-element output, got shape {:?}",
+        value.shape()
+    );
+    if value.node().is_some() {
+        value.backward();
+    }
+
+    let grads = leaves
+        .iter()
+        .map(|leaf| leaf.grad().unwrap_or_else(|| Tensor::zeros(leaf.shape())))
+        .collect();
+    (value, grads)
+}
+
+/// Run a backward pass from `root` and add the gradient to every node it
+/// reaches.
+fn run_backward(root: &Rc<Node>, seed: Tensor) {
+    for (node, grad) in propagate(root, seed, None, false) {
+        node.accumulate_grad(grad);
+    }
+}
+
+/// Gradients of a single-element `output` with respect to each of
+/// `inputs`, returned instead of stored.
+///
+/// With `create_graph`, the backward pass is itself recorded: the
+/// gradients are part of the graph and can be differentiated again. That
+/// gives second derivatives, and losses that contain a gradient, such as
+/// gradient penalties (WGAN-GP) or the inner update of MAML. Without it,
+/// the gradients are plain tensors.
+///
+/// Unlike [`Tensor::backward`], nothing is stored on the graph, so
+/// repeated calls do not accumulate. An input `output` does not depend on
+/// gets zeros. The backward pass is pruned to the nodes between `output`
+/// and `inputs`: branches that only lead to other leaves are skipped.
+///
+/// A few backward rules are computed outside the graph: those of `prod`,
+/// `prod_dim`, `digamma`, the [`Csr`](crate::tensor::sparse::Csr) products
+/// and softmax, and [`checkpoint`](crate::autograd::checkpoint). With
+/// `create_graph` they panic rather than record a gradient that
+/// differentiates to zero. The rules of a
+/// [`Function`](crate::autograd::Function) are recorded like any other
+/// ops, and are only right to the extent they are built from them.
+///
+/// # Panics
+/// - Panics if `output` is not part of a graph
+/// - Panics if `output` has more than one element
+/// - Panics with `create_graph` if the backward pass reaches one of the
+///   rules computed outside the graph
+///
+/// # Example
+/// ```
+/// use delta::autograd::grad;
+/// use delta::tensor::Tensor;
+/// // f(x) = x³: f'(x) = 3x², f''(x) = 6x
+/// let x = Tensor::from_vec(vec![2.0], &[1]).requires_grad(true);
+/// let y = x.powi(3).sum();
+/// let dy = grad(&y, &[x.clone()], true);
+/// assert_eq!(dy[0].to_vec(), vec![12.0]);
+/// let d2y = grad(&dy[0].sum(), &[x.clone()], false);
+/// assert_eq!(d2y[0].to_vec(), vec![12.0]);
+/// ```
+pub fn grad(output: &Tensor, inputs: &[Tensor], create_graph: bool) -> Vec<Tensor> {
+    assert!(
+        output.node().is_some(),
+        "grad called on a tensor that is not part of a graph"
+    );
+    assert_eq!(
+        output.nelems(),
+        1,
+        "grad requires a single-element output, got shape {:?}",
+        output.shape()
+    );
+    let seed = Tensor::from_vec(vec![1.0], output.shape());
+    gradients(output, seed, inputs, create_graph)
+}
+
+/// Gradients of `output` (weighted by `seed`, shaped like it) with respect
+/// to each of `inputs`, without storing anything on the graph.
+///
+/// That makes it safe to call repeatedly on one graph, e.g. once per row
+/// of a Jacobian. An input `output` does not depend on gets zeros. With
+/// `create_graph` the backward pass is recorded, see [`grad`].
+///
+/// Only the part of the graph between `output` and `inputs` is visited:
+/// branches that lead to other leaves are pruned, so their backward
+/// functions (and gradient hooks) do not run.
+pub(crate) fn gradients(
+    output: &Tensor,
+    seed: Tensor,
+    inputs: &[Tensor],
+    create_graph: bool,
+) -> Vec<Tensor> {
+    let targets: HashSet<*const Node> = inputs
+        .iter()
+        .filter_map(|t| t.node().map(Rc::as_ptr))
+        .collect();
+    let mut grads: HashMap<*const Node, Tensor> = match output.node() {
+        Some(root) => propagate(root, seed, Some(&targets), create_graph)
+            .into_iter()
+            .map(|(node, grad)| (Rc::as_ptr(&node), grad))
+            .collect(),
+        None => HashMap::new(),
+    };
+
+    inputs
+        .iter()
+        .map(|input| {
+            input
+                .node()
+                .and_then(|node| grads.remove(&Rc::as_ptr(node)))
+                .unwrap_or_else(|| Tensor::zeros(input.shape()))
+        })
+        .collect()
+}
+
+/// Propagate `seed` (the gradient of `root`) back through the graph and
+/// return the gradient of every node reached.
+///
+/// Nodes are visited in reverse topological order, so by the time a node
+/// is processed every path through it has contributed to its gradient.
+/// A tensor used twice (e.g. `x * x`) therefore gets both contributions
+/// summed before its own backward function runs. Gradient hooks run on
+/// that complete gradient, and what they return is what flows on.
+///
+/// With `targets`, nodes that have no path to any of them are dead: no
+/// gradient is sent their way and their backward functions never run.
+///
+/// Backward functions are built from differentiable ops, so with
+/// `create_graph` the pass records itself like any other computation.
+/// The few that are not (see [`record_first_order`](crate::autograd::record_first_order))
+/// panic instead of recording a gradient that is silently wrong.
+fn propagate(
+    root: &Rc<Node>,
+    seed: Tensor,
+    targets: Option<&HashSet<*const Node>>,
+    create_graph: bool,
+) -> Vec<(Rc<Node>, Tensor)> {
+    let _guard = GradModeGuard::new(create_graph);
+
+    let order = topological_order(root);
+    let live = targets.map(|targets| live_nodes(&order, targets));
+    let is_live = |node: &Rc<Node>| live.as_ref().is_none_or(|l| l.contains(&Rc::as_ptr(node)));
+
+    let mut pending: HashMap<*const Node, Tensor> = HashMap::new();
+    pending.insert(Rc::as_ptr(root), seed);
+
+    let mut done = Vec::new();
+    for node in order.into_iter().rev() {
+        let Some(grad) = pending.remove(&Rc::as_ptr(&node)) else {
+            continue;
+        };
+        if !is_live(&node) {
+            continue;
+        }
+        let grad = node.apply_hooks(grad);
+
+        if let Some(backward) = node.backward_fn() {
+            assert!(
+                !create_graph || !node.is_first_order(),
+                "the backward of {} is computed outside the graph and cannot be \
+                 differentiated again; run it with create_graph off",
+                node.op()
+            );
+            let input_grads = backward(&grad);
+            assert_eq!(
+                input_grads.len(),
+                node.inputs().len(),
+                "backward of {} returned {} gradients for {} inputs",
+                node.op(),
+                input_grads.len(),
+                node.inputs().len()
+            );
+            if is_anomaly_enabled() {
+                check_gradients(&node, &input_grads);
+            }
+
+            let inputs = node.inputs().iter().zip(node.input_versions());
+            for ((input, &version), g) in inputs.zip(input_grads) {
+                let Some(input) = input.as_ref().filter(|input| is_live(input)) else {
+                    continue;
+                };
+                // A leaf has no backward function to invalidate
+                assert!(
+                    version == 0 || input.is_leaf(),
+                    "{} used the output of {} after it was modified in place, so its \
+                     gradient cannot be computed; use the recorded in-place ops (add_, \
+                     mul_, ...) or modify a copy",
+                    node.op(),
+                    input.op()
+                );
+                debug_assert_eq!(
+                    g.shape(),
+                    input.shape(),
+                    "backward of {} produced a gradient of the wrong shape",
+                    node.op()
+                );
+                match pending.entry(Rc::as_ptr(input)) {
+                    Entry::Occupied(mut acc) => {
+                        let sum = acc.get().add(&g);
+                        acc.insert(sum);
+                    }
+                    Entry::Vacant(slot) => {
+                        slot.insert(g);
+                    }
+                }
+            }
+        }
+
+        done.push((node, grad));
+    }
+    done
+}
+
+/// The nodes of `order` (inputs before users) from which some node of
+/// `targets` can be reached, targets included.
+fn live_nodes(order: &[Rc<Node>], targets: &HashSet<*const Node>) -> HashSet<*const Node> {
+    let mut live = HashSet::new();
+    for node in order {
+        let ptr = Rc::as_ptr(node);
+        let feeds_target = node
+            .inputs()
+            .iter()
+            .flatten()
+            .any(|input| live.contains(&Rc::as_ptr(input)));
+        if targets.contains(&ptr) || feeds_target {
+            live.insert(ptr);
+        }
+    }
+    live
+}
+
+/// All nodes reachable from `root`, inputs before the nodes that use them.
+///
+/// Iterative depth-first search: graphs from long loops can be far deeper
+/// than the call stack allows.
+pub(crate) fn topological_order(root: &Rc<Node>) -> Vec<Rc<Node>> {
+    let mut order = Vec::new();
+    let mut visited = HashSet::new();
+    // (node, inputs already pushed)
+    let mut stack = vec![(Rc::clone(root), false)];
+
+    while let Some((node, expanded)) = stack.pop() {
+        if expanded {
+            order.push(node);
+            continue;
+        }
+        if !visited.insert(Rc::as_ptr(&node)) {
+            continue;
+        }
+
+        stack.push((Rc::clone(&node), true));
+        for input in node.inputs().iter().flatten() {
+            if !visited.contains(&Rc::as_ptr(input)) {
+                stack.push((Rc::clone(input), false));
+            }
+        }
+    }
+    order
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use crate::autograd::testing::check_grad;
+    use crate::autograd::{grad, gradients, value_and_grad};
+    use crate::tensor::Tensor;
+
+    #[test]
+    fn test_chain_rule() {
+        // loss = sum((a * b + a)²), a = 2, b = 3: d/da = 2(ab + a)(b + 1)
+        let a = Tensor::from_vec(vec![2.0], &[1]).requires_grad(true);
+        let b = Tensor::from_vec(vec![3.0], &[1]).requires_grad(true);
+        let y = a.mul(&b).add(&a);
+        let loss = y.mul(&y).sum();
+        loss.backward();
+
+        assert_eq!(a.grad().unwrap().to_vec(), vec![2.0 * 8.0 * 4.0]);
+        assert_eq!(b.grad().unwrap().to_vec(), vec![2.0 * 8.0 * 2.0]);
+    }
+
+    #[test]
+    fn test_shared_input_accumulates() {
+        // x is used by both branches of a diamond: loss = sum(x*x + 3x)
+        let x = Tensor::from_vec(vec![1.0, -2.0], &[2]).requires_grad(true);
+        let loss = x.mul(&x).add(&x.scalar_mul(3.0)).sum();
+        loss.backward();
+        assert_eq!(x.grad().unwrap().to_vec(), vec![5.0, -1.0]);
+    }
+
+    #[test]
+    fn test_intermediate_gradients() {
+        let x = Tensor::from_vec(vec![1.0, 2.0], &[2]).requires_grad(true);
+        let y = x.scalar_mul(2.0);
+        let loss = y.mul(&y).sum();
+        loss.backward();
+        // d loss / d y = 2y, d loss / d x = 4y
+        assert_eq!(y.grad().unwrap().to_vec(), vec![4.0, 8.0]);
+        assert_eq!(x.grad().unwrap().to_vec(), vec![8.0, 16.0]);
+        assert_eq!(loss.grad().unwrap().to_vec(), vec![1.0]);
+    }
+
+    #[test]
+    fn test_constants_are_not_recorded() {
+        let c = Tensor::from_vec(vec![2.0, 3.0], &[2]);
+        assert!(c.mul(&c).node().is_none());
+
+        let x = Tensor::from_vec(vec![1.0, 1.0], &[2]).requires_grad(true);
+        let loss = x.mul(&c).sum();
+        loss.backward();
+        assert_eq!(x.grad().unwrap().to_vec(), vec![2.0, 3.0]);
+        assert!(c.node().is_none());
+    }
+
+    #[test]
+    fn test_gradients_are_not_recorded() {
+        let x = Tensor::from_vec(vec![3.0], &[1]).requires_grad(true);
+        x.mul(&x).sum().backward();
+        assert!(x.grad().unwrap().node().is_none());
+    }
+
+    #[test]
+    fn test_backward_accumulates() {
+        let x = Tensor::from_vec(vec![1.0], &[1]).requires_grad(true);
+        let loss = x.scalar_mul(4.0).sum();
+        loss.backward();
+        loss.backward();
+        assert_eq!(x.grad().unwrap().to_vec(), vec![8.0]);
+
+        // Across different graphs too, e.g. two mini-batches
+        x.powi(2).sum().backward();
+        assert_eq!(x.grad().unwrap().to_vec(), vec![10.0]);
+    }
+
+    #[test]
+    fn test_zero_grad() {
+        let x = Tensor::from_vec(vec![1.0, 2.0], &[2]).requires_grad(true);
+        let y = x.scalar_mul(3.0);
+        y.sum().backward();
+        assert!(y.grad().is_some());
+
+        // Clones share the gradient, so resetting through one resets all
+        x.clone().zero_grad();
+        assert!(x.grad().is_none());
+        y.zero_grad();
+        y.sum().backward();
+        assert_eq!(x.grad().unwrap().to_vec(), vec![3.0, 3.0]);
+        assert_eq!(y.grad().unwrap().to_vec(), vec![1.0, 1.0]);
+
+        // A no-op outside the graph
+        Tensor::zeros(&[1]).zero_grad();
+    }
+
+    #[test]
+    fn test_value_and_grad() {
+        let a = Tensor::from_vec(vec![1.0, 2.0], &[2]);
+        let unused = Tensor::from_vec(vec![5.0], &[1]);
+        let (value, grads) = value_and_grad(|p| p[0].powi(2).sum(), &[a.clone(), unused]);
+        assert_eq!(value.get(&[]), 5.0);
+        assert_eq!(grads[0].to_vec(), vec![2.0, 4.0]);
+        assert_eq!(grads[1].to_vec(), vec![0.0]);
+        // The inputs themselves stay out of the graph
+        assert!(a.node().is_none());
+    }
+
+    #[test]
+    fn test_gradients_with_seed() {
+        // Rows of the Jacobian of y = x² at (2, 5): diag(4, 10)
+        let x = Tensor::from_vec(vec![2.0, 5.0], &[2]).requires_grad(true);
+        let y = x.powi(2);
+        let inputs = [x.clone()];
+        let row0 = gradients(&y, Tensor::from_vec(vec![1.0, 0.0], &[2]), &inputs, false);
+        let row1 = gradients(&y, Tensor::from_vec(vec![0.0, 1.0], &[2]), &inputs, false);
+        assert_eq!(row0[0].to_vec(), vec![4.0, 0.0]);
+        assert_eq!(row1[0].to_vec(), vec![0.0, 10.0]);
+        // Nothing is stored on the graph
+        assert!(x.grad().is_none());
+
+        let unrelated = Tensor::zeros(&[3]).requires_grad(true);
+        let g = gradients(&y, Tensor::zeros(&[2]), &[unrelated], false);
+        assert_eq!(g[0].to_vec(), vec![0.0; 3]);
+    }
+
+    #[test]
+    fn test_grad_create_graph() {
+        let x = Tensor::from_vec(vec![1.0, 2.0], &[2]).requires_grad(true);
+        let y = x.powi(3).sum();
+        let plain = grad(&y, std::slice::from_ref(&x), false);
+        assert!(plain[0].node().is_none());
+
+        let dy = grad(&y, std::slice::from_ref(&x), true);
+        assert_eq!(dy[0].to_vec(), vec![3.0, 12.0]);
+        assert!(dy[0].node().is_some());
+        // Nothing was stored along the way
+        assert!(x.grad().is_none());
+
+        dy[0].sum().backward();
+        assert_eq!(x.grad().unwrap().to_vec(), vec![6.0, 12.0]);
+    }
+
+    #[test]
+    fn test_gradient_penalty() {
+        // WGAN-GP style: penalty = (‖∇ₓ f‖ - 1)² for f(x) = w · x, whose
+        // input gradient is w itself, so d penalty / dw = 2 (‖w‖ - 1) w / ‖w‖
+        let w = Tensor::from_vec(vec![3.0, 4.0], &[2]).requires_grad(true);
+        let x = Tensor::from_vec(vec![0.5, -1.0], &[2]).requires_grad(true);
+        let f = w.mul(&x).sum();
+        let gx = grad(&f, &[x], true);
+        let penalty = gx[0].powi(2).sum().sqrt().scalar_add(-1.0).powi(2);
+        penalty.backward();
+
+        let g = w.grad().unwrap().to_vec();
+        assert!((g[0] - 2.0 * 4.0 * 0.6).abs() < 1e-5);
+        assert!((g[1] - 2.0 * 4.0 * 0.8).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_maml_step() {
+        // Inner step w' = w - lr · dL/dw with L(w) = (w - a)², then the outer
+        // loss (w' - b)² differentiated through the inner step:
+        // w' = w - 2 lr (w - a), dw'/dw = 1 - 2 lr
+        let (a, b, lr) = (1.0, 3.0, 0.1);
+        let w = Tensor::from_vec(vec![2.0], &[1]).requires_grad(true);
+        let inner = w.scalar_add(-a).powi(2).sum();
+        let g = grad(&inner, std::slice::from_ref(&w), true);
+        let adapted = w.sub(&g[0].scalar_mul(lr));
+        adapted.scalar_add(-b).powi(2).sum().backward();
+
+        let w_prime = 2.0 - 2.0 * lr * (2.0 - a);
+        let expected = 2.0 * (w_prime - b) * (1.0 - 2.0 * lr);
+        assert!((w.grad().unwrap().get(&[0]) - expected).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_second_derivatives_match_finite_differences() {
+        // Differentiating the gradient checks every backward rule involved
+        // is itself differentiated correctly
+        let x = Tensor::from_vec(vec![0.3, -0.8, 1.2, 0.5, -0.1, 0.9], &[2, 3]);
+        let w = Tensor::from_vec(vec![0.5, -1.0, 0.25, 2.0, 1.5, -0.5], &[3, 2]);
+        let input_grad = |f: fn(&[Tensor]) -> Tensor| {
+            move |t: &[Tensor]| {
+                // The finite-difference evaluations pass plain tensors
+                let t: Vec<Tensor> = t.iter().map(|x| x.clone().requires_grad(true)).collect();
+                grad(&f(&t), &t[..1], true)[0].clone()
+            }
+        };
+        let x_only = std::slice::from_ref(&x);
+
+        check_grad(
+            input_grad(|t| t[0].matmul(&t[1]).tanh().sum()),
+            &[x.clone(), w],
+        );
+        check_grad(input_grad(|t| t[0].sigmoid().mul(&t[0]).sum()), x_only);
+        check_grad(
+            input_grad(|t| t[0].log_softmax(1).sum_dim(0, false).exp().sum()),
+            x_only,
+        );
+        check_grad(
+            input_grad(|t| t[0].softplus().div(&t[0].exp().scalar_add(1.0)).sum()),
+            x_only,
+        );
+        check_grad(
+            input_grad(|t| t[0].gelu().logsumexp(0, false).sum()),
+            x_only,
+        );
+    }
+
+    #[test]
+    fn test_first_order_rules() {
+        let x = Tensor::from_vec(vec![2.0, 3.0], &[2]).requires_grad(true);
+        let w = Tensor::from_vec(vec![0.5, 1.5], &[2]).requires_grad(true);
+        let y = x.prod().add(&w.powi(2).sum());
+        // The rule is right to first order
+        let g = grad(&y, std::slice::from_ref(&x), false);
+        assert_eq!(g[0].to_vec(), vec![3.0, 2.0]);
+        // and a pruned branch never runs it
+        let g = grad(&y, std::slice::from_ref(&w), true);
+        assert_eq!(g[0].to_vec(), vec![1.0, 3.0]);
+    }
+
+    #[test]
+    #[should_panic(expected = "the backward of prod is computed outside the graph")]
+    fn test_first_order_rule_under_create_graph() {
+        let x = Tensor::from_vec(vec![2.0, 3.0], &[2]).requires_grad(true);
+        let y = x.exp().prod();
+        grad(&y, std::slice::from_ref(&x), true);
+    }
+
+    #[test]
+    #[should_panic(expected = "single-element")]
+    fn test_grad_non_scalar() {
+        let x = Tensor::from_vec(vec![1.0, 2.0], &[2]).requires_grad(true);
+        grad(&x.scalar_mul(2.0), &[x], false);
+    }
+
+    #[test]
+    fn test_deep_graph() {
+        // Both the backward pass and dropping the graph must not recurse
+        // once per op.
+        let x = Tensor::from_vec(vec![1.0], &[1]).requires_grad(true);
+        let mut y = x.clone();
+        for _ in 0..50_000 {
+            y = y.scalar_add(1.0);
+        }
+        y.sum().backward();
+        assert_eq!(x.grad().unwrap().to_vec(), vec![1.0]);
+    }
+
+    #[test]
+    fn test_hook_observes_gradient() {
+        let seen = Rc::new(RefCell::new(Vec::new()));
+        let log = Rc::clone(&seen);
+        let x = Tensor::from_vec(vec![1.0, -2.0], &[2]).requires_grad(true);
+        x.register_hook(move |g| {
+            log.borrow_mut().push(g.to_vec());
+            None
+        });
+        // Both uses are summed before the hook runs
+        x.mul(&x).add(&x).sum().backward();
+        assert_eq!(*seen.borrow(), vec![vec![3.0, -3.0]]);
+        assert_eq!(x.grad().unwrap().to_vec(), vec![3.0, -3.0]);
+    }
+
+    #[test]
+    fn test_hook_replaces_gradient_upstream() {
+        let x = Tensor::from_vec(vec![1.0, 2.0], &[2]).requires_grad(true);
+        let y = x.scalar_mul(2.0);
+        y.register_hook(|g| Some(g.scalar_mul(10.0)));
+        y.register_hook(|g| Some(g.scalar_add(1.0)));
+        y.sum().backward();
+        assert_eq!(y.grad().unwrap().to_vec(), vec![11.0, 11.0]);
+        assert_eq!(x.grad().unwrap().to_vec(), vec![22.0, 22.0]);
+
+        // grad() runs the hooks too
+        let g = grad(&y.sum(), std::slice::from_ref(&x), false);
+        assert_eq!(g[0].to_vec(), vec![22.0, 22.0]);
+    }
+
+    #[test]
+    #[should_panic(expected = "wrong shape")]
+    fn test_hook_wrong_shape() {
+        let x = Tensor::from_vec(vec![1.0, 2.0], &[2]).requires_grad(true);
+        x.register_hook(|g| Some(g.sum()));
+        x.sum().backward();
+    }
+
+    #[test]
+    fn test_grad_prunes_dead_branches() {
+        let calls = Rc::new(RefCell::new(Vec::new()));
+        let watch = |t: &Tensor, name: &'static str| {
+            let log = Rc::clone(&calls);
+            t.register_hook(move |_| {
+                log.borrow_mut().push(name);
+                None
+            });
+        };
+        let x = Tensor::from_vec(vec![1.0, 2.0], &[2]).requires_grad(true);
+        let w = Tensor::from_vec(vec![3.0, 4.0], &[2]).requires_grad(true);
+        let a = x.exp();
+        let b = w.powi(2);
+        watch(&a, "a");
+        watch(&b, "b");
+        let loss = a.add(&b).sum();
+
+        // Only the branch through a leads to x
+        let g = grad(&loss, std::slice::from_ref(&x), false);
+        assert_eq!(g[0].to_vec(), x.exp().to_vec());
+        assert_eq!(*calls.borrow(), vec!["a"]);
+
+        // backward() still visits everything
+        calls.borrow_mut().clear();
+        loss.backward();
+        let mut seen = calls.borrow().clone();
+        seen.sort_unstable();
+        assert_eq!(seen, vec!["a", "b"]);
+        assert_eq!(w.grad().unwrap().to_vec(), vec![6.0, 8.0]);
+    }
+
+    #[test]
+    #[should_panic(expected = "not part of a graph")]
+    fn test_backward_without_graph() {
+        Tensor::from_vec(vec![1.0], &[1]).backward();
+    }
+
+    #[test]
+    #[should_panic(expected = "single-element")]
+    fn test_backward_non_scalar() {
+        Tensor::from_vec(vec![1.0, 2.0], &[2])
+            .requires_grad(true)
+            .scalar_mul(2.0)
+            .backward();
+    }
+}
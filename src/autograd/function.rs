@@ -0,0 +1,178 @@
+use crate::autograd::grad_mode::GradModeGuard;
+use crate::autograd::record;
+use crate::tensor::Tensor;
+
+/// A differentiable op defined by its forward computation and its
+/// backward rule.
+///
+/// For ops that are better computed some other way than by composing
+/// built-in ops: a fused kernel, a call into a numerical solver, or a
+/// function with a known closed-form gradient that the composition would
+/// get wrong or slowly. [`Function::apply`] runs `forward` and records
+/// `backward` into the graph like any built-in op, so custom and built-in
+/// ops mix freely.
+///
+/// # Example
+/// ```
+/// use delta::autograd::Function;
+/// use delta::tensor::Tensor;
+///
+/// /// Cube root by Newton's method, differentiated implicitly:
+/// /// y³ = x gives dy/dx = 1 / (3y²)
+/// struct Cbrt;
+///
+/// impl Function for Cbrt {
+///     fn forward(&self, inputs: &[Tensor]) -> Tensor {
+///         inputs[0].map(|x| {
+///             let mut y = x.max(1.0);
+///             for _ in 0..30 {
+///                 y -= (y * y * y - x) / (3.0 * y * y);
+///             }
+///             y
+///         })
+///     }
+///
+///     fn backward(&self, _inputs: &[Tensor], output: &Tensor, grad: &Tensor) -> Vec<Tensor> {
+///         vec![grad.div(&output.powi(2).scalar_mul(3.0))]
+///     }
+/// }
+///
+/// let x = Tensor::from_vec(vec![8.0, 27.0], &[2]).requires_grad(true);
+/// let y = Cbrt.apply(&[x.clone()]);
+/// assert_eq!(y.to_vec(), vec![2.0, 3.0]);
+/// y.sum().backward();
+/// let g = x.grad().unwrap().to_vec();
+/// assert!((g[0] - 1.0 / 12.0).abs() < 1e-6 && (g[1] - 1.0 / 27.0).abs() < 1e-6);
+/// ```
+pub trait Function {
+    /// Compute the output from the inputs.
+    ///
+    /// Runs with recording off: whatever ops it uses internally are not
+    /// part of the graph, only the op as a whole is.
+    fn forward(&self, inputs: &[Tensor]) -> Tensor;
+
+    /// The gradient for each input, given the gradient of the output.
+    ///
+    /// Receives the inputs and the output of the forward pass as well.
+    /// Must return one gradient per input, shaped like it; return zeros
+    /// for inputs that are not differentiable. With `create_graph` it runs
+    /// with recording on, so only the tensor ops it is built from are
+    /// differentiated again: a rule computed on raw values gives zero
+    /// second derivatives.
+    fn backward(&self, inputs: &[Tensor], output: &Tensor, grad: &Tensor) -> Vec<Tensor>;
+
+    /// Name of the op in the graph. Defaults to the type name.
+    fn name(&self) -> &'static str {
+        std::any::type_name::<Self>()
+    }
+
+    /// Run the function on `inputs` and record it for autograd.
+    ///
+    /// # Panics
+    /// Panics during the backward pass if `backward` returns the wrong
+    /// number of gradients or a gradient of the wrong shape.
+    fn apply(self, inputs: &[Tensor]) -> Tensor
+    where
+        Self: Sized + 'static,
+    {
+        let output = {
+            let _guard = GradModeGuard::new(false);
+            self.forward(inputs)
+        };
+
+        let name = self.name();
+        let saved_inputs = inputs.to_vec();
+        let saved_output = output.detach();
+        let refs: Vec<&Tensor> = inputs.iter().collect();
+        record(output, name, &refs, move |g| {
+            let grads = self.backward(&saved_inputs, &saved_output, g);
+            assert_eq!(
+                grads.len(),
+                saved_inputs.len(),
+                "backward of {} returned {} gradients for {} inputs",
+                name,
+                grads.len(),
+                saved_inputs.len()
+            );
+            for (i, (grad, input)) in grads.iter().zip(&saved_inputs).enumerate() {
+                assert_eq!(
+                    grad.shape(),
+                    input.shape(),
+                    "backward of {} returned a gradient of shape {:?} for input {} of shape {:?}",
+                    name,
+                    grad.shape(),
+                    i,
+                    input.shape()
+                );
+            }
+            grads
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::autograd::testing::check_grad;
+
+    /// sum(a * b) along rows as one fused op: [n, k] x [n, k] -> [n]
+    struct RowDot;
+
+    impl Function for RowDot {
+        fn forward(&self, inputs: &[Tensor]) -> Tensor {
+            let (a, b) = (&inputs[0], &inputs[1]);
+            a.mul(b).sum_dim(1, false)
+        }
+
+        fn backward(&self, inputs: &[Tensor], _output: &Tensor, grad: &Tensor) -> Vec<Tensor> {
+            let shape = inputs[0].shape();
+            let g = grad.reshape(&[shape[0], 1]).broadcast_to(shape);
+            vec![g.mul(&inputs[1]), g.mul(&inputs[0])]
+        }
+    }
+
+    struct WrongShape;
+
+    impl Function for WrongShape {
+        fn forward(&self, inputs: &[Tensor]) -> Tensor {
+            inputs[0].clone()
+        }
+
+        fn backward(&self, _inputs: &[Tensor], _output: &Tensor, _grad: &Tensor) -> Vec<Tensor> {
+            vec![Tensor::zeros(&[7])]
+        }
+    }
+
+    #[test]
+    fn test_custom_function_gradients() {
+        let a = Tensor::from_vec(vec![1.0, 2.0, -1.0, 0.5, 3.0, -2.0], &[2, 3]);
+        let b = Tensor::from_vec(vec![0.5, -1.0, 2.0, 1.5, 1.0, 0.25], &[2, 3]);
+        check_grad(|t| RowDot.apply(t), &[a, b]);
+    }
+
+    #[test]
+    fn test_mixes_with_builtin_ops() {
+        let a = Tensor::from_vec(vec![1.0, 2.0], &[1, 2]).requires_grad(true);
+        let b = Tensor::from_vec(vec![3.0, 4.0], &[1, 2]);
+        let out = RowDot.apply(&[a.exp(), b]);
+        assert!(out.node().unwrap().op().ends_with("RowDot"));
+        out.sum().backward();
+        let g = a.grad().unwrap().to_vec();
+        assert!((g[0] - 3.0 * 1f32.exp()).abs() < 1e-4);
+        assert!((g[1] - 4.0 * 2f32.exp()).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_untracked_inputs() {
+        // No input tracks gradients: nothing to record
+        let a = Tensor::from_vec(vec![1.0, 2.0], &[1, 2]);
+        assert!(RowDot.apply(&[a.clone(), a]).node().is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "returned a gradient of shape [7]")]
+    fn test_wrong_gradient_shape() {
+        let x = Tensor::from_vec(vec![1.0, 2.0], &[2]).requires_grad(true);
+        WrongShape.apply(&[x]).sum().backward();
+    }
+}
@@ -0,0 +1,330 @@
+use crate::autograd::grad_mode::GradModeGuard;
+use crate::autograd::gradients;
+use crate::tensor::Tensor;
+
+/// The Jacobian of `f` at `x`: every partial derivative of every output
+/// element with respect to every input element.
+///
+/// The result has shape `f(x).shape() ++ x.shape()`, so for a function
+/// from `[n]` to `[m]` it is the `[m, n]` matrix J with `J[i][j] = ∂fᵢ/∂xⱼ`.
+/// It takes one backward pass per output element. `x` is handed to `f` as
+/// a fresh leaf, detached from whatever graph it came from; the result is
+/// a plain tensor.
+///
+/// # Example
+/// ```
+/// use delta::autograd::jacobian;
+/// use delta::tensor::Tensor;
+/// // Element-wise x²: the Jacobian is diag(2x)
+/// let x = Tensor::from_vec(vec![1.0, 2.0, 3.0], &[3]);
+/// let j = jacobian(|x| x.powi(2), &x);
+/// assert_eq!(j.shape(), &[3, 3]);
+/// assert_eq!(j.to_vec(), vec![2.0, 0.0, 0.0,
+///                             0.0, 4.0, 0.0,
+///                             0.0, 0.0, 6.0]);
+/// ```
+pub fn jacobian(f: impl FnOnce(&Tensor) -> Tensor, x: &Tensor) -> Tensor {
+    let leaf = x.detach().requires_grad(true);
+    let out = {
+        let _guard = GradModeGuard::new(true);
+        f(&leaf)
+    };
+
+    let m = out.nelems();
+    let mut data = Vec::with_capacity(m * x.nelems());
+    for i in 0..m {
+        let mut seed = vec![0.0; m];
+        seed[i] = 1.0;
+        let seed = Tensor::from_vec(seed, out.shape());
+        let row = gradients(&out, seed, std::slice::from_ref(&leaf), false);
+        data.extend(row[0].to_vec());
+    }
+    Tensor::from_vec(data, &[out.shape(), x.shape()].concat())
+}
+
+/// The Hessian of a scalar function `f` at `x`: the matrix of second
+/// derivatives `H[i][j] = ∂²f/∂xᵢ∂xⱼ`, shaped `x.shape() ++ x.shape()`.
+///
+/// Computed as the [`jacobian`] of the gradient, which is recorded with
+/// `create_graph` (see [`grad`](crate::autograd::grad)), so it panics on
+/// the same backward rules computed outside the graph.
+///
+/// # Panics
+/// Panics if `f` returns more than one element.
+///
+/// # Example
+/// ```
+/// use delta::autograd::hessian;
+/// use delta::tensor::Tensor;
+/// // f(x) = Σ xᵢ³: H = diag(6 xᵢ)
+/// let x = Tensor::from_vec(vec![1.0, 2.0], &[2]);
+/// let h = hessian(|x| x.powi(3).sum(), &x);
+/// assert_eq!(h.to_vec(), vec![6.0, 0.0,
+///                             0.0, 12.0]);
+/// ```
+pub fn hessian(f: impl FnOnce(&Tensor) -> Tensor, x: &Tensor) -> Tensor {
+    jacobian(
+        |x| {
+            let y = f(x);
+            assert_eq!(
+                y.nelems(),
+                1,
+                "hessian requires a single-element output, got shape {:?}",
+                y.shape()
+            );
+            let seed = Tensor::from_vec(vec![1.0], y.shape());
+            gradients(&y, seed, std::slice::from_ref(x), true).remove(0)
+        },
+        x,
+    )
+}
+
+/// Reverse-mode derivative as a function: `f(x)` together with its
+/// pullback, the closure mapping a cotangent `u` (shaped like the output)
+/// to the vector-Jacobian product `Jᵀ u` (shaped like `x`).
+///
+/// Nothing is stored on tensors: the pullback returns gradients rather
+/// than accumulating them in [`Tensor::grad`], so it can be called any
+/// number of times, with different cotangents, and training code can be
+/// written as plain functions of their inputs. The graph of `f(x)` stays
+/// alive as long as the pullback does.
+///
+/// The pullback's results are plain tensors.
+///
+/// # Panics
+/// The pullback panics if the cotangent is not shaped like the output.
+///
+/// # Example
+/// ```
+/// use delta::autograd::vjp;
+/// use delta::tensor::Tensor;
+///
+/// let x = Tensor::from_vec(vec![1.0, 2.0, 3.0], &[3]);
+/// let (y, pullback) = vjp(|x| x.powi(2), &x);
+/// assert_eq!(y.to_vec(), vec![1.0, 4.0, 9.0]);
+/// // J = diag(2x)
+/// let u = Tensor::from_vec(vec![1.0, 0.0, 0.5], &[3]);
+/// assert_eq!(pullback(&u).to_vec(), vec![2.0, 0.0, 3.0]);
+/// // The gradient of Σ y, without touching x.grad()
+/// assert_eq!(pullback(&Tensor::from_vec(vec![1.0; 3], &[3])).to_vec(), vec![2.0, 4.0, 6.0]);
+/// ```
+pub fn vjp<F: FnOnce(&Tensor) -> Tensor>(
+    f: F,
+    x: &Tensor,
+) -> (Tensor, impl Fn(&Tensor) -> Tensor + use<F>) {
+    let leaf = x.detach().requires_grad(true);
+    let out = {
+        let _guard = GradModeGuard::new(true);
+        f(&leaf)
+    };
+
+    let value = out.detach();
+    let pullback = move |u: &Tensor| {
+        assert_eq!(
+            u.shape(),
+            out.shape(),
+            "vjp expected a cotangent of shape {:?}, got {:?}",
+            out.shape(),
+            u.shape()
+        );
+        gradients(&out, u.detach(), std::slice::from_ref(&leaf), false).remove(0)
+    };
+    (value, pullback)
+}
+
+/// Forward-mode derivative: `f(x)` together with the Jacobian-vector
+/// product `J v`, the directional derivative of `f` at `x` along `v`.
+///
+/// Where a reverse pass gives the gradient of one output with respect to
+/// all inputs, a JVP gives the derivative of all outputs along one input
+/// direction, so it is the cheap direction when outputs outnumber inputs.
+/// It costs two backward passes, whatever the number of outputs, instead
+/// of the one per output a [`jacobian`] would take.
+///
+/// It is computed with the double-backward trick rather than dual
+/// numbers: the vector-Jacobian product `u ↦ Jᵀ u` is recorded with
+/// `create_graph` for a placeholder cotangent u, and since it is linear in
+/// u, differentiating `(Jᵀ u) · v` with respect to u gives `J v`. Every op
+/// is covered by its backward rule, except those computed outside the
+/// graph: like [`grad`](crate::autograd::grad) with `create_graph`, it
+/// panics on them.
+///
+/// Combined with reverse mode it gives Hessian-vector products without
+/// forming the Hessian: the JVP of the gradient is `H v`.
+///
+/// # Panics
+/// Panics if `v` is not shaped like `x`.
+///
+/// # Example
+/// ```
+/// use delta::autograd::{grad, jvp};
+/// use delta::tensor::Tensor;
+///
+/// // Directional derivative of x² along v is 2 x v, element-wise
+/// let x = Tensor::from_vec(vec![1.0, 2.0, 3.0], &[3]);
+/// let v = Tensor::from_vec(vec![1.0, 0.0, -1.0], &[3]);
+/// let (y, jv) = jvp(|x| x.powi(2), &x, &v);
+/// assert_eq!(y.to_vec(), vec![1.0, 4.0, 9.0]);
+/// assert_eq!(jv.to_vec(), vec![2.0, 0.0, -6.0]);
+///
+/// // Hessian-vector product of Σ x³ (H = diag(6x))
+/// let gradient = |x: &Tensor| grad(&x.powi(3).sum(), &[x.clone()], true).remove(0);
+/// let (_, hv) = jvp(gradient, &x, &v);
+/// assert_eq!(hv.to_vec(), vec![6.0, 0.0, -18.0]);
+/// ```
+pub fn jvp(f: impl FnOnce(&Tensor) -> Tensor, x: &Tensor, v: &Tensor) -> (Tensor, Tensor) {
+    assert_eq!(
+        x.shape(),
+        v.shape(),
+        "jvp expected a tangent of shape {:?}, got {:?}",
+        x.shape(),
+        v.shape()
+    );
+    let leaf = x.detach().requires_grad(true);
+    let out = {
+        let _guard = GradModeGuard::new(true);
+        f(&leaf)
+    };
+
+    // Jᵀ u, recorded as a function of the placeholder u
+    let u = Tensor::zeros(out.shape()).requires_grad(true);
+    let vjp = gradients(&out, u.clone(), std::slice::from_ref(&leaf), true).remove(0);
+    let dot = {
+        let _guard = GradModeGuard::new(true);
+        vjp.mul(v).sum()
+    };
+    let seed = Tensor::from_vec(vec![1.0], &[]);
+    let jv = gradients(&dot, seed, &[u], false).remove(0);
+    (out.detach(), jv)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::autograd::testing::assert_close;
+    use crate::tensor::sparse::Csr;
+
+    #[test]
+    fn test_jacobian_of_linear_map() {
+        let a = Tensor::from_vec(vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0], &[3, 2]);
+        let x = Tensor::from_vec(vec![0.5, -1.0], &[2, 1]);
+        let j = jacobian(|x| a.matmul(x), &x);
+        assert_eq!(j.shape(), &[3, 1, 2, 1]);
+        assert_eq!(j.to_vec(), a.to_vec());
+    }
+
+    #[test]
+    fn test_jacobian_elementwise() {
+        let x = Tensor::from_vec(vec![0.0, 1.0, -2.0], &[3]);
+        let j = jacobian(|x| x.exp(), &x);
+        let e = x.exp().to_vec();
+        assert_close(
+            &j.to_vec(),
+            &[e[0], 0.0, 0.0, 0.0, e[1], 0.0, 0.0, 0.0, e[2]],
+            1e-4,
+        );
+    }
+
+    #[test]
+    fn test_jacobian_of_constant() {
+        let x = Tensor::from_vec(vec![1.0, 2.0], &[2]);
+        let j = jacobian(|_| Tensor::zeros(&[3]), &x);
+        assert_eq!(j.to_vec(), vec![0.0; 6]);
+    }
+
+    #[test]
+    fn test_hessian() {
+        // f(x, y) = x² y + sin(y)
+        let f = |v: &Tensor| {
+            let x = v.mul(&Tensor::from_vec(vec![1.0, 0.0], &[2])).sum();
+            let y = v.mul(&Tensor::from_vec(vec![0.0, 1.0], &[2])).sum();
+            x.powi(2).mul(&y).add(&y.sin())
+        };
+        let (x, y) = (1.5f32, 0.5f32);
+        let h = hessian(f, &Tensor::from_vec(vec![x, y], &[2]));
+        assert_eq!(h.shape(), &[2, 2]);
+        assert_close(&h.to_vec(), &[2.0 * y, 2.0 * x, 2.0 * x, -y.sin()], 1e-4);
+    }
+
+    #[test]
+    fn test_hessian_of_quadratic_form() {
+        // ½ xᵀ A x with symmetric A has Hessian A
+        let a = Tensor::from_vec(vec![2.0, 0.5, 0.5, 1.0], &[2, 2]);
+        let x = Tensor::from_vec(vec![0.3, -0.7], &[2, 1]);
+        let h = hessian(|x| x.t().matmul(&a).matmul(x).sum().scalar_mul(0.5), &x);
+        assert_eq!(h.shape(), &[2, 1, 2, 1]);
+        assert_close(&h.to_vec(), &a.to_vec(), 1e-4);
+    }
+
+    #[test]
+    fn test_vjp_matches_jacobian() {
+        let a = Tensor::from_vec(vec![1.0, -2.0, 0.5, 3.0, 0.2, -1.0], &[3, 2]);
+        let f = |x: &Tensor| a.matmul(x).tanh();
+        let x = Tensor::from_vec(vec![0.3, -0.4], &[2, 1]);
+        let (y, pullback) = vjp(f, &x);
+        assert!(y.is_leaf());
+        let j = jacobian(f, &x).reshape(&[3, 2]);
+        for u in [vec![1.0, 0.0, 0.0], vec![0.5, -1.0, 2.0]] {
+            let u = Tensor::from_vec(u, &[3, 1]);
+            let expected = j.t().matmul(&u);
+            // Repeated calls don't accumulate
+            assert_close(&pullback(&u).to_vec(), &expected.to_vec(), 1e-4);
+        }
+    }
+
+    #[test]
+    fn test_vjp_leaves_grads_alone() {
+        let w = Tensor::from_vec(vec![2.0, -1.0], &[2]).requires_grad(true);
+        let x = Tensor::from_vec(vec![1.0, 3.0], &[2]);
+        let (_, pullback) = vjp(|x| x.mul(&w), &x);
+        assert_eq!(
+            pullback(&Tensor::from_vec(vec![1.0, 1.0], &[2])).to_vec(),
+            vec![2.0, -1.0]
+        );
+        assert!(w.grad().is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "vjp expected a cotangent of shape [2]")]
+    fn test_vjp_wrong_cotangent() {
+        let (_, pullback) = vjp(|x| x.exp(), &Tensor::zeros(&[2]));
+        pullback(&Tensor::zeros(&[3]));
+    }
+
+    #[test]
+    fn test_jvp_matches_jacobian() {
+        let a = Tensor::from_vec(vec![1.0, -2.0, 0.5, 3.0, 0.2, -1.0], &[3, 2]);
+        let f = |x: &Tensor| a.matmul(x).tanh().mul(&a.matmul(x)).exp();
+        let x = Tensor::from_vec(vec![0.3, -0.4], &[2, 1]);
+        let v = Tensor::from_vec(vec![1.5, 0.5], &[2, 1]);
+        let (y, jv) = jvp(f, &x, &v);
+        assert_eq!(y.to_vec(), f(&x).to_vec());
+        assert!(y.is_leaf() && jv.is_leaf());
+        let expected = jacobian(f, &x).reshape(&[3, 2]).matmul(&v);
+        assert_eq!(jv.shape(), &[3, 1]);
+        assert_close(&jv.to_vec(), &expected.to_vec(), 1e-4);
+    }
+
+    #[test]
+    fn test_jvp_of_identity_and_constant() {
+        let x = Tensor::from_vec(vec![1.0, 2.0], &[2]);
+        let v = Tensor::from_vec(vec![-3.0, 4.0], &[2]);
+        assert_eq!(jvp(|x| x.clone(), &x, &v).1.to_vec(), v.to_vec());
+        let (_, jv) = jvp(|_| Tensor::zeros(&[3]), &x, &v);
+        assert_eq!(jv.to_vec(), vec![0.0; 3]);
+    }
+
+    #[test]
+    #[should_panic(expected = "the backward of csr_matmul is computed outside the graph")]
+    fn test_jvp_through_first_order_rule() {
+        let a = Csr::from_dense(&Tensor::from_vec(vec![1.0, 0.0, 0.0, 2.0], &[2, 2]));
+        let x = Tensor::from_vec(vec![1.0, 2.0], &[2, 1]);
+        jvp(|x| a.matmul(x), &x, &x);
+    }
+
+    #[test]
+    #[should_panic(expected = "hessian requires a single-element output")]
+    fn test_hessian_non_scalar() {
+        hessian(|x| x.exp(), &Tensor::zeros(&[2]));
+    }
+}
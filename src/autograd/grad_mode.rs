@@ -0,0 +1,131 @@
+use std::cell::Cell;
+
+thread_local! {
+    static GRAD_ENABLED: Cell<bool> = const { Cell::new(true) };
+}
+
+/// Whether ops currently record themselves into the graph.
+pub(crate) fn is_enabled() -> bool {
+    GRAD_ENABLED.with(Cell::get)
+}
+
+/// Whether ops on tracked tensors are currently recorded: true unless
+/// inside [`no_grad`] or while a [`NoGradGuard`] is alive.
+pub fn is_grad_enabled() -> bool {
+    is_enabled()
+}
+
+/// Sets the grad mode for as long as it is alive, then restores the
+/// previous mode (also on panic, since it runs in `Drop`).
+pub(crate) struct GradModeGuard {
+    prev: bool,
+}
+
+impl GradModeGuard {
+    pub(crate) fn new(enabled: bool) -> Self {
+        let prev = GRAD_ENABLED.with(|flag| flag.replace(enabled));
+        Self { prev }
+    }
+}
+
+impl Drop for GradModeGuard {
+    fn drop(&mut self) {
+        GRAD_ENABLED.with(|flag| flag.set(self.prev));
+    }
+}
+
+/// Disables graph recording until dropped.
+///
+/// While it is alive, ops produce plain tensors even when their inputs
+/// track gradients, so no graph (and none of the memory it holds on to) is
+/// built: use it for inference, evaluation and metrics. Guards nest, and
+/// dropping one restores whatever mode was active before it. The mode is
+/// per thread.
+///
+/// # Example
+/// ```
+/// use delta::autograd::{NoGradGuard, is_grad_enabled};
+/// use delta::tensor::Tensor;
+/// let w = Tensor::from_vec(vec![2.0], &[1]).requires_grad(true);
+/// {
+///     let _guard = NoGradGuard::new();
+///     assert!(w.scalar_mul(3.0).is_leaf());
+/// }
+/// assert!(is_grad_enabled());
+/// assert!(!w.scalar_mul(3.0).is_leaf());
+/// ```
+pub struct NoGradGuard {
+    _guard: GradModeGuard,
+}
+
+impl NoGradGuard {
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        Self {
+            _guard: GradModeGuard::new(false),
+        }
+    }
+}
+
+/// Run `f` with graph recording disabled and return its result.
+///
+/// The closure form of [`NoGradGuard`].
+///
+/// # Example
+/// ```
+/// use delta::autograd::no_grad;
+/// use delta::tensor::Tensor;
+/// let w = Tensor::from_vec(vec![1.0, -1.0], &[2]).requires_grad(true);
+/// let x = Tensor::from_vec(vec![3.0, 4.0], &[2]);
+/// let prediction = no_grad(|| w.mul(&x).sum());
+/// assert!(prediction.is_leaf());
+/// assert_eq!(prediction.get(&[]), -1.0);
+/// ```
+pub fn no_grad<R>(f: impl FnOnce() -> R) -> R {
+    let _guard = NoGradGuard::new();
+    f()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tensor::Tensor;
+
+    #[test]
+    fn test_no_grad_does_not_record() {
+        let w = Tensor::from_vec(vec![1.0, 2.0], &[2]).requires_grad(true);
+        let y = no_grad(|| w.powi(2).add(&w).sum());
+        assert!(y.node().is_none());
+        assert!(is_grad_enabled());
+        // Tracking on the leaf itself is untouched
+        assert!(w.node().is_some());
+    }
+
+    #[test]
+    fn test_guards_nest() {
+        {
+            let _outer = NoGradGuard::new();
+            {
+                let _inner = NoGradGuard::new();
+                assert!(!is_grad_enabled());
+            }
+            assert!(!is_grad_enabled());
+        }
+        assert!(is_grad_enabled());
+    }
+
+    #[test]
+    fn test_mode_restored_on_panic() {
+        let result = std::panic::catch_unwind(|| no_grad(|| panic!("boom")));
+        assert!(result.is_err());
+        assert!(is_grad_enabled());
+    }
+
+    #[test]
+    fn test_value_and_grad_inside_no_grad() {
+        // Functions that need gradients switch recording back on
+        let x = Tensor::from_vec(vec![1.0], &[1]);
+        let (_, grads) = no_grad(|| crate::autograd::value_and_grad(|p| p[0].powi(2).sum(), &[x]));
+        assert_eq!(grads[0].to_vec(), vec![2.0]);
+    }
+}
@@ -0,0 +1,216 @@
+use std::fmt;
+
+use crate::autograd::value_and_grad;
+use crate::tensor::Tensor;
+
+/// One gradient element where the backward rule and finite differences
+/// disagree.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GradMismatch {
+    /// Position of the input in the slice passed to [`gradcheck`].
+    pub input: usize,
+    /// Index of the element within that input.
+    pub index: Vec<usize>,
+    /// The gradient computed by the backward pass.
+    pub analytic: f32,
+    /// The central finite-difference estimate.
+    pub numeric: f32,
+}
+
+/// Outcome of a [`gradcheck`].
+#[derive(Debug, Clone)]
+pub struct GradcheckReport {
+    /// Every element that failed the tolerance, in input order.
+    pub mismatches: Vec<GradMismatch>,
+    /// Number of gradient elements compared.
+    pub checked: usize,
+}
+
+impl GradcheckReport {
+    /// Whether every element was within tolerance.
+    pub fn passed(&self) -> bool {
+        self.mismatches.is_empty()
+    }
+}
+
+impl fmt::Display for GradcheckReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "gradcheck: {} of {} elements mismatched",
+            self.mismatches.len(),
+            self.checked
+        )?;
+        for m in &self.mismatches {
+            write!(
+                f,
+                "\n  input {} {:?}: analytic {} vs numeric {}",
+                m.input, m.index, m.analytic, m.numeric
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Compare the gradients of `f` at `inputs` against central finite
+/// differences, element by element.
+///
+/// The output of `f` is reduced to a scalar with fixed, distinct weights
+/// per element, so a backward rule that gets the right values in the wrong
+/// places (say, a missing transpose) is caught too. Each input element is
+/// then nudged by ±`eps`, and the slope `(f(x + eps) - f(x - eps)) / 2eps`
+/// compared with the analytic gradient: an element passes if they differ
+/// by at most `tol · max(1, |numeric|)`, an absolute tolerance for small
+/// gradients and a relative one for large ones.
+///
+/// Everything runs in f32, so `eps` trades truncation error against
+/// rounding: around 1e-3 with a `tol` of 1e-2 suits most smooth functions.
+/// Keep inputs away from kinks (`relu` at 0, `abs` at 0), where the two
+/// sides of the difference see different slopes. Inputs `f` does not use
+/// have a zero gradient. `f` may call [`grad`](crate::autograd::grad)
+/// itself with `create_graph` on, to check second derivatives.
+///
+/// Costs one backward pass plus two evaluations of `f` per input element.
+///
+/// # Panics
+/// Panics if `eps` or `tol` is not positive.
+///
+/// # Example
+/// ```
+/// use delta::autograd::gradcheck;
+/// use delta::tensor::Tensor;
+/// let a = Tensor::from_vec(vec![0.5, -1.0, 2.0, 0.3], &[2, 2]);
+/// let b = Tensor::from_vec(vec![1.5, 0.2], &[2, 1]);
+/// let report = gradcheck(|t| t[0].matmul(&t[1]).tanh(), &[a, b], 1e-3, 1e-2);
+/// assert!(report.passed(), "{}", report);
+/// assert_eq!(report.checked, 6);
+/// ```
+pub fn gradcheck(
+    f: impl Fn(&[Tensor]) -> Tensor,
+    inputs: &[Tensor],
+    eps: f32,
+    tol: f32,
+) -> GradcheckReport {
+    assert!(
+        eps > 0.0 && tol > 0.0,
+        "gradcheck requires positive eps and tol, got {} and {}",
+        eps,
+        tol
+    );
+    let weights = |out: &Tensor| {
+        let w = (0..out.nelems()).map(|i| 1.0 + 0.25 * i as f32).collect();
+        Tensor::from_vec(w, out.shape())
+    };
+    let loss = |inputs: &[Tensor]| {
+        let out = f(inputs);
+        out.mul(&weights(&out)).sum()
+    };
+
+    let (_, grads) = value_and_grad(loss, inputs);
+
+    let mut mismatches = Vec::new();
+    let mut checked = 0;
+    for (i, (input, grad)) in inputs.iter().zip(&grads).enumerate() {
+        for (j, analytic) in grad.to_vec().into_iter().enumerate() {
+            let nudged = |delta: f32| {
+                let mut data = input.to_vec();
+                data[j] += delta;
+                let mut args: Vec<Tensor> = inputs.iter().map(Tensor::detach).collect();
+                args[i] = Tensor::from_vec(data, input.shape());
+                // Recorded like any call, so `f` may itself take gradients
+                loss(&args).get(&[])
+            };
+            let numeric = (nudged(eps) - nudged(-eps)) / (2.0 * eps);
+            checked += 1;
+            // False when either side is NaN
+            let close = (analytic - numeric).abs() <= tol * numeric.abs().max(1.0);
+            if !close {
+                mismatches.push(GradMismatch {
+                    input: i,
+                    index: unravel(j, input.shape()),
+                    analytic,
+                    numeric,
+                });
+            }
+        }
+    }
+    GradcheckReport {
+        mismatches,
+        checked,
+    }
+}
+
+/// The multi-dimensional index of the `flat`-th element of a row-major
+/// tensor of the given shape.
+fn unravel(mut flat: usize, shape: &[usize]) -> Vec<usize> {
+    let mut index = vec![0; shape.len()];
+    for (i, &dim) in index.iter_mut().zip(shape).rev() {
+        *i = flat % dim;
+        flat /= dim;
+    }
+    index
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::autograd::{Function, record};
+
+    /// x², with a backward rule that forgets the factor 2.
+    struct BadSquare;
+
+    impl Function for BadSquare {
+        fn forward(&self, inputs: &[Tensor]) -> Tensor {
+            inputs[0].map(|v| v * v)
+        }
+
+        fn backward(&self, inputs: &[Tensor], _output: &Tensor, grad: &Tensor) -> Vec<Tensor> {
+            vec![grad.mul(&inputs[0])]
+        }
+    }
+
+    #[test]
+    fn test_passes_for_correct_rules() {
+        let x = Tensor::from_vec(vec![0.3, -1.2, 0.8, 2.0, -0.4, 1.1], &[2, 3]);
+        let report = gradcheck(|t| t[0].exp().mul(&t[0].sin()), &[x], 1e-3, 1e-2);
+        assert!(report.passed(), "{}", report);
+        assert_eq!(report.checked, 6);
+    }
+
+    #[test]
+    fn test_reports_each_mismatch() {
+        let x = Tensor::from_vec(vec![0.0, 1.0, -2.0, 3.0], &[2, 2]);
+        let report = gradcheck(|t| BadSquare.apply(t), &[x], 1e-3, 1e-2);
+        // Off by a factor 2 everywhere but at 0
+        let indices: Vec<Vec<usize>> = report.mismatches.iter().map(|m| m.index.clone()).collect();
+        assert_eq!(indices, vec![vec![0, 1], vec![1, 0], vec![1, 1]]);
+        let m = &report.mismatches[2];
+        assert_eq!(m.input, 0);
+        // The weight of element 3 is 1.75
+        assert!((m.analytic - 1.75 * 3.0).abs() < 1e-5);
+        assert!((m.numeric - 1.75 * 6.0).abs() < 1e-2);
+        assert!(
+            report
+                .to_string()
+                .starts_with("gradcheck: 3 of 4 elements mismatched")
+        );
+    }
+
+    #[test]
+    fn test_unused_inputs_and_nan() {
+        let x = Tensor::from_vec(vec![1.0], &[1]);
+        let y = Tensor::from_vec(vec![2.0], &[1]);
+        assert!(gradcheck(|t| t[0].scalar_mul(3.0), &[x.clone(), y], 1e-3, 1e-2).passed());
+
+        // A backward rule producing NaN never passes
+        let nan_grad =
+            |t: &[Tensor]| record(t[0].clone(), "nan", &[&t[0]], |g| vec![g.map(|_| f32::NAN)]);
+        assert!(!gradcheck(nan_grad, &[x], 1e-3, 1e-2).passed());
+    }
+
+    #[test]
+    fn test_unravel() {
+        assert_eq!(unravel(5, &[2, 3]), vec![1, 2]);
+        assert_eq!(unravel(0, &[]), Vec::<usize>::new());
+    }
+}
@@ -0,0 +1,94 @@
+//! Inspecting the recorded computation graph.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::rc::Rc;
+
+use crate::autograd::engine::topological_order;
+use crate::tensor::Tensor;
+
+/// Describe the graph that produced `output` in Graphviz DOT.
+///
+/// Every node reachable from `output` becomes a vertex labelled with its
+/// op and shape, with an edge from each input to the op that used it, so
+/// data flows towards the output at the bottom. Leaves are drawn as
+/// boxes, and `output` with a double border. Constant arguments are not
+/// part of the graph and do not appear.
+///
+/// Render it with `dot -Tsvg graph.dot -o graph.svg`. Whatever the
+/// picture shows is what the graph keeps alive: a graph growing across
+/// training steps points at a tensor that should have been detached.
+///
+/// # Panics
+/// Panics if `output` is not part of a graph.
+///
+/// # Example
+/// ```
+/// use delta::autograd::graph::to_dot;
+/// use delta::tensor::Tensor;
+/// let w = Tensor::from_vec(vec![1.0, 2.0], &[2]).requires_grad(true);
+/// let loss = w.mul(&w).sum();
+/// let dot = to_dot(&loss);
+/// assert!(dot.starts_with("digraph autograd {"));
+/// assert!(dot.contains("[label=\"mul\\n[2]\"]"));
+/// ```
+pub fn to_dot(output: &Tensor) -> String {
+    let root = output
+        .node()
+        .expect("to_dot called on a tensor that is not part of a graph");
+    let order = topological_order(root);
+    let ids: HashMap<_, _> = order
+        .iter()
+        .enumerate()
+        .map(|(i, node)| (Rc::as_ptr(node), i))
+        .collect();
+
+    let mut dot = String::from("digraph autograd {\n    node [fontname=\"monospace\"];\n");
+    for (i, node) in order.iter().enumerate() {
+        let mut attrs = format!("label=\"{}\\n{:?}\"", node.op(), node.shape());
+        if node.is_leaf() {
+            attrs.push_str(", shape=box");
+        }
+        if Rc::ptr_eq(node, root) {
+            attrs.push_str(", peripheries=2");
+        }
+        writeln!(dot, "    n{} [{}];", i, attrs).unwrap();
+    }
+    for (i, node) in order.iter().enumerate() {
+        for input in node.inputs().iter().flatten() {
+            writeln!(dot, "    n{} -> n{};", ids[&Rc::as_ptr(input)], i).unwrap();
+        }
+    }
+    dot.push_str("}\n");
+    dot
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_dot() {
+        let x = Tensor::from_vec(vec![1.0, 2.0, 3.0, 4.0], &[2, 2]).requires_grad(true);
+        let c = Tensor::from_vec(vec![0.5, 0.5], &[2]);
+        // x is used twice: one vertex, two edges
+        let y = x.matmul(&x).reshape(&[4]).sum_dim(0, false);
+        let dot = to_dot(&y.add(&c.sum()));
+        assert_eq!(
+            dot,
+            "digraph autograd {\n    node [fontname=\"monospace\"];\n    \
+             n0 [label=\"leaf\\n[2, 2]\", shape=box];\n    \
+             n1 [label=\"matmul\\n[2, 2]\"];\n    \
+             n2 [label=\"reshape\\n[4]\"];\n    \
+             n3 [label=\"sum_dim\\n[]\"];\n    \
+             n4 [label=\"add\\n[]\", peripheries=2];\n    \
+             n0 -> n1;\n    n0 -> n1;\n    n1 -> n2;\n    n2 -> n3;\n    n3 -> n4;\n}\n"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "not part of a graph")]
+    fn test_to_dot_without_graph() {
+        to_dot(&Tensor::zeros(&[1]));
+    }
+}
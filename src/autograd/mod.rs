@@ -0,0 +1,53 @@
+//! Reverse-mode automatic differentiation.
+//!
+//! Every differentiable op whose inputs take part in a graph records a
+//! node holding the op's backward function, linked to the nodes of its
+//! inputs. [`Tensor::backward`](crate::tensor::Tensor::backward) walks that
+//! graph from the output back to the leaves, applying the chain rule.
+//!
+//! ```text
+//!   a ──┐
+//!       ├─ mul ── c ── sum ── loss
+//!   b ──┘
+//! ```
+//!
+//! Ops only record when at least one input is part of a graph, so plain
+//! tensors never pay for bookkeeping beyond a cheap check. Recording can
+//! also be switched off altogether with [`no_grad`], for inference.
+//!
+//! User-defined ops plug into the same graph through [`Function`], are
+//! shared by name with [`register_op`], checked against finite differences
+//! with [`gradcheck`], and
+//! [`checkpoint`] trades memory for recomputation on deep models, with
+//! [`checkpoint_offload`] spilling what is left to disk. When a
+//! gradient turns NaN, [`detect_anomaly`] finds the op responsible.
+
+mod anomaly;
+mod checkpoint;
+mod engine;
+mod function;
+mod functional;
+mod grad_mode;
+mod gradcheck;
+pub mod graph;
+mod node;
+mod offload;
+mod overrides;
+mod registry;
+
+pub use anomaly::{AnomalyGuard, detect_anomaly, is_anomaly_enabled};
+pub use checkpoint::{checkpoint, checkpoint_offload};
+pub use engine::grad;
+pub(crate) use engine::{gradients, value_and_grad};
+pub use function::Function;
+pub use functional::{hessian, jacobian, jvp, vjp};
+pub(crate) use grad_mode::GradModeGuard;
+pub use grad_mode::{NoGradGuard, is_grad_enabled, no_grad};
+pub use gradcheck::{GradMismatch, GradcheckReport, gradcheck};
+pub(crate) use node::{Node, record, record_first_order};
+pub use offload::ScratchFile;
+pub use overrides::{override_gradient, remove_gradient_override};
+pub use registry::{call_op, is_op_registered, register_op, registered_ops};
+
+#[cfg(test)]
+pub(crate) mod testing;
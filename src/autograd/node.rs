@@ -0,0 +1,293 @@
+use std::backtrace::Backtrace;
+use std::cell::{Cell, RefCell};
+use std::fmt;
+use std::rc::Rc;
+
+use crate::autograd::{anomaly, grad_mode, overrides};
+use crate::debug;
+use crate::tensor::Tensor;
+
+/// Maps the gradient of a node's output to one gradient per input.
+pub(crate) type BackwardFn = Box<dyn Fn(&Tensor) -> Vec<Tensor>>;
+
+/// Sees the complete gradient of a node during a backward pass, and may
+/// replace it.
+pub(crate) type GradHook = Box<dyn Fn(&Tensor) -> Option<Tensor>>;
+
+/// A vertex of the computation graph.
+///
+/// Leaves are tensors the user asked gradients for; they have no backward
+/// function. Every other node was produced by an op and knows how to turn
+/// its output gradient into gradients for its inputs.
+///
+/// Backward functions capture the op's *inputs* (never its output), so
+/// references only point from outputs towards leaves and `Rc` never forms
+/// a cycle.
+///
+/// The version counts writes to the tensor's values that bypassed the
+/// graph (`set`, `map_inplace`, in-place ops with recording off). Each
+/// node remembers the versions its inputs had when it was recorded, so the
+/// backward pass can tell when an op consumed values that its input's
+/// backward function no longer describes.
+///
+/// A frozen leaf is one the user switched off with
+/// `Tensor::requires_grad_(false)`: its tensors present no node to ops,
+/// which then treat them as constants, until it is switched back on.
+///
+/// A first-order node has a backward function computed outside the graph
+/// (see [`record_first_order`]): its gradients are right, but cannot be
+/// differentiated again.
+pub(crate) struct Node {
+    op: &'static str,
+    shape: Vec<usize>,
+    inputs: Vec<Option<Rc<Node>>>,
+    input_versions: Vec<u64>,
+    version: Cell<u64>,
+    backward: Option<BackwardFn>,
+    grad: RefCell<Option<Tensor>>,
+    hooks: RefCell<Vec<GradHook>>,
+    trace: Option<Backtrace>,
+    frozen: Cell<bool>,
+    first_order: bool,
+}
+
+impl Node {
+    /// A leaf node for a tensor of the given shape.
+    pub(crate) fn leaf(shape: &[usize]) -> Rc<Node> {
+        Rc::new(Node {
+            op: "leaf",
+            shape: shape.to_vec(),
+            inputs: Vec::new(),
+            input_versions: Vec::new(),
+            version: Cell::new(0),
+            backward: None,
+            grad: RefCell::new(None),
+            hooks: RefCell::new(Vec::new()),
+            trace: None,
+            frozen: Cell::new(false),
+            first_order: false,
+        })
+    }
+
+    /// Whether this node was created by the user rather than by an op.
+    pub(crate) fn is_leaf(&self) -> bool {
+        self.backward.is_none()
+    }
+
+    /// Whether this leaf is excluded from gradient computation.
+    pub(crate) fn is_frozen(&self) -> bool {
+        self.frozen.get()
+    }
+
+    pub(crate) fn set_frozen(&self, frozen: bool) {
+        self.frozen.set(frozen);
+    }
+
+    /// Whether the backward function of this node is computed outside the
+    /// graph, so that it cannot run with `create_graph`.
+    pub(crate) fn is_first_order(&self) -> bool {
+        self.first_order
+    }
+
+    /// Name of the op that produced this node ("leaf" for leaves).
+    pub(crate) fn op(&self) -> &'static str {
+        self.op
+    }
+
+    /// Shape of the tensor this node stands for.
+    pub(crate) fn shape(&self) -> &[usize] {
+        &self.shape
+    }
+
+    /// Input nodes, one per op argument. `None` marks an argument that is
+    /// not part of the graph (a constant).
+    pub(crate) fn inputs(&self) -> &[Option<Rc<Node>>] {
+        &self.inputs
+    }
+
+    /// Backtrace of the op call that created this node, captured when it
+    /// was recorded in anomaly mode.
+    pub(crate) fn trace(&self) -> Option<&Backtrace> {
+        self.trace.as_ref()
+    }
+
+    /// Versions of the inputs when this node was recorded, one per input.
+    pub(crate) fn input_versions(&self) -> &[u64] {
+        &self.input_versions
+    }
+
+    /// Number of unrecorded in-place writes to the tensor since this node
+    /// was created.
+    pub(crate) fn version(&self) -> u64 {
+        self.version.get()
+    }
+
+    pub(crate) fn bump_version(&self) {
+        self.version.set(self.version.get() + 1);
+    }
+
+    pub(crate) fn backward_fn(&self) -> Option<&BackwardFn> {
+        self.backward.as_ref()
+    }
+
+    /// The gradient accumulated by backward passes since the last reset,
+    /// if any.
+    pub(crate) fn grad(&self) -> Option<Tensor> {
+        self.grad.borrow().clone()
+    }
+
+    /// Add `grad` to the stored gradient (or store it, if there is none).
+    pub(crate) fn accumulate_grad(&self, grad: Tensor) {
+        let mut slot = self.grad.borrow_mut();
+        *slot = Some(match slot.take() {
+            Some(acc) => acc.add(&grad),
+            None => grad,
+        });
+    }
+
+    pub(crate) fn clear_grad(&self) {
+        *self.grad.borrow_mut() = None;
+    }
+
+    pub(crate) fn add_hook(&self, hook: GradHook) {
+        self.hooks.borrow_mut().push(hook);
+    }
+
+    /// Run the registered hooks on `grad` in registration order, each one
+    /// seeing the result of the previous.
+    ///
+    /// # Panics
+    /// Panics if a hook returns a gradient of a different shape.
+    pub(crate) fn apply_hooks(&self, grad: Tensor) -> Tensor {
+        let hooks = self.hooks.borrow();
+        hooks.iter().fold(grad, |grad, hook| match hook(&grad) {
+            Some(replaced) => {
+                assert_eq!(
+                    replaced.shape(),
+                    grad.shape(),
+                    "gradient hook on {} returned a gradient of the wrong shape",
+                    self.op
+                );
+                replaced
+            }
+            None => grad,
+        })
+    }
+}
+
+impl fmt::Debug for Node {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Node")
+            .field("op", &self.op)
+            .field("shape", &self.shape)
+            .field("inputs", &self.inputs.len())
+            .field("hooks", &self.hooks.borrow().len())
+            .finish()
+    }
+}
+
+impl Drop for Node {
+    /// Tear the graph down iteratively.
+    ///
+    /// The default drop recurses once per node, so dropping the output of a
+    /// long chain of ops (e.g. an unrolled loop) would overflow the stack.
+    /// Instead, inputs this node solely owns are moved onto a worklist and
+    /// dismantled there.
+    fn drop(&mut self) {
+        // The closure holds clones of the inputs; release those first so the
+        // `inputs` list below is the last owner where possible.
+        self.backward = None;
+        let mut stack: Vec<Rc<Node>> = self.inputs.drain(..).flatten().collect();
+        while let Some(node) = stack.pop() {
+            if let Some(mut node) = Rc::into_inner(node) {
+                node.backward = None;
+                stack.extend(node.inputs.drain(..).flatten());
+            }
+        }
+    }
+}
+
+/// Attach a graph node to the output of an op.
+///
+/// `backward` receives the gradient of `output` and must return one
+/// gradient per entry of `inputs`, each shaped like that input. If grad
+/// mode is off or no input is part of a graph, `output` is returned
+/// untouched and `backward` is dropped. If a gradient override is
+/// registered for `op`, it is recorded instead of `backward`. The op is
+/// first added to the trace being recorded, if any, and in debug mode
+/// `output` is checked against the watches.
+pub(crate) fn record(
+    output: Tensor,
+    op: &'static str,
+    inputs: &[&Tensor],
+    backward: impl Fn(&Tensor) -> Vec<Tensor> + 'static,
+) -> Tensor {
+    attach(output, op, inputs, Box::new(backward), false)
+}
+
+/// [`record`] for an op whose backward function is computed outside the
+/// graph (on raw values, or through a nested backward pass), so that
+/// differentiating it again would silently give zeros. A backward pass
+/// with `create_graph` panics when it reaches such a node, unless a
+/// gradient override replaces its rule.
+pub(crate) fn record_first_order(
+    output: Tensor,
+    op: &'static str,
+    inputs: &[&Tensor],
+    backward: impl Fn(&Tensor) -> Vec<Tensor> + 'static,
+) -> Tensor {
+    attach(output, op, inputs, Box::new(backward), true)
+}
+
+fn attach(
+    output: Tensor,
+    op: &'static str,
+    inputs: &[&Tensor],
+    backward: BackwardFn,
+    first_order: bool,
+) -> Tensor {
+    debug::trace_op(op, inputs, &output);
+    debug::check_watches(op, inputs, &output);
+    if !grad_mode::is_enabled() || inputs.iter().all(|t| t.node().is_none()) {
+        return output;
+    }
+
+    let (backward, first_order): (BackwardFn, bool) = match overrides::gradient_override(op) {
+        Some(rule) => {
+            let saved: Vec<Tensor> = inputs.iter().map(|t| (*t).clone()).collect();
+            // Detached, or the node would own its own output
+            let out = output.detach();
+            let backward: BackwardFn = Box::new(move |g| {
+                let grads = rule(&saved, &out, g);
+                for (grad, input) in grads.iter().zip(&saved) {
+                    assert_eq!(
+                        grad.shape(),
+                        input.shape(),
+                        "gradient override for {} returned a gradient of the wrong shape",
+                        op
+                    );
+                }
+                grads
+            });
+            (backward, false)
+        }
+        None => (backward, first_order),
+    };
+    let node = Node {
+        op,
+        shape: output.shape().to_vec(),
+        inputs: inputs.iter().map(|t| t.node().cloned()).collect(),
+        input_versions: inputs
+            .iter()
+            .map(|t| t.node().map_or(0, |n| n.version()))
+            .collect(),
+        version: Cell::new(0),
+        backward: Some(backward),
+        grad: RefCell::new(None),
+        hooks: RefCell::new(Vec::new()),
+        trace: anomaly::capture_trace(),
+        frozen: Cell::new(false),
+        first_order,
+    };
+    output.with_node(Rc::new(node))
+}
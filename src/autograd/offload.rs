@@ -0,0 +1,222 @@
+use std::cell::{Cell, RefCell};
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::tensor::Tensor;
+
+/// Names of temporary scratch files, unique within the process.
+static TEMP_FILES: AtomicUsize = AtomicUsize::new(0);
+
+/// A file that offloaded activations are spilled to; see
+/// [`checkpoint_offload`](crate::autograd::checkpoint_offload).
+///
+/// Regions are appended as activations are spilled and stay allocated as
+/// long as any graph references one of them. Once every region has been
+/// released, new spills start again from the beginning, so a training loop
+/// reuses the same space step after step. The operating system caches
+/// recently written pages, so reads back during the backward pass usually
+/// come from memory the kernel can still reclaim under pressure.
+///
+/// Share one file between many segments with an `Rc`.
+#[derive(Debug)]
+pub struct ScratchFile {
+    file: RefCell<File>,
+    path: PathBuf,
+    remove_on_drop: bool,
+    /// End of the last allocated region, in bytes
+    end: Cell<u64>,
+    /// Number of regions still referenced
+    live: Cell<usize>,
+    /// Total size of the regions still referenced, in bytes
+    in_use: Cell<u64>,
+    /// Largest `in_use` reached
+    peak: Cell<u64>,
+}
+
+impl ScratchFile {
+    /// Create (or truncate) a scratch file at `path`. The file is left in
+    /// place when dropped.
+    pub fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::open(path.as_ref().to_path_buf(), false)
+    }
+
+    /// A scratch file in the system temporary directory, removed when
+    /// dropped.
+    pub fn temp() -> io::Result<Self> {
+        let name = format!(
+            "delta-scratch-{}-{}.bin",
+            std::process::id(),
+            TEMP_FILES.fetch_add(1, Ordering::Relaxed)
+        );
+        Self::open(std::env::temp_dir().join(name), true)
+    }
+
+    fn open(path: PathBuf, remove_on_drop: bool) -> io::Result<Self> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)?;
+        Ok(Self {
+            file: RefCell::new(file),
+            path,
+            remove_on_drop,
+            end: Cell::new(0),
+            live: Cell::new(0),
+            in_use: Cell::new(0),
+            peak: Cell::new(0),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Bytes held by regions that are still referenced. Released regions
+    /// stop counting at once, although their space is only reused once
+    /// every region has been released.
+    pub fn bytes_in_use(&self) -> u64 {
+        self.in_use.get()
+    }
+
+    /// The most bytes in use at any one time.
+    pub fn peak_bytes(&self) -> u64 {
+        self.peak.get()
+    }
+
+    /// Number of spilled tensors still referenced.
+    pub fn live_regions(&self) -> usize {
+        self.live.get()
+    }
+}
+
+impl Drop for ScratchFile {
+    fn drop(&mut self) {
+        if self.remove_on_drop {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+}
+
+/// The values of a tensor written out to a [`ScratchFile`], read back on
+/// demand. Releases its region when dropped.
+pub(crate) struct Spilled {
+    file: Rc<ScratchFile>,
+    offset: u64,
+    shape: Vec<usize>,
+}
+
+impl Spilled {
+    /// Write the values of `tensor` to the end of `file`.
+    ///
+    /// # Panics
+    /// Panics if writing the file fails.
+    pub(crate) fn new(tensor: &Tensor, file: &Rc<ScratchFile>) -> Self {
+        let bytes: Vec<u8> = tensor
+            .to_vec()
+            .iter()
+            .flat_map(|v| v.to_le_bytes())
+            .collect();
+        let offset = file.end.get();
+        let result = {
+            let mut f = file.file.borrow_mut();
+            f.seek(SeekFrom::Start(offset))
+                .and_then(|_| f.write_all(&bytes))
+        };
+        if let Err(e) = result {
+            panic!(
+                "failed to spill an activation of shape {:?} to {}: {}",
+                tensor.shape(),
+                file.path.display(),
+                e
+            );
+        }
+        file.end.set(offset + bytes.len() as u64);
+        file.in_use.set(file.in_use.get() + bytes.len() as u64);
+        file.peak.set(file.peak.get().max(file.in_use.get()));
+        file.live.set(file.live.get() + 1);
+        Self {
+            file: Rc::clone(file),
+            offset,
+            shape: tensor.shape().to_vec(),
+        }
+    }
+
+    /// Read the tensor back, as a constant.
+    ///
+    /// # Panics
+    /// Panics if reading the file fails.
+    pub(crate) fn load(&self) -> Tensor {
+        let n: usize = self.shape.iter().product();
+        let mut bytes = vec![0; n * 4];
+        let result = {
+            let mut f = self.file.file.borrow_mut();
+            f.seek(SeekFrom::Start(self.offset))
+                .and_then(|_| f.read_exact(&mut bytes))
+        };
+        if let Err(e) = result {
+            panic!(
+                "failed to read a spilled activation of shape {:?} back from {}: {}",
+                self.shape,
+                self.file.path.display(),
+                e
+            );
+        }
+        let data = bytes
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect();
+        Tensor::from_vec(data, &self.shape)
+    }
+}
+
+impl Drop for Spilled {
+    fn drop(&mut self) {
+        let bytes = 4 * self.shape.iter().product::<usize>() as u64;
+        self.file.in_use.set(self.file.in_use.get() - bytes);
+        let live = self.file.live.get() - 1;
+        self.file.live.set(live);
+        if live == 0 {
+            self.file.end.set(0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip_and_reuse() {
+        let file = Rc::new(ScratchFile::temp().unwrap());
+        let a = Tensor::from_vec(vec![1.5, -2.0, f32::MIN_POSITIVE], &[3]);
+        let b = Tensor::from_vec(vec![0.25; 4], &[2, 2]).t();
+        let (sa, sb) = (Spilled::new(&a, &file), Spilled::new(&b, &file));
+        assert_eq!(file.bytes_in_use(), 28);
+        assert_eq!(sa.load().to_vec(), a.to_vec());
+        assert_eq!(sb.load().shape(), &[2, 2]);
+
+        drop(sa);
+        // Only the region of b is still referenced
+        assert_eq!((file.live_regions(), file.bytes_in_use()), (1, 16));
+        drop(sb);
+        assert_eq!((file.live_regions(), file.bytes_in_use()), (0, 0));
+        let sc = Spilled::new(&a, &file);
+        assert_eq!(file.bytes_in_use(), 12);
+        assert_eq!(file.peak_bytes(), 28);
+        assert_eq!(sc.load().to_vec(), a.to_vec());
+    }
+
+    #[test]
+    fn test_temp_file_removed_on_drop() {
+        let file = ScratchFile::temp().unwrap();
+        let path = file.path().to_path_buf();
+        assert!(path.exists());
+        drop(file);
+        assert!(!path.exists());
+    }
+}
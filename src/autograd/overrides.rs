@@ -0,0 +1,148 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, LazyLock, RwLock};
+
+use crate::tensor::Tensor;
+
+/// A replacement backward rule: `(inputs, output, grad)` to one gradient
+/// per input, like [`Function::backward`](crate::autograd::Function::backward).
+type Override = Arc<dyn Fn(&[Tensor], &Tensor, &Tensor) -> Vec<Tensor> + Send + Sync>;
+
+static OVERRIDES: LazyLock<RwLock<HashMap<String, Override>>> =
+    LazyLock::new(|| RwLock::new(HashMap::new()));
+
+/// Whether any override is registered, so that recording an op skips the
+/// lock when there are none (the common case).
+static ANY: AtomicBool = AtomicBool::new(false);
+
+/// Replace the backward rule of every op named `op` recorded from now on.
+///
+/// The name is the one the graph shows (see
+/// [`to_dot`](crate::autograd::graph::to_dot)): `"exp"`, `"matmul"`,
+/// `"softmax"`, or the name of a [`Function`](crate::autograd::Function).
+/// `backward` receives the op's inputs, its output and the gradient of the
+/// output, and must return one gradient per input. It is made of tensor
+/// ops like any backward rule, so it is recorded when differentiating
+/// twice. Use it to swap in a faster or more stable rule (a fused softmax
+/// backward, say) without touching the op itself.
+///
+/// Overrides are global and shared by all threads; registering one for an
+/// op that already has one replaces it. Nodes recorded before the call
+/// keep the rule they were recorded with.
+///
+/// # Example
+/// ```
+/// use delta::autograd::{override_gradient, remove_gradient_override};
+/// use delta::tensor::Tensor;
+/// // Straight-through rounding: pass the gradient through unchanged
+/// override_gradient("round", |_inputs, _output, grad| vec![grad.clone()]);
+/// let x = Tensor::from_vec(vec![0.3, 1.7], &[2]).requires_grad(true);
+/// x.round().sum().backward();
+/// assert_eq!(x.grad().unwrap().to_vec(), vec![1.0, 1.0]);
+/// remove_gradient_override("round");
+/// ```
+pub fn override_gradient(
+    op: &str,
+    backward: impl Fn(&[Tensor], &Tensor, &Tensor) -> Vec<Tensor> + Send + Sync + 'static,
+) {
+    let mut overrides = OVERRIDES.write().unwrap_or_else(|e| e.into_inner());
+    overrides.insert(op.to_string(), Arc::new(backward));
+    ANY.store(true, Ordering::Release);
+}
+
+/// Go back to the built-in backward rule of `op`. Returns whether an
+/// override was registered.
+pub fn remove_gradient_override(op: &str) -> bool {
+    let mut overrides = OVERRIDES.write().unwrap_or_else(|e| e.into_inner());
+    let removed = overrides.remove(op).is_some();
+    ANY.store(!overrides.is_empty(), Ordering::Release);
+    removed
+}
+
+/// The override registered for `op`, if any.
+pub(crate) fn gradient_override(op: &str) -> Option<Override> {
+    if !ANY.load(Ordering::Acquire) {
+        return None;
+    }
+    let overrides = OVERRIDES.read().unwrap_or_else(|e| e.into_inner());
+    overrides.get(op).cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::autograd::{Function, grad};
+
+    /// x³, under a name no other test uses.
+    struct Cube;
+
+    impl Function for Cube {
+        fn forward(&self, inputs: &[Tensor]) -> Tensor {
+            inputs[0].powi(3)
+        }
+
+        fn backward(&self, inputs: &[Tensor], _output: &Tensor, grad: &Tensor) -> Vec<Tensor> {
+            vec![grad.mul(&inputs[0].powi(2).scalar_mul(3.0))]
+        }
+
+        fn name(&self) -> &'static str {
+            "test.cube"
+        }
+    }
+
+    #[test]
+    fn test_override_and_remove() {
+        let x = Tensor::from_vec(vec![1.0, 2.0], &[2]).requires_grad(true);
+        let before = Cube.apply(std::slice::from_ref(&x));
+
+        // 3x² written through the output instead: 3y / x
+        override_gradient("test.cube", |inputs, output, grad| {
+            vec![
+                grad.mul(&output.scalar_mul(3.0).div(&inputs[0]))
+                    .scalar_mul(10.0),
+            ]
+        });
+        let y = Cube.apply(std::slice::from_ref(&x));
+        let g = grad(&y.sum(), std::slice::from_ref(&x), false);
+        assert_eq!(g[0].to_vec(), vec![30.0, 120.0]);
+
+        // Nodes recorded earlier keep the built-in rule
+        let g = grad(&before.sum(), std::slice::from_ref(&x), false);
+        assert_eq!(g[0].to_vec(), vec![3.0, 12.0]);
+
+        assert!(remove_gradient_override("test.cube"));
+        assert!(!remove_gradient_override("test.cube"));
+        let y = Cube.apply(std::slice::from_ref(&x));
+        let g = grad(&y.sum(), std::slice::from_ref(&x), false);
+        assert_eq!(g[0].to_vec(), vec![3.0, 12.0]);
+    }
+
+    #[test]
+    fn test_override_is_differentiable() {
+        override_gradient("test.cube2", |inputs, _output, grad| {
+            vec![grad.mul(&inputs[0].powi(2).scalar_mul(3.0))]
+        });
+        struct Named;
+        impl Function for Named {
+            fn forward(&self, inputs: &[Tensor]) -> Tensor {
+                inputs[0].powi(3)
+            }
+            fn backward(&self, inputs: &[Tensor], _: &Tensor, grad: &Tensor) -> Vec<Tensor> {
+                vec![Tensor::zeros(inputs[0].shape()).mul(grad)]
+            }
+            fn name(&self) -> &'static str {
+                "test.cube2"
+            }
+        }
+        // d²/dx² x³ = 6x, only right if the override was used and recorded
+        let x = Tensor::from_vec(vec![2.0], &[1]).requires_grad(true);
+        let dy = grad(
+            &Named.apply(std::slice::from_ref(&x)).sum(),
+            std::slice::from_ref(&x),
+            true,
+        );
+        let d2y = grad(&dy[0].sum(), std::slice::from_ref(&x), false);
+        assert_eq!(d2y[0].to_vec(), vec![12.0]);
+        remove_gradient_override("test.cube2");
+    }
+}
@@ -0,0 +1,166 @@
+use std::collections::HashMap;
+use std::sync::{Arc, LazyLock, RwLock};
+
+use crate::autograd::Function;
+use crate::tensor::Tensor;
+
+type Op = Arc<dyn Function + Send + Sync>;
+
+/// Registered ops by name, each with its name leaked once so that graph
+/// nodes can refer to it for the rest of the program.
+static REGISTRY: LazyLock<RwLock<HashMap<&'static str, Op>>> =
+    LazyLock::new(|| RwLock::new(HashMap::new()));
+
+/// Make `op` callable by `name` from anywhere in the program, through
+/// [`call_op`].
+///
+/// Lets a crate built on delta ship new ops (a fused kernel with its
+/// backward rule, say) that code elsewhere looks up by name, without
+/// either side depending on the other's types. The registry is global and
+/// shared by all threads; ops stay registered until the program exits.
+/// Recorded nodes carry the registered name, so it shows up in
+/// [`to_dot`](crate::autograd::graph::to_dot) and anomaly reports.
+///
+/// # Panics
+/// Panics if an op is already registered under `name`.
+///
+/// # Example
+/// ```
+/// use delta::autograd::{Function, call_op, register_op};
+/// use delta::tensor::Tensor;
+///
+/// /// x ↦ x² + x in one pass
+/// struct SquarePlus;
+///
+/// impl Function for SquarePlus {
+///     fn forward(&self, inputs: &[Tensor]) -> Tensor {
+///         inputs[0].map(|x| x * x + x)
+///     }
+///
+///     fn backward(&self, inputs: &[Tensor], _output: &Tensor, grad: &Tensor) -> Vec<Tensor> {
+///         vec![grad.mul(&inputs[0].scalar_mul(2.0).scalar_add(1.0))]
+///     }
+/// }
+///
+/// register_op("mylib.square_plus", SquarePlus);
+/// let x = Tensor::from_vec(vec![1.0, 2.0], &[2]).requires_grad(true);
+/// let y = call_op("mylib.square_plus", &[x.clone()]);
+/// assert_eq!(y.to_vec(), vec![2.0, 6.0]);
+/// y.sum().backward();
+/// assert_eq!(x.grad().unwrap().to_vec(), vec![3.0, 5.0]);
+/// ```
+pub fn register_op(name: &str, op: impl Function + Send + Sync + 'static) {
+    let mut registry = REGISTRY.write().unwrap_or_else(|e| e.into_inner());
+    assert!(
+        !registry.contains_key(name),
+        "an op is already registered as {:?}",
+        name
+    );
+    let name: &'static str = Box::leak(name.into());
+    registry.insert(name, Arc::new(op));
+}
+
+/// Run the op registered as `name` on `inputs`, recording it for autograd
+/// like [`Function::apply`].
+///
+/// # Panics
+/// Panics if no op is registered under `name`.
+pub fn call_op(name: &str, inputs: &[Tensor]) -> Tensor {
+    let (name, op) = {
+        let registry = REGISTRY.read().unwrap_or_else(|e| e.into_inner());
+        let (&name, op) = registry
+            .get_key_value(name)
+            .unwrap_or_else(|| panic!("no op is registered as {:?}", name));
+        (name, Arc::clone(op))
+    };
+    Registered { name, op }.apply(inputs)
+}
+
+/// Whether an op is registered under `name`.
+pub fn is_op_registered(name: &str) -> bool {
+    let registry = REGISTRY.read().unwrap_or_else(|e| e.into_inner());
+    registry.contains_key(name)
+}
+
+/// The names of all registered ops, sorted.
+pub fn registered_ops() -> Vec<&'static str> {
+    let registry = REGISTRY.read().unwrap_or_else(|e| e.into_inner());
+    let mut names: Vec<&'static str> = registry.keys().copied().collect();
+    names.sort_unstable();
+    names
+}
+
+/// A registered op, applied under its registered name.
+struct Registered {
+    name: &'static str,
+    op: Op,
+}
+
+impl Function for Registered {
+    fn forward(&self, inputs: &[Tensor]) -> Tensor {
+        self.op.forward(inputs)
+    }
+
+    fn backward(&self, inputs: &[Tensor], output: &Tensor, grad: &Tensor) -> Vec<Tensor> {
+        self.op.backward(inputs, output, grad)
+    }
+
+    fn name(&self) -> &'static str {
+        self.name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+    use crate::autograd::testing::check_grad;
+
+    /// Scale by a constant fixed at registration.
+    struct Scale(f32);
+
+    impl Function for Scale {
+        fn forward(&self, inputs: &[Tensor]) -> Tensor {
+            inputs[0].scalar_mul(self.0)
+        }
+
+        fn backward(&self, _inputs: &[Tensor], _output: &Tensor, grad: &Tensor) -> Vec<Tensor> {
+            vec![grad.scalar_mul(self.0)]
+        }
+    }
+
+    #[test]
+    fn test_register_and_call() {
+        register_op("test.triple", Scale(3.0));
+        assert!(is_op_registered("test.triple"));
+        assert!(registered_ops().contains(&"test.triple"));
+
+        let x = Tensor::from_vec(vec![1.0, -2.0], &[2]).requires_grad(true);
+        let y = call_op("test.triple", std::slice::from_ref(&x));
+        assert_eq!(y.node().unwrap().op(), "test.triple");
+        check_grad(|t| call_op("test.triple", t), &[x]);
+    }
+
+    #[test]
+    fn test_shared_across_threads() {
+        thread::spawn(|| register_op("test.halve", Scale(0.5)))
+            .join()
+            .unwrap();
+        let y = call_op("test.halve", &[Tensor::from_vec(vec![4.0], &[1])]);
+        assert_eq!(y.to_vec(), vec![2.0]);
+    }
+
+    #[test]
+    #[should_panic(expected = "already registered as \"test.twice\"")]
+    fn test_duplicate_name() {
+        register_op("test.twice", Scale(2.0));
+        register_op("test.twice", Scale(2.0));
+    }
+
+    #[test]
+    #[should_panic(expected = "no op is registered as \"test.missing\"")]
+    fn test_unknown_op() {
+        call_op("test.missing", &[]);
+    }
+}
@@ -0,0 +1,56 @@
+//! Helpers for testing backward rules and numerical results.
+
+use crate::autograd::gradcheck;
+use crate::tensor::Tensor;
+
+/// Assert [`gradcheck`] passes for `f` at `inputs`, with the `eps` and
+/// `tol` that suit the crate's smooth ops.
+pub(crate) fn check_grad(f: impl Fn(&[Tensor]) -> Tensor, inputs: &[Tensor]) {
+    compare_grads(None, f, inputs);
+}
+
+/// [`check_grad`] for one of several ops checked in a loop, naming `op`
+/// in the failure message.
+pub(crate) fn check_named_grad(op: &str, f: impl Fn(&[Tensor]) -> Tensor, inputs: &[Tensor]) {
+    compare_grads(Some(op), f, inputs);
+}
+
+fn compare_grads(op: Option<&str>, f: impl Fn(&[Tensor]) -> Tensor, inputs: &[Tensor]) {
+    let report = gradcheck(f, inputs, 1e-3, 1e-2);
+    assert!(
+        report.passed(),
+        "{}{}",
+        op.map_or_else(String::new, |op| format!("{}: ", op)),
+        report
+    );
+}
+
+/// Assert `actual` and `expected` have the same length and differ by at
+/// most `tol` at every element.
+pub(crate) fn assert_close(actual: &[f32], expected: &[f32], tol: f32) {
+    assert_eq!(
+        actual.len(),
+        expected.len(),
+        "{:?} and {:?} have different lengths",
+        actual,
+        expected
+    );
+    for (i, (a, e)) in actual.iter().zip(expected).enumerate() {
+        assert!(
+            (a - e).abs() <= tol,
+            "element {}: {} vs {} (tolerance {}) in {:?} vs {:?}",
+            i,
+            a,
+            e,
+            tol,
+            actual,
+            expected
+        );
+    }
+}
+
+/// [`assert_close`] for tensors, which must also have the same shape.
+pub(crate) fn assert_tensors_close(actual: &Tensor, expected: &Tensor, tol: f32) {
+    assert_eq!(actual.shape(), expected.shape());
+    assert_close(&actual.to_vec(), &expected.to_vec(), tol);
+}
@@ -0,0 +1,123 @@
+use std::any::Any;
+
+use crate::backend::{Backend, DeviceBuffer, Kernel};
+use crate::tensor::matmul;
+
+/// Host memory, as used by [`Cpu`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct CpuBuffer(pub Vec<f32>);
+
+impl DeviceBuffer for CpuBuffer {
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// The reference backend: buffers are plain vectors and kernels are the
+/// ones tensors use themselves.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Cpu;
+
+fn host(buf: &dyn DeviceBuffer) -> &[f32] {
+    &buf.as_any()
+        .downcast_ref::<CpuBuffer>()
+        .expect("cpu backend given a buffer from another backend")
+        .0
+}
+
+fn host_mut(buf: &mut dyn DeviceBuffer) -> &mut [f32] {
+    &mut buf
+        .as_any_mut()
+        .downcast_mut::<CpuBuffer>()
+        .expect("cpu backend given a buffer from another backend")
+        .0
+}
+
+impl Backend for Cpu {
+    fn name(&self) -> &'static str {
+        "cpu"
+    }
+
+    fn alloc(&self, len: usize) -> Box<dyn DeviceBuffer> {
+        Box::new(CpuBuffer(vec![0.0; len]))
+    }
+
+    fn copy_from_host(&self, src: &[f32], dst: &mut dyn DeviceBuffer) {
+        host_mut(dst).copy_from_slice(src);
+    }
+
+    fn copy_to_host(&self, src: &dyn DeviceBuffer, dst: &mut [f32]) {
+        dst.copy_from_slice(host(src));
+    }
+
+    fn supports(&self, _kernel: &Kernel) -> bool {
+        true
+    }
+
+    fn run_kernel(
+        &self,
+        kernel: &Kernel,
+        inputs: &[&dyn DeviceBuffer],
+        output: &mut dyn DeviceBuffer,
+    ) {
+        let (a, b) = (host(inputs[0]), host(inputs[1]));
+        let out = host_mut(output);
+        let f: fn(f32, f32) -> f32 = match *kernel {
+            Kernel::Matmul { m, k, n } => {
+                out.copy_from_slice(&matmul::matmul(a, b, m, k, n));
+                return;
+            }
+            Kernel::Add => |x, y| x + y,
+            Kernel::Sub => |x, y| x - y,
+            Kernel::Mul => |x, y| x * y,
+            Kernel::Div => |x, y| x / y,
+        };
+        for ((o, &x), &y) in out.iter_mut().zip(a).zip(b) {
+            *o = f(x, y);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::run;
+
+    #[test]
+    fn test_kernels() {
+        let (a, b) = ([1.0, 2.0, 3.0, 4.0], [2.0, 4.0, 1.0, 8.0]);
+        assert_eq!(
+            run(&Cpu, &Kernel::Add, &[&a, &b]),
+            vec![3.0, 6.0, 4.0, 12.0]
+        );
+        assert_eq!(
+            run(&Cpu, &Kernel::Sub, &[&a, &b]),
+            vec![-1.0, -2.0, 2.0, -4.0]
+        );
+        assert_eq!(
+            run(&Cpu, &Kernel::Mul, &[&a, &b]),
+            vec![2.0, 8.0, 3.0, 32.0]
+        );
+        assert_eq!(run(&Cpu, &Kernel::Div, &[&a, &b]), vec![0.5, 0.5, 3.0, 0.5]);
+        let c = run(&Cpu, &Kernel::Matmul { m: 2, k: 2, n: 2 }, &[&a, &b]);
+        assert_eq!(c, vec![4.0, 20.0, 10.0, 44.0]);
+    }
+
+    #[test]
+    fn test_copies_round_trip() {
+        let mut buf = Cpu.alloc(3);
+        assert_eq!(buf.len(), 3);
+        Cpu.copy_from_host(&[1.0, 2.0, 3.0], buf.as_mut());
+        let mut back = [0.0; 3];
+        Cpu.copy_to_host(buf.as_ref(), &mut back);
+        assert_eq!(back, [1.0, 2.0, 3.0]);
+    }
+}
@@ -0,0 +1,161 @@
+//! Compute backends: where buffers live and kernels run.
+//!
+//! A [`Backend`] allocates device buffers, copies data between them and the
+//! host, and runs the [`Kernel`]s it supports. Backends are registered by
+//! name in a global, thread-safe registry, so a crate outside delta can
+//! provide one (a GPU through Metal or ROCm, a BLAS binding) with
+//! [`register_backend`] and code anywhere can look it up with
+//! [`get_backend`]. The reference [`Cpu`] backend is always registered, as
+//! `"cpu"`.
+//!
+//! Tensors themselves still live in host memory; [`run`] is the bridge,
+//! moving host data through a backend for one kernel.
+//!
+//! New backends are gated by [`verify`], which runs each supported kernel
+//! on random inputs against a naive reference and reports the largest
+//! difference in ULPs.
+
+mod cpu;
+mod registry;
+mod verify;
+
+pub use cpu::{Cpu, CpuBuffer};
+pub use registry::{backend_names, get_backend, register_backend};
+pub use verify::{KernelCheck, VerificationReport, ulp_distance, verify};
+
+use std::any::Any;
+
+/// A kernel a backend may run, on contiguous row-major f32 buffers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Kernel {
+    /// Element-wise `a + b` of two buffers of the output's length.
+    Add,
+    /// Element-wise `a - b`.
+    Sub,
+    /// Element-wise `a * b`.
+    Mul,
+    /// Element-wise `a / b`.
+    Div,
+    /// `a` `[m, k]` times `b` `[k, n]` into `[m, n]`.
+    Matmul { m: usize, k: usize, n: usize },
+}
+
+impl Kernel {
+    /// Lengths of the input buffers and of the output buffer the kernel
+    /// expects, given the output length for element-wise kernels.
+    fn lengths(&self, len: usize) -> (Vec<usize>, usize) {
+        match *self {
+            Kernel::Matmul { m, k, n } => (vec![m * k, k * n], m * n),
+            _ => (vec![len, len], len),
+        }
+    }
+}
+
+/// Memory owned by a backend. Backends downcast their own buffers through
+/// [`DeviceBuffer::as_any`].
+pub trait DeviceBuffer: Any {
+    /// Number of f32 elements.
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn as_any(&self) -> &dyn Any;
+
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+/// A device that can hold f32 buffers and run kernels on them.
+///
+/// Every method receives only buffers this backend allocated; an
+/// implementation may panic otherwise.
+pub trait Backend: Send + Sync {
+    /// The name the backend is registered under, e.g. `"cpu"`.
+    fn name(&self) -> &'static str;
+
+    /// A zero-filled buffer of `len` elements.
+    fn alloc(&self, len: usize) -> Box<dyn DeviceBuffer>;
+
+    /// Copy `src` from host memory into `dst`, of the same length.
+    fn copy_from_host(&self, src: &[f32], dst: &mut dyn DeviceBuffer);
+
+    /// Copy `src` into host memory at `dst`, of the same length.
+    fn copy_to_host(&self, src: &dyn DeviceBuffer, dst: &mut [f32]);
+
+    /// Whether [`Backend::run_kernel`] accepts `kernel`.
+    fn supports(&self, kernel: &Kernel) -> bool;
+
+    /// Run `kernel` on `inputs`, writing into `output`. Buffer lengths
+    /// have been checked against the kernel.
+    fn run_kernel(
+        &self,
+        kernel: &Kernel,
+        inputs: &[&dyn DeviceBuffer],
+        output: &mut dyn DeviceBuffer,
+    );
+}
+
+/// Run `kernel` on host data through `backend`: copy the inputs in, run,
+/// and copy the result back. For element-wise kernels the inputs must
+/// have the same length, which is the output's.
+///
+/// # Panics
+/// - Panics if the backend does not support the kernel
+/// - Panics if the inputs do not have the lengths the kernel expects
+///
+/// # Example
+/// ```
+/// use delta::backend::{Kernel, get_backend, run};
+/// let cpu = get_backend("cpu").unwrap();
+/// let c = run(cpu.as_ref(), &Kernel::Matmul { m: 1, k: 2, n: 1 }, &[&[1.0, 2.0], &[3.0, 4.0]]);
+/// assert_eq!(c, vec![11.0]);
+/// ```
+pub fn run(backend: &dyn Backend, kernel: &Kernel, inputs: &[&[f32]]) -> Vec<f32> {
+    assert!(
+        backend.supports(kernel),
+        "backend {} does not support {:?}",
+        backend.name(),
+        kernel
+    );
+    let (lens, out_len) = kernel.lengths(inputs.first().map_or(0, |x| x.len()));
+    let got: Vec<usize> = inputs.iter().map(|x| x.len()).collect();
+    assert_eq!(
+        got, lens,
+        "{:?} expects inputs of lengths {:?}, got {:?}",
+        kernel, lens, got
+    );
+
+    let buffers: Vec<Box<dyn DeviceBuffer>> = inputs
+        .iter()
+        .map(|x| {
+            let mut buf = backend.alloc(x.len());
+            backend.copy_from_host(x, buf.as_mut());
+            buf
+        })
+        .collect();
+    let refs: Vec<&dyn DeviceBuffer> = buffers.iter().map(|b| b.as_ref()).collect();
+    let mut output = backend.alloc(out_len);
+    backend.run_kernel(kernel, &refs, output.as_mut());
+
+    let mut result = vec![0.0; out_len];
+    backend.copy_to_host(output.as_ref(), &mut result);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[should_panic(expected = "expects inputs of lengths [6, 6]")]
+    fn test_run_checks_lengths() {
+        let cpu = get_backend("cpu").unwrap();
+        run(
+            cpu.as_ref(),
+            &Kernel::Matmul { m: 2, k: 3, n: 2 },
+            &[&[0.0; 6], &[0.0; 4]],
+        );
+    }
+}
@@ -0,0 +1,116 @@
+use std::collections::HashMap;
+use std::sync::{Arc, LazyLock, RwLock};
+
+use crate::backend::{Backend, Cpu};
+
+static BACKENDS: LazyLock<RwLock<HashMap<&'static str, Arc<dyn Backend>>>> = LazyLock::new(|| {
+    let cpu: Arc<dyn Backend> = Arc::new(Cpu);
+    RwLock::new(HashMap::from([(cpu.name(), cpu)]))
+});
+
+/// Make `backend` available to the whole program under its
+/// [`Backend::name`].
+///
+/// # Panics
+/// Panics if a backend is already registered under that name.
+pub fn register_backend(backend: impl Backend + 'static) {
+    let mut backends = BACKENDS.write().unwrap_or_else(|e| e.into_inner());
+    let name = backend.name();
+    assert!(
+        !backends.contains_key(name),
+        "a backend is already registered as {:?}",
+        name
+    );
+    backends.insert(name, Arc::new(backend));
+}
+
+/// The backend registered as `name`, if any.
+pub fn get_backend(name: &str) -> Option<Arc<dyn Backend>> {
+    let backends = BACKENDS.read().unwrap_or_else(|e| e.into_inner());
+    backends.get(name).cloned()
+}
+
+/// The names of all registered backends, sorted.
+pub fn backend_names() -> Vec<&'static str> {
+    let backends = BACKENDS.read().unwrap_or_else(|e| e.into_inner());
+    let mut names: Vec<&'static str> = backends.keys().copied().collect();
+    names.sort_unstable();
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread;
+
+    use super::*;
+    use crate::backend::{CpuBuffer, DeviceBuffer, Kernel, run};
+
+    /// Delegates to the CPU, only supports addition and counts its calls.
+    struct Counting(&'static str, AtomicUsize);
+
+    impl Backend for Counting {
+        fn name(&self) -> &'static str {
+            self.0
+        }
+
+        fn alloc(&self, len: usize) -> Box<dyn DeviceBuffer> {
+            Box::new(CpuBuffer(vec![0.0; len]))
+        }
+
+        fn copy_from_host(&self, src: &[f32], dst: &mut dyn DeviceBuffer) {
+            Cpu.copy_from_host(src, dst)
+        }
+
+        fn copy_to_host(&self, src: &dyn DeviceBuffer, dst: &mut [f32]) {
+            Cpu.copy_to_host(src, dst)
+        }
+
+        fn supports(&self, kernel: &Kernel) -> bool {
+            *kernel == Kernel::Add
+        }
+
+        fn run_kernel(
+            &self,
+            kernel: &Kernel,
+            inputs: &[&dyn DeviceBuffer],
+            output: &mut dyn DeviceBuffer,
+        ) {
+            self.1.fetch_add(1, Ordering::Relaxed);
+            Cpu.run_kernel(kernel, inputs, output)
+        }
+    }
+
+    #[test]
+    fn test_cpu_is_registered() {
+        assert!(backend_names().contains(&"cpu"));
+        assert_eq!(get_backend("cpu").unwrap().name(), "cpu");
+        assert!(get_backend("test.missing").is_none());
+    }
+
+    #[test]
+    fn test_register_from_another_thread() {
+        thread::spawn(|| register_backend(Counting("test.counting", AtomicUsize::new(0))))
+            .join()
+            .unwrap();
+        let backend = get_backend("test.counting").unwrap();
+        assert_eq!(
+            run(backend.as_ref(), &Kernel::Add, &[&[1.0], &[2.0]]),
+            vec![3.0]
+        );
+        assert!(!backend.supports(&Kernel::Mul));
+    }
+
+    #[test]
+    #[should_panic(expected = "already registered as \"cpu\"")]
+    fn test_duplicate_name() {
+        register_backend(Cpu);
+    }
+
+    #[test]
+    #[should_panic(expected = "does not support")]
+    fn test_unsupported_kernel() {
+        let backend = Counting("test.unregistered", AtomicUsize::new(0));
+        run(&backend, &Kernel::Mul, &[&[1.0], &[2.0]]);
+    }
+}
@@ -0,0 +1,342 @@
+use std::fmt;
+
+use crate::backend::{Backend, Kernel, run};
+use crate::random::Rng;
+use crate::tensor::Tensor;
+
+/// Lengths the element-wise kernels are checked at: scalars, odd tails
+/// and sizes past any plausible vector width.
+const ELEMENTWISE_LENS: [usize; 4] = [1, 7, 64, 1000];
+
+/// Matmul sizes covering each in-tree kernel (unrolled, general, blocked)
+/// and the ragged edges in between.
+const MATMUL_SHAPES: [(usize, usize, usize); 6] = [
+    (1, 1, 1),
+    (3, 5, 2),
+    (8, 8, 8),
+    (9, 3, 2),
+    (17, 33, 9),
+    (130, 129, 131),
+];
+
+/// Distance between two floats in units in the last place: the number of
+/// representable f32 values between them.
+///
+/// `0.0` and `-0.0` are 0 apart, as are two NaNs; a NaN and a number are
+/// `u32::MAX` apart.
+///
+/// # Example
+/// ```
+/// use delta::backend::ulp_distance;
+/// assert_eq!(ulp_distance(1.0, 1.0 + f32::EPSILON), 1);
+/// assert_eq!(ulp_distance(-f32::MIN_POSITIVE, f32::MIN_POSITIVE), 2 << 23);
+/// ```
+pub fn ulp_distance(a: f32, b: f32) -> u32 {
+    if a.is_nan() || b.is_nan() {
+        return if a.is_nan() && b.is_nan() {
+            0
+        } else {
+            u32::MAX
+        };
+    }
+    // Reorder the sign-magnitude bits so that consecutive floats are
+    // consecutive integers across zero
+    let ordered = |x: f32| {
+        let bits = x.to_bits() as i32 as i64;
+        if bits < 0 {
+            i32::MIN as i64 - bits
+        } else {
+            bits
+        }
+    };
+    (ordered(a) - ordered(b))
+        .unsigned_abs()
+        .min(u32::MAX as u64) as u32
+}
+
+/// How far one kernel's output strayed from the reference.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KernelCheck {
+    pub kernel: Kernel,
+    /// Number of elements of the first input.
+    pub len: usize,
+    /// The largest [`ulp_distance`] over all output elements.
+    pub max_ulp: u32,
+    /// Output element where `max_ulp` was reached.
+    pub index: usize,
+    pub expected: f32,
+    pub got: f32,
+}
+
+/// The result of [`verify`]: one [`KernelCheck`] per case the backend
+/// supports.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VerificationReport {
+    pub backend: &'static str,
+    pub checks: Vec<KernelCheck>,
+    /// Cases skipped because the backend does not support the kernel.
+    pub skipped: Vec<Kernel>,
+}
+
+impl VerificationReport {
+    /// The largest ULP difference over every check (0 if none ran).
+    pub fn max_ulp(&self) -> u32 {
+        self.checks.iter().map(|c| c.max_ulp).max().unwrap_or(0)
+    }
+
+    /// Whether every check stayed within `tolerance` ULPs.
+    pub fn passed(&self, tolerance: u32) -> bool {
+        self.max_ulp() <= tolerance
+    }
+}
+
+impl fmt::Display for VerificationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "backend {}: {} checks, max {} ulp",
+            self.backend,
+            self.checks.len(),
+            self.max_ulp()
+        )?;
+        for c in &self.checks {
+            write!(
+                f,
+                "  {:?} ({} elements): {} ulp",
+                c.kernel, c.len, c.max_ulp
+            )?;
+            if c.max_ulp > 0 {
+                write!(
+                    f,
+                    " at {} (expected {}, got {})",
+                    c.index, c.expected, c.got
+                )?;
+            }
+            writeln!(f)?;
+        }
+        for kernel in &self.skipped {
+            writeln!(f, "  {:?}: not supported", kernel)?;
+        }
+        Ok(())
+    }
+}
+
+/// Naive row-major `[m, k] @ [k, n]`, each output a dot product summed in
+/// order of `k`.
+fn reference_matmul(a: &[f32], b: &[f32], m: usize, k: usize, n: usize) -> Vec<f32> {
+    let mut out = vec![0.0; m * n];
+    for i in 0..m {
+        for j in 0..n {
+            let mut acc = 0.0;
+            for p in 0..k {
+                acc += a[i * k + p] * b[p * n + j];
+            }
+            out[i * n + j] = acc;
+        }
+    }
+    out
+}
+
+fn reference(kernel: &Kernel, a: &[f32], b: &[f32]) -> Vec<f32> {
+    let f: fn(f32, f32) -> f32 = match *kernel {
+        Kernel::Matmul { m, k, n } => return reference_matmul(a, b, m, k, n),
+        Kernel::Add => |x, y| x + y,
+        Kernel::Sub => |x, y| x - y,
+        Kernel::Mul => |x, y| x * y,
+        Kernel::Div => |x, y| x / y,
+    };
+    a.iter().zip(b).map(|(&x, &y)| f(x, y)).collect()
+}
+
+/// Random `rows x cols` matrix, drawn as the transpose of a random
+/// `cols x rows` tensor and gathered into row-major order, so the host
+/// data comes from a strided view.
+fn strided(rows: usize, cols: usize, rng: &mut Rng) -> Vec<f32> {
+    Tensor::randn(&[cols, rows], rng).t().to_vec()
+}
+
+/// Run every kernel `backend` supports on random inputs and compare it
+/// with a naive reference implementation, element by element, in ULPs.
+///
+/// This is the gate for new backends: kernels that reorder arithmetic
+/// (SIMD, threads, a GPU) are expected to differ by a few ULPs, a wrong
+/// kernel by far more. A backend crate would assert in its tests that
+/// `verify(&backend, seed).passed(tolerance)` for a tolerance it can
+/// justify, printing the report when it fails. The inputs are seeded by
+/// `seed`, so a failure is reproducible.
+///
+/// # Example
+/// ```
+/// use delta::backend::{Cpu, verify};
+/// let report = verify(&Cpu, 0);
+/// // The reference backend runs the very loops the reference does
+/// assert!(report.passed(0), "{}", report);
+/// ```
+pub fn verify(backend: &dyn Backend, seed: u64) -> VerificationReport {
+    let mut rng = Rng::new(seed);
+    let mut cases: Vec<(Kernel, Vec<f32>, Vec<f32>)> = Vec::new();
+    for kernel in [Kernel::Add, Kernel::Sub, Kernel::Mul, Kernel::Div] {
+        for len in ELEMENTWISE_LENS {
+            let a = strided(len, 1, &mut rng);
+            let mut b = strided(1, len, &mut rng);
+            if kernel == Kernel::Div {
+                // Away from zero, where any rounding difference explodes
+                for x in &mut b {
+                    *x = x.signum() * (x.abs() + 0.5);
+                }
+            }
+            cases.push((kernel, a, b));
+        }
+    }
+    for (m, k, n) in MATMUL_SHAPES {
+        let (a, b) = (strided(m, k, &mut rng), strided(k, n, &mut rng));
+        cases.push((Kernel::Matmul { m, k, n }, a, b));
+    }
+
+    let mut checks = Vec::new();
+    let mut skipped = Vec::new();
+    for (kernel, a, b) in cases {
+        if !backend.supports(&kernel) {
+            if !skipped.contains(&kernel) {
+                skipped.push(kernel);
+            }
+            continue;
+        }
+        let expected = reference(&kernel, &a, &b);
+        let got = run(backend, &kernel, &[&a, &b]);
+        let (index, max_ulp) = expected
+            .iter()
+            .zip(&got)
+            .map(|(&e, &g)| ulp_distance(e, g))
+            .enumerate()
+            .fold(
+                (0, 0),
+                |worst, (i, d)| if d > worst.1 { (i, d) } else { worst },
+            );
+        checks.push(KernelCheck {
+            kernel,
+            len: a.len(),
+            max_ulp,
+            index,
+            expected: expected[index],
+            got: got[index],
+        });
+    }
+    VerificationReport {
+        backend: backend.name(),
+        checks,
+        skipped,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::{Cpu, DeviceBuffer};
+
+    #[test]
+    fn test_ulp_distance() {
+        assert_eq!(ulp_distance(1.5, 1.5), 0);
+        assert_eq!(ulp_distance(0.0, -0.0), 0);
+        assert_eq!(
+            ulp_distance(f32::from_bits(1), f32::from_bits(1 << 31 | 1)),
+            2
+        );
+        assert_eq!(ulp_distance(f32::NAN, f32::NAN), 0);
+        assert_eq!(ulp_distance(f32::NAN, 0.0), u32::MAX);
+        assert_eq!(ulp_distance(f32::MAX, f32::INFINITY), 1);
+    }
+
+    #[test]
+    fn test_cpu_matches_reference() {
+        let report = verify(&Cpu, 7);
+        assert_eq!(report.backend, "cpu");
+        assert_eq!(
+            report.checks.len(),
+            4 * ELEMENTWISE_LENS.len() + MATMUL_SHAPES.len()
+        );
+        assert!(report.skipped.is_empty());
+        assert_eq!(report.max_ulp(), 0, "{}", report);
+    }
+
+    /// Sums matmul dot products back to front, and has no division.
+    struct Reordered;
+
+    impl Backend for Reordered {
+        fn name(&self) -> &'static str {
+            "reordered"
+        }
+
+        fn alloc(&self, len: usize) -> Box<dyn DeviceBuffer> {
+            Cpu.alloc(len)
+        }
+
+        fn copy_from_host(&self, src: &[f32], dst: &mut dyn DeviceBuffer) {
+            Cpu.copy_from_host(src, dst)
+        }
+
+        fn copy_to_host(&self, src: &dyn DeviceBuffer, dst: &mut [f32]) {
+            Cpu.copy_to_host(src, dst)
+        }
+
+        fn supports(&self, kernel: &Kernel) -> bool {
+            *kernel != Kernel::Div
+        }
+
+        fn run_kernel(
+            &self,
+            kernel: &Kernel,
+            inputs: &[&dyn DeviceBuffer],
+            output: &mut dyn DeviceBuffer,
+        ) {
+            let Kernel::Matmul { m, k, n } = *kernel else {
+                return Cpu.run_kernel(kernel, inputs, output);
+            };
+            let mut a = vec![0.0; m * k];
+            let mut b = vec![0.0; k * n];
+            Cpu.copy_to_host(inputs[0], &mut a);
+            Cpu.copy_to_host(inputs[1], &mut b);
+            let mut out = vec![0.0; m * n];
+            for i in 0..m {
+                for j in 0..n {
+                    out[i * n + j] = (0..k).rev().map(|p| a[i * k + p] * b[p * n + j]).sum();
+                }
+            }
+            Cpu.copy_from_host(&out, output);
+        }
+    }
+
+    #[test]
+    fn test_reports_reordered_sums() {
+        let report = verify(&Reordered, 7);
+        assert_eq!(report.skipped, vec![Kernel::Div]);
+        let elementwise = report
+            .checks
+            .iter()
+            .filter(|c| !matches!(c.kernel, Kernel::Matmul { .. }));
+        assert!(elementwise.clone().all(|c| c.max_ulp == 0));
+        assert_eq!(elementwise.count(), 3 * ELEMENTWISE_LENS.len());
+        // Summation order changes the rounding of long dot products
+        assert!(report.max_ulp() > 0);
+        assert!(!report.passed(0));
+        let text = report.to_string();
+        assert!(text.starts_with("backend reordered: "), "{}", text);
+        assert!(text.contains("Div: not supported"));
+    }
+
+    #[test]
+    fn test_tensor_kernels_on_strided_inputs() {
+        // The tensor front end gathers strided views before dispatching
+        let mut rng = Rng::new(3);
+        for (m, k, n) in MATMUL_SHAPES {
+            let a = Tensor::randn(&[k, m], &mut rng).t();
+            let b = Tensor::randn(&[n, k], &mut rng).t();
+            let expected = reference_matmul(&a.to_vec(), &b.to_vec(), m, k, n);
+            assert_eq!(a.matmul(&b).to_vec(), expected, "[{}, {}, {}]", m, k, n);
+        }
+        let a = Tensor::randn(&[5, 3], &mut rng).t();
+        let b = Tensor::randn(&[3, 5], &mut rng);
+        let expected = reference(&Kernel::Mul, &a.to_vec(), &b.to_vec());
+        assert_eq!(Tensor::mul(&a, &b).to_vec(), expected);
+    }
+}
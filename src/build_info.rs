@@ -0,0 +1,131 @@
+use std::fmt;
+
+use crate::backend::backend_names;
+
+/// What this build of delta can do and which code paths it runs, as
+/// returned by [`build_info`].
+///
+/// Its `Display` form is meant to be pasted into bug reports.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BuildInfo {
+    /// The crate version.
+    pub version: &'static str,
+    /// Cargo features enabled at compile time (`ffi`, `parallel`).
+    pub features: Vec<&'static str>,
+    /// The widest SIMD instruction set the compiler was allowed to target,
+    /// e.g. `"avx2"` or `"neon"`. Kernels are scalar loops that the
+    /// compiler auto-vectorizes within this limit; `None` if no SIMD
+    /// extension beyond the architecture baseline was detected.
+    pub simd: Option<&'static str>,
+    /// Whether a BLAS library backs matrix products. Delta has no
+    /// dependencies, so this is always false.
+    pub blas: bool,
+    /// Registered compute backends (see [`crate::backend`]), `"cpu"`
+    /// always among them.
+    pub backends: Vec<&'static str>,
+    /// Element types tensors can hold.
+    pub dtypes: Vec<&'static str>,
+    /// Hardware threads available to the process.
+    pub threads: usize,
+    /// Target architecture and operating system.
+    pub target: (&'static str, &'static str),
+    /// Whether debug assertions (extra internal checks) are compiled in.
+    pub debug_assertions: bool,
+}
+
+/// Report the features, code paths and resources of this build.
+///
+/// Backends are read from the registry at call time, so ones registered
+/// by other crates are included.
+///
+/// # Example
+/// ```
+/// let info = delta::build_info();
+/// assert_eq!(info.dtypes, vec!["f32"]);
+/// assert!(info.backends.contains(&"cpu"));
+/// println!("{}", info);
+/// ```
+pub fn build_info() -> BuildInfo {
+    let features = [
+        ("ffi", cfg!(feature = "ffi")),
+        ("parallel", cfg!(feature = "parallel")),
+    ]
+    .into_iter()
+    .filter_map(|(name, on)| on.then_some(name))
+    .collect();
+    BuildInfo {
+        version: env!("CARGO_PKG_VERSION"),
+        features,
+        simd: simd_level(),
+        blas: false,
+        backends: backend_names(),
+        dtypes: vec!["f32"],
+        threads: std::thread::available_parallelism().map_or(1, |n| n.get()),
+        target: (std::env::consts::ARCH, std::env::consts::OS),
+        debug_assertions: cfg!(debug_assertions),
+    }
+}
+
+/// The widest enabled SIMD target feature, most capable first.
+fn simd_level() -> Option<&'static str> {
+    [
+        ("avx512f", cfg!(target_feature = "avx512f")),
+        ("avx2", cfg!(target_feature = "avx2")),
+        ("avx", cfg!(target_feature = "avx")),
+        ("sse4.2", cfg!(target_feature = "sse4.2")),
+        ("sse2", cfg!(target_feature = "sse2")),
+        ("neon", cfg!(target_feature = "neon")),
+        ("simd128", cfg!(target_feature = "simd128")),
+    ]
+    .into_iter()
+    .find_map(|(name, on)| on.then_some(name))
+}
+
+impl fmt::Display for BuildInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let list = |items: &[&str]| {
+            if items.is_empty() {
+                "none".to_string()
+            } else {
+                items.join(", ")
+            }
+        };
+        writeln!(f, "delta {}", self.version)?;
+        writeln!(f, "  target:   {}-{}", self.target.0, self.target.1)?;
+        writeln!(f, "  features: {}", list(&self.features))?;
+        writeln!(f, "  simd:     {}", self.simd.unwrap_or("none"))?;
+        writeln!(f, "  blas:     {}", if self.blas { "yes" } else { "no" })?;
+        writeln!(f, "  backends: {}", list(&self.backends))?;
+        writeln!(f, "  dtypes:   {}", list(&self.dtypes))?;
+        writeln!(f, "  threads:  {}", self.threads)?;
+        write!(
+            f,
+            "  debug assertions: {}",
+            if self.debug_assertions { "on" } else { "off" }
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_info() {
+        let info = build_info();
+        assert_eq!(info.version, env!("CARGO_PKG_VERSION"));
+        assert!(info.threads >= 1);
+        assert_eq!(info.target.0, std::env::consts::ARCH);
+        assert_eq!(info.debug_assertions, cfg!(debug_assertions));
+        #[cfg(target_arch = "x86_64")]
+        assert!(info.simd.is_some(), "SSE2 is part of the x86-64 baseline");
+    }
+
+    #[test]
+    fn test_display() {
+        let text = build_info().to_string();
+        assert!(text.starts_with(&format!("delta {}\n", env!("CARGO_PKG_VERSION"))));
+        assert!(text.contains("\n  dtypes:   f32\n"));
+        assert!(text.contains("\n  blas:     no\n"));
+    }
+}
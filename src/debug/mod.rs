@@ -0,0 +1,20 @@
+//! Tools for hunting down where values go wrong.
+//!
+//! - [`watch`] conditions on op outputs, checked after every op in
+//!   [`debug_mode`]: a breakpoint on a condition rather than on a line.
+//! - [`record_trace`] and [`replay`] capture the sequence of ops a
+//!   computation runs, with fingerprints of their inputs and outputs, so a
+//!   numeric bug can be shared as a [`Trace`] file and checked on another
+//!   machine.
+//!
+//! Both hook into op recording, so they see every differentiable op, with
+//! or without a graph; non-differentiable ops (comparisons, masks) are
+//! invisible to them.
+
+mod trace;
+mod watch;
+
+pub(crate) use trace::trace_op;
+pub use trace::{Divergence, Fingerprint, Trace, TraceEvent, record_trace, replay};
+pub(crate) use watch::check_watches;
+pub use watch::{DebugGuard, clear_watches, debug_mode, is_debug_enabled, unwatch, watch, watches};
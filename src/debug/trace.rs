@@ -0,0 +1,358 @@
+//! Op traces: what a computation ran, fingerprinted, for bug reports.
+//!
+//! A trace lists every op in execution order with the shape and a hash of
+//! the exact bits of each input and of the output. It is small enough to
+//! attach to an issue even for large tensors, and [`replay`] reruns the
+//! computation against it and reports the first op whose inputs or output
+//! differ: the point where two machines, two builds or two runs part ways.
+//!
+//! The file format is plain text, one op per line:
+//!
+//! ```text
+//! delta-trace 1
+//! exp [2]:9a1fe8b0c1d2e3f4 <- [2]:0123456789abcdef
+//! ```
+
+use std::cell::RefCell;
+use std::fmt;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::tensor::Tensor;
+
+const HEADER: &str = "delta-trace 1";
+
+thread_local! {
+    /// Events of the innermost [`record_trace`] in progress, if any.
+    static RECORDING: RefCell<Option<Vec<TraceEvent>>> = const { RefCell::new(None) };
+}
+
+/// Shape and FNV-1a hash of the values of a tensor, in logical order.
+///
+/// Two tensors share a fingerprint when they hold bitwise identical
+/// values, so even a difference in the last bit of one element shows.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fingerprint {
+    pub shape: Vec<usize>,
+    pub hash: u64,
+}
+
+impl Fingerprint {
+    pub fn of(tensor: &Tensor) -> Self {
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        for v in tensor.to_vec() {
+            for byte in v.to_bits().to_le_bytes() {
+                hash ^= byte as u64;
+                hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+            }
+        }
+        Self {
+            shape: tensor.shape().to_vec(),
+            hash,
+        }
+    }
+}
+
+impl fmt::Display for Fingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let dims: Vec<String> = self.shape.iter().map(|d| d.to_string()).collect();
+        write!(f, "[{}]:{:016x}", dims.join(","), self.hash)
+    }
+}
+
+impl FromStr for Fingerprint {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        let bad = || format!("malformed fingerprint {:?}", s);
+        let (shape, hash) = s.split_once(':').ok_or_else(bad)?;
+        let dims = shape
+            .strip_prefix('[')
+            .and_then(|s| s.strip_suffix(']'))
+            .ok_or_else(bad)?;
+        let shape = if dims.is_empty() {
+            Vec::new()
+        } else {
+            dims.split(',')
+                .map(|d| d.parse().map_err(|_| bad()))
+                .collect::<Result<_, _>>()?
+        };
+        let hash = u64::from_str_radix(hash, 16).map_err(|_| bad())?;
+        Ok(Self { shape, hash })
+    }
+}
+
+/// One op of a trace.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceEvent {
+    pub op: String,
+    pub inputs: Vec<Fingerprint>,
+    pub output: Fingerprint,
+}
+
+impl fmt::Display for TraceEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} <-", self.op, self.output)?;
+        for input in &self.inputs {
+            write!(f, " {}", input)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for TraceEvent {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        let mut parts = s.split_whitespace();
+        let (Some(op), Some(output), Some("<-")) = (parts.next(), parts.next(), parts.next())
+        else {
+            return Err(format!("malformed trace line {:?}", s));
+        };
+        Ok(Self {
+            op: op.to_string(),
+            output: output.parse()?,
+            inputs: parts.map(str::parse).collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+/// The ops a computation ran, in order. See the [module docs](self).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Trace {
+    pub events: Vec<TraceEvent>,
+}
+
+impl Trace {
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// The first op where `other` departs from this trace, if any.
+    pub fn first_divergence(&self, other: &Trace) -> Option<Divergence> {
+        let n = self.len().max(other.len());
+        (0..n).find_map(|index| {
+            let (expected, actual) = (self.events.get(index), other.events.get(index));
+            (expected != actual).then(|| Divergence {
+                index,
+                expected: expected.cloned(),
+                actual: actual.cloned(),
+            })
+        })
+    }
+
+    /// Write the trace to a file in the text format.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        std::fs::write(path, self.to_string())
+    }
+
+    /// Read a trace written by [`Trace::save`].
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        std::fs::read_to_string(path)?
+            .parse()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
+
+impl fmt::Display for Trace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", HEADER)?;
+        for event in &self.events {
+            writeln!(f, "{}", event)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Trace {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        let mut lines = s.lines();
+        if lines.next() != Some(HEADER) {
+            return Err(format!(
+                "not a trace: expected {:?} on the first line",
+                HEADER
+            ));
+        }
+        let events = lines
+            .filter(|line| !line.trim().is_empty())
+            .map(str::parse)
+            .collect::<Result<_, _>>()?;
+        Ok(Self { events })
+    }
+}
+
+/// Where a replayed computation departed from its trace.
+///
+/// `expected` is `None` if the replay ran more ops than the trace has,
+/// `actual` is `None` if it ran fewer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    pub index: usize,
+    pub expected: Option<TraceEvent>,
+    pub actual: Option<TraceEvent>,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let show = |e: &Option<TraceEvent>| e.as_ref().map_or("nothing".into(), |e| e.to_string());
+        write!(
+            f,
+            "trace diverged at op {}: expected {}, got {}",
+            self.index,
+            show(&self.expected),
+            show(&self.actual)
+        )
+    }
+}
+
+/// Run `f`, recording every op it runs, and return its result along with
+/// the trace.
+///
+/// Fingerprinting reads every input and output, so this is slow. Only the
+/// innermost of nested calls sees the ops. Traces are per thread.
+///
+/// # Example
+/// ```
+/// use delta::debug::{record_trace, replay};
+/// use delta::tensor::Tensor;
+///
+/// let model = || {
+///     let x = Tensor::from_vec(vec![0.5, -1.0, 2.0], &[3]);
+///     x.exp().sub(&x.scalar_mul(3.0)).sum()
+/// };
+/// let (_, trace) = record_trace(model);
+/// assert_eq!(trace.len(), 4);
+/// // Shared as `trace.save(path)`, checked elsewhere with `Trace::load`
+/// assert!(replay(&trace, model).is_ok());
+/// ```
+pub fn record_trace<R>(f: impl FnOnce() -> R) -> (R, Trace) {
+    let prev = RECORDING.with(|r| r.replace(Some(Vec::new())));
+    /// Restores the outer recording even if `f` panics.
+    struct Restore(Option<Option<Vec<TraceEvent>>>);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            if let Some(prev) = self.0.take() {
+                RECORDING.with(|r| r.replace(prev));
+            }
+        }
+    }
+    let mut restore = Restore(Some(prev));
+    let result = f();
+    let events = RECORDING.with(|r| r.replace(restore.0.take().unwrap()));
+    (
+        result,
+        Trace {
+            events: events.unwrap_or_default(),
+        },
+    )
+}
+
+/// Rerun `f` and check it runs exactly the ops of `trace`, on bitwise
+/// identical values.
+///
+/// Returns the result of `f`, or the first op that differs.
+#[allow(clippy::result_large_err)]
+pub fn replay<R>(trace: &Trace, f: impl FnOnce() -> R) -> Result<R, Divergence> {
+    let (result, actual) = record_trace(f);
+    match trace.first_divergence(&actual) {
+        Some(divergence) => Err(divergence),
+        None => Ok(result),
+    }
+}
+
+/// Append `op` to the trace being recorded, if any.
+pub(crate) fn trace_op(op: &str, inputs: &[&Tensor], output: &Tensor) {
+    // Fingerprinting runs no recorded ops, so the borrow is never reentered
+    RECORDING.with(|r| {
+        if let Some(events) = r.borrow_mut().as_mut() {
+            events.push(TraceEvent {
+                op: op.to_string(),
+                inputs: inputs.iter().map(|t| Fingerprint::of(t)).collect(),
+                output: Fingerprint::of(output),
+            });
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn model(scale: f32) -> Tensor {
+        let x = Tensor::from_vec(vec![0.5, -1.0, 2.0, 0.25], &[2, 2]).requires_grad(true);
+        x.matmul(&x).scalar_mul(scale).tanh().sum()
+    }
+
+    #[test]
+    fn test_records_ops_in_order() {
+        let (y, trace) = record_trace(|| model(2.0));
+        let ops: Vec<&str> = trace.events.iter().map(|e| e.op.as_str()).collect();
+        assert_eq!(ops, vec!["matmul", "scalar_mul", "tanh", "sum"]);
+        assert_eq!(trace.events[0].inputs.len(), 2);
+        assert_eq!(trace.events[3].output, Fingerprint::of(&y));
+        assert_eq!(trace.events[3].output.shape, Vec::<usize>::new());
+    }
+
+    #[test]
+    fn test_replay_reports_first_divergence() {
+        let (_, trace) = record_trace(|| model(2.0));
+        assert!(replay(&trace, || model(2.0)).is_ok());
+
+        let divergence = replay(&trace, || model(2.0 + 1e-6)).unwrap_err();
+        // The matmul is untouched; the scale changes the next output
+        assert_eq!(divergence.index, 1);
+        assert_eq!(divergence.actual.unwrap().op, "scalar_mul");
+
+        let longer = replay(&trace, || model(2.0).tanh()).unwrap_err();
+        assert_eq!(longer.index, 4);
+        assert!(longer.expected.is_none());
+    }
+
+    #[test]
+    fn test_text_round_trip() {
+        let (_, trace) = record_trace(|| model(1.0));
+        let text = trace.to_string();
+        assert!(text.starts_with("delta-trace 1\nmatmul [2,2]:"));
+        assert_eq!(text.parse::<Trace>().unwrap(), trace);
+
+        let path = std::env::temp_dir().join(format!("delta-trace-{}.txt", std::process::id()));
+        trace.save(&path).unwrap();
+        let loaded = Trace::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded, trace);
+    }
+
+    #[test]
+    fn test_rejects_malformed_text() {
+        assert!("matmul [2]:00 <-".parse::<Trace>().is_err());
+        let err = "delta-trace 1\nexp [2:00 <-".parse::<Trace>().unwrap_err();
+        assert_eq!(err, "malformed fingerprint \"[2:00\"");
+    }
+
+    #[test]
+    fn test_nothing_recorded_outside() {
+        let (_, outer) = record_trace(|| {
+            let (_, inner) = record_trace(|| model(1.0));
+            assert_eq!(inner.len(), 4);
+            model(1.0).exp()
+        });
+        assert_eq!(outer.len(), 5);
+        assert!(RECORDING.with(|r| r.borrow().is_none()));
+    }
+
+    #[test]
+    fn test_fingerprint_sees_every_bit() {
+        let a = Tensor::from_vec(vec![1.0, 2.0], &[2]);
+        let b = Tensor::from_vec(vec![1.0, f32::from_bits(2f32.to_bits() + 1)], &[2]);
+        assert_ne!(Fingerprint::of(&a), Fingerprint::of(&b));
+        // Same values, different layout
+        assert_ne!(Fingerprint::of(&a), Fingerprint::of(&a.reshape(&[1, 2])));
+    }
+}
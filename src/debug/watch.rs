@@ -0,0 +1,219 @@
+//! A watch is a named predicate on tensors. While debug mode is on (see
+//! [`DebugGuard`] and [`debug_mode`]), every differentiable op checks its
+//! output against each watch right after computing it, and panics at the
+//! first one that fires, naming the watch, the op and the shapes of its
+//! inputs: a breakpoint on a condition rather than on a line.
+//!
+//! ```should_panic
+//! use delta::debug::{debug_mode, watch};
+//! use delta::tensor::Tensor;
+//!
+//! watch("nan", |t| t.has_nan());
+//! let x = Tensor::from_vec(vec![1.0, -1.0], &[2]);
+//! // Panics at the ln, not wherever the NaN would have surfaced later
+//! debug_mode(|| x.scalar_mul(2.0).ln().exp().sum());
+//! ```
+//!
+//! Watches and the mode are per thread. Outside debug mode the watches
+//! stay registered but cost nothing beyond a flag check per op; checking
+//! them runs each predicate on every op output, which is slow.
+
+use std::cell::{Cell, RefCell};
+
+use crate::tensor::Tensor;
+
+type Condition = Box<dyn Fn(&Tensor) -> bool>;
+
+thread_local! {
+    static DEBUG_ENABLED: Cell<bool> = const { Cell::new(false) };
+    /// Set while the watches run, so the ops a condition calls are not
+    /// checked themselves.
+    static CHECKING: Cell<bool> = const { Cell::new(false) };
+    static WATCHES: RefCell<Vec<(String, Condition)>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Whether debug mode is currently on.
+pub fn is_debug_enabled() -> bool {
+    DEBUG_ENABLED.with(Cell::get)
+}
+
+/// Turns debug mode on until dropped.
+///
+/// Guards nest, and the mode is per thread.
+pub struct DebugGuard {
+    prev: bool,
+}
+
+impl DebugGuard {
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        let prev = DEBUG_ENABLED.with(|flag| flag.replace(true));
+        Self { prev }
+    }
+}
+
+impl Drop for DebugGuard {
+    fn drop(&mut self) {
+        DEBUG_ENABLED.with(|flag| flag.set(self.prev));
+    }
+}
+
+/// Run `f` with debug mode on and return its result.
+///
+/// The closure form of [`DebugGuard`].
+pub fn debug_mode<R>(f: impl FnOnce() -> R) -> R {
+    let _guard = DebugGuard::new();
+    f()
+}
+
+/// Register a watch: in debug mode, every op whose output makes
+/// `condition` return true panics, reporting `name`.
+///
+/// Watches are checked in registration order.
+///
+/// # Panics
+/// Panics if a watch named `name` is already registered on this thread.
+pub fn watch(name: &str, condition: impl Fn(&Tensor) -> bool + 'static) {
+    WATCHES.with(|watches| {
+        let mut watches = watches.borrow_mut();
+        assert!(
+            watches.iter().all(|(n, _)| n != name),
+            "a watch is already registered as {:?}",
+            name
+        );
+        watches.push((name.to_string(), Box::new(condition)));
+    });
+}
+
+/// Remove the watch named `name`. Returns whether there was one.
+pub fn unwatch(name: &str) -> bool {
+    WATCHES.with(|watches| {
+        let mut watches = watches.borrow_mut();
+        let before = watches.len();
+        watches.retain(|(n, _)| n != name);
+        watches.len() != before
+    })
+}
+
+/// Remove every watch registered on this thread.
+pub fn clear_watches() {
+    WATCHES.with(|watches| watches.borrow_mut().clear());
+}
+
+/// Names of the watches registered on this thread, in registration order.
+pub fn watches() -> Vec<String> {
+    WATCHES.with(|watches| watches.borrow().iter().map(|(n, _)| n.clone()).collect())
+}
+
+/// Resets [`CHECKING`] even if a condition panics.
+struct CheckingGuard;
+
+impl Drop for CheckingGuard {
+    fn drop(&mut self) {
+        CHECKING.with(|flag| flag.set(false));
+    }
+}
+
+/// Panic if `output`, just computed by `op` from `inputs`, triggers a watch.
+pub(crate) fn check_watches(op: &str, inputs: &[&Tensor], output: &Tensor) {
+    if !is_debug_enabled() || CHECKING.with(|flag| flag.replace(true)) {
+        return;
+    }
+    let triggered = {
+        let _checking = CheckingGuard;
+        WATCHES.with(|watches| {
+            watches
+                .borrow()
+                .iter()
+                .find(|(_, condition)| condition(output))
+                .map(|(name, _)| name.clone())
+        })
+    };
+    if let Some(name) = triggered {
+        let shapes: Vec<&[usize]> = inputs.iter().map(|t| t.shape()).collect();
+        panic!(
+            "watch {:?} triggered by the output of {} (input shapes {:?}, output shape {:?})",
+            name,
+            op,
+            shapes,
+            output.shape()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_triggers_at_first_bad_op() {
+        let result = std::panic::catch_unwind(|| {
+            watch("nan", |t| t.has_nan());
+            let x = Tensor::from_vec(vec![4.0, -1.0], &[2]);
+            debug_mode(|| x.exp().sqrt().sub(&x.scalar_mul(2.0)).ln().sum())
+        });
+        clear_watches();
+        let message = *result.unwrap_err().downcast::<String>().unwrap();
+        assert_eq!(
+            message,
+            "watch \"nan\" triggered by the output of ln (input shapes [[2]], output shape [2])"
+        );
+    }
+
+    #[test]
+    fn test_inactive_outside_debug_mode() {
+        watch("always", |_| true);
+        let x = Tensor::from_vec(vec![1.0], &[1]);
+        assert_eq!(x.exp().to_vec(), vec![1f32.exp()]);
+        assert!(!is_debug_enabled());
+        clear_watches();
+    }
+
+    #[test]
+    fn test_conditions_may_run_ops() {
+        // The ops inside the condition must not check the watches again
+        watch("large", |t| t.abs().sum().get(&[]) > 100.0);
+        let x = Tensor::from_vec(vec![1.0, 2.0], &[2]).requires_grad(true);
+        let y = debug_mode(|| x.scalar_mul(3.0).sum());
+        assert_eq!(y.get(&[]), 9.0);
+        let result = std::panic::catch_unwind(|| {
+            let x = Tensor::from_vec(vec![50.0, 60.0], &[2]);
+            debug_mode(|| x.scalar_mul(1.0))
+        });
+        assert!(result.is_err());
+        // A panic inside the check must not leave it switched off
+        assert!(!CHECKING.with(Cell::get));
+        clear_watches();
+    }
+
+    #[test]
+    fn test_register_and_remove() {
+        watch("a", |t| t.has_nan());
+        watch("b", |t| t.has_inf());
+        assert_eq!(watches(), vec!["a", "b"]);
+        assert!(unwatch("a"));
+        assert!(!unwatch("a"));
+        assert_eq!(watches(), vec!["b"]);
+        clear_watches();
+        assert!(watches().is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "already registered as \"dup\"")]
+    fn test_duplicate_watch() {
+        watch("dup", |_| false);
+        watch("dup", |_| false);
+    }
+
+    #[test]
+    fn test_guards_nest() {
+        {
+            let _outer = DebugGuard::new();
+            {
+                let _inner = DebugGuard::new();
+            }
+            assert!(is_debug_enabled());
+        }
+        assert!(!is_debug_enabled());
+    }
+}
@@ -0,0 +1,168 @@
+use crate::distributions::{Distribution, KlDivergence, check_shape};
+use crate::random::Rng;
+use crate::tensor::Tensor;
+
+/// Element-wise Bernoulli distributions over {0, 1}.
+///
+/// Stored as logits, the log odds ln(p / (1 - p)), so that `log_prob`
+/// stays accurate for probabilities close to 0 or 1; a classifier's raw
+/// outputs can be passed to [`Bernoulli::from_logits`] directly.
+#[derive(Debug, Clone)]
+pub struct Bernoulli {
+    logits: Tensor,
+}
+
+impl Bernoulli {
+    /// From probabilities of drawing 1.
+    ///
+    /// # Panics
+    /// Panics if a probability is outside [0, 1].
+    pub fn new(probs: Tensor) -> Self {
+        assert!(
+            probs.to_vec().iter().all(|p| (0.0..=1.0).contains(p)),
+            "Bernoulli requires probabilities in [0, 1]"
+        );
+        // p = 0 or 1 gives infinite logits; the largest finite ones keep
+        // log_prob free of 0 * inf
+        let logits = probs.ln().sub(&probs.neg().scalar_add(1.0).ln());
+        Self {
+            logits: logits.nan_to_num(0.0, f32::MAX, f32::MIN),
+        }
+    }
+
+    /// From log odds.
+    pub fn from_logits(logits: Tensor) -> Self {
+        Self { logits }
+    }
+
+    pub fn logits(&self) -> &Tensor {
+        &self.logits
+    }
+
+    /// The probabilities of drawing 1.
+    pub fn probs(&self) -> Tensor {
+        self.logits.sigmoid()
+    }
+}
+
+impl Distribution for Bernoulli {
+    fn sample(&self, rng: &mut Rng) -> Tensor {
+        let draws = self
+            .probs()
+            .to_vec()
+            .into_iter()
+            .map(|p| if rng.uniform() < p { 1.0 } else { 0.0 })
+            .collect();
+        Tensor::from_vec(draws, self.logits.shape())
+    }
+
+    /// `x ln p + (1 - x) ln(1 - p)`, computed as `x l - softplus(l)` from
+    /// the logits l.
+    ///
+    /// # Panics
+    /// Panics if `value` is not shaped like the parameters.
+    fn log_prob(&self, value: &Tensor) -> Tensor {
+        check_shape("Bernoulli", self.logits.shape(), value);
+        value.mul(&self.logits).sub(&self.logits.softplus())
+    }
+
+    /// `softplus(l) - p l`
+    fn entropy(&self) -> Tensor {
+        let l = &self.logits;
+        l.softplus().sub(&self.probs().mul(l))
+    }
+}
+
+impl KlDivergence for Bernoulli {
+    /// `p (ln p - ln q) + (1 - p) (ln(1 - p) - ln(1 - q))`
+    ///
+    /// # Panics
+    /// Panics if the two have different shapes.
+    fn kl_divergence(&self, q: &Bernoulli) -> Tensor {
+        assert_eq!(
+            self.logits.shape(),
+            q.logits.shape(),
+            "kl_divergence between Bernoullis of shapes {:?} and {:?}",
+            self.logits.shape(),
+            q.logits.shape()
+        );
+        // ln p = l - softplus(l), ln(1 - p) = -softplus(l)
+        let (lp, lq) = (&self.logits, &q.logits);
+        let (sp, sq) = (lp.softplus(), lq.softplus());
+        let p = self.probs();
+        let ones = p.neg().scalar_add(1.0);
+        p.mul(&lp.sub(&sp).sub(&lq.sub(&sq)))
+            .add(&ones.mul(&sq.sub(&sp)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::autograd::testing::check_grad;
+
+    fn bernoulli(probs: &[f32]) -> Bernoulli {
+        Bernoulli::new(Tensor::from_vec(probs.to_vec(), &[probs.len()]))
+    }
+
+    #[test]
+    fn test_log_prob() {
+        let d = bernoulli(&[0.2, 0.9]);
+        let lp = d.log_prob(&Tensor::from_vec(vec![1.0, 0.0], &[2])).to_vec();
+        assert!((lp[0] - 0.2f32.ln()).abs() < 1e-6);
+        assert!((lp[1] - 0.1f32.ln()).abs() < 1e-5);
+
+        // Extreme logits stay finite where ln(sigmoid(l)) would not
+        let confident = Bernoulli::from_logits(Tensor::from_vec(vec![-200.0], &[1]));
+        let lp = confident.log_prob(&Tensor::from_vec(vec![1.0], &[1]));
+        assert_eq!(lp.to_vec(), vec![-200.0]);
+    }
+
+    #[test]
+    fn test_entropy_and_kl() {
+        let p = bernoulli(&[0.5, 0.1]);
+        let h = p.entropy().to_vec();
+        assert!((h[0] - std::f32::consts::LN_2).abs() < 1e-6);
+        let expected = -(0.1f32 * 0.1f32.ln() + 0.9 * 0.9f32.ln());
+        assert!((h[1] - expected).abs() < 1e-6);
+
+        let q = bernoulli(&[0.25, 0.1]);
+        let kl = p.kl_divergence(&q).to_vec();
+        let expected = 0.5 * (0.5f32 / 0.25).ln() + 0.5 * (0.5f32 / 0.75).ln();
+        assert!((kl[0] - expected).abs() < 1e-6);
+        assert!(kl[1].abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_sample() {
+        let d = bernoulli(&[0.0, 0.3, 1.0]);
+        let mut rng = Rng::new(5);
+        let mut ones = [0.0; 3];
+        for _ in 0..5000 {
+            let s = d.sample(&mut rng).to_vec();
+            ones.iter_mut().zip(s).for_each(|(c, x)| *c += x);
+        }
+        assert_eq!(ones[0], 0.0);
+        assert!((ones[1] / 5000.0 - 0.3).abs() < 0.02);
+        assert_eq!(ones[2], 5000.0);
+    }
+
+    #[test]
+    fn test_gradients() {
+        let x = Tensor::from_vec(vec![1.0, 0.0, 1.0], &[3]);
+        let logits = Tensor::from_vec(vec![-1.0, 0.5, 2.0], &[3]);
+        check_grad(
+            |t| Bernoulli::from_logits(t[0].clone()).log_prob(&x),
+            std::slice::from_ref(&logits),
+        );
+        check_grad(
+            |t| Bernoulli::from_logits(t[0].clone()).entropy(),
+            std::slice::from_ref(&logits),
+        );
+        let q = Bernoulli::from_logits(Tensor::from_vec(vec![0.3, -0.2, 1.0], &[3]));
+        check_grad(
+            |t| Bernoulli::from_logits(t[0].clone()).kl_divergence(&q),
+            &[logits],
+        );
+    }
+}
@@ -0,0 +1,229 @@
+use crate::distributions::{Distribution, KlDivergence, check_shape};
+use crate::random::Rng;
+use crate::tensor::Tensor;
+
+/// Categorical distributions over `0..k`, batched over all but the last
+/// dimension.
+///
+/// Logits of shape `[..., k]` give one distribution per leading index.
+/// Samples and `log_prob` values are indexed by that batch shape, with
+/// categories as f32 indices (there is no integer dtype).
+///
+/// # Example
+/// ```
+/// use delta::distributions::{Categorical, Distribution};
+/// use delta::tensor::Tensor;
+///
+/// // A policy over 3 actions, for two states
+/// let logits = Tensor::from_vec(vec![0.0, 0.0, 0.0,
+///                                    10.0, 0.0, 0.0], &[2, 3]);
+/// let pi = Categorical::from_logits(logits);
+/// let actions = Tensor::from_vec(vec![2.0, 0.0], &[2]);
+/// let lp = pi.log_prob(&actions).to_vec();
+/// assert!((lp[0] - (1.0f32 / 3.0).ln()).abs() < 1e-6);
+/// assert!(lp[1] > -1e-3);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Categorical {
+    /// Normalized: log probabilities
+    log_probs: Tensor,
+}
+
+impl Categorical {
+    /// From probabilities along the last dimension. They are normalized,
+    /// so any non-negative weights will do.
+    ///
+    /// # Panics
+    /// - Panics if `probs` is 0-dimensional or has no categories
+    /// - Panics if a probability is negative
+    pub fn new(probs: Tensor) -> Self {
+        assert!(
+            probs.to_vec().iter().all(|&p| p >= 0.0),
+            "Categorical requires non-negative probabilities"
+        );
+        // Zero probabilities give the most negative finite logit rather
+        // than -inf, which keeps entropy and KL free of 0 * inf
+        Self::from_logits(probs.ln().nan_to_num(0.0, f32::MAX, f32::MIN))
+    }
+
+    /// From unnormalized log probabilities along the last dimension.
+    ///
+    /// # Panics
+    /// Panics if `logits` is 0-dimensional or has no categories.
+    pub fn from_logits(logits: Tensor) -> Self {
+        assert!(
+            logits.ndim() >= 1 && logits.shape()[logits.ndim() - 1] > 0,
+            "Categorical requires at least one category, got shape {:?}",
+            logits.shape()
+        );
+        let last = logits.ndim() - 1;
+        Self {
+            log_probs: logits.log_softmax(last),
+        }
+    }
+
+    /// Number of categories.
+    pub fn num_categories(&self) -> usize {
+        self.log_probs.shape()[self.last_dim()]
+    }
+
+    /// Shape of the batch of distributions (the logits minus the last
+    /// dimension).
+    pub fn batch_shape(&self) -> &[usize] {
+        &self.log_probs.shape()[..self.last_dim()]
+    }
+
+    /// Normalized log probabilities, shaped like the logits.
+    pub fn logits(&self) -> &Tensor {
+        &self.log_probs
+    }
+
+    pub fn probs(&self) -> Tensor {
+        self.log_probs.exp()
+    }
+
+    fn last_dim(&self) -> usize {
+        self.log_probs.ndim() - 1
+    }
+}
+
+impl Distribution for Categorical {
+    /// Draws by inverting each distribution's CDF.
+    fn sample(&self, rng: &mut Rng) -> Tensor {
+        let k = self.num_categories();
+        let draws = self
+            .probs()
+            .to_vec()
+            .chunks(k)
+            .map(|probs| {
+                let u = rng.uniform();
+                let mut cdf = 0.0;
+                // Rounding can leave the CDF just short of 1: fall back to
+                // the last category with nonzero mass
+                let mut last = 0;
+                for (i, &p) in probs.iter().enumerate() {
+                    if p > 0.0 {
+                        last = i;
+                    }
+                    cdf += p;
+                    if u < cdf {
+                        return i as f32;
+                    }
+                }
+                last as f32
+            })
+            .collect();
+        Tensor::from_vec(draws, self.batch_shape())
+    }
+
+    /// Log probability of category indices, shaped like the batch.
+    ///
+    /// # Panics
+    /// - Panics if `value` is not shaped like the batch
+    /// - Panics if a value is not an index in `0..k`
+    fn log_prob(&self, value: &Tensor) -> Tensor {
+        check_shape("Categorical", self.batch_shape(), value);
+        let k = self.num_categories();
+        let mut one_hot = vec![0.0; value.nelems() * k];
+        for (row, &v) in value.to_vec().iter().enumerate() {
+            assert!(
+                v >= 0.0 && v.fract() == 0.0 && (v as usize) < k,
+                "Categorical::log_prob expected category indices in 0..{}, got {}",
+                k,
+                v
+            );
+            one_hot[row * k + v as usize] = 1.0;
+        }
+        let one_hot = Tensor::from_vec(one_hot, self.log_probs.shape());
+        self.log_probs.mul(&one_hot).sum_dim(self.last_dim(), false)
+    }
+
+    /// `-Σ p ln p`, shaped like the batch.
+    fn entropy(&self) -> Tensor {
+        let p_log_p = self.probs().mul(&self.log_probs);
+        p_log_p.sum_dim(self.last_dim(), false).neg()
+    }
+}
+
+impl KlDivergence for Categorical {
+    /// `Σ p (ln p - ln q)`, shaped like the batch.
+    ///
+    /// # Panics
+    /// Panics if the logits have different shapes.
+    fn kl_divergence(&self, q: &Categorical) -> Tensor {
+        assert_eq!(
+            self.log_probs.shape(),
+            q.log_probs.shape(),
+            "kl_divergence between Categoricals of shapes {:?} and {:?}",
+            self.log_probs.shape(),
+            q.log_probs.shape()
+        );
+        let log_ratio = self.log_probs.sub(&q.log_probs);
+        self.probs().mul(&log_ratio).sum_dim(self.last_dim(), false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::autograd::testing::check_grad;
+
+    #[test]
+    fn test_new_normalizes() {
+        let d = Categorical::new(Tensor::from_vec(vec![1.0, 3.0], &[2]));
+        let p = d.probs().to_vec();
+        assert!((p[0] - 0.25).abs() < 1e-6 && (p[1] - 0.75).abs() < 1e-6);
+        assert_eq!(d.batch_shape(), &[] as &[usize]);
+        assert_eq!(d.num_categories(), 2);
+    }
+
+    #[test]
+    fn test_entropy_and_kl() {
+        let uniform = Categorical::new(Tensor::from_vec(vec![1.0; 4], &[4]));
+        assert!((uniform.entropy().get(&[]) - 4f32.ln()).abs() < 1e-6);
+
+        // Zero-probability categories contribute nothing, not NaN
+        let p = Categorical::new(Tensor::from_vec(vec![0.5, 0.5, 0.0, 0.0], &[4]));
+        assert!((p.entropy().get(&[]) - 2f32.ln()).abs() < 1e-6);
+        let kl = p.kl_divergence(&uniform).get(&[]);
+        assert!((kl - 2f32.ln()).abs() < 1e-6, "{}", kl);
+    }
+
+    #[test]
+    fn test_sample() {
+        let probs = Tensor::from_vec(vec![0.2, 0.0, 0.8, 0.0, 1.0, 0.0], &[2, 3]);
+        let d = Categorical::new(probs);
+        let mut rng = Rng::new(11);
+        let mut counts = [0; 3];
+        for _ in 0..5000 {
+            let s = d.sample(&mut rng);
+            assert_eq!(s.shape(), &[2]);
+            assert_eq!(s.get(&[1]), 1.0);
+            counts[s.get(&[0]) as usize] += 1;
+        }
+        assert_eq!(counts[1], 0);
+        assert!((counts[0] as f32 / 5000.0 - 0.2).abs() < 0.02);
+    }
+
+    #[test]
+    fn test_gradients() {
+        let logits = Tensor::from_vec(vec![0.5, -1.0, 2.0, 0.1, 0.3, -0.7], &[2, 3]);
+        let actions = Tensor::from_vec(vec![2.0, 0.0], &[2]);
+        let q = Categorical::from_logits(Tensor::from_vec(
+            vec![1.0, 0.0, -1.0, 0.0, 0.5, 0.2],
+            &[2, 3],
+        ));
+        let from = |t: &[Tensor]| Categorical::from_logits(t[0].clone());
+        let inputs = std::slice::from_ref(&logits);
+        check_grad(|t| from(t).log_prob(&actions), inputs);
+        check_grad(|t| from(t).entropy(), inputs);
+        check_grad(|t| from(t).kl_divergence(&q), inputs);
+    }
+
+    #[test]
+    #[should_panic(expected = "category indices in 0..3")]
+    fn test_log_prob_out_of_range() {
+        let d = Categorical::from_logits(Tensor::zeros(&[3]));
+        d.log_prob(&Tensor::from_vec(vec![3.0], &[]));
+    }
+}
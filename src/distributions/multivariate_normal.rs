@@ -0,0 +1,403 @@
+use crate::autograd::no_grad;
+use crate::distributions::HALF_LN_TAU;
+use crate::distributions::{Distribution, KlDivergence, Rsample};
+use crate::random::Rng;
+use crate::tensor::Tensor;
+use crate::tensor::broadcast_shapes;
+
+/// A d-dimensional normal distribution N(μ, Σ), parameterized by the mean
+/// and the Cholesky factor L of the covariance (Σ = L Lᵀ).
+///
+/// Working with L keeps `log_prob` to a triangular solve and makes the
+/// log-determinant a sum of logs of the diagonal. Gradients flow to both
+/// `loc` and the factor, or through [`Tensor::cholesky`] to a covariance
+/// given to [`MultivariateNormal::new`].
+///
+/// The parameters may be batched: a mean `[..., d]` and a factor
+/// `[..., d, d]` describe one distribution per batch index, e.g. the
+/// components of a mixture density network. The two batch shapes are
+/// broadcast against each other, so a single covariance can be shared by
+/// a batch of means. `log_prob`, `entropy` and `kl_divergence` return one
+/// value per batch index.
+///
+/// # Example
+/// ```
+/// use delta::distributions::{Distribution, MultivariateNormal};
+/// use delta::tensor::Tensor;
+///
+/// let loc = Tensor::zeros(&[2]);
+/// let cov = Tensor::from_vec(vec![1.0, 0.0,
+///                                 0.0, 1.0], &[2, 2]);
+/// let d = MultivariateNormal::new(loc, &cov);
+/// // A batch of 3 points gives 3 log densities
+/// let xs = Tensor::zeros(&[3, 2]);
+/// assert_eq!(d.log_prob(&xs).shape(), &[3]);
+///
+/// // 4 means sharing one covariance
+/// let batched = MultivariateNormal::new(Tensor::zeros(&[4, 2]), &cov);
+/// assert_eq!(batched.batch_shape(), &[4]);
+/// assert_eq!(batched.log_prob(&Tensor::zeros(&[2])).shape(), &[4]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct MultivariateNormal {
+    /// [..., d]
+    loc: Tensor,
+    /// [..., d, d], lower triangular with a positive diagonal
+    scale_tril: Tensor,
+}
+
+impl MultivariateNormal {
+    /// From a mean `[..., d]` and a symmetric positive-definite covariance
+    /// `[..., d, d]`.
+    ///
+    /// # Panics
+    /// - Panics if the shapes don't match or the batch shapes don't
+    ///   broadcast
+    /// - Panics if a covariance is not positive definite
+    pub fn new(loc: Tensor, covariance: &Tensor) -> Self {
+        Self::from_scale_tril(loc, covariance.cholesky())
+    }
+
+    /// From a mean `[..., d]` and a lower-triangular factor `[..., d, d]`
+    /// of the covariance. Only the lower triangle is used.
+    ///
+    /// # Panics
+    /// - Panics if the shapes don't match or the batch shapes don't
+    ///   broadcast
+    /// - Panics if the diagonal of a factor is not positive
+    pub fn from_scale_tril(loc: Tensor, scale_tril: Tensor) -> Self {
+        let (nl, ns) = (loc.ndim(), scale_tril.ndim());
+        let d = loc.shape().last().copied().unwrap_or(0);
+        let batch = (nl >= 1 && ns >= 2 && scale_tril.shape()[ns - 2..] == [d, d])
+            .then(|| broadcast_shapes(&loc.shape()[..nl - 1], &scale_tril.shape()[..ns - 2]))
+            .flatten();
+        let Some(batch) = batch else {
+            panic!(
+                "MultivariateNormal expects loc [..., d] and scale_tril [..., d, d], got {:?} and {:?}",
+                loc.shape(),
+                scale_tril.shape()
+            );
+        };
+        assert!(
+            scale_tril
+                .to_vec()
+                .chunks(d * d)
+                .all(|l| (0..d).all(|i| l[i * d + i] > 0.0)),
+            "MultivariateNormal requires a positive diagonal in scale_tril"
+        );
+        Self {
+            loc: expand(&loc, &[&batch[..], &[d]].concat()),
+            scale_tril: expand(&scale_tril.tril(), &[&batch[..], &[d, d]].concat()),
+        }
+    }
+
+    /// The dimension d.
+    pub fn dim(&self) -> usize {
+        self.loc.shape()[self.loc.ndim() - 1]
+    }
+
+    /// Shape of the batch of distributions (`[]` for a single one).
+    pub fn batch_shape(&self) -> &[usize] {
+        &self.loc.shape()[..self.loc.ndim() - 1]
+    }
+
+    /// The mean, `[..., d]`.
+    pub fn loc(&self) -> &Tensor {
+        &self.loc
+    }
+
+    /// The Cholesky factor L of the covariance, `[..., d, d]`.
+    pub fn scale_tril(&self) -> &Tensor {
+        &self.scale_tril
+    }
+
+    /// The covariance L Lᵀ, `[..., d, d]`.
+    pub fn covariance(&self) -> Tensor {
+        let lt = self.scale_tril.matrix_transpose();
+        self.scale_tril.batch_matmul(&lt)
+    }
+
+    /// ½ ln det Σ = Σᵢ ln Lᵢᵢ, shaped like the batch
+    fn half_log_det(&self) -> Tensor {
+        let d = self.dim();
+        let eye = (0..d * d)
+            .map(|i| if i / d == i % d { 1.0 } else { 0.0 })
+            .collect();
+        let eye = Tensor::from_vec(eye, &[d, d]).broadcast_to(self.scale_tril.shape());
+        let diag = sum_last(&self.scale_tril.mul(&eye));
+        sum_last(&diag.ln())
+    }
+}
+
+/// `t` broadcast to `shape`, without a copy if it already has that shape.
+fn expand(t: &Tensor, shape: &[usize]) -> Tensor {
+    if t.shape() == shape {
+        t.clone()
+    } else {
+        t.broadcast_to(shape)
+    }
+}
+
+fn sum_last(t: &Tensor) -> Tensor {
+    t.sum_dim(t.ndim() - 1, false)
+}
+
+impl Distribution for MultivariateNormal {
+    fn sample(&self, rng: &mut Rng) -> Tensor {
+        no_grad(|| self.rsample(rng))
+    }
+
+    /// `-½ ‖L⁻¹(x - μ)‖² - ½ ln det Σ - (d/2) ln 2π`
+    ///
+    /// `value` is `[..., d]`; its batch dimensions broadcast against the
+    /// distribution's, and the result has the broadcast batch shape. For a
+    /// single distribution, `[d]` gives a scalar and `[n, d]` gives `[n]`.
+    ///
+    /// # Panics
+    /// Panics if `value` is not `[..., d]` or its batch dimensions don't
+    /// broadcast.
+    fn log_prob(&self, value: &Tensor) -> Tensor {
+        let d = self.dim();
+        let nv = value.ndim();
+        let batch = (nv >= 1 && value.shape()[nv - 1] == d)
+            .then(|| broadcast_shapes(&value.shape()[..nv - 1], self.batch_shape()))
+            .flatten();
+        let Some(batch) = batch else {
+            panic!(
+                "MultivariateNormal::log_prob expected a value of shape [..., {}] broadcasting with batch shape {:?}, got {:?}",
+                d,
+                self.batch_shape(),
+                value.shape()
+            );
+        };
+
+        let full = [&batch[..], &[d]].concat();
+        // Every centered point as a column
+        let diff = expand(value, &full).sub(&expand(&self.loc, &full));
+        let diff = diff.reshape(&[&batch[..], &[d, 1]].concat());
+        let l = expand(&self.scale_tril, &[&batch[..], &[d, d]].concat());
+        let z = l.solve_triangular(&diff, false).reshape(&full);
+        let maha = sum_last(&z.powi(2));
+        let constant = -(d as f32) * HALF_LN_TAU;
+        maha.scalar_mul(-0.5)
+            .sub(&expand(&self.half_log_det(), &batch))
+            .scalar_add(constant)
+    }
+
+    /// `(d/2)(1 + ln 2π) + ½ ln det Σ`
+    fn entropy(&self) -> Tensor {
+        let d = self.dim() as f32;
+        self.half_log_det().scalar_add(d * (0.5 + HALF_LN_TAU))
+    }
+}
+
+impl Rsample for MultivariateNormal {
+    /// `μ + L ε` with ε standard normal.
+    fn rsample(&self, rng: &mut Rng) -> Tensor {
+        let shape = self.loc.shape();
+        let eps = Tensor::randn(&[shape, &[1]].concat(), rng);
+        let offset = self.scale_tril.batch_matmul(&eps).reshape(shape);
+        self.loc.add(&offset)
+    }
+}
+
+impl KlDivergence for MultivariateNormal {
+    /// `½ [tr(Σq⁻¹ Σp) + (μq - μp)ᵀ Σq⁻¹ (μq - μp) - d + ln det Σq - ln det Σp]`
+    ///
+    /// Batch shapes broadcast; the result has the broadcast batch shape.
+    ///
+    /// # Panics
+    /// Panics if the two have different dimensions or their batch shapes
+    /// don't broadcast.
+    fn kl_divergence(&self, q: &MultivariateNormal) -> Tensor {
+        let d = self.dim();
+        let batch = (d == q.dim())
+            .then(|| broadcast_shapes(self.batch_shape(), q.batch_shape()))
+            .flatten();
+        let Some(batch) = batch else {
+            panic!(
+                "kl_divergence between MultivariateNormals of shapes {:?} and {:?}",
+                self.loc.shape(),
+                q.loc.shape()
+            );
+        };
+        let matrices = [&batch[..], &[d, d]].concat();
+        let (lp, lq) = (
+            expand(&self.scale_tril, &matrices),
+            expand(&q.scale_tril, &matrices),
+        );
+        // tr(Σq⁻¹ Σp) = ‖Lq⁻¹ Lp‖²_F
+        let trace = sum_last(&sum_last(&lq.solve_triangular(&lp, false).powi(2)));
+        let full = [&batch[..], &[d]].concat();
+        let diff = expand(&q.loc, &full).sub(&expand(&self.loc, &full));
+        let diff = diff.reshape(&[&batch[..], &[d, 1]].concat());
+        let maha = sum_last(&sum_last(&lq.solve_triangular(&diff, false).powi(2)));
+        let log_det = expand(&q.half_log_det(), &batch).sub(&expand(&self.half_log_det(), &batch));
+        trace
+            .add(&maha)
+            .scalar_add(-(d as f32))
+            .scalar_mul(0.5)
+            .add(&log_det)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::autograd::testing::check_grad;
+    use crate::distributions::Normal;
+
+    fn cov() -> Tensor {
+        Tensor::from_vec(vec![2.0, 0.6, 0.6, 1.0], &[2, 2])
+    }
+
+    #[test]
+    fn test_log_prob() {
+        let loc = Tensor::from_vec(vec![1.0, -1.0], &[2]);
+        let d = MultivariateNormal::new(loc, &cov());
+        let x = Tensor::from_vec(vec![0.5, 0.0], &[2]);
+
+        // Direct: Σ⁻¹ = [[1, -0.6], [-0.6, 2]] / 1.64
+        let (dx, dy) = (-0.5f32, 1.0f32);
+        let maha = (dx * dx - 1.2 * dx * dy + 2.0 * dy * dy) / 1.64;
+        let expected = -0.5 * maha - 0.5 * 1.64f32.ln() - 2.0 * HALF_LN_TAU;
+        let lp = d.log_prob(&x);
+        assert_eq!(lp.shape(), &[] as &[usize]);
+        assert!((lp.get(&[]) - expected).abs() < 1e-5);
+
+        let batch = Tensor::from_vec(vec![0.5, 0.0, 1.0, -1.0], &[2, 2]);
+        let lps = d.log_prob(&batch).to_vec();
+        assert!((lps[0] - expected).abs() < 1e-5);
+        assert!((lps[1] - (-0.5 * 1.64f32.ln() - 2.0 * HALF_LN_TAU)).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_diagonal_matches_normal() {
+        let loc = Tensor::from_vec(vec![0.5, -2.0], &[2]);
+        let scale = Tensor::from_vec(vec![1.5, 0.3], &[2]);
+        let cov = Tensor::from_vec(vec![2.25, 0.0, 0.0, 0.09], &[2, 2]);
+        let mvn = MultivariateNormal::new(loc.clone(), &cov);
+        let normal = Normal::new(loc, scale);
+
+        let x = Tensor::from_vec(vec![1.0, -1.5], &[2]);
+        let expected = normal.log_prob(&x).sum().get(&[]);
+        assert!((mvn.log_prob(&x).get(&[]) - expected).abs() < 1e-5);
+        let expected = normal.entropy().sum().get(&[]);
+        assert!((mvn.entropy().get(&[]) - expected).abs() < 1e-5);
+
+        let q_cov = Tensor::from_vec(vec![1.0, 0.0, 0.0, 4.0], &[2, 2]);
+        let q = MultivariateNormal::new(Tensor::zeros(&[2]), &q_cov);
+        let q_normal = Normal::new(Tensor::zeros(&[2]), Tensor::from_vec(vec![1.0, 2.0], &[2]));
+        let expected = normal.kl_divergence(&q_normal).sum().get(&[]);
+        assert!((mvn.kl_divergence(&q).get(&[]) - expected).abs() < 1e-5);
+        assert!(mvn.kl_divergence(&mvn).get(&[]).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_sample_covariance() {
+        let d = MultivariateNormal::new(Tensor::from_vec(vec![1.0, 0.0], &[2]), &cov());
+        let mut rng = Rng::new(9);
+        let n = 20_000;
+        let xs: Vec<Vec<f32>> = (0..n).map(|_| d.sample(&mut rng).to_vec()).collect();
+        let mean = |i: usize| xs.iter().map(|x| x[i]).sum::<f32>() / n as f32;
+        let (m0, m1) = (mean(0), mean(1));
+        let cov01 = xs.iter().map(|x| (x[0] - m0) * (x[1] - m1)).sum::<f32>() / n as f32;
+        assert!((m0 - 1.0).abs() < 0.03 && m1.abs() < 0.03);
+        assert!((cov01 - 0.6).abs() < 0.03, "{}", cov01);
+    }
+
+    #[test]
+    fn test_gradients() {
+        let loc = Tensor::from_vec(vec![0.3, -0.2], &[2]);
+        let tril = cov().cholesky();
+        let xs = Tensor::from_vec(vec![0.5, 0.0, 1.0, -1.0, -0.3, 0.7], &[3, 2]);
+        let from = |t: &[Tensor]| MultivariateNormal::from_scale_tril(t[0].clone(), t[1].clone());
+        let inputs = [loc, tril];
+        check_grad(|t| from(t).log_prob(&xs), &inputs);
+        // The entropy does not depend on the mean
+        let entropy = |t: &[Tensor]| {
+            MultivariateNormal::from_scale_tril(inputs[0].clone(), t[0].clone()).entropy()
+        };
+        check_grad(entropy, &inputs[1..]);
+        let q = MultivariateNormal::new(
+            Tensor::from_vec(vec![1.0, 1.0], &[2]),
+            &cov().scalar_mul(0.5),
+        );
+        check_grad(|t| from(t).kl_divergence(&q), &inputs);
+        check_grad(|t| q.kl_divergence(&from(t)), &inputs);
+        check_grad(|t| from(t).rsample(&mut Rng::new(4)), &inputs);
+    }
+
+    /// Two components: [2, 2] means and [2, 2, 2] covariances
+    fn mixture() -> (Tensor, Tensor) {
+        let locs = Tensor::from_vec(vec![1.0, -1.0, 0.0, 2.0], &[2, 2]);
+        let mut covs = cov().to_vec();
+        covs.extend(vec![0.5, -0.1, -0.1, 0.8]);
+        (locs, Tensor::from_vec(covs, &[2, 2, 2]))
+    }
+
+    fn component(locs: &Tensor, covs: &Tensor, i: usize) -> MultivariateNormal {
+        let loc = Tensor::from_vec(locs.to_vec()[2 * i..2 * i + 2].to_vec(), &[2]);
+        let cov = Tensor::from_vec(covs.to_vec()[4 * i..4 * i + 4].to_vec(), &[2, 2]);
+        MultivariateNormal::new(loc, &cov)
+    }
+
+    #[test]
+    fn test_batched_matches_components() {
+        let (locs, covs) = mixture();
+        let d = MultivariateNormal::new(locs.clone(), &covs);
+        assert_eq!(d.batch_shape(), &[2]);
+        assert_eq!(d.covariance().shape(), &[2, 2, 2]);
+
+        // One point per component, and one point broadcast to both
+        let xs = Tensor::from_vec(vec![0.5, 0.0, -0.3, 1.0], &[2, 2]);
+        let x = Tensor::from_vec(vec![0.2, 0.4], &[2]);
+        let (lps, lp_shared) = (d.log_prob(&xs).to_vec(), d.log_prob(&x).to_vec());
+        let h = d.entropy().to_vec();
+        let q = MultivariateNormal::new(Tensor::zeros(&[2]), &cov());
+        let kl = d.kl_divergence(&q).to_vec();
+        for i in 0..2 {
+            let c = component(&locs, &covs, i);
+            let xi = Tensor::from_vec(xs.to_vec()[2 * i..2 * i + 2].to_vec(), &[2]);
+            assert!((lps[i] - c.log_prob(&xi).get(&[])).abs() < 1e-5);
+            assert!((lp_shared[i] - c.log_prob(&x).get(&[])).abs() < 1e-5);
+            assert!((h[i] - c.entropy().get(&[])).abs() < 1e-5);
+            assert!((kl[i] - c.kl_divergence(&q).get(&[])).abs() < 1e-5);
+        }
+
+        // [n, 1, d] against a batch of 2 gives [n, 2]
+        let grid = Tensor::zeros(&[3, 1, 2]);
+        assert_eq!(d.log_prob(&grid).shape(), &[3, 2]);
+        assert_eq!(d.sample(&mut Rng::new(0)).shape(), &[2, 2]);
+    }
+
+    #[test]
+    fn test_shared_covariance() {
+        let (locs, _) = mixture();
+        let d = MultivariateNormal::new(locs, &cov());
+        assert_eq!(d.scale_tril().shape(), &[2, 2, 2]);
+        assert_eq!(
+            &d.scale_tril().to_vec()[4..],
+            &cov().cholesky().to_vec()[..]
+        );
+    }
+
+    #[test]
+    fn test_batched_gradients() {
+        let (locs, covs) = mixture();
+        let xs = Tensor::from_vec(vec![0.5, 0.0, -0.3, 1.0, 0.2, 0.2], &[3, 1, 2]);
+        let symmetric = |t: &Tensor| t.add(&t.matrix_transpose()).scalar_mul(0.5);
+        let from = |t: &[Tensor]| MultivariateNormal::new(t[0].clone(), &symmetric(&t[1]));
+        let inputs = [locs, covs];
+        check_grad(|t| from(t).log_prob(&xs), &inputs);
+        let q = MultivariateNormal::new(Tensor::zeros(&[2]), &cov());
+        check_grad(|t| from(t).kl_divergence(&q), &inputs);
+    }
+
+    #[test]
+    #[should_panic(expected = "expected a value of shape [..., 2]")]
+    fn test_log_prob_shape() {
+        let d = MultivariateNormal::new(Tensor::zeros(&[2]), &cov());
+        d.log_prob(&Tensor::zeros(&[3]));
+    }
+}
@@ -0,0 +1,215 @@
+use crate::autograd::no_grad;
+use crate::distributions::{Distribution, KlDivergence, Rsample, check_shape};
+use crate::random::Rng;
+use crate::tensor::Tensor;
+
+/// ½ ln(2π)
+pub(crate) const HALF_LN_TAU: f32 = 0.918_938_5;
+
+/// Element-wise normal (Gaussian) distributions N(loc, scale²).
+///
+/// `loc` and `scale` have the same shape; every element is an independent
+/// univariate normal, and `log_prob` and `entropy` are element-wise.
+#[derive(Debug, Clone)]
+pub struct Normal {
+    loc: Tensor,
+    scale: Tensor,
+}
+
+impl Normal {
+    /// # Panics
+    /// - Panics if `loc` and `scale` have different shapes
+    /// - Panics if an element of `scale` is not positive
+    pub fn new(loc: Tensor, scale: Tensor) -> Self {
+        assert_eq!(
+            loc.shape(),
+            scale.shape(),
+            "Normal expects loc and scale of the same shape, got {:?} and {:?}",
+            loc.shape(),
+            scale.shape()
+        );
+        assert!(
+            scale.to_vec().iter().all(|&s| s > 0.0),
+            "Normal requires a positive scale"
+        );
+        Self { loc, scale }
+    }
+
+    /// The mean.
+    pub fn loc(&self) -> &Tensor {
+        &self.loc
+    }
+
+    /// The standard deviation.
+    pub fn scale(&self) -> &Tensor {
+        &self.scale
+    }
+}
+
+impl Distribution for Normal {
+    fn sample(&self, rng: &mut Rng) -> Tensor {
+        no_grad(|| self.rsample(rng))
+    }
+
+    /// `-(x - μ)² / 2σ² - ln σ - ½ ln 2π`
+    ///
+    /// # Panics
+    /// Panics if `value` is not shaped like the parameters.
+    fn log_prob(&self, value: &Tensor) -> Tensor {
+        check_shape("Normal", self.loc.shape(), value);
+        let z = value.sub(&self.loc).div(&self.scale);
+        z.powi(2)
+            .scalar_mul(-0.5)
+            .sub(&self.scale.ln())
+            .scalar_add(-HALF_LN_TAU)
+    }
+
+    /// `½ + ½ ln 2π + ln σ`
+    fn entropy(&self) -> Tensor {
+        self.scale.ln().scalar_add(0.5 + HALF_LN_TAU)
+    }
+}
+
+impl Rsample for Normal {
+    /// `μ + σ ε` with ε standard normal.
+    fn rsample(&self, rng: &mut Rng) -> Tensor {
+        let eps = Tensor::randn(self.loc.shape(), rng);
+        self.loc.add(&self.scale.mul(&eps))
+    }
+}
+
+impl KlDivergence for Normal {
+    /// See [`kl_normal_normal`].
+    fn kl_divergence(&self, q: &Normal) -> Tensor {
+        kl_normal_normal(self, q)
+    }
+}
+
+/// Element-wise KL(p ‖ q) between normals, in closed form:
+/// `ln(σq / σp) + (σp² + (μp - μq)²) / 2σq² - ½`.
+///
+/// With q the standard normal this is the KL term of a VAE loss.
+///
+/// # Panics
+/// Panics if the two have different shapes.
+///
+/// # Example
+/// ```
+/// use delta::distributions::{Normal, kl_normal_normal};
+/// use delta::tensor::Tensor;
+/// let posterior = Normal::new(
+///     Tensor::from_vec(vec![0.0, 1.0], &[2]),
+///     Tensor::from_vec(vec![1.0, 1.0], &[2]),
+/// );
+/// let prior = Normal::new(Tensor::zeros(&[2]), Tensor::from_vec(vec![1.0, 1.0], &[2]));
+/// // Only the shifted mean costs anything: ½ μ²
+/// assert_eq!(kl_normal_normal(&posterior, &prior).to_vec(), vec![0.0, 0.5]);
+/// ```
+pub fn kl_normal_normal(p: &Normal, q: &Normal) -> Tensor {
+    assert_eq!(
+        p.loc.shape(),
+        q.loc.shape(),
+        "kl_divergence between Normals of shapes {:?} and {:?}",
+        p.loc.shape(),
+        q.loc.shape()
+    );
+    let log_ratio = q.scale.ln().sub(&p.scale.ln());
+    let spread = p.scale.powi(2).add(&p.loc.sub(&q.loc).powi(2));
+    let q_var = q.scale.powi(2).scalar_mul(2.0);
+    log_ratio.add(&spread.div(&q_var)).scalar_add(-0.5)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::{LN_2, PI};
+
+    use super::*;
+    use crate::autograd::testing::check_grad;
+
+    fn normal(loc: &[f32], scale: &[f32]) -> Normal {
+        let n = loc.len();
+        Normal::new(
+            Tensor::from_vec(loc.to_vec(), &[n]),
+            Tensor::from_vec(scale.to_vec(), &[n]),
+        )
+    }
+
+    #[test]
+    fn test_half_ln_tau() {
+        assert!((HALF_LN_TAU - 0.5 * (2.0 * PI).ln()).abs() < 1e-7);
+    }
+
+    #[test]
+    fn test_log_prob_and_entropy() {
+        let d = normal(&[0.0, 1.0], &[1.0, 2.0]);
+        let lp = d.log_prob(&Tensor::from_vec(vec![0.0, 3.0], &[2])).to_vec();
+        assert!((lp[0] - (-0.918_938_5)).abs() < 1e-6);
+        // z = 1: -0.5 - ln 2 - ½ ln 2π
+        assert!((lp[1] - (-0.5 - LN_2 - 0.918_938_5)).abs() < 1e-6);
+
+        let h = d.entropy().to_vec();
+        assert!((h[0] - 1.418_938_5).abs() < 1e-6);
+        assert!((h[1] - (1.418_938_5 + LN_2)).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_sample_moments() {
+        let d = normal(&[3.0], &[0.5]);
+        let mut rng = Rng::new(0);
+        let xs: Vec<f32> = (0..10_000).map(|_| d.sample(&mut rng).get(&[0])).collect();
+        let mean = xs.iter().sum::<f32>() / xs.len() as f32;
+        let var = xs.iter().map(|x| (x - mean).powi(2)).sum::<f32>() / xs.len() as f32;
+        assert!((mean - 3.0).abs() < 0.02, "{}", mean);
+        assert!((var - 0.25).abs() < 0.01, "{}", var);
+    }
+
+    #[test]
+    fn test_sample_is_not_recorded() {
+        let loc = Tensor::zeros(&[2]).requires_grad(true);
+        let d = Normal::new(loc, Tensor::from_vec(vec![1.0, 1.0], &[2]));
+        assert!(d.sample(&mut Rng::new(0)).is_leaf());
+    }
+
+    #[test]
+    fn test_rsample_gradients() {
+        let loc = Tensor::from_vec(vec![0.0, 1.0], &[2]);
+        let scale = Tensor::from_vec(vec![1.5, 0.7], &[2]);
+        // Same seed on every evaluation: the noise is held fixed
+        check_grad(
+            |t| Normal::new(t[0].clone(), t[1].clone()).rsample(&mut Rng::new(3)),
+            &[loc, scale],
+        );
+    }
+
+    #[test]
+    fn test_kl() {
+        let p = normal(&[0.0, 1.0], &[1.0, 0.5]);
+        let q = normal(&[0.0, -1.0], &[1.0, 2.0]);
+        let kl = p.kl_divergence(&q).to_vec();
+        assert_eq!(kl[0], 0.0);
+        let expected = (4.0f32).ln() + (0.25 + 4.0) / 8.0 - 0.5;
+        assert!((kl[1] - expected).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_gradients() {
+        let x = Tensor::from_vec(vec![0.5, -1.0], &[2]);
+        let loc = Tensor::from_vec(vec![0.0, 1.0], &[2]);
+        let scale = Tensor::from_vec(vec![1.5, 0.7], &[2]);
+        check_grad(
+            |t| Normal::new(t[0].clone(), t[1].clone()).log_prob(&x),
+            &[loc.clone(), scale.clone()],
+        );
+        let q = Normal::new(scale.clone(), loc.scalar_add(1.0));
+        check_grad(
+            |t| Normal::new(t[0].clone(), t[1].clone()).kl_divergence(&q),
+            &[loc, scale],
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "positive scale")]
+    fn test_non_positive_scale() {
+        normal(&[0.0], &[0.0]);
+    }
+}
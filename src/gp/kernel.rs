@@ -0,0 +1,257 @@
+use crate::tensor::Tensor;
+
+/// A covariance function for Gaussian processes.
+///
+/// Hyperparameters are scalar tensors on a log scale, so that any real
+/// value is valid and they can be optimized without constraints; gradients
+/// of [`Kernel::compute`] flow back to them.
+pub trait Kernel {
+    /// Covariance matrix `[n, m]` between the rows of `x1` `[n, d]` and
+    /// `x2` `[m, d]`.
+    fn compute(&self, x1: &Tensor, x2: &Tensor) -> Tensor;
+
+    /// The hyperparameters, in the order [`Kernel::with_params`] expects.
+    fn params(&self) -> Vec<Tensor>;
+
+    /// The same kernel with its hyperparameters replaced.
+    fn with_params(&self, params: &[Tensor]) -> Self
+    where
+        Self: Sized;
+
+    /// The variances `k(x, x)` of the rows of `x` `[n, d]`, as `[n]`.
+    ///
+    /// The default computes the full matrix and keeps its diagonal.
+    fn diag(&self, x: &Tensor) -> Tensor {
+        let n = x.shape()[0];
+        let eye = (0..n * n)
+            .map(|i| if i / n == i % n { 1.0 } else { 0.0 })
+            .collect();
+        let k = self.compute(x, x);
+        k.mul(&Tensor::from_vec(eye, &[n, n])).sum_dim(1, false)
+    }
+}
+
+/// Squared Euclidean distances `[n, m]` between the rows of `x1` and `x2`,
+/// divided by the squared lengthscale.
+///
+/// # Panics
+/// Panics if the inputs are not `[n, d]` and `[m, d]`.
+fn scaled_sq_dist(x1: &Tensor, x2: &Tensor, log_lengthscale: &Tensor) -> Tensor {
+    assert!(
+        x1.ndim() == 2 && x2.ndim() == 2 && x1.shape()[1] == x2.shape()[1],
+        "kernel inputs must be [n, d] and [m, d], got {:?} and {:?}",
+        x1.shape(),
+        x2.shape()
+    );
+    let (n, m, d) = (x1.shape()[0], x2.shape()[0], x1.shape()[1]);
+    let a = x1.reshape(&[n, 1, d]).broadcast_to(&[n, m, d]);
+    let b = x2.reshape(&[1, m, d]).broadcast_to(&[n, m, d]);
+    let sq_dist = a.sub(&b).powi(2).sum_dim(2, false);
+    let inv_l2 = log_lengthscale.scalar_mul(-2.0).exp();
+    sq_dist.mul(&inv_l2.broadcast_to(&[n, m]))
+}
+
+/// `σ² · k(r)` for a scalar signal variance given on a log scale.
+fn scale_by_variance(k: Tensor, log_variance: &Tensor) -> Tensor {
+    let shape = k.shape().to_vec();
+    k.mul(&log_variance.exp().broadcast_to(&shape))
+}
+
+fn log_param(value: f32, name: &str) -> Tensor {
+    assert!(value > 0.0, "{} must be positive, got {}", name, value);
+    Tensor::from_vec(vec![value.ln()], &[])
+}
+
+/// The squared exponential kernel `σ² exp(-r² / 2ℓ²)`: very smooth
+/// functions varying on a length scale ℓ.
+///
+/// # Example
+/// ```
+/// use delta::gp::{Kernel, Rbf};
+/// use delta::tensor::Tensor;
+/// let k = Rbf::new(1.0, 2.0);
+/// let x = Tensor::from_vec(vec![0.0, 1.0], &[2, 1]);
+/// let cov = k.compute(&x, &x).to_vec();
+/// assert_eq!(cov[0], 2.0);
+/// assert!((cov[1] - 2.0 * (-0.5f32).exp()).abs() < 1e-6);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Rbf {
+    log_lengthscale: Tensor,
+    log_variance: Tensor,
+}
+
+impl Rbf {
+    /// # Panics
+    /// Panics if `lengthscale` or `variance` is not positive.
+    pub fn new(lengthscale: f32, variance: f32) -> Self {
+        Self {
+            log_lengthscale: log_param(lengthscale, "lengthscale"),
+            log_variance: log_param(variance, "variance"),
+        }
+    }
+
+    pub fn lengthscale(&self) -> f32 {
+        self.log_lengthscale.get(&[]).exp()
+    }
+
+    /// The signal variance σ².
+    pub fn variance(&self) -> f32 {
+        self.log_variance.get(&[]).exp()
+    }
+}
+
+impl Kernel for Rbf {
+    fn compute(&self, x1: &Tensor, x2: &Tensor) -> Tensor {
+        let r2 = scaled_sq_dist(x1, x2, &self.log_lengthscale);
+        scale_by_variance(r2.scalar_mul(-0.5).exp(), &self.log_variance)
+    }
+
+    /// `[ln ℓ, ln σ²]`
+    fn params(&self) -> Vec<Tensor> {
+        vec![self.log_lengthscale.clone(), self.log_variance.clone()]
+    }
+
+    fn with_params(&self, params: &[Tensor]) -> Self {
+        Self {
+            log_lengthscale: params[0].clone(),
+            log_variance: params[1].clone(),
+        }
+    }
+}
+
+/// The Matérn kernel with smoothness ν ∈ {½, 3⁄2, 5⁄2}: rougher functions
+/// than [`Rbf`], from continuous (ν = ½, the exponential kernel) to twice
+/// differentiable (ν = 5⁄2).
+///
+/// With `s = √(2ν) r / ℓ`:
+/// - ν = ½: `σ² exp(-s)`
+/// - ν = 3⁄2: `σ² (1 + s) exp(-s)`
+/// - ν = 5⁄2: `σ² (1 + s + s²/3) exp(-s)`
+#[derive(Debug, Clone)]
+pub struct Matern {
+    nu: f32,
+    log_lengthscale: Tensor,
+    log_variance: Tensor,
+}
+
+impl Matern {
+    /// # Panics
+    /// - Panics if `nu` is not 0.5, 1.5 or 2.5
+    /// - Panics if `lengthscale` or `variance` is not positive
+    pub fn new(nu: f32, lengthscale: f32, variance: f32) -> Self {
+        assert!(
+            [0.5, 1.5, 2.5].contains(&nu),
+            "Matern supports nu = 0.5, 1.5 or 2.5, got {}",
+            nu
+        );
+        Self {
+            nu,
+            log_lengthscale: log_param(lengthscale, "lengthscale"),
+            log_variance: log_param(variance, "variance"),
+        }
+    }
+
+    pub fn nu(&self) -> f32 {
+        self.nu
+    }
+
+    pub fn lengthscale(&self) -> f32 {
+        self.log_lengthscale.get(&[]).exp()
+    }
+
+    /// The signal variance σ².
+    pub fn variance(&self) -> f32 {
+        self.log_variance.get(&[]).exp()
+    }
+}
+
+impl Kernel for Matern {
+    fn compute(&self, x1: &Tensor, x2: &Tensor) -> Tensor {
+        let r2 = scaled_sq_dist(x1, x2, &self.log_lengthscale);
+        // The offset keeps the gradient of the square root finite at r = 0
+        let s = r2.scalar_mul(2.0 * self.nu).scalar_add(1e-12).sqrt();
+        let decay = s.neg().exp();
+        let k = if self.nu == 0.5 {
+            decay
+        } else if self.nu == 1.5 {
+            s.scalar_add(1.0).mul(&decay)
+        } else {
+            let poly = s.add(&s.powi(2).scalar_mul(1.0 / 3.0)).scalar_add(1.0);
+            poly.mul(&decay)
+        };
+        scale_by_variance(k, &self.log_variance)
+    }
+
+    /// `[ln ℓ, ln σ²]`
+    fn params(&self) -> Vec<Tensor> {
+        vec![self.log_lengthscale.clone(), self.log_variance.clone()]
+    }
+
+    fn with_params(&self, params: &[Tensor]) -> Self {
+        Self {
+            nu: self.nu,
+            log_lengthscale: params[0].clone(),
+            log_variance: params[1].clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::autograd::testing::check_grad;
+
+    fn points() -> Tensor {
+        Tensor::from_vec(vec![0.0, 0.0, 1.0, 0.5, -0.5, 2.0], &[3, 2])
+    }
+
+    #[test]
+    fn test_rbf_values() {
+        let k = Rbf::new(2.0, 1.5);
+        let cov = k.compute(&points(), &points());
+        assert_eq!(cov.shape(), &[3, 3]);
+        // |x0 - x1|² = 1.25
+        let expected = 1.5 * (-1.25f32 / 8.0).exp();
+        assert!((cov.get(&[0, 1]) - expected).abs() < 1e-6);
+        assert_eq!(cov.get(&[1, 0]), cov.get(&[0, 1]));
+        assert_eq!(k.diag(&points()).to_vec(), vec![1.5; 3]);
+        assert!((k.lengthscale() - 2.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_matern_values() {
+        let r = 1.25f32.sqrt();
+        for (nu, f) in [
+            (0.5, (-r).exp()),
+            (1.5, (1.0 + 3f32.sqrt() * r) * (-(3f32.sqrt()) * r).exp()),
+            (
+                2.5,
+                (1.0 + 5f32.sqrt() * r + 5.0 * r * r / 3.0) * (-(5f32.sqrt()) * r).exp(),
+            ),
+        ] {
+            let k = Matern::new(nu, 1.0, 1.0);
+            let cov = k.compute(&points(), &points());
+            assert!((cov.get(&[0, 1]) - f).abs() < 1e-5, "nu = {}", nu);
+            assert!((cov.get(&[2, 2]) - 1.0).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_hyperparameter_gradients() {
+        let x = points();
+        let params = Rbf::new(0.8, 1.3).params();
+        check_grad(
+            |p| Rbf::new(1.0, 1.0).with_params(p).compute(&x, &x),
+            &params,
+        );
+        let matern = Matern::new(1.5, 1.0, 1.0);
+        check_grad(|p| matern.with_params(p).compute(&x, &x), &params);
+    }
+
+    #[test]
+    #[should_panic(expected = "nu = 0.5, 1.5 or 2.5")]
+    fn test_matern_unsupported_nu() {
+        Matern::new(1.0, 1.0, 1.0);
+    }
+}
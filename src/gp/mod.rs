@@ -0,0 +1,13 @@
+//! Gaussian process regression.
+//!
+//! A Gaussian process is a prior over functions, fixed by a [`Kernel`]
+//! giving the covariance between function values at any two inputs.
+//! [`GpRegression`] conditions it on noisy observations in closed form,
+//! through a Cholesky factorization, and fits the kernel hyperparameters
+//! by maximizing the log marginal likelihood with gradients from autograd.
+
+mod kernel;
+mod regression;
+
+pub use kernel::{Kernel, Matern, Rbf};
+pub use regression::GpRegression;
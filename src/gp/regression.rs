@@ -0,0 +1,231 @@
+use crate::autograd::no_grad;
+use crate::distributions::HALF_LN_TAU;
+use crate::gp::Kernel;
+use crate::optim::{LBFGS, LineSearch};
+use crate::tensor::Tensor;
+
+/// Added to the diagonal of every covariance matrix before factoring it,
+/// relative to the mean prior variance, so that nearly duplicate inputs or
+/// long lengthscales don't make it singular in f32.
+const JITTER: f32 = 1e-5;
+
+/// Exact Gaussian process regression with Gaussian observation noise and a
+/// zero prior mean.
+///
+/// Holds the training data, the kernel and the noise variance. The
+/// posterior is computed from a Cholesky factor of `K + σₙ² I`, refactored
+/// on each call (the training set is assumed small, a few thousand points
+/// at most). The hyperparameters are fitted by maximizing the log marginal
+/// likelihood, which is differentiable with respect to all of them.
+///
+/// # Example
+/// ```
+/// use delta::gp::{GpRegression, Rbf};
+/// use delta::tensor::Tensor;
+///
+/// let x = Tensor::from_vec(vec![-2.0, -1.0, 0.0, 1.0, 2.0], &[5, 1]);
+/// let y = x.sin().reshape(&[5]);
+/// let mut gp = GpRegression::new(Rbf::new(1.0, 1.0), x, y, 1e-4);
+/// gp.fit(20);
+///
+/// let (mean, var) = gp.predict(&Tensor::from_vec(vec![0.5], &[1, 1]));
+/// assert!((mean.get(&[0]) - 0.5f32.sin()).abs() < 0.05);
+/// assert!(var.get(&[0]) < 0.01);
+/// ```
+#[derive(Debug, Clone)]
+pub struct GpRegression<K: Kernel> {
+    kernel: K,
+    log_noise: Tensor,
+    x: Tensor,
+    y: Tensor,
+}
+
+/// Cholesky factor of `K(x, x) + noise I`.
+fn factor(kernel: &impl Kernel, log_noise: &Tensor, x: &Tensor) -> Tensor {
+    let n = x.shape()[0];
+    let eye = (0..n * n)
+        .map(|i| if i / n == i % n { 1.0 } else { 0.0 })
+        .collect();
+    let eye = Tensor::from_vec(eye, &[n, n]);
+    let k = kernel.compute(x, x);
+    // A constant: the jitter is a numerical safeguard, not a parameter
+    let scale = k.mul(&eye).sum().get(&[]) / n as f32;
+    let noise = log_noise.exp().scalar_add(JITTER * scale);
+    k.add(&eye.mul(&noise.broadcast_to(&[n, n]))).cholesky()
+}
+
+/// `ln p(y | x)` under the GP prior: `-½ yᵀ K⁻¹ y - ½ ln det K - (n/2) ln 2π`.
+fn log_marginal_likelihood(
+    kernel: &impl Kernel,
+    log_noise: &Tensor,
+    x: &Tensor,
+    y: &Tensor,
+) -> Tensor {
+    let n = x.shape()[0];
+    let l = factor(kernel, log_noise, x);
+    let z = l.solve_triangular(&y.reshape(&[n, 1]), false);
+    let eye = (0..n * n)
+        .map(|i| if i / n == i % n { 1.0 } else { 0.0 })
+        .collect();
+    let half_log_det = l
+        .mul(&Tensor::from_vec(eye, &[n, n]))
+        .sum_dim(1, false)
+        .ln()
+        .sum();
+    z.powi(2)
+        .sum()
+        .scalar_mul(-0.5)
+        .sub(&half_log_det)
+        .scalar_add(-(n as f32) * HALF_LN_TAU)
+}
+
+impl<K: Kernel> GpRegression<K> {
+    /// A GP over training inputs `x` `[n, d]` with targets `y` `[n]`.
+    ///
+    /// # Panics
+    /// - Panics if `x` is not `[n, d]` or `y` is not `[n]`
+    /// - Panics if `noise_variance` is not positive
+    pub fn new(kernel: K, x: Tensor, y: Tensor, noise_variance: f32) -> Self {
+        assert!(
+            x.ndim() == 2 && y.shape() == [x.shape()[0]],
+            "GpRegression expects inputs [n, d] and targets [n], got {:?} and {:?}",
+            x.shape(),
+            y.shape()
+        );
+        assert!(
+            noise_variance > 0.0,
+            "noise_variance must be positive, got {}",
+            noise_variance
+        );
+        Self {
+            kernel,
+            log_noise: Tensor::from_vec(vec![noise_variance.ln()], &[]),
+            x,
+            y,
+        }
+    }
+
+    pub fn kernel(&self) -> &K {
+        &self.kernel
+    }
+
+    /// The variance σₙ² of the observation noise.
+    pub fn noise_variance(&self) -> f32 {
+        self.log_noise.get(&[]).exp()
+    }
+
+    /// `ln p(y | x)`, the log marginal likelihood of the training targets
+    /// under the current hyperparameters, as a differentiable scalar.
+    pub fn log_marginal_likelihood(&self) -> Tensor {
+        log_marginal_likelihood(&self.kernel, &self.log_noise, &self.x, &self.y)
+    }
+
+    /// Fit the kernel hyperparameters and the noise variance by maximizing
+    /// the log marginal likelihood with L-BFGS, for up to `max_iter`
+    /// iterations. Returns the final log marginal likelihood.
+    pub fn fit(&mut self, max_iter: usize) -> f32 {
+        let mut params = self.kernel.params();
+        params.push(self.log_noise.clone());
+        let k = params.len() - 1;
+
+        let mut opt = LBFGS::new(params)
+            .max_iter(max_iter)
+            .line_search(LineSearch::StrongWolfe);
+        let (kernel, x, y) = (&self.kernel, &self.x, &self.y);
+        opt.step(|p| log_marginal_likelihood(&kernel.with_params(&p[..k]), &p[k], x, y).neg());
+
+        let fitted = opt.params();
+        self.kernel = self.kernel.with_params(&fitted[..k]);
+        self.log_noise = fitted[k].clone();
+        no_grad(|| self.log_marginal_likelihood().get(&[]))
+    }
+
+    /// Posterior mean and variance of the latent function at test inputs
+    /// `x` `[m, d]`, each `[m]`. Add [`GpRegression::noise_variance`] to
+    /// the variance for the predictive distribution of new observations.
+    ///
+    /// Differentiable with respect to the test inputs and the
+    /// hyperparameters.
+    ///
+    /// # Panics
+    /// Panics if `x` does not have the training inputs' number of columns.
+    pub fn predict(&self, x: &Tensor) -> (Tensor, Tensor) {
+        let n = self.x.shape()[0];
+        let l = factor(&self.kernel, &self.log_noise, &self.x);
+        let cross = self.kernel.compute(&self.x, x);
+        // mean = K*ᵀ K⁻¹ y = vᵀ z, variance = k** - Σ v², with v = L⁻¹ K*
+        let z = l.solve_triangular(&self.y.reshape(&[n, 1]), false);
+        let v = l.solve_triangular(&cross, false);
+        let m = x.shape()[0];
+        let mean = v.t().matmul(&z).reshape(&[m]);
+        let var = self.kernel.diag(x).sub(&v.powi(2).sum_dim(0, false));
+        (mean, var)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::autograd::testing::check_grad;
+    use crate::gp::{Matern, Rbf};
+
+    fn data() -> (Tensor, Tensor) {
+        let x = Tensor::from_vec(vec![-1.5, -0.7, 0.0, 0.4, 1.1, 2.0], &[6, 1]);
+        let y = Tensor::from_vec(vec![-0.9, -0.6, 0.1, 0.35, 0.9, 0.85], &[6]);
+        (x, y)
+    }
+
+    #[test]
+    fn test_interpolates_with_low_noise() {
+        let (x, y) = data();
+        let gp = GpRegression::new(Rbf::new(1.0, 1.0), x.clone(), y.clone(), 1e-4);
+        let (mean, var) = gp.predict(&x);
+        for (m, t) in mean.to_vec().iter().zip(y.to_vec()) {
+            assert!((m - t).abs() < 1e-2, "{} vs {}", m, t);
+        }
+        assert!(var.to_vec().iter().all(|&v| (-1e-3..1e-3).contains(&v)));
+
+        // Far from the data the prior comes back
+        let (mean, var) = gp.predict(&Tensor::from_vec(vec![10.0], &[1, 1]));
+        assert!(mean.get(&[0]).abs() < 1e-3);
+        assert!((var.get(&[0]) - 1.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_log_marginal_likelihood() {
+        // One point: ln N(y; 0, σ² + σₙ²)
+        let x = Tensor::from_vec(vec![0.0], &[1, 1]);
+        let y = Tensor::from_vec(vec![1.5], &[1]);
+        let gp = GpRegression::new(Rbf::new(1.0, 2.0), x, y, 0.5);
+        let var = 2.5 + 2.0 * JITTER;
+        let expected = -0.5 * 1.5f32.powi(2) / var - 0.5 * var.ln() - HALF_LN_TAU;
+        assert!((gp.log_marginal_likelihood().get(&[]) - expected).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_fit_improves_likelihood() {
+        let (x, y) = data();
+        let mut gp = GpRegression::new(Matern::new(2.5, 0.2, 0.2), x, y, 0.5);
+        let before = gp.log_marginal_likelihood().get(&[]);
+        let after = gp.fit(30);
+        assert!(after > before + 1.0, "{} -> {}", before, after);
+        assert!(gp.noise_variance() < 0.5);
+    }
+
+    #[test]
+    fn test_gradients() {
+        let (x, y) = data();
+        let test = Tensor::from_vec(vec![0.2, 3.0], &[2, 1]);
+        let mut params = Rbf::new(0.9, 1.2).params();
+        params.push(Tensor::from_vec(vec![0.1f32.ln()], &[]));
+        let gp = |p: &[Tensor]| GpRegression {
+            kernel: Rbf::new(1.0, 1.0).with_params(&p[..2]),
+            log_noise: p[2].clone(),
+            x: x.clone(),
+            y: y.clone(),
+        };
+        check_grad(|p| gp(p).log_marginal_likelihood(), &params);
+        check_grad(|p| gp(p).predict(&test).0, &params);
+        check_grad(|p| gp(p).predict(&test).1, &params);
+    }
+}
@@ -0,0 +1,450 @@
+//! Low-level element-wise kernels over raw strided buffers.
+//!
+//! The building blocks for element-wise ops defined outside delta. A
+//! [`Strided`] describes how a logical `shape` is laid out in a flat
+//! buffer (`strides` and `offset`, in elements), the same way a
+//! [`Tensor`] is; [`Tensor::strided`] borrows a tensor as one. A kernel
+//! walks the output in row-major order, following every buffer's strides
+//! and broadcasting the inputs to the output's shape, so custom ops get
+//! the same semantics as the built-in ones without copying their inputs
+//! into contiguous vectors first.
+//!
+//! With the `parallel` feature, large contiguous outputs are split into
+//! chunks that run on scoped threads, one per available core; each
+//! element is still computed by exactly one call of the closure, so
+//! results do not depend on the number of threads. Without it, kernels
+//! never leave the calling thread.
+//!
+//! # Example
+//! ```
+//! use delta::kernel::{Strided, StridedMut, for_each_zip};
+//! use delta::tensor::Tensor;
+//!
+//! // A custom op: hypot(a, b), broadcasting a column against a row
+//! let a = Tensor::from_vec(vec![3.0, 5.0], &[2, 1]);
+//! let b = Tensor::from_vec(vec![4.0, 12.0], &[2]);
+//! let mut out = vec![0.0; 4];
+//! for_each_zip(
+//!     &mut StridedMut::contiguous(&mut out, &[2, 2]),
+//!     &a.strided(),
+//!     &b.strided(),
+//!     f32::hypot,
+//! );
+//! let c = Tensor::from_vec(out, &[2, 2]);
+//! assert_eq!(c.to_vec(), a.zip_map(&b, f32::hypot).to_vec());
+//! assert_eq!((c.get(&[0, 0]), c.get(&[1, 1])), (5.0, 13.0));
+//! ```
+
+use crate::tensor::{Shape, Tensor, broadcast_shapes};
+
+/// Outputs with at least this many elements are split across threads.
+#[cfg(feature = "parallel")]
+const PARALLEL_MIN: usize = 1 << 16;
+
+/// A read-only strided view into a flat buffer: element `[i, j, ...]` of
+/// `shape` lives at `offset + i * strides[0] + j * strides[1] + ...`.
+///
+/// Strides may be zero (one element repeated along a dimension) and need
+/// not be row-major (a transposed view).
+#[derive(Debug, Clone)]
+pub struct Strided<'a> {
+    data: &'a [f32],
+    shape: Vec<usize>,
+    strides: Vec<usize>,
+    offset: usize,
+}
+
+/// A writable strided view into a flat buffer; see [`Strided`].
+///
+/// If two indices map to the same element, the later one in row-major
+/// order wins.
+#[derive(Debug)]
+pub struct StridedMut<'a> {
+    data: &'a mut [f32],
+    shape: Vec<usize>,
+    strides: Vec<usize>,
+    offset: usize,
+}
+
+/// Check that every index of `shape` lands inside a buffer of `len`.
+fn check_layout(len: usize, shape: &[usize], strides: &[usize], offset: usize) {
+    assert_eq!(
+        shape.len(),
+        strides.len(),
+        "shape {:?} and strides {:?} have different lengths",
+        shape,
+        strides
+    );
+    if shape.contains(&0) {
+        return;
+    }
+    let last = offset
+        + shape
+            .iter()
+            .zip(strides)
+            .map(|(d, s)| (d - 1) * s)
+            .sum::<usize>();
+    assert!(
+        last < len,
+        "shape {:?} with strides {:?} and offset {} reaches element {}, past a buffer of {}",
+        shape,
+        strides,
+        offset,
+        last,
+        len
+    );
+}
+
+impl<'a> Strided<'a> {
+    /// A view of `shape` laid out in `data` by `strides` and `offset`.
+    ///
+    /// # Panics
+    /// Panics if `strides` is not as long as `shape`, or if some index
+    /// falls outside `data`.
+    pub fn new(data: &'a [f32], shape: &[usize], strides: &[usize], offset: usize) -> Self {
+        check_layout(data.len(), shape, strides, offset);
+        Self {
+            data,
+            shape: shape.to_vec(),
+            strides: strides.to_vec(),
+            offset,
+        }
+    }
+
+    /// A row-major view of all of `data`.
+    ///
+    /// # Panics
+    /// Panics if `data` does not have `shape`'s number of elements.
+    pub fn contiguous(data: &'a [f32], shape: &[usize]) -> Self {
+        let shape = Shape::new(shape);
+        assert_eq!(
+            data.len(),
+            shape.nelems(),
+            "buffer of {} elements cannot be viewed as {:?}",
+            data.len(),
+            shape.dims()
+        );
+        Self::new(data, shape.dims(), &shape.strides(), 0)
+    }
+
+    pub fn shape(&self) -> &[usize] {
+        &self.shape
+    }
+
+    pub fn strides(&self) -> &[usize] {
+        &self.strides
+    }
+
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+impl<'a> StridedMut<'a> {
+    /// A writable view of `shape` laid out in `data` by `strides` and
+    /// `offset`.
+    ///
+    /// # Panics
+    /// Panics if `strides` is not as long as `shape`, or if some index
+    /// falls outside `data`.
+    pub fn new(data: &'a mut [f32], shape: &[usize], strides: &[usize], offset: usize) -> Self {
+        check_layout(data.len(), shape, strides, offset);
+        Self {
+            data,
+            shape: shape.to_vec(),
+            strides: strides.to_vec(),
+            offset,
+        }
+    }
+
+    /// A row-major view of all of `data`.
+    ///
+    /// # Panics
+    /// Panics if `data` does not have `shape`'s number of elements.
+    pub fn contiguous(data: &'a mut [f32], shape: &[usize]) -> Self {
+        let shape = Shape::new(shape);
+        assert_eq!(
+            data.len(),
+            shape.nelems(),
+            "buffer of {} elements cannot be viewed as {:?}",
+            data.len(),
+            shape.dims()
+        );
+        Self::new(data, shape.dims(), &shape.strides(), 0)
+    }
+
+    pub fn shape(&self) -> &[usize] {
+        &self.shape
+    }
+
+    pub fn strides(&self) -> &[usize] {
+        &self.strides
+    }
+
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+impl Tensor {
+    /// Borrow the tensor's storage as a [`Strided`] view, for the kernels
+    /// in [`crate::kernel`].
+    pub fn strided(&self) -> Strided<'_> {
+        let (data, strides, offset) = self.raw_parts();
+        Strided {
+            data,
+            shape: self.shape().to_vec(),
+            strides: strides.to_vec(),
+            offset,
+        }
+    }
+}
+
+/// Strides for reading `view` as if it had the (broadcast) shape `to`:
+/// stretched and missing dims get stride 0.
+fn strides_for(view: &Strided, to: &[usize], name: &str) -> Vec<usize> {
+    assert!(
+        broadcast_shapes(&view.shape, to).as_deref() == Some(to),
+        "{} of shape {:?} does not broadcast to the output shape {:?}",
+        name,
+        view.shape,
+        to
+    );
+    let pad = to.len() - view.shape.len();
+    (0..to.len())
+        .map(|i| {
+            if i < pad || view.shape[i - pad] == 1 {
+                0
+            } else {
+                view.strides[i - pad]
+            }
+        })
+        .collect()
+}
+
+/// Positions of one element in each of several buffers, advanced in
+/// row-major order over `shape`.
+struct Cursor<'s, const K: usize> {
+    shape: &'s [usize],
+    strides: [&'s [usize]; K],
+    indices: Vec<usize>,
+    pos: [usize; K],
+}
+
+impl<'s, const K: usize> Cursor<'s, K> {
+    /// A cursor at row-major element `start`.
+    fn new(
+        shape: &'s [usize],
+        strides: [&'s [usize]; K],
+        offsets: [usize; K],
+        start: usize,
+    ) -> Self {
+        let mut indices = vec![0; shape.len()];
+        let mut rest = start;
+        for (i, &d) in shape.iter().enumerate().rev() {
+            indices[i] = rest % d;
+            rest /= d;
+        }
+        let mut pos = offsets;
+        for (p, s) in pos.iter_mut().zip(&strides) {
+            *p += indices.iter().zip(*s).map(|(i, s)| i * s).sum::<usize>();
+        }
+        Self {
+            shape,
+            strides,
+            indices,
+            pos,
+        }
+    }
+
+    fn advance(&mut self) {
+        for dim in (0..self.shape.len()).rev() {
+            self.indices[dim] += 1;
+            if self.indices[dim] < self.shape[dim] {
+                for (p, s) in self.pos.iter_mut().zip(&self.strides) {
+                    *p += s[dim];
+                }
+                return;
+            }
+            self.indices[dim] = 0;
+            for (p, s) in self.pos.iter_mut().zip(&self.strides) {
+                *p -= (self.shape[dim] - 1) * s[dim];
+            }
+        }
+    }
+}
+
+/// Set every element of `out` to `f(a, b)` of the matching elements of
+/// `a` and `b`, which are broadcast to `out`'s shape (NumPy rules: shapes
+/// right-aligned, dims equal or 1).
+///
+/// With the `parallel` feature, outputs of at least 65536 elements laid
+/// out row-major are computed on several threads, which is why `f` must
+/// be `Sync`.
+///
+/// # Panics
+/// Panics if `a` or `b` does not broadcast to the shape of `out`.
+pub fn for_each_zip(
+    out: &mut StridedMut,
+    a: &Strided,
+    b: &Strided,
+    f: impl Fn(f32, f32) -> f32 + Sync,
+) {
+    let shape = out.shape.clone();
+    let sa = strides_for(a, &shape, "first input");
+    let sb = strides_for(b, &shape, "second input");
+    let n: usize = shape.iter().product();
+    if n == 0 {
+        return;
+    }
+    let (da, db) = (a.data, b.data);
+
+    let row_major = out.strides == Shape::new(&shape).strides();
+    if !row_major {
+        let mut cursor = Cursor::new(
+            &shape,
+            [&sa, &sb, &out.strides],
+            [a.offset, b.offset, out.offset],
+            0,
+        );
+        for _ in 0..n {
+            let [ia, ib, io] = cursor.pos;
+            out.data[io] = f(da[ia], db[ib]);
+            cursor.advance();
+        }
+        return;
+    }
+
+    // Row-major output: fill `dst`, the elements from row-major `start` on
+    let fill = |dst: &mut [f32], start: usize| {
+        let mut cursor = Cursor::new(&shape, [&sa, &sb], [a.offset, b.offset], start);
+        for v in dst {
+            let [ia, ib] = cursor.pos;
+            *v = f(da[ia], db[ib]);
+            cursor.advance();
+        }
+    };
+    let region = &mut out.data[out.offset..out.offset + n];
+    #[cfg(feature = "parallel")]
+    {
+        let threads = std::thread::available_parallelism().map_or(1, |t| t.get());
+        if n >= PARALLEL_MIN && threads > 1 {
+            let chunk = n.div_ceil(threads);
+            let fill = &fill;
+            std::thread::scope(|scope| {
+                for (k, dst) in region.chunks_mut(chunk).enumerate() {
+                    scope.spawn(move || fill(dst, k * chunk));
+                }
+            });
+            return;
+        }
+    }
+    fill(region, 0);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::random::Rng;
+
+    #[test]
+    fn test_matches_zip_map() {
+        let mut rng = Rng::new(0);
+        let a = Tensor::randn(&[3, 4], &mut rng).t();
+        let b = Tensor::randn(&[3], &mut rng);
+        let mut out = vec![0.0; 12];
+        for_each_zip(
+            &mut StridedMut::contiguous(&mut out, &[4, 3]),
+            &a.strided(),
+            &b.strided(),
+            |x, y| x * y - 1.0,
+        );
+        assert_eq!(out, a.zip_map(&b, |x, y| x * y - 1.0).to_vec());
+    }
+
+    #[test]
+    fn test_strided_output() {
+        // Write the transpose of a + b into the columns of a 2x2 buffer
+        let a = Strided::contiguous(&[1.0, 2.0], &[2]);
+        let b = Strided::new(&[10.0, 0.0, 20.0], &[2, 1], &[2, 1], 0);
+        let mut buf = [0.0; 5];
+        for_each_zip(
+            &mut StridedMut::new(&mut buf, &[2, 2], &[1, 2], 1),
+            &a,
+            &b,
+            |x, y| x + y,
+        );
+        assert_eq!(buf, [0.0, 11.0, 21.0, 12.0, 22.0]);
+    }
+
+    #[test]
+    fn test_large_output() {
+        let n = (1 << 16) * 3 + 7;
+        let data: Vec<f32> = (0..n).map(|i| i as f32).collect();
+        let a = Strided::contiguous(&data, &[n]);
+        let b = Strided::contiguous(&[0.5], &[1]);
+        let mut out = vec![0.0; n + 2];
+        for_each_zip(
+            &mut StridedMut::new(&mut out, &[n], &[1], 2),
+            &a,
+            &b,
+            |x, y| x * y,
+        );
+        assert_eq!(&out[..2], &[0.0, 0.0]);
+        assert!(
+            out[2..]
+                .iter()
+                .enumerate()
+                .all(|(i, &v)| v == i as f32 * 0.5)
+        );
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_parallel_matches_serial() {
+        // A transposed input against a broadcast row, large enough to run
+        // on threads when written row-major
+        let (rows, cols) = (PARALLEL_MIN / 64 + 3, 64);
+        let mut rng = Rng::new(0);
+        let a = Tensor::randn(&[cols, rows], &mut rng).t();
+        let b = Tensor::randn(&[cols], &mut rng);
+        let f = |x: f32, y: f32| x.mul_add(y, x.sin());
+        let mut threaded = vec![0.0; rows * cols];
+        for_each_zip(
+            &mut StridedMut::contiguous(&mut threaded, &[rows, cols]),
+            &a.strided(),
+            &b.strided(),
+            f,
+        );
+        // Column-major outputs always take the serial path
+        let mut serial = vec![0.0; rows * cols];
+        for_each_zip(
+            &mut StridedMut::new(&mut serial, &[rows, cols], &[1, rows], 0),
+            &a.strided(),
+            &b.strided(),
+            f,
+        );
+        let serial = Tensor::from_vec(serial, &[cols, rows]).t().to_vec();
+        assert_eq!(threaded, serial);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "second input of shape [2] does not broadcast to the output shape [2, 3]"
+    )]
+    fn test_rejects_incompatible_input() {
+        let mut out = [0.0; 6];
+        for_each_zip(
+            &mut StridedMut::contiguous(&mut out, &[2, 3]),
+            &Strided::contiguous(&[0.0; 3], &[3]),
+            &Strided::contiguous(&[0.0; 2], &[2]),
+            |x, _| x,
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "reaches element 6, past a buffer of 6")]
+    fn test_rejects_view_past_buffer() {
+        Strided::new(&[0.0; 6], &[2, 3], &[3, 1], 1);
+    }
+}
@@ -2,4 +2,20 @@
 //!
 //! A tensor autograd engine from scratch.
 
+pub mod augment;
+pub mod autograd;
+pub mod backend;
+mod build_info;
+pub mod debug;
+pub mod distributions;
+pub mod gp;
+pub mod kernel;
+pub mod metrics;
+pub mod nn;
+pub mod ode;
+pub mod optim;
+pub mod optimize;
+pub mod random;
 pub mod tensor;
+
+pub use build_info::{BuildInfo, build_info};
@@ -0,0 +1,308 @@
+//! Threshold curves for binary classifiers.
+//!
+//! All functions take `scores` (higher = more likely positive) and `labels`
+//! (1.0 = positive, 0.0 = negative) as 1D tensors of equal length.
+
+use crate::tensor::Tensor;
+
+/// Cumulative true/false positive counts at each distinct threshold.
+///
+/// Samples are sorted by descending score. A threshold is emitted wherever
+/// the score changes, so tied scores are counted together, matching the
+/// "predict positive if score >= threshold" rule.
+///
+/// Returns `(thresholds, tps, fps)`, ordered from the highest threshold to
+/// the lowest.
+fn binary_clf_counts(scores: &Tensor, labels: &Tensor) -> (Vec<f32>, Vec<f32>, Vec<f32>) {
+    assert_eq!(
+        scores.ndim(),
+        1,
+        "scores must be 1D, got {}D",
+        scores.ndim()
+    );
+    assert_eq!(
+        scores.shape(),
+        labels.shape(),
+        "Shape mismatch: scores {:?} vs labels {:?}",
+        scores.shape(),
+        labels.shape()
+    );
+
+    let scores = scores.to_vec();
+    let labels = labels.to_vec();
+    assert!(
+        scores.iter().all(|s| !s.is_nan()),
+        "scores must not contain NaN"
+    );
+
+    let mut order: Vec<usize> = (0..scores.len()).collect();
+    order.sort_by(|&a, &b| scores[b].total_cmp(&scores[a]));
+
+    let (mut thresholds, mut tps, mut fps) = (Vec::new(), Vec::new(), Vec::new());
+    let (mut tp, mut fp) = (0.0, 0.0);
+    for (k, &i) in order.iter().enumerate() {
+        if labels[i] != 0.0 {
+            tp += 1.0;
+        } else {
+            fp += 1.0;
+        }
+
+        // Only emit a point once all samples tied at this score are counted
+        let last_of_tie = order
+            .get(k + 1)
+            .is_none_or(|&next| scores[next] != scores[i]);
+        if last_of_tie {
+            thresholds.push(scores[i]);
+            tps.push(tp);
+            fps.push(fp);
+        }
+    }
+    (thresholds, tps, fps)
+}
+
+/// Receiver operating characteristic curve.
+///
+/// Returns `(fpr, tpr, thresholds)` as 1D tensors of equal length, ordered
+/// by decreasing threshold. Point `i` is the false and true positive rate
+/// when predicting positive for `score >= thresholds[i]`. The curve starts
+/// at (0, 0) with threshold +inf, so it always spans from (0, 0) to (1, 1).
+///
+/// # Panics
+/// - Panics if `scores` is not 1D or shapes don't match
+/// - Panics if `scores` contains NaN
+/// - Panics if `labels` doesn't contain both classes (the rates are undefined)
+///
+/// # Example
+/// ```
+/// use delta::metrics::roc_curve;
+/// use delta::tensor::Tensor;
+/// let scores = Tensor::from_vec(vec![0.1, 0.4, 0.35, 0.8], &[4]);
+/// let labels = Tensor::from_vec(vec![0.0, 0.0, 1.0, 1.0], &[4]);
+/// let (fpr, tpr, thresholds) = roc_curve(&scores, &labels);
+/// assert_eq!(fpr.to_vec(), vec![0.0, 0.0, 0.5, 0.5, 1.0]);
+/// assert_eq!(tpr.to_vec(), vec![0.0, 0.5, 0.5, 1.0, 1.0]);
+/// assert_eq!(thresholds.get(&[1]), 0.8);
+/// ```
+pub fn roc_curve(scores: &Tensor, labels: &Tensor) -> (Tensor, Tensor, Tensor) {
+    let (mut thresholds, mut tps, mut fps) = binary_clf_counts(scores, labels);
+    let pos = tps.last().copied().unwrap_or(0.0);
+    let neg = fps.last().copied().unwrap_or(0.0);
+    assert!(
+        pos > 0.0 && neg > 0.0,
+        "ROC curve requires both positive and negative labels ({} positive, {} negative)",
+        pos,
+        neg
+    );
+
+    thresholds.insert(0, f32::INFINITY);
+    tps.insert(0, 0.0);
+    fps.insert(0, 0.0);
+
+    let n = thresholds.len();
+    let fpr = fps.iter().map(|fp| fp / neg).collect();
+    let tpr = tps.iter().map(|tp| tp / pos).collect();
+    (
+        Tensor::from_vec(fpr, &[n]),
+        Tensor::from_vec(tpr, &[n]),
+        Tensor::from_vec(thresholds, &[n]),
+    )
+}
+
+/// Precision-recall curve.
+///
+/// Returns `(precision, recall, thresholds)` as 1D tensors of equal length,
+/// ordered by decreasing threshold (so recall is non-decreasing). The curve
+/// starts at recall 0 / precision 1 with threshold +inf.
+///
+/// # Panics
+/// - Panics if `scores` is not 1D or shapes don't match
+/// - Panics if `scores` contains NaN
+/// - Panics if `labels` has no positives (recall is undefined)
+///
+/// # Example
+/// ```
+/// use delta::metrics::pr_curve;
+/// use delta::tensor::Tensor;
+/// let scores = Tensor::from_vec(vec![0.1, 0.4, 0.35, 0.8], &[4]);
+/// let labels = Tensor::from_vec(vec![0.0, 0.0, 1.0, 1.0], &[4]);
+/// let (precision, recall, _) = pr_curve(&scores, &labels);
+/// assert_eq!(recall.to_vec(), vec![0.0, 0.5, 0.5, 1.0, 1.0]);
+/// assert_eq!(precision.get(&[3]), 2.0 / 3.0);
+/// ```
+pub fn pr_curve(scores: &Tensor, labels: &Tensor) -> (Tensor, Tensor, Tensor) {
+    let (mut thresholds, tps, fps) = binary_clf_counts(scores, labels);
+    let pos = tps.last().copied().unwrap_or(0.0);
+    assert!(pos > 0.0, "PR curve requires at least one positive label");
+
+    thresholds.insert(0, f32::INFINITY);
+    let mut precision = vec![1.0];
+    let mut recall = vec![0.0];
+    for (tp, fp) in tps.iter().zip(&fps) {
+        precision.push(tp / (tp + fp));
+        recall.push(tp / pos);
+    }
+
+    let n = thresholds.len();
+    (
+        Tensor::from_vec(precision, &[n]),
+        Tensor::from_vec(recall, &[n]),
+        Tensor::from_vec(thresholds, &[n]),
+    )
+}
+
+/// Area under a curve by the trapezoidal rule.
+///
+/// `x` must be monotonic (either direction); a decreasing `x` gives the
+/// same positive area as the reversed curve.
+///
+/// # Panics
+/// Panics if `x` and `y` are not 1D tensors of the same length.
+///
+/// # Example
+/// ```
+/// use delta::metrics::auc;
+/// use delta::tensor::Tensor;
+/// let x = Tensor::from_vec(vec![0.0, 0.5, 1.0], &[3]);
+/// let y = Tensor::from_vec(vec![0.0, 1.0, 1.0], &[3]);
+/// assert_eq!(auc(&x, &y), 0.75);
+/// ```
+pub fn auc(x: &Tensor, y: &Tensor) -> f32 {
+    assert_eq!(x.ndim(), 1, "auc requires 1D tensors, got {}D", x.ndim());
+    assert_eq!(
+        x.shape(),
+        y.shape(),
+        "Shape mismatch: {:?} vs {:?}",
+        x.shape(),
+        y.shape()
+    );
+
+    let (x, y) = (x.to_vec(), y.to_vec());
+    let area: f32 = x
+        .windows(2)
+        .zip(y.windows(2))
+        .map(|(xs, ys)| (xs[1] - xs[0]) * (ys[0] + ys[1]) / 2.0)
+        .sum();
+    area.abs()
+}
+
+/// Area under the ROC curve.
+///
+/// Equals the probability that a random positive is scored above a random
+/// negative (ties count half). 0.5 is chance level, 1.0 is perfect ranking.
+///
+/// # Panics
+/// Same conditions as [`roc_curve`].
+pub fn roc_auc_score(scores: &Tensor, labels: &Tensor) -> f32 {
+    let (fpr, tpr, _) = roc_curve(scores, labels);
+    auc(&fpr, &tpr)
+}
+
+/// Average precision: Σ (R_n - R_{n-1}) * P_n over the PR curve.
+///
+/// A step-wise summary of the PR curve. Unlike trapezoidal `auc` on
+/// precision/recall, it doesn't interpolate linearly between points, which
+/// would be overly optimistic.
+///
+/// # Panics
+/// Same conditions as [`pr_curve`].
+pub fn average_precision(scores: &Tensor, labels: &Tensor) -> f32 {
+    let (precision, recall, _) = pr_curve(scores, labels);
+    let (precision, recall) = (precision.to_vec(), recall.to_vec());
+    recall
+        .windows(2)
+        .zip(&precision[1..])
+        .map(|(r, p)| (r[1] - r[0]) * p)
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn example() -> (Tensor, Tensor) {
+        let scores = Tensor::from_vec(vec![0.1, 0.4, 0.35, 0.8], &[4]);
+        let labels = Tensor::from_vec(vec![0.0, 0.0, 1.0, 1.0], &[4]);
+        (scores, labels)
+    }
+
+    #[test]
+    fn test_roc_curve() {
+        let (scores, labels) = example();
+        let (fpr, tpr, thresholds) = roc_curve(&scores, &labels);
+        assert_eq!(fpr.to_vec(), vec![0.0, 0.0, 0.5, 0.5, 1.0]);
+        assert_eq!(tpr.to_vec(), vec![0.0, 0.5, 0.5, 1.0, 1.0]);
+        assert_eq!(
+            thresholds.to_vec(),
+            vec![f32::INFINITY, 0.8, 0.4, 0.35, 0.1]
+        );
+    }
+
+    #[test]
+    fn test_roc_curve_ties() {
+        // Tied scores collapse into a single point
+        let scores = Tensor::from_vec(vec![0.5, 0.5, 0.5, 0.9], &[4]);
+        let labels = Tensor::from_vec(vec![1.0, 0.0, 1.0, 0.0], &[4]);
+        let (fpr, tpr, thresholds) = roc_curve(&scores, &labels);
+        assert_eq!(thresholds.to_vec(), vec![f32::INFINITY, 0.9, 0.5]);
+        assert_eq!(fpr.to_vec(), vec![0.0, 0.5, 1.0]);
+        assert_eq!(tpr.to_vec(), vec![0.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    #[should_panic(expected = "both positive and negative")]
+    fn test_roc_curve_single_class() {
+        let scores = Tensor::from_vec(vec![0.1, 0.2], &[2]);
+        let labels = Tensor::from_vec(vec![1.0, 1.0], &[2]);
+        roc_curve(&scores, &labels);
+    }
+
+    #[test]
+    #[should_panic(expected = "Shape mismatch")]
+    fn test_roc_curve_shape_mismatch() {
+        let scores = Tensor::from_vec(vec![0.1, 0.2], &[2]);
+        let labels = Tensor::from_vec(vec![1.0, 0.0, 1.0], &[3]);
+        roc_curve(&scores, &labels);
+    }
+
+    #[test]
+    fn test_pr_curve() {
+        let (scores, labels) = example();
+        let (precision, recall, thresholds) = pr_curve(&scores, &labels);
+        assert_eq!(precision.to_vec(), vec![1.0, 1.0, 0.5, 2.0 / 3.0, 0.5]);
+        assert_eq!(recall.to_vec(), vec![0.0, 0.5, 0.5, 1.0, 1.0]);
+        assert_eq!(thresholds.shape(), &[5]);
+    }
+
+    #[test]
+    fn test_auc() {
+        let x = Tensor::from_vec(vec![0.0, 1.0], &[2]);
+        let y = Tensor::from_vec(vec![0.0, 1.0], &[2]);
+        assert_eq!(auc(&x, &y), 0.5);
+
+        // Decreasing x gives the same area
+        let x = Tensor::from_vec(vec![1.0, 0.5, 0.0], &[3]);
+        let y = Tensor::from_vec(vec![1.0, 1.0, 0.0], &[3]);
+        assert_eq!(auc(&x, &y), 0.75);
+    }
+
+    #[test]
+    fn test_roc_auc_score() {
+        let (scores, labels) = example();
+        assert_eq!(roc_auc_score(&scores, &labels), 0.75);
+
+        // Perfect and inverted rankings
+        let labels = Tensor::from_vec(vec![0.0, 0.0, 1.0, 1.0], &[4]);
+        let perfect = Tensor::from_vec(vec![0.1, 0.2, 0.3, 0.4], &[4]);
+        let inverted = Tensor::from_vec(vec![0.4, 0.3, 0.2, 0.1], &[4]);
+        assert_eq!(roc_auc_score(&perfect, &labels), 1.0);
+        assert_eq!(roc_auc_score(&inverted, &labels), 0.0);
+    }
+
+    #[test]
+    fn test_average_precision() {
+        let (scores, labels) = example();
+        // 0.5 * 1.0 + 0.0 * 0.5 + 0.5 * (2/3) + 0.0 * 0.5
+        let ap = average_precision(&scores, &labels);
+        assert!((ap - (0.5 + 1.0 / 3.0)).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,5 @@
+mod curve;
+mod running;
+
+pub use curve::{auc, average_precision, pr_curve, roc_auc_score, roc_curve};
+pub use running::RunningStat;
@@ -0,0 +1,227 @@
+/// Streaming mean/variance/min/max over a sequence of values.
+///
+/// Keeps O(1) state no matter how many values are pushed, so loss or
+/// gradient-norm statistics can be tracked across a whole training run
+/// without storing the history.
+///
+/// Uses Welford's algorithm: instead of accumulating Σx and Σx² (which
+/// cancel catastrophically when the variance is small relative to the
+/// mean), it updates the mean and the sum of squared deviations `m2`
+/// incrementally. State is kept in f64.
+///
+/// # Example
+/// ```
+/// use delta::metrics::RunningStat;
+/// let mut stat = RunningStat::new();
+/// for loss in [2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0] {
+///     stat.push(loss);
+/// }
+/// assert_eq!(stat.mean(), 5.0);
+/// assert_eq!(stat.variance(), 4.0);
+/// assert_eq!(stat.std(), 2.0);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct RunningStat {
+    count: u64,
+    mean: f64,
+    m2: f64,
+    min: f32,
+    max: f32,
+}
+
+impl RunningStat {
+    /// Create an empty accumulator.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add one value.
+    pub fn push(&mut self, x: f32) {
+        if self.count == 0 {
+            self.min = x;
+            self.max = x;
+        } else {
+            self.min = self.min.min(x);
+            self.max = self.max.max(x);
+        }
+
+        self.count += 1;
+        let x = x as f64;
+        let delta = x - self.mean;
+        self.mean += delta / self.count as f64;
+        // Uses the *updated* mean, which is what makes the update exact
+        self.m2 += delta * (x - self.mean);
+    }
+
+    /// Combine with statistics gathered separately (e.g. per worker).
+    ///
+    /// The result is the same as if every value had been pushed into a
+    /// single accumulator (Chan et al.'s pairwise update).
+    pub fn merge(&mut self, other: &RunningStat) {
+        if other.count == 0 {
+            return;
+        }
+        if self.count == 0 {
+            *self = other.clone();
+            return;
+        }
+
+        let n_a = self.count as f64;
+        let n_b = other.count as f64;
+        let n = n_a + n_b;
+        let delta = other.mean - self.mean;
+
+        self.mean += delta * n_b / n;
+        self.m2 += other.m2 + delta * delta * n_a * n_b / n;
+        self.count += other.count;
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+    }
+
+    /// Clear all accumulated state.
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Number of values pushed.
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Mean of the values, or NaN if empty.
+    pub fn mean(&self) -> f32 {
+        if self.count == 0 {
+            return f32::NAN;
+        }
+        self.mean as f32
+    }
+
+    /// Population variance (divides by n), or NaN if empty.
+    pub fn variance(&self) -> f32 {
+        if self.count == 0 {
+            return f32::NAN;
+        }
+        (self.m2 / self.count as f64) as f32
+    }
+
+    /// Sample variance (divides by n - 1), or NaN with fewer than 2 values.
+    pub fn sample_variance(&self) -> f32 {
+        if self.count < 2 {
+            return f32::NAN;
+        }
+        (self.m2 / (self.count - 1) as f64) as f32
+    }
+
+    /// Population standard deviation, or NaN if empty.
+    pub fn std(&self) -> f32 {
+        self.variance().sqrt()
+    }
+
+    /// Smallest value pushed, or NaN if empty.
+    pub fn min(&self) -> f32 {
+        if self.count == 0 {
+            return f32::NAN;
+        }
+        self.min
+    }
+
+    /// Largest value pushed, or NaN if empty.
+    pub fn max(&self) -> f32 {
+        if self.count == 0 {
+            return f32::NAN;
+        }
+        self.max
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_push() {
+        let mut stat = RunningStat::new();
+        for x in [1.0, 2.0, 3.0, 4.0] {
+            stat.push(x);
+        }
+        assert_eq!(stat.count(), 4);
+        assert_eq!(stat.mean(), 2.5);
+        assert_eq!(stat.variance(), 1.25);
+        assert!((stat.sample_variance() - 5.0 / 3.0).abs() < 1e-6);
+        assert_eq!(stat.min(), 1.0);
+        assert_eq!(stat.max(), 4.0);
+    }
+
+    #[test]
+    fn test_empty() {
+        let stat = RunningStat::new();
+        assert_eq!(stat.count(), 0);
+        assert!(stat.mean().is_nan());
+        assert!(stat.variance().is_nan());
+        assert!(stat.min().is_nan());
+        assert!(stat.max().is_nan());
+    }
+
+    #[test]
+    fn test_single_value() {
+        let mut stat = RunningStat::new();
+        stat.push(-3.0);
+        assert_eq!(stat.mean(), -3.0);
+        assert_eq!(stat.variance(), 0.0);
+        assert!(stat.sample_variance().is_nan());
+        assert_eq!(stat.min(), -3.0);
+        assert_eq!(stat.max(), -3.0);
+    }
+
+    #[test]
+    fn test_numerical_stability() {
+        // Large offset, tiny spread: the naive Σx² - n·mean² loses everything
+        let mut stat = RunningStat::new();
+        for x in [1e6 + 1.0, 1e6 + 2.0, 1e6 + 3.0] {
+            stat.push(x);
+        }
+        assert_eq!(stat.mean(), 1e6 + 2.0);
+        assert!((stat.variance() - 2.0 / 3.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_merge() {
+        let values = [0.5, 1.5, -2.0, 3.0, 8.0, 13.0, 21.0];
+        let mut all = RunningStat::new();
+        values.iter().for_each(|&x| all.push(x));
+
+        let (mut a, mut b) = (RunningStat::new(), RunningStat::new());
+        values[..3].iter().for_each(|&x| a.push(x));
+        values[3..].iter().for_each(|&x| b.push(x));
+        a.merge(&b);
+
+        assert_eq!(a.count(), all.count());
+        assert!((a.mean() - all.mean()).abs() < 1e-6);
+        assert!((a.variance() - all.variance()).abs() < 1e-4);
+        assert_eq!(a.min(), -2.0);
+        assert_eq!(a.max(), 21.0);
+    }
+
+    #[test]
+    fn test_merge_empty() {
+        let mut a = RunningStat::new();
+        let mut b = RunningStat::new();
+        b.push(2.0);
+
+        a.merge(&RunningStat::new());
+        assert_eq!(a.count(), 0);
+
+        a.merge(&b);
+        assert_eq!(a.count(), 1);
+        assert_eq!(a.mean(), 2.0);
+    }
+
+    #[test]
+    fn test_reset() {
+        let mut stat = RunningStat::new();
+        stat.push(1.0);
+        stat.reset();
+        assert_eq!(stat.count(), 0);
+        assert!(stat.mean().is_nan());
+    }
+}
@@ -0,0 +1,911 @@
+//! Scaled dot-product attention, dense and block-sparse.
+//!
+//! Attention lets every query position mix the values of the key
+//! positions, weighted by `softmax(q kᵀ / √d)`. Dense attention costs
+//! time and memory quadratic in the sequence length; [`BlockSparse`]
+//! restricts each query to a local window of blocks plus a few global
+//! tokens, so the cost grows linearly and long sequences stay tractable
+//! on a CPU.
+//!
+//! For autoregressive decoding, [`causal_attention`] takes the position
+//! of the first query, and a [`KvCache`] accumulates the keys and values
+//! of past steps: decoding one token at a time gives the same outputs as
+//! running the whole sequence at once. Combine with
+//! [`RotaryEmbedding`](crate::nn::RotaryEmbedding), which takes the same
+//! position offset.
+//!
+//! Masks are additive: 0 where a query may see a key, [`MASK_FILL`]
+//! where it may not. [`causal_mask`] and [`padding_mask`] build the usual
+//! ones, [`combine_masks`] merges them, and [`masked_attention`] applies
+//! one. Masks broadcast to the scores by the NumPy rules
+//! ([`broadcast_mask`]); [`expand_padding_mask`] lines a per-sequence
+//! mask up with the batch of scores that have heads.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::tensor::sparse::Csr;
+use crate::tensor::{Storage, Tensor};
+
+/// `softmax(q kᵀ / √d) v` over the last two dimensions.
+///
+/// `q` is `[..., n, d]`, `k` is `[..., m, d]` and `v` is `[..., m, dv]`,
+/// with the same leading (batch, head) dimensions; the result is
+/// `[..., n, dv]`.
+///
+/// # Panics
+/// Panics if the shapes do not fit together.
+///
+/// # Example
+/// ```
+/// use delta::nn::attention::scaled_dot_product_attention;
+/// use delta::tensor::Tensor;
+///
+/// // Two keys, the query only matches the second
+/// let q = Tensor::from_vec(vec![0.0, 10.0], &[1, 2]);
+/// let k = Tensor::from_vec(vec![1.0, 0.0, 0.0, 1.0], &[2, 2]);
+/// let v = Tensor::from_vec(vec![-1.0, 1.0], &[2, 1]);
+/// let out = scaled_dot_product_attention(&q, &k, &v);
+/// assert!((out.get(&[0, 0]) - 1.0).abs() < 1e-2);
+/// ```
+pub fn scaled_dot_product_attention(q: &Tensor, k: &Tensor, v: &Tensor) -> Tensor {
+    attend(q, k, v, None)
+}
+
+/// Causal attention for queries at positions `offset..offset + n` over
+/// keys at positions `0..m`: each query sees the keys up to its own
+/// position. Shapes as for [`scaled_dot_product_attention`].
+///
+/// With `offset = 0` and `m = n` this is ordinary masked self-attention
+/// over a whole sequence. When decoding step by step, pass the position of
+/// the new tokens and the keys and values of every position so far (see
+/// [`KvCache`]); the result is bitwise the same as the corresponding rows
+/// of the full-sequence evaluation.
+///
+/// # Panics
+/// - Panics if the shapes do not fit together
+/// - Panics if there are fewer than `offset + n` keys
+///
+/// # Example
+/// ```
+/// use delta::nn::attention::causal_attention;
+/// use delta::tensor::Tensor;
+///
+/// let x = Tensor::from_vec(vec![1.0, 0.0, 0.0, 1.0, 1.0, 1.0], &[3, 2]);
+/// let out = causal_attention(&x, &x, &x, 0);
+/// // The first position only sees itself
+/// assert_eq!(out.to_vec()[..2], [1.0, 0.0]);
+/// ```
+pub fn causal_attention(q: &Tensor, k: &Tensor, v: &Tensor, offset: usize) -> Tensor {
+    let nd = q.ndim();
+    let (n, m) = (q.shape()[nd.max(2) - 2], k.shape()[k.ndim().max(2) - 2]);
+    assert!(
+        offset + n <= m,
+        "causal attention for queries at {}..{} needs keys for every position up to them, got {}",
+        offset,
+        offset + n,
+        m
+    );
+    attend(q, k, v, Some(&causal_mask(n, m, offset)))
+}
+
+/// `softmax(q kᵀ / √d + mask) v`: attention with an additive `mask` on
+/// the scores `[..., n, m]`, broadcast as by [`broadcast_mask`]. Shapes
+/// as for [`scaled_dot_product_attention`].
+///
+/// Build the mask with [`causal_mask`], [`padding_mask`] and
+/// [`combine_masks`]; any other additive bias works too. The mask is not
+/// differentiated.
+///
+/// # Panics
+/// - Panics if the shapes do not fit together
+/// - Panics if the mask cannot be broadcast to the scores
+///
+/// # Example
+/// ```
+/// use delta::nn::attention::{combine_masks, causal_mask, masked_attention, padding_mask};
+/// use delta::random::Rng;
+/// use delta::tensor::Tensor;
+///
+/// // Two sequences of lengths 3 and 2, padded to 3
+/// let x = Tensor::randn(&[2, 3, 4], &mut Rng::new(0));
+/// let mask = combine_masks(&[&causal_mask(3, 3, 0), &padding_mask(&[3, 2], 3)], &[2, 3, 3]);
+/// let out = masked_attention(&x, &x, &x, &mask);
+/// assert_eq!(out.shape(), &[2, 3, 4]);
+/// ```
+pub fn masked_attention(q: &Tensor, k: &Tensor, v: &Tensor, mask: &Tensor) -> Tensor {
+    attend(q, k, v, Some(mask))
+}
+
+/// The score mask value of hidden positions.
+///
+/// Finite, so a query that sees no key at all (a padding query) gets
+/// uniform weights rather than NaN; large enough that `exp` of a hidden
+/// score is exactly 0, so visible positions get the same weights as if
+/// the hidden ones were not there.
+pub const MASK_FILL: f32 = -1e9;
+
+/// The additive causal mask `[n, m]` for queries at positions
+/// `offset..offset + n` over keys at positions `0..m`: 0 where the key is
+/// at or before the query, [`MASK_FILL`] after.
+///
+/// # Example
+/// ```
+/// use delta::nn::attention::{MASK_FILL, causal_mask};
+///
+/// let mask = causal_mask(2, 2, 0);
+/// assert_eq!(mask.to_vec(), vec![0.0, MASK_FILL, 0.0, 0.0]);
+/// ```
+pub fn causal_mask(n: usize, m: usize, offset: usize) -> Tensor {
+    let mask = (0..n * m)
+        .map(|i| {
+            if i % m > offset + i / m {
+                MASK_FILL
+            } else {
+                0.0
+            }
+        })
+        .collect();
+    Tensor::from_vec(mask, &[n, m])
+}
+
+/// The additive padding mask `[batch, 1, m]` for a batch of key
+/// sequences padded to `m` positions: 0 at the first `lengths[b]`
+/// positions of sequence `b`, [`MASK_FILL`] at its padding. The middle
+/// dimension broadcasts over the queries of scores `[batch, n, m]`; for
+/// scores with heads, see [`expand_padding_mask`].
+///
+/// # Panics
+/// Panics if a length is more than `m`.
+///
+/// # Example
+/// ```
+/// use delta::nn::attention::{MASK_FILL, padding_mask};
+///
+/// let mask = padding_mask(&[2, 1], 2);
+/// assert_eq!(mask.shape(), &[2, 1, 2]);
+/// assert_eq!(mask.to_vec(), vec![0.0, 0.0, 0.0, MASK_FILL]);
+/// ```
+pub fn padding_mask(lengths: &[usize], m: usize) -> Tensor {
+    let mask = lengths
+        .iter()
+        .flat_map(|&len| {
+            assert!(
+                len <= m,
+                "padding_mask lengths must be at most {}, got {}",
+                m,
+                len
+            );
+            (0..m).map(move |j| if j < len { 0.0 } else { MASK_FILL })
+        })
+        .collect();
+    Tensor::from_vec(mask, &[lengths.len(), 1, m])
+}
+
+/// `mask` broadcast to attention scores of `shape` `[..., n, m]`, by
+/// the NumPy rules: dimensions are aligned from the right, and each one
+/// of the mask is that of the scores or 1.
+///
+/// So a `[n, m]` mask applies to every sequence and head, a `[heads, n,
+/// m]` bias to its head in every sequence, and a `[batch, 1, m]`
+/// [`padding_mask`] to every query of its sequence in scores `[batch, n,
+/// m]`. Scores with heads put a dimension between the batch and the
+/// queries; broadcast a per-sequence mask to them with
+/// [`expand_padding_mask`].
+///
+/// # Panics
+/// Panics if `mask` cannot be broadcast to `shape`.
+///
+/// # Example
+/// ```
+/// use delta::nn::attention::broadcast_mask;
+/// use delta::tensor::Tensor;
+///
+/// // Two sequences, three heads, four queries, five keys: a bias per head
+/// let bias = Tensor::zeros(&[3, 4, 5]);
+/// assert_eq!(broadcast_mask(&bias, &[2, 3, 4, 5]).shape(), &[2, 3, 4, 5]);
+/// ```
+pub fn broadcast_mask(mask: &Tensor, shape: &[usize]) -> Tensor {
+    let (k, nd) = (mask.ndim(), shape.len());
+    assert!(
+        k <= nd
+            && mask
+                .shape()
+                .iter()
+                .zip(&shape[nd - k..])
+                .all(|(&a, &s)| a == s || a == 1),
+        "Cannot broadcast mask {:?} to attention scores {:?}",
+        mask.shape(),
+        shape
+    );
+    mask.detach().broadcast_to(shape)
+}
+
+/// A per-sequence key mask broadcast to attention scores of `shape`
+/// `[batch, ..., n, m]`, its first dimension aligned with the batch
+/// rather than from the right: a key-padding mask `[batch, m]`, or a
+/// [`padding_mask`] `[batch, 1, m]`, hides the padded keys of each
+/// sequence from every head and query of that sequence.
+///
+/// # Panics
+/// Panics if `mask` is neither `[batch, m]` nor `[batch, 1, m]` for the
+/// batch and key count of `shape`, or `shape` has fewer than three
+/// dimensions.
+///
+/// # Example
+/// ```
+/// use delta::nn::attention::{MASK_FILL, expand_padding_mask, padding_mask};
+///
+/// // Two sequences of lengths 5 and 3, two heads, two queries
+/// let mask = expand_padding_mask(&padding_mask(&[5, 3], 5), &[2, 2, 2, 5]);
+/// assert_eq!(mask.shape(), &[2, 2, 2, 5]);
+/// assert_eq!(mask.to_vec()[35..], [0.0, 0.0, 0.0, MASK_FILL, MASK_FILL]);
+/// ```
+pub fn expand_padding_mask(mask: &Tensor, shape: &[usize]) -> Tensor {
+    let nd = shape.len();
+    let fits = |b: usize, m: usize| nd >= 3 && b == shape[0] && m == shape[nd - 1];
+    assert!(
+        match *mask.shape() {
+            [b, m] | [b, 1, m] => fits(b, m),
+            _ => false,
+        },
+        "expand_padding_mask expects a mask [{}, m] or [{}, 1, m] for attention scores [batch, ..., n, m], got {:?} for {:?}",
+        shape.first().copied().unwrap_or(0),
+        shape.first().copied().unwrap_or(0),
+        mask.shape(),
+        shape
+    );
+    let mut aligned = vec![1; nd];
+    aligned[0] = shape[0];
+    aligned[nd - 1] = shape[nd - 1];
+    mask.detach().reshape(&aligned).broadcast_to(shape)
+}
+
+/// The sum of additive `masks`, each broadcast to the scores `shape` as
+/// by [`broadcast_mask`], and floored at [`MASK_FILL`]: a position hidden
+/// by any mask is hidden, with the same fill however many hide it. With
+/// no masks, zeros.
+///
+/// # Panics
+/// Panics if a mask cannot be broadcast to `shape`.
+///
+/// # Example
+/// ```
+/// use delta::nn::attention::{MASK_FILL, causal_mask, combine_masks, padding_mask};
+///
+/// let mask = combine_masks(&[&causal_mask(2, 2, 0), &padding_mask(&[1], 2)], &[1, 2, 2]);
+/// assert_eq!(mask.to_vec(), vec![0.0, MASK_FILL, 0.0, MASK_FILL]);
+/// ```
+pub fn combine_masks(masks: &[&Tensor], shape: &[usize]) -> Tensor {
+    let mut sum = vec![0.0; shape.iter().product()];
+    for mask in masks {
+        for (s, m) in sum.iter_mut().zip(broadcast_mask(mask, shape).to_vec()) {
+            *s += m;
+        }
+    }
+    Tensor::from_vec(sum.into_iter().map(|s| s.max(MASK_FILL)).collect(), shape)
+}
+
+/// `softmax(q kᵀ / √d + mask) v`, the mask broadcast to the scores.
+fn attend(q: &Tensor, k: &Tensor, v: &Tensor, mask: Option<&Tensor>) -> Tensor {
+    let nd = q.ndim();
+    assert!(
+        nd >= 2 && k.ndim() == nd && v.ndim() == nd && q.shape()[nd - 1] == k.shape()[nd - 1],
+        "attention expects q [..., n, d], k [..., m, d] and v [..., m, dv], got {:?}, {:?} and {:?}",
+        q.shape(),
+        k.shape(),
+        v.shape()
+    );
+    let d = q.shape()[nd - 1];
+    let scores = q
+        .batch_matmul(&k.matrix_transpose())
+        .scalar_mul(1.0 / (d as f32).sqrt());
+    let scores = match mask {
+        Some(mask) => scores.add(&broadcast_mask(mask, scores.shape())),
+        None => scores,
+    };
+    scores.softmax(nd - 1).batch_matmul(v)
+}
+
+/// Keys and values of the positions decoded so far.
+///
+/// Each step, [`KvCache::append`] adds the keys and values of the new
+/// positions, `[..., n, d]`, along the sequence dimension and returns
+/// everything cached, ready for [`causal_attention`] with the number of
+/// positions before the step as offset.
+///
+/// The cache stores plain values, cut from the graph: it is meant for
+/// inference, where nothing is trained through past steps.
+///
+/// # Example
+/// ```
+/// use delta::nn::attention::{KvCache, causal_attention};
+/// use delta::random::Rng;
+/// use delta::tensor::Tensor;
+///
+/// let mut rng = Rng::new(0);
+/// let x = Tensor::randn(&[4, 8], &mut rng);
+/// let full = causal_attention(&x, &x, &x, 0).to_vec();
+///
+/// let mut cache = KvCache::new();
+/// for t in 0..4 {
+///     let token = Tensor::from_vec(x.to_vec()[t * 8..(t + 1) * 8].to_vec(), &[1, 8]);
+///     let offset = cache.len();
+///     let (k, v) = cache.append(&token, &token);
+///     let out = causal_attention(&token, &k, &v, offset);
+///     assert_eq!(out.to_vec(), full[t * 8..(t + 1) * 8]);
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct KvCache {
+    /// Leading dimensions, key width and value width, set by the first
+    /// append
+    dims: Option<(Vec<usize>, usize, usize)>,
+    keys: Rows,
+    values: Rows,
+    len: usize,
+}
+
+/// Rows `[lead, len, w]` kept in a buffer `[lead, capacity, w]`, so that
+/// appending writes the new rows in place.
+#[derive(Debug, Clone, Default)]
+struct Rows {
+    data: Rc<Storage>,
+    capacity: usize,
+}
+
+impl Rows {
+    /// Write `new` `[lead, n, w]` after the first `len` rows of each of the
+    /// `lead` blocks, doubling the capacity when it runs out.
+    fn append(&mut self, new: &[f32], lead: usize, len: usize, n: usize, w: usize) {
+        if len + n > self.capacity {
+            let capacity = (2 * self.capacity).max(len + n);
+            let old = self.data.as_slice();
+            let mut data = vec![0.0; lead * capacity * w];
+            for l in 0..lead {
+                data[l * capacity * w..][..len * w]
+                    .copy_from_slice(&old[l * self.capacity * w..][..len * w]);
+            }
+            self.data = Rc::new(Storage::from_vec(data));
+            self.capacity = capacity;
+        }
+        // In place, unless a view from an earlier append is still alive
+        let data = Rc::make_mut(&mut self.data).as_mut_slice();
+        for l in 0..lead {
+            data[(l * self.capacity + len) * w..][..n * w]
+                .copy_from_slice(&new[l * n * w..][..n * w]);
+        }
+    }
+
+    /// The first `len` rows of each block, shaped `lead ++ [len, w]`, as a
+    /// view of the buffer.
+    fn view(&self, lead: &[usize], len: usize, w: usize) -> Tensor {
+        let mut strides = vec![w, 1];
+        let mut stride = self.capacity * w;
+        for &dim in lead.iter().rev() {
+            strides.insert(0, stride);
+            stride *= dim;
+        }
+        Tensor::from_storage(Rc::clone(&self.data), &[lead, &[len, w]].concat(), strides)
+    }
+}
+
+impl KvCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of positions cached.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Add keys `[..., n, d]` and values `[..., n, dv]` for `n` new
+    /// positions, and return the keys and values of every cached position,
+    /// `[..., len, d]` and `[..., len, dv]`.
+    ///
+    /// The cache grows its buffers geometrically and writes the new
+    /// positions in place, so a step costs the size of the new keys and
+    /// values rather than of the whole cache. The returned tensors are
+    /// views of those buffers: one kept alive until the next append makes
+    /// that append copy the cache once.
+    ///
+    /// # Panics
+    /// - Panics if `k` and `v` do not have the same leading dimensions and
+    ///   number of positions
+    /// - Panics if they do not match the shapes appended before
+    pub fn append(&mut self, k: &Tensor, v: &Tensor) -> (Tensor, Tensor) {
+        let nd = k.ndim();
+        assert!(
+            nd >= 2 && v.ndim() == nd && k.shape()[..nd - 1] == v.shape()[..nd - 1],
+            "KvCache expects keys [..., n, d] and values [..., n, dv], got {:?} and {:?}",
+            k.shape(),
+            v.shape()
+        );
+        let lead = k.shape()[..nd - 2].to_vec();
+        let (d, dv) = (k.shape()[nd - 1], v.shape()[nd - 1]);
+        let dims = (lead.clone(), d, dv);
+        let cached = self.dims.get_or_insert_with(|| dims.clone());
+        assert!(
+            *cached == dims,
+            "KvCache was started with leading dimensions {:?}, key width {} and value width {}, got keys {:?} and values {:?}",
+            cached.0,
+            cached.1,
+            cached.2,
+            k.shape(),
+            v.shape()
+        );
+
+        let (batches, n) = (lead.iter().product(), k.shape()[nd - 2]);
+        self.keys.append(&k.to_vec(), batches, self.len, n, d);
+        self.values.append(&v.to_vec(), batches, self.len, n, dv);
+        self.len += n;
+        (
+            self.keys.view(&lead, self.len, d),
+            self.values.view(&lead, self.len, dv),
+        )
+    }
+
+    /// Forget every cached position.
+    pub fn clear(&mut self) {
+        *self = Self::new();
+    }
+}
+
+/// A block-sparse attention pattern: local windows plus global tokens.
+///
+/// The sequence is cut into blocks of `block_size` positions. A query
+/// attends to the keys in its own block and in the `window` blocks on
+/// each side; the first `global_tokens` positions attend to, and are
+/// attended by, every position, to carry information across the whole
+/// sequence (as in BigBird and Longformer). With `causal`, no query sees
+/// a later key.
+///
+/// Only the allowed scores are computed, with the sparse kernels of
+/// [`Csr`], so the cost is linear in the sequence length for a fixed
+/// window. With a window covering every block this is dense attention.
+/// The pattern itself is built row by row from the allowed key ranges,
+/// in time proportional to its entries, and kept for the next call with
+/// the same lengths.
+///
+/// # Example
+/// ```
+/// use delta::nn::attention::BlockSparse;
+/// use delta::random::Rng;
+/// use delta::tensor::Tensor;
+///
+/// let pattern = BlockSparse::new(16).window(1).global_tokens(2);
+/// let mut rng = Rng::new(0);
+/// let x = Tensor::randn(&[512, 8], &mut rng);
+/// let out = pattern.attention(&x, &x, &x);
+/// assert_eq!(out.shape(), &[512, 8]);
+/// assert!(pattern.pattern(512, 512).density() < 0.11);
+/// ```
+#[derive(Debug, Clone)]
+pub struct BlockSparse {
+    block_size: usize,
+    window: usize,
+    global_tokens: usize,
+    causal: bool,
+    /// The pattern of the last call, for its `(n, m)`
+    cache: RefCell<Option<Csr>>,
+}
+
+impl PartialEq for BlockSparse {
+    fn eq(&self, other: &Self) -> bool {
+        (
+            self.block_size,
+            self.window,
+            self.global_tokens,
+            self.causal,
+        ) == (
+            other.block_size,
+            other.window,
+            other.global_tokens,
+            other.causal,
+        )
+    }
+}
+
+impl BlockSparse {
+    /// Blocks of `block_size` positions, each attending only to itself,
+    /// with no global tokens and no causal mask.
+    ///
+    /// # Panics
+    /// Panics if `block_size` is 0.
+    pub fn new(block_size: usize) -> Self {
+        assert!(block_size > 0, "block_size must be positive");
+        Self {
+            block_size,
+            window: 0,
+            global_tokens: 0,
+            causal: false,
+            cache: RefCell::new(None),
+        }
+    }
+
+    /// Number of neighbouring blocks on each side a query also sees.
+    pub fn window(mut self, blocks: usize) -> Self {
+        self.window = blocks;
+        *self.cache.get_mut() = None;
+        self
+    }
+
+    /// Number of leading positions that attend and are attended globally.
+    pub fn global_tokens(mut self, n: usize) -> Self {
+        self.global_tokens = n;
+        *self.cache.get_mut() = None;
+        self
+    }
+
+    /// Whether queries are kept from seeing later keys.
+    pub fn causal(mut self, causal: bool) -> Self {
+        self.causal = causal;
+        *self.cache.get_mut() = None;
+        self
+    }
+
+    /// Whether query position `query` may attend to key position `key`.
+    pub fn allows(&self, query: usize, key: usize) -> bool {
+        if self.causal && key > query {
+            return false;
+        }
+        let global = query < self.global_tokens || key < self.global_tokens;
+        global || (query / self.block_size).abs_diff(key / self.block_size) <= self.window
+    }
+
+    /// The allowed `(query, key)` pairs for `n` queries and `m` keys.
+    pub fn pattern(&self, n: usize, m: usize) -> Csr {
+        let mut cache = self.cache.borrow_mut();
+        if let Some(pattern) = cache.as_ref().filter(|p| (p.rows(), p.cols()) == (n, m)) {
+            return pattern.clone();
+        }
+        let (bs, global) = (self.block_size, self.global_tokens.min(m));
+        let mut row_ptr = vec![0];
+        let mut col_idx = Vec::new();
+        for query in 0..n {
+            let end = if self.causal { m.min(query + 1) } else { m };
+            if query < self.global_tokens {
+                col_idx.extend(0..end);
+            } else {
+                // The global keys, then the window of blocks around the
+                // query's own, merged where they overlap
+                let block = query / bs;
+                let lo = block.saturating_sub(self.window) * bs;
+                let hi = block.saturating_add(self.window + 1).saturating_mul(bs);
+                let (lo, hi) = (lo.max(global).min(end), hi.min(end));
+                col_idx.extend(0..global.min(end));
+                col_idx.extend(lo..hi.max(lo));
+            }
+            row_ptr.push(col_idx.len());
+        }
+        let nnz = col_idx.len();
+        let pattern = Csr::new(n, m, row_ptr, col_idx, Tensor::zeros(&[nnz]));
+        *cache = Some(pattern.clone());
+        pattern
+    }
+
+    /// Attention restricted to the pattern, for one head: `q` `[n, d]`,
+    /// `k` `[m, d]` and `v` `[m, dv]` give `[n, dv]`. A query with no
+    /// allowed key gets zeros.
+    ///
+    /// Differentiable with respect to `q`, `k` and `v`.
+    ///
+    /// # Panics
+    /// Panics if the shapes do not fit together.
+    pub fn attention(&self, q: &Tensor, k: &Tensor, v: &Tensor) -> Tensor {
+        assert!(
+            q.ndim() == 2
+                && k.ndim() == 2
+                && v.ndim() == 2
+                && q.shape()[1] == k.shape()[1]
+                && k.shape()[0] == v.shape()[0],
+            "block-sparse attention expects q [n, d], k [m, d] and v [m, dv], got {:?}, {:?} and {:?}",
+            q.shape(),
+            k.shape(),
+            v.shape()
+        );
+        let d = q.shape()[1];
+        let pattern = self.pattern(q.shape()[0], k.shape()[0]);
+        let scores = pattern
+            .sampled_matmul(q, k)
+            .scalar_mul(1.0 / (d as f32).sqrt());
+        pattern.with_values(scores).softmax_rows().matmul(v)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::autograd::testing::{assert_tensors_close, check_grad};
+    use crate::random::Rng;
+
+    fn qkv(n: usize, d: usize, seed: u64) -> [Tensor; 3] {
+        let mut rng = Rng::new(seed);
+        [
+            Tensor::randn(&[n, d], &mut rng),
+            Tensor::randn(&[n, d], &mut rng),
+            Tensor::randn(&[n, d], &mut rng),
+        ]
+    }
+
+    #[test]
+    fn test_dense_rows_are_convex_combinations() {
+        let [q, k, _] = qkv(4, 3, 1);
+        let ones = Tensor::from_vec(vec![1.0; 4], &[4, 1]);
+        let out = scaled_dot_product_attention(&q, &k, &ones);
+        assert_tensors_close(&out, &Tensor::from_vec(vec![1.0; 4], &[4, 1]), 1e-5);
+    }
+
+    #[test]
+    fn test_dense_batched_matches_per_matrix() {
+        let [q, k, v] = qkv(6, 2, 2);
+        let [q2, k2, v2] = [&q, &k, &v].map(|t| t.reshape(&[1, 6, 2]));
+        let batched = scaled_dot_product_attention(&q2, &k2, &v2);
+        assert_tensors_close(
+            &batched.reshape(&[6, 2]),
+            &scaled_dot_product_attention(&q, &k, &v),
+            1e-5,
+        );
+    }
+
+    #[test]
+    fn test_full_window_is_dense() {
+        let [q, k, v] = qkv(10, 4, 3);
+        let sparse = BlockSparse::new(3).window(4).attention(&q, &k, &v);
+        assert_tensors_close(&sparse, &scaled_dot_product_attention(&q, &k, &v), 1e-5);
+    }
+
+    #[test]
+    fn test_matches_masked_dense_attention() {
+        let pattern = BlockSparse::new(2).window(1).global_tokens(1).causal(true);
+        let [q, k, v] = qkv(9, 3, 4);
+        let mask: Vec<f32> = (0..81)
+            .map(|i| {
+                if pattern.allows(i / 9, i % 9) {
+                    0.0
+                } else {
+                    -1e9
+                }
+            })
+            .collect();
+        let scores = q.matmul(&k.t()).scalar_mul(1.0 / 3f32.sqrt());
+        let reference = scores
+            .add(&Tensor::from_vec(mask, &[9, 9]))
+            .softmax(1)
+            .matmul(&v);
+        assert_tensors_close(&pattern.attention(&q, &k, &v), &reference, 1e-5);
+    }
+
+    #[test]
+    fn test_pattern() {
+        let pattern = BlockSparse::new(2).window(0).global_tokens(1);
+        // Own block, plus position 0 both ways
+        assert!(pattern.allows(4, 5) && pattern.allows(5, 0) && pattern.allows(0, 7));
+        assert!(!pattern.allows(3, 4));
+        assert!(!pattern.clone().causal(true).allows(4, 5));
+        // Per row: own block (2) plus the global token, except in row 0..2
+        assert_eq!(pattern.pattern(8, 8).nnz(), 8 + 2 + 3 * 6);
+
+        // Built from the key ranges, the pattern is exactly what allows
+        // admits, whatever the lengths
+        for causal in [false, true] {
+            for (window, global) in [(0, 0), (1, 2), (2, 5), (9, 1)] {
+                let p = BlockSparse::new(3)
+                    .window(window)
+                    .global_tokens(global)
+                    .causal(causal);
+                for (n, m) in [(10, 10), (7, 11), (11, 4), (1, 3)] {
+                    let built = p.pattern(n, m);
+                    let reference = Csr::from_pattern(n, m, |q, k| p.allows(q, k));
+                    assert_eq!(built.row_ptr(), reference.row_ptr());
+                    assert_eq!(built.col_idx(), reference.col_idx());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_gradients() {
+        let pattern = BlockSparse::new(2).window(1).global_tokens(1);
+        check_grad(|t| pattern.attention(&t[0], &t[1], &t[2]), &qkv(6, 2, 5));
+        check_grad(
+            |t| scaled_dot_product_attention(&t[0], &t[1], &t[2]),
+            &qkv(3, 2, 6),
+        );
+        check_grad(|t| causal_attention(&t[0], &t[1], &t[2], 0), &qkv(4, 2, 7));
+    }
+
+    #[test]
+    fn test_causal_matches_masked_dense() {
+        let [q, k, v] = qkv(5, 3, 8);
+        let mask: Vec<f32> = (0..25)
+            .map(|i| if i % 5 > i / 5 { -1e9 } else { 0.0 })
+            .collect();
+        let reference = q
+            .matmul(&k.t())
+            .scalar_mul(1.0 / 3f32.sqrt())
+            .add(&Tensor::from_vec(mask, &[5, 5]))
+            .softmax(1)
+            .matmul(&v);
+        assert_tensors_close(&causal_attention(&q, &k, &v, 0), &reference, 1e-5);
+    }
+
+    #[test]
+    fn test_causal_offset_chunks_match_full() {
+        // Prefill three positions, then a chunk of two
+        let [q, k, v] = qkv(5, 3, 9);
+        let full = causal_attention(&q, &k, &v, 0).to_vec();
+        let rows = |x: &Tensor, r: std::ops::Range<usize>| {
+            Tensor::from_vec(x.to_vec()[r.start * 3..r.end * 3].to_vec(), &[r.len(), 3])
+        };
+        let mut cache = KvCache::new();
+        let (k3, v3) = cache.append(&rows(&k, 0..3), &rows(&v, 0..3));
+        let head = causal_attention(&rows(&q, 0..3), &k3, &v3, 0);
+        assert_eq!(head.to_vec(), full[..9]);
+        let (k5, v5) = cache.append(&rows(&k, 3..5), &rows(&v, 3..5));
+        assert_eq!(k5.to_vec(), k.to_vec());
+        assert_eq!(v5.to_vec(), v.to_vec());
+        let tail = causal_attention(&rows(&q, 3..5), &k5, &v5, 3);
+        assert_eq!(tail.to_vec(), full[9..]);
+    }
+
+    #[test]
+    fn test_kv_cache_grows_in_place() {
+        // Two heads, one position per step
+        let step = |t: usize| Tensor::from_vec(vec![t as f32, -(t as f32)], &[2, 1, 1]);
+        let mut cache = KvCache::new();
+        for t in 0..5 {
+            cache.append(&step(t), &step(t));
+        }
+        assert_eq!(cache.keys.capacity, 8);
+        let before = Rc::as_ptr(&cache.keys.data);
+        let (k, _) = cache.append(&step(5), &step(5));
+        assert_eq!(Rc::as_ptr(&cache.keys.data), before);
+        assert_eq!(k.shape(), &[2, 6, 1]);
+        assert_eq!(
+            k.to_vec(),
+            vec![
+                0.0, 1.0, 2.0, 3.0, 4.0, 5.0, 0.0, -1.0, -2.0, -3.0, -4.0, -5.0
+            ]
+        );
+        // A view kept across an append still sees its own positions
+        cache.append(&step(6), &step(6));
+        assert_eq!(k.shape(), &[2, 6, 1]);
+        assert_eq!(k.to_vec()[5], 5.0);
+    }
+
+    #[test]
+    fn test_kv_cache_views_display_their_rows() {
+        let step = |t: usize| Tensor::from_vec(vec![t as f32, -(t as f32)], &[2, 1, 1]);
+        let mut cache = KvCache::new();
+        for t in 0..4 {
+            cache.append(&step(t), &step(t));
+        }
+        // Past the capacity of 4: the buffer grows, and its rows are
+        // strided by the new capacity rather than the length
+        let (k, _) = cache.append(&step(4), &step(4));
+        assert_eq!(cache.keys.capacity, 8);
+        let printed = format!("{}", k);
+        assert_eq!(
+            printed,
+            format!("{}", Tensor::from_vec(k.to_vec(), k.shape()))
+        );
+        assert!(printed.contains("[-4.0000]]"));
+    }
+
+    #[test]
+    fn test_kv_cache_rejects_mismatched_shapes() {
+        let mut cache = KvCache::new();
+        cache.append(&Tensor::zeros(&[2, 1, 4]), &Tensor::zeros(&[2, 1, 3]));
+        assert_eq!(cache.len(), 1);
+        let err = std::panic::catch_unwind(move || {
+            cache.append(&Tensor::zeros(&[1, 1, 4]), &Tensor::zeros(&[1, 1, 3]));
+        });
+        assert!(err.is_err());
+        let err = std::panic::catch_unwind(|| {
+            let [q, k, v] = qkv(3, 2, 0);
+            causal_attention(&q, &k, &v, 1);
+        });
+        assert!(err.is_err());
+    }
+
+    #[test]
+    fn test_masked_matches_causal() {
+        let [q, k, v] = qkv(5, 3, 10);
+        assert_eq!(
+            masked_attention(&q, &k, &v, &causal_mask(5, 5, 0)).to_vec(),
+            causal_attention(&q, &k, &v, 0).to_vec()
+        );
+        check_grad(
+            |t| masked_attention(&t[0], &t[1], &t[2], &causal_mask(4, 4, 0)),
+            &qkv(4, 2, 11),
+        );
+    }
+
+    #[test]
+    fn test_padding_ignores_padded_keys() {
+        let mut rng = Rng::new(12);
+        // The second sequence is 2 tokens padded to 4
+        let x = Tensor::randn(&[2, 4, 3], &mut rng);
+        let heads = x.reshape(&[2, 1, 4, 3]).broadcast_to(&[2, 2, 4, 3]);
+        let mask = expand_padding_mask(&padding_mask(&[4, 2], 4), &[2, 2, 4, 4]);
+        let out = masked_attention(&heads, &heads, &heads, &mask).to_vec();
+
+        let short = Tensor::from_vec(x.to_vec()[12..18].to_vec(), &[2, 3]);
+        let alone = scaled_dot_product_attention(&short, &short, &short);
+        // Both heads, first two queries of the second sequence
+        assert_tensors_close(
+            &Tensor::from_vec(out[24..30].to_vec(), &[2, 3]),
+            &alone,
+            1e-5,
+        );
+        assert_tensors_close(
+            &Tensor::from_vec(out[36..42].to_vec(), &[2, 3]),
+            &alone,
+            1e-5,
+        );
+    }
+
+    #[test]
+    fn test_combine_masks() {
+        let mask = combine_masks(
+            &[&causal_mask(3, 3, 0), &padding_mask(&[3, 1], 3)],
+            &[2, 3, 3],
+        );
+        let f = MASK_FILL;
+        #[rustfmt::skip]
+        assert_eq!(
+            mask.to_vec(),
+            vec![
+                0.0, f, f, 0.0, 0.0, f, 0.0, 0.0, 0.0,
+                0.0, f, f, 0.0, f, f, 0.0, f, f,
+            ]
+        );
+        assert_eq!(combine_masks(&[], &[2, 2]).to_vec(), vec![0.0; 4]);
+        // A query that sees nothing gets uniform weights, not NaN
+        let [q, k, v] = qkv(2, 2, 13);
+        let hidden = Tensor::from_vec(vec![f; 4], &[2, 2]);
+        assert!(
+            masked_attention(&q, &k, &v, &hidden)
+                .to_vec()
+                .iter()
+                .all(|x| x.is_finite())
+        );
+    }
+
+    #[test]
+    fn test_masks_broadcast_from_the_right() {
+        // A bias per head, with as many sequences as heads: each head
+        // gets its own bias in every sequence
+        let bias = Tensor::from_vec(vec![1.0, 2.0], &[2, 1, 1]);
+        let mask = broadcast_mask(&bias, &[2, 2, 1, 1]);
+        assert_eq!(mask.to_vec(), vec![1.0, 2.0, 1.0, 2.0]);
+        // A key-padding mask per sequence, expanded over the heads
+        let keys = Tensor::from_vec(vec![0.0, MASK_FILL], &[2, 1]);
+        let mask = expand_padding_mask(&keys, &[2, 2, 1, 1]);
+        assert_eq!(mask.to_vec(), vec![0.0, 0.0, MASK_FILL, MASK_FILL]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot broadcast mask [3, 1, 4] to attention scores [2, 2, 4, 4]")]
+    fn test_mask_batch_mismatch() {
+        broadcast_mask(&padding_mask(&[4, 4, 4], 4), &[2, 2, 4, 4]);
+    }
+
+    #[test]
+    #[should_panic(expected = "expand_padding_mask expects a mask [2, m] or [2, 1, m]")]
+    fn test_padding_mask_batch_mismatch() {
+        expand_padding_mask(&padding_mask(&[4, 4, 4], 4), &[2, 2, 4, 4]);
+    }
+
+    #[test]
+    #[should_panic(expected = "padding_mask lengths must be at most 3, got 4")]
+    fn test_padding_longer_than_sequence() {
+        padding_mask(&[2, 4], 3);
+    }
+}
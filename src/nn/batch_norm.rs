@@ -0,0 +1,302 @@
+use std::cell::Cell;
+
+use crate::nn::{Buffer, Module};
+use crate::tensor::Tensor;
+
+/// Batch normalization over `N` spatial dimensions: each channel is
+/// normalized by the mean and variance over the batch (and the spatial
+/// positions), then scaled by `gamma` and shifted by `beta`. Use it
+/// through [`BatchNorm1d`] and [`BatchNorm2d`].
+///
+/// In training mode, the layer normalizes with the statistics of the
+/// current batch and folds them into running estimates,
+/// `running = (1 - momentum) running + momentum batch` (the variance
+/// unbiased). In evaluation mode it normalizes with the running estimates
+/// instead, so an output only depends on its own input. Layers start in
+/// training mode; switch with [`Module::train`] and [`Module::eval`].
+/// The running statistics are the layer's [buffers](Module::buffers).
+///
+/// # Example
+/// ```
+/// use delta::nn::{BatchNorm2d, Module};
+/// use delta::random::Rng;
+/// use delta::tensor::Tensor;
+///
+/// let bn = BatchNorm2d::new(3);
+/// let x = Tensor::randn(&[8, 3, 4, 4], &mut Rng::new(0)).scalar_mul(5.0);
+/// let y = bn.forward(&x);
+/// // Each channel comes out with zero mean
+/// assert!(y.sum().to_vec()[0].abs() < 1e-3);
+///
+/// bn.eval();
+/// let single = bn.forward(&Tensor::zeros(&[1, 3, 4, 4]));
+/// assert_eq!(single.shape(), &[1, 3, 4, 4]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct BatchNorm<const N: usize> {
+    gamma: Tensor,
+    beta: Tensor,
+    running_mean: Buffer,
+    running_var: Buffer,
+    momentum: f32,
+    eps: f32,
+    training: Cell<bool>,
+}
+
+/// Batch normalization of features `[batch, channels]` or sequences
+/// `[batch, channels, length]`.
+pub type BatchNorm1d = BatchNorm<1>;
+
+/// Batch normalization of images `[batch, channels, h, w]`.
+pub type BatchNorm2d = BatchNorm<2>;
+
+impl<const N: usize> BatchNorm<N> {
+    /// A layer for `num_features` channels, with `gamma = 1`, `beta = 0`,
+    /// running mean 0 and running variance 1, momentum 0.1 and
+    /// `eps = 1e-5`.
+    pub fn new(num_features: usize) -> Self {
+        let fill = |v: f32| Tensor::from_vec(vec![v; num_features], &[num_features]);
+        Self {
+            gamma: fill(1.0).requires_grad(true),
+            beta: fill(0.0).requires_grad(true),
+            running_mean: Buffer::new(fill(0.0)),
+            running_var: Buffer::new(fill(1.0)),
+            momentum: 0.1,
+            eps: 1e-5,
+            training: Cell::new(true),
+        }
+    }
+
+    /// Weight of the current batch in the running statistics.
+    pub fn momentum(mut self, momentum: f32) -> Self {
+        self.momentum = momentum;
+        self
+    }
+
+    /// Added to the variance before taking its square root.
+    pub fn eps(mut self, eps: f32) -> Self {
+        self.eps = eps;
+        self
+    }
+
+    pub fn num_features(&self) -> usize {
+        self.gamma.nelems()
+    }
+
+    pub fn gamma(&self) -> &Tensor {
+        &self.gamma
+    }
+
+    pub fn beta(&self) -> &Tensor {
+        &self.beta
+    }
+
+    pub fn running_mean(&self) -> Tensor {
+        self.running_mean.get()
+    }
+
+    pub fn running_var(&self) -> Tensor {
+        self.running_var.get()
+    }
+
+    pub fn is_training(&self) -> bool {
+        self.training.get()
+    }
+}
+
+impl<const N: usize> Module for BatchNorm<N> {
+    /// # Panics
+    /// - Panics if `input` is not `[batch, channels, spatial...]` with
+    ///   `N` spatial dimensions (or none, for `BatchNorm1d`)
+    /// - Panics in training mode if there is only one value per channel
+    fn forward(&self, input: &Tensor) -> Tensor {
+        let shape = input.shape();
+        let c = self.num_features();
+        assert!(
+            (shape.len() == N + 2 || (N == 1 && shape.len() == 2)) && shape[1] == c,
+            "BatchNorm{}d expects inputs [batch, {}{}], got {:?}",
+            N,
+            c,
+            ", ...".repeat(N),
+            shape
+        );
+        let (batch, positions) = (shape[0], shape[2..].iter().product::<usize>());
+        // Channels in the middle: statistics reduce dimensions 0 and 2
+        let x = input.reshape(&[batch, c, positions]);
+        let per_channel = |t: &Tensor| t.reshape(&[1, c, 1]).broadcast_to(x.shape());
+
+        let (mean, var) = if self.is_training() {
+            let count = batch * positions;
+            assert!(
+                count > 1,
+                "BatchNorm{}d needs more than one value per channel in training mode, got input {:?}",
+                N,
+                shape
+            );
+            let mean = x
+                .sum_dim(2, true)
+                .sum_dim(0, true)
+                .scalar_mul(1.0 / count as f32);
+            let centered = x.sub(&mean.broadcast_to(x.shape()));
+            let var = centered
+                .mul(&centered)
+                .sum_dim(2, true)
+                .sum_dim(0, true)
+                .scalar_mul(1.0 / count as f32);
+
+            let m = self.momentum;
+            let unbiased = var.detach().scalar_mul(count as f32 / (count - 1) as f32);
+            let update = |buffer: &Buffer, batch: &Tensor| {
+                let blended = buffer
+                    .get()
+                    .scalar_mul(1.0 - m)
+                    .add(&batch.reshape(&[c]).scalar_mul(m));
+                buffer.set(blended);
+            };
+            update(&self.running_mean, &mean.detach());
+            update(&self.running_var, &unbiased);
+            (mean.broadcast_to(x.shape()), var.broadcast_to(x.shape()))
+        } else {
+            (
+                per_channel(&self.running_mean.get()),
+                per_channel(&self.running_var.get()),
+            )
+        };
+
+        let normalized = x.sub(&mean).div(&var.scalar_add(self.eps).sqrt());
+        normalized
+            .mul(&per_channel(&self.gamma))
+            .add(&per_channel(&self.beta))
+            .reshape(shape)
+    }
+
+    /// Record the mode: in evaluation mode, normalize with the running statistics.
+    fn train(&self, mode: bool) {
+        self.training.set(mode);
+    }
+
+    /// `[gamma, beta]`.
+    fn parameters(&self) -> Vec<&Tensor> {
+        vec![&self.gamma, &self.beta]
+    }
+
+    fn parameters_mut(&mut self) -> Vec<&mut Tensor> {
+        vec![&mut self.gamma, &mut self.beta]
+    }
+
+    fn named_parameters(&self) -> Vec<(String, &Tensor)> {
+        vec![("gamma".into(), &self.gamma), ("beta".into(), &self.beta)]
+    }
+
+    /// `[running_mean, running_var]`.
+    fn buffers(&self) -> Vec<&Buffer> {
+        vec![&self.running_mean, &self.running_var]
+    }
+
+    fn named_buffers(&self) -> Vec<(String, &Buffer)> {
+        vec![
+            ("running_mean".into(), &self.running_mean),
+            ("running_var".into(), &self.running_var),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::autograd::testing::check_grad;
+    use crate::random::Rng;
+
+    fn channel_stats(y: &Tensor, channel: usize) -> (f32, f32) {
+        let [n, c, h, w] = y.shape().try_into().unwrap();
+        let data = y.to_vec();
+        let values: Vec<f32> = (0..n)
+            .flat_map(|b| {
+                let start = (b * c + channel) * h * w;
+                data[start..start + h * w].to_vec()
+            })
+            .collect();
+        let mean = values.iter().sum::<f32>() / values.len() as f32;
+        let var = values.iter().map(|v| (v - mean).powi(2)).sum::<f32>() / values.len() as f32;
+        (mean, var)
+    }
+
+    #[test]
+    fn test_training_normalizes_each_channel() {
+        let bn = BatchNorm2d::new(2);
+        let x = Tensor::randn(&[4, 2, 3, 3], &mut Rng::new(1))
+            .scalar_mul(3.0)
+            .scalar_add(2.0);
+        let y = bn.forward(&x);
+        for c in 0..2 {
+            let (mean, var) = channel_stats(&y, c);
+            assert!(
+                mean.abs() < 1e-5 && (var - 1.0).abs() < 1e-3,
+                "{} {}",
+                mean,
+                var
+            );
+        }
+    }
+
+    #[test]
+    fn test_running_statistics() {
+        let bn = BatchNorm1d::new(1).momentum(0.5);
+        let x = Tensor::from_vec(vec![1.0, 3.0], &[2, 1]);
+        bn.forward(&x);
+        // Batch mean 2, unbiased variance 2
+        assert_eq!(bn.running_mean().to_vec(), vec![1.0]);
+        assert_eq!(bn.running_var().to_vec(), vec![1.5]);
+        bn.forward(&x);
+        assert_eq!(bn.running_mean().to_vec(), vec![1.5]);
+        assert_eq!(bn.buffers().len(), 2);
+    }
+
+    #[test]
+    fn test_eval_uses_running_statistics() {
+        let bn = BatchNorm1d::new(2);
+        let x = Tensor::randn(&[16, 2, 5], &mut Rng::new(2)).scalar_add(4.0);
+        for _ in 0..50 {
+            bn.forward(&x);
+        }
+        bn.eval();
+        assert!(!bn.is_training());
+        let before = (bn.running_mean().to_vec(), bn.running_var().to_vec());
+        // A lone sample is normalized as part of the seen distribution
+        let one = Tensor::from_vec(vec![4.0; 10], &[1, 2, 5]);
+        let y = bn.forward(&one).to_vec();
+        let (mean, var) = (before.0[0], before.1[0]);
+        assert!((y[0] - (4.0 - mean) / (var + 1e-5).sqrt()).abs() < 1e-5);
+        assert_eq!(
+            (bn.running_mean().to_vec(), bn.running_var().to_vec()),
+            before
+        );
+    }
+
+    #[test]
+    fn test_gradients() {
+        let mut rng = Rng::new(3);
+        let x = Tensor::randn(&[3, 2, 2], &mut rng);
+        let gamma = Tensor::randn(&[2], &mut rng);
+        let beta = Tensor::randn(&[2], &mut rng);
+        check_grad(
+            |t| {
+                let mut bn = BatchNorm1d::new(2);
+                bn.gamma = t[1].clone();
+                bn.beta = t[2].clone();
+                bn.forward(&t[0]).tanh()
+            },
+            &[x.clone(), gamma, beta],
+        );
+        let bn = BatchNorm1d::new(2);
+        bn.forward(&x);
+        bn.eval();
+        check_grad(|t| bn.forward(&t[0]).tanh(), &[x]);
+    }
+
+    #[test]
+    #[should_panic(expected = "BatchNorm2d expects inputs [batch, 3, ..., ...], got [2, 3, 4]")]
+    fn test_wrong_rank() {
+        BatchNorm2d::new(3).forward(&Tensor::zeros(&[2, 3, 4]));
+    }
+}
@@ -0,0 +1,321 @@
+use crate::nn::{Buffer, Module};
+use crate::tensor::Tensor;
+
+/// A list of submodules of any types, registered under their indices.
+///
+/// The list is a [`Module`] whose parameters, buffers and
+/// [children](Module::children) are those of its entries, so optimizers,
+/// [`Module::train`] and [`Module::state_dict`] (names `0.weight`,
+/// `1.bias`, ...) see every entry. It has no computation of its own: the
+/// owning model decides how its entries are called, so architectures can
+/// grow or pick layers at run time.
+///
+/// # Panics
+/// [`Module::forward`] panics; call the entries instead.
+///
+/// # Example
+/// ```
+/// use delta::nn::{Linear, Module, ModuleList};
+/// use delta::random::Rng;
+/// use delta::tensor::Tensor;
+///
+/// let mut rng = Rng::new(0);
+/// let mut blocks = ModuleList::new();
+/// for _ in 0..3 {
+///     blocks.push(Linear::new(4, 4, &mut rng));
+/// }
+/// assert_eq!(blocks.num_parameters(), 3 * (16 + 4));
+/// assert_eq!(blocks.named_parameters()[2].0, "1.weight");
+///
+/// // A residual stack over the entries
+/// let mut x = Tensor::randn(&[2, 4], &mut rng);
+/// for block in blocks.iter() {
+///     x = x.add(&block.forward(&x).relu());
+/// }
+/// assert_eq!(x.shape(), &[2, 4]);
+/// ```
+#[derive(Default)]
+pub struct ModuleList {
+    modules: Vec<Box<dyn Module>>,
+}
+
+impl ModuleList {
+    /// An empty list.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append `module`, registered as entry `len()`.
+    pub fn push(&mut self, module: impl Module + 'static) {
+        self.modules.push(Box::new(module));
+    }
+
+    pub fn len(&self) -> usize {
+        self.modules.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.modules.is_empty()
+    }
+
+    /// Entry `index`, or `None` past the end.
+    pub fn get(&self, index: usize) -> Option<&dyn Module> {
+        self.modules.get(index).map(|m| m.as_ref())
+    }
+
+    /// Entry `index` mutably, or `None` past the end.
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut dyn Module> {
+        match self.modules.get_mut(index) {
+            Some(m) => Some(m.as_mut()),
+            None => None,
+        }
+    }
+
+    /// The entries in order.
+    pub fn iter(&self) -> impl Iterator<Item = &dyn Module> {
+        self.modules.iter().map(|m| m.as_ref())
+    }
+}
+
+impl Module for ModuleList {
+    fn forward(&self, _input: &Tensor) -> Tensor {
+        panic!("ModuleList has no forward of its own; call its entries");
+    }
+
+    /// The parameters of every entry, in order.
+    fn parameters(&self) -> Vec<&Tensor> {
+        self.modules.iter().flat_map(|m| m.parameters()).collect()
+    }
+
+    fn parameters_mut(&mut self) -> Vec<&mut Tensor> {
+        self.modules
+            .iter_mut()
+            .flat_map(|m| m.parameters_mut())
+            .collect()
+    }
+
+    /// The buffers of every entry, in order.
+    fn buffers(&self) -> Vec<&Buffer> {
+        self.modules.iter().flat_map(|m| m.buffers()).collect()
+    }
+
+    /// The entries, named by their indices.
+    fn children(&self) -> Vec<(String, &dyn Module)> {
+        self.iter()
+            .enumerate()
+            .map(|(i, m)| (i.to_string(), m))
+            .collect()
+    }
+}
+
+/// Submodules of any types by name, in insertion order.
+///
+/// Like [`ModuleList`], a [`Module`] whose parameters, buffers and
+/// [children](Module::children) are those of its entries, named by key
+/// (`expert_a.weight`), with no computation of its own. Suited to models
+/// that choose a submodule per input: one head per task, or the experts of
+/// a mixture.
+///
+/// # Panics
+/// [`Module::forward`] panics; call the entries instead.
+///
+/// # Example
+/// ```
+/// use delta::nn::{Linear, Module, ModuleDict};
+/// use delta::random::Rng;
+/// use delta::tensor::Tensor;
+///
+/// let mut rng = Rng::new(0);
+/// let mut heads = ModuleDict::new();
+/// heads.insert("sentiment", Linear::new(8, 2, &mut rng));
+/// heads.insert("topic", Linear::new(8, 5, &mut rng));
+///
+/// let features = Tensor::randn(&[3, 8], &mut rng);
+/// let topics = heads.get("topic").unwrap().forward(&features);
+/// assert_eq!(topics.shape(), &[3, 5]);
+/// assert_eq!(heads.state_dict().len(), 4);
+/// ```
+#[derive(Default)]
+pub struct ModuleDict {
+    modules: Vec<(String, Box<dyn Module>)>,
+}
+
+impl ModuleDict {
+    /// An empty dictionary.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register `module` under `name`, replacing (in place) any entry of
+    /// that name.
+    ///
+    /// # Panics
+    /// Panics if `name` is empty or contains a `.`, which separates the
+    /// parts of hierarchical names.
+    pub fn insert(&mut self, name: impl Into<String>, module: impl Module + 'static) {
+        let name = name.into();
+        assert!(
+            !name.is_empty() && !name.contains('.'),
+            "ModuleDict names must be nonempty and contain no '.', got {:?}",
+            name
+        );
+        let module: Box<dyn Module> = Box::new(module);
+        match self.modules.iter_mut().find(|(key, _)| *key == name) {
+            Some(entry) => entry.1 = module,
+            None => self.modules.push((name, module)),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.modules.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.modules.is_empty()
+    }
+
+    pub fn contains_key(&self, name: &str) -> bool {
+        self.modules.iter().any(|(key, _)| key == name)
+    }
+
+    /// The entry `name`, if any.
+    pub fn get(&self, name: &str) -> Option<&dyn Module> {
+        self.modules
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, m)| m.as_ref())
+    }
+
+    /// The entry `name` mutably, if any.
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut dyn Module> {
+        match self.modules.iter_mut().find(|(key, _)| key == name) {
+            Some((_, m)) => Some(m.as_mut()),
+            None => None,
+        }
+    }
+
+    /// The names in insertion order.
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.modules.iter().map(|(key, _)| key.as_str())
+    }
+
+    /// The entries with their names, in insertion order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &dyn Module)> {
+        self.modules
+            .iter()
+            .map(|(key, m)| (key.as_str(), m.as_ref()))
+    }
+}
+
+impl Module for ModuleDict {
+    fn forward(&self, _input: &Tensor) -> Tensor {
+        panic!("ModuleDict has no forward of its own; call its entries");
+    }
+
+    /// The parameters of every entry, in insertion order.
+    fn parameters(&self) -> Vec<&Tensor> {
+        self.modules
+            .iter()
+            .flat_map(|(_, m)| m.parameters())
+            .collect()
+    }
+
+    fn parameters_mut(&mut self) -> Vec<&mut Tensor> {
+        self.modules
+            .iter_mut()
+            .flat_map(|(_, m)| m.parameters_mut())
+            .collect()
+    }
+
+    /// The buffers of every entry, in insertion order.
+    fn buffers(&self) -> Vec<&Buffer> {
+        self.modules.iter().flat_map(|(_, m)| m.buffers()).collect()
+    }
+
+    /// The entries, named by their keys.
+    fn children(&self) -> Vec<(String, &dyn Module)> {
+        self.iter().map(|(key, m)| (key.to_string(), m)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nn::{BatchNorm1d, Dropout, Linear};
+    use crate::random::Rng;
+
+    #[test]
+    fn test_list_registers_entries() {
+        let mut rng = Rng::new(0);
+        let mut list = ModuleList::new();
+        list.push(Linear::new(2, 3, &mut rng));
+        list.push(BatchNorm1d::new(3));
+        list.push(Dropout::new(0.5, &mut rng));
+        assert_eq!(list.len(), 3);
+
+        let names: Vec<String> = list.state_dict().into_keys().collect();
+        assert_eq!(
+            names,
+            [
+                "0.bias",
+                "0.weight",
+                "1.beta",
+                "1.gamma",
+                "1.running_mean",
+                "1.running_var"
+            ]
+        );
+        let modules: Vec<String> = list.named_modules().into_iter().map(|(n, _)| n).collect();
+        assert_eq!(modules, ["", "0", "1", "2"]);
+    }
+
+    #[test]
+    fn test_dict_round_trip_and_replace() {
+        let mut rng = Rng::new(1);
+        let mut experts = ModuleDict::new();
+        experts.insert("a", Linear::new(2, 2, &mut rng));
+        experts.insert("b", Linear::new(2, 2, &mut rng));
+        let state = experts.state_dict();
+
+        // Replacing keeps the position, and loading restores the weights
+        experts.insert("a", Linear::new(2, 2, &mut rng));
+        assert_eq!(experts.keys().collect::<Vec<_>>(), ["a", "b"]);
+        experts.load_state_dict(&state, true).unwrap();
+        assert_eq!(
+            experts.get("a").unwrap().parameters()[0].to_vec(),
+            state["a.weight"].to_vec()
+        );
+        assert!(experts.get_mut("c").is_none());
+    }
+
+    #[test]
+    fn test_train_reaches_entries() {
+        let mut rng = Rng::new(2);
+        let mut heads = ModuleDict::new();
+        heads.insert("drop", Dropout::new(0.5, &mut rng));
+        heads.eval();
+        let x = Tensor::from_vec(vec![1.0; 8], &[8]);
+        assert_eq!(heads.get("drop").unwrap().forward(&x).to_vec(), x.to_vec());
+    }
+
+    #[test]
+    fn test_gradients_reach_entries() {
+        let mut rng = Rng::new(3);
+        let mut list = ModuleList::new();
+        list.push(Linear::new(2, 2, &mut rng));
+        list.push(Linear::new(2, 1, &mut rng));
+        let x = Tensor::randn(&[4, 2], &mut rng);
+        list.get(1)
+            .unwrap()
+            .forward(&list.get(0).unwrap().forward(&x))
+            .sum()
+            .backward();
+        assert!(list.parameters().iter().all(|p| p.grad().is_some()));
+    }
+
+    #[test]
+    #[should_panic(expected = "ModuleDict names must be nonempty and contain no '.', got \"a.b\"")]
+    fn test_dotted_name() {
+        ModuleDict::new().insert("a.b", Linear::new(1, 1, &mut Rng::new(4)));
+    }
+}
@@ -0,0 +1,226 @@
+use crate::nn::{Module, functional};
+use crate::random::Rng;
+use crate::tensor::{ConvOptions, Tensor};
+
+/// A convolution layer over `N` spatial dimensions, inputs
+/// `[batch, in_channels, spatial...]`. Use it through [`Conv1d`],
+/// [`Conv2d`] and [`Conv3d`].
+///
+/// The weight is `[out_channels, in_channels / groups, kernel...]` and the
+/// optional bias `[out_channels]`; stride, padding, dilation and groups
+/// come from the [`ConvOptions`]. See [`Tensor::conv2d`] for the output
+/// size.
+///
+/// # Example
+/// ```
+/// use delta::nn::{Conv2d, Module};
+/// use delta::random::Rng;
+/// use delta::tensor::{ConvOptions, Tensor};
+///
+/// let mut rng = Rng::new(0);
+/// // A "same" 3x3 convolution followed by a strided downsampling one
+/// let conv = Conv2d::new(3, 8, [3, 3], ConvOptions::new().padding([1, 1]), &mut rng);
+/// let down = Conv2d::new(8, 8, [2, 2], ConvOptions::new().stride([2, 2]), &mut rng);
+///
+/// let images = Tensor::randn(&[4, 3, 16, 16], &mut rng);
+/// let y = down.forward(&conv.forward(&images).relu());
+/// assert_eq!(y.shape(), &[4, 8, 8, 8]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Conv<const N: usize> {
+    weight: Tensor,
+    bias: Option<Tensor>,
+    options: ConvOptions<N>,
+}
+
+/// 1D convolution over sequences `[batch, channels, length]` (audio, time
+/// series).
+pub type Conv1d = Conv<1>;
+
+/// 2D convolution over images `[batch, channels, h, w]`.
+pub type Conv2d = Conv<2>;
+
+/// 3D convolution over volumes `[batch, channels, d, h, w]` (video,
+/// volumetric data).
+pub type Conv3d = Conv<3>;
+
+impl<const N: usize> Conv<N> {
+    /// A layer with weight and bias drawn uniformly from
+    /// `[-1/√fan_in, 1/√fan_in)`, where `fan_in` is the number of inputs
+    /// each output sees, `in_channels / groups` times the kernel size.
+    ///
+    /// # Panics
+    /// Panics if the channel counts are not divisible by the number of
+    /// groups, or the kernel is empty.
+    pub fn new(
+        in_channels: usize,
+        out_channels: usize,
+        kernel_size: [usize; N],
+        options: ConvOptions<N>,
+        rng: &mut Rng,
+    ) -> Self {
+        let groups = options.groups;
+        assert!(
+            groups > 0 && in_channels.is_multiple_of(groups) && out_channels.is_multiple_of(groups),
+            "Conv{}d with {} groups requires channel counts divisible by it, got {} in and {} out",
+            N,
+            groups,
+            in_channels,
+            out_channels
+        );
+        let fan_in = in_channels / groups * kernel_size.iter().product::<usize>();
+        assert!(fan_in > 0, "Conv{}d needs at least one input per output", N);
+        let bound = 1.0 / (fan_in as f32).sqrt();
+        let mut uniform = |shape: &[usize]| {
+            Tensor::rand(shape, rng)
+                .scalar_mul(2.0 * bound)
+                .scalar_add(-bound)
+                .requires_grad(true)
+        };
+        let mut shape = vec![out_channels, in_channels / groups];
+        shape.extend(kernel_size);
+        let weight = uniform(&shape);
+        let bias = uniform(&[out_channels]);
+        Self {
+            weight,
+            bias: Some(bias),
+            options,
+        }
+    }
+
+    /// A layer with the given weight `[out, in / groups, kernel...]` and
+    /// bias `[out]`, tracked for gradients.
+    ///
+    /// # Panics
+    /// Panics if the shapes do not fit together.
+    pub fn from_parts(weight: Tensor, bias: Option<Tensor>, options: ConvOptions<N>) -> Self {
+        assert_eq!(
+            weight.ndim(),
+            N + 2,
+            "Conv{}d weight must be [out, in / groups] followed by {} kernel dimensions, got {:?}",
+            N,
+            N,
+            weight.shape()
+        );
+        if let Some(b) = &bias {
+            assert_eq!(
+                b.shape(),
+                &weight.shape()[..1],
+                "Conv{}d bias must be [out] for a weight of shape {:?}, got {:?}",
+                N,
+                weight.shape(),
+                b.shape()
+            );
+        }
+        Self {
+            weight: weight.requires_grad(true),
+            bias: bias.map(|b| b.requires_grad(true)),
+            options,
+        }
+    }
+
+    /// The same layer without its bias.
+    pub fn without_bias(self) -> Self {
+        Self { bias: None, ..self }
+    }
+
+    pub fn weight(&self) -> &Tensor {
+        &self.weight
+    }
+
+    pub fn bias(&self) -> Option<&Tensor> {
+        self.bias.as_ref()
+    }
+
+    pub fn options(&self) -> &ConvOptions<N> {
+        &self.options
+    }
+}
+
+impl<const N: usize> Module for Conv<N> {
+    /// # Panics
+    /// Panics if `input` is not `[batch, in_channels, spatial...]`, or is
+    /// smaller than the dilated kernel after padding.
+    fn forward(&self, input: &Tensor) -> Tensor {
+        functional::conv(input, &self.weight, self.bias.as_ref(), &self.options)
+    }
+
+    /// `[weight, bias]`, or `[weight]` without a bias.
+    fn parameters(&self) -> Vec<&Tensor> {
+        std::iter::once(&self.weight).chain(&self.bias).collect()
+    }
+
+    fn parameters_mut(&mut self) -> Vec<&mut Tensor> {
+        std::iter::once(&mut self.weight)
+            .chain(&mut self.bias)
+            .collect()
+    }
+
+    fn named_parameters(&self) -> Vec<(String, &Tensor)> {
+        std::iter::once(("weight".to_string(), &self.weight))
+            .chain(self.bias.iter().map(|b| ("bias".to_string(), b)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::autograd::testing::check_grad;
+
+    #[test]
+    fn test_bias_per_channel() {
+        let w = Tensor::zeros(&[2, 1, 1, 1]);
+        let b = Tensor::from_vec(vec![1.5, -2.0], &[2]);
+        let conv = Conv2d::from_parts(w, Some(b), ConvOptions::new());
+        let y = conv.forward(&Tensor::zeros(&[1, 1, 2, 2]));
+        assert_eq!(y.to_vec(), vec![1.5, 1.5, 1.5, 1.5, -2.0, -2.0, -2.0, -2.0]);
+    }
+
+    #[test]
+    fn test_init() {
+        let mut rng = Rng::new(2);
+        let conv = Conv2d::new(4, 6, [3, 3], ConvOptions::new().groups(2), &mut rng);
+        assert_eq!(conv.weight().shape(), &[6, 2, 3, 3]);
+        assert_eq!(conv.num_parameters(), 6 * 2 * 9 + 6);
+        let bound = 1.0 / 18f32.sqrt();
+        assert!(conv.weight().to_vec().iter().all(|w| w.abs() <= bound));
+    }
+
+    #[test]
+    fn test_gradients() {
+        let mut rng = Rng::new(3);
+        let options = ConvOptions::new().stride([2, 2]).padding([1, 0]);
+        let x = Tensor::randn(&[2, 2, 4, 5], &mut rng);
+        let w = Tensor::randn(&[3, 2, 2, 3], &mut rng);
+        let b = Tensor::randn(&[3], &mut rng);
+        check_grad(
+            |t| Conv2d::from_parts(t[1].clone(), Some(t[2].clone()), options).forward(&t[0]),
+            &[x, w, b],
+        );
+    }
+
+    #[test]
+    fn test_conv1d_and_conv3d() {
+        let mut rng = Rng::new(4);
+        let conv = Conv1d::new(2, 5, [3], ConvOptions::new().padding([1]), &mut rng);
+        assert_eq!(conv.weight().shape(), &[5, 2, 3]);
+        let y = conv.forward(&Tensor::randn(&[3, 2, 10], &mut rng));
+        assert_eq!(y.shape(), &[3, 5, 10]);
+
+        let options = ConvOptions::new().stride([1, 2, 2]);
+        let x = Tensor::randn(&[1, 2, 3, 4, 4], &mut rng);
+        let w = Tensor::randn(&[2, 2, 2, 2, 2], &mut rng);
+        let b = Tensor::randn(&[2], &mut rng);
+        check_grad(
+            |t| Conv3d::from_parts(t[1].clone(), Some(t[2].clone()), options).forward(&t[0]),
+            &[x, w, b],
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "divisible by it, got 3 in and 4 out")]
+    fn test_groups_must_divide_channels() {
+        Conv2d::new(3, 4, [1, 1], ConvOptions::new().groups(2), &mut Rng::new(0));
+    }
+}
@@ -0,0 +1,148 @@
+use std::cell::{Cell, RefCell};
+
+use crate::nn::summary;
+use crate::nn::{Module, functional};
+use crate::random::Rng;
+use crate::tensor::Tensor;
+
+/// Dropout: in training mode, zero each input element with probability
+/// `p` and scale the survivors by `1 / (1 - p)`, so the expected output
+/// equals the input. In evaluation mode, the identity.
+///
+/// The layer owns a generator seeded at construction, so a run repeats
+/// exactly from the seed of the [`Rng`] the model was built with. Layers
+/// start in training mode; switch with [`Module::train`] and
+/// [`Module::eval`].
+///
+/// # Example
+/// ```
+/// use delta::nn::{Dropout, Module};
+/// use delta::random::Rng;
+/// use delta::tensor::Tensor;
+///
+/// let dropout = Dropout::new(0.5, &mut Rng::new(0));
+/// let y = dropout.forward(&Tensor::from_vec(vec![1.0; 1000], &[1000]));
+/// assert!(y.to_vec().iter().all(|&v| v == 0.0 || v == 2.0));
+///
+/// dropout.eval();
+/// assert_eq!(dropout.forward(&Tensor::from_vec(vec![1.0], &[1])).to_vec(), vec![1.0]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Dropout {
+    p: f32,
+    rng: RefCell<Rng>,
+    training: Cell<bool>,
+}
+
+impl Dropout {
+    /// Dropout with probability `p`, its generator seeded from `rng`.
+    ///
+    /// # Panics
+    /// Panics unless `0 <= p < 1`.
+    pub fn new(p: f32, rng: &mut Rng) -> Self {
+        assert!(
+            (0.0..1.0).contains(&p),
+            "Dropout probability must be in [0, 1), got {}",
+            p
+        );
+        Self {
+            p,
+            rng: RefCell::new(Rng::new(rng.next_u64())),
+            training: Cell::new(true),
+        }
+    }
+
+    pub fn p(&self) -> f32 {
+        self.p
+    }
+
+    pub fn is_training(&self) -> bool {
+        self.training.get()
+    }
+}
+
+impl Module for Dropout {
+    fn forward(&self, input: &Tensor) -> Tensor {
+        // A summary pass must not shift the masks of the next real one
+        let saved = summary::is_recording().then(|| self.rng.borrow().clone());
+        let output = functional::dropout(
+            input,
+            self.p,
+            self.is_training(),
+            &mut self.rng.borrow_mut(),
+        );
+        if let Some(rng) = saved {
+            *self.rng.borrow_mut() = rng;
+        }
+        output
+    }
+
+    /// Record the mode: in evaluation mode, pass inputs through unchanged.
+    fn train(&self, mode: bool) {
+        self.training.set(mode);
+    }
+
+    fn parameters(&self) -> Vec<&Tensor> {
+        Vec::new()
+    }
+
+    fn parameters_mut(&mut self) -> Vec<&mut Tensor> {
+        Vec::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_drop_rate_and_expectation() {
+        let dropout = Dropout::new(0.3, &mut Rng::new(1));
+        let y = dropout.forward(&Tensor::from_vec(vec![1.0; 20_000], &[100, 200]));
+        let values = y.to_vec();
+        let dropped = values.iter().filter(|&&v| v == 0.0).count() as f32 / 20_000.0;
+        assert!((dropped - 0.3).abs() < 0.02, "{}", dropped);
+        let mean = values.iter().sum::<f32>() / 20_000.0;
+        assert!((mean - 1.0).abs() < 0.03, "{}", mean);
+        assert_eq!(y.shape(), &[100, 200]);
+    }
+
+    #[test]
+    fn test_reproducible_from_seed() {
+        let x = Tensor::from_vec(vec![1.0; 64], &[64]);
+        let a = Dropout::new(0.5, &mut Rng::new(7));
+        let b = Dropout::new(0.5, &mut Rng::new(7));
+        for _ in 0..3 {
+            assert_eq!(a.forward(&x).to_vec(), b.forward(&x).to_vec());
+        }
+        // Each call draws a fresh mask
+        assert_ne!(a.forward(&x).to_vec(), a.forward(&x).to_vec());
+    }
+
+    #[test]
+    fn test_gradient_follows_mask() {
+        let dropout = Dropout::new(0.5, &mut Rng::new(2));
+        let x = Tensor::from_vec(vec![3.0; 32], &[32]).requires_grad(true);
+        let y = dropout.forward(&x);
+        y.sum().backward();
+        let grad = x.grad().unwrap().to_vec();
+        for (g, v) in grad.iter().zip(y.to_vec()) {
+            assert_eq!(*g, v / 3.0);
+        }
+    }
+
+    #[test]
+    fn test_eval_is_identity() {
+        let dropout = Dropout::new(0.9, &mut Rng::new(3));
+        dropout.eval();
+        assert!(!dropout.is_training());
+        let x = Tensor::from_vec(vec![1.0, 2.0], &[2]);
+        assert_eq!(dropout.forward(&x).to_vec(), x.to_vec());
+    }
+
+    #[test]
+    #[should_panic(expected = "Dropout probability must be in [0, 1), got 1")]
+    fn test_rejects_p_one() {
+        Dropout::new(1.0, &mut Rng::new(0));
+    }
+}
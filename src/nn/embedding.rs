@@ -0,0 +1,150 @@
+use crate::nn::{Module, functional};
+use crate::random::Rng;
+use crate::tensor::Tensor;
+
+/// A lookup table: index `i` maps to row `i` of a weight
+/// `[num_embeddings, dim]`.
+///
+/// Indices come as a tensor of whole numbers, any shape `[...]`, and the
+/// output is `[..., dim]`. The gradient of the output is scattered back
+/// to the rows that were looked up, summed where an index repeats; there
+/// is no gradient with respect to the indices.
+///
+/// # Example
+/// ```
+/// use delta::nn::{Embedding, Module};
+/// use delta::random::Rng;
+/// use delta::tensor::Tensor;
+///
+/// let embedding = Embedding::new(100, 8, &mut Rng::new(0));
+/// // Two sentences of three tokens
+/// let tokens = Tensor::from_vec(vec![5.0, 17.0, 5.0, 99.0, 0.0, 3.0], &[2, 3]);
+/// let vectors = embedding.forward(&tokens);
+/// assert_eq!(vectors.shape(), &[2, 3, 8]);
+/// assert_eq!(embedding.lookup(&[5]).to_vec(), vectors.to_vec()[..8]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Embedding {
+    weight: Tensor,
+}
+
+impl Embedding {
+    /// A table with standard normal entries.
+    pub fn new(num_embeddings: usize, dim: usize, rng: &mut Rng) -> Self {
+        Self {
+            weight: Tensor::randn(&[num_embeddings, dim], rng).requires_grad(true),
+        }
+    }
+
+    /// A table with the given weight `[num_embeddings, dim]` (pretrained
+    /// vectors, say), tracked for gradients.
+    ///
+    /// # Panics
+    /// Panics if `weight` is not 2D.
+    pub fn from_weight(weight: Tensor) -> Self {
+        assert_eq!(
+            weight.ndim(),
+            2,
+            "Embedding weight must be [num_embeddings, dim], got {:?}",
+            weight.shape()
+        );
+        Self {
+            weight: weight.requires_grad(true),
+        }
+    }
+
+    pub fn num_embeddings(&self) -> usize {
+        self.weight.shape()[0]
+    }
+
+    pub fn dim(&self) -> usize {
+        self.weight.shape()[1]
+    }
+
+    pub fn weight(&self) -> &Tensor {
+        &self.weight
+    }
+
+    /// The rows of `indices`, `[indices.len(), dim]`.
+    ///
+    /// # Panics
+    /// Panics if an index is out of range.
+    pub fn lookup(&self, indices: &[usize]) -> Tensor {
+        functional::gather_rows(&self.weight, indices, &[indices.len()])
+    }
+}
+
+impl Module for Embedding {
+    /// # Panics
+    /// Panics if an index is negative, not a whole number, or out of
+    /// range.
+    fn forward(&self, indices: &Tensor) -> Tensor {
+        functional::embedding(indices, &self.weight)
+    }
+
+    /// `[weight]`.
+    fn parameters(&self) -> Vec<&Tensor> {
+        vec![&self.weight]
+    }
+
+    fn parameters_mut(&mut self) -> Vec<&mut Tensor> {
+        vec![&mut self.weight]
+    }
+
+    fn named_parameters(&self) -> Vec<(String, &Tensor)> {
+        vec![("weight".into(), &self.weight)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::autograd::testing::check_grad;
+
+    fn table() -> Embedding {
+        Embedding::from_weight(Tensor::from_vec(
+            vec![0.0, 1.0, 10.0, 11.0, 20.0, 21.0],
+            &[3, 2],
+        ))
+    }
+
+    #[test]
+    fn test_gathers_rows() {
+        let e = table();
+        let y = e.forward(&Tensor::from_vec(vec![2.0, 0.0, 2.0], &[3]));
+        assert_eq!(y.shape(), &[3, 2]);
+        assert_eq!(y.to_vec(), vec![20.0, 21.0, 0.0, 1.0, 20.0, 21.0]);
+        assert_eq!(e.lookup(&[1]).to_vec(), vec![10.0, 11.0]);
+    }
+
+    #[test]
+    fn test_gradient_scatters_to_rows() {
+        let e = table();
+        let y = e.forward(&Tensor::from_vec(vec![2.0, 0.0, 2.0], &[1, 3]));
+        y.sum().backward();
+        assert_eq!(
+            e.weight().grad().unwrap().to_vec(),
+            vec![1.0, 1.0, 0.0, 0.0, 2.0, 2.0]
+        );
+        check_grad(
+            |t| {
+                Embedding::from_weight(t[0].clone())
+                    .lookup(&[1, 1, 0])
+                    .tanh()
+            },
+            &[e.weight().detach()],
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Embedding index 3 out of range for 3 embeddings")]
+    fn test_out_of_range() {
+        table().lookup(&[3]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Embedding indices must be whole numbers >= 0, got 1.5")]
+    fn test_fractional_index() {
+        table().forward(&Tensor::from_vec(vec![1.5], &[1]));
+    }
+}
@@ -0,0 +1,409 @@
+//! Stateless versions of the layers: the same computations as
+//! [`Linear`](crate::nn::Linear), [`Conv2d`](crate::nn::Conv2d),
+//! [`Dropout`](crate::nn::Dropout) and the rest, with the parameters
+//! passed in rather than owned.
+//!
+//! The layers call into these, so a custom [`Module`](crate::nn::Module)
+//! built from them behaves exactly like one built from layer structs. The
+//! losses have no layer counterpart.
+//!
+//! # Example
+//! ```
+//! use delta::nn::functional as F;
+//! use delta::random::Rng;
+//! use delta::tensor::Tensor;
+//!
+//! let mut rng = Rng::new(0);
+//! // A two layer classifier with weights kept by hand
+//! let w1 = Tensor::randn(&[8, 4], &mut rng).requires_grad(true);
+//! let w2 = Tensor::randn(&[3, 8], &mut rng).requires_grad(true);
+//!
+//! let x = Tensor::randn(&[5, 4], &mut rng);
+//! let labels = Tensor::from_vec(vec![0.0, 2.0, 1.0, 1.0, 0.0], &[5]);
+//! let hidden = F::linear(&x, &w1, None).relu();
+//! let loss = F::cross_entropy(&F::linear(&hidden, &w2, None), &labels);
+//! loss.backward();
+//! assert_eq!(w1.grad().unwrap().shape(), &[8, 4]);
+//! ```
+
+use std::rc::Rc;
+
+use crate::random::Rng;
+use crate::tensor::{self, ConvOptions, Tensor, conv_nd};
+
+/// `input W^T + b` for `input` `[..., in]`, `weight` `[out, in]` and
+/// `bias` `[out]`, giving `[..., out]`: the computation of
+/// [`Linear`](crate::nn::Linear).
+///
+/// # Panics
+/// Panics if `weight` is not 2D or the shapes do not match as above.
+pub fn linear(input: &Tensor, weight: &Tensor, bias: Option<&Tensor>) -> Tensor {
+    assert_eq!(
+        weight.ndim(),
+        2,
+        "linear weight must be [out, in], got {:?}",
+        weight.shape()
+    );
+    let (out_features, in_features) = (weight.shape()[0], weight.shape()[1]);
+    let shape = input.shape();
+    assert!(
+        shape.last() == Some(&in_features),
+        "linear expects inputs [..., {}], got {:?}",
+        in_features,
+        shape
+    );
+    let batch: usize = shape[..shape.len() - 1].iter().product();
+    let y = input.reshape(&[batch, in_features]).matmul(&weight.t());
+    let y = match bias {
+        Some(b) => y.add(&b.broadcast_to(y.shape())),
+        None => y,
+    };
+    let mut out_shape = shape.to_vec();
+    *out_shape.last_mut().unwrap() = out_features;
+    y.reshape(&out_shape)
+}
+
+/// Convolution over `N` spatial dimensions with the bias `[c_out]`
+/// broadcast over the channels: the computation of
+/// [`Conv`](crate::nn::Conv).
+pub(crate) fn conv<const N: usize>(
+    input: &Tensor,
+    weight: &Tensor,
+    bias: Option<&Tensor>,
+    options: &ConvOptions<N>,
+) -> Tensor {
+    let y = conv_nd(input, weight, options);
+    match bias {
+        Some(b) => {
+            let mut shape = vec![1; N + 2];
+            shape[1] = b.nelems();
+            y.add(&b.reshape(&shape).broadcast_to(y.shape()))
+        }
+        None => y,
+    }
+}
+
+/// [`Tensor::conv1d`] plus an optional bias `[c_out]`.
+///
+/// # Panics
+/// Panics as [`Tensor::conv1d`] does.
+pub fn conv1d(
+    input: &Tensor,
+    weight: &Tensor,
+    bias: Option<&Tensor>,
+    options: &ConvOptions<1>,
+) -> Tensor {
+    conv(input, weight, bias, options)
+}
+
+/// [`Tensor::conv2d`] plus an optional bias `[c_out]`.
+///
+/// # Panics
+/// Panics as [`Tensor::conv2d`] does.
+pub fn conv2d(
+    input: &Tensor,
+    weight: &Tensor,
+    bias: Option<&Tensor>,
+    options: &ConvOptions<2>,
+) -> Tensor {
+    conv(input, weight, bias, options)
+}
+
+/// [`Tensor::conv3d`] plus an optional bias `[c_out]`.
+///
+/// # Panics
+/// Panics as [`Tensor::conv3d`] does.
+pub fn conv3d(
+    input: &Tensor,
+    weight: &Tensor,
+    bias: Option<&Tensor>,
+    options: &ConvOptions<3>,
+) -> Tensor {
+    conv(input, weight, bias, options)
+}
+
+/// Zero each element with probability `p` and scale the rest by
+/// `1 / (1 - p)`, drawing one number from `rng` per element; `input`
+/// unchanged when not `training`. The computation of
+/// [`Dropout`](crate::nn::Dropout).
+///
+/// # Panics
+/// Panics if `p` is not in `[0, 1)`.
+pub fn dropout(input: &Tensor, p: f32, training: bool, rng: &mut Rng) -> Tensor {
+    assert!(
+        (0.0..1.0).contains(&p),
+        "dropout probability must be in [0, 1), got {}",
+        p
+    );
+    if !training || p == 0.0 {
+        return input.clone();
+    }
+    let scale = 1.0 / (1.0 - p);
+    let mask = (0..input.nelems())
+        .map(|_| if rng.uniform() < p { 0.0 } else { scale })
+        .collect();
+    input.mul(&Tensor::from_vec(mask, input.shape()))
+}
+
+/// Rows of `weight` `[num_embeddings, dim]` picked by `indices`, a
+/// tensor of whole numbers `[...]`, giving `[..., dim]`: the computation
+/// of [`Embedding`](crate::nn::Embedding).
+///
+/// # Panics
+/// Panics if `weight` is not 2D, or an index is negative, not a whole
+/// number, or out of range.
+pub fn embedding(indices: &Tensor, weight: &Tensor) -> Tensor {
+    let idx: Vec<usize> = indices
+        .to_vec()
+        .into_iter()
+        .map(|v| {
+            assert!(
+                v >= 0.0 && v.fract() == 0.0,
+                "Embedding indices must be whole numbers >= 0, got {}",
+                v
+            );
+            v as usize
+        })
+        .collect();
+    gather_rows(weight, &idx, indices.shape())
+}
+
+/// Rows `indices` of `weight`, shaped `[shape..., dim]`, with the
+/// gradient scattered back to the rows. Only those rows are read.
+pub(crate) fn gather_rows(weight: &Tensor, indices: &[usize], shape: &[usize]) -> Tensor {
+    assert_eq!(
+        weight.ndim(),
+        2,
+        "Embedding weight must be [num_embeddings, dim], got {:?}",
+        weight.shape()
+    );
+    let (n, dim) = (weight.shape()[0], weight.shape()[1]);
+    if let Some(&bad) = indices.iter().find(|&&i| i >= n) {
+        panic!("Embedding index {} out of range for {} embeddings", bad, n);
+    }
+    let mut out_shape = shape.to_vec();
+    out_shape.push(dim);
+    tensor::gather_rows(weight, Rc::from(indices), "embedding").reshape(&out_shape)
+}
+
+/// Normalize `input` `[..., d]` over its last dimension, then scale by
+/// `gamma` `[d]` and shift by `beta` `[d]`: the computation of
+/// [`LayerNorm`](crate::nn::LayerNorm).
+///
+/// # Panics
+/// Panics if the last dimension of `input` is not the size of `gamma` and
+/// `beta`.
+pub fn layer_norm(input: &Tensor, gamma: &Tensor, beta: &Tensor, eps: f32) -> Tensor {
+    let shape = input.shape();
+    let d = gamma.nelems();
+    assert!(
+        shape.last() == Some(&d) && beta.nelems() == d,
+        "layer_norm expects inputs [..., {}] and beta [{}], got {:?} and {:?}",
+        d,
+        d,
+        shape,
+        beta.shape()
+    );
+    normalize_last(input, eps)
+        .mul(&gamma.broadcast_to(shape))
+        .add(&beta.broadcast_to(shape))
+}
+
+/// Normalize `input` `[batch, channels, spatial...]` over each group of
+/// `channels / num_groups` consecutive channels and their spatial
+/// positions, then scale by `gamma` `[channels]` and shift by `beta`
+/// `[channels]`: the computation of [`GroupNorm`](crate::nn::GroupNorm).
+///
+/// # Panics
+/// Panics if `input` has fewer than two dimensions, `num_groups` does not
+/// divide the channels, or `gamma` and `beta` are not `[channels]`.
+pub fn group_norm(
+    input: &Tensor,
+    num_groups: usize,
+    gamma: &Tensor,
+    beta: &Tensor,
+    eps: f32,
+) -> Tensor {
+    let shape = input.shape();
+    assert!(
+        shape.len() >= 2,
+        "group_norm expects inputs [batch, channels, ...], got {:?}",
+        shape
+    );
+    let channels = shape[1];
+    assert!(
+        num_groups > 0 && channels.is_multiple_of(num_groups),
+        "group_norm needs a number of groups dividing the {} channels, got {}",
+        channels,
+        num_groups
+    );
+    assert!(
+        gamma.shape() == [channels] && beta.shape() == [channels],
+        "group_norm expects gamma and beta [{}], got {:?} and {:?}",
+        channels,
+        gamma.shape(),
+        beta.shape()
+    );
+    let per_group = input.nelems() / (shape[0] * num_groups).max(1);
+    let y = normalize_last(&input.reshape(&[shape[0], num_groups, per_group]), eps).reshape(shape);
+    let mut channel_shape = vec![1; shape.len()];
+    channel_shape[1] = channels;
+    y.mul(&gamma.reshape(&channel_shape).broadcast_to(shape))
+        .add(&beta.reshape(&channel_shape).broadcast_to(shape))
+}
+
+/// `input` with zero mean and unit variance over its last dimension, the
+/// reduction shared by the normalization layers.
+fn normalize_last(input: &Tensor, eps: f32) -> Tensor {
+    let shape = input.shape();
+    let last = shape.len() - 1;
+    let d = shape[last] as f32;
+    let mean = input.sum_dim(last, true).scalar_mul(1.0 / d);
+    let centered = input.sub(&mean.broadcast_to(shape));
+    let var = centered
+        .mul(&centered)
+        .sum_dim(last, true)
+        .scalar_mul(1.0 / d);
+    centered.mul(&var.rsqrt_eps(eps).broadcast_to(shape))
+}
+
+/// [`Tensor::softmax`] along `dim`.
+pub fn softmax(input: &Tensor, dim: usize) -> Tensor {
+    input.softmax(dim)
+}
+
+/// [`Tensor::log_softmax`] along `dim`.
+pub fn log_softmax(input: &Tensor, dim: usize) -> Tensor {
+    input.log_softmax(dim)
+}
+
+/// The mean negative log likelihood of `targets` under `softmax(logits)`,
+/// a scalar: `logits` are `[..., classes]` and `targets` `[...]` hold
+/// class indices as whole numbers.
+///
+/// Computed through [`Tensor::log_softmax`], so it is stable for large
+/// logits. Differentiable with respect to `logits`.
+///
+/// # Panics
+/// Panics if the shapes do not match as above, or a target is not a class
+/// index.
+///
+/// # Example
+/// ```
+/// use delta::nn::functional::cross_entropy;
+/// use delta::tensor::Tensor;
+///
+/// // Uniform logits over four classes: the loss is ln 4
+/// let loss = cross_entropy(&Tensor::zeros(&[2, 4]), &Tensor::from_vec(vec![0.0, 3.0], &[2]));
+/// assert!((loss.to_vec()[0] - 4f32.ln()).abs() < 1e-6);
+/// ```
+pub fn cross_entropy(logits: &Tensor, targets: &Tensor) -> Tensor {
+    let shape = logits.shape();
+    assert!(
+        !shape.is_empty() && &shape[..shape.len() - 1] == targets.shape(),
+        "cross_entropy expects logits [..., classes] and targets [...], got {:?} and {:?}",
+        shape,
+        targets.shape()
+    );
+    let classes = shape[shape.len() - 1];
+    let mut one_hot = vec![0.0; logits.nelems()];
+    for (k, t) in targets.to_vec().into_iter().enumerate() {
+        assert!(
+            t >= 0.0 && t.fract() == 0.0 && (t as usize) < classes,
+            "cross_entropy targets must be class indices below {}, got {}",
+            classes,
+            t
+        );
+        one_hot[k * classes + t as usize] = 1.0;
+    }
+    logits
+        .log_softmax(shape.len() - 1)
+        .mul(&Tensor::from_vec(one_hot, shape))
+        .sum()
+        .scalar_mul(-1.0 / targets.nelems().max(1) as f32)
+}
+
+/// The mean squared difference between `input` and `target`, a scalar.
+///
+/// # Panics
+/// Panics if the shapes differ.
+pub fn mse_loss(input: &Tensor, target: &Tensor) -> Tensor {
+    assert_eq!(
+        input.shape(),
+        target.shape(),
+        "mse_loss expects matching shapes"
+    );
+    let diff = input.sub(target);
+    diff.mul(&diff)
+        .sum()
+        .scalar_mul(1.0 / input.nelems().max(1) as f32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::autograd::testing::check_grad;
+    use crate::nn::{Conv2d, Dropout, LayerNorm, Linear, Module};
+
+    #[test]
+    fn test_matches_layers() {
+        let mut rng = Rng::new(0);
+        let x = Tensor::randn(&[2, 3, 4], &mut rng);
+        let fc = Linear::new(4, 5, &mut rng);
+        assert_eq!(
+            linear(&x, fc.weight(), fc.bias()).to_vec(),
+            fc.forward(&x).to_vec()
+        );
+
+        let norm = LayerNorm::new(4);
+        assert_eq!(
+            layer_norm(&x, norm.gamma(), norm.beta(), 1e-5).to_vec(),
+            norm.forward(&x).to_vec()
+        );
+
+        let images = Tensor::randn(&[1, 2, 5, 5], &mut rng);
+        let options = ConvOptions::new().padding([1, 1]);
+        let conv = Conv2d::new(2, 3, [3, 3], options, &mut rng);
+        assert_eq!(
+            conv2d(&images, conv.weight(), conv.bias(), &options).to_vec(),
+            conv.forward(&images).to_vec()
+        );
+
+        // The layer seeds its own generator from the one it is given
+        let layer = Dropout::new(0.5, &mut Rng::new(7));
+        assert_eq!(
+            dropout(&x, 0.5, true, &mut Rng::new(Rng::new(7).next_u64())).to_vec(),
+            layer.forward(&x).to_vec()
+        );
+        assert_eq!(dropout(&x, 0.5, false, &mut rng).to_vec(), x.to_vec());
+    }
+
+    #[test]
+    fn test_cross_entropy() {
+        let logits = Tensor::from_vec(vec![2.0, 0.0, 0.0, 0.0, 1.0, 3.0], &[2, 3]);
+        let targets = Tensor::from_vec(vec![0.0, 2.0], &[2]);
+        let loss = cross_entropy(&logits, &targets).to_vec()[0];
+        let nll =
+            |row: [f32; 3], t: usize| -(row[t] - row.iter().map(|v| v.exp()).sum::<f32>().ln());
+        let expected = (nll([2.0, 0.0, 0.0], 0) + nll([0.0, 1.0, 3.0], 2)) / 2.0;
+        assert!((loss - expected).abs() < 1e-6, "{} {}", loss, expected);
+
+        let logits = Tensor::randn(&[2, 3, 4], &mut Rng::new(1));
+        let targets = Tensor::from_vec(vec![0.0, 3.0, 1.0, 2.0, 2.0, 0.0], &[2, 3]);
+        check_grad(|t| cross_entropy(&t[0], &targets), &[logits]);
+    }
+
+    #[test]
+    fn test_mse_loss() {
+        let a = Tensor::from_vec(vec![1.0, 2.0, 3.0], &[3]);
+        let b = Tensor::from_vec(vec![1.0, 0.0, 6.0], &[3]);
+        assert_eq!(mse_loss(&a, &b).to_vec(), vec![13.0 / 3.0]);
+        check_grad(|t| mse_loss(&t[0], &t[1]), &[a, b]);
+    }
+
+    #[test]
+    #[should_panic(expected = "cross_entropy targets must be class indices below 3, got 3")]
+    fn test_cross_entropy_bad_target() {
+        cross_entropy(&Tensor::zeros(&[1, 3]), &Tensor::from_vec(vec![3.0], &[1]));
+    }
+}
@@ -0,0 +1,264 @@
+use crate::nn::{Module, functional};
+use crate::tensor::Tensor;
+
+/// Group normalization: the channels of an input `[batch, channels,
+/// spatial...]` are split into `num_groups` groups, each normalized by
+/// the mean and variance over its channels and spatial positions, then
+/// every channel is scaled by `gamma` and shifted by `beta`.
+///
+/// The statistics are per sample, as for [`LayerNorm`](crate::nn::LayerNorm),
+/// so the layer behaves the same in training and evaluation and for any
+/// batch size, batches of one included. One group is layer normalization
+/// over `[channels, spatial...]`; one group per channel is
+/// [`InstanceNorm2d`].
+///
+/// # Example
+/// ```
+/// use delta::nn::{GroupNorm, Module};
+/// use delta::random::Rng;
+/// use delta::tensor::Tensor;
+///
+/// let norm = GroupNorm::new(2, 6);
+/// let x = Tensor::randn(&[1, 6, 4, 4], &mut Rng::new(0)).scalar_add(3.0);
+/// let y = norm.forward(&x).to_vec();
+/// // Channels 0-2 form the first group, and come out with zero mean
+/// assert!(y[..48].iter().sum::<f32>().abs() < 1e-3);
+/// ```
+#[derive(Debug, Clone)]
+pub struct GroupNorm {
+    num_groups: usize,
+    gamma: Tensor,
+    beta: Tensor,
+    eps: f32,
+}
+
+impl GroupNorm {
+    /// A layer for `channels` channels in `num_groups` groups, with
+    /// `gamma = 1`, `beta = 0` and `eps = 1e-5`.
+    ///
+    /// # Panics
+    /// Panics if `num_groups` does not divide `channels`.
+    pub fn new(num_groups: usize, channels: usize) -> Self {
+        assert!(
+            num_groups > 0 && channels.is_multiple_of(num_groups),
+            "GroupNorm needs a number of groups dividing the {} channels, got {}",
+            channels,
+            num_groups
+        );
+        let fill = |v: f32| Tensor::from_vec(vec![v; channels], &[channels]).requires_grad(true);
+        Self {
+            num_groups,
+            gamma: fill(1.0),
+            beta: fill(0.0),
+            eps: 1e-5,
+        }
+    }
+
+    /// Added to the variance before taking its square root.
+    pub fn eps(mut self, eps: f32) -> Self {
+        self.eps = eps;
+        self
+    }
+
+    pub fn num_groups(&self) -> usize {
+        self.num_groups
+    }
+
+    pub fn channels(&self) -> usize {
+        self.gamma.nelems()
+    }
+
+    pub fn gamma(&self) -> &Tensor {
+        &self.gamma
+    }
+
+    pub fn beta(&self) -> &Tensor {
+        &self.beta
+    }
+}
+
+impl Module for GroupNorm {
+    /// # Panics
+    /// Panics if `input` is not `[batch, channels, spatial...]`.
+    fn forward(&self, input: &Tensor) -> Tensor {
+        let shape = input.shape();
+        assert!(
+            shape.len() >= 2 && shape[1] == self.channels(),
+            "GroupNorm expects inputs [batch, {}, ...], got {:?}",
+            self.channels(),
+            shape
+        );
+        functional::group_norm(input, self.num_groups, &self.gamma, &self.beta, self.eps)
+    }
+
+    /// `[gamma, beta]`.
+    fn parameters(&self) -> Vec<&Tensor> {
+        vec![&self.gamma, &self.beta]
+    }
+
+    fn parameters_mut(&mut self) -> Vec<&mut Tensor> {
+        vec![&mut self.gamma, &mut self.beta]
+    }
+
+    fn named_parameters(&self) -> Vec<(String, &Tensor)> {
+        vec![("gamma".into(), &self.gamma), ("beta".into(), &self.beta)]
+    }
+}
+
+/// Instance normalization of images `[batch, channels, h, w]`: each
+/// channel of each image is normalized over its own pixels, then scaled
+/// by `gamma` and shifted by `beta`. Common in style transfer, where the
+/// contrast of one image should not depend on the rest of the batch.
+///
+/// A [`GroupNorm`] with one group per channel, restricted to images.
+///
+/// # Example
+/// ```
+/// use delta::nn::{InstanceNorm2d, Module};
+/// use delta::random::Rng;
+/// use delta::tensor::Tensor;
+///
+/// let norm = InstanceNorm2d::new(3);
+/// let x = Tensor::randn(&[2, 3, 5, 5], &mut Rng::new(0)).scalar_mul(4.0);
+/// let y = norm.forward(&x).to_vec();
+/// assert!(y.chunks(25).all(|plane| plane.iter().sum::<f32>().abs() < 1e-3));
+/// ```
+#[derive(Debug, Clone)]
+pub struct InstanceNorm2d {
+    norm: GroupNorm,
+}
+
+impl InstanceNorm2d {
+    /// A layer for `channels` channels, with `gamma = 1`, `beta = 0` and
+    /// `eps = 1e-5`.
+    pub fn new(channels: usize) -> Self {
+        Self {
+            norm: GroupNorm::new(channels, channels),
+        }
+    }
+
+    /// Added to the variance before taking its square root.
+    pub fn eps(mut self, eps: f32) -> Self {
+        self.norm = self.norm.eps(eps);
+        self
+    }
+
+    pub fn channels(&self) -> usize {
+        self.norm.channels()
+    }
+
+    pub fn gamma(&self) -> &Tensor {
+        self.norm.gamma()
+    }
+
+    pub fn beta(&self) -> &Tensor {
+        self.norm.beta()
+    }
+}
+
+impl Module for InstanceNorm2d {
+    /// # Panics
+    /// Panics if `input` is not `[batch, channels, h, w]`.
+    fn forward(&self, input: &Tensor) -> Tensor {
+        assert!(
+            input.ndim() == 4 && input.shape()[1] == self.channels(),
+            "InstanceNorm2d expects inputs [batch, {}, h, w], got {:?}",
+            self.channels(),
+            input.shape()
+        );
+        self.norm.call(input)
+    }
+
+    /// `[gamma, beta]`.
+    fn parameters(&self) -> Vec<&Tensor> {
+        self.norm.parameters()
+    }
+
+    fn parameters_mut(&mut self) -> Vec<&mut Tensor> {
+        self.norm.parameters_mut()
+    }
+
+    fn named_parameters(&self) -> Vec<(String, &Tensor)> {
+        self.norm.named_parameters()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::autograd::testing::check_grad;
+    use crate::nn::LayerNorm;
+    use crate::random::Rng;
+
+    #[test]
+    fn test_groups_normalize_independently() {
+        let x = Tensor::randn(&[2, 4, 3], &mut Rng::new(0))
+            .scalar_mul(3.0)
+            .scalar_add(2.0);
+        let y = GroupNorm::new(2, 4).forward(&x).to_vec();
+        // Each sample holds two groups of 2 channels x 3 positions
+        for group in y.chunks(6) {
+            let mean = group.iter().sum::<f32>() / 6.0;
+            let var = group.iter().map(|v| (v - mean).powi(2)).sum::<f32>() / 6.0;
+            assert!(
+                mean.abs() < 1e-5 && (var - 1.0).abs() < 1e-3,
+                "{} {}",
+                mean,
+                var
+            );
+        }
+    }
+
+    #[test]
+    fn test_one_group_is_layer_norm() {
+        let x = Tensor::randn(&[3, 2, 4], &mut Rng::new(1));
+        let grouped = GroupNorm::new(1, 2).forward(&x).to_vec();
+        let layered = LayerNorm::new(8).forward(&x.reshape(&[3, 8])).to_vec();
+        for (a, b) in grouped.iter().zip(&layered) {
+            assert!((a - b).abs() < 1e-5, "{} {}", a, b);
+        }
+    }
+
+    #[test]
+    fn test_gradients() {
+        let mut rng = Rng::new(2);
+        let x = Tensor::randn(&[2, 4, 3], &mut rng);
+        let gamma = Tensor::randn(&[4], &mut rng);
+        let beta = Tensor::randn(&[4], &mut rng);
+        check_grad(
+            |t| {
+                let mut norm = GroupNorm::new(2, 4);
+                norm.gamma = t[1].clone();
+                norm.beta = t[2].clone();
+                norm.forward(&t[0]).tanh()
+            },
+            &[x, gamma, beta],
+        );
+    }
+
+    #[test]
+    fn test_instance_norm_ignores_batch() {
+        let mut rng = Rng::new(3);
+        let norm = InstanceNorm2d::new(2);
+        let a = Tensor::randn(&[1, 2, 3, 3], &mut rng);
+        let b = Tensor::randn(&[1, 2, 3, 3], &mut rng).scalar_mul(10.0);
+        let mut both = a.to_vec();
+        both.extend(b.to_vec());
+        let y = norm
+            .forward(&Tensor::from_vec(both, &[2, 2, 3, 3]))
+            .to_vec();
+        assert_eq!(y[..18], norm.forward(&a).to_vec());
+    }
+
+    #[test]
+    #[should_panic(expected = "GroupNorm needs a number of groups dividing the 6 channels, got 4")]
+    fn test_groups_must_divide_channels() {
+        GroupNorm::new(4, 6);
+    }
+
+    #[test]
+    #[should_panic(expected = "InstanceNorm2d expects inputs [batch, 2, h, w], got [2, 2, 3]")]
+    fn test_instance_norm_wants_images() {
+        InstanceNorm2d::new(2).forward(&Tensor::zeros(&[2, 2, 3]));
+    }
+}
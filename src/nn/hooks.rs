@@ -0,0 +1,264 @@
+use std::cell::{Cell, RefCell};
+use std::marker::PhantomData;
+use std::rc::Rc;
+
+use crate::nn::Module;
+use crate::nn::module::module_key;
+use crate::tensor::Tensor;
+
+type PreHook = Rc<dyn Fn(&Tensor) -> Option<Tensor>>;
+type PostHook = Rc<dyn Fn(&Tensor, &Tensor) -> Option<Tensor>>;
+
+enum Hook {
+    Pre(PreHook),
+    Post(PostHook),
+}
+
+struct Entry {
+    id: u64,
+    module: (usize, &'static str),
+    hook: Hook,
+}
+
+thread_local! {
+    /// Every registered hook, in registration order.
+    static HOOKS: RefCell<Vec<Entry>> = const { RefCell::new(Vec::new()) };
+    static NEXT_ID: Cell<u64> = const { Cell::new(0) };
+}
+
+/// Keeps a forward hook registered; dropping it removes the hook.
+///
+/// Returned by [`register_forward_pre_hook`] and
+/// [`register_forward_hook`]. It borrows the module, which
+/// therefore stays where it is for as long as the hook is in place.
+#[must_use = "the hook is removed as soon as its handle is dropped"]
+pub struct HookHandle<'a> {
+    id: u64,
+    module: PhantomData<&'a ()>,
+}
+
+impl HookHandle<'_> {
+    /// Remove the hook now, same as dropping the handle.
+    pub fn remove(self) {}
+}
+
+impl Drop for HookHandle<'_> {
+    fn drop(&mut self) {
+        HOOKS.with_borrow_mut(|hooks| hooks.retain(|e| e.id != self.id));
+    }
+}
+
+fn register<'a, M: Module + ?Sized>(module: &'a M, hook: Hook) -> HookHandle<'a> {
+    // Values of a zero-sized type share one address, so a hook on one
+    // would fire for every module of the type
+    assert!(
+        std::mem::size_of_val(module) != 0,
+        "cannot hook {}: modules of a zero-sized type cannot be told apart",
+        module.type_name()
+    );
+    let id = NEXT_ID.replace(NEXT_ID.get() + 1);
+    HOOKS.with_borrow_mut(|hooks| {
+        hooks.push(Entry {
+            id,
+            module: module_key(module),
+            hook,
+        })
+    });
+    HookHandle {
+        id,
+        module: PhantomData,
+    }
+}
+
+/// Call `hook` with the input of every [`Module::call`] of `module`,
+/// before its `forward` runs. Returning `None` leaves the input unchanged;
+/// returning a tensor replaces it. Hooks run in the order they were
+/// registered, each seeing the result of the previous.
+///
+/// The hook stays in place until the returned handle is dropped. `module`
+/// may be a whole model or a submodule reached through
+/// [`Module::named_modules`]. Entry points other than `forward` that run
+/// like `call`, such as
+/// [`MultiheadAttention::attend`](crate::nn::MultiheadAttention::attend),
+/// are hooked too.
+///
+/// # Panics
+/// Panics if `module` has a zero-sized type: modules are told apart by
+/// address, which such values do not have.
+pub fn register_forward_pre_hook<'a, M: Module + ?Sized>(
+    module: &'a M,
+    hook: impl Fn(&Tensor) -> Option<Tensor> + 'static,
+) -> HookHandle<'a> {
+    register(module, Hook::Pre(Rc::new(hook)))
+}
+
+/// Call `hook` with the input and output of every [`Module::call`] of
+/// `module`: to collect intermediate activations (for a perceptual loss,
+/// or to debug a model) without changing the model's code. Returning
+/// `None` leaves the output unchanged; returning a tensor replaces it.
+/// Hooks run in the order they were registered, each seeing the result of
+/// the previous.
+///
+/// The hook stays in place until the returned handle is dropped. `module`
+/// may be a whole model or a submodule reached through
+/// [`Module::named_modules`], and entry points that run like `call` are
+/// hooked too, as for [`register_forward_pre_hook`].
+///
+/// # Panics
+/// Panics if `module` has a zero-sized type.
+///
+/// # Example
+/// ```
+/// use std::cell::RefCell;
+/// use std::rc::Rc;
+///
+/// use delta::nn::{Module, TransformerEncoder, TransformerEncoderLayer, register_forward_hook};
+/// use delta::random::Rng;
+/// use delta::tensor::Tensor;
+///
+/// let mut rng = Rng::new(0);
+/// let encoder = TransformerEncoder::new(vec![
+///     TransformerEncoderLayer::new(8, 2, 16, &mut rng),
+///     TransformerEncoderLayer::new(8, 2, 16, &mut rng),
+/// ]);
+///
+/// // Keep the output of the first layer's feed-forward expansion
+/// let features = Rc::new(RefCell::new(None));
+/// let sink = features.clone();
+/// let (_, linear1) = encoder
+///     .named_modules()
+///     .into_iter()
+///     .find(|(name, _)| name == "layers.0.linear1")
+///     .unwrap();
+/// let handle = register_forward_hook(linear1, move |_, output| {
+///     *sink.borrow_mut() = Some(output.clone());
+///     None
+/// });
+///
+/// encoder.forward(&Tensor::randn(&[3, 8], &mut rng));
+/// assert_eq!(features.borrow().as_ref().unwrap().shape(), &[3, 16]);
+/// drop(handle);
+/// ```
+pub fn register_forward_hook<'a, M: Module + ?Sized>(
+    module: &'a M,
+    hook: impl Fn(&Tensor, &Tensor) -> Option<Tensor> + 'static,
+) -> HookHandle<'a> {
+    register(module, Hook::Post(Rc::new(hook)))
+}
+
+/// The hooks on `module`, cloned out of the registry so that they can
+/// themselves run modules or register hooks.
+fn hooks_of<T>(module: (usize, &'static str), pick: impl Fn(&Hook) -> Option<T>) -> Vec<T> {
+    HOOKS.with_borrow(|hooks| {
+        hooks
+            .iter()
+            .filter(|e| e.module == module)
+            .filter_map(|e| pick(&e.hook))
+            .collect()
+    })
+}
+
+/// `input` as transformed by the pre-hooks on `module`, or `None` if no
+/// hook replaced it.
+pub(crate) fn run_pre(module: (usize, &'static str), input: &Tensor) -> Option<Tensor> {
+    let pre = hooks_of(module, |h| match h {
+        Hook::Pre(f) => Some(f.clone()),
+        Hook::Post(_) => None,
+    });
+    pre.iter().fold(None, |replaced, hook| {
+        hook(replaced.as_ref().unwrap_or(input)).or(replaced)
+    })
+}
+
+/// `output` as transformed by the post-hooks on `module`.
+pub(crate) fn run_post(module: (usize, &'static str), input: &Tensor, output: Tensor) -> Tensor {
+    let post = hooks_of(module, |h| match h {
+        Hook::Post(f) => Some(f.clone()),
+        Hook::Pre(_) => None,
+    });
+    post.iter().fold(output, |output, hook| {
+        hook(input, &output).unwrap_or(output)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nn::{Linear, ModuleList};
+    use crate::random::Rng;
+
+    #[test]
+    fn test_pre_and_post_hooks() {
+        let mut rng = Rng::new(0);
+        let layer = Linear::new(2, 2, &mut rng);
+        let x = Tensor::randn(&[3, 2], &mut rng);
+        let plain = layer.forward(&x).to_vec();
+
+        let _double = register_forward_pre_hook(&layer, |x| Some(x.scalar_mul(2.0)));
+        let _keep = register_forward_pre_hook(&layer, |_| None);
+        let seen = Rc::new(RefCell::new(Vec::new()));
+        let sink = seen.clone();
+        let _record = register_forward_hook(&layer, move |input, output| {
+            sink.borrow_mut().push((input.to_vec(), output.to_vec()));
+            None
+        });
+        let _negate = register_forward_hook(&layer, |_, output| Some(output.neg()));
+
+        let y = layer.call(&x);
+        assert_eq!(y.to_vec(), layer.forward(&x.scalar_mul(2.0)).neg().to_vec());
+        let seen = seen.borrow();
+        assert_eq!(seen.len(), 1);
+        assert_eq!(seen[0].0, x.scalar_mul(2.0).to_vec());
+        // forward itself is not hooked
+        assert_eq!(layer.forward(&x).to_vec(), plain);
+    }
+
+    #[test]
+    fn test_handle_removes_hook() {
+        let layer = Linear::new(1, 1, &mut Rng::new(1));
+        let x = Tensor::from_vec(vec![1.0], &[1, 1]);
+        let plain = layer.call(&x).to_vec();
+        let handle = register_forward_hook(&layer, |_, _| Some(Tensor::zeros(&[1, 1])));
+        assert_eq!(layer.call(&x).to_vec(), vec![0.0]);
+        handle.remove();
+        assert_eq!(layer.call(&x).to_vec(), plain);
+        assert!(HOOKS.with_borrow(|hooks| hooks.is_empty()));
+    }
+
+    #[test]
+    fn test_hook_by_name() {
+        let mut rng = Rng::new(2);
+        let mut list = ModuleList::new();
+        list.push(Linear::new(2, 2, &mut rng));
+        list.push(Linear::new(2, 2, &mut rng));
+        let (_, second) = list.named_modules().swap_remove(2);
+        let count = Rc::new(Cell::new(0));
+        let calls = count.clone();
+        let _handle = register_forward_hook(second, move |_, _| {
+            calls.set(calls.get() + 1);
+            None
+        });
+        let x = Tensor::zeros(&[1, 2]);
+        list.get(0).unwrap().call(&x);
+        list.get(1).unwrap().call(&x);
+        assert_eq!(count.get(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "modules of a zero-sized type cannot be told apart")]
+    fn test_zero_sized_module_rejected() {
+        struct Identity;
+        impl Module for Identity {
+            fn forward(&self, input: &Tensor) -> Tensor {
+                input.clone()
+            }
+            fn parameters(&self) -> Vec<&Tensor> {
+                Vec::new()
+            }
+            fn parameters_mut(&mut self) -> Vec<&mut Tensor> {
+                Vec::new()
+            }
+        }
+        let _handle = register_forward_hook(&Identity, |_, _| None);
+    }
+}
@@ -0,0 +1,137 @@
+use crate::nn::{Module, functional};
+use crate::tensor::Tensor;
+
+/// Layer normalization over the last dimension: each feature vector is
+/// normalized by its own mean and variance, then scaled by `gamma` and
+/// shifted by `beta`.
+///
+/// Unlike [`BatchNorm`](crate::nn::BatchNorm), the statistics never mix
+/// samples, so the layer behaves the same in training and evaluation and
+/// for any batch size.
+///
+/// # Example
+/// ```
+/// use delta::nn::{LayerNorm, Module};
+/// use delta::tensor::Tensor;
+///
+/// let norm = LayerNorm::new(4);
+/// let x = Tensor::from_vec(vec![1.0, 2.0, 3.0, 4.0, 10.0, 10.0, 10.0, 10.0], &[2, 4]);
+/// let y = norm.forward(&x).to_vec();
+/// // Zero mean per row; a constant row maps to zeros
+/// assert!(y[..4].iter().sum::<f32>().abs() < 1e-6);
+/// assert_eq!(y[4..], [0.0; 4]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct LayerNorm {
+    gamma: Tensor,
+    beta: Tensor,
+    eps: f32,
+}
+
+impl LayerNorm {
+    /// A layer for vectors of `dim` features, with `gamma = 1`, `beta = 0`
+    /// and `eps = 1e-5`.
+    pub fn new(dim: usize) -> Self {
+        let fill = |v: f32| Tensor::from_vec(vec![v; dim], &[dim]).requires_grad(true);
+        Self {
+            gamma: fill(1.0),
+            beta: fill(0.0),
+            eps: 1e-5,
+        }
+    }
+
+    /// Added to the variance before taking its square root.
+    pub fn eps(mut self, eps: f32) -> Self {
+        self.eps = eps;
+        self
+    }
+
+    pub fn dim(&self) -> usize {
+        self.gamma.nelems()
+    }
+
+    pub fn gamma(&self) -> &Tensor {
+        &self.gamma
+    }
+
+    pub fn beta(&self) -> &Tensor {
+        &self.beta
+    }
+}
+
+impl Module for LayerNorm {
+    /// # Panics
+    /// Panics if the last dimension of `input` is not `dim`.
+    fn forward(&self, input: &Tensor) -> Tensor {
+        let shape = input.shape();
+        let d = self.dim();
+        assert!(
+            shape.last() == Some(&d),
+            "LayerNorm expects inputs [..., {}], got {:?}",
+            d,
+            shape
+        );
+        functional::layer_norm(input, &self.gamma, &self.beta, self.eps)
+    }
+
+    /// `[gamma, beta]`.
+    fn parameters(&self) -> Vec<&Tensor> {
+        vec![&self.gamma, &self.beta]
+    }
+
+    fn parameters_mut(&mut self) -> Vec<&mut Tensor> {
+        vec![&mut self.gamma, &mut self.beta]
+    }
+
+    fn named_parameters(&self) -> Vec<(String, &Tensor)> {
+        vec![("gamma".into(), &self.gamma), ("beta".into(), &self.beta)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::autograd::testing::check_grad;
+    use crate::random::Rng;
+
+    #[test]
+    fn test_normalizes_each_vector() {
+        let x = Tensor::randn(&[2, 3, 8], &mut Rng::new(0))
+            .scalar_mul(4.0)
+            .scalar_add(1.0);
+        let y = LayerNorm::new(8).forward(&x).to_vec();
+        for row in y.chunks(8) {
+            let mean = row.iter().sum::<f32>() / 8.0;
+            let var = row.iter().map(|v| (v - mean).powi(2)).sum::<f32>() / 8.0;
+            assert!(
+                mean.abs() < 1e-5 && (var - 1.0).abs() < 1e-3,
+                "{} {}",
+                mean,
+                var
+            );
+        }
+    }
+
+    #[test]
+    fn test_gradients() {
+        let mut rng = Rng::new(1);
+        let x = Tensor::randn(&[3, 4], &mut rng);
+        let gamma = Tensor::randn(&[4], &mut rng);
+        let beta = Tensor::randn(&[4], &mut rng);
+        check_grad(
+            |t| {
+                let mut norm = LayerNorm::new(4);
+                norm.gamma = t[1].clone();
+                norm.beta = t[2].clone();
+                norm.forward(&t[0]).tanh()
+            },
+            &[x, gamma, beta],
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "LayerNorm expects inputs [..., 4], got [2, 3]")]
+    fn test_wrong_dim() {
+        LayerNorm::new(4).forward(&Tensor::zeros(&[2, 3]));
+    }
+}
@@ -0,0 +1,195 @@
+use crate::nn::{Module, functional};
+use crate::random::Rng;
+use crate::tensor::Tensor;
+
+/// A fully connected layer: `y = x Wᵀ + b`.
+///
+/// The weight is `[out_features, in_features]` and the optional bias
+/// `[out_features]`. Inputs are `[..., in_features]`; every leading
+/// dimension is treated as a batch dimension.
+#[derive(Debug, Clone)]
+pub struct Linear {
+    weight: Tensor,
+    bias: Option<Tensor>,
+}
+
+impl Linear {
+    /// A layer with weight and bias drawn uniformly from `[-1/√in, 1/√in)`,
+    /// which keeps the output variance independent of `in_features`.
+    ///
+    /// # Panics
+    /// Panics if `in_features` is 0.
+    pub fn new(in_features: usize, out_features: usize, rng: &mut Rng) -> Self {
+        assert!(in_features > 0, "Linear needs at least one input feature");
+        let bound = 1.0 / (in_features as f32).sqrt();
+        let mut uniform = |shape: &[usize]| {
+            Tensor::rand(shape, rng)
+                .scalar_mul(2.0 * bound)
+                .scalar_add(-bound)
+                .requires_grad(true)
+        };
+        let weight = uniform(&[out_features, in_features]);
+        let bias = uniform(&[out_features]);
+        Self {
+            weight,
+            bias: Some(bias),
+        }
+    }
+
+    /// A layer with the given weight `[out, in]` and bias `[out]`, tracked
+    /// for gradients.
+    ///
+    /// # Panics
+    /// Panics if the shapes do not fit together.
+    pub fn from_parts(weight: Tensor, bias: Option<Tensor>) -> Self {
+        assert_eq!(
+            weight.ndim(),
+            2,
+            "Linear weight must be [out, in], got {:?}",
+            weight.shape()
+        );
+        if let Some(b) = &bias {
+            assert_eq!(
+                b.shape(),
+                &weight.shape()[..1],
+                "Linear bias must be [out] for a weight of shape {:?}, got {:?}",
+                weight.shape(),
+                b.shape()
+            );
+        }
+        Self {
+            weight: weight.requires_grad(true),
+            bias: bias.map(|b| b.requires_grad(true)),
+        }
+    }
+
+    /// The same layer without its bias.
+    pub fn without_bias(self) -> Self {
+        Self { bias: None, ..self }
+    }
+
+    pub fn in_features(&self) -> usize {
+        self.weight.shape()[1]
+    }
+
+    pub fn out_features(&self) -> usize {
+        self.weight.shape()[0]
+    }
+
+    pub fn weight(&self) -> &Tensor {
+        &self.weight
+    }
+
+    pub fn bias(&self) -> Option<&Tensor> {
+        self.bias.as_ref()
+    }
+}
+
+impl Module for Linear {
+    /// # Panics
+    /// Panics if the last dimension of `input` is not `in_features`.
+    fn forward(&self, input: &Tensor) -> Tensor {
+        let shape = input.shape();
+        assert!(
+            shape.last() == Some(&self.in_features()),
+            "Linear expects inputs [..., {}], got {:?}",
+            self.in_features(),
+            shape
+        );
+        functional::linear(input, &self.weight, self.bias.as_ref())
+    }
+
+    /// `[weight, bias]`, or `[weight]` without a bias.
+    fn parameters(&self) -> Vec<&Tensor> {
+        std::iter::once(&self.weight).chain(&self.bias).collect()
+    }
+
+    fn parameters_mut(&mut self) -> Vec<&mut Tensor> {
+        std::iter::once(&mut self.weight)
+            .chain(&mut self.bias)
+            .collect()
+    }
+
+    fn named_parameters(&self) -> Vec<(String, &Tensor)> {
+        std::iter::once(("weight".to_string(), &self.weight))
+            .chain(self.bias.iter().map(|b| ("bias".to_string(), b)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::autograd::testing::check_grad;
+
+    fn layer() -> Linear {
+        Linear::from_parts(
+            Tensor::from_vec(vec![1.0, 0.0, -1.0, 0.5, 2.0, 0.25], &[2, 3]),
+            Some(Tensor::from_vec(vec![0.1, -0.2], &[2])),
+        )
+    }
+
+    #[test]
+    fn test_forward() {
+        let x = Tensor::from_vec(vec![1.0, 2.0, 3.0], &[1, 3]);
+        let y = layer().forward(&x);
+        assert_eq!(y.shape(), &[1, 2]);
+        let expected = [1.0 - 3.0 + 0.1, 0.5 + 4.0 + 0.75 - 0.2];
+        for (a, b) in y.to_vec().iter().zip(expected) {
+            assert!((a - b).abs() < 1e-6);
+        }
+        let no_bias = layer().without_bias().forward(&x);
+        assert_eq!(no_bias.to_vec(), vec![-2.0, 5.25]);
+    }
+
+    #[test]
+    fn test_leading_batch_dims() {
+        let x = Tensor::from_vec((0..12).map(|i| i as f32 * 0.1).collect(), &[2, 2, 3]);
+        let y = layer().forward(&x);
+        assert_eq!(y.shape(), &[2, 2, 2]);
+        let flat = layer().forward(&x.reshape(&[4, 3]));
+        assert_eq!(y.to_vec(), flat.to_vec());
+    }
+
+    #[test]
+    fn test_init() {
+        let mut rng = Rng::new(1);
+        let layer = Linear::new(16, 4, &mut rng);
+        assert_eq!(layer.weight().shape(), &[4, 16]);
+        assert_eq!(layer.num_parameters(), 68);
+        assert!(layer.weight().to_vec().iter().all(|w| w.abs() <= 0.25));
+        assert!(layer.parameters().iter().all(|p| p.is_leaf()));
+    }
+
+    #[test]
+    fn test_gradients() {
+        let x = Tensor::from_vec(vec![0.3, -1.0, 0.5, 2.0, 0.1, -0.4], &[2, 3]);
+        let l = layer();
+        let params = [l.weight().clone(), l.bias().unwrap().clone(), x];
+        check_grad(
+            |p| {
+                Linear::from_parts(p[0].clone(), Some(p[1].clone()))
+                    .forward(&p[2])
+                    .tanh()
+            },
+            &params,
+        );
+    }
+
+    #[test]
+    fn test_zero_grad() {
+        let l = layer();
+        l.forward(&Tensor::from_vec(vec![1.0, 1.0, 1.0], &[3]))
+            .sum()
+            .backward();
+        assert!(l.weight().grad().is_some());
+        l.zero_grad();
+        assert!(l.parameters().iter().all(|p| p.grad().is_none()));
+    }
+
+    #[test]
+    #[should_panic(expected = "Linear expects inputs [..., 3], got [2, 2]")]
+    fn test_wrong_input_width() {
+        layer().forward(&Tensor::from_vec(vec![0.0; 4], &[2, 2]));
+    }
+}
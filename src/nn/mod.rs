@@ -0,0 +1,54 @@
+//! Neural network building blocks.
+//!
+//! Layers implement [`Module`], which gives every model the same shape: a
+//! `forward` function and the list of parameters to train.
+
+pub mod attention;
+mod batch_norm;
+mod container;
+mod conv;
+mod dropout;
+mod embedding;
+pub mod functional;
+mod group_norm;
+mod hooks;
+mod layer_norm;
+mod linear;
+mod module;
+pub mod parallel;
+pub mod parametrize;
+mod pool;
+mod positional_encoding;
+mod rnn;
+mod rotary;
+mod sparse_linear;
+mod spectral_norm;
+mod state_dict;
+mod summary;
+mod transformer;
+mod weight_norm;
+
+pub use batch_norm::{BatchNorm, BatchNorm1d, BatchNorm2d};
+pub use container::{ModuleDict, ModuleList};
+pub use conv::{Conv, Conv1d, Conv2d, Conv3d};
+pub use dropout::Dropout;
+pub use embedding::Embedding;
+pub use group_norm::{GroupNorm, InstanceNorm2d};
+pub use hooks::{HookHandle, register_forward_hook, register_forward_pre_hook};
+pub use layer_norm::LayerNorm;
+pub use linear::Linear;
+pub use module::{Buffer, Module};
+pub use parallel::{ColumnParallelLinear, RowParallelLinear};
+pub use pool::{AdaptiveAvgPool2d, AvgPool2d, MaxPool2d};
+pub use positional_encoding::SinusoidalPositionalEncoding;
+pub use rnn::{RnnCell, scan};
+pub use rotary::RotaryEmbedding;
+pub use sparse_linear::SparseLinear;
+pub use spectral_norm::SpectralNorm;
+pub use state_dict::{LoadReport, StateDict, StateDictError};
+pub use summary::{LayerSummary, Summary, summary};
+pub use transformer::{
+    MultiheadAttention, TransformerDecoder, TransformerDecoderLayer, TransformerEncoder,
+    TransformerEncoderLayer,
+};
+pub use weight_norm::{WeightNorm, weight_norm};
@@ -0,0 +1,404 @@
+use std::cell::RefCell;
+use std::fmt;
+
+use crate::nn::hooks;
+use crate::nn::state_dict::{self, LoadReport, StateDict, StateDictError};
+use crate::nn::summary;
+use crate::tensor::Tensor;
+
+/// A layer, or a model built from layers: a differentiable function of
+/// its input with trainable parameters.
+///
+/// Parameters are leaves tracked for gradients, owned by the module.
+/// [`Module::parameters`] lists them, so that optimizers and utilities
+/// (zeroing gradients, counting weights) work the same on any model; a
+/// module made of submodules lists theirs, in a fixed order. State that is
+/// not trained but belongs to the model, such as running statistics, is
+/// kept in [`Buffer`]s and listed by [`Module::buffers`].
+///
+/// A module made of submodules also lists them in [`Module::children`],
+/// which lets operations walk the whole module tree: [`Module::train`] and
+/// [`Module::eval`] switch every layer that behaves differently at
+/// inference (dropout, batch norm) in one call.
+///
+/// Submodules are run through [`Module::call`], which is `forward` plus
+/// the [forward hooks](crate::nn::register_forward_hook) registered on
+/// the module.
+///
+/// # Example
+/// ```
+/// use delta::nn::{Linear, Module};
+/// use delta::random::Rng;
+/// use delta::tensor::Tensor;
+///
+/// struct Mlp {
+///     hidden: Linear,
+///     out: Linear,
+/// }
+///
+/// impl Module for Mlp {
+///     fn forward(&self, x: &Tensor) -> Tensor {
+///         self.out.forward(&self.hidden.forward(x).tanh())
+///     }
+///
+///     fn parameters(&self) -> Vec<&Tensor> {
+///         let mut params = self.hidden.parameters();
+///         params.extend(self.out.parameters());
+///         params
+///     }
+///
+///     fn parameters_mut(&mut self) -> Vec<&mut Tensor> {
+///         let mut params = self.hidden.parameters_mut();
+///         params.extend(self.out.parameters_mut());
+///         params
+///     }
+/// }
+///
+/// let mut rng = Rng::new(0);
+/// let mlp = Mlp { hidden: Linear::new(3, 8, &mut rng), out: Linear::new(8, 1, &mut rng) };
+/// assert_eq!(mlp.num_parameters(), 3 * 8 + 8 + 8 + 1);
+///
+/// mlp.forward(&Tensor::from_vec(vec![0.5, -1.0, 2.0], &[1, 3])).sum().backward();
+/// assert!(mlp.parameters().iter().all(|p| p.grad().is_some()));
+///
+/// // parameters_mut lets a copy load the weights by name
+/// let mut copy = Mlp { hidden: Linear::new(3, 8, &mut rng), out: Linear::new(8, 1, &mut rng) };
+/// copy.load_state_dict(&mlp.state_dict(), true).unwrap();
+/// assert_eq!(copy.out.weight().to_vec(), mlp.out.weight().to_vec());
+/// ```
+///
+/// Listing the submodules makes `train` and `eval` reach them:
+/// ```
+/// use delta::nn::{Dropout, Linear, Module};
+/// use delta::random::Rng;
+/// use delta::tensor::Tensor;
+///
+/// struct Net {
+///     hidden: Linear,
+///     dropout: Dropout,
+/// }
+///
+/// impl Module for Net {
+///     fn forward(&self, x: &Tensor) -> Tensor {
+///         self.dropout.forward(&self.hidden.forward(x).relu())
+///     }
+///
+///     fn parameters(&self) -> Vec<&Tensor> {
+///         self.hidden.parameters()
+///     }
+///
+///     fn parameters_mut(&mut self) -> Vec<&mut Tensor> {
+///         self.hidden.parameters_mut()
+///     }
+///
+///     fn children(&self) -> Vec<(String, &dyn Module)> {
+///         vec![("hidden".into(), &self.hidden), ("dropout".into(), &self.dropout)]
+///     }
+/// }
+///
+/// let mut rng = Rng::new(0);
+/// let net = Net { hidden: Linear::new(4, 4, &mut rng), dropout: Dropout::new(0.5, &mut rng) };
+/// net.eval();
+/// assert!(!net.dropout.is_training());
+/// let x = Tensor::from_vec(vec![1.0; 4], &[1, 4]);
+/// assert_eq!(net.forward(&x).to_vec(), net.forward(&x).to_vec());
+/// ```
+pub trait Module {
+    /// Apply the module to `input`.
+    fn forward(&self, input: &Tensor) -> Tensor;
+
+    /// Apply the module like [`Module::forward`], running its
+    /// [forward hooks](crate::nn::register_forward_hook) and visibly to
+    /// tools that watch a model run:
+    /// [`summary`](crate::nn::summary) records the output shape of each
+    /// layer called this way. Modules made of submodules call them through
+    /// `call`. Not meant to be overridden.
+    fn call(&self, input: &Tensor) -> Tensor {
+        call_with(self, input, |input| self.forward(input))
+    }
+
+    /// The full name of the module's type, e.g. `delta::nn::linear::Linear`.
+    fn type_name(&self) -> &'static str {
+        std::any::type_name::<Self>()
+    }
+
+    /// The trainable parameters, in a fixed order.
+    fn parameters(&self) -> Vec<&Tensor>;
+
+    /// The non-trainable state, in a fixed order. None by default.
+    fn buffers(&self) -> Vec<&Buffer> {
+        Vec::new()
+    }
+
+    /// The direct submodules, with their names (a field name, or an index
+    /// for a list of layers), in a fixed order. None by default.
+    fn children(&self) -> Vec<(String, &dyn Module)> {
+        Vec::new()
+    }
+
+    /// The parameters with hierarchical names, in the order of
+    /// [`Module::parameters`]: a submodule's parameters are named
+    /// `child.name` after the child they belong to, e.g.
+    /// `encoder.layers.3.self_attn.q_proj.weight`.
+    ///
+    /// By default, a parameter found in no child is named by its position
+    /// in [`Module::parameters`]; layers owning their parameters override
+    /// this with field names (`weight`, `bias`, ...).
+    fn named_parameters(&self) -> Vec<(String, &Tensor)> {
+        let from_children = self
+            .children()
+            .into_iter()
+            .flat_map(|(child, module)| prefixed(&child, module.named_parameters()))
+            .collect();
+        name_by_owner(self.parameters(), from_children)
+    }
+
+    /// The parameters, mutably, in the order of [`Module::parameters`];
+    /// used by [`Module::load_state_dict`] to overwrite them.
+    fn parameters_mut(&mut self) -> Vec<&mut Tensor>;
+
+    /// The buffers with hierarchical names, in the order of
+    /// [`Module::buffers`], named like [`Module::named_parameters`].
+    fn named_buffers(&self) -> Vec<(String, &Buffer)> {
+        let from_children = self
+            .children()
+            .into_iter()
+            .flat_map(|(child, module)| prefixed(&child, module.named_buffers()))
+            .collect();
+        name_by_owner(self.buffers(), from_children)
+    }
+
+    /// A copy of every parameter and buffer by hierarchical name; see
+    /// [`StateDict`].
+    fn state_dict(&self) -> StateDict {
+        state_dict::collect(self)
+    }
+
+    /// Overwrite parameters and buffers with the values in `state`, by
+    /// name. Parameters stay the same leaves, so gradients and anything
+    /// holding them keep working.
+    ///
+    /// With `strict`, `state` must hold exactly this module's names.
+    /// Otherwise names missing on either side are skipped and listed in
+    /// the [`LoadReport`], for loading part of a model (a pretrained
+    /// backbone under a new head). A value of the wrong shape is always
+    /// an error. Nothing is written unless loading succeeds.
+    fn load_state_dict(
+        &mut self,
+        state: &StateDict,
+        strict: bool,
+    ) -> Result<LoadReport, StateDictError> {
+        state_dict::load(self, state, strict)
+    }
+
+    /// Every module in the tree below this one, depth first and each
+    /// before its own children, named by its path (`layers.0.self_attn`).
+    /// The module itself comes first, named `""`.
+    fn named_modules(&self) -> Vec<(String, &dyn Module)>
+    where
+        Self: Sized,
+    {
+        let mut modules: Vec<(String, &dyn Module)> = vec![(String::new(), self)];
+        collect_modules("", self, &mut modules);
+        modules
+    }
+
+    /// Call `f` on every module of the tree with its name, in the order of
+    /// [`Module::named_modules`].
+    fn apply(&self, mut f: impl FnMut(&str, &dyn Module))
+    where
+        Self: Sized,
+    {
+        for (name, module) in self.named_modules() {
+            f(&name, module);
+        }
+    }
+
+    /// Switch this module and every submodule between training (`true`)
+    /// and evaluation mode. Modules start in training mode.
+    ///
+    /// By default this forwards to [`Module::children`]; layers that
+    /// behave differently at inference override it to record the mode.
+    fn train(&self, mode: bool) {
+        for (_, child) in self.children() {
+            child.train(mode);
+        }
+    }
+
+    /// Switch to evaluation mode: `train(false)`.
+    fn eval(&self) {
+        self.train(false);
+    }
+
+    /// Stop tracking gradients of every parameter, with
+    /// [`Tensor::requires_grad_`]: they act as constants in later
+    /// forward passes, get no gradients and are left out of
+    /// [`Module::trainable_parameters`]. Freeze a pretrained backbone
+    /// while fine-tuning a new head, say.
+    fn freeze(&self) {
+        for p in self.parameters() {
+            p.requires_grad_(false);
+        }
+    }
+
+    /// Undo [`Module::freeze`]: track gradients of every parameter again.
+    fn unfreeze(&self) {
+        for p in self.parameters() {
+            p.requires_grad_(true);
+        }
+    }
+
+    /// The parameters still tracking gradients, the ones to hand an
+    /// optimizer, in the order of [`Module::parameters`].
+    fn trainable_parameters(&self) -> Vec<&Tensor> {
+        self.parameters()
+            .into_iter()
+            .filter(|p| p.tracks_grad())
+            .collect()
+    }
+
+    /// Total number of scalar parameters.
+    fn num_parameters(&self) -> usize {
+        self.parameters().iter().map(|p| p.nelems()).sum()
+    }
+
+    /// Clear the gradients of every parameter.
+    fn zero_grad(&self) {
+        for p in self.parameters() {
+            p.zero_grad();
+        }
+    }
+}
+
+/// [`Module::call`] for an entry point of `module` other than `forward`
+/// (cross-attention, a decoder layer given memory): the forward hooks run
+/// around `run`, whose output is recorded for
+/// [`summary`](crate::nn::summary). The hooks see, and may replace, the
+/// first input only; `run` captures the others.
+pub(crate) fn call_with<M: Module + ?Sized>(
+    module: &M,
+    input: &Tensor,
+    run: impl FnOnce(&Tensor) -> Tensor,
+) -> Tensor {
+    let key = module_key(module);
+    let replaced = hooks::run_pre(key, input);
+    let input = replaced.as_ref().unwrap_or(input);
+    let output = hooks::run_post(key, input, run(input));
+    summary::observe(key, &output);
+    output
+}
+
+/// Identifies a module while it is alive: its address and type, since a
+/// module and its first field may share an address. Values of a
+/// zero-sized type have no address of their own, so all modules of such
+/// a type share one key.
+pub(crate) fn module_key<M: Module + ?Sized>(module: &M) -> (usize, &'static str) {
+    (module as *const M as *const () as usize, module.type_name())
+}
+
+/// `items` named `prefix.name`.
+fn prefixed<'a, T: ?Sized>(prefix: &str, items: Vec<(String, &'a T)>) -> Vec<(String, &'a T)> {
+    items
+        .into_iter()
+        .map(|(name, item)| (format!("{}.{}", prefix, name), item))
+        .collect()
+}
+
+/// Name each of `own` after the entry of `from_children` it is, or by its
+/// position if it belongs to no child.
+fn name_by_owner<'a, T>(
+    own: Vec<&'a T>,
+    from_children: Vec<(String, &'a T)>,
+) -> Vec<(String, &'a T)> {
+    own.into_iter()
+        .enumerate()
+        .map(|(i, item)| {
+            let name = from_children
+                .iter()
+                .find(|(_, c)| std::ptr::eq(*c, item))
+                .map_or_else(|| i.to_string(), |(name, _)| name.clone());
+            (name, item)
+        })
+        .collect()
+}
+
+/// Append the modules below `module`, named under `prefix`.
+fn collect_modules<'a>(
+    prefix: &str,
+    module: &'a dyn Module,
+    out: &mut Vec<(String, &'a dyn Module)>,
+) {
+    for (name, child) in module.children() {
+        let path = if prefix.is_empty() {
+            name
+        } else {
+            format!("{}.{}", prefix, name)
+        };
+        out.push((path.clone(), child));
+        collect_modules(&path, child, out);
+    }
+}
+
+/// Non-trainable state of a module, updated by the module itself (e.g. the
+/// running mean of a batch norm) rather than by an optimizer.
+///
+/// Holds a tensor outside any graph. Modules update buffers from
+/// `forward`, which only has `&self`, so the value lives in a cell:
+/// [`Buffer::get`] returns a cheap copy of the current value and
+/// [`Buffer::set`] replaces it.
+///
+/// # Example
+/// ```
+/// use delta::nn::Buffer;
+/// use delta::tensor::Tensor;
+///
+/// let count = Buffer::new(Tensor::from_vec(vec![0.0], &[1]));
+/// count.set(count.get().scalar_add(1.0));
+/// assert_eq!(count.get().to_vec(), vec![1.0]);
+/// ```
+pub struct Buffer(RefCell<Tensor>);
+
+impl Buffer {
+    /// A buffer holding `value`, detached from any graph.
+    pub fn new(value: Tensor) -> Self {
+        Self(RefCell::new(value.detach()))
+    }
+
+    /// The current value.
+    pub fn get(&self) -> Tensor {
+        self.0.borrow().clone()
+    }
+
+    /// Replace the value, detached from any graph.
+    ///
+    /// # Panics
+    /// Panics if `value` has a different shape.
+    pub fn set(&self, value: Tensor) {
+        let mut current = self.0.borrow_mut();
+        assert_eq!(
+            value.shape(),
+            current.shape(),
+            "buffer of shape {:?} cannot hold a value of shape {:?}",
+            current.shape(),
+            value.shape()
+        );
+        *current = value.detach();
+    }
+
+    pub fn shape(&self) -> Vec<usize> {
+        self.0.borrow().shape().to_vec()
+    }
+}
+
+impl Clone for Buffer {
+    /// A buffer holding the same value, updated independently.
+    fn clone(&self) -> Self {
+        Self::new(self.get())
+    }
+}
+
+impl fmt::Debug for Buffer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Buffer").field(&*self.0.borrow()).finish()
+    }
+}
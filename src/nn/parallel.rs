@@ -0,0 +1,474 @@
+//! Tensor-parallel layers: one weight split across devices.
+//!
+//! With tensor (model) parallelism, each of `world_size` ranks holds a
+//! shard of every large weight and computes its part of the layer; the
+//! ranks then exchange activations through two collectives, gathering the
+//! shards of an output or summing partial results. [`Collective`] is the
+//! hook through which a layer calls them: a distributed runtime implements
+//! it over its transport, while [`SingleProcess`] is the trivial group of
+//! one.
+//!
+//! The layers split a [`Linear`] the two ways that compose:
+//! [`ColumnParallelLinear`] splits the outputs, [`RowParallelLinear`] the
+//! inputs. A column-parallel layer without gathering feeds a row-parallel
+//! one directly, so a block such as an MLP needs a single reduction.
+//!
+//! # Example
+//! ```
+//! use std::rc::Rc;
+//!
+//! use delta::nn::parallel::SingleProcess;
+//! use delta::nn::{ColumnParallelLinear, Linear, Module, RowParallelLinear};
+//! use delta::random::Rng;
+//! use delta::tensor::Tensor;
+//!
+//! let mut rng = Rng::new(0);
+//! let (up, down) = (Linear::new(4, 16, &mut rng), Linear::new(16, 4, &mut rng));
+//! let x = Tensor::randn(&[2, 4], &mut rng);
+//!
+//! // Every rank runs the same code on its own shards
+//! let group = Rc::new(SingleProcess);
+//! let up_shard = ColumnParallelLinear::from_linear(&up, group.clone()).gather_output(false);
+//! let down_shard = RowParallelLinear::from_linear(&down, group);
+//! let y = down_shard.forward(&up_shard.forward(&x).relu());
+//!
+//! let expected = down.forward(&up.forward(&x).relu());
+//! assert!(y.to_vec().iter().zip(expected.to_vec()).all(|(a, b)| (a - b).abs() < 1e-5));
+//! ```
+
+use std::fmt;
+use std::rc::Rc;
+
+use crate::nn::{Linear, Module};
+use crate::random::Rng;
+use crate::tensor::Tensor;
+
+/// The collectives tensor-parallel layers call, implemented by a
+/// distributed runtime on each rank.
+///
+/// Every rank of the group calls the same collectives in the same order.
+/// For training, implementations should record the collectives as ops
+/// whose backward passes are the dual collectives: the gradient of a
+/// gather is this rank's slice of the incoming gradient, and the gradient
+/// of a sum is the incoming gradient, unchanged.
+pub trait Collective {
+    /// Index of this rank, `0..world_size()`.
+    fn rank(&self) -> usize;
+
+    /// Number of ranks in the group.
+    fn world_size(&self) -> usize;
+
+    /// Concatenate the shards `[..., n]` of every rank along the last
+    /// dimension, in rank order, giving `[..., world_size · n]` on every
+    /// rank.
+    fn all_gather(&self, shard: &Tensor) -> Tensor;
+
+    /// Element-wise sum of `partial` over every rank, on every rank.
+    fn all_reduce(&self, partial: &Tensor) -> Tensor;
+}
+
+/// A group of one: rank 0 of 1, where the collectives are the identity.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SingleProcess;
+
+impl Collective for SingleProcess {
+    fn rank(&self) -> usize {
+        0
+    }
+
+    fn world_size(&self) -> usize {
+        1
+    }
+
+    fn all_gather(&self, shard: &Tensor) -> Tensor {
+        shard.clone()
+    }
+
+    fn all_reduce(&self, partial: &Tensor) -> Tensor {
+        partial.clone()
+    }
+}
+
+/// Rank and world size of `group`, checked to split `n` evenly.
+fn shard_of(group: &dyn Collective, n: usize, what: &str) -> (usize, usize) {
+    let (rank, world) = (group.rank(), group.world_size());
+    assert!(
+        rank < world && n.is_multiple_of(world),
+        "cannot split {} {} over {} ranks (this is rank {})",
+        n,
+        what,
+        world,
+        rank
+    );
+    (rank, n / world)
+}
+
+/// A fully connected layer `y = x Wᵀ + b` whose output features are split
+/// across the ranks of a [`Collective`] group.
+///
+/// Each rank holds `out_features / world_size` rows of the weight and the
+/// matching slice of the bias, and computes that slice of the output. By
+/// default, [`Module::forward`] then gathers the full output on every
+/// rank; with [`ColumnParallelLinear::gather_output`] off it returns the
+/// local slice, ready for a [`RowParallelLinear`].
+pub struct ColumnParallelLinear {
+    local: Linear,
+    group: Rc<dyn Collective>,
+    gather_output: bool,
+}
+
+impl ColumnParallelLinear {
+    /// This rank's shard of a layer initialized as [`Linear::new`]. Ranks
+    /// seeding `rng` alike hold the shards of the same layer.
+    pub fn new(
+        in_features: usize,
+        out_features: usize,
+        group: Rc<dyn Collective>,
+        rng: &mut Rng,
+    ) -> Self {
+        Self::from_linear(&Linear::new(in_features, out_features, rng), group)
+    }
+
+    /// This rank's shard of `linear`, as fresh parameters.
+    ///
+    /// # Panics
+    /// Panics if the output features do not split evenly over the group.
+    pub fn from_linear(linear: &Linear, group: Rc<dyn Collective>) -> Self {
+        let (rank, rows) = shard_of(&*group, linear.out_features(), "output features");
+        let slice = |t: &Tensor, width: usize| {
+            let data = t.to_vec();
+            data[rank * rows * width..(rank + 1) * rows * width].to_vec()
+        };
+        let in_features = linear.in_features();
+        let weight = Tensor::from_vec(slice(linear.weight(), in_features), &[rows, in_features]);
+        let bias = linear
+            .bias()
+            .map(|b| Tensor::from_vec(slice(b, 1), &[rows]));
+        Self {
+            local: Linear::from_parts(weight, bias),
+            group,
+            gather_output: true,
+        }
+    }
+
+    /// Whether [`Module::forward`] gathers the full output (the default)
+    /// or returns this rank's slice.
+    pub fn gather_output(self, gather: bool) -> Self {
+        Self {
+            gather_output: gather,
+            ..self
+        }
+    }
+
+    /// The local shard: weight `[out_features / world_size, in_features]`.
+    pub fn local(&self) -> &Linear {
+        &self.local
+    }
+
+    pub fn group(&self) -> &dyn Collective {
+        &*self.group
+    }
+
+    /// This rank's slice of the output, `[..., out_features / world_size]`,
+    /// without communication.
+    pub fn forward_local(&self, input: &Tensor) -> Tensor {
+        self.local.call(input)
+    }
+}
+
+impl Module for ColumnParallelLinear {
+    /// # Panics
+    /// Panics if the last dimension of `input` is not `in_features`.
+    fn forward(&self, input: &Tensor) -> Tensor {
+        let y = self.forward_local(input);
+        if self.gather_output {
+            self.group.all_gather(&y)
+        } else {
+            y
+        }
+    }
+
+    /// The local weight and bias shards.
+    fn parameters(&self) -> Vec<&Tensor> {
+        self.local.parameters()
+    }
+
+    fn parameters_mut(&mut self) -> Vec<&mut Tensor> {
+        self.local.parameters_mut()
+    }
+
+    fn children(&self) -> Vec<(String, &dyn Module)> {
+        vec![("local".into(), &self.local)]
+    }
+}
+
+impl fmt::Debug for ColumnParallelLinear {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ColumnParallelLinear")
+            .field("local", &self.local)
+            .field("rank", &self.group.rank())
+            .field("world_size", &self.group.world_size())
+            .field("gather_output", &self.gather_output)
+            .finish()
+    }
+}
+
+/// A fully connected layer `y = x Wᵀ + b` whose input features are split
+/// across the ranks of a [`Collective`] group.
+///
+/// Each rank holds `in_features / world_size` columns of the weight and
+/// takes the matching slice of the input, `[..., in_features /
+/// world_size]` (the output of a [`ColumnParallelLinear`] without
+/// gathering). [`Module::forward`] sums the partial products over the group
+/// and then adds the bias, which every rank holds whole.
+pub struct RowParallelLinear {
+    local: Linear,
+    bias: Option<Tensor>,
+    group: Rc<dyn Collective>,
+}
+
+impl RowParallelLinear {
+    /// This rank's shard of a layer initialized as [`Linear::new`]. Ranks
+    /// seeding `rng` alike hold the shards of the same layer.
+    pub fn new(
+        in_features: usize,
+        out_features: usize,
+        group: Rc<dyn Collective>,
+        rng: &mut Rng,
+    ) -> Self {
+        Self::from_linear(&Linear::new(in_features, out_features, rng), group)
+    }
+
+    /// This rank's shard of `linear`, as fresh parameters.
+    ///
+    /// # Panics
+    /// Panics if the input features do not split evenly over the group.
+    pub fn from_linear(linear: &Linear, group: Rc<dyn Collective>) -> Self {
+        let (rank, cols) = shard_of(&*group, linear.in_features(), "input features");
+        let (out, in_features) = (linear.out_features(), linear.in_features());
+        let data = linear.weight().to_vec();
+        let weight = (0..out)
+            .flat_map(|r| data[r * in_features + rank * cols..][..cols].to_vec())
+            .collect();
+        Self {
+            local: Linear::from_parts(Tensor::from_vec(weight, &[out, cols]), None),
+            bias: linear
+                .bias()
+                .map(|b| Tensor::from_vec(b.to_vec(), &[out]).requires_grad(true)),
+            group,
+        }
+    }
+
+    /// The local shard: weight `[out_features, in_features / world_size]`,
+    /// without the bias.
+    pub fn local(&self) -> &Linear {
+        &self.local
+    }
+
+    pub fn bias(&self) -> Option<&Tensor> {
+        self.bias.as_ref()
+    }
+
+    pub fn group(&self) -> &dyn Collective {
+        &*self.group
+    }
+
+    /// This rank's partial product, `[..., out_features]`, without the
+    /// bias or communication.
+    pub fn forward_local(&self, input: &Tensor) -> Tensor {
+        self.local.call(input)
+    }
+}
+
+impl Module for RowParallelLinear {
+    /// # Panics
+    /// Panics if the last dimension of `input` is not this rank's share
+    /// of `in_features`.
+    fn forward(&self, input: &Tensor) -> Tensor {
+        let y = self.group.all_reduce(&self.forward_local(input));
+        match &self.bias {
+            Some(b) => y.add(&b.broadcast_to(y.shape())),
+            None => y,
+        }
+    }
+
+    /// The local weight shard, then the bias.
+    fn parameters(&self) -> Vec<&Tensor> {
+        let mut params = self.local.parameters();
+        params.extend(&self.bias);
+        params
+    }
+
+    fn parameters_mut(&mut self) -> Vec<&mut Tensor> {
+        let mut params = self.local.parameters_mut();
+        params.extend(&mut self.bias);
+        params
+    }
+
+    /// `local.weight`, then `bias`.
+    fn named_parameters(&self) -> Vec<(String, &Tensor)> {
+        let mut params = vec![("local.weight".to_string(), self.local.weight())];
+        params.extend(self.bias.iter().map(|b| ("bias".to_string(), b)));
+        params
+    }
+
+    fn children(&self) -> Vec<(String, &dyn Module)> {
+        vec![("local".into(), &self.local)]
+    }
+}
+
+impl fmt::Debug for RowParallelLinear {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RowParallelLinear")
+            .field("local", &self.local)
+            .field("bias", &self.bias)
+            .field("rank", &self.group.rank())
+            .field("world_size", &self.group.world_size())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::autograd::testing::assert_close;
+    use std::cell::RefCell;
+
+    /// Rank `rank` of a simulated group, logging the collectives called
+    /// and answering them as if every other rank contributed zeros.
+    struct Probe {
+        rank: usize,
+        world: usize,
+        calls: RefCell<Vec<(&'static str, Vec<usize>)>>,
+    }
+
+    impl Probe {
+        fn new(rank: usize, world: usize) -> Rc<Self> {
+            Rc::new(Self {
+                rank,
+                world,
+                calls: RefCell::new(Vec::new()),
+            })
+        }
+    }
+
+    impl Collective for Probe {
+        fn rank(&self) -> usize {
+            self.rank
+        }
+
+        fn world_size(&self) -> usize {
+            self.world
+        }
+
+        fn all_gather(&self, shard: &Tensor) -> Tensor {
+            self.calls
+                .borrow_mut()
+                .push(("all_gather", shard.shape().to_vec()));
+            let n = *shard.shape().last().unwrap();
+            let mut shape = shard.shape().to_vec();
+            *shape.last_mut().unwrap() *= self.world;
+            let data = shard.to_vec();
+            let out = data
+                .chunks(n)
+                .flat_map(|row| {
+                    let mut full = vec![0.0; n * self.world];
+                    full[self.rank * n..][..n].copy_from_slice(row);
+                    full
+                })
+                .collect();
+            Tensor::from_vec(out, &shape)
+        }
+
+        fn all_reduce(&self, partial: &Tensor) -> Tensor {
+            self.calls
+                .borrow_mut()
+                .push(("all_reduce", partial.shape().to_vec()));
+            partial.clone()
+        }
+    }
+
+    #[test]
+    fn test_column_shards_concatenate_to_full_output() {
+        let mut rng = Rng::new(1);
+        let full = Linear::new(3, 6, &mut rng);
+        let x = Tensor::randn(&[4, 3], &mut rng);
+        let expected = full.forward(&x).to_vec();
+        for rank in 0..3 {
+            let shard = ColumnParallelLinear::from_linear(&full, Probe::new(rank, 3));
+            assert_eq!(shard.local().weight().shape(), &[2, 3]);
+            let y = shard.forward_local(&x).to_vec();
+            let columns: Vec<f32> = (0..4)
+                .flat_map(|r| expected[r * 6 + rank * 2..][..2].to_vec())
+                .collect();
+            assert_close(&y, &columns, 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_row_partials_sum_to_full_output() {
+        let mut rng = Rng::new(2);
+        let full = Linear::new(4, 3, &mut rng);
+        let x = Tensor::randn(&[2, 4], &mut rng).to_vec();
+        let expected = full.forward(&Tensor::from_vec(x.clone(), &[2, 4])).to_vec();
+        let mut total = [0.0; 6];
+        for rank in 0..2 {
+            let shard = RowParallelLinear::from_linear(&full, Probe::new(rank, 2));
+            let xs: Vec<f32> = (0..2)
+                .flat_map(|r| x[r * 4 + rank * 2..][..2].to_vec())
+                .collect();
+            let partial = shard.forward_local(&Tensor::from_vec(xs, &[2, 2]));
+            for (t, p) in total.iter_mut().zip(partial.to_vec()) {
+                *t += p;
+            }
+        }
+        let bias = full.bias().unwrap().to_vec();
+        let total: Vec<f32> = total
+            .iter()
+            .enumerate()
+            .map(|(i, t)| t + bias[i % 3])
+            .collect();
+        assert_close(&total, &expected, 1e-5);
+    }
+
+    #[test]
+    fn test_forward_calls_the_hooks() {
+        let mut rng = Rng::new(3);
+        let group = Probe::new(1, 2);
+        let column = ColumnParallelLinear::new(3, 4, group.clone(), &mut rng);
+        let y = column.forward(&Tensor::randn(&[5, 3], &mut rng));
+        assert_eq!(y.shape(), &[5, 4]);
+        // Rank 1 holds the second half of the outputs
+        assert!(y.to_vec().chunks(4).all(|row| row[..2] == [0.0, 0.0]));
+
+        let row = RowParallelLinear::new(4, 2, group.clone(), &mut rng);
+        row.forward(&column.forward_local(&Tensor::randn(&[5, 3], &mut rng)));
+        assert_eq!(
+            *group.calls.borrow(),
+            vec![("all_gather", vec![5, 2]), ("all_reduce", vec![5, 2])]
+        );
+        assert_eq!(row.parameters().len(), 2);
+        assert_eq!(row.num_parameters(), 2 * 2 + 2);
+    }
+
+    #[test]
+    fn test_single_process_matches_linear_and_trains() {
+        let mut rng = Rng::new(4);
+        let full = Linear::new(3, 2, &mut rng);
+        let column = ColumnParallelLinear::from_linear(&full, Rc::new(SingleProcess));
+        let row = RowParallelLinear::from_linear(&full, Rc::new(SingleProcess));
+        let x = Tensor::randn(&[2, 3], &mut rng);
+        let expected = full.forward(&x).to_vec();
+        assert_close(&column.forward(&x).to_vec(), &expected, 1e-5);
+        assert_close(&row.forward(&x).to_vec(), &expected, 1e-5);
+        row.forward(&x).sum().backward();
+        assert!(row.parameters().iter().all(|p| p.grad().is_some()));
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot split 5 output features over 2 ranks")]
+    fn test_uneven_split() {
+        let full = Linear::new(3, 5, &mut Rng::new(0));
+        ColumnParallelLinear::from_linear(&full, Probe::new(0, 2));
+    }
+}
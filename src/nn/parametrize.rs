@@ -0,0 +1,331 @@
+//! Constrained parameters through reparametrization.
+//!
+//! Instead of optimizing a constrained parameter directly (and projecting
+//! it back after every step), optimize an unconstrained `raw` tensor and
+//! compute the parameter from it with a differentiable map whose every
+//! output satisfies the constraint:
+//!
+//! ```text
+//!   raw (any values) ── forward ──> value (positive / unit-norm / orthogonal)
+//! ```
+//!
+//! Gradients flow through `forward` into `raw`, so any optimizer keeps the
+//! constraint without knowing about it. With [`LBFGS`](crate::optim::LBFGS),
+//! hand it the raw tensors and apply the parametrization in the closure:
+//!
+//! ```
+//! use delta::nn::parametrize::{Parametrization, Positive};
+//! use delta::optim::LBFGS;
+//! use delta::tensor::Tensor;
+//!
+//! // A scale that must stay positive, fitted towards 2
+//! let raw = Positive.right_inverse(&Tensor::from_vec(vec![0.5], &[1])).unwrap();
+//! let mut opt = LBFGS::new(vec![raw]);
+//! opt.step(|p| Positive.forward(&p[0]).scalar_add(-2.0).powi(2).sum());
+//! let scale = Positive.forward(&opt.params()[0]);
+//! assert!((scale.get(&[0]) - 2.0).abs() < 1e-3);
+//! ```
+
+use crate::tensor::Tensor;
+
+/// A differentiable map from unconstrained tensors onto a constraint set.
+pub trait Parametrization {
+    /// The constrained value for `raw`, recorded for autograd.
+    fn forward(&self, raw: &Tensor) -> Tensor;
+
+    /// A raw tensor that `forward` maps to `value`, used to start from a
+    /// given value. `None` if the parametrization cannot be inverted.
+    fn right_inverse(&self, value: &Tensor) -> Option<Tensor> {
+        let _ = value;
+        None
+    }
+}
+
+/// Strictly positive values: `softplus(raw)`.
+///
+/// Softplus is the usual choice over `exp`: it grows linearly rather than
+/// exponentially, so large raw values do not make for huge gradients.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Positive;
+
+impl Parametrization for Positive {
+    fn forward(&self, raw: &Tensor) -> Tensor {
+        raw.softplus()
+    }
+
+    /// The inverse of softplus, `y + ln(1 - e^-y)`.
+    ///
+    /// # Panics
+    /// Panics if `value` has an element that is not positive.
+    fn right_inverse(&self, value: &Tensor) -> Option<Tensor> {
+        Some(value.map(|y| {
+            assert!(y > 0.0, "Positive requires positive values, got {}", y);
+            y + (-(-y).exp_m1()).ln()
+        }))
+    }
+}
+
+/// Vectors of unit Euclidean norm along `dim`: `raw / ‖raw‖`.
+///
+/// With `dim = 1` every row of a matrix is normalized, with `dim = 0`
+/// every column. A raw lane of all zeros has no direction and produces
+/// NaNs.
+#[derive(Debug, Clone, Copy)]
+pub struct UnitNorm {
+    pub dim: usize,
+}
+
+impl Parametrization for UnitNorm {
+    fn forward(&self, raw: &Tensor) -> Tensor {
+        let norm = raw.powi(2).sum_dim(self.dim, true).sqrt();
+        raw.div(&norm.broadcast_to(raw.shape()))
+    }
+
+    /// A unit-norm value is its own preimage.
+    fn right_inverse(&self, value: &Tensor) -> Option<Tensor> {
+        Some(value.clone())
+    }
+}
+
+/// Matrices with orthonormal columns, as a product of Householder
+/// reflections.
+///
+/// For a raw `[n, k]` matrix (n >= k), column i below and on the diagonal
+/// holds a reflection vector vᵢ (the entries above the diagonal are
+/// ignored), and the value is the first k columns of
+///
+/// ```text
+///   Q = H₀ H₁ ... Hₖ₋₁,   Hᵢ = I - 2 vᵢ vᵢᵀ / (vᵢᵀ vᵢ)
+/// ```
+///
+/// Every reflection is orthogonal, so Q is too, whatever the raw values,
+/// and any matrix with orthonormal columns is reachable up to the signs
+/// of its columns. The same layout LAPACK uses for QR factors.
+///
+/// A raw column that is zero on and below the diagonal does not define a
+/// reflection and produces NaNs; the identity matrix is a safe start.
+/// There is no right inverse.
+///
+/// # Panics
+/// Panics if `raw` is not 2D or has more columns than rows.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Orthogonal;
+
+impl Parametrization for Orthogonal {
+    fn forward(&self, raw: &Tensor) -> Tensor {
+        assert_eq!(
+            raw.ndim(),
+            2,
+            "Orthogonal requires a 2D tensor, got shape {:?}",
+            raw.shape()
+        );
+        let (n, k) = (raw.shape()[0], raw.shape()[1]);
+        assert!(
+            n >= k,
+            "Orthogonal requires at least as many rows as columns, got shape {:?}",
+            raw.shape()
+        );
+
+        let mut eye = vec![0.0; n * k];
+        (0..k).for_each(|i| eye[i * k + i] = 1.0);
+        let mut q = Tensor::from_vec(eye, &[n, k]);
+
+        // Apply the reflections right to left: Q = H₀ (H₁ (... (Hₖ₋₁ I)))
+        for i in (0..k).rev() {
+            // vᵢ: column i of raw, zeroed above the diagonal
+            let mut select = vec![0.0; n * k];
+            (i..n).for_each(|row| select[row * k + i] = 1.0);
+            let mut one_hot = vec![0.0; k];
+            one_hot[i] = 1.0;
+            let v = raw
+                .mul(&Tensor::from_vec(select, &[n, k]))
+                .matmul(&Tensor::from_vec(one_hot, &[k, 1]));
+
+            // H q = q - 2 v (vᵀ q) / (vᵀ v)
+            let vtq = v.t().matmul(&q);
+            let vtv = v.t().matmul(&v).broadcast_to(&[n, k]);
+            q = q.sub(&v.matmul(&vtq).scalar_mul(2.0).div(&vtv));
+        }
+        q
+    }
+}
+
+/// A tensor kept under a constraint by a [`Parametrization`].
+///
+/// Holds the raw tensor, tracked for gradients, and computes the
+/// constrained value from it on demand. After a backward pass through
+/// [`Parametrized::value`], update the raw tensor from its gradient with
+/// [`Parametrized::set_raw`].
+///
+/// # Example
+/// ```
+/// use delta::nn::parametrize::{Parametrized, Positive};
+/// use delta::tensor::Tensor;
+///
+/// // Gradient descent pulling a positive scale towards -1: it approaches
+/// // 0 but never crosses it
+/// let mut scale = Parametrized::from_value(Tensor::from_vec(vec![1.0], &[1]), Positive);
+/// for _ in 0..100 {
+///     scale.value().scalar_add(1.0).powi(2).sum().backward();
+///     let raw = scale.raw();
+///     let step = raw.grad().unwrap().scalar_mul(0.5);
+///     scale.set_raw(raw.sub(&step));
+/// }
+/// let v = scale.value().get(&[0]);
+/// assert!(v > 0.0 && v < 0.1);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Parametrized<P: Parametrization> {
+    raw: Tensor,
+    parametrization: P,
+}
+
+impl<P: Parametrization> Parametrized<P> {
+    /// Wrap `raw` (any values) under `parametrization`.
+    pub fn new(raw: Tensor, parametrization: P) -> Self {
+        Self {
+            raw: raw.detach().requires_grad(true),
+            parametrization,
+        }
+    }
+
+    /// Start from a constrained `value`, through the right inverse.
+    ///
+    /// # Panics
+    /// Panics if the parametrization has no right inverse.
+    pub fn from_value(value: Tensor, parametrization: P) -> Self {
+        let raw = parametrization
+            .right_inverse(&value)
+            .expect("this parametrization has no right inverse, use Parametrized::new");
+        Self::new(raw, parametrization)
+    }
+
+    /// The constrained value, recorded so gradients reach the raw tensor.
+    pub fn value(&self) -> Tensor {
+        self.parametrization.forward(&self.raw)
+    }
+
+    /// The raw tensor (a leaf tracked for gradients).
+    pub fn raw(&self) -> &Tensor {
+        &self.raw
+    }
+
+    /// Replace the raw tensor's values, e.g. after an optimizer step.
+    ///
+    /// Only the values of `raw` are kept: it becomes a fresh leaf with no
+    /// gradient.
+    ///
+    /// # Panics
+    /// Panics if `raw` has a different shape.
+    pub fn set_raw(&mut self, raw: Tensor) {
+        assert_eq!(
+            raw.shape(),
+            self.raw.shape(),
+            "set_raw expected shape {:?}, got {:?}",
+            self.raw.shape(),
+            raw.shape()
+        );
+        self.raw = raw.detach().requires_grad(true);
+    }
+
+    pub fn parametrization(&self) -> &P {
+        &self.parametrization
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::autograd::testing::{assert_close, check_grad};
+
+    #[test]
+    fn test_positive() {
+        let raw = Tensor::from_vec(vec![-30.0, -1.0, 0.0, 5.0], &[4]);
+        assert!(Positive.forward(&raw).to_vec().iter().all(|&v| v > 0.0));
+
+        let value = Tensor::from_vec(vec![0.01, 0.5, 1.0, 20.0], &[4]);
+        let back = Positive.forward(&Positive.right_inverse(&value).unwrap());
+        assert_close(&back.to_vec(), &value.to_vec(), 1e-5);
+    }
+
+    #[test]
+    #[should_panic(expected = "positive values")]
+    fn test_positive_inverse_rejects_zero() {
+        Positive.right_inverse(&Tensor::zeros(&[1]));
+    }
+
+    #[test]
+    fn test_unit_norm() {
+        let raw = Tensor::from_vec(vec![3.0, 4.0, 0.0, -2.0], &[2, 2]);
+        let rows = UnitNorm { dim: 1 }.forward(&raw);
+        assert_close(&rows.to_vec(), &[0.6, 0.8, 0.0, -1.0], 1e-5);
+
+        let cols = UnitNorm { dim: 0 }.forward(&raw);
+        let norms = cols.powi(2).sum_dim(0, false).to_vec();
+        assert_close(&norms, &[1.0, 1.0], 1e-5);
+    }
+
+    #[test]
+    fn test_orthogonal() {
+        let raw = Tensor::from_vec(
+            vec![
+                0.3, -1.2, 0.8, 0.5, -0.4, 1.1, 0.9, 0.2, -0.7, 0.6, 0.1, -0.3,
+            ],
+            &[4, 3],
+        );
+        let q = Orthogonal.forward(&raw);
+        assert_eq!(q.shape(), &[4, 3]);
+        let gram = q.t().matmul(&q);
+        assert_close(
+            &gram.to_vec(),
+            &[1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0],
+            1e-5,
+        );
+
+        // The entries above the diagonal do not matter
+        let mut changed = raw.clone();
+        changed.set(&[0, 2], 5.0);
+        assert_close(&Orthogonal.forward(&changed).to_vec(), &q.to_vec(), 1e-5);
+    }
+
+    #[test]
+    #[should_panic(expected = "at least as many rows")]
+    fn test_orthogonal_wide() {
+        Orthogonal.forward(&Tensor::zeros(&[2, 3]));
+    }
+
+    #[test]
+    fn test_gradients() {
+        let x = Tensor::from_vec(vec![-1.0, 0.5, 2.0, 1.5, -0.3, 0.8], &[3, 2]);
+        check_grad(|t| Positive.forward(&t[0]), std::slice::from_ref(&x));
+        check_grad(
+            |t| UnitNorm { dim: 0 }.forward(&t[0]),
+            std::slice::from_ref(&x),
+        );
+        check_grad(|t| Orthogonal.forward(&t[0]), &[x]);
+    }
+
+    #[test]
+    fn test_parametrized() {
+        let value = Tensor::from_vec(vec![0.6, 0.8], &[1, 2]);
+        let mut p = Parametrized::from_value(value, UnitNorm { dim: 1 });
+        assert!(p.raw().is_leaf() && p.raw().node().is_some());
+
+        // Push the first component up: the value rotates but stays unit
+        let target = Tensor::from_vec(vec![1.0, 0.0], &[1, 2]);
+        for _ in 0..50 {
+            p.value().sub(&target).powi(2).sum().backward();
+            let raw = p.raw();
+            p.set_raw(raw.sub(&raw.grad().unwrap().scalar_mul(0.5)));
+        }
+        let v = p.value().to_vec();
+        assert!((v[0] * v[0] + v[1] * v[1] - 1.0).abs() < 1e-5);
+        assert!(v[0] > 0.99, "{:?}", v);
+    }
+
+    #[test]
+    #[should_panic(expected = "no right inverse")]
+    fn test_from_value_without_inverse() {
+        Parametrized::from_value(Tensor::zeros(&[2, 2]), Orthogonal);
+    }
+}
@@ -0,0 +1,175 @@
+use crate::nn::Module;
+use crate::tensor::Tensor;
+
+/// Max pooling over images `[batch, channels, h, w]`; see
+/// [`Tensor::max_pool2d`].
+///
+/// The stride defaults to the kernel size, giving non-overlapping
+/// windows.
+///
+/// # Example
+/// ```
+/// use delta::nn::{MaxPool2d, Module};
+/// use delta::tensor::Tensor;
+///
+/// let pool = MaxPool2d::new([2, 2]);
+/// let y = pool.forward(&Tensor::zeros(&[8, 3, 32, 32]));
+/// assert_eq!(y.shape(), &[8, 3, 16, 16]);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MaxPool2d {
+    kernel: [usize; 2],
+    stride: [usize; 2],
+    padding: [usize; 2],
+}
+
+impl MaxPool2d {
+    pub fn new(kernel: [usize; 2]) -> Self {
+        Self {
+            kernel,
+            stride: kernel,
+            padding: [0, 0],
+        }
+    }
+
+    pub fn stride(mut self, stride: [usize; 2]) -> Self {
+        self.stride = stride;
+        self
+    }
+
+    pub fn padding(mut self, padding: [usize; 2]) -> Self {
+        self.padding = padding;
+        self
+    }
+}
+
+impl Module for MaxPool2d {
+    fn forward(&self, input: &Tensor) -> Tensor {
+        input.max_pool2d(self.kernel, self.stride, self.padding)
+    }
+
+    fn parameters(&self) -> Vec<&Tensor> {
+        Vec::new()
+    }
+
+    fn parameters_mut(&mut self) -> Vec<&mut Tensor> {
+        Vec::new()
+    }
+}
+
+/// Average pooling over images `[batch, channels, h, w]`; see
+/// [`Tensor::avg_pool2d`].
+///
+/// The stride defaults to the kernel size, giving non-overlapping
+/// windows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AvgPool2d {
+    kernel: [usize; 2],
+    stride: [usize; 2],
+    padding: [usize; 2],
+}
+
+impl AvgPool2d {
+    pub fn new(kernel: [usize; 2]) -> Self {
+        Self {
+            kernel,
+            stride: kernel,
+            padding: [0, 0],
+        }
+    }
+
+    pub fn stride(mut self, stride: [usize; 2]) -> Self {
+        self.stride = stride;
+        self
+    }
+
+    pub fn padding(mut self, padding: [usize; 2]) -> Self {
+        self.padding = padding;
+        self
+    }
+}
+
+impl Module for AvgPool2d {
+    fn forward(&self, input: &Tensor) -> Tensor {
+        input.avg_pool2d(self.kernel, self.stride, self.padding)
+    }
+
+    fn parameters(&self) -> Vec<&Tensor> {
+        Vec::new()
+    }
+
+    fn parameters_mut(&mut self) -> Vec<&mut Tensor> {
+        Vec::new()
+    }
+}
+
+/// Average pooling to a fixed output size, whatever the input size; see
+/// [`Tensor::adaptive_avg_pool2d`].
+///
+/// # Example
+/// ```
+/// use delta::nn::{AdaptiveAvgPool2d, Conv2d, Linear, Module};
+/// use delta::random::Rng;
+/// use delta::tensor::{ConvOptions, Tensor};
+///
+/// let mut rng = Rng::new(0);
+/// let features = Conv2d::new(3, 16, [3, 3], ConvOptions::new(), &mut rng);
+/// let pool = AdaptiveAvgPool2d::new([1, 1]);
+/// let classifier = Linear::new(16, 10, &mut rng);
+///
+/// // The head takes any image size
+/// for size in [12, 29] {
+///     let x = Tensor::randn(&[2, 3, size, size], &mut rng);
+///     let pooled = pool.forward(&features.forward(&x)).reshape(&[2, 16]);
+///     assert_eq!(classifier.forward(&pooled).shape(), &[2, 10]);
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AdaptiveAvgPool2d {
+    output: [usize; 2],
+}
+
+impl AdaptiveAvgPool2d {
+    pub fn new(output: [usize; 2]) -> Self {
+        Self { output }
+    }
+}
+
+impl Module for AdaptiveAvgPool2d {
+    fn forward(&self, input: &Tensor) -> Tensor {
+        input.adaptive_avg_pool2d(self.output)
+    }
+
+    fn parameters(&self) -> Vec<&Tensor> {
+        Vec::new()
+    }
+
+    fn parameters_mut(&mut self) -> Vec<&mut Tensor> {
+        Vec::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::random::Rng;
+
+    #[test]
+    fn test_layers_match_functional_forms() {
+        let x = Tensor::randn(&[2, 3, 7, 6], &mut Rng::new(0));
+        let max = MaxPool2d::new([3, 2]).stride([2, 2]).padding([1, 1]);
+        assert_eq!(
+            max.forward(&x).to_vec(),
+            x.max_pool2d([3, 2], [2, 2], [1, 1]).to_vec()
+        );
+        let avg = AvgPool2d::new([2, 2]);
+        assert_eq!(
+            avg.forward(&x).to_vec(),
+            x.avg_pool2d([2, 2], [2, 2], [0, 0]).to_vec()
+        );
+        assert_eq!(avg.forward(&x).shape(), &[2, 3, 3, 3]);
+        let adaptive = AdaptiveAvgPool2d::new([2, 4]);
+        assert_eq!(adaptive.forward(&x).shape(), &[2, 3, 2, 4]);
+        assert_eq!(adaptive.num_parameters(), 0);
+    }
+}
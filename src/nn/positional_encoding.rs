@@ -0,0 +1,182 @@
+use std::cell::RefCell;
+
+use crate::nn::Module;
+use crate::tensor::Tensor;
+
+/// Sinusoidal positional encoding, as in the original transformer.
+///
+/// Adds to the token at position `p` the vector
+/// `PE[p, 2i] = sin(p · base^(-2i/d))`, `PE[p, 2i + 1] = cos(p ·
+/// base^(-2i/d))`: every feature pair is a sinusoid of its own
+/// wavelength, so nearby positions get similar vectors and relative
+/// offsets are linear functions of the encoding. There is nothing to
+/// train; for rotations of queries and keys instead, see
+/// [`RotaryEmbedding`](crate::nn::RotaryEmbedding).
+///
+/// The table is computed once per position and cached, growing as longer
+/// sequences come in. [`SinusoidalPositionalEncoding::apply`] takes the
+/// position of the first token, so a decoder fed one token at a time gets
+/// exactly the rows the full sequence would. As a [`Module`], the layer
+/// encodes sequences starting at position 0.
+///
+/// # Example
+/// ```
+/// use delta::nn::{Module, SinusoidalPositionalEncoding};
+/// use delta::tensor::Tensor;
+///
+/// let pe = SinusoidalPositionalEncoding::new(4, 10_000.0);
+/// // Position 0 is sin 0 = 0 and cos 0 = 1 in every pair
+/// assert_eq!(pe.encoding(0, 1).to_vec(), vec![0.0, 1.0, 0.0, 1.0]);
+///
+/// let tokens = Tensor::zeros(&[2, 5, 4]);
+/// let encoded = pe.forward(&tokens);
+/// assert_eq!(encoded.to_vec()[4..8], pe.encoding(1, 1).to_vec());
+/// ```
+#[derive(Debug, Clone)]
+pub struct SinusoidalPositionalEncoding {
+    dim: usize,
+    base: f64,
+    /// Row-major `[positions, dim]` encodings
+    table: RefCell<Vec<f32>>,
+}
+
+impl SinusoidalPositionalEncoding {
+    /// Encodings of `dim` features per token, with wavelengths growing
+    /// geometrically from 2π up to `2π base` (10 000 in the original
+    /// paper).
+    ///
+    /// # Panics
+    /// Panics if `dim` is 0.
+    pub fn new(dim: usize, base: f64) -> Self {
+        assert!(
+            dim > 0,
+            "SinusoidalPositionalEncoding needs a nonzero feature count"
+        );
+        Self {
+            dim,
+            base,
+            table: RefCell::new(Vec::new()),
+        }
+    }
+
+    pub fn dim(&self) -> usize {
+        self.dim
+    }
+
+    /// Number of positions whose encodings are cached.
+    pub fn cached_positions(&self) -> usize {
+        self.table.borrow().len() / self.dim
+    }
+
+    /// Encodings `[n, dim]` of positions `offset..offset + n`, extending
+    /// the cache as needed.
+    pub fn encoding(&self, offset: usize, n: usize) -> Tensor {
+        let d = self.dim;
+        let mut table = self.table.borrow_mut();
+        // Angles in f64, so every position is as accurate as the first
+        for p in table.len() / d..offset + n {
+            for i in 0..d {
+                let freq = self.base.powf(-2.0 * (i / 2) as f64 / d as f64);
+                let angle = p as f64 * freq;
+                table.push(if i % 2 == 0 { angle.sin() } else { angle.cos() } as f32);
+            }
+        }
+        Tensor::from_vec(table[offset * d..(offset + n) * d].to_vec(), &[n, d])
+    }
+
+    /// `x` `[..., n, dim]` plus the encodings of positions
+    /// `offset..offset + n`. Differentiable in `x`.
+    ///
+    /// # Panics
+    /// Panics if `x` is not `[..., n, dim]`.
+    pub fn apply(&self, x: &Tensor, offset: usize) -> Tensor {
+        let nd = x.ndim();
+        assert!(
+            nd >= 2 && x.shape()[nd - 1] == self.dim,
+            "SinusoidalPositionalEncoding expects inputs [..., n, {}], got {:?}",
+            self.dim,
+            x.shape()
+        );
+        x.add(
+            &self
+                .encoding(offset, x.shape()[nd - 2])
+                .broadcast_to(x.shape()),
+        )
+    }
+}
+
+impl Module for SinusoidalPositionalEncoding {
+    /// [`apply`](SinusoidalPositionalEncoding::apply) with the first token
+    /// at position 0.
+    fn forward(&self, input: &Tensor) -> Tensor {
+        self.apply(input, 0)
+    }
+
+    fn parameters(&self) -> Vec<&Tensor> {
+        Vec::new()
+    }
+
+    fn parameters_mut(&mut self) -> Vec<&mut Tensor> {
+        Vec::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::autograd::testing::check_grad;
+    use crate::random::Rng;
+
+    #[test]
+    fn test_formula() {
+        let pe = SinusoidalPositionalEncoding::new(6, 100.0);
+        let row = pe.encoding(3, 1).to_vec();
+        for i in 0..3 {
+            let angle = 3.0 * 100f64.powf(-2.0 * i as f64 / 6.0);
+            assert!((row[2 * i] - angle.sin() as f32).abs() < 1e-6);
+            assert!((row[2 * i + 1] - angle.cos() as f32).abs() < 1e-6);
+        }
+        // An odd feature count ends on a sine
+        let odd = SinusoidalPositionalEncoding::new(3, 100.0);
+        assert_eq!(
+            odd.encoding(2, 1).to_vec()[2],
+            (2.0 * 100f64.powf(-2.0 / 3.0)).sin() as f32
+        );
+    }
+
+    #[test]
+    fn test_offsets_match_full_sequence() {
+        let pe = SinusoidalPositionalEncoding::new(4, 10_000.0);
+        let x = Tensor::randn(&[2, 5, 4], &mut Rng::new(0));
+        let full = pe.forward(&x).to_vec();
+        let fresh = SinusoidalPositionalEncoding::new(4, 10_000.0);
+        let last = Tensor::from_vec(x.to_vec()[16..20].to_vec(), &[1, 4]);
+        assert_eq!(fresh.apply(&last, 4).to_vec(), full[16..20]);
+        // The encoding is the same for every sequence of the batch
+        let shift = |b: usize, t: usize| full[(b * 5 + t) * 4] - x.to_vec()[(b * 5 + t) * 4];
+        assert!((shift(0, 3) - shift(1, 3)).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_cache_grows_on_demand() {
+        let pe = SinusoidalPositionalEncoding::new(2, 10.0);
+        assert_eq!(pe.cached_positions(), 0);
+        pe.apply(&Tensor::zeros(&[3, 2]), 4);
+        assert_eq!(pe.cached_positions(), 7);
+        pe.encoding(0, 2);
+        assert_eq!(pe.cached_positions(), 7);
+    }
+
+    #[test]
+    fn test_grad() {
+        let pe = SinusoidalPositionalEncoding::new(4, 10.0);
+        let x = Tensor::randn(&[3, 4], &mut Rng::new(1));
+        check_grad(|t| pe.forward(&t[0]).tanh(), std::slice::from_ref(&x));
+    }
+
+    #[test]
+    #[should_panic(expected = "SinusoidalPositionalEncoding expects inputs [..., n, 4], got [4]")]
+    fn test_wants_sequences() {
+        SinusoidalPositionalEncoding::new(4, 10.0).forward(&Tensor::zeros(&[4]));
+    }
+}
@@ -0,0 +1,242 @@
+use crate::tensor::{Tensor, narrow, pad};
+
+/// One step of a recurrent network: from the input at a time step and the
+/// state left by the previous step, an output and the next state.
+///
+/// Implement it for a new kind of cell and [`scan`] unrolls it over
+/// sequences, batched and differentiable through every step. The state is
+/// whatever the cell carries between steps: one tensor for a GRU, a pair
+/// for an LSTM. A cell with parameters also implements
+/// [`Module`](crate::nn::Module) to list them.
+///
+/// # Example
+/// A minGRU cell, `h = (1 - z) h_prev + z h̃` with the gate `z` and the
+/// candidate `h̃` computed from the input alone:
+/// ```
+/// use delta::nn::{Linear, Module, RnnCell, scan};
+/// use delta::random::Rng;
+/// use delta::tensor::Tensor;
+///
+/// struct MinGru {
+///     gate: Linear,
+///     candidate: Linear,
+/// }
+///
+/// impl RnnCell for MinGru {
+///     type State = Tensor;
+///
+///     fn initial_state(&self, batch: usize) -> Tensor {
+///         Tensor::zeros(&[batch, self.gate.out_features()])
+///     }
+///
+///     fn step(&self, input: &Tensor, h: &Tensor) -> (Tensor, Tensor) {
+///         let z = self.gate.forward(input).sigmoid();
+///         let keep = z.neg().scalar_add(1.0);
+///         let h = keep.mul(h).add(&z.mul(&self.candidate.forward(input)));
+///         (h.clone(), h)
+///     }
+/// }
+///
+/// let mut rng = Rng::new(0);
+/// let cell = MinGru { gate: Linear::new(3, 5, &mut rng), candidate: Linear::new(3, 5, &mut rng) };
+/// // 2 sequences of 7 steps
+/// let (outputs, last) = scan(&cell, &Tensor::randn(&[2, 7, 3], &mut rng), None);
+/// assert_eq!(outputs.shape(), &[2, 7, 5]);
+/// assert_eq!(last.shape(), &[2, 5]);
+///
+/// outputs.sum().backward();
+/// assert!(cell.gate.weight().grad().is_some());
+/// ```
+pub trait RnnCell {
+    /// What the cell carries from one step to the next.
+    type State: Clone;
+
+    /// The state before the first step, for `batch` sequences.
+    fn initial_state(&self, batch: usize) -> Self::State;
+
+    /// Advance by one step: `input` is `[batch, features]`, and the output
+    /// `[batch, out]`.
+    fn step(&self, input: &Tensor, state: &Self::State) -> (Tensor, Self::State);
+}
+
+/// Run `cell` over sequences `inputs` `[batch, time, features]`, from
+/// `state` (the cell's initial state if `None`), and return the outputs
+/// of every step stacked `[batch, time, out]` with the final state.
+///
+/// Each step is recorded like any other computation, so gradients flow
+/// back through time to the inputs, the initial state and the cell's
+/// parameters, and differentiate again as far as the cell's ops do.
+/// Splitting the inputs into steps and stacking the outputs cost
+/// O(n log time) for `n` elements, forward and backward.
+///
+/// # Panics
+/// - Panics if `inputs` is not 3D or has no time steps
+/// - Panics if the cell's outputs change shape from one step to the next
+pub fn scan<C: RnnCell + ?Sized>(
+    cell: &C,
+    inputs: &Tensor,
+    state: Option<C::State>,
+) -> (Tensor, C::State) {
+    assert!(
+        inputs.ndim() == 3 && inputs.shape()[1] > 0,
+        "scan expects inputs [batch, time, features] with at least one step, got {:?}",
+        inputs.shape()
+    );
+    let (batch, time) = (inputs.shape()[0], inputs.shape()[1]);
+    let mut state = state.unwrap_or_else(|| cell.initial_state(batch));
+    let mut outputs = Vec::with_capacity(time);
+    for step in time_steps(inputs) {
+        let (output, next) = cell.step(&step, &state);
+        outputs.push(output);
+        state = next;
+    }
+    (stack_steps(&outputs), state)
+}
+
+/// The steps of `x` `[batch, time, features]`, each `[batch, features]`.
+///
+/// Split in halves recursively rather than one step at a time: every
+/// level of the split handles each element once, forward and backward,
+/// so the cost is O(n log time) rather than O(n time).
+fn time_steps(x: &Tensor) -> Vec<Tensor> {
+    let &[batch, time, features] = x.shape() else {
+        unreachable!()
+    };
+    if time == 1 {
+        return vec![x.reshape(&[batch, features])];
+    }
+    let half = time / 2;
+    let mut steps = time_steps(&narrow(x, 1, 0, half));
+    steps.extend(time_steps(&narrow(x, 1, half, time - half)));
+    steps
+}
+
+/// Outputs `[batch, ...]` of consecutive steps, `[batch, time, ...]`.
+fn stack_steps(steps: &[Tensor]) -> Tensor {
+    let shape = steps[0].shape();
+    for (t, s) in steps.iter().enumerate() {
+        assert_eq!(
+            s.shape(),
+            shape,
+            "RnnCell outputs must keep their shape, got {:?} at step 0 and {:?} at step {}",
+            shape,
+            s.shape(),
+            t
+        );
+    }
+    join_steps(steps)
+}
+
+/// [`stack_steps`], joining halves like [`time_steps`] splits them.
+fn join_steps(steps: &[Tensor]) -> Tensor {
+    if let [step] = steps {
+        let mut shape = step.shape().to_vec();
+        shape.insert(1, 1);
+        return step.reshape(&shape);
+    }
+    let (first, second) = steps.split_at(steps.len() / 2);
+    let (a, b) = (join_steps(first), join_steps(second));
+    pad(&a, 1, 0, second.len()).add(&pad(&b, 1, first.len(), 0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::autograd::grad;
+    use crate::autograd::testing::check_grad;
+    use crate::nn::{Linear, Module};
+    use crate::random::Rng;
+
+    /// `h = tanh(W x + U h_prev)`, with the output doubled so it differs
+    /// from the state.
+    struct Elman {
+        input: Linear,
+        hidden: Linear,
+    }
+
+    impl RnnCell for Elman {
+        type State = Tensor;
+
+        fn initial_state(&self, batch: usize) -> Tensor {
+            Tensor::zeros(&[batch, self.hidden.out_features()])
+        }
+
+        fn step(&self, x: &Tensor, h: &Tensor) -> (Tensor, Tensor) {
+            let h = self.input.forward(x).add(&self.hidden.forward(h)).tanh();
+            (h.scalar_mul(2.0), h)
+        }
+    }
+
+    fn elman(rng: &mut Rng) -> Elman {
+        Elman {
+            input: Linear::new(3, 4, rng),
+            hidden: Linear::new(4, 4, rng),
+        }
+    }
+
+    #[test]
+    fn test_matches_manual_unrolling() {
+        let mut rng = Rng::new(0);
+        let cell = elman(&mut rng);
+        let xs = Tensor::randn(&[2, 5, 3], &mut rng);
+        let (outputs, last) = scan(&cell, &xs, None);
+        assert_eq!(outputs.shape(), &[2, 5, 4]);
+
+        let data = xs.to_vec();
+        let mut h = cell.initial_state(2);
+        for t in 0..5 {
+            let x: Vec<f32> = (0..2)
+                .flat_map(|b| data[(b * 5 + t) * 3..(b * 5 + t + 1) * 3].to_vec())
+                .collect();
+            let (out, next) = cell.step(&Tensor::from_vec(x, &[2, 3]), &h);
+            let out = out.to_vec();
+            let stacked = outputs.to_vec();
+            for b in 0..2 {
+                assert_eq!(
+                    stacked[(b * 5 + t) * 4..(b * 5 + t + 1) * 4],
+                    out[b * 4..(b + 1) * 4]
+                );
+            }
+            h = next;
+        }
+        assert_eq!(last.to_vec(), h.to_vec());
+    }
+
+    #[test]
+    fn test_gradients_through_time() {
+        let mut rng = Rng::new(1);
+        let cell = elman(&mut rng);
+        let xs = Tensor::randn(&[2, 4, 3], &mut rng);
+        let h0 = Tensor::randn(&[2, 4], &mut rng);
+        check_grad(
+            |t| {
+                let (outputs, last) = scan(&cell, &t[0], Some(t[1].clone()));
+                outputs.sum().add(&last.powi(2).sum())
+            },
+            &[xs, h0],
+        );
+    }
+
+    #[test]
+    fn test_second_order() {
+        let mut rng = Rng::new(3);
+        let cell = elman(&mut rng);
+        let xs = Tensor::randn(&[2, 5, 3], &mut rng);
+        check_grad(
+            |t| {
+                let x = t[0].clone().requires_grad(true);
+                let (outputs, _) = scan(&cell, &x, None);
+                grad(&outputs.sum(), std::slice::from_ref(&x), true)[0].mul(&x)
+            },
+            std::slice::from_ref(&xs),
+        );
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "scan expects inputs [batch, time, features] with at least one step, got [2, 0, 3]"
+    )]
+    fn test_empty_sequence() {
+        scan(&elman(&mut Rng::new(2)), &Tensor::zeros(&[2, 0, 3]), None);
+    }
+}
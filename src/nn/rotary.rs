@@ -0,0 +1,233 @@
+use std::cell::RefCell;
+
+use crate::nn::Module;
+use crate::tensor::{Tensor, narrow, pad};
+
+/// Rotary position embedding (RoPE).
+///
+/// Rotates each pair of features `(x[i], x[i + d/2])` of the token at
+/// position `p` by the angle `p · base^(-2i/d)`. Applied to queries and
+/// keys before attention, it makes their dot products depend only on the
+/// distance between positions.
+///
+/// The cosine and sine tables are computed once per position and cached,
+/// growing as longer sequences come in. [`RotaryEmbedding::apply`] takes
+/// the position of the first token, so a decoder can rotate one token at a
+/// time and get exactly the rows the full sequence would. As a
+/// [`Module`], the layer rotates sequences starting at position 0.
+///
+/// # Example
+/// ```
+/// use delta::nn::RotaryEmbedding;
+/// use delta::random::Rng;
+/// use delta::tensor::Tensor;
+///
+/// let rope = RotaryEmbedding::new(4, 10_000.0);
+/// let x = Tensor::randn(&[3, 4], &mut Rng::new(0));
+/// let full = rope.apply(&x, 0).to_vec();
+/// // The last token on its own, at position 2
+/// let last = Tensor::from_vec(x.to_vec()[8..].to_vec(), &[1, 4]);
+/// assert_eq!(rope.apply(&last, 2).to_vec(), full[8..]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct RotaryEmbedding {
+    dim: usize,
+    base: f64,
+    /// Row-major `[positions, dim]` cosines and sines, each angle repeated
+    /// for both halves
+    tables: RefCell<(Vec<f32>, Vec<f32>)>,
+}
+
+impl RotaryEmbedding {
+    /// Rotations for `dim` features per token, with wavelengths growing
+    /// geometrically up to `base` (10 000 in the original paper).
+    ///
+    /// # Panics
+    /// Panics if `dim` is odd or 0.
+    pub fn new(dim: usize, base: f64) -> Self {
+        assert!(
+            dim > 0 && dim.is_multiple_of(2),
+            "RotaryEmbedding needs an even, nonzero feature count, got {}",
+            dim
+        );
+        Self {
+            dim,
+            base,
+            tables: RefCell::new((Vec::new(), Vec::new())),
+        }
+    }
+
+    pub fn dim(&self) -> usize {
+        self.dim
+    }
+
+    /// Number of positions whose rotations are cached.
+    pub fn cached_positions(&self) -> usize {
+        self.tables.borrow().0.len() / self.dim
+    }
+
+    /// Cosines and sines `[n, dim]` for positions `offset..offset + n`,
+    /// extending the cache as needed.
+    fn rows(&self, offset: usize, n: usize) -> (Tensor, Tensor) {
+        let (d, half) = (self.dim, self.dim / 2);
+        let mut tables = self.tables.borrow_mut();
+        let (cos, sin) = &mut *tables;
+        // Angles in f64, so every position is as accurate as the first
+        for p in cos.len() / d..offset + n {
+            for i in 0..d {
+                let freq = self.base.powf(-2.0 * (i % half) as f64 / d as f64);
+                let angle = p as f64 * freq;
+                cos.push(angle.cos() as f32);
+                sin.push(angle.sin() as f32);
+            }
+        }
+        let span = offset * d..(offset + n) * d;
+        (
+            Tensor::from_vec(cos[span.clone()].to_vec(), &[n, d]),
+            Tensor::from_vec(sin[span].to_vec(), &[n, d]),
+        )
+    }
+
+    /// Rotate `x` `[..., n, dim]`, whose tokens are at positions
+    /// `offset..offset + n`. Differentiable in `x`.
+    ///
+    /// # Panics
+    /// Panics if `x` is not `[..., n, dim]`.
+    pub fn apply(&self, x: &Tensor, offset: usize) -> Tensor {
+        let nd = x.ndim();
+        assert!(
+            nd >= 2 && x.shape()[nd - 1] == self.dim,
+            "RotaryEmbedding expects inputs [..., n, {}], got {:?}",
+            self.dim,
+            x.shape()
+        );
+        let (axis, half) = (nd - 1, self.dim / 2);
+        let (cos, sin) = self.rows(offset, x.shape()[nd - 2]);
+        let (cos, sin) = (cos.broadcast_to(x.shape()), sin.broadcast_to(x.shape()));
+
+        // [x1, x2] -> [-x2, x1], from the two halves
+        let (x1, x2) = (narrow(x, axis, 0, half), narrow(x, axis, half, half));
+        let rotated = pad(&x2.neg(), axis, 0, half).add(&pad(&x1, axis, half, 0));
+        x.mul(&cos).add(&rotated.mul(&sin))
+    }
+}
+
+impl Module for RotaryEmbedding {
+    /// [`apply`](RotaryEmbedding::apply) with the first token at position
+    /// 0.
+    fn forward(&self, input: &Tensor) -> Tensor {
+        self.apply(input, 0)
+    }
+
+    fn parameters(&self) -> Vec<&Tensor> {
+        Vec::new()
+    }
+
+    fn parameters_mut(&mut self) -> Vec<&mut Tensor> {
+        Vec::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::autograd::testing::check_grad;
+    use crate::nn::attention::{KvCache, causal_attention};
+    use crate::random::Rng;
+
+    #[test]
+    fn test_rotation_preserves_pair_norms() {
+        let rope = RotaryEmbedding::new(4, 100.0);
+        let x = Tensor::randn(&[5, 4], &mut Rng::new(1));
+        let (a, b) = (x.to_vec(), rope.apply(&x, 3).to_vec());
+        for t in 0..5 {
+            for i in 0..2 {
+                let before = a[t * 4 + i].hypot(a[t * 4 + i + 2]);
+                let after = b[t * 4 + i].hypot(b[t * 4 + i + 2]);
+                assert!((before - after).abs() < 1e-5);
+            }
+        }
+        // Position 0 is the identity
+        let first = Tensor::from_vec(a[..4].to_vec(), &[1, 4]);
+        assert_eq!(rope.apply(&first, 0).to_vec(), a[..4]);
+        assert_eq!(rope.forward(&x).to_vec(), rope.apply(&x, 0).to_vec());
+    }
+
+    #[test]
+    fn test_scores_depend_on_distance_only() {
+        let rope = RotaryEmbedding::new(6, 10_000.0);
+        let mut rng = Rng::new(2);
+        let (q, k) = (
+            Tensor::randn(&[1, 6], &mut rng),
+            Tensor::randn(&[1, 6], &mut rng),
+        );
+        let score = |pq, pk| {
+            let (q, k) = (rope.apply(&q, pq), rope.apply(&k, pk));
+            q.matmul(&k.t()).to_vec()[0]
+        };
+        assert!((score(5, 2) - score(13, 10)).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_cache_grows_on_demand() {
+        let rope = RotaryEmbedding::new(2, 10.0);
+        assert_eq!(rope.cached_positions(), 0);
+        let x = Tensor::from_vec(vec![1.0; 12], &[2, 3, 2]);
+        rope.apply(&x, 4);
+        assert_eq!(rope.cached_positions(), 7);
+        rope.apply(&x, 0);
+        assert_eq!(rope.cached_positions(), 7);
+    }
+
+    #[test]
+    fn test_batched_matches_per_head() {
+        let rope = RotaryEmbedding::new(4, 10_000.0);
+        let x = Tensor::randn(&[2, 3, 4], &mut Rng::new(3));
+        let out = rope.apply(&x, 1).to_vec();
+        for h in 0..2 {
+            let head = Tensor::from_vec(x.to_vec()[h * 12..(h + 1) * 12].to_vec(), &[3, 4]);
+            assert_eq!(rope.apply(&head, 1).to_vec(), out[h * 12..(h + 1) * 12]);
+        }
+    }
+
+    #[test]
+    fn test_grad() {
+        let rope = RotaryEmbedding::new(4, 10.0);
+        let x = Tensor::randn(&[3, 4], &mut Rng::new(4));
+        check_grad(|t| rope.apply(&t[0], 2).sum(), std::slice::from_ref(&x));
+    }
+
+    #[test]
+    fn test_streaming_decode_matches_full_sequence() {
+        let (heads, n, d) = (2, 6, 4);
+        let rope = RotaryEmbedding::new(d, 10_000.0);
+        let mut rng = Rng::new(5);
+        let [q, k, v] = [(); 3].map(|_| Tensor::randn(&[heads, n, d], &mut rng));
+        let full = causal_attention(&rope.apply(&q, 0), &rope.apply(&k, 0), &v, 0).to_vec();
+
+        // One token per step, rotated at its position, keys and values cached
+        let token = |x: &Tensor, t: usize| {
+            let data = x.to_vec();
+            let rows: Vec<f32> = (0..heads)
+                .flat_map(|h| data[(h * n + t) * d..(h * n + t + 1) * d].to_vec())
+                .collect();
+            Tensor::from_vec(rows, &[heads, 1, d])
+        };
+        let fresh = RotaryEmbedding::new(d, 10_000.0);
+        let mut cache = KvCache::new();
+        for t in 0..n {
+            let (keys, values) = cache.append(&fresh.apply(&token(&k, t), t), &token(&v, t));
+            let out = causal_attention(&fresh.apply(&token(&q, t), t), &keys, &values, t).to_vec();
+            for h in 0..heads {
+                assert_eq!(
+                    out[h * d..(h + 1) * d],
+                    full[(h * n + t) * d..(h * n + t + 1) * d],
+                    "step {} head {}",
+                    t,
+                    h
+                );
+            }
+        }
+        assert_eq!(cache.len(), n);
+    }
+}
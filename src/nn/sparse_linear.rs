@@ -0,0 +1,201 @@
+use crate::nn::{Linear, Module};
+use crate::tensor::Tensor;
+use crate::tensor::sparse::Csr;
+
+/// A fully connected layer with a sparse weight, `y = x Wᵀ + b` with `W`
+/// stored in CSR form.
+///
+/// Built from a dense [`Linear`] whose weight has been pruned (entries
+/// set to zero): only the surviving weights are stored and trained, and
+/// the forward pass costs time proportional to their number, so pruning
+/// turns into an actual speedup. The sparsity pattern stays fixed; pruned
+/// weights never come back. Inputs are `[..., in_features]`, as for
+/// [`Linear`].
+///
+/// # Example
+/// ```
+/// use delta::nn::{Linear, Module, SparseLinear};
+/// use delta::tensor::Tensor;
+///
+/// let w = Tensor::from_vec(vec![0.0, 2.0, 0.0,
+///                               1.0, 0.0, 0.0], &[2, 3]);
+/// let dense = Linear::from_parts(w, None);
+/// let sparse = SparseLinear::from_linear(&dense);
+/// assert_eq!(sparse.num_parameters(), 2);
+///
+/// let x = Tensor::from_vec(vec![1.0, 2.0, 3.0], &[1, 3]);
+/// assert_eq!(sparse.forward(&x).to_vec(), dense.forward(&x).to_vec());
+/// ```
+#[derive(Debug, Clone)]
+pub struct SparseLinear {
+    weight: Csr,
+    bias: Option<Tensor>,
+}
+
+impl SparseLinear {
+    /// The nonzero weights of `linear`, and its bias, as new parameters.
+    pub fn from_linear(linear: &Linear) -> Self {
+        let weight = Csr::from_dense(linear.weight());
+        let values = weight.values().clone().requires_grad(true);
+        Self {
+            weight: weight.with_values(values),
+            bias: linear.bias().map(|b| b.detach().requires_grad(true)),
+        }
+    }
+
+    /// A layer with the given `[out, in]` weight and `[out]` bias. The
+    /// weight values are tracked for gradients.
+    ///
+    /// # Panics
+    /// Panics if the bias does not have one entry per row of the weight.
+    pub fn from_parts(weight: Csr, bias: Option<Tensor>) -> Self {
+        if let Some(b) = &bias {
+            assert_eq!(
+                b.shape(),
+                &[weight.rows()],
+                "SparseLinear bias must be [{}], got {:?}",
+                weight.rows(),
+                b.shape()
+            );
+        }
+        let values = weight.values().clone().requires_grad(true);
+        Self {
+            weight: weight.with_values(values),
+            bias: bias.map(|b| b.requires_grad(true)),
+        }
+    }
+
+    /// The equivalent dense layer, with pruned weights as zeros.
+    pub fn to_linear(&self) -> Linear {
+        Linear::from_parts(
+            self.weight.to_dense().detach(),
+            self.bias.as_ref().map(Tensor::detach),
+        )
+    }
+
+    pub fn in_features(&self) -> usize {
+        self.weight.cols()
+    }
+
+    pub fn out_features(&self) -> usize {
+        self.weight.rows()
+    }
+
+    pub fn weight(&self) -> &Csr {
+        &self.weight
+    }
+
+    pub fn bias(&self) -> Option<&Tensor> {
+        self.bias.as_ref()
+    }
+}
+
+impl Module for SparseLinear {
+    /// # Panics
+    /// Panics if the last dimension of `input` is not `in_features`.
+    fn forward(&self, input: &Tensor) -> Tensor {
+        let shape = input.shape();
+        assert!(
+            shape.last() == Some(&self.in_features()),
+            "SparseLinear expects inputs [..., {}], got {:?}",
+            self.in_features(),
+            shape
+        );
+        let batch: usize = shape[..shape.len() - 1].iter().product();
+        // (x Wᵀ)ᵀ = W xᵀ, with the sparse matrix on the left
+        let x = input.reshape(&[batch, self.in_features()]);
+        let y = self.weight.matmul(&x.t()).t();
+        let y = match &self.bias {
+            Some(b) => y.add(&b.broadcast_to(y.shape())),
+            None => y,
+        };
+        let mut out_shape = shape.to_vec();
+        *out_shape.last_mut().unwrap() = self.out_features();
+        y.reshape(&out_shape)
+    }
+
+    /// `[values, bias]`: the stored weights and the bias, if any.
+    fn parameters(&self) -> Vec<&Tensor> {
+        std::iter::once(self.weight.values())
+            .chain(&self.bias)
+            .collect()
+    }
+
+    fn parameters_mut(&mut self) -> Vec<&mut Tensor> {
+        std::iter::once(self.weight.values_mut())
+            .chain(&mut self.bias)
+            .collect()
+    }
+
+    fn named_parameters(&self) -> Vec<(String, &Tensor)> {
+        std::iter::once(("weight".to_string(), self.weight.values()))
+            .chain(self.bias.iter().map(|b| ("bias".to_string(), b)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::random::Rng;
+
+    /// A random layer with roughly every other weight pruned.
+    fn pruned() -> Linear {
+        let mut rng = Rng::new(4);
+        let dense = Linear::new(6, 4, &mut rng);
+        let mask = Tensor::rand(&[4, 6], &mut rng).gt_scalar(0.5);
+        let weight = dense.weight().detach().mul(&mask);
+        Linear::from_parts(weight, dense.bias().map(Tensor::detach))
+    }
+
+    #[test]
+    fn test_matches_dense() {
+        let dense = pruned();
+        let sparse = SparseLinear::from_linear(&dense);
+        assert!(sparse.weight().nnz() < 24);
+        let x = Tensor::randn(&[2, 3, 6], &mut Rng::new(5));
+        let (a, b) = (sparse.forward(&x), dense.forward(&x));
+        assert_eq!(a.shape(), &[2, 3, 4]);
+        for (a, b) in a.to_vec().iter().zip(b.to_vec()) {
+            assert!((a - b).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_gradients_are_dense_gradients_at_kept_weights() {
+        let dense = pruned();
+        let sparse = SparseLinear::from_linear(&dense);
+        let x = Tensor::randn(&[5, 6], &mut Rng::new(6));
+        dense.forward(&x).tanh().sum().backward();
+        sparse.forward(&x).tanh().sum().backward();
+
+        let full = dense.weight().grad().unwrap().to_vec();
+        let w = sparse.weight();
+        let kept: Vec<f32> = (0..w.rows())
+            .flat_map(|r| (w.row_ptr()[r]..w.row_ptr()[r + 1]).map(move |p| (r, p)))
+            .map(|(r, p)| full[r * 6 + w.col_idx()[p]])
+            .collect();
+        let got = w.values().grad().unwrap().to_vec();
+        for (a, b) in got.iter().zip(kept) {
+            assert!((a - b).abs() < 1e-5);
+        }
+        let bias = sparse.bias().unwrap().grad().unwrap().to_vec();
+        for (a, b) in bias
+            .iter()
+            .zip(dense.bias().unwrap().grad().unwrap().to_vec())
+        {
+            assert!((a - b).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let dense = pruned();
+        let back = SparseLinear::from_linear(&dense).to_linear();
+        assert_eq!(back.weight().to_vec(), dense.weight().to_vec());
+        assert_eq!(
+            back.bias().unwrap().to_vec(),
+            dense.bias().unwrap().to_vec()
+        );
+    }
+}
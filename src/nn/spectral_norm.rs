@@ -0,0 +1,184 @@
+use std::cell::RefCell;
+
+use crate::nn::parametrize::Parametrization;
+use crate::tensor::Tensor;
+
+/// Spectral normalization: the weight divided by its largest singular
+/// value σ, so the layer it feeds is 1-Lipschitz.
+///
+/// Used for GAN discriminators and other models whose Lipschitz constant
+/// has to stay bounded. σ is estimated by power iteration, and rather
+/// than iterating to convergence on every forward, the estimate of the
+/// top singular vector is kept and refined by a few iterations each time:
+/// over the course of training it tracks the slowly changing weight.
+///
+/// σ = uᵀ W v is differentiated with u and v held fixed, as in Miyato et
+/// al. (2018). Weights with more than two dimensions (convolution
+/// kernels, `[out, in, ...]`) are treated as an `[out, in * ...]` matrix.
+///
+/// It is a [`Parametrization`]: wrap a weight with
+/// [`Parametrized`](crate::nn::parametrize::Parametrized), or call
+/// `forward` on the raw weight inside an optimizer closure.
+///
+/// # Example
+/// ```
+/// use delta::nn::SpectralNorm;
+/// use delta::nn::parametrize::Parametrized;
+/// use delta::tensor::Tensor;
+///
+/// let w = Tensor::from_vec(vec![3.0, 0.0,
+///                               0.0, 1.0], &[2, 2]);
+/// let w = Parametrized::new(w, SpectralNorm::new().n_power_iterations(10));
+/// let normalized = w.value().to_vec();
+/// assert!((normalized[0] - 1.0).abs() < 1e-4);
+/// assert!((normalized[3] - 1.0 / 3.0).abs() < 1e-4);
+/// ```
+#[derive(Debug, Clone)]
+pub struct SpectralNorm {
+    n_power_iterations: usize,
+    eps: f32,
+    /// Current estimate of the top left singular vector
+    u: RefCell<Option<Vec<f32>>>,
+}
+
+impl Default for SpectralNorm {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SpectralNorm {
+    /// Spectral normalization with one power iteration per forward.
+    pub fn new() -> Self {
+        Self {
+            n_power_iterations: 1,
+            eps: 1e-12,
+            u: RefCell::new(None),
+        }
+    }
+
+    /// Power iterations per forward.
+    ///
+    /// # Panics
+    /// Panics if `n` is 0.
+    pub fn n_power_iterations(mut self, n: usize) -> Self {
+        assert!(n > 0, "n_power_iterations must be at least 1");
+        self.n_power_iterations = n;
+        self
+    }
+
+    /// Lower bound on the norms the power iteration divides by. It keeps
+    /// the estimate finite, but a weight that is all zeros still has σ = 0
+    /// and normalizes to NaNs.
+    pub fn eps(mut self, eps: f32) -> Self {
+        self.eps = eps;
+        self
+    }
+
+    /// Scale `x` to unit norm.
+    fn normalize(&self, mut x: Vec<f32>) -> Vec<f32> {
+        let norm = x.iter().map(|v| v * v).sum::<f32>().sqrt().max(self.eps);
+        x.iter_mut().for_each(|v| *v /= norm);
+        x
+    }
+}
+
+impl Parametrization for SpectralNorm {
+    /// # Panics
+    /// Panics if `raw` has fewer than two dimensions.
+    fn forward(&self, raw: &Tensor) -> Tensor {
+        assert!(
+            raw.ndim() >= 2,
+            "SpectralNorm requires a weight with at least 2 dimensions, got shape {:?}",
+            raw.shape()
+        );
+        let rows = raw.shape()[0];
+        let cols = raw.nelems() / rows;
+        let data = raw.to_vec();
+
+        let mut slot = self.u.borrow_mut();
+        let u = match slot.take() {
+            Some(u) if u.len() == rows => u,
+            // A constant start; power iteration only stalls from it if it
+            // happens to be orthogonal to the top singular vector
+            _ => self.normalize(vec![1.0; rows]),
+        };
+
+        // v = normalize(Wᵀ u), u = normalize(W v)
+        let (mut u, mut v) = (u, vec![0.0; cols]);
+        for _ in 0..self.n_power_iterations {
+            let mut wtu = vec![0.0; cols];
+            for (row, ui) in data.chunks(cols).zip(&u) {
+                wtu.iter_mut().zip(row).for_each(|(acc, w)| *acc += w * ui);
+            }
+            v = self.normalize(wtu);
+            let wv = data
+                .chunks(cols)
+                .map(|row| row.iter().zip(&v).map(|(w, vj)| w * vj).sum())
+                .collect();
+            u = self.normalize(wv);
+        }
+        *slot = Some(u.clone());
+
+        let w = raw.reshape(&[rows, cols]);
+        let sigma = Tensor::from_vec(u, &[1, rows])
+            .matmul(&w)
+            .matmul(&Tensor::from_vec(v, &[cols, 1]));
+        raw.div(&sigma.broadcast_to(raw.shape()))
+    }
+
+    /// A spectrally normalized weight has σ = 1, so it is its own
+    /// preimage.
+    fn right_inverse(&self, value: &Tensor) -> Option<Tensor> {
+        Some(value.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::autograd::testing::check_grad;
+
+    /// Largest singular value, by running power iteration to convergence.
+    fn top_singular_value(w: &Tensor) -> f32 {
+        let sn = SpectralNorm::new().n_power_iterations(200);
+        let normalized = sn.forward(w);
+        w.to_vec()[0] / normalized.to_vec()[0]
+    }
+
+    #[test]
+    fn test_estimate_converges_across_forwards() {
+        let w = Tensor::from_vec(vec![2.0, 1.0, 0.5, 1.0, 3.0, -1.0], &[2, 3]);
+        let sn = SpectralNorm::new();
+        for _ in 0..50 {
+            sn.forward(&w);
+        }
+        let normalized = sn.forward(&w);
+        let sigma = top_singular_value(&normalized);
+        assert!((sigma - 1.0).abs() < 1e-4, "{}", sigma);
+    }
+
+    #[test]
+    fn test_conv_weight() {
+        let data: Vec<f32> = (0..24).map(|i| ((i * 7) % 5) as f32 - 2.0).collect();
+        let w = Tensor::from_vec(data, &[2, 3, 2, 2]);
+        let normalized = SpectralNorm::new().n_power_iterations(100).forward(&w);
+        assert_eq!(normalized.shape(), &[2, 3, 2, 2]);
+        let sigma = top_singular_value(&normalized.reshape(&[2, 12]));
+        assert!((sigma - 1.0).abs() < 1e-4, "{}", sigma);
+    }
+
+    #[test]
+    fn test_gradient() {
+        let w = Tensor::from_vec(vec![1.0, 0.5, -0.3, 2.0], &[2, 2]);
+        let sn = SpectralNorm::new().n_power_iterations(50);
+        sn.forward(&w);
+        check_grad(|t| sn.forward(&t[0]), &[w]);
+    }
+
+    #[test]
+    #[should_panic(expected = "at least 2 dimensions")]
+    fn test_vector_weight() {
+        SpectralNorm::new().forward(&Tensor::zeros(&[3]));
+    }
+}
@@ -0,0 +1,240 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::autograd::no_grad;
+use crate::nn::Module;
+use crate::tensor::Tensor;
+
+/// The weights of a model: every parameter and buffer by its hierarchical
+/// name (see [`Module::named_parameters`]), as plain values outside any
+/// graph.
+///
+/// Returned by [`Module::state_dict`] and read by
+/// [`Module::load_state_dict`]. It is an ordinary map, so checkpoints can
+/// be stored in any format, and entries renamed or dropped before
+/// loading.
+///
+/// # Example
+/// ```
+/// use delta::nn::{Linear, Module};
+/// use delta::random::Rng;
+///
+/// let mut rng = Rng::new(0);
+/// let trained = Linear::new(3, 2, &mut rng);
+/// let mut fresh = Linear::new(3, 2, &mut rng);
+///
+/// let state = trained.state_dict();
+/// assert_eq!(state.keys().collect::<Vec<_>>(), ["bias", "weight"]);
+/// fresh.load_state_dict(&state, true).unwrap();
+/// assert_eq!(fresh.weight().to_vec(), trained.weight().to_vec());
+/// ```
+pub type StateDict = BTreeMap<String, Tensor>;
+
+/// The names skipped by a non-strict [`Module::load_state_dict`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LoadReport {
+    /// Names of the module that the state did not have, left unchanged
+    pub missing: Vec<String>,
+    /// Names in the state that the module does not have
+    pub unexpected: Vec<String>,
+}
+
+/// Why [`Module::load_state_dict`] refused a state.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StateDictError {
+    /// Names of the module missing from the state (strict loading only)
+    pub missing: Vec<String>,
+    /// Names in the state the module does not have (strict loading only)
+    pub unexpected: Vec<String>,
+    /// Names whose value has the wrong shape: name, expected shape, shape
+    /// in the state
+    pub mismatched: Vec<(String, Vec<usize>, Vec<usize>)>,
+}
+
+impl fmt::Display for StateDictError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut problems = Vec::new();
+        if !self.missing.is_empty() {
+            problems.push(format!("missing {}", self.missing.join(", ")));
+        }
+        if !self.unexpected.is_empty() {
+            problems.push(format!("unexpected {}", self.unexpected.join(", ")));
+        }
+        for (name, expected, got) in &self.mismatched {
+            problems.push(format!("{} should be {:?}, got {:?}", name, expected, got));
+        }
+        write!(f, "cannot load state dict: {}", problems.join("; "))
+    }
+}
+
+impl std::error::Error for StateDictError {}
+
+/// [`Module::state_dict`].
+pub(crate) fn collect<M: Module + ?Sized>(module: &M) -> StateDict {
+    let params = module
+        .named_parameters()
+        .into_iter()
+        .map(|(name, p)| (name, p.detach()));
+    let buffers = module
+        .named_buffers()
+        .into_iter()
+        .map(|(name, b)| (name, b.get()));
+    params.chain(buffers).collect()
+}
+
+/// [`Module::load_state_dict`].
+pub(crate) fn load<M: Module + ?Sized>(
+    module: &mut M,
+    state: &StateDict,
+    strict: bool,
+) -> Result<LoadReport, StateDictError> {
+    let mut shapes: Vec<(String, Vec<usize>)> = module
+        .named_parameters()
+        .into_iter()
+        .map(|(name, p)| (name, p.shape().to_vec()))
+        .collect();
+    shapes.extend(
+        module
+            .named_buffers()
+            .into_iter()
+            .map(|(name, b)| (name, b.shape())),
+    );
+
+    let mut error = StateDictError::default();
+    for (name, shape) in &shapes {
+        match state.get(name) {
+            None => error.missing.push(name.clone()),
+            Some(value) if value.shape() != shape.as_slice() => {
+                error
+                    .mismatched
+                    .push((name.clone(), shape.clone(), value.shape().to_vec()))
+            }
+            Some(_) => {}
+        }
+    }
+    error.unexpected = state
+        .keys()
+        .filter(|key| !shapes.iter().any(|(name, _)| name == *key))
+        .cloned()
+        .collect();
+    let report = LoadReport {
+        missing: std::mem::take(&mut error.missing),
+        unexpected: std::mem::take(&mut error.unexpected),
+    };
+    if strict {
+        error.missing = report.missing.clone();
+        error.unexpected = report.unexpected.clone();
+    }
+    if error != StateDictError::default() {
+        return Err(error);
+    }
+
+    for (name, buffer) in module.named_buffers() {
+        if let Some(value) = state.get(&name) {
+            buffer.set(value.clone());
+        }
+    }
+    let names: Vec<String> = module
+        .named_parameters()
+        .into_iter()
+        .map(|(name, _)| name)
+        .collect();
+    let params = module.parameters_mut();
+    assert_eq!(
+        params.len(),
+        names.len(),
+        "Module::parameters_mut returned {} parameters, but Module::parameters {}",
+        params.len(),
+        names.len()
+    );
+    no_grad(|| {
+        for (name, p) in names.iter().zip(params) {
+            if let Some(value) = state.get(name) {
+                p.copy_(value);
+            }
+        }
+    });
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nn::{BatchNorm1d, Linear, TransformerEncoder, TransformerEncoderLayer};
+    use crate::random::Rng;
+
+    #[test]
+    fn test_round_trip_keeps_parameters_tracked() {
+        let mut rng = Rng::new(0);
+        let source = BatchNorm1d::new(2);
+        source.forward(&Tensor::randn(&[4, 2], &mut rng));
+        let state = source.state_dict();
+        assert_eq!(
+            state.keys().collect::<Vec<_>>(),
+            ["beta", "gamma", "running_mean", "running_var"]
+        );
+
+        let mut target = BatchNorm1d::new(2);
+        let gamma = target.gamma().clone();
+        assert_eq!(
+            target.load_state_dict(&state, true),
+            Ok(LoadReport::default())
+        );
+        assert_eq!(
+            target.running_mean().to_vec(),
+            source.running_mean().to_vec()
+        );
+        // The same leaf, now holding the loaded value
+        assert!(target.gamma().is_leaf());
+        target
+            .forward(&Tensor::randn(&[4, 2], &mut rng))
+            .sum()
+            .backward();
+        assert!(gamma.grad().is_some());
+    }
+
+    #[test]
+    fn test_partial_loading() {
+        let mut rng = Rng::new(1);
+        let layers = |rng: &mut Rng, n: usize| {
+            TransformerEncoder::new(
+                (0..n)
+                    .map(|_| TransformerEncoderLayer::new(4, 2, 8, rng))
+                    .collect(),
+            )
+        };
+        let small = layers(&mut rng, 1);
+        let mut big = layers(&mut rng, 2);
+        let state = small.state_dict();
+
+        let err = big.load_state_dict(&state, true).unwrap_err();
+        assert_eq!(err.missing.len(), 16);
+        assert!(err.missing[0].starts_with("layers.1."));
+
+        let report = big.load_state_dict(&state, false).unwrap();
+        assert_eq!((report.missing.len(), report.unexpected.len()), (16, 0));
+        assert_eq!(
+            big.named_parameters()[0].1.to_vec(),
+            state["layers.0.self_attn.q_proj.weight"].to_vec()
+        );
+    }
+
+    #[test]
+    fn test_shape_mismatch_loads_nothing() {
+        let mut rng = Rng::new(2);
+        let mut layer = Linear::new(3, 2, &mut rng);
+        let before = layer.weight().to_vec();
+        let mut state = Linear::new(3, 2, &mut rng).state_dict();
+        state.insert("bias".into(), Tensor::zeros(&[3]));
+        state.insert("extra".into(), Tensor::zeros(&[1]));
+
+        let err = layer.load_state_dict(&state, false).unwrap_err();
+        assert_eq!(err.mismatched, vec![("bias".into(), vec![2], vec![3])]);
+        assert!(err.unexpected.is_empty());
+        assert_eq!(
+            err.to_string(),
+            "cannot load state dict: bias should be [2], got [3]"
+        );
+        assert_eq!(layer.weight().to_vec(), before);
+    }
+}
@@ -0,0 +1,340 @@
+use std::cell::RefCell;
+use std::fmt;
+
+use crate::autograd::no_grad;
+use crate::nn::Module;
+use crate::nn::module::module_key;
+use crate::tensor::Tensor;
+
+/// A module, by [`module_key`], and the shape of one of its outputs.
+type Call = ((usize, &'static str), Vec<usize>);
+
+thread_local! {
+    /// The modules run through [`Module::call`] while a summary is being
+    /// taken, in call order.
+    static CALLS: RefCell<Option<Vec<Call>>> = const { RefCell::new(None) };
+}
+
+/// Record a call of a module, if a summary is being taken.
+pub(crate) fn observe(key: (usize, &'static str), output: &Tensor) {
+    CALLS.with_borrow_mut(|calls| {
+        if let Some(calls) = calls {
+            calls.push((key, output.shape().to_vec()));
+        }
+    });
+}
+
+/// Whether a summary pass is running. Layers with hidden state that is
+/// not a buffer (the generator of [`Dropout`](crate::nn::Dropout)) keep
+/// it as it was.
+pub(crate) fn is_recording() -> bool {
+    CALLS.with_borrow(Option::is_some)
+}
+
+/// Stops recording when the forward pass ends, even by a panic.
+struct Recording;
+
+impl Recording {
+    fn start() -> Self {
+        CALLS.set(Some(Vec::new()));
+        Recording
+    }
+}
+
+impl Drop for Recording {
+    fn drop(&mut self) {
+        CALLS.set(None);
+    }
+}
+
+/// One submodule in a [`Summary`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LayerSummary {
+    /// Hierarchical name, as in [`Module::named_modules`]
+    pub name: String,
+    /// Type name without module paths, e.g. `Conv<2>`
+    pub type_name: String,
+    /// Shape of the output of its last call, `None` if it was not called
+    /// through [`Module::call`]
+    pub output_shape: Option<Vec<usize>>,
+    /// Number of scalar parameters tracking gradients, its submodules'
+    /// included
+    pub trainable: usize,
+    /// Number of scalar parameters frozen, its submodules' included
+    pub frozen: usize,
+}
+
+/// The layers of a model with their output shapes and parameter counts,
+/// returned by [`summary`]. Displays as a table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Summary {
+    /// Type name of the model, without module paths
+    pub model: String,
+    /// Every submodule, in the order of [`Module::named_modules`]
+    pub layers: Vec<LayerSummary>,
+    /// Shape of the model's output
+    pub output_shape: Vec<usize>,
+    /// Number of scalar parameters of the model tracking gradients
+    pub trainable: usize,
+    /// Number of scalar parameters of the model frozen
+    pub frozen: usize,
+}
+
+/// Run `model` on zeros of `input_shape` and list every layer with the
+/// shape it produced and its parameter counts, trainable and frozen
+/// (see [`Module::freeze`]), like the summary of Keras.
+///
+/// The pass runs without recording gradients, buffers (running
+/// statistics) are restored afterwards and dropout layers leave their
+/// generators where they were, so the model is left as it was. State a
+/// user module keeps outside its buffers is not restored.
+/// Output shapes are seen for the layers run through [`Module::call`],
+/// which is how the layers of this crate call their submodules; the rest
+/// show `-`. A shape bug shows up here as the panic of the first layer
+/// given the wrong input, before any training.
+///
+/// # Panics
+/// Panics if `model` cannot be run on inputs of `input_shape`.
+///
+/// # Example
+/// ```
+/// use delta::nn::{Module, TransformerEncoder, TransformerEncoderLayer, summary};
+/// use delta::random::Rng;
+///
+/// let mut rng = Rng::new(0);
+/// let encoder = TransformerEncoder::new(vec![TransformerEncoderLayer::new(8, 2, 16, &mut rng)]);
+/// encoder.layers()[0].self_attn().freeze();
+///
+/// let s = summary(&encoder, &[4, 10, 8]);
+/// assert_eq!(s.output_shape, vec![4, 10, 8]);
+/// let linear1 = s.layers.iter().find(|l| l.name == "layers.0.linear1").unwrap();
+/// assert_eq!(linear1.output_shape, Some(vec![4, 10, 16]));
+/// assert_eq!(s.frozen, 4 * (8 * 8 + 8));
+/// println!("{}", s);
+/// ```
+pub fn summary<M: Module>(model: &M, input_shape: &[usize]) -> Summary {
+    let buffers: Vec<Tensor> = model.buffers().iter().map(|b| b.get()).collect();
+    let (output, calls) = {
+        let recording = Recording::start();
+        let output = no_grad(|| model.call(&Tensor::zeros(input_shape)));
+        let calls = CALLS.take().unwrap_or_default();
+        drop(recording);
+        (output, calls)
+    };
+    for (buffer, value) in model.buffers().into_iter().zip(buffers) {
+        buffer.set(value);
+    }
+
+    let counts = |module: &dyn Module| {
+        let (mut trainable, mut frozen) = (0, 0);
+        for p in module.parameters() {
+            if p.tracks_grad() {
+                trainable += p.nelems();
+            } else {
+                frozen += p.nelems();
+            }
+        }
+        (trainable, frozen)
+    };
+    let layers = model
+        .named_modules()
+        .into_iter()
+        .skip(1)
+        .map(|(name, module)| {
+            let key = module_key(module);
+            let (trainable, frozen) = counts(module);
+            LayerSummary {
+                name,
+                type_name: short_type_name(module.type_name()),
+                output_shape: calls
+                    .iter()
+                    .rev()
+                    .find(|(k, _)| *k == key)
+                    .map(|(_, shape)| shape.clone()),
+                trainable,
+                frozen,
+            }
+        })
+        .collect();
+    let (trainable, frozen) = counts(model);
+    Summary {
+        model: short_type_name(model.type_name()),
+        layers,
+        output_shape: output.shape().to_vec(),
+        trainable,
+        frozen,
+    }
+}
+
+/// `name` with every path dropped: `delta::nn::conv::Conv<2>` becomes
+/// `Conv<2>`.
+fn short_type_name(name: &str) -> String {
+    let mut out = String::new();
+    let mut path = String::new();
+    for c in name.chars() {
+        if c.is_alphanumeric() || c == '_' || c == ':' {
+            path.push(c);
+        } else {
+            out.push_str(path.rsplit("::").next().unwrap_or_default());
+            path.clear();
+            out.push(c);
+        }
+    }
+    out.push_str(path.rsplit("::").next().unwrap_or_default());
+    out
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let header = ["Layer", "Type", "Output shape", "Trainable", "Frozen"];
+        let rows: Vec<[String; 5]> = self
+            .layers
+            .iter()
+            .map(|l| {
+                [
+                    l.name.clone(),
+                    l.type_name.clone(),
+                    l.output_shape
+                        .as_ref()
+                        .map_or_else(|| "-".to_string(), |s| format!("{:?}", s)),
+                    l.trainable.to_string(),
+                    l.frozen.to_string(),
+                ]
+            })
+            .collect();
+        let mut widths = header.map(str::len);
+        for row in &rows {
+            for (w, cell) in widths.iter_mut().zip(row) {
+                *w = (*w).max(cell.chars().count());
+            }
+        }
+        let rule = "-".repeat(widths.iter().sum::<usize>() + 2 * (widths.len() - 1));
+
+        writeln!(f, "Model: {}", self.model)?;
+        writeln!(f, "{}", rule)?;
+        let line = |f: &mut fmt::Formatter<'_>, cells: [&str; 5]| {
+            let text: Vec<String> = cells
+                .iter()
+                .zip(widths)
+                .enumerate()
+                // Names left-aligned, counts right-aligned
+                .map(|(i, (cell, w))| match i {
+                    0..=2 => format!("{:<w$}", cell),
+                    _ => format!("{:>w$}", cell),
+                })
+                .collect();
+            writeln!(f, "{}", text.join("  ").trim_end())
+        };
+        line(f, header)?;
+        writeln!(f, "{}", rule)?;
+        for row in &rows {
+            line(f, row.each_ref().map(String::as_str))?;
+        }
+        writeln!(f, "{}", rule)?;
+        writeln!(f, "Output shape: {:?}", self.output_shape)?;
+        write!(
+            f,
+            "Parameters: {} ({} trainable, {} frozen)",
+            self.trainable + self.frozen,
+            self.trainable,
+            self.frozen
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nn::{
+        BatchNorm1d, Dropout, Linear, ModuleList, TransformerDecoder, TransformerDecoderLayer,
+    };
+    use crate::random::Rng;
+
+    #[test]
+    fn test_table() {
+        let mut rng = Rng::new(0);
+        let mut list = ModuleList::new();
+        list.push(Linear::new(3, 4, &mut rng));
+        list.push(BatchNorm1d::new(4));
+        list.get(0).unwrap().freeze();
+
+        // Entries called by hand are not seen; the list has no forward
+        struct Net(ModuleList);
+        impl Module for Net {
+            fn forward(&self, x: &Tensor) -> Tensor {
+                let x = self.0.get(0).unwrap().call(x);
+                self.0.get(1).unwrap().call(&x)
+            }
+            fn parameters(&self) -> Vec<&Tensor> {
+                self.0.parameters()
+            }
+            fn parameters_mut(&mut self) -> Vec<&mut Tensor> {
+                self.0.parameters_mut()
+            }
+            fn buffers(&self) -> Vec<&crate::nn::Buffer> {
+                self.0.buffers()
+            }
+            fn children(&self) -> Vec<(String, &dyn Module)> {
+                vec![("blocks".into(), &self.0)]
+            }
+        }
+        let net = Net(list);
+        let mean = net.0.get(1).unwrap().buffers()[0].get().to_vec();
+
+        let s = summary(&net, &[2, 3]);
+        assert_eq!(s.model, "Net");
+        assert_eq!((s.trainable, s.frozen), (8, 16));
+        assert_eq!(s.layers[0].output_shape, None);
+        assert_eq!(s.layers[2].type_name, "BatchNorm<1>");
+        assert_eq!(s.layers[2].output_shape, Some(vec![2, 4]));
+        // The running statistics were restored
+        assert_eq!(net.0.get(1).unwrap().buffers()[0].get().to_vec(), mean);
+        assert_eq!(
+            s.to_string(),
+            "Model: Net
+-------------------------------------------------------
+Layer     Type          Output shape  Trainable  Frozen
+-------------------------------------------------------
+blocks    ModuleList    -                     8      16
+blocks.0  Linear        [2, 4]                0      16
+blocks.1  BatchNorm<1>  [2, 4]                8       0
+-------------------------------------------------------
+Output shape: [2, 4]
+Parameters: 24 (8 trainable, 16 frozen)"
+        );
+    }
+
+    #[test]
+    fn test_leaves_dropout_and_attention_visible() {
+        let mut rng = Rng::new(3);
+        let dropout = Dropout::new(0.5, &mut rng);
+        let x = Tensor::from_vec(vec![1.0; 16], &[16]);
+        let twin = dropout.clone();
+        summary(&dropout, &[16]);
+        // The generator did not move
+        assert_eq!(dropout.forward(&x).to_vec(), twin.forward(&x).to_vec());
+
+        let decoder =
+            TransformerDecoder::new(vec![TransformerDecoderLayer::new(8, 2, 16, &mut rng)]);
+        let s = summary(&decoder, &[3, 8]);
+        for name in ["layers.0", "layers.0.self_attn"] {
+            let layer = s.layers.iter().find(|l| l.name == name).unwrap();
+            assert_eq!(layer.output_shape, Some(vec![3, 8]), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_short_type_name() {
+        assert_eq!(short_type_name("delta::nn::conv::Conv<2>"), "Conv<2>");
+        assert_eq!(
+            short_type_name("a::Wrapper<b::c::Inner, [d::E; 2]>"),
+            "Wrapper<Inner, [E; 2]>"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Linear expects inputs [..., 3], got [2, 5]")]
+    fn test_shape_bug() {
+        summary(&Linear::new(3, 1, &mut Rng::new(1)), &[2, 5]);
+    }
+}
@@ -0,0 +1,914 @@
+//! Transformer building blocks: multi-head attention, encoder and decoder
+//! layers, and stacks of them.
+//!
+//! Layers take sequences `[..., n, dim]`, leading dimensions being batch
+//! dimensions. Each layer is attention followed by a two-layer ReLU
+//! feed-forward network of width `ff_dim`, every block wrapped in a
+//! residual connection and a [`LayerNorm`]. By default the norm follows
+//! the residual sum (post-norm, as in the original Transformer);
+//! `norm_first(true)` normalizes the input of each block instead
+//! (pre-norm), which trains more stably in deep stacks. A pre-norm stack
+//! usually ends with a final norm; see [`TransformerEncoder::norm`].
+
+use crate::nn::attention::{
+    broadcast_mask, causal_attention, masked_attention, scaled_dot_product_attention,
+};
+use crate::nn::module::call_with;
+use crate::nn::{LayerNorm, Linear, Module};
+use crate::random::Rng;
+use crate::tensor::Tensor;
+
+/// Multi-head attention: queries, keys and values are projected, split
+/// into `heads` heads of `dim / heads` features that attend
+/// independently, and the heads' outputs are concatenated and projected
+/// back to `dim`.
+///
+/// # Example
+/// ```
+/// use delta::nn::{Module, MultiheadAttention};
+/// use delta::random::Rng;
+/// use delta::tensor::Tensor;
+///
+/// let mut rng = Rng::new(0);
+/// let attn = MultiheadAttention::new(16, 4, &mut rng);
+/// let x = Tensor::randn(&[2, 5, 16], &mut rng);
+/// assert_eq!(attn.forward(&x).shape(), &[2, 5, 16]);
+///
+/// // Cross-attention over a memory of another length
+/// let memory = Tensor::randn(&[2, 7, 16], &mut rng);
+/// assert_eq!(attn.attend(&x, &memory, false).shape(), &[2, 5, 16]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct MultiheadAttention {
+    q_proj: Linear,
+    k_proj: Linear,
+    v_proj: Linear,
+    out_proj: Linear,
+    heads: usize,
+}
+
+impl MultiheadAttention {
+    /// Attention over `dim` features with `heads` heads, the projections
+    /// initialized as by [`Linear::new`].
+    ///
+    /// # Panics
+    /// Panics unless `heads` is positive and divides `dim`.
+    pub fn new(dim: usize, heads: usize, rng: &mut Rng) -> Self {
+        assert!(
+            heads > 0 && dim.is_multiple_of(heads),
+            "MultiheadAttention needs a number of heads dividing dim {}, got {}",
+            dim,
+            heads
+        );
+        Self {
+            q_proj: Linear::new(dim, dim, rng),
+            k_proj: Linear::new(dim, dim, rng),
+            v_proj: Linear::new(dim, dim, rng),
+            out_proj: Linear::new(dim, dim, rng),
+            heads,
+        }
+    }
+
+    pub fn dim(&self) -> usize {
+        self.q_proj.in_features()
+    }
+
+    pub fn heads(&self) -> usize {
+        self.heads
+    }
+
+    /// Queries from `query` `[..., n, dim]` attending to keys and values
+    /// from `memory` `[..., m, dim]`, with the same leading dimensions.
+    /// With `causal`, position `i` only sees memory positions up to `i`
+    /// (for self-attention, where `memory` is `query`). Runs like
+    /// [`Module::call`]: the forward hooks see `query`.
+    ///
+    /// # Panics
+    /// - Panics if the shapes do not fit together
+    /// - Panics with `causal` if `memory` is shorter than `query`
+    pub fn attend(&self, query: &Tensor, memory: &Tensor, causal: bool) -> Tensor {
+        call_with(self, query, |q| self.attend_with(q, memory, causal, None))
+    }
+
+    /// [`attend`](MultiheadAttention::attend) with an additive `mask` on
+    /// the scores `[..., n, m]` of each sequence, shared by every head.
+    /// It is broadcast as by [`broadcast_mask`]: a `[n, m]` mask applies
+    /// to every sequence, a `[batch, 1, m]` padding mask to every query of
+    /// its sequence. Build it with the helpers of
+    /// [`attention`](crate::nn::attention). Runs like [`Module::call`].
+    ///
+    /// # Panics
+    /// - Panics if the shapes do not fit together
+    /// - Panics if the mask cannot be broadcast to the scores
+    ///
+    /// # Example
+    /// ```
+    /// use delta::nn::MultiheadAttention;
+    /// use delta::nn::attention::padding_mask;
+    /// use delta::random::Rng;
+    /// use delta::tensor::Tensor;
+    ///
+    /// let mut rng = Rng::new(0);
+    /// let mha = MultiheadAttention::new(8, 2, &mut rng);
+    /// // The second sequence has one real token, then padding
+    /// let x = Tensor::randn(&[2, 3, 8], &mut rng);
+    /// let out = mha.attend_masked(&x, &x, &padding_mask(&[3, 1], 3));
+    /// assert_eq!(out.shape(), &[2, 3, 8]);
+    /// ```
+    pub fn attend_masked(&self, query: &Tensor, memory: &Tensor, mask: &Tensor) -> Tensor {
+        call_with(self, query, |q| {
+            self.attend_with(q, memory, false, Some(mask))
+        })
+    }
+
+    fn attend_with(
+        &self,
+        query: &Tensor,
+        memory: &Tensor,
+        causal: bool,
+        mask: Option<&Tensor>,
+    ) -> Tensor {
+        let qs = query.shape();
+        let nd = qs.len();
+        assert!(
+            nd >= 2
+                && qs[nd - 1] == self.dim()
+                && memory.ndim() == nd
+                && memory.shape()[..nd - 2] == qs[..nd - 2]
+                && memory.shape()[nd - 1] == self.dim(),
+            "MultiheadAttention expects query [..., n, {}] and memory [..., m, {}], got {:?} and {:?}",
+            self.dim(),
+            self.dim(),
+            qs,
+            memory.shape()
+        );
+        let (n, m) = (qs[nd - 2], memory.shape()[nd - 2]);
+        let batches: usize = qs[..nd - 2].iter().product();
+        let (h, dh) = (self.heads, self.dim() / self.heads);
+        // [batches, len, dim] -> [batches, heads, len, dh]
+        let split =
+            |x: Tensor, len: usize| x.reshape(&[batches, len, h, dh]).permute(&[0, 2, 1, 3]);
+
+        let q = split(self.q_proj.call(query), n);
+        let k = split(self.k_proj.call(memory), m);
+        let v = split(self.v_proj.call(memory), m);
+        let heads = if let Some(mask) = mask {
+            // The same mask for every head
+            let scores = [&qs[..nd - 2], &[n, m]].concat();
+            let mask = broadcast_mask(mask, &scores).reshape(&[batches, 1, n, m]);
+            masked_attention(&q, &k, &v, &mask)
+        } else if causal {
+            causal_attention(&q, &k, &v, 0)
+        } else {
+            scaled_dot_product_attention(&q, &k, &v)
+        };
+        let merged = heads.permute(&[0, 2, 1, 3]).reshape(qs);
+        self.out_proj.call(&merged)
+    }
+}
+
+impl Module for MultiheadAttention {
+    /// Self-attention: `attend(input, input, false)`.
+    fn forward(&self, input: &Tensor) -> Tensor {
+        self.attend_with(input, input, false, None)
+    }
+
+    /// The query, key, value and output projections' parameters, in that
+    /// order.
+    fn parameters(&self) -> Vec<&Tensor> {
+        [&self.q_proj, &self.k_proj, &self.v_proj, &self.out_proj]
+            .into_iter()
+            .flat_map(|l| l.parameters())
+            .collect()
+    }
+
+    fn parameters_mut(&mut self) -> Vec<&mut Tensor> {
+        [
+            &mut self.q_proj,
+            &mut self.k_proj,
+            &mut self.v_proj,
+            &mut self.out_proj,
+        ]
+        .into_iter()
+        .flat_map(|l| l.parameters_mut())
+        .collect()
+    }
+
+    fn children(&self) -> Vec<(String, &dyn Module)> {
+        vec![
+            ("q_proj".into(), &self.q_proj),
+            ("k_proj".into(), &self.k_proj),
+            ("v_proj".into(), &self.v_proj),
+            ("out_proj".into(), &self.out_proj),
+        ]
+    }
+}
+
+/// `linear2(relu(linear1(x)))`.
+fn feed_forward(linear1: &Linear, linear2: &Linear, x: &Tensor) -> Tensor {
+    linear2.call(&linear1.call(x).relu())
+}
+
+/// `x + block(x)` then a norm (post-norm), or `x + block(norm(x))`
+/// (pre-norm).
+fn residual(
+    x: &Tensor,
+    norm: &LayerNorm,
+    norm_first: bool,
+    block: impl Fn(&Tensor) -> Tensor,
+) -> Tensor {
+    if norm_first {
+        x.add(&block(&norm.call(x)))
+    } else {
+        norm.call(&x.add(&block(x)))
+    }
+}
+
+/// A Transformer encoder layer: self-attention, then a feed-forward
+/// network.
+///
+/// With [`causal`](TransformerEncoderLayer::causal), each position only
+/// attends to the positions before it, which makes a stack of these the
+/// decoder-only model of GPT-style language models.
+///
+/// # Example
+/// ```
+/// use delta::nn::{Module, TransformerEncoderLayer};
+/// use delta::random::Rng;
+/// use delta::tensor::Tensor;
+///
+/// let mut rng = Rng::new(0);
+/// let layer = TransformerEncoderLayer::new(16, 4, 64, &mut rng).norm_first(true);
+/// let x = Tensor::randn(&[2, 10, 16], &mut rng);
+/// assert_eq!(layer.call(&x).shape(), &[2, 10, 16]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct TransformerEncoderLayer {
+    self_attn: MultiheadAttention,
+    linear1: Linear,
+    linear2: Linear,
+    norm1: LayerNorm,
+    norm2: LayerNorm,
+    norm_first: bool,
+    causal: bool,
+}
+
+impl TransformerEncoderLayer {
+    /// A post-norm layer over `dim` features with `heads` attention heads
+    /// and a feed-forward network of width `ff_dim`.
+    ///
+    /// # Panics
+    /// Panics unless `heads` is positive and divides `dim`.
+    pub fn new(dim: usize, heads: usize, ff_dim: usize, rng: &mut Rng) -> Self {
+        Self {
+            self_attn: MultiheadAttention::new(dim, heads, rng),
+            linear1: Linear::new(dim, ff_dim, rng),
+            linear2: Linear::new(ff_dim, dim, rng),
+            norm1: LayerNorm::new(dim),
+            norm2: LayerNorm::new(dim),
+            norm_first: false,
+            causal: false,
+        }
+    }
+
+    /// Normalize the input of each block (pre-norm) instead of the output
+    /// of each residual sum (post-norm).
+    pub fn norm_first(mut self, norm_first: bool) -> Self {
+        self.norm_first = norm_first;
+        self
+    }
+
+    /// Mask self-attention so no position sees a later one.
+    pub fn causal(mut self, causal: bool) -> Self {
+        self.causal = causal;
+        self
+    }
+
+    pub fn dim(&self) -> usize {
+        self.self_attn.dim()
+    }
+
+    pub fn self_attn(&self) -> &MultiheadAttention {
+        &self.self_attn
+    }
+}
+
+impl Module for TransformerEncoderLayer {
+    /// # Panics
+    /// Panics if `input` is not `[..., n, dim]`.
+    fn forward(&self, input: &Tensor) -> Tensor {
+        let x = residual(input, &self.norm1, self.norm_first, |x| {
+            let attn = &self.self_attn;
+            call_with(attn, x, |x| attn.attend_with(x, x, self.causal, None))
+        });
+        residual(&x, &self.norm2, self.norm_first, |x| {
+            feed_forward(&self.linear1, &self.linear2, x)
+        })
+    }
+
+    /// Self-attention, feed-forward and norm parameters, in that order.
+    fn parameters(&self) -> Vec<&Tensor> {
+        let mut params = self.self_attn.parameters();
+        params.extend(self.linear1.parameters());
+        params.extend(self.linear2.parameters());
+        params.extend(self.norm1.parameters());
+        params.extend(self.norm2.parameters());
+        params
+    }
+
+    fn parameters_mut(&mut self) -> Vec<&mut Tensor> {
+        let mut params = self.self_attn.parameters_mut();
+        params.extend(self.linear1.parameters_mut());
+        params.extend(self.linear2.parameters_mut());
+        params.extend(self.norm1.parameters_mut());
+        params.extend(self.norm2.parameters_mut());
+        params
+    }
+
+    fn children(&self) -> Vec<(String, &dyn Module)> {
+        vec![
+            ("self_attn".into(), &self.self_attn),
+            ("linear1".into(), &self.linear1),
+            ("linear2".into(), &self.linear2),
+            ("norm1".into(), &self.norm1),
+            ("norm2".into(), &self.norm2),
+        ]
+    }
+}
+
+/// A Transformer decoder layer: causal self-attention, cross-attention
+/// over the encoder's output (the memory), then a feed-forward network.
+///
+/// [`TransformerDecoderLayer::decode`] takes the memory; as a
+/// [`Module`], the layer runs without one, skipping cross-attention.
+///
+/// # Example
+/// ```
+/// use delta::nn::TransformerDecoderLayer;
+/// use delta::random::Rng;
+/// use delta::tensor::Tensor;
+///
+/// let mut rng = Rng::new(0);
+/// let layer = TransformerDecoderLayer::new(16, 4, 64, &mut rng);
+/// let target = Tensor::randn(&[2, 6, 16], &mut rng);
+/// let memory = Tensor::randn(&[2, 9, 16], &mut rng);
+/// assert_eq!(layer.decode(&target, &memory).shape(), &[2, 6, 16]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct TransformerDecoderLayer {
+    self_attn: MultiheadAttention,
+    cross_attn: MultiheadAttention,
+    linear1: Linear,
+    linear2: Linear,
+    norm1: LayerNorm,
+    norm2: LayerNorm,
+    norm3: LayerNorm,
+    norm_first: bool,
+}
+
+impl TransformerDecoderLayer {
+    /// A post-norm layer over `dim` features with `heads` attention heads
+    /// and a feed-forward network of width `ff_dim`.
+    ///
+    /// # Panics
+    /// Panics unless `heads` is positive and divides `dim`.
+    pub fn new(dim: usize, heads: usize, ff_dim: usize, rng: &mut Rng) -> Self {
+        Self {
+            self_attn: MultiheadAttention::new(dim, heads, rng),
+            cross_attn: MultiheadAttention::new(dim, heads, rng),
+            linear1: Linear::new(dim, ff_dim, rng),
+            linear2: Linear::new(ff_dim, dim, rng),
+            norm1: LayerNorm::new(dim),
+            norm2: LayerNorm::new(dim),
+            norm3: LayerNorm::new(dim),
+            norm_first: false,
+        }
+    }
+
+    /// Normalize the input of each block (pre-norm) instead of the output
+    /// of each residual sum (post-norm).
+    pub fn norm_first(mut self, norm_first: bool) -> Self {
+        self.norm_first = norm_first;
+        self
+    }
+
+    pub fn dim(&self) -> usize {
+        self.self_attn.dim()
+    }
+
+    /// Decode `target` `[..., n, dim]` attending to `memory`
+    /// `[..., m, dim]`. Runs like [`Module::call`]: the forward hooks see
+    /// `target`.
+    ///
+    /// # Panics
+    /// Panics if the shapes do not fit together.
+    pub fn decode(&self, target: &Tensor, memory: &Tensor) -> Tensor {
+        call_with(self, target, |x| self.run(x, Some(memory)))
+    }
+
+    fn run(&self, target: &Tensor, memory: Option<&Tensor>) -> Tensor {
+        let mut x = residual(target, &self.norm1, self.norm_first, |x| {
+            let attn = &self.self_attn;
+            call_with(attn, x, |x| attn.attend_with(x, x, true, None))
+        });
+        if let Some(memory) = memory {
+            x = residual(&x, &self.norm2, self.norm_first, |x| {
+                self.cross_attn.attend(x, memory, false)
+            });
+        }
+        residual(&x, &self.norm3, self.norm_first, |x| {
+            feed_forward(&self.linear1, &self.linear2, x)
+        })
+    }
+}
+
+impl Module for TransformerDecoderLayer {
+    /// Decode without memory: causal self-attention and feed-forward only.
+    fn forward(&self, input: &Tensor) -> Tensor {
+        self.run(input, None)
+    }
+
+    /// Self-attention, cross-attention, feed-forward and norm parameters,
+    /// in that order.
+    fn parameters(&self) -> Vec<&Tensor> {
+        let mut params = self.self_attn.parameters();
+        params.extend(self.cross_attn.parameters());
+        params.extend(self.linear1.parameters());
+        params.extend(self.linear2.parameters());
+        params.extend(self.norm1.parameters());
+        params.extend(self.norm2.parameters());
+        params.extend(self.norm3.parameters());
+        params
+    }
+
+    fn parameters_mut(&mut self) -> Vec<&mut Tensor> {
+        let mut params = self.self_attn.parameters_mut();
+        params.extend(self.cross_attn.parameters_mut());
+        params.extend(self.linear1.parameters_mut());
+        params.extend(self.linear2.parameters_mut());
+        params.extend(self.norm1.parameters_mut());
+        params.extend(self.norm2.parameters_mut());
+        params.extend(self.norm3.parameters_mut());
+        params
+    }
+
+    fn children(&self) -> Vec<(String, &dyn Module)> {
+        vec![
+            ("self_attn".into(), &self.self_attn),
+            ("cross_attn".into(), &self.cross_attn),
+            ("linear1".into(), &self.linear1),
+            ("linear2".into(), &self.linear2),
+            ("norm1".into(), &self.norm1),
+            ("norm2".into(), &self.norm2),
+            ("norm3".into(), &self.norm3),
+        ]
+    }
+}
+
+/// A stack of [`TransformerEncoderLayer`]s, applied in order, optionally
+/// followed by a final [`LayerNorm`].
+///
+/// # Example
+/// ```
+/// use delta::nn::{LayerNorm, Module, TransformerEncoder, TransformerEncoderLayer};
+/// use delta::random::Rng;
+/// use delta::tensor::Tensor;
+///
+/// let mut rng = Rng::new(0);
+/// let layers = (0..3)
+///     .map(|_| TransformerEncoderLayer::new(16, 4, 64, &mut rng).norm_first(true))
+///     .collect();
+/// let encoder = TransformerEncoder::new(layers).norm(LayerNorm::new(16));
+/// let x = Tensor::randn(&[2, 10, 16], &mut rng);
+/// assert_eq!(encoder.forward(&x).shape(), &[2, 10, 16]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct TransformerEncoder {
+    layers: Vec<TransformerEncoderLayer>,
+    norm: Option<LayerNorm>,
+}
+
+impl TransformerEncoder {
+    /// # Panics
+    /// Panics if `layers` is empty or the layers have different widths.
+    pub fn new(layers: Vec<TransformerEncoderLayer>) -> Self {
+        let dims: Vec<usize> = layers.iter().map(|l| l.dim()).collect();
+        assert!(
+            !dims.is_empty() && dims.iter().all(|&d| d == dims[0]),
+            "TransformerEncoder needs one or more layers of the same width, got widths {:?}",
+            dims
+        );
+        Self { layers, norm: None }
+    }
+
+    /// Normalize the output of the last layer.
+    pub fn norm(mut self, norm: LayerNorm) -> Self {
+        self.norm = Some(norm);
+        self
+    }
+
+    pub fn layers(&self) -> &[TransformerEncoderLayer] {
+        &self.layers
+    }
+}
+
+impl Module for TransformerEncoder {
+    fn forward(&self, input: &Tensor) -> Tensor {
+        let x = self
+            .layers
+            .iter()
+            .fold(input.clone(), |x, layer| layer.call(&x));
+        match &self.norm {
+            Some(norm) => norm.call(&x),
+            None => x,
+        }
+    }
+
+    /// Every layer's parameters in order, then the final norm's.
+    fn parameters(&self) -> Vec<&Tensor> {
+        self.layers
+            .iter()
+            .flat_map(|l| l.parameters())
+            .chain(self.norm.iter().flat_map(|n| n.parameters()))
+            .collect()
+    }
+
+    fn parameters_mut(&mut self) -> Vec<&mut Tensor> {
+        self.layers
+            .iter_mut()
+            .flat_map(|l| l.parameters_mut())
+            .chain(self.norm.iter_mut().flat_map(|n| n.parameters_mut()))
+            .collect()
+    }
+
+    /// `layers.0`, `layers.1`, ..., then `norm` if there is one.
+    fn children(&self) -> Vec<(String, &dyn Module)> {
+        let mut children: Vec<(String, &dyn Module)> = self
+            .layers
+            .iter()
+            .enumerate()
+            .map(|(i, l)| (format!("layers.{}", i), l as &dyn Module))
+            .collect();
+        if let Some(norm) = &self.norm {
+            children.push(("norm".into(), norm));
+        }
+        children
+    }
+}
+
+/// A stack of [`TransformerDecoderLayer`]s, each attending to the same
+/// memory, optionally followed by a final [`LayerNorm`].
+///
+/// # Example
+/// ```
+/// use delta::nn::{Module, TransformerDecoder, TransformerDecoderLayer, TransformerEncoder,
+///                 TransformerEncoderLayer};
+/// use delta::random::Rng;
+/// use delta::tensor::Tensor;
+///
+/// let mut rng = Rng::new(0);
+/// let encoder = TransformerEncoder::new(vec![TransformerEncoderLayer::new(8, 2, 32, &mut rng)]);
+/// let decoder = TransformerDecoder::new(vec![TransformerDecoderLayer::new(8, 2, 32, &mut rng)]);
+///
+/// let source = Tensor::randn(&[1, 12, 8], &mut rng);
+/// let target = Tensor::randn(&[1, 5, 8], &mut rng);
+/// let out = decoder.decode(&target, &encoder.forward(&source));
+/// assert_eq!(out.shape(), &[1, 5, 8]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct TransformerDecoder {
+    layers: Vec<TransformerDecoderLayer>,
+    norm: Option<LayerNorm>,
+}
+
+impl TransformerDecoder {
+    /// # Panics
+    /// Panics if `layers` is empty or the layers have different widths.
+    pub fn new(layers: Vec<TransformerDecoderLayer>) -> Self {
+        let dims: Vec<usize> = layers.iter().map(|l| l.dim()).collect();
+        assert!(
+            !dims.is_empty() && dims.iter().all(|&d| d == dims[0]),
+            "TransformerDecoder needs one or more layers of the same width, got widths {:?}",
+            dims
+        );
+        Self { layers, norm: None }
+    }
+
+    /// Normalize the output of the last layer.
+    pub fn norm(mut self, norm: LayerNorm) -> Self {
+        self.norm = Some(norm);
+        self
+    }
+
+    pub fn layers(&self) -> &[TransformerDecoderLayer] {
+        &self.layers
+    }
+
+    /// Decode `target` `[..., n, dim]`, every layer attending to `memory`
+    /// `[..., m, dim]`. Runs like [`Module::call`]: the forward hooks see
+    /// `target`.
+    ///
+    /// # Panics
+    /// Panics if the shapes do not fit together.
+    pub fn decode(&self, target: &Tensor, memory: &Tensor) -> Tensor {
+        call_with(self, target, |x| self.run(x, Some(memory)))
+    }
+
+    fn run(&self, target: &Tensor, memory: Option<&Tensor>) -> Tensor {
+        let x = self.layers.iter().fold(target.clone(), |x, layer| {
+            call_with(layer, &x, |x| layer.run(x, memory))
+        });
+        match &self.norm {
+            Some(norm) => norm.call(&x),
+            None => x,
+        }
+    }
+}
+
+impl Module for TransformerDecoder {
+    /// Decode without memory, skipping every layer's cross-attention.
+    fn forward(&self, input: &Tensor) -> Tensor {
+        self.run(input, None)
+    }
+
+    /// Every layer's parameters in order, then the final norm's.
+    fn parameters(&self) -> Vec<&Tensor> {
+        self.layers
+            .iter()
+            .flat_map(|l| l.parameters())
+            .chain(self.norm.iter().flat_map(|n| n.parameters()))
+            .collect()
+    }
+
+    fn parameters_mut(&mut self) -> Vec<&mut Tensor> {
+        self.layers
+            .iter_mut()
+            .flat_map(|l| l.parameters_mut())
+            .chain(self.norm.iter_mut().flat_map(|n| n.parameters_mut()))
+            .collect()
+    }
+
+    /// `layers.0`, `layers.1`, ..., then `norm` if there is one.
+    fn children(&self) -> Vec<(String, &dyn Module)> {
+        let mut children: Vec<(String, &dyn Module)> = self
+            .layers
+            .iter()
+            .enumerate()
+            .map(|(i, l)| (format!("layers.{}", i), l as &dyn Module))
+            .collect();
+        if let Some(norm) = &self.norm {
+            children.push(("norm".into(), norm));
+        }
+        children
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use super::*;
+    use crate::autograd::testing::{assert_close, check_grad};
+    use crate::nn::attention::{causal_mask, padding_mask};
+    use crate::nn::register_forward_hook;
+
+    /// The values of the first `end` positions of a `[n, d]` sequence.
+    fn rows(x: &Tensor, end: usize) -> Vec<f32> {
+        let d = x.shape()[x.ndim() - 1];
+        x.to_vec()[..end * d].to_vec()
+    }
+
+    #[test]
+    fn test_heads_attend_independently() {
+        // With identity projections, each head is plain attention over its
+        // slice of the features
+        let mut attn = MultiheadAttention::new(4, 2, &mut Rng::new(0));
+        let eye = Tensor::from_vec(
+            (0..16)
+                .map(|i| if i % 5 == 0 { 1.0 } else { 0.0 })
+                .collect(),
+            &[4, 4],
+        );
+        let identity = || Linear::from_parts(eye.clone(), None);
+        attn.q_proj = identity();
+        attn.k_proj = identity();
+        attn.v_proj = identity();
+        attn.out_proj = identity();
+
+        let x = Tensor::randn(&[3, 4], &mut Rng::new(1));
+        let out = attn.forward(&x).to_vec();
+        let data = x.to_vec();
+        for head in 0..2 {
+            let part: Vec<f32> = (0..3)
+                .flat_map(|i| data[i * 4 + head * 2..i * 4 + head * 2 + 2].to_vec())
+                .collect();
+            let part = Tensor::from_vec(part, &[3, 2]);
+            let expected = scaled_dot_product_attention(&part, &part, &part).to_vec();
+            for i in 0..3 {
+                for j in 0..2 {
+                    let got = out[i * 4 + head * 2 + j];
+                    assert!((got - expected[i * 2 + j]).abs() < 1e-5);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_causal_layers_ignore_the_future() {
+        let mut rng = Rng::new(2);
+        let encoder = TransformerEncoderLayer::new(8, 2, 16, &mut rng).causal(true);
+        let decoder = TransformerDecoderLayer::new(8, 2, 16, &mut rng).norm_first(true);
+        let x = Tensor::randn(&[6, 8], &mut rng);
+        let mut changed = x.to_vec();
+        changed[5 * 8..].iter_mut().for_each(|v| *v += 1.0);
+        let changed = Tensor::from_vec(changed, &[6, 8]);
+        let memory = Tensor::randn(&[4, 8], &mut rng);
+
+        assert_close(
+            &rows(&encoder.forward(&x), 5),
+            &rows(&encoder.forward(&changed), 5),
+            1e-5,
+        );
+        assert_close(
+            &rows(&decoder.decode(&x, &memory), 5),
+            &rows(&decoder.decode(&changed, &memory), 5),
+            1e-5,
+        );
+    }
+
+    #[test]
+    fn test_attend_masked() {
+        let mut rng = Rng::new(10);
+        let mha = MultiheadAttention::new(8, 2, &mut rng);
+        let x = Tensor::randn(&[2, 4, 8], &mut rng);
+        assert_close(
+            &mha.attend_masked(&x, &x, &causal_mask(4, 4, 0)).to_vec(),
+            &mha.attend(&x, &x, true).to_vec(),
+            1e-5,
+        );
+
+        // Padding of the second sequence does not reach its real tokens
+        let out = mha.attend_masked(&x, &x, &padding_mask(&[4, 3], 4));
+        let short = Tensor::from_vec(x.to_vec()[32..56].to_vec(), &[3, 8]);
+        assert_close(&out.to_vec()[32..56], &mha.forward(&short).to_vec(), 1e-5);
+    }
+
+    #[test]
+    fn test_stack_parameters() {
+        let mut rng = Rng::new(3);
+        let layer = TransformerEncoderLayer::new(8, 2, 32, &mut rng);
+        // 4 projections, two feed-forward layers and two norms
+        let expected = 4 * (8 * 8 + 8) + (8 * 32 + 32) + (32 * 8 + 8) + 2 * 16;
+        assert_eq!(layer.num_parameters(), expected);
+
+        let encoder = TransformerEncoder::new(vec![
+            layer,
+            TransformerEncoderLayer::new(8, 2, 32, &mut rng),
+        ])
+        .norm(LayerNorm::new(8));
+        assert_eq!(encoder.num_parameters(), 2 * expected + 16);
+        assert_eq!(encoder.parameters().len(), 2 * 16 + 2);
+
+        let decoder = TransformerDecoderLayer::new(8, 2, 32, &mut rng);
+        assert_eq!(decoder.num_parameters(), expected + 4 * (8 * 8 + 8) + 16);
+    }
+
+    #[test]
+    fn test_hooks_see_attention_and_layers() {
+        let mut rng = Rng::new(12);
+        let encoder = TransformerEncoderLayer::new(8, 2, 16, &mut rng);
+        let decoder =
+            TransformerDecoder::new(vec![TransformerDecoderLayer::new(8, 2, 16, &mut rng)]);
+        let seen = Rc::new(RefCell::new(Vec::new()));
+        let hook = |name: &'static str| {
+            let sink = seen.clone();
+            move |_: &Tensor, output: &Tensor| {
+                sink.borrow_mut().push((name, output.shape().to_vec()));
+                None
+            }
+        };
+        let layer = &decoder.layers()[0];
+        let _handles = [
+            register_forward_hook(encoder.self_attn(), hook("encoder.self_attn")),
+            register_forward_hook(&layer.cross_attn, hook("cross_attn")),
+            register_forward_hook(layer, hook("layers.0")),
+        ];
+
+        let (x, memory) = (
+            Tensor::randn(&[3, 8], &mut rng),
+            Tensor::randn(&[5, 8], &mut rng),
+        );
+        encoder.call(&x);
+        decoder.decode(&x, &memory);
+        assert_eq!(
+            *seen.borrow(),
+            vec![
+                ("encoder.self_attn", vec![3, 8]),
+                ("cross_attn", vec![3, 8]),
+                ("layers.0", vec![3, 8]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_children() {
+        let mut rng = Rng::new(5);
+        let decoder = TransformerDecoder::new(vec![
+            TransformerDecoderLayer::new(4, 2, 8, &mut rng),
+            TransformerDecoderLayer::new(4, 2, 8, &mut rng),
+        ])
+        .norm(LayerNorm::new(4));
+        let names: Vec<String> = decoder.children().into_iter().map(|(n, _)| n).collect();
+        assert_eq!(names, ["layers.0", "layers.1", "norm"]);
+        let layer = &decoder.children()[1].1;
+        assert_eq!(layer.children().len(), 7);
+        assert_eq!(layer.children()[1].1.num_parameters(), 4 * (4 * 4 + 4));
+    }
+
+    #[test]
+    fn test_named_parameters_and_modules() {
+        let mut rng = Rng::new(6);
+        let encoder = TransformerEncoder::new(vec![
+            TransformerEncoderLayer::new(4, 2, 8, &mut rng),
+            TransformerEncoderLayer::new(4, 2, 8, &mut rng),
+        ]);
+        let named = encoder.named_parameters();
+        assert_eq!(named.len(), encoder.parameters().len());
+        assert_eq!(named[0].0, "layers.0.self_attn.q_proj.weight");
+        assert_eq!(named[7].0, "layers.0.self_attn.out_proj.bias");
+        assert_eq!(named.last().unwrap().0, "layers.1.norm2.beta");
+        for ((_, a), b) in named.iter().zip(encoder.parameters()) {
+            assert!(std::ptr::eq(*a, b));
+        }
+
+        let modules: Vec<String> = encoder
+            .named_modules()
+            .into_iter()
+            .map(|(n, _)| n)
+            .collect();
+        // The encoder, then per layer itself, attention and its 4
+        // projections, 2 feed-forward layers and 2 norms
+        assert_eq!(modules.len(), 1 + 2 * 10);
+        assert_eq!(
+            modules[..4],
+            [
+                "",
+                "layers.0",
+                "layers.0.self_attn",
+                "layers.0.self_attn.q_proj"
+            ]
+        );
+
+        let mut weights = 0;
+        encoder.apply(|name, m| {
+            if name.ends_with("proj") {
+                weights += m.num_parameters();
+            }
+        });
+        assert_eq!(weights, 2 * 4 * (4 * 4 + 4));
+    }
+
+    #[test]
+    fn test_freeze() {
+        let mut rng = Rng::new(8);
+        let layer = TransformerEncoderLayer::new(4, 2, 8, &mut rng);
+        layer.self_attn().freeze();
+        let trainable = layer.trainable_parameters().len();
+        assert_eq!(trainable, layer.parameters().len() - 8);
+
+        let x = Tensor::randn(&[3, 4], &mut rng);
+        layer.forward(&x).sum().backward();
+        for (name, p) in layer.named_parameters() {
+            assert_eq!(
+                p.grad().is_some(),
+                !name.starts_with("self_attn."),
+                "{}",
+                name
+            );
+        }
+
+        layer.unfreeze();
+        assert_eq!(layer.trainable_parameters().len(), layer.parameters().len());
+    }
+
+    #[test]
+    fn test_gradients() {
+        let mut rng = Rng::new(4);
+        let layer = TransformerDecoderLayer::new(4, 2, 8, &mut rng).norm_first(true);
+        let target = Tensor::randn(&[3, 4], &mut rng);
+        let memory = Tensor::randn(&[2, 4], &mut rng);
+        check_grad(|t| layer.decode(&t[0], &t[1]).tanh(), &[target, memory]);
+
+        let encoder = TransformerEncoderLayer::new(4, 2, 8, &mut rng);
+        let x = Tensor::randn(&[2, 3, 4], &mut rng);
+        encoder.forward(&x).sum().backward();
+        assert!(encoder.parameters().iter().all(|p| p.grad().is_some()));
+    }
+
+    #[test]
+    #[should_panic(expected = "MultiheadAttention needs a number of heads dividing dim 6, got 4")]
+    fn test_heads_must_divide_dim() {
+        MultiheadAttention::new(6, 4, &mut Rng::new(0));
+    }
+}
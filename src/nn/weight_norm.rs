@@ -0,0 +1,189 @@
+use crate::tensor::Tensor;
+
+/// Weight normalization: a weight split into a magnitude `g` and a
+/// direction `v`, recombined as `w = g * v / ‖v‖`.
+///
+/// Salimans & Kingma (2016). Decoupling the length of each weight vector
+/// from its direction tends to condition optimization better than
+/// training `w` directly. The norm is taken over every dimension except
+/// `dim`, so with `dim = 0` each output unit of a `[out, in]` linear or
+/// `[out, in, k...]` convolution weight gets its own magnitude; `g` has
+/// the shape of the weight with every other dimension reduced to 1.
+///
+/// Both `g` and `v` are leaves tracked for gradients. Train them, then
+/// [`WeightNorm::remove`] folds them back into a plain weight.
+///
+/// # Example
+/// ```
+/// use delta::nn::weight_norm;
+/// use delta::tensor::Tensor;
+///
+/// let w = Tensor::from_vec(vec![3.0, 4.0,
+///                               0.0, 2.0], &[2, 2]);
+/// let wn = weight_norm(&w, 0);
+/// assert_eq!(wn.g().to_vec(), vec![5.0, 2.0]);
+/// assert_eq!(wn.weight().to_vec(), w.to_vec());
+///
+/// let loss = wn.weight().sum();
+/// loss.backward();
+/// assert!(wn.g().grad().is_some() && wn.v().grad().is_some());
+/// ```
+#[derive(Debug, Clone)]
+pub struct WeightNorm {
+    g: Tensor,
+    v: Tensor,
+    dim: usize,
+}
+
+/// Reparametrize `weight` with weight normalization over every dimension
+/// except `dim`. See [`WeightNorm`].
+///
+/// # Panics
+/// Panics if `dim` is out of range.
+pub fn weight_norm(weight: &Tensor, dim: usize) -> WeightNorm {
+    WeightNorm::new(weight, dim)
+}
+
+impl WeightNorm {
+    /// Split `weight` so that `weight()` initially reproduces it.
+    ///
+    /// # Panics
+    /// Panics if `dim` is out of range.
+    pub fn new(weight: &Tensor, dim: usize) -> Self {
+        assert!(
+            dim < weight.ndim(),
+            "dim {} out of range for a weight of shape {:?}",
+            dim,
+            weight.shape()
+        );
+        let v = weight.detach();
+        let g = norm_except(&v, dim);
+        Self {
+            g: g.requires_grad(true),
+            v: v.requires_grad(true),
+            dim,
+        }
+    }
+
+    /// The weight `g * v / ‖v‖`, recorded so gradients reach `g` and `v`.
+    pub fn weight(&self) -> Tensor {
+        let scale = self.g.div(&norm_except(&self.v, self.dim));
+        self.v.mul(&scale.broadcast_to(self.v.shape()))
+    }
+
+    /// The magnitude, one entry per index along `dim`.
+    pub fn g(&self) -> &Tensor {
+        &self.g
+    }
+
+    /// The direction, shaped like the weight.
+    pub fn v(&self) -> &Tensor {
+        &self.v
+    }
+
+    /// Replace the magnitude's values, e.g. after an optimizer step. Only
+    /// the values are kept; it becomes a fresh leaf.
+    ///
+    /// # Panics
+    /// Panics if `g` has a different shape.
+    pub fn set_g(&mut self, g: Tensor) {
+        assert_eq!(
+            g.shape(),
+            self.g.shape(),
+            "set_g expected shape {:?}, got {:?}",
+            self.g.shape(),
+            g.shape()
+        );
+        self.g = g.detach().requires_grad(true);
+    }
+
+    /// Replace the direction's values, like [`WeightNorm::set_g`].
+    ///
+    /// # Panics
+    /// Panics if `v` has a different shape.
+    pub fn set_v(&mut self, v: Tensor) {
+        assert_eq!(
+            v.shape(),
+            self.v.shape(),
+            "set_v expected shape {:?}, got {:?}",
+            self.v.shape(),
+            v.shape()
+        );
+        self.v = v.detach().requires_grad(true);
+    }
+
+    /// Fold `g` and `v` back into a plain weight: a leaf tracked for
+    /// gradients, holding the current value of `weight()`.
+    pub fn remove(self) -> Tensor {
+        self.weight().detach().requires_grad(true)
+    }
+}
+
+/// Euclidean norm over every dimension but `dim`, keeping them as size 1.
+fn norm_except(t: &Tensor, dim: usize) -> Tensor {
+    let mut sq = t.powi(2);
+    for d in (0..t.ndim()).filter(|&d| d != dim) {
+        sq = sq.sum_dim(d, true);
+    }
+    sq.sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::autograd::testing::{assert_close, check_grad};
+
+    #[test]
+    fn test_conv_shaped_weight() {
+        let data: Vec<f32> = (0..12).map(|i| i as f32 - 5.5).collect();
+        let w = Tensor::from_vec(data, &[2, 3, 2]);
+        let wn = weight_norm(&w, 0);
+        assert_eq!(wn.g().shape(), &[2, 1, 1]);
+        assert_close(&wn.weight().to_vec(), &w.to_vec(), 1e-5);
+
+        let per_column = weight_norm(&w, 2);
+        assert_eq!(per_column.g().shape(), &[1, 1, 2]);
+    }
+
+    #[test]
+    fn test_magnitude_and_direction_are_decoupled() {
+        let w = Tensor::from_vec(vec![3.0, 4.0], &[1, 2]);
+        let mut wn = weight_norm(&w, 0);
+        // Scaling v leaves the weight unchanged; g alone sets its length
+        wn.set_v(wn.v().scalar_mul(10.0));
+        assert_close(&wn.weight().to_vec(), &[3.0, 4.0], 1e-5);
+        wn.set_g(Tensor::from_vec(vec![10.0], &[1, 1]));
+        assert_close(&wn.weight().to_vec(), &[6.0, 8.0], 1e-5);
+    }
+
+    #[test]
+    fn test_gradients() {
+        let g = Tensor::from_vec(vec![2.0, 0.5], &[2, 1]);
+        let v = Tensor::from_vec(vec![1.0, -2.0, 0.5, 3.0, 1.0, -1.0], &[2, 3]);
+        check_grad(
+            |t| {
+                let mut wn = weight_norm(&t[1], 0);
+                wn.g = t[0].clone();
+                wn.v = t[1].clone();
+                wn.weight()
+            },
+            &[g, v],
+        );
+    }
+
+    #[test]
+    fn test_remove() {
+        let w = Tensor::from_vec(vec![1.0, 2.0, 2.0, 1.0], &[2, 2]);
+        let mut wn = weight_norm(&w, 1);
+        wn.set_g(wn.g().scalar_mul(2.0));
+        let plain = wn.remove();
+        assert!(plain.is_leaf() && plain.node().is_some());
+        assert_close(&plain.to_vec(), &w.scalar_mul(2.0).to_vec(), 1e-5);
+    }
+
+    #[test]
+    #[should_panic(expected = "out of range")]
+    fn test_dim_out_of_range() {
+        weight_norm(&Tensor::zeros(&[2, 2]), 2);
+    }
+}
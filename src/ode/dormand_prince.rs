@@ -0,0 +1,345 @@
+use crate::ode::{check_shape, step};
+use crate::tensor::Tensor;
+
+/// Nodes c, stage coefficients a, 5th-order weights b and the error
+/// weights b - b* (b* being the embedded 4th-order weights) of the
+/// Dormand-Prince 5(4) pair.
+const C: [f32; 6] = [0.2, 0.3, 0.8, 8.0 / 9.0, 1.0, 1.0];
+const A: [&[f32]; 6] = [
+    &[0.2],
+    &[3.0 / 40.0, 9.0 / 40.0],
+    &[44.0 / 45.0, -56.0 / 15.0, 32.0 / 9.0],
+    &[
+        19372.0 / 6561.0,
+        -25360.0 / 2187.0,
+        64448.0 / 6561.0,
+        -212.0 / 729.0,
+    ],
+    &[
+        9017.0 / 3168.0,
+        -355.0 / 33.0,
+        46732.0 / 5247.0,
+        49.0 / 176.0,
+        -5103.0 / 18656.0,
+    ],
+    &[
+        35.0 / 384.0,
+        0.0,
+        500.0 / 1113.0,
+        125.0 / 192.0,
+        -2187.0 / 6784.0,
+        11.0 / 84.0,
+    ],
+];
+const E: [f32; 7] = [
+    71.0 / 57600.0,
+    0.0,
+    -71.0 / 16695.0,
+    71.0 / 1920.0,
+    -17253.0 / 339200.0,
+    22.0 / 525.0,
+    -1.0 / 40.0,
+];
+
+/// Outcome of an adaptive integration.
+#[derive(Debug, Clone)]
+pub struct OdeSolution {
+    /// The state at the final time.
+    pub y: Tensor,
+    /// Accepted steps.
+    pub steps: usize,
+    /// Steps rejected for exceeding the tolerance and retried smaller.
+    pub rejected: usize,
+}
+
+/// Adaptive Runge-Kutta solver using the Dormand-Prince 5(4) pair (the
+/// method behind `ode45` and SciPy's `RK45`).
+///
+/// Each step computes a 5th-order solution and an embedded 4th-order one;
+/// their difference estimates the local error. A step is accepted when
+/// the error, measured relative to `atol + rtol |y|` per element (RMS over
+/// elements), is at most 1, and the next step size is chosen from it.
+/// The last stage of a step is the first of the next, so an accepted step
+/// costs 6 evaluations of the dynamics.
+///
+/// Step size control runs on plain values; only the accepted steps take
+/// part in the graph, so gradients are those of the discrete solution.
+///
+/// # Example
+/// ```
+/// use delta::ode::DormandPrince;
+/// use delta::tensor::Tensor;
+///
+/// // Logistic growth y' = y (1 - y), y(0) = 0.1
+/// let y0 = Tensor::from_vec(vec![0.1], &[1]);
+/// let sol = DormandPrince::new()
+///     .rtol(1e-6)
+///     .integrate(|_t, y| y.mul(&y.neg().scalar_add(1.0)), &y0, 0.0, 5.0);
+/// let exact = 1.0 / (1.0 + 9.0 * (-5.0f32).exp());
+/// assert!((sol.y.get(&[0]) - exact).abs() < 1e-5);
+/// assert!(sol.steps < 100);
+/// ```
+#[derive(Debug, Clone)]
+pub struct DormandPrince {
+    rtol: f32,
+    atol: f32,
+    first_step: Option<f32>,
+    max_steps: usize,
+}
+
+impl Default for DormandPrince {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DormandPrince {
+    /// A solver with the defaults: relative tolerance 1e-5, absolute
+    /// tolerance 1e-7, automatic first step, at most 10000 steps.
+    pub fn new() -> Self {
+        Self {
+            rtol: 1e-5,
+            atol: 1e-7,
+            first_step: None,
+            max_steps: 10_000,
+        }
+    }
+
+    /// Relative error tolerance.
+    pub fn rtol(mut self, rtol: f32) -> Self {
+        self.rtol = rtol;
+        self
+    }
+
+    /// Absolute error tolerance, which matters for elements near zero.
+    pub fn atol(mut self, atol: f32) -> Self {
+        self.atol = atol;
+        self
+    }
+
+    /// Size of the first trial step. By default it is guessed from the
+    /// scale of the state and of its derivative.
+    pub fn first_step(mut self, h: f32) -> Self {
+        self.first_step = Some(h);
+        self
+    }
+
+    /// Maximum number of steps, accepted or rejected.
+    pub fn max_steps(mut self, max_steps: usize) -> Self {
+        self.max_steps = max_steps;
+        self
+    }
+
+    /// Integrate dy/dt = f(t, y) from `(t0, y0)` to `t1`. `t1` may be
+    /// before `t0`.
+    ///
+    /// # Panics
+    /// - Panics if `f` returns a tensor not shaped like the state
+    /// - Panics if the solution is not reached within `max_steps` (the
+    ///   problem is too stiff, or the tolerances too tight for f32)
+    pub fn integrate(
+        &self,
+        f: impl Fn(f32, &Tensor) -> Tensor,
+        y0: &Tensor,
+        t0: f32,
+        t1: f32,
+    ) -> OdeSolution {
+        let mut y = y0.clone();
+        let mut sol = OdeSolution {
+            y: y0.clone(),
+            steps: 0,
+            rejected: 0,
+        };
+        if t0 == t1 {
+            return sol;
+        }
+
+        let dir = (t1 - t0).signum();
+        let mut k1 = f(t0, &y);
+        check_shape(&k1, &y);
+        let mut h = self
+            .first_step
+            .unwrap_or_else(|| self.initial_step(&y, &k1))
+            .abs()
+            .min((t1 - t0).abs());
+        let mut t = t0;
+
+        while (t1 - t) * dir > 0.0 {
+            assert!(
+                sol.steps + sol.rejected < self.max_steps,
+                "DormandPrince did not reach t = {} within {} steps (stopped at t = {})",
+                t1,
+                self.max_steps,
+                t
+            );
+            // Land exactly on t1, and don't leave a sliver of a last step
+            if (t1 - t - dir * h) * dir < 1e-6 * h {
+                h = (t1 - t).abs();
+            }
+            let hs = dir * h;
+
+            let mut ks = vec![k1.clone()];
+            for (c, a) in C[..5].iter().zip(&A[..5]) {
+                let refs: Vec<&Tensor> = ks.iter().collect();
+                let stage = f(t + c * hs, &step(&y, hs, &refs, a));
+                ks.push(stage);
+            }
+            let refs: Vec<&Tensor> = ks.iter().collect();
+            let y_new = step(&y, hs, &refs, A[5]);
+            // The last stage is the derivative at the new solution
+            ks.push(f(t + hs, &y_new));
+            let refs: Vec<&Tensor> = ks.iter().collect();
+
+            let err = self.error_norm(&refs, hs, &y, &y_new);
+            if err <= 1.0 {
+                t += hs;
+                y = y_new;
+                k1 = ks.pop().unwrap();
+                sol.steps += 1;
+            } else {
+                sol.rejected += 1;
+            }
+            // Standard controller with a safety factor, limited to
+            // shrinking 5x or growing 10x per step
+            let factor = if err == 0.0 {
+                10.0
+            } else {
+                (0.9 * err.powf(-0.2)).clamp(0.2, 10.0)
+            };
+            h *= if err <= 1.0 { factor } else { factor.min(1.0) };
+        }
+        sol.y = y;
+        sol
+    }
+
+    /// RMS over elements of the local error estimate, each scaled by its
+    /// tolerance.
+    fn error_norm(&self, ks: &[&Tensor], h: f32, y: &Tensor, y_new: &Tensor) -> f32 {
+        let ks: Vec<Vec<f32>> = ks.iter().map(|k| k.to_vec()).collect();
+        let (y, y_new) = (y.to_vec(), y_new.to_vec());
+        let n = y.len().max(1);
+        let sum: f32 = (0..y.len())
+            .map(|i| {
+                let err: f32 = E.iter().zip(&ks).map(|(e, k)| e * k[i]).sum::<f32>() * h;
+                let scale = self.atol + self.rtol * y[i].abs().max(y_new[i].abs());
+                (err / scale).powi(2)
+            })
+            .sum();
+        (sum / n as f32).sqrt()
+    }
+
+    /// A first step that changes the state by about 1% (Hairer, Nørsett
+    /// and Wanner's heuristic, without its second stage).
+    fn initial_step(&self, y: &Tensor, dy: &Tensor) -> f32 {
+        let rms = |v: &[f32], y: &[f32]| {
+            let s: f32 = v
+                .iter()
+                .zip(y)
+                .map(|(v, y)| (v / (self.atol + self.rtol * y.abs())).powi(2))
+                .sum();
+            (s / v.len().max(1) as f32).sqrt()
+        };
+        let y_data = y.to_vec();
+        let (d0, d1) = (rms(&y_data, &y_data), rms(&dy.to_vec(), &y_data));
+        if d0 < 1e-5 || d1 < 1e-5 {
+            1e-6
+        } else {
+            0.01 * d0 / d1
+        }
+    }
+}
+
+/// Integrate dy/dt = f(t, y) from `(t0, y0)` to `t1` with the default
+/// [`DormandPrince`] solver and return y(t1).
+///
+/// # Example
+/// ```
+/// use delta::ode::dopri5;
+/// use delta::tensor::Tensor;
+/// let y0 = Tensor::from_vec(vec![1.0], &[1]);
+/// let y1 = dopri5(|_t, y| y.neg(), &y0, 0.0, 1.0);
+/// assert!((y1.get(&[0]) - (-1.0f32).exp()).abs() < 1e-5);
+/// ```
+pub fn dopri5(f: impl Fn(f32, &Tensor) -> Tensor, y0: &Tensor, t0: f32, t1: f32) -> Tensor {
+    DormandPrince::new().integrate(f, y0, t0, t1).y
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::autograd::testing::check_grad;
+
+    #[test]
+    fn test_tableau_consistency() {
+        // Each row of a sums to its node, and the error weights sum to 0
+        for (c, a) in C.iter().zip(A) {
+            assert!((a.iter().sum::<f32>() - c).abs() < 1e-5);
+        }
+        assert!(E.iter().sum::<f32>().abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_time_dependent() {
+        // y' = cos t, y(0) = 0: y = sin t
+        let y0 = Tensor::zeros(&[1]);
+        let f = |t: f32, y: &Tensor| Tensor::from_vec(vec![t.cos()], y.shape());
+        let sol = DormandPrince::new().integrate(f, &y0, 0.0, 3.0);
+        assert!((sol.y.get(&[0]) - 3f32.sin()).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_tolerance_controls_steps() {
+        let a = Tensor::from_vec(vec![0.0, 1.0, -1.0, 0.0], &[2, 2]);
+        let f = |_t: f32, y: &Tensor| a.matmul(y);
+        let y0 = Tensor::from_vec(vec![1.0, 0.0], &[2, 1]);
+        let tau = std::f32::consts::TAU;
+        let loose = DormandPrince::new().rtol(1e-3).integrate(f, &y0, 0.0, tau);
+        let tight = DormandPrince::new().rtol(1e-6).integrate(f, &y0, 0.0, tau);
+        assert!(loose.steps < tight.steps);
+        assert!((tight.y.get(&[0, 0]) - 1.0).abs() < 1e-4);
+        assert!(tight.y.get(&[1, 0]).abs() < 1e-4);
+
+        let back = DormandPrince::new().integrate(f, &tight.y, tau, 0.0);
+        assert!((back.y.get(&[0, 0]) - 1.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_rejects_oversized_first_step() {
+        let y0 = Tensor::from_vec(vec![1.0], &[1]);
+        let sol = DormandPrince::new().first_step(10.0).integrate(
+            |_, y| y.scalar_mul(-5.0),
+            &y0,
+            0.0,
+            2.0,
+        );
+        assert!(sol.rejected > 0);
+        assert!((sol.y.get(&[0]) - (-10.0f32).exp()).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_gradients_through_solver() {
+        let inputs = [
+            Tensor::from_vec(vec![0.5, -1.0], &[2]),
+            Tensor::from_vec(vec![0.3], &[1]),
+        ];
+        // The same steps for every evaluation: fix them with a generous
+        // first step and a loose tolerance on a smooth problem
+        let solver = DormandPrince::new().rtol(1e-2).first_step(0.5);
+        check_grad(
+            |p| {
+                let a = p[1].broadcast_to(&[2]);
+                solver.integrate(|_, y| y.mul(&a), &p[0], 0.0, 1.0).y
+            },
+            &inputs,
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "did not reach t = 1")]
+    fn test_max_steps() {
+        let y0 = Tensor::from_vec(vec![1.0], &[1]);
+        DormandPrince::new()
+            .max_steps(3)
+            .integrate(|_, y| y.scalar_mul(-100.0), &y0, 0.0, 1.0);
+    }
+}
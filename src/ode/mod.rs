@@ -0,0 +1,35 @@
+//! Numerical solvers for ordinary differential equations dy/dt = f(t, y).
+//!
+//! The state is a [`Tensor`](crate::tensor::Tensor) of any shape and the
+//! dynamics a closure returning a tensor of the same shape. Every step is
+//! made of tensor ops, so when the initial state or the parameters inside
+//! the dynamics track gradients, the final state is differentiable with
+//! respect to them (backpropagation through the solver), as a neural ODE
+//! needs.
+
+mod dormand_prince;
+mod rk4;
+
+pub use dormand_prince::{DormandPrince, OdeSolution, dopri5};
+pub use rk4::rk4;
+
+use crate::tensor::Tensor;
+
+/// `y + h Σ cᵢ kᵢ`, skipping zero coefficients.
+fn step(y: &Tensor, h: f32, ks: &[&Tensor], coefs: &[f32]) -> Tensor {
+    ks.iter()
+        .zip(coefs)
+        .filter(|(_, c)| **c != 0.0)
+        .fold(y.clone(), |acc, (k, c)| acc.add(&k.scalar_mul(h * c)))
+}
+
+/// Panic unless the dynamics preserved the shape of the state.
+fn check_shape(dy: &Tensor, y: &Tensor) {
+    assert_eq!(
+        dy.shape(),
+        y.shape(),
+        "ODE dynamics returned shape {:?} for a state of shape {:?}",
+        dy.shape(),
+        y.shape()
+    );
+}
@@ -0,0 +1,108 @@
+use crate::ode::{check_shape, step};
+use crate::tensor::Tensor;
+
+/// Integrate dy/dt = f(t, y) from `(t0, y0)` to `t1` with `steps` steps of
+/// the classical fourth-order Runge-Kutta method, and return y(t1).
+///
+/// The step size is fixed, `(t1 - t0) / steps`, and the error shrinks like
+/// its fourth power. `t1` may be before `t0`, to integrate backwards.
+///
+/// # Panics
+/// - Panics if `steps` is 0
+/// - Panics if `f` returns a tensor not shaped like the state
+///
+/// # Example
+/// ```
+/// use delta::ode::rk4;
+/// use delta::tensor::Tensor;
+/// // y' = -y, y(0) = 1: y(1) = 1/e
+/// let y0 = Tensor::from_vec(vec![1.0], &[1]);
+/// let y1 = rk4(|_t, y| y.neg(), &y0, 0.0, 1.0, 20);
+/// assert!((y1.get(&[0]) - (-1.0f32).exp()).abs() < 1e-6);
+/// ```
+pub fn rk4(
+    f: impl Fn(f32, &Tensor) -> Tensor,
+    y0: &Tensor,
+    t0: f32,
+    t1: f32,
+    steps: usize,
+) -> Tensor {
+    assert!(steps > 0, "rk4 requires at least one step");
+    let h = (t1 - t0) / steps as f32;
+    let mut y = y0.clone();
+    for i in 0..steps {
+        let t = t0 + i as f32 * h;
+        let k1 = f(t, &y);
+        check_shape(&k1, &y);
+        let k2 = f(t + h / 2.0, &step(&y, h, &[&k1], &[0.5]));
+        let k3 = f(t + h / 2.0, &step(&y, h, &[&k2], &[0.5]));
+        let k4 = f(t + h, &step(&y, h, &[&k3], &[1.0]));
+        let sixth = 1.0 / 6.0;
+        y = step(
+            &y,
+            h,
+            &[&k1, &k2, &k3, &k4],
+            &[sixth, 2.0 * sixth, 2.0 * sixth, sixth],
+        );
+    }
+    y
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::autograd::testing::check_grad;
+
+    #[test]
+    fn test_fourth_order_convergence() {
+        let y0 = Tensor::from_vec(vec![1.0], &[1]);
+        let exact = 2f32.exp();
+        let error = |steps| (rk4(|_, y| y.clone(), &y0, 0.0, 2.0, steps).get(&[0]) - exact).abs();
+        // Halving the step divides the error by about 2⁴
+        let ratio = error(4) / error(8);
+        assert!((12.0..20.0).contains(&ratio), "{}", ratio);
+    }
+
+    #[test]
+    fn test_harmonic_oscillator() {
+        // (x, v)' = (v, -x): a full period returns to the start
+        let a = Tensor::from_vec(vec![0.0, 1.0, -1.0, 0.0], &[2, 2]);
+        let f = |_t: f32, y: &Tensor| a.matmul(y);
+        let y0 = Tensor::from_vec(vec![1.0, 0.0], &[2, 1]);
+        let y = rk4(f, &y0, 0.0, std::f32::consts::TAU, 200);
+        assert!((y.get(&[0, 0]) - 1.0).abs() < 1e-4);
+        assert!(y.get(&[1, 0]).abs() < 1e-4);
+
+        // And backwards
+        let back = rk4(f, &y, std::f32::consts::TAU, 0.0, 200);
+        assert!((back.get(&[0, 0]) - 1.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_gradients_through_solver() {
+        // y' = a y: y(1) = y0 eᵃ
+        let inputs = [
+            Tensor::from_vec(vec![0.5, -1.0], &[2]),
+            Tensor::from_vec(vec![0.3], &[1]),
+        ];
+        check_grad(
+            |p| {
+                let a = p[1].broadcast_to(&[2]);
+                rk4(|_, y| y.mul(&a), &p[0], 0.0, 1.0, 10)
+            },
+            &inputs,
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "ODE dynamics returned shape [1]")]
+    fn test_shape_mismatch() {
+        rk4(
+            |_, _| Tensor::zeros(&[1]),
+            &Tensor::zeros(&[2]),
+            0.0,
+            1.0,
+            1,
+        );
+    }
+}
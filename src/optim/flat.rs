@@ -0,0 +1,37 @@
+//! Helpers for treating a list of parameter tensors as one flat vector.
+
+use crate::tensor::Tensor;
+
+pub(super) fn dot(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+pub(super) fn max_abs(v: &[f32]) -> f32 {
+    v.iter().fold(0.0, |m, x| m.max(x.abs()))
+}
+
+/// x += a * y
+pub(super) fn axpy(x: &mut [f32], a: f32, y: &[f32]) {
+    x.iter_mut().zip(y).for_each(|(x, y)| *x += a * y);
+}
+
+/// x + t * d
+pub(super) fn offset(x: &[f32], t: f32, d: &[f32]) -> Vec<f32> {
+    x.iter().zip(d).map(|(x, d)| x + t * d).collect()
+}
+
+/// Concatenate the elements of all tensors.
+pub(super) fn flatten(tensors: &[Tensor]) -> Vec<f32> {
+    tensors.iter().flat_map(|t| t.to_vec()).collect()
+}
+
+/// Inverse of [`flatten`]: refill the tensors (keeping their shapes) from
+/// consecutive chunks of `flat`.
+pub(super) fn unflatten(tensors: &mut [Tensor], flat: &[f32]) {
+    let mut rest = flat;
+    for t in tensors.iter_mut() {
+        let (chunk, tail) = rest.split_at(t.nelems());
+        *t = Tensor::from_vec(chunk.to_vec(), t.shape());
+        rest = tail;
+    }
+}
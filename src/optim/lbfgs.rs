@@ -0,0 +1,359 @@
+use std::collections::VecDeque;
+
+use crate::autograd::value_and_grad;
+use crate::optim::flat::{axpy, dot, flatten, max_abs, offset, unflatten};
+use crate::optim::line_search::{Trial, strong_wolfe};
+use crate::tensor::Tensor;
+
+/// How [`LBFGS`] picks the step size along each search direction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineSearch {
+    /// Always step by the learning rate.
+    Fixed,
+    /// Search for a step satisfying the strong Wolfe conditions. Costs a
+    /// few extra loss evaluations per iteration but is far more robust.
+    StrongWolfe,
+}
+
+/// Limited-memory BFGS, a quasi-Newton method.
+///
+/// Builds an approximation of the inverse Hessian from the last
+/// `history_size` parameter and gradient changes, and uses it to scale the
+/// gradient into a much better search direction than plain gradient
+/// descent. Suited to small, smooth, full-batch problems (curve fitting,
+/// physics models), where it typically converges in a handful of steps.
+///
+/// The optimizer owns the parameters. Each [`LBFGS::step`] runs up to
+/// `max_iter` iterations and calls the closure to re-evaluate the loss at
+/// new parameter values; the closure receives the current parameters,
+/// tracked for gradients, and returns the scalar loss.
+///
+/// # Example
+/// ```
+/// use delta::optim::{LBFGS, LineSearch};
+/// use delta::tensor::Tensor;
+///
+/// // Rosenbrock: minimum at (1, 1)
+/// let start = vec![Tensor::from_vec(vec![-1.5], &[1]), Tensor::from_vec(vec![2.0], &[1])];
+/// let mut opt = LBFGS::new(start).line_search(LineSearch::StrongWolfe);
+/// for _ in 0..10 {
+///     opt.step(|p| {
+///         let (x, y) = (&p[0], &p[1]);
+///         let a = x.neg().scalar_add(1.0).powi(2);
+///         let b = y.sub(&x.powi(2)).powi(2).scalar_mul(100.0);
+///         a.add(&b).sum()
+///     });
+/// }
+/// assert!((opt.params()[0].get(&[0]) - 1.0).abs() < 1e-3);
+/// assert!((opt.params()[1].get(&[0]) - 1.0).abs() < 1e-3);
+/// ```
+#[derive(Debug)]
+pub struct LBFGS {
+    params: Vec<Tensor>,
+    lr: f32,
+    max_iter: usize,
+    max_eval: usize,
+    tolerance_grad: f32,
+    tolerance_change: f32,
+    history_size: usize,
+    line_search: LineSearch,
+    state: State,
+}
+
+/// What carries over between calls to `step`.
+#[derive(Debug, Default)]
+struct State {
+    /// Recent curvature pairs (s, y, 1 / y·s), oldest first, where s is a
+    /// parameter change and y the matching gradient change
+    history: VecDeque<(Vec<f32>, Vec<f32>, f32)>,
+    /// Scale of the initial inverse Hessian, y·s / y·y of the newest pair
+    gamma: f32,
+    /// Last search direction, step size and gradient
+    dir: Vec<f32>,
+    t: f32,
+    prev_grad: Vec<f32>,
+    iterations: usize,
+}
+
+impl LBFGS {
+    /// Create an optimizer over `params` with the defaults: learning rate
+    /// 1, 20 iterations per step, history of 100, fixed step size.
+    pub fn new(params: Vec<Tensor>) -> Self {
+        Self {
+            params,
+            lr: 1.0,
+            max_iter: 20,
+            max_eval: 25,
+            tolerance_grad: 1e-7,
+            tolerance_change: 1e-9,
+            history_size: 100,
+            line_search: LineSearch::Fixed,
+            state: State::default(),
+        }
+    }
+
+    /// Step size (the initial trial step when using a line search).
+    pub fn lr(mut self, lr: f32) -> Self {
+        self.lr = lr;
+        self
+    }
+
+    /// Maximum iterations per call to [`LBFGS::step`]. Also sets the
+    /// evaluation budget to 1.25x this.
+    pub fn max_iter(mut self, max_iter: usize) -> Self {
+        self.max_iter = max_iter;
+        self.max_eval = max_iter + max_iter / 4;
+        self
+    }
+
+    /// Number of curvature pairs kept for the Hessian approximation.
+    ///
+    /// # Panics
+    /// Panics if `history_size` is 0.
+    pub fn history_size(mut self, history_size: usize) -> Self {
+        assert!(history_size > 0, "history_size must be at least 1");
+        self.history_size = history_size;
+        self
+    }
+
+    /// Stop once every gradient component is at most this in magnitude.
+    pub fn tolerance_grad(mut self, tolerance: f32) -> Self {
+        self.tolerance_grad = tolerance;
+        self
+    }
+
+    /// Stop once the loss or the parameters change by less than this.
+    pub fn tolerance_change(mut self, tolerance: f32) -> Self {
+        self.tolerance_change = tolerance;
+        self
+    }
+
+    /// Step size strategy, see [`LineSearch`].
+    pub fn line_search(mut self, line_search: LineSearch) -> Self {
+        self.line_search = line_search;
+        self
+    }
+
+    /// The current parameter values.
+    pub fn params(&self) -> &[Tensor] {
+        &self.params
+    }
+
+    /// Run one optimization step and return the loss at its start.
+    ///
+    /// # Panics
+    /// Panics if the closure returns more than one element.
+    pub fn step(&mut self, mut closure: impl FnMut(&[Tensor]) -> Tensor) -> f32 {
+        let x = flatten(&self.params);
+        let (mut loss, mut grad) = self.evaluate(&mut closure, &x);
+        let orig_loss = loss;
+        if max_abs(&grad) <= self.tolerance_grad {
+            return orig_loss;
+        }
+
+        let mut evals = 1;
+        for iter in 1..=self.max_iter {
+            self.state.iterations += 1;
+            let dir = if self.state.iterations == 1 {
+                self.state.history.clear();
+                self.state.gamma = 1.0;
+                grad.iter().map(|g| -g).collect()
+            } else {
+                self.update_history(&grad);
+                self.direction(&grad)
+            };
+
+            let t = if self.state.iterations == 1 {
+                // Unit-free first step: the gradient's scale is unknown
+                let l1: f32 = grad.iter().map(|g| g.abs()).sum();
+                (1.0 / l1).min(1.0) * self.lr
+            } else {
+                self.lr
+            };
+
+            let gtd = dot(&grad, &dir);
+            if gtd > -self.tolerance_change {
+                // Not a descent direction (numerically)
+                break;
+            }
+
+            let prev_loss = loss;
+            let x = flatten(&self.params);
+            self.state.prev_grad = grad.clone();
+            let t = match self.line_search {
+                LineSearch::StrongWolfe => {
+                    let start = Trial {
+                        t: 0.0,
+                        loss,
+                        grad: grad.clone(),
+                        gtd,
+                    };
+                    let tolerance_change = self.tolerance_change;
+                    let eval = |t: f32| self.evaluate(&mut closure, &offset(&x, t, &dir));
+                    let (best, ls_evals) = strong_wolfe(eval, &dir, &start, t, tolerance_change);
+                    loss = best.loss;
+                    grad = best.grad;
+                    evals += ls_evals;
+                    best.t
+                }
+                LineSearch::Fixed => {
+                    if iter != self.max_iter {
+                        (loss, grad) = self.evaluate(&mut closure, &offset(&x, t, &dir));
+                        evals += 1;
+                    }
+                    t
+                }
+            };
+            unflatten(&mut self.params, &offset(&x, t, &dir));
+            self.state.dir = dir;
+            self.state.t = t;
+
+            if iter == self.max_iter || evals >= self.max_eval {
+                break;
+            }
+            if max_abs(&grad) <= self.tolerance_grad {
+                break;
+            }
+            if t * max_abs(&self.state.dir) <= self.tolerance_change {
+                break;
+            }
+            if (loss - prev_loss).abs() < self.tolerance_change {
+                break;
+            }
+        }
+
+        orig_loss
+    }
+
+    /// Loss and flat gradient with the parameters set to `x`.
+    fn evaluate(
+        &self,
+        closure: &mut impl FnMut(&[Tensor]) -> Tensor,
+        x: &[f32],
+    ) -> (f32, Vec<f32>) {
+        let mut params = self.params.clone();
+        unflatten(&mut params, x);
+        let (loss, grads) = value_and_grad(|p| closure(p), &params);
+        (loss.to_vec()[0], flatten(&grads))
+    }
+
+    /// Record the curvature pair from the last iteration, dropping the
+    /// oldest when the history is full. Pairs with y·s <= 0 would break
+    /// the positive-definiteness of the approximation and are skipped.
+    fn update_history(&mut self, grad: &[f32]) {
+        let state = &mut self.state;
+        let y: Vec<f32> = grad
+            .iter()
+            .zip(&state.prev_grad)
+            .map(|(g, p)| g - p)
+            .collect();
+        let s: Vec<f32> = state.dir.iter().map(|d| d * state.t).collect();
+        let ys = dot(&y, &s);
+        if ys > 1e-10 {
+            if state.history.len() == self.history_size {
+                state.history.pop_front();
+            }
+            state.gamma = ys / dot(&y, &y);
+            state.history.push_back((s, y, 1.0 / ys));
+        }
+    }
+
+    /// The two-loop recursion: -H g for the implicit inverse Hessian H.
+    fn direction(&self, grad: &[f32]) -> Vec<f32> {
+        let history = &self.state.history;
+        let mut q: Vec<f32> = grad.iter().map(|g| -g).collect();
+
+        let mut alphas = vec![0.0; history.len()];
+        for (i, (s, y, rho)) in history.iter().enumerate().rev() {
+            alphas[i] = rho * dot(s, &q);
+            axpy(&mut q, -alphas[i], y);
+        }
+
+        q.iter_mut().for_each(|v| *v *= self.state.gamma);
+        for ((s, y, rho), alpha) in history.iter().zip(alphas) {
+            let beta = rho * dot(y, &q);
+            axpy(&mut q, alpha - beta, s);
+        }
+        q
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rosenbrock(p: &[Tensor]) -> Tensor {
+        let (x, y) = (&p[0], &p[1]);
+        let a = x.neg().scalar_add(1.0).powi(2);
+        let b = y.sub(&x.powi(2)).powi(2).scalar_mul(100.0);
+        a.add(&b).sum()
+    }
+
+    fn start() -> Vec<Tensor> {
+        vec![
+            Tensor::from_vec(vec![-1.2], &[1]),
+            Tensor::from_vec(vec![1.0], &[1]),
+        ]
+    }
+
+    #[test]
+    fn test_rosenbrock_strong_wolfe() {
+        let mut opt = LBFGS::new(start()).line_search(LineSearch::StrongWolfe);
+        for _ in 0..5 {
+            opt.step(rosenbrock);
+        }
+        assert!((opt.params()[0].get(&[0]) - 1.0).abs() < 1e-3);
+        assert!((opt.params()[1].get(&[0]) - 1.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_quadratic_fixed_step() {
+        // Separable quadratic with very different curvatures, where plain
+        // gradient descent with lr = 1 would diverge along the steep axis
+        let scale = Tensor::from_vec(vec![1.0, 10.0, 0.1], &[3]);
+        let target = Tensor::from_vec(vec![3.0, -1.0, 2.0], &[3]);
+        let loss = |p: &[Tensor]| p[0].sub(&target).powi(2).mul(&scale).sum();
+
+        let mut opt = LBFGS::new(vec![Tensor::zeros(&[3])]).max_iter(50);
+        opt.step(loss);
+        for (x, t) in opt.params()[0].to_vec().iter().zip(target.to_vec()) {
+            assert!((x - t).abs() < 1e-3, "{} != {}", x, t);
+        }
+    }
+
+    #[test]
+    fn test_step_returns_initial_loss() {
+        let mut opt = LBFGS::new(start());
+        let first = opt.step(rosenbrock);
+        assert!((first - 24.2).abs() < 1e-4);
+        let second = opt.step(rosenbrock);
+        assert!(second < first);
+    }
+
+    #[test]
+    fn test_history_is_bounded() {
+        let mut opt = LBFGS::new(start())
+            .history_size(3)
+            .line_search(LineSearch::StrongWolfe);
+        opt.step(rosenbrock);
+        assert!(opt.state.history.len() <= 3);
+    }
+
+    #[test]
+    fn test_stops_at_optimum() {
+        let at_min = vec![
+            Tensor::from_vec(vec![1.0], &[1]),
+            Tensor::from_vec(vec![1.0], &[1]),
+        ];
+        let mut opt = LBFGS::new(at_min);
+        assert_eq!(opt.step(rosenbrock), 0.0);
+        assert_eq!(opt.state.iterations, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "single-element")]
+    fn test_non_scalar_loss() {
+        let mut opt = LBFGS::new(vec![Tensor::zeros(&[2])]);
+        opt.step(|p| p[0].scalar_add(1.0));
+    }
+}
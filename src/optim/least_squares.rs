@@ -0,0 +1,377 @@
+use crate::autograd::{GradModeGuard, gradients};
+use crate::optim::flat::{flatten, unflatten};
+use crate::tensor::Tensor;
+
+/// Outcome of a [`least_squares`] fit.
+#[derive(Debug, Clone)]
+pub struct LeastSquaresResult {
+    /// Fitted parameters, shaped like the initial ones.
+    pub params: Vec<Tensor>,
+    /// Final cost ½‖r‖².
+    pub cost: f32,
+    /// Iterations taken, counting rejected steps.
+    pub iterations: usize,
+    /// Whether a tolerance was met (rather than running out of iterations).
+    pub converged: bool,
+}
+
+/// Levenberg-Marquardt solver for nonlinear least squares.
+///
+/// Minimizes ½‖r(p)‖² for a residual function r. Each iteration linearizes
+/// r around the current parameters with its Jacobian J (computed by
+/// autograd, one backward pass per residual) and solves the damped normal
+/// equations
+///
+/// ```text
+///   (JᵀJ + λ diag(JᵀJ)) δ = -Jᵀr
+/// ```
+///
+/// Small λ gives the Gauss-Newton step, which converges fast near the
+/// optimum; large λ gives a short, scaled gradient step, which is safe far
+/// from it. λ shrinks after every step that lowers the cost and grows
+/// after every step that does not.
+///
+/// Meant for curve fitting and calibration: a few to a few hundred
+/// parameters and residuals. The normal equations are solved densely in
+/// f64.
+///
+/// # Example
+/// ```
+/// use delta::optim::LevenbergMarquardt;
+/// use delta::tensor::Tensor;
+///
+/// // Fit y = a * t + b through three points
+/// let t = Tensor::from_vec(vec![0.0, 1.0, 2.0], &[3]);
+/// let y = Tensor::from_vec(vec![1.0, 3.0, 5.0], &[3]);
+/// let init = vec![Tensor::zeros(&[1]), Tensor::zeros(&[1])];
+/// let fit = LevenbergMarquardt::new().max_iter(50).solve(
+///     |p| {
+///         let a = p[0].broadcast_to(&[3]);
+///         let b = p[1].broadcast_to(&[3]);
+///         a.mul(&t).add(&b).sub(&y)
+///     },
+///     init,
+/// );
+/// assert!(fit.converged);
+/// assert!((fit.params[0].get(&[0]) - 2.0).abs() < 1e-3);
+/// assert!((fit.params[1].get(&[0]) - 1.0).abs() < 1e-3);
+/// ```
+#[derive(Debug, Clone)]
+pub struct LevenbergMarquardt {
+    max_iter: usize,
+    ftol: f64,
+    xtol: f64,
+    gtol: f64,
+    damping: f64,
+}
+
+impl Default for LevenbergMarquardt {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LevenbergMarquardt {
+    /// A solver with the defaults: 100 iterations, tolerances of 1e-8 and
+    /// initial damping λ = 1e-3.
+    pub fn new() -> Self {
+        Self {
+            max_iter: 100,
+            ftol: 1e-8,
+            xtol: 1e-8,
+            gtol: 1e-8,
+            damping: 1e-3,
+        }
+    }
+
+    /// Maximum number of iterations.
+    pub fn max_iter(mut self, max_iter: usize) -> Self {
+        self.max_iter = max_iter;
+        self
+    }
+
+    /// Stop when an accepted step lowers the cost by less than this
+    /// fraction.
+    pub fn ftol(mut self, ftol: f64) -> Self {
+        self.ftol = ftol;
+        self
+    }
+
+    /// Stop when the step is shorter than this, relative to the
+    /// parameters.
+    pub fn xtol(mut self, xtol: f64) -> Self {
+        self.xtol = xtol;
+        self
+    }
+
+    /// Stop when every component of the gradient Jᵀr is at most this.
+    pub fn gtol(mut self, gtol: f64) -> Self {
+        self.gtol = gtol;
+        self
+    }
+
+    /// Initial damping λ.
+    pub fn damping(mut self, damping: f64) -> Self {
+        self.damping = damping;
+        self
+    }
+
+    /// Fit `params` so the residuals returned by `residuals` are as small
+    /// as possible in the least-squares sense.
+    ///
+    /// `residuals` may return a tensor of any shape; its elements are the
+    /// residuals.
+    pub fn solve(
+        &self,
+        residuals: impl Fn(&[Tensor]) -> Tensor,
+        params: Vec<Tensor>,
+    ) -> LeastSquaresResult {
+        let mut params = params;
+        let n = flatten(&params).len();
+        let (mut r, mut jac) = linearize(&residuals, &params);
+        let mut cost = half_squared_norm(&r);
+        let mut lambda = self.damping;
+        let mut converged = false;
+        let mut iterations = 0;
+
+        while iterations < self.max_iter {
+            iterations += 1;
+
+            // Normal equations: JᵀJ (n x n) and Jᵀr (n), from row-major J
+            let mut jtj = vec![0.0; n * n];
+            let mut jtr = vec![0.0; n];
+            for (row, ri) in jac.chunks(n.max(1)).zip(&r) {
+                for i in 0..n {
+                    jtr[i] += row[i] * ri;
+                    for j in 0..n {
+                        jtj[i * n + j] += row[i] * row[j];
+                    }
+                }
+            }
+            if jtr.iter().all(|g| g.abs() <= self.gtol) {
+                converged = true;
+                break;
+            }
+
+            let mut a = jtj.clone();
+            for i in 0..n {
+                // Marquardt's scaling makes the damping parameter-unit-free
+                a[i * n + i] += lambda * jtj[i * n + i].max(f64::EPSILON);
+            }
+            let rhs: Vec<f64> = jtr.iter().map(|g| -g).collect();
+            let Some(delta) = cholesky_solve(&a, &rhs, n) else {
+                lambda *= 10.0;
+                continue;
+            };
+
+            let x: Vec<f64> = flatten(&params).into_iter().map(f64::from).collect();
+            let x_norm = x.iter().map(|v| v * v).sum::<f64>().sqrt();
+            let step_norm = delta.iter().map(|v| v * v).sum::<f64>().sqrt();
+            if step_norm <= self.xtol * (x_norm + self.xtol) {
+                converged = true;
+                break;
+            }
+
+            let x_new: Vec<f32> = x.iter().zip(&delta).map(|(x, d)| (x + d) as f32).collect();
+            let mut candidate = params.clone();
+            unflatten(&mut candidate, &x_new);
+            let r_new: Vec<f64> = residuals(&candidate)
+                .to_vec()
+                .into_iter()
+                .map(f64::from)
+                .collect();
+            let cost_new = half_squared_norm(&r_new);
+
+            if cost_new < cost {
+                let reduction = cost - cost_new;
+                params = candidate;
+                (r, jac) = linearize(&residuals, &params);
+                lambda = (lambda / 10.0).max(1e-12);
+                let cost_old = cost;
+                cost = cost_new;
+                if reduction <= self.ftol * cost_old {
+                    converged = true;
+                    break;
+                }
+            } else {
+                lambda *= 10.0;
+            }
+        }
+
+        LeastSquaresResult {
+            params,
+            cost: cost as f32,
+            iterations,
+            converged,
+        }
+    }
+}
+
+/// Fit `params` with [`LevenbergMarquardt`] using the default settings.
+pub fn least_squares(
+    residuals: impl Fn(&[Tensor]) -> Tensor,
+    params: Vec<Tensor>,
+) -> LeastSquaresResult {
+    LevenbergMarquardt::new().solve(residuals, params)
+}
+
+fn half_squared_norm(r: &[f64]) -> f64 {
+    0.5 * r.iter().map(|v| v * v).sum::<f64>()
+}
+
+/// Residuals at `params` and their Jacobian, row-major (one row per
+/// residual, one column per parameter element).
+fn linearize(residuals: &impl Fn(&[Tensor]) -> Tensor, params: &[Tensor]) -> (Vec<f64>, Vec<f64>) {
+    let leaves: Vec<Tensor> = params
+        .iter()
+        .map(|p| p.detach().requires_grad(true))
+        .collect();
+    let out = {
+        let _guard = GradModeGuard::new(true);
+        residuals(&leaves)
+    };
+    let m = out.nelems();
+
+    let mut jac = Vec::new();
+    for i in 0..m {
+        let mut seed = vec![0.0; m];
+        seed[i] = 1.0;
+        let row = gradients(&out, Tensor::from_vec(seed, out.shape()), &leaves, false);
+        jac.extend(flatten(&row).into_iter().map(f64::from));
+    }
+
+    let r = out.to_vec().into_iter().map(f64::from).collect();
+    (r, jac)
+}
+
+/// Solve A x = b for symmetric positive-definite A (n x n, row-major).
+///
+/// Returns `None` if A is not positive definite.
+fn cholesky_solve(a: &[f64], b: &[f64], n: usize) -> Option<Vec<f64>> {
+    // A = L Lᵀ
+    let mut l = vec![0.0; n * n];
+    for i in 0..n {
+        for j in 0..=i {
+            let s: f64 = (0..j).map(|k| l[i * n + k] * l[j * n + k]).sum();
+            if i == j {
+                let d = a[i * n + i] - s;
+                if d <= 0.0 || !d.is_finite() {
+                    return None;
+                }
+                l[i * n + i] = d.sqrt();
+            } else {
+                l[i * n + j] = (a[i * n + j] - s) / l[j * n + j];
+            }
+        }
+    }
+
+    // Forward substitution L y = b, then back substitution Lᵀ x = y
+    let mut y = vec![0.0; n];
+    for i in 0..n {
+        let s: f64 = (0..i).map(|k| l[i * n + k] * y[k]).sum();
+        y[i] = (b[i] - s) / l[i * n + i];
+    }
+    let mut x = vec![0.0; n];
+    for i in (0..n).rev() {
+        let s: f64 = (i + 1..n).map(|k| l[k * n + i] * x[k]).sum();
+        x[i] = (y[i] - s) / l[i * n + i];
+    }
+    Some(x)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exponential_decay() {
+        // y = 2.5 e^(-0.7 t), sampled at t = 0, 0.5, ..., 5
+        let ts: Vec<f32> = (0..11).map(|i| i as f32 * 0.5).collect();
+        let ys: Vec<f32> = ts.iter().map(|t| 2.5 * (-0.7 * t).exp()).collect();
+        let (t, y) = (Tensor::from_vec(ts, &[11]), Tensor::from_vec(ys, &[11]));
+
+        let init = vec![
+            Tensor::from_vec(vec![1.0], &[1]),
+            Tensor::from_vec(vec![0.1], &[1]),
+        ];
+        let fit = least_squares(
+            |p| {
+                let rate = p[1].broadcast_to(&[11]);
+                let model = rate.mul(&t).neg().exp().mul(&p[0].broadcast_to(&[11]));
+                model.sub(&y)
+            },
+            init,
+        );
+
+        assert!(fit.converged);
+        assert!(fit.cost < 1e-8);
+        assert!((fit.params[0].get(&[0]) - 2.5).abs() < 1e-3);
+        assert!((fit.params[1].get(&[0]) - 0.7).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_rosenbrock_residuals() {
+        // r = [10 (y - x²), 1 - x]: minimum at (1, 1) from a start where
+        // the undamped Gauss-Newton step overshoots badly
+        let e0 = Tensor::from_vec(vec![1.0, 0.0], &[2]);
+        let e1 = Tensor::from_vec(vec![0.0, 1.0], &[2]);
+        let init = vec![Tensor::from_vec(vec![-1.2, 1.0], &[2])];
+        let fit = least_squares(
+            |p| {
+                let x = p[0].mul(&e0).sum().reshape(&[1]);
+                let y = p[0].mul(&e1).sum().reshape(&[1]);
+                let r0 = y.sub(&x.powi(2)).scalar_mul(10.0);
+                let r1 = x.neg().scalar_add(1.0);
+                r0.broadcast_to(&[2])
+                    .mul(&e0)
+                    .add(&r1.broadcast_to(&[2]).mul(&e1))
+            },
+            init,
+        );
+
+        assert!(fit.converged);
+        let p = fit.params[0].to_vec();
+        assert!(
+            (p[0] - 1.0).abs() < 1e-3 && (p[1] - 1.0).abs() < 1e-3,
+            "{:?}",
+            p
+        );
+    }
+
+    #[test]
+    fn test_already_optimal() {
+        let target = Tensor::from_vec(vec![1.0, 2.0], &[2]);
+        let fit = least_squares(|p| p[0].sub(&target), vec![target.clone()]);
+        assert!(fit.converged);
+        assert_eq!(fit.iterations, 1);
+        assert_eq!(fit.cost, 0.0);
+        assert_eq!(fit.params[0].to_vec(), vec![1.0, 2.0]);
+    }
+
+    #[test]
+    fn test_max_iter() {
+        let fit = LevenbergMarquardt::new()
+            .max_iter(1)
+            .solve(|p| p[0].exp(), vec![Tensor::from_vec(vec![1.0], &[1])]);
+        assert_eq!(fit.iterations, 1);
+        assert!(!fit.converged);
+    }
+
+    #[test]
+    fn test_inside_no_grad() {
+        // The Jacobian is still recorded
+        let target = Tensor::from_vec(vec![3.0], &[1]);
+        let fit = crate::autograd::no_grad(|| {
+            least_squares(|p| p[0].sub(&target), vec![Tensor::zeros(&[1])])
+        });
+        assert!((fit.params[0].get(&[0]) - 3.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_cholesky_solve() {
+        // [[4, 2], [2, 3]] x = [2, 1] -> x = [0.5, 0]
+        let x = cholesky_solve(&[4.0, 2.0, 2.0, 3.0], &[2.0, 1.0], 2).unwrap();
+        assert!((x[0] - 0.5).abs() < 1e-12 && x[1].abs() < 1e-12);
+        assert!(cholesky_solve(&[1.0, 2.0, 2.0, 1.0], &[1.0, 1.0], 2).is_none());
+    }
+}
@@ -0,0 +1,189 @@
+//! Line search along a fixed direction, on flattened parameter vectors.
+
+use crate::optim::flat::{dot, max_abs};
+
+/// Sufficient decrease (Armijo) constant.
+const C1: f32 = 1e-4;
+/// Curvature constant. 0.9 is the usual choice for quasi-Newton methods.
+const C2: f32 = 0.9;
+const MAX_EVALS: usize = 25;
+
+/// A trial point of the line search: step size, loss, gradient and the
+/// directional derivative `gradient · d`.
+#[derive(Clone)]
+pub(super) struct Trial {
+    pub(super) t: f32,
+    pub(super) loss: f32,
+    pub(super) grad: Vec<f32>,
+    pub(super) gtd: f32,
+}
+
+/// Minimizer of the cubic through two points with known values and slopes,
+/// clamped to `bounds` (bisection if the cubic has no real minimizer).
+fn cubic_interpolate(a: &Trial, b: &Trial, bounds: (f32, f32)) -> f32 {
+    let (lo, hi) = bounds;
+    let d1 = a.gtd + b.gtd - 3.0 * (a.loss - b.loss) / (a.t - b.t);
+    let d2_square = d1 * d1 - a.gtd * b.gtd;
+    if d2_square < 0.0 {
+        return (lo + hi) / 2.0;
+    }
+
+    let d2 = d2_square.sqrt();
+    let min_pos = if a.t <= b.t {
+        b.t - (b.t - a.t) * ((b.gtd + d2 - d1) / (b.gtd - a.gtd + 2.0 * d2))
+    } else {
+        a.t - (a.t - b.t) * ((a.gtd + d2 - d1) / (a.gtd - b.gtd + 2.0 * d2))
+    };
+    min_pos.clamp(lo, hi)
+}
+
+/// Find a step size satisfying the strong Wolfe conditions:
+///
+/// ```text
+///   f(t) <= f(0) + C1 t f'(0)       (sufficient decrease)
+///   |f'(t)| <= C2 |f'(0)|           (curvature)
+/// ```
+///
+/// `eval(t)` returns the loss and gradient at `x + t d`; `start` is the
+/// trial at t = 0 and `t` the first step to try. Follows Nocedal & Wright,
+/// Algorithm 3.5/3.6: grow the step until a bracket containing an
+/// acceptable point is found, then shrink the bracket by cubic
+/// interpolation. Returns the best trial and the number of evaluations.
+pub(super) fn strong_wolfe(
+    mut eval: impl FnMut(f32) -> (f32, Vec<f32>),
+    d: &[f32],
+    start: &Trial,
+    t: f32,
+    tolerance_change: f32,
+) -> (Trial, usize) {
+    let trial = |t: f32, eval: &mut dyn FnMut(f32) -> (f32, Vec<f32>)| {
+        let (loss, grad) = eval(t);
+        let gtd = dot(&grad, d);
+        Trial { t, loss, grad, gtd }
+    };
+    let armijo = |p: &Trial| p.loss <= start.loss + C1 * p.t * start.gtd;
+    let curvature = |p: &Trial| p.gtd.abs() <= -C2 * start.gtd;
+    let d_norm = max_abs(d);
+
+    // Bracketing phase
+    let mut prev = start.clone();
+    let mut cur = trial(t, &mut eval);
+    let mut evals = 1;
+    let mut bracket = loop {
+        if !armijo(&cur) || (evals > 1 && cur.loss >= prev.loss) {
+            break [prev, cur];
+        }
+        if curvature(&cur) {
+            return (cur, evals);
+        }
+        if cur.gtd >= 0.0 {
+            break [prev, cur];
+        }
+        if evals == MAX_EVALS {
+            // Out of budget: settle for the better of the start and here
+            break [start.clone(), cur];
+        }
+
+        // Still descending: extrapolate to a longer step
+        let bounds = (cur.t + 0.01 * (cur.t - prev.t), cur.t * 10.0);
+        let next = cubic_interpolate(&prev, &cur, bounds);
+        prev = cur;
+        cur = trial(next, &mut eval);
+        evals += 1;
+    };
+
+    // Zoom phase: `low` is the endpoint with the lower loss
+    let mut low = if bracket[0].loss <= bracket[1].loss {
+        0
+    } else {
+        1
+    };
+    let mut insufficient_progress = false;
+    while evals < MAX_EVALS {
+        let (lo, hi) = (
+            bracket[0].t.min(bracket[1].t),
+            bracket[0].t.max(bracket[1].t),
+        );
+        if (hi - lo) * d_norm < tolerance_change {
+            break;
+        }
+
+        let mut t = cubic_interpolate(&bracket[0], &bracket[1], (lo, hi));
+        // Don't let the trial crowd one end of the bracket, or the bracket
+        // stops shrinking
+        let eps = 0.1 * (hi - lo);
+        if (hi - t).min(t - lo) < eps {
+            if insufficient_progress || t >= hi || t <= lo {
+                t = if (t - hi).abs() < (t - lo).abs() {
+                    hi - eps
+                } else {
+                    lo + eps
+                };
+                insufficient_progress = false;
+            } else {
+                insufficient_progress = true;
+            }
+        } else {
+            insufficient_progress = false;
+        }
+
+        let new = trial(t, &mut eval);
+        evals += 1;
+        let high = 1 - low;
+        if !armijo(&new) || new.loss >= bracket[low].loss {
+            bracket[high] = new;
+            low = if bracket[0].loss <= bracket[1].loss {
+                0
+            } else {
+                1
+            };
+        } else {
+            if curvature(&new) {
+                return (new, evals);
+            }
+            if new.gtd * (bracket[high].t - bracket[low].t) >= 0.0 {
+                bracket[high] = bracket[low].clone();
+            }
+            bracket[low] = new;
+        }
+    }
+
+    let [a, b] = bracket;
+    (if low == 0 { a } else { b }, evals)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cubic_interpolate_exact_for_quadratic() {
+        // f(t) = (t - 2)², f'(t) = 2(t - 2)
+        let point = |t: f32| Trial {
+            t,
+            loss: (t - 2.0) * (t - 2.0),
+            grad: vec![],
+            gtd: 2.0 * (t - 2.0),
+        };
+        let t = cubic_interpolate(&point(0.0), &point(5.0), (0.0, 5.0));
+        assert!((t - 2.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_strong_wolfe_on_quadratic() {
+        // f(x) = (x - 3)² from x = 0 along d = 1
+        let f = |x: f32| ((x - 3.0) * (x - 3.0), vec![2.0 * (x - 3.0)]);
+        let d = [1.0];
+        let (loss, grad) = f(0.0);
+        let start = Trial {
+            t: 0.0,
+            loss,
+            gtd: dot(&grad, &d),
+            grad,
+        };
+        let (best, evals) = strong_wolfe(f, &d, &start, 1.0, 1e-9);
+        assert!(best.loss <= start.loss + C1 * best.t * start.gtd);
+        assert!(best.gtd.abs() <= -C2 * start.gtd);
+        assert!(evals <= MAX_EVALS);
+    }
+}
@@ -0,0 +1,9 @@
+//! Optimizers that update parameters from their gradients.
+
+mod flat;
+mod lbfgs;
+mod least_squares;
+mod line_search;
+
+pub use lbfgs::{LBFGS, LineSearch};
+pub use least_squares::{LeastSquaresResult, LevenbergMarquardt, least_squares};
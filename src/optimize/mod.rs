@@ -0,0 +1,13 @@
+//! Root finding and minimization of functions of one or a few variables.
+//!
+//! [`bisect`] and [`golden_section`] work on plain `f32` functions and
+//! need only their values, inside a bracketing interval. [`newton`] solves
+//! square systems `f(x) = 0` over a tensor of unknowns, with the Jacobian
+//! computed by autograd, and converges much faster from a good starting
+//! point.
+
+mod newton;
+mod scalar;
+
+pub use newton::{NewtonResult, newton};
+pub use scalar::{bisect, golden_section};
@@ -0,0 +1,226 @@
+use crate::autograd::{GradModeGuard, gradients};
+use crate::tensor::Tensor;
+
+/// Outcome of a [`newton`] solve.
+#[derive(Debug, Clone)]
+pub struct NewtonResult {
+    /// The last iterate, shaped like the starting point.
+    pub x: Tensor,
+    /// Largest absolute value of `f(x)` at the last iterate.
+    pub residual: f32,
+    /// Newton steps taken.
+    pub iterations: usize,
+    /// Whether the residual fell to the tolerance (rather than running out
+    /// of iterations or hitting a singular Jacobian).
+    pub converged: bool,
+}
+
+/// Solve `f(x) = 0` by Newton's method, starting from `x0`.
+///
+/// `f` maps a tensor of n unknowns to a tensor of n equations (the shapes
+/// may differ, the element counts may not). Each step evaluates `f` and its
+/// Jacobian J, by autograd with one backward pass per equation, and moves
+/// to `x - J⁻¹ f(x)`, solving the dense linear system in f64. Stops once
+/// every `|fᵢ(x)|` is at most `tol`, after `max_iter` steps, or when J is
+/// singular.
+///
+/// Convergence is quadratic near a simple root but not guaranteed from far
+/// away; for a single unknown with a known bracket, [`bisect`] is the safe
+/// choice. To minimize a smooth function, pass its gradient as `f`.
+///
+/// [`bisect`]: crate::optimize::bisect
+///
+/// # Panics
+/// Panics if `f(x0)` does not have as many elements as `x0`.
+///
+/// # Example
+/// ```
+/// use delta::optimize::newton;
+/// use delta::tensor::Tensor;
+///
+/// // A v + v³ = b, a mildly nonlinear system
+/// let a = Tensor::from_vec(vec![4.0, 1.0, 1.0, 3.0], &[2, 2]);
+/// let b = Tensor::from_vec(vec![1.0, 2.0], &[2]);
+/// let f = |v: &Tensor| a.matmul(&v.reshape(&[2, 1])).reshape(&[2]).add(&v.powi(3)).sub(&b);
+///
+/// let sol = newton(f, &Tensor::zeros(&[2]), 1e-6, 20);
+/// assert!(sol.converged);
+/// assert!(f(&sol.x).to_vec().iter().all(|r| r.abs() <= 1e-6));
+/// ```
+pub fn newton(
+    f: impl Fn(&Tensor) -> Tensor,
+    x0: &Tensor,
+    tol: f32,
+    max_iter: usize,
+) -> NewtonResult {
+    let n = x0.nelems();
+    let mut x = x0.detach();
+    let mut iterations = 0;
+    loop {
+        let (fx, jac) = linearize(&f, &x);
+        assert_eq!(
+            fx.len(),
+            n,
+            "newton expects as many equations as unknowns, got {} for {}",
+            fx.len(),
+            n
+        );
+        let residual = fx.iter().fold(0.0f32, |m, v| m.max(v.abs() as f32));
+        if residual <= tol || iterations == max_iter {
+            return NewtonResult {
+                x,
+                residual,
+                iterations,
+                converged: residual <= tol,
+            };
+        }
+        let Some(delta) = lu_solve(jac, fx, n) else {
+            return NewtonResult {
+                x,
+                residual,
+                iterations,
+                converged: false,
+            };
+        };
+
+        let values = x
+            .to_vec()
+            .iter()
+            .zip(delta)
+            .map(|(&xi, d)| (f64::from(xi) - d) as f32)
+            .collect();
+        x = Tensor::from_vec(values, x.shape());
+        iterations += 1;
+    }
+}
+
+/// `f(x)` and its Jacobian, row-major (one row per equation).
+fn linearize(f: &impl Fn(&Tensor) -> Tensor, x: &Tensor) -> (Vec<f64>, Vec<f64>) {
+    let leaf = x.detach().requires_grad(true);
+    let out = {
+        let _guard = GradModeGuard::new(true);
+        f(&leaf)
+    };
+    let m = out.nelems();
+
+    let mut jac = Vec::with_capacity(m * x.nelems());
+    for i in 0..m {
+        let mut seed = vec![0.0; m];
+        seed[i] = 1.0;
+        let seed = Tensor::from_vec(seed, out.shape());
+        let row = gradients(&out, seed, std::slice::from_ref(&leaf), false);
+        jac.extend(row[0].to_vec().into_iter().map(f64::from));
+    }
+
+    let fx = out.to_vec().into_iter().map(f64::from).collect();
+    (fx, jac)
+}
+
+/// Solve A x = b (n x n, row-major) by Gaussian elimination with partial
+/// pivoting.
+///
+/// Returns `None` if A is singular.
+fn lu_solve(mut a: Vec<f64>, mut b: Vec<f64>, n: usize) -> Option<Vec<f64>> {
+    for col in 0..n {
+        let pivot =
+            (col..n).max_by(|&i, &j| a[i * n + col].abs().total_cmp(&a[j * n + col].abs()))?;
+        let p = a[pivot * n + col];
+        if p == 0.0 || !p.is_finite() {
+            return None;
+        }
+        if pivot != col {
+            for k in 0..n {
+                a.swap(pivot * n + k, col * n + k);
+            }
+            b.swap(pivot, col);
+        }
+        for row in col + 1..n {
+            let factor = a[row * n + col] / p;
+            for k in col..n {
+                a[row * n + k] -= factor * a[col * n + k];
+            }
+            b[row] -= factor * b[col];
+        }
+    }
+
+    let mut x = vec![0.0; n];
+    for i in (0..n).rev() {
+        let s: f64 = (i + 1..n).map(|k| a[i * n + k] * x[k]).sum();
+        x[i] = (b[i] - s) / a[i * n + i];
+    }
+    Some(x)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::autograd::grad;
+
+    #[test]
+    fn test_scalar_root() {
+        // x³ = 2 from x = 1, as a 0-d tensor
+        let sol = newton(
+            |x| x.powi(3).scalar_add(-2.0),
+            &Tensor::from_vec(vec![1.0], &[]),
+            1e-6,
+            20,
+        );
+        assert!(sol.converged);
+        assert!(sol.iterations < 10);
+        assert!((sol.x.get(&[]) - 2f32.cbrt()).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_minimize_through_gradient() {
+        // Minimize the Rosenbrock function by solving ∇f = 0, with the
+        // gradient itself from autograd
+        let pick = |v: &Tensor, i: usize| {
+            let mut mask = vec![0.0; 2];
+            mask[i] = 1.0;
+            v.mul(&Tensor::from_vec(mask, &[2])).sum()
+        };
+        let rosenbrock = |v: &Tensor| {
+            let (x, y) = (pick(v, 0), pick(v, 1));
+            let a = x.scalar_add(-1.0).powi(2);
+            a.add(&y.sub(&x.powi(2)).powi(2).scalar_mul(100.0))
+        };
+        let sol = newton(
+            |v| grad(&rosenbrock(v), std::slice::from_ref(v), true).remove(0),
+            &Tensor::from_vec(vec![0.8, 0.6], &[2]),
+            1e-4,
+            50,
+        );
+        assert!(sol.converged, "{:?}", sol);
+        for v in sol.x.to_vec() {
+            assert!((v - 1.0).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn test_singular_jacobian_stops() {
+        // f'(0) = 0, so the first step cannot be taken
+        let sol = newton(
+            |x| x.powi(2).scalar_add(1.0),
+            &Tensor::from_vec(vec![0.0], &[1]),
+            1e-6,
+            20,
+        );
+        assert!(!sol.converged);
+        assert_eq!(sol.iterations, 0);
+        assert_eq!(sol.residual, 1.0);
+    }
+
+    #[test]
+    fn test_lu_solve_pivots() {
+        // The leading zero needs a row swap
+        let x = lu_solve(vec![0.0, 2.0, 1.0, 1.0], vec![4.0, 3.0], 2).unwrap();
+        assert_eq!(x, vec![1.0, 2.0]);
+        assert!(lu_solve(vec![1.0, 2.0, 2.0, 4.0], vec![1.0, 1.0], 2).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "as many equations as unknowns")]
+    fn test_non_square_system() {
+        newton(|x| x.sum(), &Tensor::zeros(&[2]), 1e-6, 5);
+    }
+}
@@ -0,0 +1,142 @@
+/// A root of `f` in `[a, b]` by bisection, to within `tol`.
+///
+/// `f` must be continuous and change sign over the interval; the interval
+/// is halved, keeping the sign change, until it is no wider than `tol` (or
+/// f32 cannot split it any further). Slow but certain: one evaluation of
+/// `f` per halving, and no derivatives.
+///
+/// # Panics
+/// - Panics if `tol` is not positive
+/// - Panics if `f(a)` and `f(b)` have the same sign
+///
+/// # Example
+/// ```
+/// use delta::optimize::bisect;
+/// let root = bisect(|x| x * x - 2.0, 0.0, 2.0, 1e-6);
+/// assert!((root - 2f32.sqrt()).abs() < 1e-6);
+/// ```
+pub fn bisect(f: impl Fn(f32) -> f32, a: f32, b: f32, tol: f32) -> f32 {
+    assert!(
+        tol > 0.0,
+        "bisect requires a positive tolerance, got {}",
+        tol
+    );
+    let (mut lo, mut hi) = (a.min(b), a.max(b));
+    let (f_lo, f_hi) = (f(lo), f(hi));
+    if f_lo == 0.0 {
+        return lo;
+    }
+    if f_hi == 0.0 {
+        return hi;
+    }
+    assert!(
+        (f_lo < 0.0) != (f_hi < 0.0),
+        "bisect requires a sign change over [{}, {}], got f = {} and {}",
+        lo,
+        hi,
+        f_lo,
+        f_hi
+    );
+
+    let lo_negative = f_lo < 0.0;
+    loop {
+        let mid = lo + (hi - lo) / 2.0;
+        if hi - lo <= tol || mid == lo || mid == hi {
+            return mid;
+        }
+        let f_mid = f(mid);
+        if f_mid == 0.0 {
+            return mid;
+        }
+        if (f_mid < 0.0) == lo_negative {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+    }
+}
+
+/// 1 / φ, the fraction of the interval kept by each golden-section step.
+const INV_PHI: f32 = 0.618_034;
+
+/// A minimizer of `f` over `[a, b]` by golden-section search, to within
+/// `tol`.
+///
+/// `f` should be unimodal on the interval (decreasing, then increasing);
+/// otherwise the result is a local minimum, or an endpoint. Each step
+/// shrinks the interval by the golden ratio and costs one evaluation.
+///
+/// # Panics
+/// Panics if `tol` is not positive.
+///
+/// # Example
+/// ```
+/// use delta::optimize::golden_section;
+/// let x = golden_section(|x| (x - 1.5).powi(2), -4.0, 4.0, 1e-5);
+/// assert!((x - 1.5).abs() < 1e-4);
+/// ```
+pub fn golden_section(f: impl Fn(f32) -> f32, a: f32, b: f32, tol: f32) -> f32 {
+    assert!(
+        tol > 0.0,
+        "golden_section requires a positive tolerance, got {}",
+        tol
+    );
+    let (mut lo, mut hi) = (a.min(b), a.max(b));
+    // Interior points at the golden ratios, lo < x1 < x2 < hi
+    let mut x1 = hi - INV_PHI * (hi - lo);
+    let mut x2 = lo + INV_PHI * (hi - lo);
+    let (mut f1, mut f2) = (f(x1), f(x2));
+    while hi - lo > tol && lo < x1 && x1 < x2 && x2 < hi {
+        if f1 < f2 {
+            // The minimum is in [lo, x2]; the old x1 becomes the new x2
+            hi = x2;
+            x2 = x1;
+            f2 = f1;
+            x1 = hi - INV_PHI * (hi - lo);
+            f1 = f(x1);
+        } else {
+            lo = x1;
+            x1 = x2;
+            f1 = f2;
+            x2 = lo + INV_PHI * (hi - lo);
+            f2 = f(x2);
+        }
+    }
+    lo + (hi - lo) / 2.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bisect() {
+        let root = bisect(|x| x.cos() - x, 0.0, 1.0, 1e-6);
+        assert!((root.cos() - root).abs() < 1e-5);
+        // Decreasing functions and reversed bounds work too
+        let root = bisect(|x| 3.0 - x, 10.0, -10.0, 1e-5);
+        assert!((root - 3.0).abs() < 1e-5);
+        assert_eq!(bisect(|x| x, 0.0, 1.0, 1e-3), 0.0);
+    }
+
+    #[test]
+    fn test_bisect_tiny_tolerance_terminates() {
+        let root = bisect(|x| x - 1.0 / 3.0, 0.0, 1.0, f32::MIN_POSITIVE);
+        assert!((root - 1.0 / 3.0).abs() < 1e-7);
+    }
+
+    #[test]
+    #[should_panic(expected = "sign change")]
+    fn test_bisect_no_sign_change() {
+        bisect(|x| x * x + 1.0, -1.0, 1.0, 1e-6);
+    }
+
+    #[test]
+    fn test_golden_section() {
+        let x = golden_section(|x| x.cosh(), -1.0, 3.0, 1e-5);
+        assert!(x.abs() < 1e-3);
+        // Monotonic: the minimum is at an endpoint
+        let x = golden_section(|x| x, 2.0, 5.0, 1e-5);
+        assert!((x - 2.0).abs() < 1e-4);
+    }
+}
@@ -0,0 +1,146 @@
+//! Seeded pseudo-random numbers.
+//!
+//! Everything random in the crate takes an explicit [`Rng`], so a run is
+//! reproducible from its seed and no global state is involved.
+
+use std::f32::consts::TAU;
+
+use crate::tensor::Tensor;
+
+/// A small, fast pseudo-random generator (xoshiro256\*\*).
+///
+/// Statistically solid for simulation and initialization, but not
+/// cryptographically secure.
+///
+/// # Example
+/// ```
+/// use delta::random::Rng;
+/// let mut a = Rng::new(42);
+/// let mut b = Rng::new(42);
+/// assert_eq!(a.next_u64(), b.next_u64());
+/// let u = a.uniform();
+/// assert!((0.0..1.0).contains(&u));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: [u64; 4],
+}
+
+impl Rng {
+    /// A generator seeded with `seed`. Equal seeds give equal streams.
+    pub fn new(seed: u64) -> Self {
+        // Expand the seed with SplitMix64, as the xoshiro authors
+        // recommend: it never produces the all-zero state
+        let mut x = seed;
+        let mut next = || {
+            x = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+            let mut z = x;
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+            z ^ (z >> 31)
+        };
+        Self {
+            state: [next(), next(), next(), next()],
+        }
+    }
+
+    /// The next 64 random bits.
+    pub fn next_u64(&mut self) -> u64 {
+        let s = &mut self.state;
+        let result = s[1].wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+        let t = s[1] << 17;
+        s[2] ^= s[0];
+        s[3] ^= s[1];
+        s[1] ^= s[2];
+        s[0] ^= s[3];
+        s[2] ^= t;
+        s[3] = s[3].rotate_left(45);
+        result
+    }
+
+    /// Uniform in [0, 1).
+    pub fn uniform(&mut self) -> f32 {
+        // The top 24 bits fill an f32 mantissa exactly
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    /// Standard normal (mean 0, variance 1), by the Box-Muller transform.
+    pub fn normal(&mut self) -> f32 {
+        // 1 - u is in (0, 1], so the log is finite
+        let u1 = 1.0 - self.uniform();
+        let u2 = self.uniform();
+        (-2.0 * u1.ln()).sqrt() * (TAU * u2).cos()
+    }
+
+    /// Uniform integer in `0..n`.
+    ///
+    /// # Panics
+    /// Panics if `n` is 0.
+    pub fn below(&mut self, n: usize) -> usize {
+        assert!(n > 0, "below requires n > 0");
+        // Multiply-shift: maps 64 random bits onto 0..n with negligible bias
+        ((self.next_u64() as u128 * n as u128) >> 64) as usize
+    }
+}
+
+impl Tensor {
+    /// A tensor of uniform samples in [0, 1).
+    pub fn rand(shape: &[usize], rng: &mut Rng) -> Tensor {
+        let n = shape.iter().product();
+        Tensor::from_vec((0..n).map(|_| rng.uniform()).collect(), shape)
+    }
+
+    /// A tensor of standard normal samples.
+    pub fn randn(shape: &[usize], rng: &mut Rng) -> Tensor {
+        let n = shape.iter().product();
+        Tensor::from_vec((0..n).map(|_| rng.normal()).collect(), shape)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reproducible() {
+        let (mut a, mut b) = (Rng::new(7), Rng::new(7));
+        for _ in 0..10 {
+            assert_eq!(a.next_u64(), b.next_u64());
+        }
+        assert_ne!(Rng::new(1).next_u64(), Rng::new(2).next_u64());
+    }
+
+    #[test]
+    fn test_uniform_moments() {
+        let mut rng = Rng::new(0);
+        let xs: Vec<f32> = (0..20_000).map(|_| rng.uniform()).collect();
+        assert!(xs.iter().all(|x| (0.0..1.0).contains(x)));
+        let mean = xs.iter().sum::<f32>() / xs.len() as f32;
+        assert!((mean - 0.5).abs() < 0.01, "{}", mean);
+    }
+
+    #[test]
+    fn test_normal_moments() {
+        let t = Tensor::randn(&[20_000], &mut Rng::new(1));
+        let xs = t.to_vec();
+        let n = xs.len() as f32;
+        let mean = xs.iter().sum::<f32>() / n;
+        let var = xs.iter().map(|x| (x - mean).powi(2)).sum::<f32>() / n;
+        assert!(mean.abs() < 0.03, "{}", mean);
+        assert!((var - 1.0).abs() < 0.03, "{}", var);
+    }
+
+    #[test]
+    fn test_below() {
+        let mut rng = Rng::new(3);
+        let mut counts = [0; 3];
+        for _ in 0..3000 {
+            counts[rng.below(3)] += 1;
+        }
+        assert!(
+            counts.iter().all(|&c| (900..1100).contains(&c)),
+            "{:?}",
+            counts
+        );
+    }
+}
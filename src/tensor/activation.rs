@@ -0,0 +1,347 @@
+use crate::autograd::record;
+use crate::tensor::Tensor;
+use crate::tensor::reduce::logsumexp_lane;
+
+/// Logistic sigmoid of a single value, evaluated without overflow.
+///
+/// For very negative x, e^-x overflows f32, so that branch uses the
+/// equivalent form e^x / (1 + e^x) instead.
+fn sigmoid_scalar(x: f32) -> f32 {
+    if x >= 0.0 {
+        1.0 / (1.0 + (-x).exp())
+    } else {
+        let e = x.exp();
+        e / (1.0 + e)
+    }
+}
+
+impl Tensor {
+    /// Rectified linear unit: max(0, x)
+    pub fn relu(&self) -> Tensor {
+        let out = self.unary_op(|x| x.max(0.0));
+        let x = self.clone();
+        record(out, "relu", &[self], move |g| {
+            vec![g.mul(&x.gt_scalar(0.0))]
+        })
+    }
+
+    /// Leaky ReLU: x if x > 0, otherwise negative_slope * x
+    ///
+    /// # Example
+    /// ```
+    /// use delta::tensor::Tensor;
+    /// let t = Tensor::from_vec(vec![-2.0, 3.0], &[2]);
+    /// assert_eq!(t.leaky_relu(0.1).to_vec(), vec![-0.2, 3.0]);
+    /// ```
+    pub fn leaky_relu(&self, negative_slope: f32) -> Tensor {
+        let out = self.unary_op(|x| if x > 0.0 { x } else { negative_slope * x });
+        let x = self.clone();
+        record(out, "leaky_relu", &[self], move |g| {
+            let slope = x.unary_op(|v| if v > 0.0 { 1.0 } else { negative_slope });
+            vec![g.mul(&slope)]
+        })
+    }
+
+    /// Logistic sigmoid: 1 / (1 + e^-x), in (0, 1)
+    ///
+    /// Stable for large |x| in either direction.
+    pub fn sigmoid(&self) -> Tensor {
+        let out = self.unary_op(sigmoid_scalar);
+        let x = self.clone();
+        record(out, "sigmoid", &[self], move |g| {
+            // σ' = σ(1 - σ)
+            let s = x.sigmoid();
+            vec![g.mul(&s.mul(&s.neg().scalar_add(1.0)))]
+        })
+    }
+
+    /// Sigmoid linear unit (a.k.a. swish): x * sigmoid(x)
+    pub fn silu(&self) -> Tensor {
+        let out = self.unary_op(|x| x * sigmoid_scalar(x));
+        let x = self.clone();
+        record(out, "silu", &[self], move |g| {
+            // d/dx xσ(x) = σ(1 + x(1 - σ))
+            let s = x.sigmoid();
+            let d = s.mul(&x.mul(&s.neg().scalar_add(1.0)).scalar_add(1.0));
+            vec![g.mul(&d)]
+        })
+    }
+
+    /// Gaussian error linear unit, tanh approximation.
+    ///
+    /// ```text
+    ///   gelu(x) ≈ 0.5 * x * (1 + tanh(√(2/π) * (x + 0.044715 * x³)))
+    /// ```
+    ///
+    /// This is the approximation used by GPT-2/BERT. It stays within ~1e-3
+    /// of the exact x * Φ(x).
+    pub fn gelu(&self) -> Tensor {
+        // √(2/π)
+        const SQRT_2_OVER_PI: f32 = 0.797_884_6;
+        const COEFF: f32 = 0.044_715;
+        let out = self.unary_op(|x| {
+            let inner = SQRT_2_OVER_PI * (x + COEFF * x * x * x);
+            0.5 * x * (1.0 + inner.tanh())
+        });
+
+        let x = self.clone();
+        record(out, "gelu", &[self], move |g| {
+            // With u = √(2/π)(x + COEFF x³) and t = tanh(u):
+            //   gelu' = 0.5(1 + t) + 0.5 x (1 - t²) u'
+            let t = x
+                .add(&x.powi(3).scalar_mul(COEFF))
+                .scalar_mul(SQRT_2_OVER_PI)
+                .tanh();
+            let du = x
+                .powi(2)
+                .scalar_mul(3.0 * COEFF)
+                .scalar_add(1.0)
+                .scalar_mul(SQRT_2_OVER_PI);
+            let sech2 = t.powi(2).neg().scalar_add(1.0);
+            let d = t
+                .scalar_add(1.0)
+                .add(&x.mul(&sech2).mul(&du))
+                .scalar_mul(0.5);
+            vec![g.mul(&d)]
+        })
+    }
+
+    /// Softplus: ln(1 + e^x), a smooth approximation of ReLU.
+    ///
+    /// Evaluated as `max(x, 0) + ln(1 + e^-|x|)`, which neither overflows for
+    /// large x nor loses precision to cancellation for very negative x.
+    pub fn softplus(&self) -> Tensor {
+        let out = self.unary_op(|x| x.max(0.0) + (-x.abs()).exp().ln_1p());
+        let x = self.clone();
+        record(out, "softplus", &[self], move |g| vec![g.mul(&x.sigmoid())])
+    }
+
+    /// Softmax along a dimension: exp(x_i) / Σ exp(x_j)
+    ///
+    /// Each lane along `dim` is turned into a probability distribution
+    /// (non-negative, sums to 1). The lane maximum is subtracted before
+    /// exponentiating, which leaves the result unchanged but keeps
+    /// exp() from overflowing on large logits.
+    ///
+    /// # Panics
+    /// Panics if `dim` is out of range.
+    ///
+    /// # Example
+    /// ```
+    /// use delta::tensor::Tensor;
+    /// let logits = Tensor::from_vec(vec![1.0, 1.0,
+    ///                                    1000.0, 1000.0], &[2, 2]);
+    /// let p = logits.softmax(1);
+    /// assert_eq!(p.to_vec(), vec![0.5, 0.5, 0.5, 0.5]);
+    /// ```
+    pub fn softmax(&self, dim: usize) -> Tensor {
+        let out = self.map_lanes(dim, |lane| {
+            let m = lane.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+            let mut sum = 0.0;
+            for x in lane.iter_mut() {
+                *x = (*x - m).exp();
+                sum += *x;
+            }
+            for x in lane.iter_mut() {
+                *x /= sum;
+            }
+        });
+
+        let x = self.clone();
+        record(out, "softmax", &[self], move |g| {
+            // Jacobian diag(s) - s sᵀ per lane: dx = s * (g - Σ g s)
+            let s = x.softmax(dim);
+            let gs = g.mul(&s);
+            let dot = gs.sum_dim(dim, true).broadcast_to(x.shape());
+            vec![gs.sub(&s.mul(&dot))]
+        })
+    }
+
+    /// Log of softmax along a dimension: x_i - logsumexp(x)
+    ///
+    /// Prefer this over `softmax(dim).ln()`: tiny probabilities underflow
+    /// to 0 in softmax and then become -inf, while this form stays finite.
+    ///
+    /// # Panics
+    /// Panics if `dim` is out of range.
+    ///
+    /// # Example
+    /// ```
+    /// use delta::tensor::Tensor;
+    /// let logits = Tensor::from_vec(vec![0.0, 200.0], &[2]);
+    /// let lp = logits.log_softmax(0);
+    /// assert_eq!(lp.get(&[0]), -200.0); // softmax().ln() would give -inf
+    /// ```
+    pub fn log_softmax(&self, dim: usize) -> Tensor {
+        let out = self.map_lanes(dim, |lane| {
+            let lse = logsumexp_lane(lane);
+            for x in lane.iter_mut() {
+                *x -= lse;
+            }
+        });
+
+        let x = self.clone();
+        record(out, "log_softmax", &[self], move |g| {
+            // dx = g - softmax(x) * Σ g
+            let total = g.sum_dim(dim, true).broadcast_to(x.shape());
+            vec![g.sub(&x.softmax(dim).mul(&total))]
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::autograd::testing::{assert_close, check_grad, check_named_grad};
+
+    type UnaryOp = fn(&Tensor) -> Tensor;
+
+    #[test]
+    fn test_relu() {
+        let t = Tensor::from_vec(vec![-1.0, 0.0, 2.0], &[3]);
+        assert_eq!(t.relu().to_vec(), vec![0.0, 0.0, 2.0]);
+    }
+
+    #[test]
+    fn test_leaky_relu() {
+        let t = Tensor::from_vec(vec![-10.0, 0.0, 2.0], &[3]);
+        assert_close(&t.leaky_relu(0.01).to_vec(), &[-0.1, 0.0, 2.0], 1e-5);
+    }
+
+    #[test]
+    fn test_sigmoid() {
+        let t = Tensor::from_vec(vec![0.0, 2.0, -2.0], &[3]);
+        let e2 = 1.0 / (1.0 + (-2f32).exp());
+        assert_close(&t.sigmoid().to_vec(), &[0.5, e2, 1.0 - e2], 1e-5);
+    }
+
+    #[test]
+    fn test_sigmoid_extremes() {
+        let t = Tensor::from_vec(vec![-1000.0, 1000.0], &[2]);
+        assert_eq!(t.sigmoid().to_vec(), vec![0.0, 1.0]);
+    }
+
+    #[test]
+    fn test_silu() {
+        let t = Tensor::from_vec(vec![0.0, 1.0, -1.0], &[3]);
+        let s1 = 1.0 / (1.0 + (-1f32).exp());
+        assert_close(&t.silu().to_vec(), &[0.0, s1, -(1.0 - s1)], 1e-5);
+    }
+
+    #[test]
+    fn test_gelu() {
+        // Reference values of the exact GELU, x * Φ(x)
+        let t = Tensor::from_vec(vec![-3.0, -1.0, 0.0, 1.0, 3.0], &[5]);
+        let exact = [-0.004_05, -0.158_655, 0.0, 0.841_345, 2.995_95];
+        for (g, e) in t.gelu().to_vec().iter().zip(exact) {
+            assert!((g - e).abs() < 1e-3, "{} != {}", g, e);
+        }
+    }
+
+    #[test]
+    fn test_softplus() {
+        let t = Tensor::from_vec(vec![0.0, 1.0, -1.0], &[3]);
+        assert_close(
+            &t.softplus().to_vec(),
+            &[2f32.ln(), 1f32.exp().ln_1p(), (-1f32).exp().ln_1p()],
+            1e-5,
+        );
+    }
+
+    #[test]
+    fn test_softplus_extremes() {
+        let t = Tensor::from_vec(vec![-100.0, 100.0], &[2]);
+        let s = t.softplus();
+        // ln(1 + e^-100) is tiny but positive, ln(1 + e^100) is ~100
+        assert!(s.get(&[0]) > 0.0 && s.get(&[0]) < 1e-30);
+        assert_eq!(s.get(&[1]), 100.0);
+    }
+
+    #[test]
+    fn test_softmax() {
+        let t = Tensor::from_vec(vec![1.0, 2.0, 3.0, 1.0, 1.0, 1.0], &[2, 3]);
+        let p = t.softmax(1);
+        assert_eq!(p.shape(), &[2, 3]);
+
+        let z: f32 = [1f32, 2.0, 3.0].iter().map(|x| x.exp()).sum();
+        assert_close(
+            &p.to_vec(),
+            &[
+                1f32.exp() / z,
+                2f32.exp() / z,
+                3f32.exp() / z,
+                1.0 / 3.0,
+                1.0 / 3.0,
+                1.0 / 3.0,
+            ],
+            1e-5,
+        );
+    }
+
+    #[test]
+    fn test_softmax_dim0_sums_to_one() {
+        let t = Tensor::from_vec(vec![0.5, -1.0, 2.0, 3.0, 0.0, -2.0], &[2, 3]);
+        let col_sums = t.softmax(0).reduce_dim(0, false, |lane| lane.iter().sum());
+        assert_close(&col_sums.to_vec(), &[1.0, 1.0, 1.0], 1e-5);
+    }
+
+    #[test]
+    fn test_softmax_large_logits() {
+        let t = Tensor::from_vec(vec![1000.0, 1001.0], &[2]);
+        let p = t.softmax(0);
+        let e = 1f32.exp();
+        assert_close(&p.to_vec(), &[1.0 / (1.0 + e), e / (1.0 + e)], 1e-5);
+    }
+
+    #[test]
+    fn test_log_softmax() {
+        let t = Tensor::from_vec(vec![1.0, 2.0, 3.0, -1.0, 0.0, 1.0], &[2, 3]);
+        let lp = t.log_softmax(1);
+        let expected: Vec<f32> = t.softmax(1).to_vec().iter().map(|p| p.ln()).collect();
+        assert_close(&lp.to_vec(), &expected, 1e-5);
+    }
+
+    #[test]
+    fn test_log_softmax_no_underflow() {
+        let t = Tensor::from_vec(vec![0.0, 200.0], &[2]);
+        let lp = t.log_softmax(0);
+        assert_close(&lp.to_vec(), &[-200.0, 0.0], 1e-5);
+    }
+
+    #[test]
+    #[should_panic(expected = "out of range")]
+    fn test_softmax_dim_out_of_range() {
+        Tensor::zeros(&[2, 3]).softmax(2);
+    }
+
+    #[test]
+    fn test_grad_elementwise() {
+        // Away from the ReLU kink at 0, where finite differences disagree
+        let x = Tensor::from_vec(vec![-2.0, -0.3, 0.4, 1.7, 3.0, -5.0], &[2, 3]);
+        let ops: [(&str, UnaryOp); 6] = [
+            ("relu", Tensor::relu),
+            ("leaky_relu", |t| t.leaky_relu(0.1)),
+            ("sigmoid", Tensor::sigmoid),
+            ("silu", Tensor::silu),
+            ("gelu", Tensor::gelu),
+            ("softplus", Tensor::softplus),
+        ];
+        for (name, op) in ops {
+            check_named_grad(name, |x| op(&x[0]), std::slice::from_ref(&x));
+        }
+    }
+
+    #[test]
+    fn test_grad_softmax() {
+        let x = Tensor::from_vec(vec![0.5, -1.0, 2.0, 0.0, 1.0, 3.0], &[2, 3]);
+        check_grad(|x| x[0].softmax(1), std::slice::from_ref(&x));
+        check_grad(|x| x[0].softmax(0), std::slice::from_ref(&x));
+    }
+
+    #[test]
+    fn test_grad_log_softmax() {
+        let x = Tensor::from_vec(vec![0.5, -1.0, 2.0, 0.0, 1.0, 3.0], &[2, 3]);
+        check_grad(|x| x[0].log_softmax(1), std::slice::from_ref(&x));
+        check_grad(|x| x[0].log_softmax(0), std::slice::from_ref(&x));
+    }
+}
@@ -0,0 +1,164 @@
+use crate::tensor::Tensor;
+
+/// Convert a predicate result into a mask value.
+fn mask(b: bool) -> f32 {
+    if b { 1.0 } else { 0.0 }
+}
+
+impl Tensor {
+    /// Element-wise `self == other` (equal) as a 0/1 mask.
+    ///
+    /// Shapes are broadcast against each other. Any comparison involving
+    /// NaN is false, except `ne`, which is true.
+    ///
+    /// # Panics
+    /// Panics if the shapes are not broadcast-compatible.
+    ///
+    /// # Example
+    /// ```
+    /// use delta::tensor::Tensor;
+    /// let preds = Tensor::from_vec(vec![1.0, 0.0, 2.0], &[3]);
+    /// let targets = Tensor::from_vec(vec![1.0, 1.0, 2.0], &[3]);
+    /// assert_eq!(preds.eq(&targets).to_vec(), vec![1.0, 0.0, 1.0]);
+    /// ```
+    pub fn eq(&self, other: &Tensor) -> Tensor {
+        self.broadcast_op(other, |a, b| mask(a == b))
+    }
+
+    /// Element-wise `self != other` (not equal) as a 0/1 mask.
+    ///
+    /// Broadcasts like [`Tensor::eq`].
+    pub fn ne(&self, other: &Tensor) -> Tensor {
+        self.broadcast_op(other, |a, b| mask(a != b))
+    }
+
+    /// Element-wise `self < other` (less than) as a 0/1 mask.
+    ///
+    /// Broadcasts like [`Tensor::eq`].
+    pub fn lt(&self, other: &Tensor) -> Tensor {
+        self.broadcast_op(other, |a, b| mask(a < b))
+    }
+
+    /// Element-wise `self <= other` (less than or equal) as a 0/1 mask.
+    ///
+    /// Broadcasts like [`Tensor::eq`].
+    pub fn le(&self, other: &Tensor) -> Tensor {
+        self.broadcast_op(other, |a, b| mask(a <= b))
+    }
+
+    /// Element-wise `self > other` (greater than) as a 0/1 mask.
+    ///
+    /// Broadcasts like [`Tensor::eq`].
+    pub fn gt(&self, other: &Tensor) -> Tensor {
+        self.broadcast_op(other, |a, b| mask(a > b))
+    }
+
+    /// Element-wise `self >= other` (greater than or equal) as a 0/1 mask.
+    ///
+    /// Broadcasts like [`Tensor::eq`].
+    pub fn ge(&self, other: &Tensor) -> Tensor {
+        self.broadcast_op(other, |a, b| mask(a >= b))
+    }
+
+    /// Element-wise `self == scalar` (equal) as a 0/1 mask.
+    pub fn eq_scalar(&self, scalar: f32) -> Tensor {
+        self.unary_op(|a| mask(a == scalar))
+    }
+
+    /// Element-wise `self != scalar` (not equal) as a 0/1 mask.
+    pub fn ne_scalar(&self, scalar: f32) -> Tensor {
+        self.unary_op(|a| mask(a != scalar))
+    }
+
+    /// Element-wise `self < scalar` (less than) as a 0/1 mask.
+    pub fn lt_scalar(&self, scalar: f32) -> Tensor {
+        self.unary_op(|a| mask(a < scalar))
+    }
+
+    /// Element-wise `self <= scalar` (less than or equal) as a 0/1 mask.
+    pub fn le_scalar(&self, scalar: f32) -> Tensor {
+        self.unary_op(|a| mask(a <= scalar))
+    }
+
+    /// Element-wise `self > scalar` (greater than) as a 0/1 mask.
+    pub fn gt_scalar(&self, scalar: f32) -> Tensor {
+        self.unary_op(|a| mask(a > scalar))
+    }
+
+    /// Element-wise `self >= scalar` (greater than or equal) as a 0/1 mask.
+    pub fn ge_scalar(&self, scalar: f32) -> Tensor {
+        self.unary_op(|a| mask(a >= scalar))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compare_same_shape() {
+        let a = Tensor::from_vec(vec![1.0, 2.0, 3.0], &[3]);
+        let b = Tensor::from_vec(vec![3.0, 2.0, 1.0], &[3]);
+        assert_eq!(a.eq(&b).to_vec(), vec![0.0, 1.0, 0.0]);
+        assert_eq!(a.ne(&b).to_vec(), vec![1.0, 0.0, 1.0]);
+        assert_eq!(a.lt(&b).to_vec(), vec![1.0, 0.0, 0.0]);
+        assert_eq!(a.le(&b).to_vec(), vec![1.0, 1.0, 0.0]);
+        assert_eq!(a.gt(&b).to_vec(), vec![0.0, 0.0, 1.0]);
+        assert_eq!(a.ge(&b).to_vec(), vec![0.0, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn test_compare_broadcast_row() {
+        // [2, 3] vs [3]: the row is compared against each row
+        let a = Tensor::from_vec(vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0], &[2, 3]);
+        let row = Tensor::from_vec(vec![1.0, 5.0, 3.0], &[3]);
+        let m = a.eq(&row);
+        assert_eq!(m.shape(), &[2, 3]);
+        assert_eq!(m.to_vec(), vec![1.0, 0.0, 1.0, 0.0, 1.0, 0.0]);
+    }
+
+    #[test]
+    fn test_compare_broadcast_outer() {
+        // [2, 1] vs [1, 3] -> [2, 3]
+        let col = Tensor::from_vec(vec![1.0, 2.0], &[2, 1]);
+        let row = Tensor::from_vec(vec![0.0, 1.0, 2.0], &[1, 3]);
+        let m = col.gt(&row);
+        assert_eq!(m.shape(), &[2, 3]);
+        assert_eq!(m.to_vec(), vec![1.0, 0.0, 0.0, 1.0, 1.0, 0.0]);
+    }
+
+    #[test]
+    fn test_compare_scalar() {
+        let a = Tensor::from_vec(vec![-1.0, 0.0, 1.0], &[3]);
+        assert_eq!(a.eq_scalar(0.0).to_vec(), vec![0.0, 1.0, 0.0]);
+        assert_eq!(a.ne_scalar(0.0).to_vec(), vec![1.0, 0.0, 1.0]);
+        assert_eq!(a.lt_scalar(0.0).to_vec(), vec![1.0, 0.0, 0.0]);
+        assert_eq!(a.le_scalar(0.0).to_vec(), vec![1.0, 1.0, 0.0]);
+        assert_eq!(a.gt_scalar(0.0).to_vec(), vec![0.0, 0.0, 1.0]);
+        assert_eq!(a.ge_scalar(0.0).to_vec(), vec![0.0, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn test_compare_nan() {
+        let a = Tensor::from_vec(vec![f32::NAN], &[1]);
+        assert_eq!(a.eq(&a).to_vec(), vec![0.0]);
+        assert_eq!(a.ne(&a).to_vec(), vec![1.0]);
+        assert_eq!(a.lt_scalar(1.0).to_vec(), vec![0.0]);
+    }
+
+    #[test]
+    fn test_accuracy_from_mask() {
+        let preds = Tensor::from_vec(vec![0.0, 2.0, 1.0, 1.0], &[4]);
+        let targets = Tensor::from_vec(vec![0.0, 1.0, 1.0, 1.0], &[4]);
+        let correct = preds.eq(&targets).count_nonzero(0).get(&[]);
+        assert_eq!(correct / 4.0, 0.75);
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot broadcast")]
+    fn test_compare_incompatible() {
+        let a = Tensor::zeros(&[2, 3]);
+        let b = Tensor::zeros(&[2]);
+        a.lt(&b);
+    }
+}
@@ -0,0 +1,610 @@
+use crate::autograd::record;
+use crate::tensor::{Tensor, matmul};
+
+/// Geometry of a convolution over `N` spatial dimensions: stride,
+/// zero padding on both sides and dilation per dimension, and the number
+/// of channel groups.
+///
+/// With `groups = g`, the input and output channels are split into `g`
+/// groups and each output group only sees its input group (`g` equal to
+/// the number of channels gives a depthwise convolution).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConvOptions<const N: usize> {
+    pub stride: [usize; N],
+    pub padding: [usize; N],
+    pub dilation: [usize; N],
+    pub groups: usize,
+}
+
+impl<const N: usize> Default for ConvOptions<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> ConvOptions<N> {
+    /// Stride 1, no padding, no dilation, one group.
+    pub fn new() -> Self {
+        Self {
+            stride: [1; N],
+            padding: [0; N],
+            dilation: [1; N],
+            groups: 1,
+        }
+    }
+
+    pub fn stride(mut self, stride: [usize; N]) -> Self {
+        self.stride = stride;
+        self
+    }
+
+    pub fn padding(mut self, padding: [usize; N]) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    pub fn dilation(mut self, dilation: [usize; N]) -> Self {
+        self.dilation = dilation;
+        self
+    }
+
+    pub fn groups(mut self, groups: usize) -> Self {
+        self.groups = groups;
+        self
+    }
+}
+
+/// Sizes of a convolution, and the im2col gather table.
+///
+/// Per batch element and group, the input block is unfolded into a
+/// `[cg * kernel, out]` column matrix whose entry `(r, q)` is the input
+/// value kernel tap `r` sees at output position `q`. `table` holds, for
+/// each entry, the offset of that value within the group's channels, or
+/// `None` where the tap falls on padding.
+struct Geometry {
+    batch: usize,
+    c_in: usize,
+    c_out: usize,
+    groups: usize,
+    /// Number of input positions per channel
+    input: usize,
+    /// Output size along each spatial dimension
+    out: Vec<usize>,
+    /// Number of output positions per channel
+    output: usize,
+    /// Rows of the column matrix: input channels per group times taps
+    rows: usize,
+    table: Vec<Option<usize>>,
+}
+
+/// Row-major multi-index of `flat` in `dims`.
+fn unravel(mut flat: usize, dims: &[usize]) -> Vec<usize> {
+    let mut idx = vec![0; dims.len()];
+    for d in (0..dims.len()).rev() {
+        idx[d] = flat % dims[d];
+        flat /= dims[d];
+    }
+    idx
+}
+
+impl Geometry {
+    /// The geometry of `conv` from input shape `x` to kernel shape `w`.
+    fn new(x: &[usize], w: &[usize], conv: &Conv) -> Self {
+        let Conv {
+            stride,
+            padding,
+            dilation,
+            groups,
+            op,
+        } = conv;
+        let (groups, op) = (*groups, *op);
+        let n = stride.len();
+        assert!(
+            x.len() == n + 2,
+            "{} expects input [batch, channels, ...] with {} spatial dimensions, got {:?}",
+            op,
+            n,
+            x
+        );
+        assert!(
+            w.len() == n + 2,
+            "{} expects weight [out_channels, in_channels / groups, ...] with {} kernel dimensions, got {:?}",
+            op,
+            n,
+            w
+        );
+        let (batch, c_in, c_out) = (x[0], x[1], w[0]);
+        assert!(
+            groups > 0 && c_in.is_multiple_of(groups) && c_out.is_multiple_of(groups),
+            "{} with {} groups requires channel counts divisible by it, got {} in and {} out",
+            op,
+            groups,
+            c_in,
+            c_out
+        );
+        let cg = c_in / groups;
+        assert_eq!(
+            w[1], cg,
+            "{} weight {:?} must have in_channels / groups = {} input channels",
+            op, w, cg
+        );
+        assert!(
+            stride.iter().chain(dilation).all(|&s| s > 0),
+            "{} stride and dilation must be positive, got {:?} and {:?}",
+            op,
+            stride,
+            dilation
+        );
+
+        let spatial = &x[2..];
+        let kernel = &w[2..];
+        let out: Vec<usize> = (0..n)
+            .map(|d| {
+                let span = dilation[d] * kernel[d].saturating_sub(1) + 1;
+                let padded = spatial[d] + 2 * padding[d];
+                assert!(
+                    kernel[d] > 0 && span <= padded,
+                    "{} kernel {:?} (dilation {:?}) does not fit the padded input {:?}",
+                    op,
+                    kernel,
+                    dilation,
+                    x
+                );
+                (padded - span) / stride[d] + 1
+            })
+            .collect();
+
+        let (input, output) = (spatial.iter().product(), out.iter().product::<usize>());
+        let taps: usize = kernel.iter().product();
+        let rows = cg * taps;
+        let mut table = Vec::with_capacity(rows * output);
+        for r in 0..rows {
+            let (c, k) = (r / taps, unravel(r % taps, kernel));
+            for q in 0..output {
+                let o = unravel(q, &out);
+                let mut offset = 0;
+                let mut inside = true;
+                for d in 0..n {
+                    let pos =
+                        (o[d] * stride[d] + k[d] * dilation[d]) as isize - padding[d] as isize;
+                    inside &= pos >= 0 && (pos as usize) < spatial[d];
+                    offset = offset * spatial[d] + pos.max(0) as usize;
+                }
+                table.push(inside.then_some(c * input + offset));
+            }
+        }
+
+        Self {
+            batch,
+            c_in,
+            c_out,
+            groups,
+            input,
+            out,
+            output,
+            rows,
+            table,
+        }
+    }
+
+    /// Output shape `[batch, c_out, out...]`.
+    fn output_shape(&self) -> Vec<usize> {
+        [self.batch, self.c_out]
+            .into_iter()
+            .chain(self.out.iter().copied())
+            .collect()
+    }
+
+    /// Offset of the input block of batch element `b`, group `g`.
+    fn input_block(&self, b: usize, g: usize) -> usize {
+        (b * self.c_in + g * (self.c_in / self.groups)) * self.input
+    }
+
+    /// Number of weights per group.
+    fn weight_block(&self) -> usize {
+        self.c_out / self.groups * self.rows
+    }
+
+    /// Offset of the output block of batch element `b`, group `g`.
+    fn output_block(&self, b: usize, g: usize) -> usize {
+        (b * self.c_out + g * (self.c_out / self.groups)) * self.output
+    }
+
+    fn im2col(&self, x: &[f32], b: usize, g: usize) -> Vec<f32> {
+        let block = &x[self.input_block(b, g)..];
+        self.table
+            .iter()
+            .map(|i| i.map_or(0.0, |i| block[i]))
+            .collect()
+    }
+
+    fn col2im(&self, col: &[f32], grad_x: &mut [f32], b: usize, g: usize) {
+        let start = self.input_block(b, g);
+        for (i, &v) in self.table.iter().zip(col) {
+            if let Some(i) = i {
+                grad_x[start + i] += v;
+            }
+        }
+    }
+}
+
+fn transpose(a: &[f32], rows: usize, cols: usize) -> Vec<f32> {
+    let mut t = vec![0.0; a.len()];
+    for i in 0..rows {
+        for j in 0..cols {
+            t[j * rows + i] = a[i * cols + j];
+        }
+    }
+    t
+}
+
+/// A convolution: its geometry and the name it is recorded under.
+///
+/// The forward op and the two halves of its backward rule are recorded
+/// as ops of their own, each differentiated by the others, so
+/// convolutions differentiate to any order (gradient penalties, Hessian
+/// products). They keep this description rather than the im2col table,
+/// which is rebuilt whenever a product needs it.
+#[derive(Debug, Clone)]
+struct Conv {
+    stride: Vec<usize>,
+    padding: Vec<usize>,
+    dilation: Vec<usize>,
+    groups: usize,
+    op: &'static str,
+}
+
+impl Conv {
+    /// Convolution (cross-correlation, as in every deep learning library)
+    /// of `x` `[batch, c_in, spatial...]` with `w` `[c_out, c_in / groups,
+    /// kernel...]`, by im2col and matrix products.
+    fn forward(&self, x: &Tensor, w: &Tensor) -> Tensor {
+        let geo = Geometry::new(x.shape(), w.shape(), self);
+        let (xs, ws) = (x.to_vec(), w.to_vec());
+        let (og, weight_block) = (geo.c_out / geo.groups, geo.weight_block());
+
+        let mut out = vec![0.0; geo.batch * geo.c_out * geo.output];
+        for b in 0..geo.batch {
+            for g in 0..geo.groups {
+                let col = geo.im2col(&xs, b, g);
+                let wg = &ws[g * weight_block..(g + 1) * weight_block];
+                let y = matmul::matmul(wg, &col, og, geo.rows, geo.output);
+                let start = geo.output_block(b, g);
+                out[start..start + y.len()].copy_from_slice(&y);
+            }
+        }
+        let out = Tensor::from_vec(out, &geo.output_shape());
+
+        let (conv, input, weight) = (self.clone(), x.clone(), w.clone());
+        record(out, self.op, &[x, w], move |grad| {
+            vec![
+                conv.input_grad(grad, &weight, input.shape()),
+                conv.weight_grad(&input, grad, weight.shape()),
+            ]
+        })
+    }
+
+    /// The gradient of the input `[batch, c_in, spatial...]` (of shape
+    /// `x_shape`) given the gradient `grad` of the output: the transposed
+    /// convolution of `grad` with `w`.
+    fn input_grad(&self, grad: &Tensor, w: &Tensor, x_shape: &[usize]) -> Tensor {
+        let geo = Geometry::new(x_shape, w.shape(), self);
+        let (gs, ws) = (grad.to_vec(), w.to_vec());
+        let (og, weight_block) = (geo.c_out / geo.groups, geo.weight_block());
+
+        let mut grad_x = vec![0.0; x_shape.iter().product()];
+        for g in 0..geo.groups {
+            let wg_t = transpose(&ws[g * weight_block..(g + 1) * weight_block], og, geo.rows);
+            for b in 0..geo.batch {
+                let start = geo.output_block(b, g);
+                let gy = &gs[start..start + og * geo.output];
+                // dcol = Wᵀ dY
+                let dcol = matmul::matmul(&wg_t, gy, geo.rows, og, geo.output);
+                geo.col2im(&dcol, &mut grad_x, b, g);
+            }
+        }
+        let out = Tensor::from_vec(grad_x, x_shape);
+
+        let (conv, output_grad, weight) = (self.clone(), grad.clone(), w.clone());
+        record(out, "conv_input_grad", &[grad, w], move |h| {
+            vec![
+                conv.forward(h, &weight),
+                conv.weight_grad(h, &output_grad, weight.shape()),
+            ]
+        })
+    }
+
+    /// The gradient of the weight (of shape `w_shape`) given the input `x`
+    /// and the gradient `grad` of the output.
+    fn weight_grad(&self, x: &Tensor, grad: &Tensor, w_shape: &[usize]) -> Tensor {
+        let geo = Geometry::new(x.shape(), w_shape, self);
+        let (xs, gs) = (x.to_vec(), grad.to_vec());
+        let (og, weight_block) = (geo.c_out / geo.groups, geo.weight_block());
+
+        let mut grad_w = vec![0.0; w_shape.iter().product()];
+        for b in 0..geo.batch {
+            for g in 0..geo.groups {
+                let col_t = transpose(&geo.im2col(&xs, b, g), geo.rows, geo.output);
+                let start = geo.output_block(b, g);
+                let gy = &gs[start..start + og * geo.output];
+                // dW = dY colᵀ
+                let dw = matmul::matmul(gy, &col_t, og, geo.output, geo.rows);
+                for (acc, v) in grad_w[g * weight_block..].iter_mut().zip(dw) {
+                    *acc += v;
+                }
+            }
+        }
+        let out = Tensor::from_vec(grad_w, w_shape);
+
+        let (conv, input, output_grad) = (self.clone(), x.clone(), grad.clone());
+        record(out, "conv_weight_grad", &[x, grad], move |h| {
+            vec![
+                conv.input_grad(&output_grad, h, input.shape()),
+                conv.forward(&input, h),
+            ]
+        })
+    }
+}
+
+/// Convolution with the geometry of `options`, recorded as `conv1d`,
+/// `conv2d` or `conv3d`.
+pub(crate) fn conv_nd<const N: usize>(x: &Tensor, w: &Tensor, options: &ConvOptions<N>) -> Tensor {
+    let op = match N {
+        1 => "conv1d",
+        2 => "conv2d",
+        3 => "conv3d",
+        _ => "conv",
+    };
+    let conv = Conv {
+        stride: options.stride.to_vec(),
+        padding: options.padding.to_vec(),
+        dilation: options.dilation.to_vec(),
+        groups: options.groups,
+        op,
+    };
+    conv.forward(x, w)
+}
+
+impl Tensor {
+    /// 1D convolution of `self` `[batch, c_in, length]` with `weight`
+    /// `[c_out, c_in / groups, k]`, giving `[batch, c_out, length']`: audio
+    /// and time series. Sizes and options as for [`Tensor::conv2d`].
+    ///
+    /// # Example
+    /// ```
+    /// use delta::tensor::{ConvOptions, Tensor};
+    ///
+    /// // A first difference filter
+    /// let x = Tensor::from_vec(vec![1.0, 4.0, 9.0, 16.0], &[1, 1, 4]);
+    /// let w = Tensor::from_vec(vec![-1.0, 1.0], &[1, 1, 2]);
+    /// assert_eq!(x.conv1d(&w, &ConvOptions::new()).to_vec(), vec![3.0, 5.0, 7.0]);
+    /// ```
+    pub fn conv1d(&self, weight: &Tensor, options: &ConvOptions<1>) -> Tensor {
+        conv_nd(self, weight, options)
+    }
+
+    /// 2D convolution of `self` `[batch, c_in, h, w]` with `weight`
+    /// `[c_out, c_in / groups, kh, kw]`, giving `[batch, c_out, h', w']`
+    /// with `h' = (h + 2 padding - dilation (kh - 1) - 1) / stride + 1`.
+    ///
+    /// Computed as im2col followed by one matrix product per batch element
+    /// and group. Differentiable with respect to `self` and `weight`, to
+    /// any order. Add a bias by broadcasting over the channels.
+    ///
+    /// # Panics
+    /// - Panics if the shapes are not as above or the channel counts are
+    ///   not divisible by the number of groups
+    /// - Panics if the dilated kernel is larger than the padded input
+    ///
+    /// # Example
+    /// ```
+    /// use delta::tensor::{ConvOptions, Tensor};
+    ///
+    /// let x = Tensor::from_vec((0..16).map(|v| v as f32).collect(), &[1, 1, 4, 4]);
+    /// // 2x2 box filter, sliding two pixels at a time
+    /// let w = Tensor::from_vec(vec![1.0; 4], &[1, 1, 2, 2]);
+    /// let y = x.conv2d(&w, &ConvOptions::new().stride([2, 2]));
+    /// assert_eq!(y.shape(), &[1, 1, 2, 2]);
+    /// assert_eq!(y.to_vec(), vec![10.0, 18.0, 42.0, 50.0]);
+    /// ```
+    pub fn conv2d(&self, weight: &Tensor, options: &ConvOptions<2>) -> Tensor {
+        conv_nd(self, weight, options)
+    }
+
+    /// 3D convolution of `self` `[batch, c_in, d, h, w]` with `weight`
+    /// `[c_out, c_in / groups, kd, kh, kw]`, giving `[batch, c_out, d', h',
+    /// w']`: video and volumetric data. Sizes and options as for
+    /// [`Tensor::conv2d`].
+    pub fn conv3d(&self, weight: &Tensor, options: &ConvOptions<3>) -> Tensor {
+        conv_nd(self, weight, options)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::autograd::testing::{assert_tensors_close, check_grad};
+    use crate::autograd::{grad, jvp};
+    use crate::random::Rng;
+
+    /// Direct 2D convolution, straight from the definition.
+    fn reference(x: &Tensor, w: &Tensor, o: &ConvOptions<2>) -> Tensor {
+        let (xs, ws) = (x.to_vec(), w.to_vec());
+        let [b, c, h, wd] = x.shape().try_into().unwrap();
+        let [co, cg, kh, kw] = w.shape().try_into().unwrap();
+        let og = co / o.groups;
+        let oh = (h + 2 * o.padding[0] - o.dilation[0] * (kh - 1) - 1) / o.stride[0] + 1;
+        let ow = (wd + 2 * o.padding[1] - o.dilation[1] * (kw - 1) - 1) / o.stride[1] + 1;
+        let mut out = vec![0.0; b * co * oh * ow];
+        for n in 0..b {
+            for oc in 0..co {
+                let group = oc / og;
+                for i in 0..oh {
+                    for j in 0..ow {
+                        let mut acc = 0.0;
+                        for ic in 0..cg {
+                            for p in 0..kh {
+                                for q in 0..kw {
+                                    let y = (i * o.stride[0] + p * o.dilation[0]) as isize
+                                        - o.padding[0] as isize;
+                                    let z = (j * o.stride[1] + q * o.dilation[1]) as isize
+                                        - o.padding[1] as isize;
+                                    if y < 0 || z < 0 || y as usize >= h || z as usize >= wd {
+                                        continue;
+                                    }
+                                    let channel = group * cg + ic;
+                                    acc += xs
+                                        [((n * c + channel) * h + y as usize) * wd + z as usize]
+                                        * ws[((oc * cg + ic) * kh + p) * kw + q];
+                                }
+                            }
+                        }
+                        out[((n * co + oc) * oh + i) * ow + j] = acc;
+                    }
+                }
+            }
+        }
+        Tensor::from_vec(out, &[b, co, oh, ow])
+    }
+
+    #[test]
+    fn test_matches_direct_convolution() {
+        let mut rng = Rng::new(0);
+        let cases = [
+            (ConvOptions::new(), [2, 3, 5, 6], [4, 3, 3, 2]),
+            (
+                ConvOptions::new().padding([1, 2]),
+                [1, 2, 4, 4],
+                [3, 2, 3, 3],
+            ),
+            (
+                ConvOptions::new().stride([2, 3]),
+                [1, 1, 7, 8],
+                [2, 1, 3, 2],
+            ),
+            (
+                ConvOptions::new().dilation([2, 1]).padding([1, 0]),
+                [2, 2, 6, 5],
+                [2, 2, 2, 3],
+            ),
+            (ConvOptions::new().groups(2), [1, 4, 5, 5], [6, 2, 3, 3]),
+            // Depthwise
+            (
+                ConvOptions::new().groups(3).padding([1, 1]),
+                [1, 3, 4, 4],
+                [3, 1, 3, 3],
+            ),
+        ];
+        for (options, x_shape, w_shape) in cases {
+            let x = Tensor::randn(&x_shape, &mut rng);
+            let w = Tensor::randn(&w_shape, &mut rng);
+            assert_tensors_close(&x.conv2d(&w, &options), &reference(&x, &w, &options), 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_gradients() {
+        let mut rng = Rng::new(1);
+        let options = ConvOptions::new()
+            .stride([2, 1])
+            .padding([1, 1])
+            .dilation([1, 2])
+            .groups(2);
+        let x = Tensor::randn(&[2, 4, 5, 5], &mut rng);
+        let w = Tensor::randn(&[2, 2, 2, 2], &mut rng);
+        check_grad(|t| t[0].conv2d(&t[1], &options).tanh(), &[x, w]);
+    }
+
+    #[test]
+    fn test_second_order() {
+        // The input gradient of a penalty on the output, differentiated
+        // again in the input and the weight (WGAN-GP through a conv)
+        let mut rng = Rng::new(3);
+        let options = ConvOptions::new().stride([2, 1]).padding([1, 0]).groups(2);
+        let x = Tensor::randn(&[1, 2, 4, 3], &mut rng);
+        let w = Tensor::randn(&[4, 1, 2, 2], &mut rng);
+        check_grad(
+            |t| {
+                let t: Vec<Tensor> = t.iter().map(|x| x.clone().requires_grad(true)).collect();
+                let y = t[0].conv2d(&t[1], &options).tanh().sum();
+                grad(&y, &t, true)[0].clone()
+            },
+            &[x, w],
+        );
+
+        // A 1x1 identity kernel passes the tangent through unchanged
+        let x = Tensor::randn(&[2, 3, 2, 2], &mut rng);
+        let v = Tensor::randn(&[2, 3, 2, 2], &mut rng);
+        let eye = Tensor::from_vec(
+            (0..9).map(|i| if i % 4 == 0 { 1.0 } else { 0.0 }).collect(),
+            &[3, 3, 1, 1],
+        );
+        let (_, jv) = jvp(|x| x.conv2d(&eye, &ConvOptions::new()), &x, &v);
+        assert_eq!(jv.to_vec(), v.to_vec());
+    }
+
+    #[test]
+    fn test_conv1d_and_conv3d_match_conv2d() {
+        let mut rng = Rng::new(2);
+        // A sequence is an image of height 1
+        let x = Tensor::randn(&[2, 4, 9], &mut rng);
+        let w = Tensor::randn(&[6, 2, 3], &mut rng);
+        let options = ConvOptions::new()
+            .stride([2])
+            .padding([2])
+            .dilation([2])
+            .groups(2);
+        let flat = x.reshape(&[2, 4, 1, 9]).conv2d(
+            &w.reshape(&[6, 2, 1, 3]),
+            &ConvOptions::new()
+                .stride([1, 2])
+                .padding([0, 2])
+                .dilation([1, 2])
+                .groups(2),
+        );
+        let y = x.conv1d(&w, &options);
+        assert_eq!(y.shape(), &[2, 6, 5]);
+        assert_tensors_close(&y, &flat.reshape(&[2, 6, 5]), 1e-5);
+
+        // A kernel of depth 1 convolves each frame of a volume separately
+        let x = Tensor::randn(&[1, 2, 1, 5, 4], &mut rng);
+        let w = Tensor::randn(&[3, 2, 1, 2, 2], &mut rng);
+        let y = x.conv3d(&w, &ConvOptions::new().padding([0, 1, 0]));
+        let frame = x.reshape(&[1, 2, 5, 4]).conv2d(
+            &w.reshape(&[3, 2, 2, 2]),
+            &ConvOptions::new().padding([1, 0]),
+        );
+        assert_eq!(y.shape(), &[1, 3, 1, 6, 3]);
+        assert_tensors_close(&y.reshape(&[1, 3, 6, 3]), &frame, 1e-5);
+    }
+
+    #[test]
+    fn test_conv1d_conv3d_gradients() {
+        let mut rng = Rng::new(4);
+        let x = Tensor::randn(&[2, 2, 6], &mut rng);
+        let w = Tensor::randn(&[2, 2, 3], &mut rng);
+        let options = ConvOptions::new().stride([2]).padding([1]);
+        check_grad(|t| t[0].conv1d(&t[1], &options), &[x, w]);
+
+        let x = Tensor::randn(&[1, 2, 3, 3, 4], &mut rng);
+        let w = Tensor::randn(&[2, 1, 2, 2, 2], &mut rng);
+        let options = ConvOptions::new().padding([1, 0, 1]).groups(2);
+        check_grad(|t| t[0].conv3d(&t[1], &options).tanh(), &[x, w]);
+    }
+
+    #[test]
+    #[should_panic(expected = "divisible by it, got 3 in and 4 out")]
+    fn test_groups_must_divide_channels() {
+        let x = Tensor::zeros(&[1, 3, 4, 4]);
+        let w = Tensor::zeros(&[4, 1, 1, 1]);
+        x.conv2d(&w, &ConvOptions::new().groups(2));
+    }
+
+    #[test]
+    #[should_panic(expected = "does not fit the padded input")]
+    fn test_kernel_too_large() {
+        let x = Tensor::zeros(&[1, 1, 3, 3]);
+        let w = Tensor::zeros(&[1, 1, 2, 2]);
+        x.conv2d(&w, &ConvOptions::new().dilation([3, 1]));
+    }
+}
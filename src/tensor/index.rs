@@ -0,0 +1,153 @@
+use std::rc::Rc;
+
+use crate::autograd::record;
+use crate::tensor::Tensor;
+
+/// Rows `rows` of `table` `[n, d]`, stacked `[rows.len(), d]`, recorded as
+/// `op`.
+///
+/// Only the selected rows are read, through the strides of `table`, so a
+/// large table is never copied. The gradient is scattered back to the
+/// rows by [`scatter_rows`], whose own gradient is this gather: the pair
+/// differentiates to any order.
+pub(crate) fn gather_rows(table: &Tensor, rows: Rc<[usize]>, op: &'static str) -> Tensor {
+    let (n, d) = (table.shape()[0], table.shape()[1]);
+    let (data, strides, offset) = table.raw_parts();
+    let mut out = Vec::with_capacity(rows.len() * d);
+    for &r in rows.iter() {
+        let start = offset + r * strides[0];
+        out.extend((0..d).map(|j| data[start + j * strides[1]]));
+    }
+    let out = Tensor::from_vec(out, &[rows.len(), d]);
+    record(out, op, &[table], move |g| {
+        vec![scatter_rows(g, rows.clone(), n)]
+    })
+}
+
+/// `[n, d]` zeros with row `k` of `src` `[rows.len(), d]` added to row
+/// `rows[k]`, so rows picked several times collect every contribution.
+/// The gradient is [`gather_rows`].
+pub(crate) fn scatter_rows(src: &Tensor, rows: Rc<[usize]>, n: usize) -> Tensor {
+    let d = src.shape()[1];
+    let values = src.to_vec();
+    let mut out = vec![0.0; n * d];
+    for (k, &r) in rows.iter().enumerate() {
+        for (acc, v) in out[r * d..(r + 1) * d]
+            .iter_mut()
+            .zip(&values[k * d..(k + 1) * d])
+        {
+            *acc += v;
+        }
+    }
+    let out = Tensor::from_vec(out, &[n, d]);
+    record(out, "scatter_rows", &[src], move |g| {
+        vec![gather_rows(g, rows.clone(), "gather_rows")]
+    })
+}
+
+/// Sizes before, along and after `axis` of `shape`.
+fn split_axis(shape: &[usize], axis: usize) -> (usize, usize, usize) {
+    (
+        shape[..axis].iter().product(),
+        shape[axis],
+        shape[axis + 1..].iter().product(),
+    )
+}
+
+/// Indices `start..start + len` of `x` along `axis`. The gradient is
+/// [`pad`], whose own gradient is this narrowing.
+pub(crate) fn narrow(x: &Tensor, axis: usize, start: usize, len: usize) -> Tensor {
+    let (outer, n, inner) = split_axis(x.shape(), axis);
+    assert!(
+        start + len <= n,
+        "narrow {}..{} is out of bounds for axis {} of {:?}",
+        start,
+        start + len,
+        axis,
+        x.shape()
+    );
+    let xs = x.to_vec();
+    let out = (0..outer)
+        .flat_map(|o| {
+            let from = (o * n + start) * inner;
+            xs[from..from + len * inner].iter().copied()
+        })
+        .collect();
+    let mut shape = x.shape().to_vec();
+    shape[axis] = len;
+    let out = Tensor::from_vec(out, &shape);
+    record(out, "narrow", &[x], move |g| {
+        vec![pad(g, axis, start, n - start - len)]
+    })
+}
+
+/// `x` with `before` zeros ahead of it and `after` zeros behind it along
+/// `axis`. The gradient is [`narrow`].
+pub(crate) fn pad(x: &Tensor, axis: usize, before: usize, after: usize) -> Tensor {
+    let (outer, n, inner) = split_axis(x.shape(), axis);
+    let xs = x.to_vec();
+    let padded = before + n + after;
+    let mut out = vec![0.0; outer * padded * inner];
+    for o in 0..outer {
+        let to = (o * padded + before) * inner;
+        out[to..to + n * inner].copy_from_slice(&xs[o * n * inner..(o + 1) * n * inner]);
+    }
+    let mut shape = x.shape().to_vec();
+    shape[axis] = padded;
+    let out = Tensor::from_vec(out, &shape);
+    record(out, "pad", &[x], move |g| vec![narrow(g, axis, before, n)])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::autograd::grad;
+    use crate::autograd::testing::check_grad;
+    use crate::random::Rng;
+
+    #[test]
+    fn test_gather_reads_views() {
+        let m = Tensor::from_vec((0..6).map(|v| v as f32).collect(), &[2, 3]);
+        // Rows of the transpose are the columns of m
+        let rows = gather_rows(&m.t(), Rc::from([2, 0, 2]), "gather_rows");
+        assert_eq!(rows.to_vec(), vec![2.0, 5.0, 0.0, 3.0, 2.0, 5.0]);
+    }
+
+    #[test]
+    fn test_gradients_to_any_order() {
+        let table = Tensor::randn(&[4, 3], &mut Rng::new(0));
+        let rows: Rc<[usize]> = Rc::from([1, 3, 1]);
+        check_grad(
+            |t| gather_rows(&t[0], rows.clone(), "gather_rows").tanh(),
+            std::slice::from_ref(&table),
+        );
+        let src = Tensor::randn(&[3, 3], &mut Rng::new(1));
+        check_grad(
+            |t| scatter_rows(&t[0], rows.clone(), 4).tanh(),
+            std::slice::from_ref(&src),
+        );
+
+        // The gradient of a gather is itself differentiable
+        let x = table.clone().requires_grad(true);
+        let y = gather_rows(&x, rows.clone(), "gather_rows").powi(2).sum();
+        let g = grad(&y, std::slice::from_ref(&x), true);
+        let h = grad(&g[0].sum(), std::slice::from_ref(&x), false);
+        // d/dx Σ_rows 2x: row 1 is picked twice
+        let expected: Vec<f32> = [0.0, 4.0, 0.0, 2.0].iter().flat_map(|&v| [v; 3]).collect();
+        assert_eq!(h[0].to_vec(), expected);
+    }
+
+    #[test]
+    fn test_narrow_and_pad() {
+        let x = Tensor::from_vec((0..12).map(|v| v as f32).collect(), &[2, 3, 2]);
+        let mid = narrow(&x, 1, 1, 2);
+        assert_eq!(mid.shape(), &[2, 2, 2]);
+        assert_eq!(mid.to_vec(), vec![2.0, 3.0, 4.0, 5.0, 8.0, 9.0, 10.0, 11.0]);
+        let back = pad(&mid, 1, 1, 0).to_vec();
+        assert_eq!(back[..6], [0.0, 0.0, 2.0, 3.0, 4.0, 5.0]);
+
+        let x = Tensor::randn(&[2, 4, 3], &mut Rng::new(2));
+        check_grad(|t| narrow(&t[0], 1, 1, 2).tanh(), std::slice::from_ref(&x));
+        check_grad(|t| pad(&t[0], 2, 2, 1).tanh(), std::slice::from_ref(&x));
+    }
+}
@@ -0,0 +1,446 @@
+use std::ops::Range;
+use std::rc::Rc;
+
+use crate::autograd::{is_grad_enabled, record};
+use crate::tensor::{Tensor, gather_rows};
+
+/// Row-major offsets, within a contiguous tensor of shape `shape`, of the
+/// elements of the region `ranges` (trailing dimensions taken whole).
+fn region_offsets(shape: &[usize], ranges: &[Range<usize>]) -> Vec<usize> {
+    let full: Vec<Range<usize>> = (0..shape.len())
+        .map(|d| ranges.get(d).cloned().unwrap_or(0..shape[d]))
+        .collect();
+    let mut offsets = vec![0];
+    for (d, range) in full.iter().enumerate() {
+        offsets = offsets
+            .iter()
+            .flat_map(|&base| range.clone().map(move |i| base * shape[d] + i))
+            .collect();
+    }
+    offsets
+}
+
+impl Tensor {
+    /// Replace the values with `op(self, other)`, `other` broadcast to the
+    /// shape of `self`.
+    ///
+    /// When the op takes part in the graph, the new values get a node of
+    /// their own, recorded like the out-of-place op, and the old values
+    /// stay available to whatever saved them (storage is copy-on-write).
+    /// Otherwise the values are overwritten where they are, without a copy
+    /// if this tensor is the only one holding its storage.
+    fn update(
+        &mut self,
+        other: Option<&Tensor>,
+        name: &str,
+        recorded: impl FnOnce(&Tensor, Option<&Tensor>) -> Tensor,
+        mut f: impl FnMut(f32, f32) -> f32,
+    ) {
+        let other = other.map(|o| {
+            if o.shape() == self.shape() {
+                o.clone()
+            } else {
+                o.broadcast_to(self.shape())
+            }
+        });
+        let tracked = self.node().is_some() || other.as_ref().is_some_and(|o| o.node().is_some());
+        if is_grad_enabled() && tracked {
+            assert!(
+                self.node().is_none_or(|node| !node.is_leaf()),
+                "{} on a leaf that requires grad would cut it from its gradient; update \
+                 parameters inside no_grad",
+                name
+            );
+            *self = recorded(self, other.as_ref());
+            return;
+        }
+
+        let values = other.map(|o| o.to_vec()).unwrap_or_default();
+        let mut i = 0;
+        self.map_inplace(|x| {
+            let y = f(x, values.get(i).copied().unwrap_or(0.0));
+            i += 1;
+            y
+        });
+    }
+
+    /// In-place `self + other`, with `other` broadcast to the shape of
+    /// `self`.
+    ///
+    /// # Autograd
+    /// With recording on and a tracked operand, the result is recorded like
+    /// [`Tensor::add`] and `self` takes its place in the graph, so
+    /// gradients stay correct. With recording off (e.g. a parameter update
+    /// inside [`no_grad`](crate::autograd::no_grad)) the values are written
+    /// in place and the write is counted by the tensor's version: a
+    /// backward pass through an op output that was overwritten this way
+    /// and then used again panics instead of returning wrong gradients.
+    ///
+    /// # Panics
+    /// - Panics if `other` cannot be broadcast to the shape of `self`
+    /// - Panics if `self` is a leaf that requires grad and recording is
+    ///   on: update parameters inside `no_grad`
+    ///
+    /// # Example
+    /// ```
+    /// use delta::autograd::no_grad;
+    /// use delta::tensor::Tensor;
+    /// let mut w = Tensor::from_vec(vec![1.0, 2.0], &[2]).requires_grad(true);
+    /// w.mul(&w).sum().backward();
+    /// // A gradient descent step, in place
+    /// let step = w.grad().unwrap().scalar_mul(-0.25);
+    /// no_grad(|| w.add_(&step));
+    /// assert_eq!(w.to_vec(), vec![0.5, 1.0]);
+    /// ```
+    pub fn add_(&mut self, other: &Tensor) {
+        self.update(Some(other), "add_", |a, b| a.add(b.unwrap()), |a, b| a + b);
+    }
+
+    /// In-place `self - other`. See [`Tensor::add_`].
+    pub fn sub_(&mut self, other: &Tensor) {
+        self.update(Some(other), "sub_", |a, b| a.sub(b.unwrap()), |a, b| a - b);
+    }
+
+    /// In-place `self * other`. See [`Tensor::add_`].
+    pub fn mul_(&mut self, other: &Tensor) {
+        self.update(Some(other), "mul_", |a, b| a.mul(b.unwrap()), |a, b| a * b);
+    }
+
+    /// In-place `self / other`. See [`Tensor::add_`].
+    pub fn div_(&mut self, other: &Tensor) {
+        self.update(Some(other), "div_", |a, b| a.div(b.unwrap()), |a, b| a / b);
+    }
+
+    /// In-place `self + scalar`. See [`Tensor::add_`].
+    pub fn scalar_add_(&mut self, scalar: f32) {
+        self.update(
+            None,
+            "scalar_add_",
+            |a, _| a.scalar_add(scalar),
+            |a, _| a + scalar,
+        );
+    }
+
+    /// In-place `self * scalar`. See [`Tensor::add_`].
+    pub fn scalar_mul_(&mut self, scalar: f32) {
+        self.update(
+            None,
+            "scalar_mul_",
+            |a, _| a.scalar_mul(scalar),
+            |a, _| a * scalar,
+        );
+    }
+
+    /// In-place negation. See [`Tensor::add_`].
+    pub fn neg_(&mut self) {
+        self.update(None, "neg_", |a, _| a.neg(), |a, _| -a);
+    }
+
+    /// In-place ReLU, `max(x, 0)`. See [`Tensor::add_`].
+    pub fn relu_(&mut self) {
+        self.update(None, "relu_", |a, _| a.relu(), |a, _| a.max(0.0));
+    }
+
+    /// Overwrite the region `ranges` of `self` with `src`, broadcast to the
+    /// shape of the region.
+    ///
+    /// `ranges` gives the indices kept along each leading dimension; the
+    /// dimensions after them are taken whole, so `&[2..3]` is row 2 of a
+    /// matrix. Views do not alias: storage is copy-on-write, so writing to
+    /// a view leaves the tensor it was taken from as it was. Recorded like
+    /// the in-place arithmetic ops (see [`Tensor::add_`]): with a tracked
+    /// operand the gradient of the region goes to `src` and the rest to
+    /// the old values of `self`, to any order.
+    ///
+    /// # Panics
+    /// - Panics if `ranges` has more entries than `self` has dimensions, or
+    ///   a range is reversed or out of bounds
+    /// - Panics if `src` cannot be broadcast to the region
+    /// - Panics if `self` is a leaf that requires grad and recording is on
+    ///
+    /// # Example
+    /// ```
+    /// use delta::tensor::Tensor;
+    ///
+    /// // A padded batch of two sequences of lengths 3 and 1
+    /// let mut batch = Tensor::zeros(&[2, 3]);
+    /// batch.slice_assign(&[0..1], &Tensor::from_vec(vec![1.0, 2.0, 3.0], &[1, 3]));
+    /// batch.slice_assign(&[1..2, 0..1], &Tensor::from_vec(vec![4.0], &[1, 1]));
+    /// assert_eq!(batch.to_vec(), vec![1.0, 2.0, 3.0, 4.0, 0.0, 0.0]);
+    /// ```
+    pub fn slice_assign(&mut self, ranges: &[Range<usize>], src: &Tensor) {
+        let shape = self.shape().to_vec();
+        assert!(
+            ranges.len() <= shape.len()
+                && ranges
+                    .iter()
+                    .zip(&shape)
+                    .all(|(r, &n)| r.start <= r.end && r.end <= n),
+            "slice_assign region {:?} is out of bounds for shape {:?}",
+            ranges,
+            shape
+        );
+        let region: Vec<usize> = (0..shape.len())
+            .map(|d| ranges.get(d).map_or(shape[d], |r| r.len()))
+            .collect();
+        let src_shape = src.shape().to_vec();
+        let values = if src_shape == region {
+            src.clone()
+        } else {
+            src.broadcast_to(&region)
+        };
+        self.write_region(ranges, &region, &values, src, "slice_assign");
+    }
+
+    /// Overwrite every element of `self` with `src`, broadcast to the shape
+    /// of `self`: [`Tensor::slice_assign`] over the whole tensor.
+    ///
+    /// # Example
+    /// ```
+    /// use delta::tensor::Tensor;
+    ///
+    /// let m = Tensor::from_vec(vec![1.0, 2.0, 3.0, 4.0], &[2, 2]);
+    /// let mut t = m.t();
+    /// t.copy_(&Tensor::from_vec(vec![5.0, 6.0], &[2]));
+    /// assert_eq!(t.to_vec(), vec![5.0, 6.0, 5.0, 6.0]);
+    /// // Views do not alias: m keeps its values
+    /// assert_eq!(m.to_vec(), vec![1.0, 2.0, 3.0, 4.0]);
+    /// ```
+    pub fn copy_(&mut self, src: &Tensor) {
+        let shape = self.shape().to_vec();
+        let values = if src.shape() == shape.as_slice() {
+            src.clone()
+        } else {
+            src.broadcast_to(&shape)
+        };
+        self.write_region(&[], &shape, &values, src, "copy_");
+    }
+
+    /// Write `values` (of shape `region`) into the region `ranges`, as
+    /// `name`.
+    fn write_region(
+        &mut self,
+        ranges: &[Range<usize>],
+        region: &[usize],
+        values: &Tensor,
+        src: &Tensor,
+        name: &'static str,
+    ) {
+        let shape = self.shape().to_vec();
+        let offsets = region_offsets(&shape, ranges);
+        let tracked = self.node().is_some() || src.node().is_some();
+        if is_grad_enabled() && tracked {
+            assert!(
+                self.node().is_none_or(|node| !node.is_leaf()),
+                "{} on a leaf that requires grad would cut it from its gradient; update \
+                 parameters inside no_grad",
+                name
+            );
+            let mut out = self.to_vec();
+            for (&i, v) in offsets.iter().zip(values.to_vec()) {
+                out[i] = v;
+            }
+            // The rest of the gradient goes to the old values, the region
+            // to `src`: both as recorded ops, so the rule differentiates
+            let mut keep = vec![1.0; out.len()];
+            for &i in &offsets {
+                keep[i] = 0.0;
+            }
+            let keep = Tensor::from_vec(keep, &shape);
+            let (region, src_shape) = (region.to_vec(), src.shape().to_vec());
+            let offsets: Rc<[usize]> = offsets.into();
+            let recorded = record(
+                Tensor::from_vec(out, &shape),
+                name,
+                &[&*self, src],
+                move |g| {
+                    let flat = g.reshape(&[g.nelems(), 1]);
+                    let part = gather_rows(&flat, offsets.clone(), "gather_rows");
+                    vec![g.mul(&keep), part.reshape(&region).sum_to(&src_shape)]
+                },
+            );
+            *self = recorded;
+            return;
+        }
+
+        // Walk the region by multi-index, so views write through their strides
+        let mut index = vec![0; shape.len()];
+        for (&flat, v) in offsets.iter().zip(values.to_vec()) {
+            let mut rest = flat;
+            for d in (0..shape.len()).rev() {
+                index[d] = rest % shape[d];
+                rest /= shape[d];
+            }
+            self.set(&index, v);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::autograd::testing::check_grad;
+    use crate::autograd::{grad, no_grad};
+    use crate::tensor::Tensor;
+
+    #[test]
+    fn test_values() {
+        let mut m = Tensor::from_vec(vec![1.0, -2.0, 3.0, -4.0], &[2, 2]);
+        m.add_(&Tensor::from_vec(vec![10.0, 20.0], &[2]));
+        assert_eq!(m.to_vec(), vec![11.0, 18.0, 13.0, 16.0]);
+        m.sub_(&Tensor::from_vec(vec![1.0], &[1]));
+        m.div_(&Tensor::from_vec(vec![2.0, 1.0, 4.0, 5.0], &[2, 2]));
+        m.mul_(&Tensor::from_vec(vec![1.0, -1.0], &[2]));
+        assert_eq!(m.to_vec(), vec![5.0, -17.0, 3.0, -3.0]);
+        m.relu_();
+        m.scalar_mul_(2.0);
+        m.scalar_add_(-1.0);
+        m.neg_();
+        assert_eq!(m.to_vec(), vec![-9.0, 1.0, -5.0, 1.0]);
+    }
+
+    #[test]
+    fn test_clones_keep_their_values() {
+        let mut t = Tensor::from_vec(vec![1.0, 2.0], &[2]);
+        t.scalar_mul_(3.0);
+        let copy = t.clone();
+        t.neg_();
+        assert_eq!(copy.to_vec(), vec![3.0, 6.0]);
+        assert_eq!(t.to_vec(), vec![-3.0, -6.0]);
+    }
+
+    #[test]
+    fn test_recorded_gradients() {
+        check_grad(
+            |t| {
+                let mut h = t[0].exp();
+                h.mul_(&t[1]);
+                h.relu_();
+                h.sub_(&t[0]);
+                h.scalar_mul_(0.5);
+                h
+            },
+            &[
+                Tensor::from_vec(vec![0.5, -1.0, 0.2], &[3]),
+                Tensor::from_vec(vec![2.0, 1.5, -3.0], &[3]),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_parameter_update_under_no_grad() {
+        let mut w = Tensor::from_vec(vec![1.0, -1.0], &[2]).requires_grad(true);
+        let x = Tensor::from_vec(vec![3.0, 4.0], &[2]);
+        let loss = w.mul(&x).sum();
+        // Updating w before the backward pass leaves the saved values alone
+        no_grad(|| w.scalar_mul_(10.0));
+        assert!(w.is_leaf());
+        loss.backward();
+        assert_eq!(w.grad().unwrap().to_vec(), vec![3.0, 4.0]);
+        assert_eq!(w.to_vec(), vec![10.0, -10.0]);
+    }
+
+    #[test]
+    #[should_panic(expected = "sum used the output of exp after it was modified in place")]
+    fn test_unrecorded_write_detected() {
+        let x = Tensor::from_vec(vec![1.0, 2.0], &[2]).requires_grad(true);
+        let mut y = x.exp();
+        no_grad(|| y.scalar_mul_(2.0));
+        y.sum().backward();
+    }
+
+    #[test]
+    fn test_write_after_use_is_fine() {
+        let x = Tensor::from_vec(vec![1.0, 2.0], &[2]).requires_grad(true);
+        let mut y = x.exp();
+        let loss = y.sum();
+        y.set(&[0], 0.0);
+        loss.backward();
+        assert_eq!(x.grad().unwrap().to_vec(), x.exp().to_vec());
+    }
+
+    #[test]
+    fn test_slice_assign_regions() {
+        let mut t = Tensor::zeros(&[2, 3, 2]);
+        t.slice_assign(&[1..2, 1..3], &Tensor::from_vec(vec![1.0, 2.0], &[2]));
+        assert_eq!(
+            t.to_vec(),
+            vec![0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 1.0, 2.0, 1.0, 2.0]
+        );
+        // Empty regions write nothing
+        t.slice_assign(&[0..0, 0..3], &Tensor::from_vec(vec![9.0], &[1]));
+        assert_eq!(t.to_vec().iter().sum::<f32>(), 6.0);
+        // Clones keep their values (storage is copy-on-write)
+        let copy = t.clone();
+        t.copy_(&Tensor::from_vec(vec![-1.0], &[1]));
+        assert!(t.to_vec().iter().all(|&v| v == -1.0));
+        assert_eq!(copy.to_vec().iter().sum::<f32>(), 6.0);
+    }
+
+    #[test]
+    fn test_slice_assign_through_strided_view() {
+        let m = Tensor::from_vec((0..6).map(|v| v as f32).collect(), &[2, 3]);
+        let mut t = m.t();
+        t.slice_assign(&[2..3, 0..2], &Tensor::from_vec(vec![7.0, 8.0], &[1, 2]));
+        assert_eq!(t.shape(), &[3, 2]);
+        assert_eq!(t.to_vec(), vec![0.0, 3.0, 1.0, 4.0, 7.0, 8.0]);
+    }
+
+    #[test]
+    fn test_slice_assign_gradients() {
+        check_grad(
+            |t| {
+                let mut h = t[0].exp();
+                h.slice_assign(&[0..1, 1..3], &t[1]);
+                h.tanh()
+            },
+            &[
+                Tensor::from_vec(vec![0.5, -1.0, 0.2, 0.1, 0.3, -0.4], &[2, 3]),
+                Tensor::from_vec(vec![2.0, -0.5], &[2]),
+            ],
+        );
+        check_grad(
+            |t| {
+                let mut h = t[0].scalar_mul(2.0);
+                h.copy_(&t[1]);
+                h
+            },
+            &[
+                Tensor::from_vec(vec![0.5, -1.0], &[2]),
+                Tensor::from_vec(vec![3.0], &[1]),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_slice_assign_second_order() {
+        // The input gradient of a write is itself differentiable
+        let src = Tensor::from_vec(vec![2.0, -0.5], &[2]);
+        check_grad(
+            |t| {
+                let x = t[0].clone().requires_grad(true);
+                let mut h = x.exp();
+                h.slice_assign(&[0..1, 1..3], &src);
+                let y = h.tanh().sum();
+                grad(&y, std::slice::from_ref(&x), true).remove(0)
+            },
+            &[Tensor::from_vec(
+                vec![0.5, -1.0, 0.2, 0.1, 0.3, -0.4],
+                &[2, 3],
+            )],
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "slice_assign region [1..3, 0..2] is out of bounds for shape [2, 2]")]
+    fn test_slice_assign_out_of_bounds() {
+        let mut t = Tensor::zeros(&[2, 2]);
+        t.slice_assign(&[1..3, 0..2], &Tensor::zeros(&[1]));
+    }
+
+    #[test]
+    #[should_panic(expected = "add_ on a leaf that requires grad")]
+    fn test_leaf_update_with_grad_on() {
+        let mut w = Tensor::from_vec(vec![1.0], &[1]).requires_grad(true);
+        w.add_(&Tensor::from_vec(vec![1.0], &[1]));
+    }
+}
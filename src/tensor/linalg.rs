@@ -0,0 +1,413 @@
+use crate::autograd::record;
+use crate::tensor::{Tensor, matmul};
+
+/// An n x n mask: 1.0 where `keep(row, col)`, 0.0 elsewhere, broadcast
+/// over the batch dimensions of `shape`.
+fn mask(shape: &[usize], keep: impl Fn(usize, usize) -> f32) -> Tensor {
+    let n = shape[shape.len() - 1];
+    let data = (0..n * n).map(|i| keep(i / n, i % n)).collect();
+    Tensor::from_vec(data, &[n, n]).broadcast_to(shape)
+}
+
+/// Number of matrices in a batch of square matrices `[..., n, n]`, and n.
+///
+/// # Panics
+/// Panics if `t` is not a batch of square matrices.
+fn square_size(t: &Tensor, op: &str) -> (usize, usize) {
+    let nd = t.ndim();
+    assert!(
+        nd >= 2 && t.shape()[nd - 2] == t.shape()[nd - 1],
+        "{} requires a square matrix or a batch of them, got shape {:?}",
+        op,
+        t.shape()
+    );
+    let n = t.shape()[nd - 1];
+    (t.shape()[..nd - 2].iter().product(), n)
+}
+
+/// The shape with its last two dimensions swapped.
+fn swap_last(shape: &[usize]) -> Vec<usize> {
+    let mut out = shape.to_vec();
+    let nd = out.len();
+    out.swap(nd - 2, nd - 1);
+    out
+}
+
+impl Tensor {
+    /// Swap the last two dimensions: transpose every matrix of a batch
+    /// `[..., m, n]`, giving `[..., n, m]`. Same as [`Tensor::transpose`]
+    /// for 2D tensors.
+    ///
+    /// # Panics
+    /// Panics if the tensor has fewer than 2 dimensions.
+    pub fn matrix_transpose(&self) -> Tensor {
+        let nd = self.ndim();
+        assert!(
+            nd >= 2,
+            "matrix_transpose requires at least 2 dimensions, got {}D",
+            nd
+        );
+        let (m, n) = (self.shape()[nd - 2], self.shape()[nd - 1]);
+        let data = self.to_vec();
+        let mut out = vec![0.0; data.len()];
+        for (b, block) in data.chunks((m * n).max(1)).enumerate() {
+            let base = b * m * n;
+            for i in 0..m {
+                for j in 0..n {
+                    out[base + j * m + i] = block[i * n + j];
+                }
+            }
+        }
+        let out = Tensor::from_vec(out, &swap_last(self.shape()));
+        record(out, "matrix_transpose", &[self], |g| {
+            vec![g.matrix_transpose()]
+        })
+    }
+
+    /// Matrix multiplication over a batch: `[..., m, k] @ [..., k, n]`
+    /// gives `[..., m, n]`, one product per batch index. Same as
+    /// [`Tensor::matmul`] for 2D tensors.
+    ///
+    /// # Panics
+    /// - Panics if either tensor has fewer than 2 dimensions
+    /// - Panics if the batch dimensions differ (they are not broadcast)
+    /// - Panics if the inner dimensions don't match
+    ///
+    /// # Example
+    /// ```
+    /// use delta::tensor::Tensor;
+    /// let a = Tensor::from_vec(vec![1.0, 2.0, 3.0, 4.0], &[2, 1, 2]);
+    /// let b = Tensor::from_vec(vec![1.0, 1.0, 0.0, 1.0], &[2, 2, 1]);
+    /// let c = a.batch_matmul(&b);
+    /// assert_eq!(c.shape(), &[2, 1, 1]);
+    /// assert_eq!(c.to_vec(), vec![3.0, 4.0]);
+    /// ```
+    pub fn batch_matmul(&self, other: &Tensor) -> Tensor {
+        let (na, nb) = (self.ndim(), other.ndim());
+        assert!(
+            na >= 2 && nb >= 2 && self.shape()[..na - 2] == other.shape()[..nb - 2],
+            "batch_matmul requires matching batch dimensions, got {:?} and {:?}",
+            self.shape(),
+            other.shape()
+        );
+        let (m, k) = (self.shape()[na - 2], self.shape()[na - 1]);
+        let n = other.shape()[nb - 1];
+        assert_eq!(
+            k,
+            other.shape()[nb - 2],
+            "Inner dimensions must match: {:?} @ {:?}",
+            self.shape(),
+            other.shape()
+        );
+
+        let batch: usize = self.shape()[..na - 2].iter().product();
+        let (a, b) = (self.to_vec(), other.to_vec());
+        let mut out = Vec::with_capacity(batch * m * n);
+        for p in 0..batch {
+            let (a, b) = (
+                &a[p * m * k..(p + 1) * m * k],
+                &b[p * k * n..(p + 1) * k * n],
+            );
+            out.extend(matmul::matmul(a, b, m, k, n));
+        }
+        let mut shape = self.shape().to_vec();
+        shape[na - 1] = n;
+        let out = Tensor::from_vec(out, &shape);
+
+        let (a, b) = (self.clone(), other.clone());
+        record(out, "batch_matmul", &[self, other], move |g| {
+            vec![
+                g.batch_matmul(&b.matrix_transpose()),
+                a.matrix_transpose().batch_matmul(g),
+            ]
+        })
+    }
+
+    /// Lower triangle of a square matrix (diagonal included), zeros above.
+    /// Batches `[..., n, n]` are handled matrix by matrix.
+    ///
+    /// # Panics
+    /// Panics if the tensor is not a square matrix or a batch of them.
+    pub fn tril(&self) -> Tensor {
+        square_size(self, "tril");
+        self.mul(&mask(self.shape(), |i, j| if j <= i { 1.0 } else { 0.0 }))
+    }
+
+    /// Upper triangle of a square matrix (diagonal included), zeros below.
+    /// Batches `[..., n, n]` are handled matrix by matrix.
+    ///
+    /// # Panics
+    /// Panics if the tensor is not a square matrix or a batch of them.
+    pub fn triu(&self) -> Tensor {
+        square_size(self, "triu");
+        self.mul(&mask(self.shape(), |i, j| if j >= i { 1.0 } else { 0.0 }))
+    }
+
+    /// Cholesky factor of a symmetric positive-definite matrix: the lower
+    /// triangular L with L Lᵀ = self.
+    ///
+    /// Only the lower triangle is read. Computed in f64. A batch
+    /// `[..., n, n]` is factored matrix by matrix.
+    ///
+    /// # Gradient
+    /// For a loss gradient L̄, `Ā = ½ (S + Sᵀ)` with
+    /// `S = L⁻ᵀ Φ(Lᵀ L̄) L⁻¹`, where Φ keeps the lower triangle and halves
+    /// the diagonal (Murray, 2016). The result is symmetric: the input is
+    /// treated as a symmetric matrix, not as independent entries.
+    ///
+    /// # Panics
+    /// - Panics if the tensor is not a square matrix or a batch of them
+    /// - Panics if a matrix is not (numerically) positive definite
+    ///
+    /// # Example
+    /// ```
+    /// use delta::tensor::Tensor;
+    /// let a = Tensor::from_vec(vec![4.0, 2.0,
+    ///                               2.0, 5.0], &[2, 2]);
+    /// let l = a.cholesky();
+    /// assert_eq!(l.to_vec(), vec![2.0, 0.0,
+    ///                             1.0, 2.0]);
+    /// ```
+    pub fn cholesky(&self) -> Tensor {
+        let (batch, n) = square_size(self, "cholesky");
+        let a: Vec<f64> = self.to_vec().into_iter().map(f64::from).collect();
+
+        let mut l = vec![0.0f64; batch * n * n];
+        for p in 0..batch {
+            let (a, l) = (&a[p * n * n..], &mut l[p * n * n..]);
+            for i in 0..n {
+                for j in 0..=i {
+                    let s: f64 = (0..j).map(|k| l[i * n + k] * l[j * n + k]).sum();
+                    if i == j {
+                        let d = a[i * n + i] - s;
+                        assert!(
+                            d > 0.0 && d.is_finite(),
+                            "cholesky requires a positive-definite matrix (pivot {} is {})",
+                            i,
+                            d
+                        );
+                        l[i * n + i] = d.sqrt();
+                    } else {
+                        l[i * n + j] = (a[i * n + j] - s) / l[j * n + j];
+                    }
+                }
+            }
+        }
+        let data = l.into_iter().map(|v| v as f32).collect();
+        let out = Tensor::from_vec(data, self.shape());
+
+        let a = self.clone();
+        record(out, "cholesky", &[self], move |g| {
+            // Recomputed rather than captured, so the rule is differentiable
+            let l = a.cholesky();
+            let phi = mask(a.shape(), |i, j| match i.cmp(&j) {
+                std::cmp::Ordering::Greater => 1.0,
+                std::cmp::Ordering::Equal => 0.5,
+                std::cmp::Ordering::Less => 0.0,
+            });
+            let lt = l.matrix_transpose();
+            let p = lt.batch_matmul(g).mul(&phi);
+            // S = L⁻ᵀ P L⁻¹, as two triangular solves with Lᵀ
+            let x = lt.solve_triangular(&p, true);
+            let s = lt
+                .solve_triangular(&x.matrix_transpose(), true)
+                .matrix_transpose();
+            vec![s.add(&s.matrix_transpose()).scalar_mul(0.5)]
+        })
+    }
+
+    /// Solve `self @ X = b` for X, where `self` is a triangular matrix.
+    ///
+    /// `upper` says which triangle of `self` holds the matrix; the other
+    /// one is ignored. `b` is `[n, k]`: k right-hand sides solved at once.
+    /// For a batch of matrices `[..., n, n]`, `b` is `[..., n, k]` with the
+    /// same batch dimensions, and each system is solved separately.
+    ///
+    /// # Gradient
+    /// `b̄ = self⁻ᵀ X̄` and `Ā = -b̄ Xᵀ`, restricted to the triangle.
+    ///
+    /// # Panics
+    /// - Panics if `self` is not a square matrix (or batch of them), or
+    ///   `b` is not `[..., n, k]` with the same batch dimensions
+    /// - Panics if the diagonal has a zero (the matrix is singular)
+    ///
+    /// # Example
+    /// ```
+    /// use delta::tensor::Tensor;
+    /// let l = Tensor::from_vec(vec![2.0, 0.0,
+    ///                               1.0, 4.0], &[2, 2]);
+    /// let b = Tensor::from_vec(vec![2.0, 9.0], &[2, 1]);
+    /// assert_eq!(l.solve_triangular(&b, false).to_vec(), vec![1.0, 2.0]);
+    /// ```
+    pub fn solve_triangular(&self, b: &Tensor, upper: bool) -> Tensor {
+        let (batch, n) = square_size(self, "solve_triangular");
+        let nd = self.ndim();
+        assert!(
+            b.ndim() == nd
+                && b.shape()[..nd - 2] == self.shape()[..nd - 2]
+                && b.shape()[nd - 2] == n,
+            "solve_triangular expected a right-hand side of shape {:?} + [{}, k], got {:?}",
+            &self.shape()[..nd - 2],
+            n,
+            b.shape()
+        );
+        let k = b.shape()[nd - 1];
+        let a = self.to_vec();
+        let b_data = b.to_vec();
+
+        let mut x = vec![0.0f32; batch * n * k];
+        let rows: Vec<usize> = if upper {
+            (0..n).rev().collect()
+        } else {
+            (0..n).collect()
+        };
+        for p in 0..batch {
+            let (a, b_data) = (&a[p * n * n..], &b_data[p * n * k..]);
+            let x = &mut x[p * n * k..];
+            for &i in &rows {
+                let diag = a[i * n + i];
+                assert!(
+                    diag != 0.0,
+                    "solve_triangular: zero on the diagonal at {}",
+                    i
+                );
+                let solved = if upper { i + 1..n } else { 0..i };
+                for c in 0..k {
+                    let s: f64 = solved
+                        .clone()
+                        .map(|j| a[i * n + j] as f64 * x[j * k + c] as f64)
+                        .sum();
+                    x[i * k + c] = ((b_data[i * k + c] as f64 - s) / diag as f64) as f32;
+                }
+            }
+        }
+        let out = Tensor::from_vec(x, b.shape());
+
+        let (a, rhs) = (self.clone(), b.clone());
+        record(out, "solve_triangular", &[self, b], move |g| {
+            let x = a.solve_triangular(&rhs, upper);
+            let gb = a.matrix_transpose().solve_triangular(g, !upper);
+            let ga = gb.batch_matmul(&x.matrix_transpose()).neg();
+            let ga = if upper { ga.triu() } else { ga.tril() };
+            vec![ga, gb]
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::autograd::testing::{assert_close, check_grad};
+    use crate::tensor::Tensor;
+
+    fn spd() -> Tensor {
+        Tensor::from_vec(vec![4.0, 1.0, 0.5, 1.0, 3.0, -0.4, 0.5, -0.4, 2.0], &[3, 3])
+    }
+
+    #[test]
+    fn test_tril_triu() {
+        let t = Tensor::from_vec(vec![1.0, 2.0, 3.0, 4.0], &[2, 2]);
+        assert_eq!(t.tril().to_vec(), vec![1.0, 0.0, 3.0, 4.0]);
+        assert_eq!(t.triu().to_vec(), vec![1.0, 2.0, 0.0, 4.0]);
+    }
+
+    #[test]
+    fn test_cholesky_reconstructs() {
+        let a = spd();
+        let l = a.cholesky();
+        assert_eq!(l.triu().sub(&l.triu().tril()).to_vec(), vec![0.0; 9]);
+        assert_close(&l.matmul(&l.t()).to_vec(), &a.to_vec(), 1e-5);
+    }
+
+    #[test]
+    #[should_panic(expected = "positive-definite")]
+    fn test_cholesky_indefinite() {
+        Tensor::from_vec(vec![1.0, 2.0, 2.0, 1.0], &[2, 2]).cholesky();
+    }
+
+    #[test]
+    fn test_solve_triangular() {
+        let l = spd().cholesky();
+        let b = Tensor::from_vec(vec![1.0, -2.0, 0.5, 3.0, 2.0, 1.0], &[3, 2]);
+        let x = l.solve_triangular(&b, false);
+        assert_close(&l.matmul(&x).to_vec(), &b.to_vec(), 1e-5);
+
+        let u = l.t();
+        let y = u.solve_triangular(&b, true);
+        assert_close(&u.matmul(&y).to_vec(), &b.to_vec(), 1e-5);
+    }
+
+    /// Two 3 x 3 SPD matrices, [2, 3, 3]
+    fn spd_batch() -> Tensor {
+        let mut data = spd().to_vec();
+        data.extend(vec![2.0, -0.5, 0.0, -0.5, 1.0, 0.3, 0.0, 0.3, 1.5]);
+        Tensor::from_vec(data, &[2, 3, 3])
+    }
+
+    #[test]
+    fn test_matrix_transpose_and_batch_matmul() {
+        let a = Tensor::from_vec((0..12).map(|i| i as f32).collect(), &[2, 2, 3]);
+        let at = a.matrix_transpose();
+        assert_eq!(at.shape(), &[2, 3, 2]);
+        assert_eq!(at.get(&[1, 2, 0]), a.get(&[1, 0, 2]));
+
+        let c = a.batch_matmul(&at);
+        for p in 0..2 {
+            let block = |t: &Tensor, rows, cols| {
+                let start = p * rows * cols;
+                Tensor::from_vec(
+                    t.to_vec()[start..start + rows * cols].to_vec(),
+                    &[rows, cols],
+                )
+            };
+            let expected = block(&a, 2, 3).matmul(&block(&at, 3, 2));
+            assert_eq!(block(&c, 2, 2).to_vec(), expected.to_vec());
+        }
+        assert_eq!(
+            a.reshape(&[2, 6]).matrix_transpose().to_vec(),
+            a.reshape(&[2, 6]).t().to_vec()
+        );
+    }
+
+    #[test]
+    fn test_batched_cholesky_and_solve() {
+        let a = spd_batch();
+        let l = a.cholesky();
+        assert_eq!(l.shape(), &[2, 3, 3]);
+        assert_close(
+            &l.batch_matmul(&l.matrix_transpose()).to_vec(),
+            &a.to_vec(),
+            1e-5,
+        );
+        // The first matrix factors as it does on its own
+        assert_close(&l.to_vec()[..9], &spd().cholesky().to_vec(), 1e-5);
+
+        let b = Tensor::from_vec((0..12).map(|i| i as f32 - 4.0).collect(), &[2, 3, 2]);
+        let x = l.solve_triangular(&b, false);
+        assert_close(&l.batch_matmul(&x).to_vec(), &b.to_vec(), 1e-5);
+    }
+
+    #[test]
+    fn test_batched_gradients() {
+        let b = Tensor::from_vec((0..12).map(|i| 0.5 * i as f32 - 2.0).collect(), &[2, 3, 2]);
+        let l = spd_batch().cholesky();
+        check_grad(|t| t[0].batch_matmul(&t[1]), &[l.clone(), b.clone()]);
+        check_grad(|t| t[0].matrix_transpose(), std::slice::from_ref(&b));
+        check_grad(|t| t[0].solve_triangular(&t[1], false), &[l, b]);
+        let symmetric = |t: &Tensor| t.add(&t.matrix_transpose()).scalar_mul(0.5);
+        check_grad(|t| symmetric(&t[0]).cholesky(), &[spd_batch()]);
+    }
+
+    #[test]
+    fn test_gradients() {
+        let b = Tensor::from_vec(vec![1.0, -2.0, 0.5, 3.0, 2.0, 1.0], &[3, 2]);
+        let l = spd().cholesky();
+        check_grad(
+            |t| t[0].solve_triangular(&t[1], false),
+            &[l.clone(), b.clone()],
+        );
+        check_grad(|t| t[0].solve_triangular(&t[1], true), &[l.t(), b]);
+        // Symmetrize the input: cholesky's gradient is the symmetric one
+        check_grad(|t| t[0].add(&t[0].t()).scalar_mul(0.5).cholesky(), &[spd()]);
+        check_grad(|t| t[0].tril().add(&t[0].triu()), &[spd()]);
+    }
+}
@@ -0,0 +1,687 @@
+use std::f32::consts::{LN_2, LN_10};
+
+use crate::autograd::record;
+use crate::tensor::Tensor;
+
+/// 1 - x², shared by the derivatives of asin, acos and tanh.
+fn one_minus_square(x: &Tensor) -> Tensor {
+    x.mul(x).neg().scalar_add(1.0)
+}
+
+impl Tensor {
+    /// Element-wise exponential: e^x
+    pub fn exp(&self) -> Tensor {
+        let out = self.unary_op(f32::exp);
+        let x = self.clone();
+        record(out, "exp", &[self], move |g| vec![g.mul(&x.exp())])
+    }
+
+    /// Element-wise natural logarithm: ln(x)
+    ///
+    /// Follows IEEE semantics: ln(0) = -inf, ln(x < 0) = NaN.
+    pub fn ln(&self) -> Tensor {
+        let out = self.unary_op(f32::ln);
+        let x = self.clone();
+        record(out, "ln", &[self], move |g| vec![g.div(&x)])
+    }
+
+    /// Element-wise base-2 logarithm: log2(x)
+    pub fn log2(&self) -> Tensor {
+        let out = self.unary_op(f32::log2);
+        let x = self.clone();
+        record(out, "log2", &[self], move |g| {
+            vec![g.div(&x.scalar_mul(LN_2))]
+        })
+    }
+
+    /// Element-wise base-10 logarithm: log10(x)
+    pub fn log10(&self) -> Tensor {
+        let out = self.unary_op(f32::log10);
+        let x = self.clone();
+        record(out, "log10", &[self], move |g| {
+            vec![g.div(&x.scalar_mul(LN_10))]
+        })
+    }
+
+    /// Element-wise square root: √x
+    ///
+    /// Negative inputs give NaN.
+    pub fn sqrt(&self) -> Tensor {
+        let out = self.unary_op(f32::sqrt);
+        let x = self.clone();
+        record(out, "sqrt", &[self], move |g| {
+            vec![g.div(&x.sqrt().scalar_mul(2.0))]
+        })
+    }
+
+    /// Element-wise reciprocal: 1 / x
+    ///
+    /// Zero maps to ±inf following the sign of the zero.
+    pub fn recip(&self) -> Tensor {
+        let out = self.unary_op(f32::recip);
+        let x = self.clone();
+        record(out, "recip", &[self], move |g| {
+            vec![g.div(&x.powi(2)).neg()]
+        })
+    }
+
+    /// Element-wise reciprocal square root: 1 / √x
+    ///
+    /// Zero gives +inf and negative inputs give NaN. See
+    /// [`Tensor::rsqrt_eps`] for the guarded form used in normalization.
+    pub fn rsqrt(&self) -> Tensor {
+        let out = self.unary_op(|x| x.sqrt().recip());
+        let x = self.clone();
+        record(out, "rsqrt", &[self], move |g| {
+            vec![g.mul(&x.powf(-1.5)).scalar_mul(-0.5)]
+        })
+    }
+
+    /// Reciprocal square root with a stabilizing epsilon: 1 / √(x + eps)
+    ///
+    /// The form used by RMSNorm/LayerNorm, where `x` is a mean of squares
+    /// that can be exactly zero. Doing the add inside one op saves a full
+    /// intermediate tensor compared to `x.scalar_add(eps).rsqrt()`.
+    ///
+    /// # Example
+    /// ```
+    /// use delta::tensor::Tensor;
+    /// let ms = Tensor::from_vec(vec![0.0, 3.0], &[2]);
+    /// assert_eq!(ms.rsqrt_eps(1.0).to_vec(), vec![1.0, 0.5]);
+    /// ```
+    pub fn rsqrt_eps(&self, eps: f32) -> Tensor {
+        let out = self.unary_op(|x| (x + eps).sqrt().recip());
+        let x = self.clone();
+        record(out, "rsqrt_eps", &[self], move |g| {
+            vec![g.mul(&x.scalar_add(eps).powf(-1.5)).scalar_mul(-0.5)]
+        })
+    }
+
+    /// Raise every element to a float power: x^s
+    ///
+    /// # Example
+    /// ```
+    /// use delta::tensor::Tensor;
+    /// let t = Tensor::from_vec(vec![1.0, 4.0, 9.0], &[3]);
+    /// assert_eq!(t.powf(0.5).to_vec(), vec![1.0, 2.0, 3.0]);
+    /// ```
+    pub fn powf(&self, s: f32) -> Tensor {
+        let out = self.unary_op(|x| x.powf(s));
+        let x = self.clone();
+        record(out, "powf", &[self], move |g| {
+            vec![g.mul(&x.powf(s - 1.0)).scalar_mul(s)]
+        })
+    }
+
+    /// Raise every element to an integer power: x^n
+    ///
+    /// Faster and more accurate than [`Tensor::powf`] for integer exponents,
+    /// and well-defined for negative bases.
+    ///
+    /// # Example
+    /// ```
+    /// use delta::tensor::Tensor;
+    /// let t = Tensor::from_vec(vec![-2.0, 3.0], &[2]);
+    /// assert_eq!(t.powi(3).to_vec(), vec![-8.0, 27.0]);
+    /// ```
+    pub fn powi(&self, n: i32) -> Tensor {
+        let out = self.unary_op(|x| x.powi(n));
+        let x = self.clone();
+        record(out, "powi", &[self], move |g| {
+            if n == 0 {
+                // Constant 1; computing 0 * x^-1 would give NaN at x = 0
+                return vec![Tensor::zeros(g.shape())];
+            }
+            vec![g.mul(&x.powi(n - 1)).scalar_mul(n as f32)]
+        })
+    }
+
+    /// Element-wise power with per-element exponents: self^other
+    ///
+    /// # Gradient
+    /// With respect to the exponent the derivative is `a^b * ln(a)`, which
+    /// is only real for positive bases; elsewhere that gradient is NaN.
+    ///
+    /// # Panics
+    /// Panics if shapes do not match.
+    ///
+    /// # Example
+    /// ```
+    /// use delta::tensor::Tensor;
+    /// let base = Tensor::from_vec(vec![2.0, 3.0, 4.0], &[3]);
+    /// let exp = Tensor::from_vec(vec![3.0, 2.0, 0.5], &[3]);
+    /// assert_eq!(base.pow(&exp).to_vec(), vec![8.0, 9.0, 2.0]);
+    /// ```
+    pub fn pow(&self, other: &Tensor) -> Tensor {
+        let out = self.binary_op(other, f32::powf);
+        let (a, b) = (self.clone(), other.clone());
+        record(out, "pow", &[self, other], move |g| {
+            let da = g.mul(&b).mul(&a.pow(&b.scalar_add(-1.0)));
+            let db = g.mul(&a.pow(&b)).mul(&a.ln());
+            vec![da, db]
+        })
+    }
+
+    /// Element-wise sine (radians)
+    pub fn sin(&self) -> Tensor {
+        let out = self.unary_op(f32::sin);
+        let x = self.clone();
+        record(out, "sin", &[self], move |g| vec![g.mul(&x.cos())])
+    }
+
+    /// Element-wise cosine (radians)
+    pub fn cos(&self) -> Tensor {
+        let out = self.unary_op(f32::cos);
+        let x = self.clone();
+        record(out, "cos", &[self], move |g| vec![g.mul(&x.sin()).neg()])
+    }
+
+    /// Element-wise tangent (radians)
+    pub fn tan(&self) -> Tensor {
+        let out = self.unary_op(f32::tan);
+        let x = self.clone();
+        record(out, "tan", &[self], move |g| vec![g.div(&x.cos().powi(2))])
+    }
+
+    /// Element-wise arcsine, in [-π/2, π/2]
+    ///
+    /// Inputs outside [-1, 1] give NaN.
+    pub fn asin(&self) -> Tensor {
+        let out = self.unary_op(f32::asin);
+        let x = self.clone();
+        record(out, "asin", &[self], move |g| {
+            vec![g.mul(&one_minus_square(&x).rsqrt())]
+        })
+    }
+
+    /// Element-wise arccosine, in [0, π]
+    ///
+    /// Inputs outside [-1, 1] give NaN.
+    pub fn acos(&self) -> Tensor {
+        let out = self.unary_op(f32::acos);
+        let x = self.clone();
+        record(out, "acos", &[self], move |g| {
+            vec![g.mul(&one_minus_square(&x).rsqrt()).neg()]
+        })
+    }
+
+    /// Element-wise arctangent, in [-π/2, π/2]
+    pub fn atan(&self) -> Tensor {
+        let out = self.unary_op(f32::atan);
+        let x = self.clone();
+        record(out, "atan", &[self], move |g| {
+            vec![g.div(&x.powi(2).scalar_add(1.0))]
+        })
+    }
+
+    /// Element-wise four-quadrant arctangent of self / other, in [-π, π]
+    ///
+    /// `self` holds the y coordinates and `other` the x coordinates, so the
+    /// result is the angle of the point (x, y). Unlike `(y / x).atan()`, the
+    /// signs of both arguments are used to pick the correct quadrant.
+    ///
+    /// # Panics
+    /// Panics if shapes do not match.
+    ///
+    /// # Example
+    /// ```
+    /// use delta::tensor::Tensor;
+    /// let y = Tensor::from_vec(vec![1.0, 1.0], &[2]);
+    /// let x = Tensor::from_vec(vec![1.0, -1.0], &[2]);
+    /// let angle = y.atan2(&x);
+    /// assert!((angle.get(&[0]) - std::f32::consts::FRAC_PI_4).abs() < 1e-6);
+    /// assert!((angle.get(&[1]) - 3.0 * std::f32::consts::FRAC_PI_4).abs() < 1e-6);
+    /// ```
+    pub fn atan2(&self, other: &Tensor) -> Tensor {
+        let out = self.binary_op(other, f32::atan2);
+        let (y, x) = (self.clone(), other.clone());
+        record(out, "atan2", &[self, other], move |g| {
+            // d/dy = x / (x² + y²), d/dx = -y / (x² + y²)
+            let r2 = x.powi(2).add(&y.powi(2));
+            vec![g.mul(&x).div(&r2), g.mul(&y).div(&r2).neg()]
+        })
+    }
+
+    /// Element-wise hyperbolic sine
+    pub fn sinh(&self) -> Tensor {
+        let out = self.unary_op(f32::sinh);
+        let x = self.clone();
+        record(out, "sinh", &[self], move |g| vec![g.mul(&x.cosh())])
+    }
+
+    /// Element-wise hyperbolic cosine
+    pub fn cosh(&self) -> Tensor {
+        let out = self.unary_op(f32::cosh);
+        let x = self.clone();
+        record(out, "cosh", &[self], move |g| vec![g.mul(&x.sinh())])
+    }
+
+    /// Element-wise hyperbolic tangent, in (-1, 1)
+    pub fn tanh(&self) -> Tensor {
+        let out = self.unary_op(f32::tanh);
+        let x = self.clone();
+        record(out, "tanh", &[self], move |g| {
+            vec![g.mul(&one_minus_square(&x.tanh()))]
+        })
+    }
+
+    /// Element-wise absolute value: |x|
+    pub fn abs(&self) -> Tensor {
+        let out = self.unary_op(f32::abs);
+        let x = self.clone();
+        record(out, "abs", &[self], move |g| vec![g.mul(&x.sign())])
+    }
+
+    /// Element-wise sign: -1.0, 0.0 or 1.0
+    ///
+    /// Unlike `f32::signum`, zero maps to 0.0 (so `x == sign(x) * |x|`
+    /// holds everywhere), and NaN stays NaN.
+    pub fn sign(&self) -> Tensor {
+        let out = self.unary_op(|x| {
+            if x > 0.0 {
+                1.0
+            } else if x < 0.0 {
+                -1.0
+            } else {
+                x // ±0.0 or NaN
+            }
+        });
+        // Piecewise constant: the gradient is zero wherever it exists
+        record(out, "sign", &[self], |g| vec![Tensor::zeros(g.shape())])
+    }
+
+    /// Round toward negative infinity
+    pub fn floor(&self) -> Tensor {
+        let out = self.unary_op(f32::floor);
+        record(out, "floor", &[self], |g| vec![Tensor::zeros(g.shape())])
+    }
+
+    /// Round toward positive infinity
+    pub fn ceil(&self) -> Tensor {
+        let out = self.unary_op(f32::ceil);
+        record(out, "ceil", &[self], |g| vec![Tensor::zeros(g.shape())])
+    }
+
+    /// Round to the nearest integer, ties to even
+    ///
+    /// 0.5 -> 0.0, 1.5 -> 2.0, 2.5 -> 2.0. Rounding half to even is unbiased
+    /// (ties don't all drift upward), which matters for quantization. This
+    /// differs from `f32::round`, which rounds ties away from zero.
+    pub fn round(&self) -> Tensor {
+        let out = self.unary_op(f32::round_ties_even);
+        record(out, "round", &[self], |g| vec![Tensor::zeros(g.shape())])
+    }
+
+    /// Round toward zero (drop the fractional part)
+    pub fn trunc(&self) -> Tensor {
+        let out = self.unary_op(f32::trunc);
+        record(out, "trunc", &[self], |g| vec![Tensor::zeros(g.shape())])
+    }
+
+    /// Fractional part: x - trunc(x)
+    ///
+    /// Takes the sign of x, e.g. fract(-1.25) = -0.25.
+    pub fn fract(&self) -> Tensor {
+        let out = self.unary_op(f32::fract);
+        record(out, "fract", &[self], |g| vec![g.clone()])
+    }
+
+    /// 1.0 where the element is NaN, 0.0 otherwise
+    pub fn isnan(&self) -> Tensor {
+        self.unary_op(|x| if x.is_nan() { 1.0 } else { 0.0 })
+    }
+
+    /// 1.0 where the element is +inf or -inf, 0.0 otherwise
+    pub fn isinf(&self) -> Tensor {
+        self.unary_op(|x| if x.is_infinite() { 1.0 } else { 0.0 })
+    }
+
+    /// Whether any element is NaN.
+    pub fn has_nan(&self) -> bool {
+        self.to_vec().iter().any(|x| x.is_nan())
+    }
+
+    /// Whether any element is +inf or -inf.
+    pub fn has_inf(&self) -> bool {
+        self.to_vec().iter().any(|x| x.is_infinite())
+    }
+
+    /// Replace NaN, +inf and -inf with the given finite values.
+    ///
+    /// Finite elements pass through unchanged.
+    ///
+    /// # Example
+    /// ```
+    /// use delta::tensor::Tensor;
+    /// let t = Tensor::from_vec(vec![f32::NAN, f32::INFINITY, f32::NEG_INFINITY, 1.5], &[4]);
+    /// let clean = t.nan_to_num(0.0, f32::MAX, f32::MIN);
+    /// assert_eq!(clean.to_vec(), vec![0.0, f32::MAX, f32::MIN, 1.5]);
+    /// ```
+    pub fn nan_to_num(&self, nan: f32, posinf: f32, neginf: f32) -> Tensor {
+        let out = self.unary_op(|x| {
+            if x.is_nan() {
+                nan
+            } else if x == f32::INFINITY {
+                posinf
+            } else if x == f32::NEG_INFINITY {
+                neginf
+            } else {
+                x
+            }
+        });
+        let x = self.clone();
+        record(out, "nan_to_num", &[self], move |g| {
+            vec![g.mul(&x.unary_op(|v| if v.is_finite() { 1.0 } else { 0.0 }))]
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::autograd::testing::{assert_close, check_grad, check_named_grad};
+
+    type UnaryOp = fn(&Tensor) -> Tensor;
+
+    #[test]
+    fn test_exp() {
+        let t = Tensor::from_vec(vec![0.0, 1.0, -1.0], &[3]);
+        assert_close(
+            &t.exp().to_vec(),
+            &[1.0, std::f32::consts::E, 1.0 / std::f32::consts::E],
+            1e-5,
+        );
+    }
+
+    #[test]
+    fn test_ln() {
+        let t = Tensor::from_vec(vec![1.0, std::f32::consts::E], &[2]);
+        assert_close(&t.ln().to_vec(), &[0.0, 1.0], 1e-5);
+
+        let edge = Tensor::from_vec(vec![0.0, -1.0], &[2]).ln();
+        assert_eq!(edge.get(&[0]), f32::NEG_INFINITY);
+        assert!(edge.get(&[1]).is_nan());
+    }
+
+    #[test]
+    fn test_exp_ln_roundtrip() {
+        let t = Tensor::from_vec(vec![0.5, 1.0, 2.0, 3.0], &[2, 2]);
+        let r = t.exp().ln();
+        assert_eq!(r.shape(), &[2, 2]);
+        assert_close(&r.to_vec(), &t.to_vec(), 1e-5);
+    }
+
+    #[test]
+    fn test_log2_log10() {
+        let t = Tensor::from_vec(vec![1.0, 8.0, 1000.0], &[3]);
+        assert_close(&t.log2().to_vec(), &[0.0, 3.0, 1000f32.log2()], 1e-5);
+        assert_close(&t.log10().to_vec(), &[0.0, 8f32.log10(), 3.0], 1e-5);
+    }
+
+    #[test]
+    fn test_sqrt() {
+        let t = Tensor::from_vec(vec![0.0, 4.0, 2.25], &[3]);
+        assert_close(&t.sqrt().to_vec(), &[0.0, 2.0, 1.5], 1e-5);
+        assert!(Tensor::from_vec(vec![-1.0], &[1]).sqrt().get(&[0]).is_nan());
+    }
+
+    #[test]
+    fn test_powf() {
+        let t = Tensor::from_vec(vec![1.0, 2.0, 4.0], &[3]);
+        assert_close(&t.powf(2.0).to_vec(), &[1.0, 4.0, 16.0], 1e-5);
+        assert_close(&t.powf(-1.0).to_vec(), &[1.0, 0.5, 0.25], 1e-5);
+    }
+
+    #[test]
+    fn test_powi() {
+        let t = Tensor::from_vec(vec![-2.0, 0.5, 3.0], &[3]);
+        assert_eq!(t.powi(2).to_vec(), vec![4.0, 0.25, 9.0]);
+        assert_eq!(t.powi(0).to_vec(), vec![1.0, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn test_pow() {
+        let base = Tensor::from_vec(vec![2.0, 9.0, 5.0, 10.0], &[2, 2]);
+        let exp = Tensor::from_vec(vec![10.0, 0.5, 0.0, -1.0], &[2, 2]);
+        let r = base.pow(&exp);
+        assert_eq!(r.shape(), &[2, 2]);
+        assert_close(&r.to_vec(), &[1024.0, 3.0, 1.0, 0.1], 1e-5);
+    }
+
+    #[test]
+    #[should_panic(expected = "Shape mismatch")]
+    fn test_pow_shape_mismatch() {
+        let a = Tensor::zeros(&[2]);
+        let b = Tensor::zeros(&[3]);
+        a.pow(&b);
+    }
+
+    #[test]
+    fn test_sin_cos_tan() {
+        use std::f32::consts::{FRAC_PI_2, FRAC_PI_4, PI};
+        let t = Tensor::from_vec(vec![0.0, FRAC_PI_2, PI], &[3]);
+        assert_close(&t.sin().to_vec(), &[0.0, 1.0, 0.0], 1e-5);
+        assert_close(&t.cos().to_vec(), &[1.0, 0.0, -1.0], 1e-5);
+
+        let t = Tensor::from_vec(vec![0.0, FRAC_PI_4], &[2]);
+        assert_close(&t.tan().to_vec(), &[0.0, 1.0], 1e-5);
+    }
+
+    #[test]
+    fn test_inverse_trig() {
+        use std::f32::consts::{FRAC_PI_2, FRAC_PI_4, PI};
+        let t = Tensor::from_vec(vec![-1.0, 0.0, 1.0], &[3]);
+        assert_close(&t.asin().to_vec(), &[-FRAC_PI_2, 0.0, FRAC_PI_2], 1e-5);
+        assert_close(&t.acos().to_vec(), &[PI, FRAC_PI_2, 0.0], 1e-5);
+        assert_close(&t.atan().to_vec(), &[-FRAC_PI_4, 0.0, FRAC_PI_4], 1e-5);
+
+        assert!(Tensor::from_vec(vec![2.0], &[1]).asin().get(&[0]).is_nan());
+    }
+
+    #[test]
+    fn test_atan2_quadrants() {
+        use std::f32::consts::FRAC_PI_4;
+        let y = Tensor::from_vec(vec![1.0, 1.0, -1.0, -1.0], &[4]);
+        let x = Tensor::from_vec(vec![1.0, -1.0, -1.0, 1.0], &[4]);
+        assert_close(
+            &y.atan2(&x).to_vec(),
+            &[FRAC_PI_4, 3.0 * FRAC_PI_4, -3.0 * FRAC_PI_4, -FRAC_PI_4],
+            1e-5,
+        );
+    }
+
+    #[test]
+    fn test_hyperbolic() {
+        let t = Tensor::from_vec(vec![0.0, 1.0], &[2]);
+        assert_close(&t.sinh().to_vec(), &[0.0, 1f32.sinh()], 1e-5);
+        assert_close(&t.cosh().to_vec(), &[1.0, 1f32.cosh()], 1e-5);
+        assert_close(&t.tanh().to_vec(), &[0.0, 1f32.tanh()], 1e-5);
+
+        // tanh saturates instead of overflowing
+        let big = Tensor::from_vec(vec![-100.0, 100.0], &[2]);
+        assert_eq!(big.tanh().to_vec(), vec![-1.0, 1.0]);
+    }
+
+    #[test]
+    fn test_abs() {
+        let t = Tensor::from_vec(vec![-2.5, 0.0, 3.0], &[3]);
+        assert_eq!(t.abs().to_vec(), vec![2.5, 0.0, 3.0]);
+    }
+
+    #[test]
+    fn test_sign() {
+        let t = Tensor::from_vec(vec![-2.5, 0.0, 3.0], &[3]);
+        assert_eq!(t.sign().to_vec(), vec![-1.0, 0.0, 1.0]);
+        assert!(
+            Tensor::from_vec(vec![f32::NAN], &[1])
+                .sign()
+                .get(&[0])
+                .is_nan()
+        );
+    }
+
+    #[test]
+    fn test_floor_ceil_trunc() {
+        let t = Tensor::from_vec(vec![-1.5, -0.2, 0.2, 1.5], &[4]);
+        assert_eq!(t.floor().to_vec(), vec![-2.0, -1.0, 0.0, 1.0]);
+        assert_eq!(t.ceil().to_vec(), vec![-1.0, -0.0, 1.0, 2.0]);
+        assert_eq!(t.trunc().to_vec(), vec![-1.0, -0.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn test_round_ties_even() {
+        let t = Tensor::from_vec(vec![0.5, 1.5, 2.5, -0.5, -1.5, 2.4, 2.6], &[7]);
+        assert_eq!(
+            t.round().to_vec(),
+            vec![0.0, 2.0, 2.0, -0.0, -2.0, 2.0, 3.0]
+        );
+    }
+
+    #[test]
+    fn test_fract() {
+        let t = Tensor::from_vec(vec![1.25, -1.25, 3.0], &[3]);
+        assert_eq!(t.fract().to_vec(), vec![0.25, -0.25, 0.0]);
+    }
+
+    #[test]
+    fn test_recip() {
+        let t = Tensor::from_vec(vec![2.0, -4.0, 0.5], &[3]);
+        assert_eq!(t.recip().to_vec(), vec![0.5, -0.25, 2.0]);
+
+        let zeros = Tensor::from_vec(vec![0.0, -0.0], &[2]).recip();
+        assert_eq!(zeros.to_vec(), vec![f32::INFINITY, f32::NEG_INFINITY]);
+    }
+
+    #[test]
+    fn test_rsqrt() {
+        let t = Tensor::from_vec(vec![4.0, 0.25, 2.0], &[3]);
+        assert_close(&t.rsqrt().to_vec(), &[0.5, 2.0, 1.0 / 2f32.sqrt()], 1e-5);
+
+        let edge = Tensor::from_vec(vec![0.0, -1.0], &[2]).rsqrt();
+        assert_eq!(edge.get(&[0]), f32::INFINITY);
+        assert!(edge.get(&[1]).is_nan());
+    }
+
+    #[test]
+    fn test_rsqrt_eps() {
+        let t = Tensor::from_vec(vec![0.0, 3.0, 8.0], &[3]);
+        let r = t.rsqrt_eps(1.0);
+        assert_close(&r.to_vec(), &[1.0, 0.5, 1.0 / 3.0], 1e-5);
+
+        // The epsilon keeps zero inputs finite
+        assert!(
+            Tensor::zeros(&[2])
+                .rsqrt_eps(1e-6)
+                .to_vec()
+                .iter()
+                .all(|x| x.is_finite())
+        );
+    }
+
+    #[test]
+    fn test_isnan_isinf() {
+        let t = Tensor::from_vec(
+            vec![1.0, f32::NAN, f32::INFINITY, f32::NEG_INFINITY, 0.0],
+            &[5],
+        );
+        assert_eq!(t.isnan().to_vec(), vec![0.0, 1.0, 0.0, 0.0, 0.0]);
+        assert_eq!(t.isinf().to_vec(), vec![0.0, 0.0, 1.0, 1.0, 0.0]);
+        assert!(t.has_nan() && t.has_inf());
+        let finite = Tensor::from_vec(vec![1.0, -2.0], &[2]);
+        assert!(!finite.has_nan() && !finite.has_inf());
+    }
+
+    #[test]
+    fn test_isnan_finds_diverged_entries() {
+        // ln of a negative number is where NaNs usually sneak in
+        let t = Tensor::from_vec(vec![1.0, -1.0, 2.0, -3.0], &[2, 2]).ln();
+        assert_eq!(t.isnan().count_nonzero(1).to_vec(), vec![1.0, 1.0]);
+    }
+
+    #[test]
+    fn test_nan_to_num() {
+        let t = Tensor::from_vec(
+            vec![f32::NAN, f32::INFINITY, f32::NEG_INFINITY, -2.5],
+            &[2, 2],
+        );
+        let r = t.nan_to_num(0.0, 100.0, -100.0);
+        assert_eq!(r.shape(), &[2, 2]);
+        assert_eq!(r.to_vec(), vec![0.0, 100.0, -100.0, -2.5]);
+    }
+
+    #[test]
+    fn test_grad_unary() {
+        // Inputs inside every op's domain (asin/acos need |x| < 1)
+        let x = Tensor::from_vec(vec![0.2, 0.5, 0.9, 0.35], &[2, 2]);
+        let ops: [(&str, UnaryOp); 20] = [
+            ("exp", Tensor::exp),
+            ("ln", Tensor::ln),
+            ("log2", Tensor::log2),
+            ("log10", Tensor::log10),
+            ("sqrt", Tensor::sqrt),
+            ("recip", Tensor::recip),
+            ("rsqrt", Tensor::rsqrt),
+            ("rsqrt_eps", |t| t.rsqrt_eps(0.1)),
+            ("powf", |t| t.powf(2.5)),
+            ("powi", |t| t.powi(3)),
+            ("sin", Tensor::sin),
+            ("cos", Tensor::cos),
+            ("tan", Tensor::tan),
+            ("asin", Tensor::asin),
+            ("acos", Tensor::acos),
+            ("atan", Tensor::atan),
+            ("sinh", Tensor::sinh),
+            ("cosh", Tensor::cosh),
+            ("tanh", Tensor::tanh),
+            ("fract", |t| t.scalar_mul(3.0).fract()),
+        ];
+        for (name, op) in ops {
+            check_named_grad(name, |x| op(&x[0]), std::slice::from_ref(&x));
+        }
+    }
+
+    #[test]
+    fn test_grad_abs() {
+        let x = Tensor::from_vec(vec![-1.5, 2.0], &[2]);
+        check_grad(|x| x[0].abs(), &[x]);
+    }
+
+    #[test]
+    fn test_grad_powi_zero() {
+        let x = Tensor::from_vec(vec![0.0, 2.0], &[2]).requires_grad(true);
+        x.powi(0).sum().backward();
+        assert_eq!(x.grad().unwrap().to_vec(), vec![0.0, 0.0]);
+    }
+
+    #[test]
+    fn test_grad_pow() {
+        let base = Tensor::from_vec(vec![0.5, 1.5, 2.0], &[3]);
+        let exp = Tensor::from_vec(vec![2.0, -1.0, 0.5], &[3]);
+        check_grad(|t| t[0].pow(&t[1]), &[base, exp]);
+    }
+
+    #[test]
+    fn test_grad_atan2() {
+        let y = Tensor::from_vec(vec![1.0, -0.5, 2.0], &[3]);
+        let x = Tensor::from_vec(vec![0.5, -1.0, -2.0], &[3]);
+        check_grad(|t| t[0].atan2(&t[1]), &[y, x]);
+    }
+
+    #[test]
+    fn test_grad_rounding_is_zero() {
+        let x = Tensor::from_vec(vec![-1.3, 0.4, 2.7], &[3]).requires_grad(true);
+        let y = x.floor().add(&x.ceil()).add(&x.round()).add(&x.trunc());
+        y.add(&x.sign()).sum().backward();
+        assert_eq!(x.grad().unwrap().to_vec(), vec![0.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn test_grad_nan_to_num() {
+        let x = Tensor::from_vec(vec![f32::NAN, 1.0, f32::INFINITY], &[3]).requires_grad(true);
+        x.nan_to_num(0.0, 1.0, -1.0)
+            .scalar_mul(2.0)
+            .sum()
+            .backward();
+        assert_eq!(x.grad().unwrap().to_vec(), vec![0.0, 2.0, 0.0]);
+    }
+}
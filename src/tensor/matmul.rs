@@ -0,0 +1,253 @@
+//! Matrix-product kernels on contiguous row-major data, shared by
+//! [`Tensor::matmul`](crate::tensor::Tensor::matmul) and
+//! [`Tensor::batch_matmul`](crate::tensor::Tensor::batch_matmul).
+
+use std::ops::Range;
+
+/// Largest dimension handled by the unrolled kernels.
+///
+/// Tiny products (3x3 rotations, 4x4 homogeneous transforms) are common
+/// and spend most of their time in loop bookkeeping; past this size the
+/// unrolled bodies only grow the code.
+const SMALL: usize = 8;
+
+/// Smallest dimension for which every side is split into cache-sized
+/// blocks.
+///
+/// Below it the operands mostly fit in cache anyway and the recursion
+/// only adds overhead.
+const LARGE: usize = 128;
+
+/// Side of the blocks the recursive kernel bottoms out at: three 64x64
+/// f32 blocks take 48 KiB, about an L1 (or small L2) cache.
+const BLOCK: usize = 64;
+
+/// `a` `[m, k]` times `b` `[k, n]`, as a row-major `[m, n]` vector.
+///
+/// Picks an unrolled kernel when every dimension is at most 8, the
+/// recursive blocked one when every dimension is at least 128, and the
+/// general loop otherwise. Each output element sums its products in order
+/// of the inner index in all three, so the choice never changes the
+/// result. (Strassen's algorithm would save multiplications on the largest
+/// products, but reorders the sums and loses accuracy in f32.)
+pub(crate) fn matmul(a: &[f32], b: &[f32], m: usize, k: usize, n: usize) -> Vec<f32> {
+    debug_assert!(a.len() == m * k && b.len() == k * n);
+    if m <= SMALL && k <= SMALL && n <= SMALL {
+        small(a, b, m, k, n)
+    } else if m >= LARGE && k >= LARGE && n >= LARGE {
+        blocked(a, b, m, k, n)
+    } else {
+        general(a, b, m, k, n)
+    }
+}
+
+/// The i-k-j loop: the innermost loop walks rows of `b` and of the output
+/// contiguously.
+fn general(a: &[f32], b: &[f32], m: usize, k: usize, n: usize) -> Vec<f32> {
+    let mut out = vec![0.0; m * n];
+    for i in 0..m {
+        let row = &mut out[i * n..(i + 1) * n];
+        for p in 0..k {
+            let a_ip = a[i * k + p];
+            for (o, &b_pj) in row.iter_mut().zip(&b[p * n..(p + 1) * n]) {
+                *o += a_ip * b_pj;
+            }
+        }
+    }
+    out
+}
+
+/// Operands of a blocked product: row-major `a` `[m, k]` and `b` `[k, n]`,
+/// and a scratch buffer reused by every leaf block.
+struct Blocked<'a> {
+    a: &'a [f32],
+    b: &'a [f32],
+    k: usize,
+    n: usize,
+    packed: Vec<f32>,
+}
+
+/// Cache-oblivious recursive matmul: halve the largest of the three
+/// dimensions until all are at most [`BLOCK`], then multiply the blocks.
+///
+/// Each leaf copies its block of `b` into one contiguous scratch buffer,
+/// allocated once, so the inner loop streams through memory that is
+/// already in cache whatever the row length of `b`.
+fn blocked(a: &[f32], b: &[f32], m: usize, k: usize, n: usize) -> Vec<f32> {
+    let mut out = vec![0.0; m * n];
+    let mut ctx = Blocked {
+        a,
+        b,
+        k,
+        n,
+        packed: Vec::with_capacity(BLOCK * BLOCK),
+    };
+    ctx.recurse(&mut out, 0..m, 0..k, 0..n);
+    out
+}
+
+impl Blocked<'_> {
+    /// `out[rows, cols] += a[rows, inner] @ b[inner, cols]`.
+    fn recurse(
+        &mut self,
+        out: &mut [f32],
+        rows: Range<usize>,
+        inner: Range<usize>,
+        cols: Range<usize>,
+    ) {
+        let (dm, dk, dn) = (rows.len(), inner.len(), cols.len());
+        if dm.max(dk).max(dn) <= BLOCK {
+            return self.leaf(out, rows, inner, cols);
+        }
+        // Splitting the inner range keeps each element's sum in order, as
+        // long as the first half is added before the second
+        if dm >= dk && dm >= dn {
+            let mid = rows.start + dm / 2;
+            self.recurse(out, rows.start..mid, inner.clone(), cols.clone());
+            self.recurse(out, mid..rows.end, inner, cols);
+        } else if dk >= dn {
+            let mid = inner.start + dk / 2;
+            self.recurse(out, rows.clone(), inner.start..mid, cols.clone());
+            self.recurse(out, rows, mid..inner.end, cols);
+        } else {
+            let mid = cols.start + dn / 2;
+            self.recurse(out, rows.clone(), inner.clone(), cols.start..mid);
+            self.recurse(out, rows, inner, mid..cols.end);
+        }
+    }
+
+    fn leaf(
+        &mut self,
+        out: &mut [f32],
+        rows: Range<usize>,
+        inner: Range<usize>,
+        cols: Range<usize>,
+    ) {
+        let (k, n, dn) = (self.k, self.n, cols.len());
+        self.packed.clear();
+        for p in inner.clone() {
+            self.packed
+                .extend_from_slice(&self.b[p * n + cols.start..p * n + cols.end]);
+        }
+        for i in rows {
+            let dst = &mut out[i * n + cols.start..i * n + cols.end];
+            for (p, src) in inner.clone().zip(self.packed.chunks_exact(dn)) {
+                let a_ip = self.a[i * k + p];
+                for (o, &b_pj) in dst.iter_mut().zip(src) {
+                    *o += a_ip * b_pj;
+                }
+            }
+        }
+    }
+}
+
+/// Dispatch to [`fixed`] on the inner and column dimensions, so that the
+/// whole body of each output row is unrolled.
+fn small(a: &[f32], b: &[f32], m: usize, k: usize, n: usize) -> Vec<f32> {
+    macro_rules! by_n {
+        ($k:literal) => {
+            match n {
+                1 => fixed::<$k, 1>(a, b, m),
+                2 => fixed::<$k, 2>(a, b, m),
+                3 => fixed::<$k, 3>(a, b, m),
+                4 => fixed::<$k, 4>(a, b, m),
+                5 => fixed::<$k, 5>(a, b, m),
+                6 => fixed::<$k, 6>(a, b, m),
+                7 => fixed::<$k, 7>(a, b, m),
+                8 => fixed::<$k, 8>(a, b, m),
+                _ => Vec::new(),
+            }
+        };
+    }
+    match k {
+        1 => by_n!(1),
+        2 => by_n!(2),
+        3 => by_n!(3),
+        4 => by_n!(4),
+        5 => by_n!(5),
+        6 => by_n!(6),
+        7 => by_n!(7),
+        8 => by_n!(8),
+        _ => vec![0.0; m * n],
+    }
+}
+
+/// `[m, K] @ [K, N]` with the sizes known at compile time: `b` is loaded
+/// once into an array and the loops over `K` and `N` unroll completely.
+fn fixed<const K: usize, const N: usize>(a: &[f32], b: &[f32], m: usize) -> Vec<f32> {
+    let mut rhs = [[0.0; N]; K];
+    for (p, row) in rhs.iter_mut().enumerate() {
+        row.copy_from_slice(&b[p * N..(p + 1) * N]);
+    }
+
+    let mut out = vec![0.0; m * N];
+    for (lhs, dst) in a.chunks_exact(K).zip(out.chunks_exact_mut(N)) {
+        let mut acc = [0.0; N];
+        for p in 0..K {
+            for j in 0..N {
+                acc[j] += lhs[p] * rhs[p][j];
+            }
+        }
+        dst.copy_from_slice(&acc);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn data(len: usize, seed: usize) -> Vec<f32> {
+        (0..len)
+            .map(|i| ((i * 7 + seed * 13) % 11) as f32 * 0.25 - 1.2)
+            .collect()
+    }
+
+    #[test]
+    fn test_small_kernels_match_general() {
+        for m in 0..=SMALL {
+            for k in 0..=SMALL {
+                for n in 0..=SMALL {
+                    let (a, b) = (data(m * k, 1), data(k * n, 2));
+                    assert_eq!(
+                        small(&a, &b, m, k, n),
+                        general(&a, &b, m, k, n),
+                        "[{}, {}] @ [{}, {}]",
+                        m,
+                        k,
+                        k,
+                        n
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_blocked_matches_general() {
+        // Uneven sizes, so the halves differ and the leaves are ragged
+        for (m, k, n) in [(70, 130, 65), (129, 3, 200), (1, 300, 2)] {
+            let (a, b) = (data(m * k, 5), data(k * n, 6));
+            assert_eq!(blocked(&a, &b, m, k, n), general(&a, &b, m, k, n));
+        }
+    }
+
+    #[test]
+    fn test_dispatch_by_size() {
+        // 4x4 transform applied to a homogeneous point
+        let t = [
+            1.0, 0.0, 0.0, 2.0, 0.0, 1.0, 0.0, -1.0, 0.0, 0.0, 1.0, 0.5, 0.0, 0.0, 0.0, 1.0,
+        ];
+        assert_eq!(
+            matmul(&t, &[1.0, 1.0, 1.0, 1.0], 4, 4, 1),
+            vec![3.0, 0.0, 1.5, 1.0]
+        );
+        // Just past the threshold takes the general path
+        let (a, b) = (data(9 * 3, 3), data(3 * 2, 4));
+        assert_eq!(matmul(&a, &b, 9, 3, 2), general(&a, &b, 9, 3, 2));
+        assert_eq!(
+            matmul(&a, &b, 9, 3, 2)[0],
+            a[0] * b[0] + a[1] * b[2] + a[2] * b[4]
+        );
+    }
+}
@@ -1,7 +1,24 @@
+mod activation;
+mod compare;
+mod conv;
+mod index;
+mod inplace;
+mod linalg;
+mod math;
+pub(crate) mod matmul;
+mod pool;
+mod reduce;
 mod shape;
+pub mod sparse;
+pub mod special;
 mod storage;
+#[allow(clippy::module_inception)]
 mod tensor;
 
-pub use shape::Shape;
+pub use conv::ConvOptions;
+pub(crate) use conv::conv_nd;
+pub(crate) use index::{gather_rows, narrow, pad};
+pub(crate) use shape::broadcast_shapes;
+pub use shape::{BroadcastError, Shape};
 pub use storage::Storage;
 pub use tensor::Tensor;
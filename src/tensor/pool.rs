@@ -0,0 +1,400 @@
+use std::rc::Rc;
+
+use crate::autograd::record;
+use crate::tensor::Tensor;
+use crate::tensor::index::gather_rows;
+
+/// Pooling windows over one `[h, w]` plane: for each output position, the
+/// offsets of the input positions it covers and the divisor of its
+/// average. Positions on padding are left out.
+struct Windows {
+    batch: usize,
+    channels: usize,
+    /// Input size of each plane
+    size: [usize; 2],
+    /// Input positions per plane
+    input: usize,
+    out: [usize; 2],
+    windows: Vec<(Vec<usize>, f32)>,
+}
+
+impl Windows {
+    fn plane(x: &Tensor, op: &str) -> (usize, usize, [usize; 2]) {
+        assert_eq!(
+            x.ndim(),
+            4,
+            "{} expects input [batch, channels, h, w], got {:?}",
+            op,
+            x.shape()
+        );
+        let s = x.shape();
+        (s[0], s[1], [s[2], s[3]])
+    }
+
+    /// Sliding windows of `kernel`, moved by `stride`, over the input
+    /// padded by `padding` on both sides. Averages divide by the full
+    /// kernel size, padding included.
+    fn sliding(
+        x: &Tensor,
+        kernel: [usize; 2],
+        stride: [usize; 2],
+        padding: [usize; 2],
+        op: &str,
+    ) -> Self {
+        let (batch, channels, size) = Self::plane(x, op);
+        assert!(
+            kernel.iter().chain(&stride).all(|&k| k > 0),
+            "{} kernel and stride must be positive, got {:?} and {:?}",
+            op,
+            kernel,
+            stride
+        );
+        let out = [0, 1].map(|d| {
+            let padded = size[d] + 2 * padding[d];
+            assert!(
+                padding[d] * 2 <= kernel[d] && kernel[d] <= padded,
+                "{} kernel {:?} with padding {:?} does not fit the input {:?} (padding may be at most half the kernel)",
+                op,
+                kernel,
+                padding,
+                x.shape()
+            );
+            (padded - kernel[d]) / stride[d] + 1
+        });
+        let divisor = (kernel[0] * kernel[1]) as f32;
+        let mut windows = Vec::with_capacity(out[0] * out[1]);
+        for i in 0..out[0] {
+            for j in 0..out[1] {
+                let start = [i * stride[0], j * stride[1]];
+                let range = |d: usize| {
+                    let lo = start[d].max(padding[d]) - padding[d];
+                    let hi = (start[d] + kernel[d]).min(size[d] + padding[d]) - padding[d];
+                    lo..hi
+                };
+                let offsets = range(0)
+                    .flat_map(|y| range(1).map(move |z| y * size[1] + z))
+                    .collect();
+                windows.push((offsets, divisor));
+            }
+        }
+        Self {
+            batch,
+            channels,
+            size,
+            input: size[0] * size[1],
+            out,
+            windows,
+        }
+    }
+
+    /// Windows splitting the input into an `out` grid: output `i` covers
+    /// inputs `floor(i h / oh)..ceil((i + 1) h / oh)`, so windows differ in
+    /// size by at most one and overlap when `oh` does not divide `h`.
+    fn adaptive(x: &Tensor, out: [usize; 2], op: &str) -> Self {
+        let (batch, channels, size) = Self::plane(x, op);
+        assert!(
+            out.iter().all(|&o| o > 0),
+            "{} output size must be positive, got {:?}",
+            op,
+            out
+        );
+        let range = |d: usize, i: usize| i * size[d] / out[d]..((i + 1) * size[d]).div_ceil(out[d]);
+        let mut windows = Vec::with_capacity(out[0] * out[1]);
+        for i in 0..out[0] {
+            for j in 0..out[1] {
+                let offsets: Vec<usize> = range(0, i)
+                    .flat_map(|y| range(1, j).map(move |z| y * size[1] + z))
+                    .collect();
+                let divisor = offsets.len() as f32;
+                windows.push((offsets, divisor));
+            }
+        }
+        Self {
+            batch,
+            channels,
+            size,
+            input: size[0] * size[1],
+            out,
+            windows,
+        }
+    }
+
+    fn output_shape(&self) -> [usize; 4] {
+        [self.batch, self.channels, self.out[0], self.out[1]]
+    }
+
+    fn planes(&self) -> usize {
+        self.batch * self.channels
+    }
+
+    /// Average over each window.
+    ///
+    /// Averaging is linear, so its gradient is the transposed map,
+    /// [`Windows::spread`], whose own gradient is this average: the pair
+    /// differentiates to any order.
+    fn average(self: &Rc<Self>, x: &Tensor, op: &'static str) -> Tensor {
+        let xs = x.to_vec();
+        let mut out = Vec::with_capacity(self.planes() * self.windows.len());
+        for p in 0..self.planes() {
+            let plane = &xs[p * self.input..(p + 1) * self.input];
+            for (offsets, divisor) in &self.windows {
+                out.push(offsets.iter().map(|&i| plane[i]).sum::<f32>() / divisor);
+            }
+        }
+        let out = Tensor::from_vec(out, &self.output_shape());
+
+        let windows = Rc::clone(self);
+        record(out, op, &[x], move |g| vec![windows.spread(g, op)])
+    }
+
+    /// The gradient of [`Windows::average`] for the output gradient `g`:
+    /// each output's share spread evenly over its window.
+    fn spread(self: &Rc<Self>, g: &Tensor, op: &'static str) -> Tensor {
+        let gs = g.to_vec();
+        let mut grad = vec![0.0; self.planes() * self.input];
+        for p in 0..self.planes() {
+            let plane = &mut grad[p * self.input..(p + 1) * self.input];
+            let gp = &gs[p * self.windows.len()..];
+            for ((offsets, divisor), &g) in self.windows.iter().zip(gp) {
+                for &i in offsets {
+                    plane[i] += g / divisor;
+                }
+            }
+        }
+        let shape = [self.batch, self.channels, self.size[0], self.size[1]];
+        let grad = Tensor::from_vec(grad, &shape);
+
+        let windows = Rc::clone(self);
+        record(grad, "avg_pool_backward", &[g], move |h| {
+            vec![windows.average(h, op)]
+        })
+    }
+
+    /// Maximum over each window, as a gather of the first maximum of each:
+    /// the gradient goes to that element, and differentiates again.
+    fn maximum(&self, x: &Tensor, op: &'static str) -> Tensor {
+        let xs = x.to_vec();
+        let mut argmax = Vec::with_capacity(self.planes() * self.windows.len());
+        for p in 0..self.planes() {
+            let start = p * self.input;
+            for (offsets, _) in &self.windows {
+                let best = offsets
+                    .iter()
+                    .map(|&i| start + i)
+                    .reduce(|a, b| if xs[b] > xs[a] { b } else { a })
+                    .expect("max pooling window is never empty");
+                argmax.push(best);
+            }
+        }
+        gather_rows(&x.reshape(&[xs.len(), 1]), Rc::from(argmax), op).reshape(&self.output_shape())
+    }
+}
+
+impl Tensor {
+    /// Max pooling of `self` `[batch, channels, h, w]` over `kernel`
+    /// windows moved by `stride`, giving `[batch, channels, h', w']` with
+    /// `h' = (h + 2 padding - kh) / stride + 1`.
+    ///
+    /// Padding never wins the maximum, and may be at most half the kernel
+    /// so that every window sees the input. Differentiable with respect to
+    /// `self` to any order, the gradient flowing to the first maximum of
+    /// each window.
+    ///
+    /// # Panics
+    /// Panics if `self` is not 4D, or the kernel or padding do not fit.
+    ///
+    /// # Example
+    /// ```
+    /// use delta::tensor::Tensor;
+    ///
+    /// let x = Tensor::from_vec((0..16).map(|v| v as f32).collect(), &[1, 1, 4, 4]);
+    /// let y = x.max_pool2d([2, 2], [2, 2], [0, 0]);
+    /// assert_eq!(y.to_vec(), vec![5.0, 7.0, 13.0, 15.0]);
+    /// ```
+    pub fn max_pool2d(
+        &self,
+        kernel: [usize; 2],
+        stride: [usize; 2],
+        padding: [usize; 2],
+    ) -> Tensor {
+        Windows::sliding(self, kernel, stride, padding, "max_pool2d").maximum(self, "max_pool2d")
+    }
+
+    /// Average pooling of `self` `[batch, channels, h, w]`; sizes as for
+    /// [`Tensor::max_pool2d`].
+    ///
+    /// Each output is the sum of its window divided by the kernel size,
+    /// padding counting as zeros. Differentiable with respect to `self` to
+    /// any order.
+    ///
+    /// # Panics
+    /// Panics if `self` is not 4D, or the kernel or padding do not fit.
+    ///
+    /// # Example
+    /// ```
+    /// use delta::tensor::Tensor;
+    ///
+    /// let x = Tensor::from_vec((0..16).map(|v| v as f32).collect(), &[1, 1, 4, 4]);
+    /// let y = x.avg_pool2d([2, 2], [2, 2], [0, 0]);
+    /// assert_eq!(y.to_vec(), vec![2.5, 4.5, 10.5, 12.5]);
+    /// ```
+    pub fn avg_pool2d(
+        &self,
+        kernel: [usize; 2],
+        stride: [usize; 2],
+        padding: [usize; 2],
+    ) -> Tensor {
+        Rc::new(Windows::sliding(
+            self,
+            kernel,
+            stride,
+            padding,
+            "avg_pool2d",
+        ))
+        .average(self, "avg_pool2d")
+    }
+
+    /// Average pooling of `self` `[batch, channels, h, w]` down to a fixed
+    /// `[batch, channels, oh, ow]`, whatever the input size: the windows
+    /// are chosen to tile the input. `[1, 1]` is global average pooling,
+    /// the usual bridge from convolutions to a classifier.
+    ///
+    /// Differentiable with respect to `self` to any order.
+    ///
+    /// # Panics
+    /// Panics if `self` is not 4D or `output` has a zero.
+    ///
+    /// # Example
+    /// ```
+    /// use delta::tensor::Tensor;
+    ///
+    /// let x = Tensor::from_vec((0..6).map(|v| v as f32).collect(), &[1, 1, 2, 3]);
+    /// assert_eq!(x.adaptive_avg_pool2d([1, 1]).to_vec(), vec![2.5]);
+    /// // Overlapping windows 0..2 and 1..3 along the width
+    /// assert_eq!(x.adaptive_avg_pool2d([1, 2]).to_vec(), vec![2.0, 3.0]);
+    /// ```
+    pub fn adaptive_avg_pool2d(&self, output: [usize; 2]) -> Tensor {
+        Rc::new(Windows::adaptive(self, output, "adaptive_avg_pool2d"))
+            .average(self, "adaptive_avg_pool2d")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::autograd::testing::check_grad;
+    use crate::autograd::{grad, jvp};
+    use crate::random::Rng;
+    use crate::tensor::ConvOptions;
+
+    #[test]
+    fn test_avg_pool_matches_box_convolution() {
+        let x = Tensor::randn(&[2, 1, 5, 6], &mut Rng::new(1));
+        let w = Tensor::from_vec(vec![1.0 / 6.0; 6], &[1, 1, 2, 3]);
+        let options = ConvOptions::new().stride([1, 2]).padding([1, 1]);
+        let expected = x.conv2d(&w, &options).to_vec();
+        let got = x.avg_pool2d([2, 3], [1, 2], [1, 1]);
+        assert_eq!(got.shape(), &[2, 1, 6, 3]);
+        for (a, b) in got.to_vec().iter().zip(expected) {
+            assert!((a - b).abs() < 1e-5, "{} vs {}", a, b);
+        }
+    }
+
+    #[test]
+    fn test_max_pool_ignores_padding() {
+        let x = Tensor::from_vec(vec![-1.0, -2.0, -3.0, -4.0], &[1, 1, 2, 2]);
+        let y = x.max_pool2d([2, 2], [1, 1], [1, 1]);
+        assert_eq!(y.shape(), &[1, 1, 3, 3]);
+        assert_eq!(
+            y.to_vec(),
+            vec![-1.0, -1.0, -2.0, -1.0, -1.0, -2.0, -3.0, -3.0, -4.0]
+        );
+    }
+
+    #[test]
+    fn test_channels_pool_independently() {
+        let x = Tensor::randn(&[2, 3, 4, 4], &mut Rng::new(2));
+        let y = x.max_pool2d([3, 3], [1, 1], [0, 0]).to_vec();
+        let xs = x.to_vec();
+        for p in 0..6 {
+            let plane = Tensor::from_vec(xs[p * 16..(p + 1) * 16].to_vec(), &[1, 1, 4, 4]);
+            assert_eq!(
+                plane.max_pool2d([3, 3], [1, 1], [0, 0]).to_vec(),
+                y[p * 4..(p + 1) * 4]
+            );
+        }
+    }
+
+    #[test]
+    fn test_adaptive_covers_uneven_sizes() {
+        let x = Tensor::randn(&[1, 2, 7, 5], &mut Rng::new(3));
+        let y = x.adaptive_avg_pool2d([3, 2]);
+        assert_eq!(y.shape(), &[1, 2, 3, 2]);
+        // Divisible sizes reduce to ordinary pooling
+        let z = Tensor::randn(&[1, 2, 6, 4], &mut Rng::new(4));
+        assert_eq!(
+            z.adaptive_avg_pool2d([3, 2]).to_vec(),
+            z.avg_pool2d([2, 2], [2, 2], [0, 0]).to_vec()
+        );
+        let mean = z.adaptive_avg_pool2d([1, 1]).to_vec()[0];
+        let expected = z.to_vec()[..24].iter().sum::<f32>() / 24.0;
+        assert!((mean - expected).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_max_pool_gradient_goes_to_maximum() {
+        let x = Tensor::from_vec(vec![1.0, 3.0, 2.0, 0.0], &[1, 1, 2, 2]).requires_grad(true);
+        x.max_pool2d([2, 2], [2, 2], [0, 0]).sum().backward();
+        assert_eq!(x.grad().unwrap().to_vec(), vec![0.0, 1.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn test_gradients() {
+        let x = Tensor::randn(&[2, 2, 5, 4], &mut Rng::new(5));
+        check_grad(
+            |t| t[0].avg_pool2d([3, 2], [2, 1], [1, 1]),
+            std::slice::from_ref(&x),
+        );
+        check_grad(
+            |t| t[0].max_pool2d([2, 2], [1, 2], [1, 0]),
+            std::slice::from_ref(&x),
+        );
+        check_grad(
+            |t| t[0].adaptive_avg_pool2d([2, 3]),
+            std::slice::from_ref(&x),
+        );
+    }
+
+    #[test]
+    fn test_second_order() {
+        let x = Tensor::randn(&[1, 2, 4, 4], &mut Rng::new(6));
+        let input_grad = |pool: fn(&Tensor) -> Tensor| {
+            move |t: &[Tensor]| {
+                let t: Vec<Tensor> = t.iter().map(|x| x.clone().requires_grad(true)).collect();
+                grad(&pool(&t[0]).tanh().sum(), &t, true)[0].mul(&t[0])
+            }
+        };
+        let x_only = std::slice::from_ref(&x);
+        check_grad(input_grad(|x| x.avg_pool2d([2, 2], [1, 1], [1, 1])), x_only);
+        check_grad(input_grad(|x| x.max_pool2d([2, 2], [2, 1], [0, 0])), x_only);
+        check_grad(input_grad(|x| x.adaptive_avg_pool2d([3, 2])), x_only);
+
+        // The tangent of the maximum of a window is that of its winner
+        let x = Tensor::from_vec(vec![1.0, 3.0, 2.0, 0.0], &[1, 1, 2, 2]);
+        let v = Tensor::from_vec(vec![10.0, 20.0, 30.0, 40.0], &[1, 1, 2, 2]);
+        let (_, jv) = jvp(|x| x.max_pool2d([2, 2], [2, 2], [0, 0]), &x, &v);
+        assert_eq!(jv.to_vec(), vec![20.0]);
+    }
+
+    #[test]
+    fn test_rejects_bad_geometry() {
+        for f in [
+            |x: &Tensor| x.max_pool2d([4, 4], [1, 1], [0, 0]),
+            |x: &Tensor| x.avg_pool2d([2, 2], [0, 1], [0, 0]),
+            |x: &Tensor| x.max_pool2d([2, 2], [1, 1], [2, 0]),
+            |x: &Tensor| x.adaptive_avg_pool2d([0, 1]),
+        ] {
+            assert!(std::panic::catch_unwind(|| f(&Tensor::zeros(&[1, 1, 3, 3]))).is_err());
+        }
+    }
+}
@@ -0,0 +1,526 @@
+use crate::autograd::{record, record_first_order};
+use crate::tensor::Tensor;
+
+/// Stable log(Σ exp(x_i)) of a single lane, see [`Tensor::logsumexp`].
+pub(crate) fn logsumexp_lane(lane: &[f32]) -> f32 {
+    let m = lane.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    if m.is_infinite() {
+        // All -inf (or empty) stays -inf; any +inf dominates the sum.
+        // Shifting by an infinite max would produce NaN instead.
+        return m;
+    }
+    m + lane.iter().map(|x| (x - m).exp()).sum::<f32>().ln()
+}
+
+/// Replace each element with the product of all the *other* elements.
+///
+/// Built from prefix and suffix products, so no division is involved.
+fn exclusive_products(lane: &mut [f32]) {
+    let mut prefix = 1.0;
+    let prefixes: Vec<f32> = lane
+        .iter()
+        .map(|&x| {
+            let p = prefix;
+            prefix *= x;
+            p
+        })
+        .collect();
+
+    let mut suffix = 1.0;
+    for (x, p) in lane.iter_mut().zip(prefixes).rev() {
+        let value = *x;
+        *x = p * suffix;
+        suffix *= value;
+    }
+}
+
+/// Stretch the gradient of a reduction along `dim` back over the input
+/// shape, so each element of a lane receives its lane's gradient.
+fn expand_reduced(grad: &Tensor, shape: &[usize], dim: usize) -> Tensor {
+    let mut kept = shape.to_vec();
+    kept[dim] = 1;
+    grad.reshape(&kept).broadcast_to(shape)
+}
+
+impl Tensor {
+    /// Reduce along a single dimension.
+    ///
+    /// The tensor is viewed as `[outer, size, inner]`, where `size` is the
+    /// length of `dim`. Every "lane" of `size` elements sharing the same outer
+    /// and inner position is collapsed into one value by `f`.
+    ///
+    /// For shape [2, 3] and dim = 1:
+    /// ```text
+    ///   [[a, b, c],      lane 0: [a, b, c] -> f(...)
+    ///    [d, e, f]]      lane 1: [d, e, f] -> f(...)
+    /// ```
+    ///
+    /// With `keepdim`, the reduced dimension stays as size 1 so the result
+    /// still lines up with the input: [2, 3] -> [2, 1] instead of [2].
+    ///
+    /// # Panics
+    /// Panics if `dim` is out of range.
+    pub(crate) fn reduce_dim(
+        &self,
+        dim: usize,
+        keepdim: bool,
+        f: impl Fn(&[f32]) -> f32,
+    ) -> Tensor {
+        assert!(
+            dim < self.ndim(),
+            "dim {} out of range for {}D tensor",
+            dim,
+            self.ndim()
+        );
+
+        let dims = self.shape();
+        let outer: usize = dims[..dim].iter().product();
+        let size = dims[dim];
+        let inner: usize = dims[dim + 1..].iter().product();
+
+        let data = self.to_vec();
+        let mut lane = Vec::with_capacity(size);
+        let mut out = Vec::with_capacity(outer * inner);
+        for o in 0..outer {
+            for n in 0..inner {
+                lane.clear();
+                lane.extend((0..size).map(|i| data[(o * size + i) * inner + n]));
+                out.push(f(&lane));
+            }
+        }
+
+        let mut out_shape = dims.to_vec();
+        if keepdim {
+            out_shape[dim] = 1;
+        } else {
+            out_shape.remove(dim);
+        }
+        Tensor::from_vec(out, &out_shape)
+    }
+
+    /// Transform each lane along `dim` in place, keeping the shape.
+    ///
+    /// Uses the same `[outer, size, inner]` view as [`Tensor::reduce_dim`],
+    /// but `f` rewrites the whole lane instead of collapsing it. This is the
+    /// building block for normalizations like softmax.
+    ///
+    /// # Panics
+    /// Panics if `dim` is out of range.
+    pub(crate) fn map_lanes(&self, dim: usize, f: impl Fn(&mut [f32])) -> Tensor {
+        assert!(
+            dim < self.ndim(),
+            "dim {} out of range for {}D tensor",
+            dim,
+            self.ndim()
+        );
+
+        let dims = self.shape();
+        let outer: usize = dims[..dim].iter().product();
+        let size = dims[dim];
+        let inner: usize = dims[dim + 1..].iter().product();
+
+        let mut data = self.to_vec();
+        let mut lane = vec![0.0; size];
+        for o in 0..outer {
+            for n in 0..inner {
+                for (i, x) in lane.iter_mut().enumerate() {
+                    *x = data[(o * size + i) * inner + n];
+                }
+                f(&mut lane);
+                for (i, x) in lane.iter().enumerate() {
+                    data[(o * size + i) * inner + n] = *x;
+                }
+            }
+        }
+
+        Tensor::from_vec(data, dims)
+    }
+
+    /// Sum of all elements, returned as a 0-d tensor.
+    ///
+    /// # Example
+    /// ```
+    /// use delta::tensor::Tensor;
+    /// let t = Tensor::from_vec(vec![1.0, 2.0, 3.0, 4.0], &[2, 2]);
+    /// assert_eq!(t.sum().get(&[]), 10.0);
+    /// ```
+    pub fn sum(&self) -> Tensor {
+        let s = self.to_vec().iter().sum();
+        let out = Tensor::from_vec(vec![s], &[]);
+        let shape = self.shape().to_vec();
+        record(out, "sum", &[self], move |g| vec![g.broadcast_to(&shape)])
+    }
+
+    /// Sum along a dimension.
+    ///
+    /// With `keepdim`, the reduced dimension is kept with size 1.
+    ///
+    /// # Panics
+    /// Panics if `dim` is out of range.
+    ///
+    /// # Example
+    /// ```
+    /// use delta::tensor::Tensor;
+    /// let t = Tensor::from_vec(vec![1.0, 2.0, 3.0,
+    ///                               4.0, 5.0, 6.0], &[2, 3]);
+    /// assert_eq!(t.sum_dim(0, false).to_vec(), vec![5.0, 7.0, 9.0]);
+    /// assert_eq!(t.sum_dim(1, true).shape(), &[2, 1]);
+    /// ```
+    pub fn sum_dim(&self, dim: usize, keepdim: bool) -> Tensor {
+        let out = self.reduce_dim(dim, keepdim, |lane| lane.iter().sum());
+        let shape = self.shape().to_vec();
+        record(out, "sum_dim", &[self], move |g| {
+            vec![expand_reduced(g, &shape, dim)]
+        })
+    }
+
+    /// Sum a broadcast tensor back down to `shape`.
+    ///
+    /// The adjoint of [`Tensor::broadcast_to`]: leading dimensions that
+    /// `shape` lacks, and dimensions where it has size 1, are summed away.
+    pub(crate) fn sum_to(&self, shape: &[usize]) -> Tensor {
+        let lead = self.ndim() - shape.len();
+        let mut out = self.clone();
+        for dim in 0..self.ndim() {
+            if dim < lead || (shape[dim - lead] == 1 && self.shape()[dim] != 1) {
+                out = out.sum_dim(dim, true);
+            }
+        }
+        out.reshape(shape)
+    }
+
+    /// Product of all elements, returned as a 0-d tensor.
+    ///
+    /// The product of an empty tensor is 1.0 (the empty product).
+    ///
+    /// # Gradient
+    /// For `y = prod(x)`, `dy/dx_i` is the product of every element *except*
+    /// `x_i`. Computing it from prefix and suffix products, rather than as
+    /// `y / x_i`, keeps it well-defined when `x` contains zeros.
+    ///
+    /// # Example
+    /// ```
+    /// use delta::tensor::Tensor;
+    /// let t = Tensor::from_vec(vec![1.0, 2.0, 3.0, 4.0], &[2, 2]);
+    /// assert_eq!(t.prod().get(&[]), 24.0);
+    /// ```
+    pub fn prod(&self) -> Tensor {
+        let p = self.to_vec().iter().product();
+        let out = Tensor::from_vec(vec![p], &[]);
+
+        let x = self.clone();
+        record_first_order(out, "prod", &[self], move |g| {
+            let mut others = x.to_vec();
+            exclusive_products(&mut others);
+            let others = Tensor::from_vec(others, x.shape());
+            vec![g.broadcast_to(x.shape()).mul(&others)]
+        })
+    }
+
+    /// Product along a dimension.
+    ///
+    /// With `keepdim`, the reduced dimension is kept with size 1.
+    ///
+    /// # Gradient
+    /// Same rule as [`Tensor::prod`], applied independently to each lane
+    /// along `dim`.
+    ///
+    /// # Panics
+    /// Panics if `dim` is out of range.
+    ///
+    /// # Example
+    /// ```
+    /// use delta::tensor::Tensor;
+    /// let t = Tensor::from_vec(vec![1.0, 2.0, 3.0,
+    ///                               4.0, 5.0, 6.0], &[2, 3]);
+    /// let p = t.prod_dim(1, false);
+    /// assert_eq!(p.shape(), &[2]);
+    /// assert_eq!(p.get(&[0]), 6.0);   // 1 * 2 * 3
+    /// assert_eq!(p.get(&[1]), 120.0); // 4 * 5 * 6
+    /// ```
+    pub fn prod_dim(&self, dim: usize, keepdim: bool) -> Tensor {
+        let out = self.reduce_dim(dim, keepdim, |lane| lane.iter().product());
+        let x = self.clone();
+        record_first_order(out, "prod_dim", &[self], move |g| {
+            let others = x.map_lanes(dim, exclusive_products);
+            vec![expand_reduced(g, x.shape(), dim).mul(&others)]
+        })
+    }
+
+    /// Log of the sum of exponentials along a dimension: log(Σ exp(x_i)).
+    ///
+    /// Computed in the numerically stable form
+    /// `m + log(Σ exp(x_i - m))` with `m = max(x)`, so every exponent is
+    /// at most 0 and nothing overflows. The naive version already overflows
+    /// f32 at x ≈ 89.
+    ///
+    /// An empty lane gives -inf (the log of an empty sum).
+    ///
+    /// # Gradient
+    /// `d/dx_i logsumexp(x) = exp(x_i - logsumexp(x))`, i.e. softmax of the
+    /// lane.
+    ///
+    /// # Panics
+    /// Panics if `dim` is out of range.
+    ///
+    /// # Example
+    /// ```
+    /// use delta::tensor::Tensor;
+    /// let t = Tensor::from_vec(vec![1000.0, 1000.0], &[2]);
+    /// let lse = t.logsumexp(0, false);
+    /// assert!((lse.get(&[]) - (1000.0 + 2f32.ln())).abs() < 1e-3);
+    /// ```
+    pub fn logsumexp(&self, dim: usize, keepdim: bool) -> Tensor {
+        let out = self.reduce_dim(dim, keepdim, logsumexp_lane);
+        let x = self.clone();
+        record(out, "logsumexp", &[self], move |g| {
+            vec![expand_reduced(g, x.shape(), dim).mul(&x.softmax(dim))]
+        })
+    }
+
+    /// 1.0 where any element along `dim` is nonzero, 0.0 otherwise.
+    ///
+    /// Until there is a bool dtype, masks are f32 tensors: nonzero counts as
+    /// true (NaN included, since NaN != 0). An empty lane gives 0.0.
+    ///
+    /// # Panics
+    /// Panics if `dim` is out of range.
+    ///
+    /// # Example
+    /// ```
+    /// use delta::tensor::Tensor;
+    /// let mask = Tensor::from_vec(vec![0.0, 0.0,
+    ///                                  0.0, 1.0], &[2, 2]);
+    /// assert_eq!(mask.any(1).to_vec(), vec![0.0, 1.0]);
+    /// ```
+    pub fn any(&self, dim: usize) -> Tensor {
+        self.reduce_dim(dim, false, |lane| {
+            if lane.iter().any(|&x| x != 0.0) {
+                1.0
+            } else {
+                0.0
+            }
+        })
+    }
+
+    /// 1.0 where every element along `dim` is nonzero, 0.0 otherwise.
+    ///
+    /// Same truthiness rule as [`Tensor::any`]. An empty lane gives 1.0.
+    ///
+    /// # Panics
+    /// Panics if `dim` is out of range.
+    ///
+    /// # Example
+    /// ```
+    /// use delta::tensor::Tensor;
+    /// let mask = Tensor::from_vec(vec![1.0, 1.0,
+    ///                                  0.0, 1.0], &[2, 2]);
+    /// assert_eq!(mask.all(1).to_vec(), vec![1.0, 0.0]);
+    /// ```
+    pub fn all(&self, dim: usize) -> Tensor {
+        self.reduce_dim(dim, false, |lane| {
+            if lane.iter().all(|&x| x != 0.0) {
+                1.0
+            } else {
+                0.0
+            }
+        })
+    }
+
+    /// Number of nonzero elements along `dim`, as f32.
+    ///
+    /// Same truthiness rule as [`Tensor::any`].
+    ///
+    /// # Panics
+    /// Panics if `dim` is out of range.
+    ///
+    /// # Example
+    /// ```
+    /// use delta::tensor::Tensor;
+    /// let t = Tensor::from_vec(vec![0.0, 2.0, 3.0,
+    ///                               0.0, 0.0, 1.0], &[2, 3]);
+    /// assert_eq!(t.count_nonzero(1).to_vec(), vec![2.0, 1.0]);
+    /// ```
+    pub fn count_nonzero(&self, dim: usize) -> Tensor {
+        self.reduce_dim(dim, false, |lane| {
+            lane.iter().filter(|&&x| x != 0.0).count() as f32
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::autograd::testing::check_grad;
+
+    #[test]
+    fn test_prod() {
+        let t = Tensor::from_vec(vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0], &[2, 3]);
+        let p = t.prod();
+        assert_eq!(p.shape(), &[] as &[usize]);
+        assert_eq!(p.get(&[]), 720.0);
+    }
+
+    #[test]
+    fn test_prod_empty() {
+        let t = Tensor::zeros(&[0]);
+        assert_eq!(t.prod().get(&[]), 1.0);
+    }
+
+    #[test]
+    fn test_prod_dim() {
+        // [[1, 2, 3],
+        //  [4, 5, 6]]
+        let t = Tensor::from_vec(vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0], &[2, 3]);
+
+        // Down the columns
+        let p0 = t.prod_dim(0, false);
+        assert_eq!(p0.shape(), &[3]);
+        assert_eq!(p0.to_vec(), vec![4.0, 10.0, 18.0]);
+
+        // Across the rows
+        let p1 = t.prod_dim(1, false);
+        assert_eq!(p1.shape(), &[2]);
+        assert_eq!(p1.to_vec(), vec![6.0, 120.0]);
+    }
+
+    #[test]
+    fn test_prod_dim_keepdim() {
+        let t = Tensor::from_vec(vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0], &[2, 3]);
+        assert_eq!(t.prod_dim(0, true).shape(), &[1, 3]);
+        assert_eq!(t.prod_dim(1, true).shape(), &[2, 1]);
+    }
+
+    #[test]
+    fn test_prod_dim_3d() {
+        // Middle dimension of a [2, 2, 2] tensor
+        let t = Tensor::from_vec(vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0], &[2, 2, 2]);
+        let p = t.prod_dim(1, false);
+        assert_eq!(p.shape(), &[2, 2]);
+        // [1*3, 2*4, 5*7, 6*8]
+        assert_eq!(p.to_vec(), vec![3.0, 8.0, 35.0, 48.0]);
+    }
+
+    #[test]
+    #[should_panic(expected = "out of range")]
+    fn test_prod_dim_out_of_range() {
+        Tensor::zeros(&[2, 3]).prod_dim(2, false);
+    }
+
+    #[test]
+    fn test_logsumexp() {
+        let t = Tensor::from_vec(vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0], &[2, 3]);
+        let lse = t.logsumexp(1, false);
+        assert_eq!(lse.shape(), &[2]);
+
+        let expected = |xs: &[f32]| xs.iter().map(|x| x.exp()).sum::<f32>().ln();
+        assert!((lse.get(&[0]) - expected(&[1.0, 2.0, 3.0])).abs() < 1e-5);
+        assert!((lse.get(&[1]) - expected(&[4.0, 5.0, 6.0])).abs() < 1e-5);
+
+        assert_eq!(t.logsumexp(0, true).shape(), &[1, 3]);
+    }
+
+    #[test]
+    fn test_logsumexp_large_values() {
+        // exp(100) overflows f32, the stable form must not
+        let t = Tensor::from_vec(vec![100.0, 100.0, 100.0], &[3]);
+        let lse = t.logsumexp(0, false).get(&[]);
+        assert!(lse.is_finite());
+        assert!((lse - (100.0 + 3f32.ln())).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_logsumexp_infinities() {
+        let t = Tensor::from_vec(vec![f32::NEG_INFINITY, f32::NEG_INFINITY], &[2]);
+        assert_eq!(t.logsumexp(0, false).get(&[]), f32::NEG_INFINITY);
+
+        let t = Tensor::from_vec(vec![1.0, f32::INFINITY], &[2]);
+        assert_eq!(t.logsumexp(0, false).get(&[]), f32::INFINITY);
+    }
+
+    #[test]
+    fn test_any() {
+        let t = Tensor::from_vec(vec![0.0, 0.0, 0.0, 0.0, -2.0, 0.0], &[2, 3]);
+        assert_eq!(t.any(1).to_vec(), vec![0.0, 1.0]);
+        assert_eq!(t.any(0).to_vec(), vec![0.0, 1.0, 0.0]);
+        assert_eq!(t.any(0).shape(), &[3]);
+    }
+
+    #[test]
+    fn test_all() {
+        let t = Tensor::from_vec(vec![1.0, 2.0, 3.0, 1.0, 0.0, 1.0], &[2, 3]);
+        assert_eq!(t.all(1).to_vec(), vec![1.0, 0.0]);
+        assert_eq!(t.all(0).to_vec(), vec![1.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn test_any_all_empty_lane() {
+        let t = Tensor::zeros(&[2, 0]);
+        assert_eq!(t.any(1).to_vec(), vec![0.0, 0.0]);
+        assert_eq!(t.all(1).to_vec(), vec![1.0, 1.0]);
+    }
+
+    #[test]
+    fn test_nan_is_truthy() {
+        let t = Tensor::from_vec(vec![f32::NAN, 0.0], &[2]);
+        assert_eq!(t.any(0).get(&[]), 1.0);
+        assert_eq!(t.count_nonzero(0).get(&[]), 1.0);
+    }
+
+    #[test]
+    fn test_count_nonzero() {
+        let t = Tensor::from_vec(vec![0.0, 2.0, 3.0, 0.0, 0.0, 1.0], &[2, 3]);
+        assert_eq!(t.count_nonzero(1).to_vec(), vec![2.0, 1.0]);
+        assert_eq!(t.count_nonzero(0).to_vec(), vec![0.0, 1.0, 2.0]);
+    }
+
+    #[test]
+    fn test_sum() {
+        let t = Tensor::from_vec(vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0], &[2, 3]);
+        assert_eq!(t.sum().shape(), &[] as &[usize]);
+        assert_eq!(t.sum().get(&[]), 21.0);
+        assert_eq!(t.sum_dim(1, false).to_vec(), vec![6.0, 15.0]);
+        assert_eq!(t.sum_dim(0, true).shape(), &[1, 3]);
+        assert_eq!(Tensor::zeros(&[0]).sum().get(&[]), 0.0);
+    }
+
+    #[test]
+    fn test_sum_to() {
+        let t = Tensor::from_vec(vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0], &[2, 3]);
+        assert_eq!(t.sum_to(&[3]).to_vec(), vec![5.0, 7.0, 9.0]);
+        assert_eq!(t.sum_to(&[2, 1]).to_vec(), vec![6.0, 15.0]);
+        assert_eq!(t.sum_to(&[]).get(&[]), 21.0);
+        assert_eq!(t.sum_to(&[2, 3]).to_vec(), t.to_vec());
+    }
+
+    #[test]
+    fn test_grad_sum() {
+        let x = Tensor::from_vec(vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0], &[2, 3]);
+        check_grad(|x| x[0].sum(), std::slice::from_ref(&x));
+        check_grad(|x| x[0].sum_dim(0, false), std::slice::from_ref(&x));
+        check_grad(|x| x[0].sum_dim(1, true), std::slice::from_ref(&x));
+    }
+
+    #[test]
+    fn test_grad_prod() {
+        let x = Tensor::from_vec(vec![0.5, -2.0, 1.5, 3.0, 1.0, -0.5], &[2, 3]);
+        check_grad(|x| x[0].prod(), std::slice::from_ref(&x));
+        check_grad(|x| x[0].prod_dim(1, false), std::slice::from_ref(&x));
+        check_grad(|x| x[0].prod_dim(0, true), std::slice::from_ref(&x));
+    }
+
+    #[test]
+    fn test_grad_prod_with_zero() {
+        // y / x_i would give NaN at the zero and 0 elsewhere
+        let x = Tensor::from_vec(vec![2.0, 0.0, 3.0], &[3]).requires_grad(true);
+        x.prod().backward();
+        assert_eq!(x.grad().unwrap().to_vec(), vec![0.0, 6.0, 0.0]);
+    }
+
+    #[test]
+    fn test_grad_logsumexp() {
+        let x = Tensor::from_vec(vec![0.5, -1.0, 2.0, 0.0, 1.0, 3.0], &[2, 3]);
+        check_grad(|x| x[0].logsumexp(1, false), std::slice::from_ref(&x));
+        check_grad(|x| x[0].logsumexp(0, true), std::slice::from_ref(&x));
+    }
+}
@@ -1,3 +1,5 @@
+use std::fmt;
+
 /// Represents the dimensions of a tensor.
 ///
 /// For example, a 2x3 matrix has shape [2, 3].
@@ -82,6 +84,196 @@ impl Shape {
         }
         strides
     }
+
+    /// The size of dimension `axis`.
+    ///
+    /// # Panics
+    /// Panics if `axis` is out of range.
+    pub fn size(&self, axis: usize) -> usize {
+        assert!(
+            axis < self.ndim(),
+            "axis {} out of range for shape {:?}",
+            axis,
+            self.dims
+        );
+        self.dims[axis]
+    }
+
+    /// The shape broadcasting `self` with `other` produces (NumPy rules):
+    /// shapes are right-aligned, missing leading dims count as 1, and each
+    /// pair of dims must be equal or contain a 1.
+    ///
+    /// # Example
+    /// ```
+    /// use delta::tensor::Shape;
+    /// let a = Shape::new(&[4, 1, 5]);
+    /// assert_eq!(a.broadcast_with(&Shape::new(&[3, 1])).unwrap().dims(), &[4, 3, 5]);
+    ///
+    /// let err = a.broadcast_with(&Shape::new(&[2, 4])).unwrap_err();
+    /// assert_eq!(err.to_string(), "shapes [4, 1, 5] and [2, 4] cannot broadcast: [4, 2, 5|4]");
+    /// ```
+    pub fn broadcast_with(&self, other: &Shape) -> Result<Shape, BroadcastError> {
+        broadcast_shapes(&self.dims, &other.dims)
+            .map(|dims| Shape { dims })
+            .ok_or_else(|| BroadcastError {
+                left: self.clone(),
+                right: other.clone(),
+            })
+    }
+
+    /// The shape with a new dimension of `size` at `axis`, shifting later
+    /// dimensions up; `axis == ndim` appends.
+    ///
+    /// # Panics
+    /// Panics if `axis > ndim`.
+    pub fn insert_dim(&self, axis: usize, size: usize) -> Shape {
+        assert!(
+            axis <= self.ndim(),
+            "cannot insert axis {} into shape {:?}",
+            axis,
+            self.dims
+        );
+        let mut dims = self.dims.clone();
+        dims.insert(axis, size);
+        Shape { dims }
+    }
+
+    /// The shape without dimension `axis`.
+    ///
+    /// # Panics
+    /// Panics if `axis` is out of range.
+    pub fn remove_dim(&self, axis: usize) -> Shape {
+        self.size(axis);
+        let mut dims = self.dims.clone();
+        dims.remove(axis);
+        Shape { dims }
+    }
+
+    /// The shape with its dimensions reordered: dimension `i` of the
+    /// result is dimension `order[i]` of `self`.
+    ///
+    /// # Example
+    /// ```
+    /// use delta::tensor::Shape;
+    /// let nchw = Shape::new(&[8, 3, 32, 32]);
+    /// assert_eq!(nchw.permuted(&[0, 2, 3, 1]).dims(), &[8, 32, 32, 3]);
+    /// ```
+    ///
+    /// # Panics
+    /// Panics if `order` is not a permutation of `0..ndim`.
+    pub fn permuted(&self, order: &[usize]) -> Shape {
+        let mut seen = vec![false; self.ndim()];
+        let valid = order.len() == self.ndim()
+            && order
+                .iter()
+                .all(|&a| a < seen.len() && !std::mem::replace(&mut seen[a], true));
+        assert!(
+            valid,
+            "{:?} is not a permutation of the axes of shape {:?}",
+            order, self.dims
+        );
+        Shape {
+            dims: order.iter().map(|&a| self.dims[a]).collect(),
+        }
+    }
+}
+
+/// Two shapes that cannot be broadcast together; see
+/// [`Shape::broadcast_with`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct BroadcastError {
+    pub left: Shape,
+    pub right: Shape,
+}
+
+impl fmt::Display for BroadcastError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "shapes {:?} and {:?} cannot broadcast: {}",
+            self.left.dims,
+            self.right.dims,
+            attempted_broadcast(&self.left.dims, &self.right.dims)
+        )
+    }
+}
+
+impl std::error::Error for BroadcastError {}
+
+/// Advance a multi-index like an odometer, last dimension fastest.
+///
+/// Returns false once every index has wrapped around, i.e. after the last
+/// element. Iterating `[0, 0]` over dims [2, 2] visits
+/// [0, 0] -> [0, 1] -> [1, 0] -> [1, 1].
+pub(crate) fn next_index(indices: &mut [usize], dims: &[usize]) -> bool {
+    for dim in (0..dims.len()).rev() {
+        indices[dim] += 1;
+        if indices[dim] < dims[dim] {
+            return true;
+        }
+        indices[dim] = 0;
+    }
+    false
+}
+
+/// Broadcast two shapes together (NumPy rules), or None if incompatible.
+///
+/// Shapes are right-aligned; missing leading dims count as 1, and each
+/// pair of dims must be equal or contain a 1.
+pub(crate) fn broadcast_shapes(a: &[usize], b: &[usize]) -> Option<Vec<usize>> {
+    let ndim = a.len().max(b.len());
+    let mut out = vec![0; ndim];
+    for (i, o) in out.iter_mut().enumerate() {
+        // Dim i of the output lines up with dim (i - pad) of each input
+        let da = (i + a.len()).checked_sub(ndim).map_or(1, |j| a[j]);
+        let db = (i + b.len()).checked_sub(ndim).map_or(1, |j| b[j]);
+        *o = match (da, db) {
+            _ if da == db => da,
+            (1, _) => db,
+            (_, 1) => da,
+            _ => return None,
+        };
+    }
+    Some(out)
+}
+
+/// The shape broadcasting `a` with `b` would produce, written out with
+/// every conflicting dimension shown as `x|y`, e.g. `[2, 3|4]`.
+///
+/// Only meaningful for error messages: compatible dims show their
+/// broadcast size.
+pub(crate) fn attempted_broadcast(a: &[usize], b: &[usize]) -> String {
+    let ndim = a.len().max(b.len());
+    let dims: Vec<String> = (0..ndim)
+        .map(|i| {
+            let da = (i + a.len()).checked_sub(ndim).map_or(1, |j| a[j]);
+            let db = (i + b.len()).checked_sub(ndim).map_or(1, |j| b[j]);
+            match broadcast_shapes(&[da], &[db]) {
+                Some(d) => d[0].to_string(),
+                None => format!("{}|{}", da, db),
+            }
+        })
+        .collect();
+    format!("[{}]", dims.join(", "))
+}
+
+/// Strides for reading a contiguous tensor of shape `from` as if it had the
+/// (broadcast) shape `to`.
+///
+/// Stretched and missing dims get stride 0, so every index along them maps
+/// back to the same element.
+pub(crate) fn broadcast_strides(from: &[usize], to: &[usize]) -> Vec<usize> {
+    let strides = Shape::new(from).strides();
+    let pad = to.len() - from.len();
+    (0..to.len())
+        .map(|i| {
+            if i < pad || from[i - pad] == 1 {
+                0
+            } else {
+                strides[i - pad]
+            }
+        })
+        .collect()
 }
 
 #[cfg(test)]
@@ -124,4 +316,88 @@ mod tests {
         // 3D, [depth, rows, cols] -> [rows * cols, cols, 1]
         assert_eq!(Shape::new(&[2, 3, 4]).strides(), vec![12, 4, 1]);
     }
+
+    #[test]
+    fn test_size_and_dims() {
+        let shape = Shape::new(&[2, 3, 4]);
+        assert_eq!(shape.size(1), 3);
+        assert_eq!(shape.insert_dim(1, 1).dims(), &[2, 1, 3, 4]);
+        assert_eq!(shape.insert_dim(3, 5).dims(), &[2, 3, 4, 5]);
+        assert_eq!(shape.remove_dim(0).dims(), &[3, 4]);
+        assert_eq!(Shape::new(&[7]).remove_dim(0).dims(), &[] as &[usize]);
+    }
+
+    #[test]
+    #[should_panic(expected = "axis 3 out of range for shape [2, 3, 4]")]
+    fn test_size_out_of_range() {
+        Shape::new(&[2, 3, 4]).size(3);
+    }
+
+    #[test]
+    fn test_broadcast_with() {
+        let a = Shape::new(&[2, 1]);
+        assert_eq!(
+            a.broadcast_with(&Shape::new(&[1, 3])),
+            Ok(Shape::new(&[2, 3]))
+        );
+        let err = Shape::new(&[2, 3])
+            .broadcast_with(&Shape::new(&[4]))
+            .unwrap_err();
+        assert_eq!(err.right, Shape::new(&[4]));
+        assert_eq!(
+            err.to_string(),
+            "shapes [2, 3] and [4] cannot broadcast: [2, 3|4]"
+        );
+    }
+
+    #[test]
+    fn test_permuted() {
+        let shape = Shape::new(&[2, 3, 4]);
+        assert_eq!(shape.permuted(&[2, 0, 1]).dims(), &[4, 2, 3]);
+        assert_eq!(Shape::new(&[]).permuted(&[]), Shape::new(&[]));
+        for bad in [&[0, 0, 1][..], &[0, 1], &[0, 1, 3]] {
+            let result = std::panic::catch_unwind(|| shape.permuted(bad));
+            assert!(result.is_err(), "{:?}", bad);
+        }
+    }
+
+    #[test]
+    fn test_next_index() {
+        let mut idx = vec![0, 0];
+        let mut visited = vec![idx.clone()];
+        while next_index(&mut idx, &[2, 3]) {
+            visited.push(idx.clone());
+        }
+        assert_eq!(visited.len(), 6);
+        assert_eq!(visited[1], vec![0, 1]);
+        assert_eq!(visited[3], vec![1, 0]);
+        assert_eq!(idx, vec![0, 0]); // wrapped around
+    }
+
+    #[test]
+    fn test_broadcast_shapes() {
+        assert_eq!(broadcast_shapes(&[2, 3], &[2, 3]), Some(vec![2, 3]));
+        assert_eq!(broadcast_shapes(&[2, 3], &[3]), Some(vec![2, 3]));
+        assert_eq!(broadcast_shapes(&[2, 1], &[1, 3]), Some(vec![2, 3]));
+        assert_eq!(broadcast_shapes(&[4, 1, 5], &[3, 1]), Some(vec![4, 3, 5]));
+        assert_eq!(broadcast_shapes(&[], &[2, 2]), Some(vec![2, 2]));
+        assert_eq!(broadcast_shapes(&[0], &[1]), Some(vec![0]));
+        assert_eq!(broadcast_shapes(&[2, 3], &[2]), None);
+    }
+
+    #[test]
+    fn test_attempted_broadcast() {
+        assert_eq!(attempted_broadcast(&[2, 3], &[4]), "[2, 3|4]");
+        assert_eq!(attempted_broadcast(&[5, 1, 3], &[2, 7, 3]), "[5|2, 7, 3]");
+        assert_eq!(attempted_broadcast(&[], &[2]), "[2]");
+    }
+
+    #[test]
+    fn test_broadcast_strides() {
+        // [3] read as [2, 3]: same row for every i
+        assert_eq!(broadcast_strides(&[3], &[2, 3]), vec![0, 1]);
+        // [2, 1] read as [2, 3]: same column value across j
+        assert_eq!(broadcast_strides(&[2, 1], &[2, 3]), vec![1, 0]);
+        assert_eq!(broadcast_strides(&[], &[2, 2]), vec![0, 0]);
+    }
 }
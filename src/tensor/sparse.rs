@@ -0,0 +1,484 @@
+//! Sparse matrices in compressed sparse row (CSR) form.
+//!
+//! A [`Csr`] matrix keeps only its nonzero entries: for each row, the
+//! columns it has entries in and their values. The structure is fixed
+//! once built; the values are an ordinary 1D tensor, so they can be
+//! parameters and receive gradients through [`Csr::matmul`]. Products with
+//! a dense matrix cost time proportional to the number of nonzeros rather
+//! than to the full size, which is what makes pruned weights and sparse
+//! attention patterns pay off.
+
+use std::rc::Rc;
+
+use crate::autograd::{record, record_first_order};
+use crate::tensor::{Tensor, gather_rows};
+
+/// A `rows x cols` sparse matrix in CSR form.
+///
+/// The entries of row `r` are at positions `row_ptr[r]..row_ptr[r + 1]`
+/// of `col_idx` and `values`, in increasing column order.
+///
+/// # Example
+/// ```
+/// use delta::tensor::Tensor;
+/// use delta::tensor::sparse::Csr;
+///
+/// let a = Csr::from_dense(&Tensor::from_vec(vec![0.0, 2.0,
+///                                                1.0, 0.0], &[2, 2]));
+/// assert_eq!(a.nnz(), 2);
+/// let b = Tensor::from_vec(vec![1.0, 2.0, 3.0, 4.0], &[2, 2]);
+/// assert_eq!(a.matmul(&b).to_vec(), vec![6.0, 8.0, 1.0, 2.0]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Csr {
+    rows: usize,
+    cols: usize,
+    row_ptr: Rc<[usize]>,
+    col_idx: Rc<[usize]>,
+    values: Tensor,
+}
+
+impl Csr {
+    /// A matrix from its CSR arrays.
+    ///
+    /// # Panics
+    /// - Panics if `row_ptr` does not have `rows + 1` non-decreasing
+    ///   entries from 0 to the number of values
+    /// - Panics if `values` is not 1D with one value per column index
+    /// - Panics if a column index is out of range or the columns of a row
+    ///   are not strictly increasing
+    pub fn new(
+        rows: usize,
+        cols: usize,
+        row_ptr: Vec<usize>,
+        col_idx: Vec<usize>,
+        values: Tensor,
+    ) -> Self {
+        assert!(
+            values.ndim() == 1 && values.shape()[0] == col_idx.len(),
+            "Csr expects one value per column index ({}), got values of shape {:?}",
+            col_idx.len(),
+            values.shape()
+        );
+        assert!(
+            row_ptr.len() == rows + 1
+                && row_ptr[0] == 0
+                && row_ptr[rows] == col_idx.len()
+                && row_ptr.windows(2).all(|w| w[0] <= w[1]),
+            "Csr row_ptr must rise from 0 to {} in {} steps, got {:?}",
+            col_idx.len(),
+            rows,
+            row_ptr
+        );
+        for r in 0..rows {
+            let row = &col_idx[row_ptr[r]..row_ptr[r + 1]];
+            assert!(
+                row.iter().all(|&c| c < cols) && row.windows(2).all(|w| w[0] < w[1]),
+                "Csr row {} must have increasing columns below {}, got {:?}",
+                r,
+                cols,
+                row
+            );
+        }
+        Self {
+            rows,
+            cols,
+            row_ptr: row_ptr.into(),
+            col_idx: col_idx.into(),
+            values,
+        }
+    }
+
+    /// The nonzero entries of a dense matrix. Gradients do not flow back
+    /// to `dense`; the values are a new tensor.
+    ///
+    /// # Panics
+    /// Panics if `dense` is not 2D.
+    pub fn from_dense(dense: &Tensor) -> Self {
+        assert_eq!(
+            dense.ndim(),
+            2,
+            "Csr::from_dense requires a 2D tensor, got shape {:?}",
+            dense.shape()
+        );
+        let (rows, cols) = (dense.shape()[0], dense.shape()[1]);
+        let data = dense.to_vec();
+        let mut row_ptr = vec![0];
+        let mut col_idx = Vec::new();
+        let mut values = Vec::new();
+        for r in 0..rows {
+            for c in 0..cols {
+                let v = data[r * cols + c];
+                if v != 0.0 {
+                    col_idx.push(c);
+                    values.push(v);
+                }
+            }
+            row_ptr.push(col_idx.len());
+        }
+        let nnz = values.len();
+        Self::new(
+            rows,
+            cols,
+            row_ptr,
+            col_idx,
+            Tensor::from_vec(values, &[nnz]),
+        )
+    }
+
+    /// The structure of the entries `(row, col)` for which `keep` holds,
+    /// with all values zero: a sparsity pattern to fill with
+    /// [`Csr::with_values`].
+    pub fn from_pattern(rows: usize, cols: usize, keep: impl Fn(usize, usize) -> bool) -> Self {
+        let mut row_ptr = vec![0];
+        let mut col_idx = Vec::new();
+        for r in 0..rows {
+            col_idx.extend((0..cols).filter(|&c| keep(r, c)));
+            row_ptr.push(col_idx.len());
+        }
+        let nnz = col_idx.len();
+        Self::new(rows, cols, row_ptr, col_idx, Tensor::zeros(&[nnz]))
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    /// Number of stored entries.
+    pub fn nnz(&self) -> usize {
+        self.col_idx.len()
+    }
+
+    /// Fraction of the entries that are stored.
+    pub fn density(&self) -> f32 {
+        self.nnz() as f32 / (self.rows * self.cols).max(1) as f32
+    }
+
+    pub fn row_ptr(&self) -> &[usize] {
+        &self.row_ptr
+    }
+
+    pub fn col_idx(&self) -> &[usize] {
+        &self.col_idx
+    }
+
+    /// The stored values, `[nnz]`.
+    pub fn values(&self) -> &Tensor {
+        &self.values
+    }
+
+    /// The stored values, mutably: for overwriting them in place, keeping
+    /// their shape.
+    pub(crate) fn values_mut(&mut self) -> &mut Tensor {
+        &mut self.values
+    }
+
+    /// The same structure with other values.
+    ///
+    /// # Panics
+    /// Panics if `values` is not `[nnz]`.
+    pub fn with_values(&self, values: Tensor) -> Self {
+        assert_eq!(
+            values.shape(),
+            &[self.nnz()],
+            "Csr::with_values expects [{}]",
+            self.nnz()
+        );
+        Self {
+            values,
+            ..self.clone()
+        }
+    }
+
+    /// Row of each stored entry.
+    fn entry_rows(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.rows).flat_map(|r| (self.row_ptr[r]..self.row_ptr[r + 1]).map(move |_| r))
+    }
+
+    /// The matrix as a dense `[rows, cols]` tensor, differentiable with
+    /// respect to the values to any order.
+    pub fn to_dense(&self) -> Tensor {
+        let positions: Vec<usize> = self
+            .entry_rows()
+            .zip(self.col_idx.iter())
+            .map(|(r, &c)| r * self.cols + c)
+            .collect();
+        let mut data = vec![0.0; self.rows * self.cols];
+        for (&i, v) in positions.iter().zip(self.values.to_vec()) {
+            data[i] = v;
+        }
+        let out = Tensor::from_vec(data, &[self.rows, self.cols]);
+        let (nnz, numel) = (self.nnz(), self.rows * self.cols);
+        let positions: Rc<[usize]> = positions.into();
+        record(out, "csr_to_dense", &[&self.values], move |g| {
+            let flat = g.reshape(&[numel, 1]);
+            vec![gather_rows(&flat, positions.clone(), "gather_rows").reshape(&[nnz])]
+        })
+    }
+
+    /// The product with a dense matrix `[cols, n]`, giving `[rows, n]`.
+    ///
+    /// Differentiable with respect to the values and to `dense`. The
+    /// backward pass is itself sparse but not recorded, so the result
+    /// cannot be differentiated twice: [`grad`](crate::autograd::grad)
+    /// with `create_graph` panics on it.
+    ///
+    /// # Panics
+    /// Panics if `dense` is not `[cols, n]`.
+    pub fn matmul(&self, dense: &Tensor) -> Tensor {
+        assert!(
+            dense.ndim() == 2 && dense.shape()[0] == self.cols,
+            "Csr matmul of a [{}, {}] matrix requires [{}, n], got {:?}",
+            self.rows,
+            self.cols,
+            self.cols,
+            dense.shape()
+        );
+        let n = dense.shape()[1];
+        let (rows, cols) = (self.rows, self.cols);
+        let entry_rows: Rc<[usize]> = self.entry_rows().collect();
+        let col_idx = Rc::clone(&self.col_idx);
+        let (values, b) = (self.values.to_vec(), dense.to_vec());
+
+        let mut out = vec![0.0; rows * n];
+        for (p, (&r, &c)) in entry_rows.iter().zip(col_idx.iter()).enumerate() {
+            let v = values[p];
+            let (dst, src) = (&mut out[r * n..(r + 1) * n], &b[c * n..(c + 1) * n]);
+            for (o, &x) in dst.iter_mut().zip(src) {
+                *o += v * x;
+            }
+        }
+        let out = Tensor::from_vec(out, &[rows, n]);
+
+        record_first_order(out, "csr_matmul", &[&self.values, dense], move |g| {
+            let g = g.to_vec();
+            let mut grad_values = vec![0.0; values.len()];
+            let mut grad_dense = vec![0.0; cols * n];
+            for (p, (&r, &c)) in entry_rows.iter().zip(col_idx.iter()).enumerate() {
+                let g_row = &g[r * n..(r + 1) * n];
+                let b_row = &b[c * n..(c + 1) * n];
+                grad_values[p] = g_row.iter().zip(b_row).map(|(x, y)| x * y).sum();
+                for (d, &x) in grad_dense[c * n..(c + 1) * n].iter_mut().zip(g_row) {
+                    *d += values[p] * x;
+                }
+            }
+            vec![
+                Tensor::from_vec(grad_values, &[values.len()]),
+                Tensor::from_vec(grad_dense, &[cols, n]),
+            ]
+        })
+    }
+
+    /// The dense product `a bᵀ` sampled at the stored positions, as
+    /// `[nnz]` values for this structure: entry `(r, c)` is the dot product
+    /// of row `r` of `a` `[rows, d]` and row `c` of `b` `[cols, d]`.
+    ///
+    /// Only the stored entries are computed, so this costs `nnz * d`
+    /// rather than `rows * cols * d`. Differentiable with respect to both
+    /// operands, to first order only as for [`Csr::matmul`].
+    ///
+    /// # Panics
+    /// Panics if `a` is not `[rows, d]` or `b` is not `[cols, d]`.
+    pub fn sampled_matmul(&self, a: &Tensor, b: &Tensor) -> Tensor {
+        assert!(
+            a.ndim() == 2
+                && b.ndim() == 2
+                && a.shape()[0] == self.rows
+                && b.shape()[0] == self.cols
+                && a.shape()[1] == b.shape()[1],
+            "Csr sampled_matmul of a [{}, {}] pattern requires [{}, d] and [{}, d], got {:?} and {:?}",
+            self.rows,
+            self.cols,
+            self.rows,
+            self.cols,
+            a.shape(),
+            b.shape()
+        );
+        let d = a.shape()[1];
+        let (rows, cols) = (self.rows, self.cols);
+        let entry_rows: Rc<[usize]> = self.entry_rows().collect();
+        let col_idx = Rc::clone(&self.col_idx);
+        let (x, y) = (a.to_vec(), b.to_vec());
+        let dot = |r: usize, c: usize| -> f32 {
+            x[r * d..(r + 1) * d]
+                .iter()
+                .zip(&y[c * d..(c + 1) * d])
+                .map(|(p, q)| p * q)
+                .sum()
+        };
+        let values: Vec<f32> = entry_rows
+            .iter()
+            .zip(col_idx.iter())
+            .map(|(&r, &c)| dot(r, c))
+            .collect();
+        let nnz = values.len();
+        let out = Tensor::from_vec(values, &[nnz]);
+
+        record_first_order(out, "csr_sampled_matmul", &[a, b], move |g| {
+            let g = g.to_vec();
+            let mut grad_a = vec![0.0; rows * d];
+            let mut grad_b = vec![0.0; cols * d];
+            for (p, (&r, &c)) in entry_rows.iter().zip(col_idx.iter()).enumerate() {
+                for k in 0..d {
+                    grad_a[r * d + k] += g[p] * y[c * d + k];
+                    grad_b[c * d + k] += g[p] * x[r * d + k];
+                }
+            }
+            vec![
+                Tensor::from_vec(grad_a, &[rows, d]),
+                Tensor::from_vec(grad_b, &[cols, d]),
+            ]
+        })
+    }
+
+    /// Softmax of the stored values within each row; absent entries count
+    /// as -inf, i.e. they get no probability. Rows without entries stay
+    /// empty. Differentiable with respect to the values, to first order
+    /// only as for [`Csr::matmul`].
+    pub fn softmax_rows(&self) -> Csr {
+        let row_ptr = Rc::clone(&self.row_ptr);
+        let mut probs = self.values.to_vec();
+        for r in 0..self.rows {
+            let lane = &mut probs[row_ptr[r]..row_ptr[r + 1]];
+            let m = lane.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+            let mut sum = 0.0;
+            for x in lane.iter_mut() {
+                *x = (*x - m).exp();
+                sum += *x;
+            }
+            for x in lane.iter_mut() {
+                *x /= sum;
+            }
+        }
+        let nnz = self.nnz();
+        let out = Tensor::from_vec(probs.clone(), &[nnz]);
+        let values = record_first_order(out, "csr_softmax_rows", &[&self.values], move |g| {
+            // dx = s * (g - Σ g s) within each row, as for dense softmax
+            let g = g.to_vec();
+            let mut dx = vec![0.0; nnz];
+            for w in row_ptr.windows(2) {
+                let dot: f32 = (w[0]..w[1]).map(|p| g[p] * probs[p]).sum();
+                for p in w[0]..w[1] {
+                    dx[p] = probs[p] * (g[p] - dot);
+                }
+            }
+            vec![Tensor::from_vec(dx, &[nnz])]
+        });
+        self.with_values(values)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::autograd::grad;
+    use crate::autograd::testing::check_grad;
+
+    fn dense() -> Tensor {
+        Tensor::from_vec(
+            vec![0.0, 1.5, 0.0, -2.0, 0.0, 0.0, 0.0, 0.0, 0.5, 0.0, 3.0, 1.0],
+            &[3, 4],
+        )
+    }
+
+    #[test]
+    fn test_from_dense() {
+        let a = Csr::from_dense(&dense());
+        assert_eq!(a.row_ptr(), &[0, 2, 2, 5]);
+        assert_eq!(a.col_idx(), &[1, 3, 0, 2, 3]);
+        assert_eq!(a.values().to_vec(), vec![1.5, -2.0, 0.5, 3.0, 1.0]);
+        assert_eq!(a.to_dense().to_vec(), dense().to_vec());
+        assert!((a.density() - 5.0 / 12.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_matmul_matches_dense() {
+        let b = Tensor::from_vec((0..8).map(|i| i as f32 * 0.5 - 1.0).collect(), &[4, 2]);
+        let a = Csr::from_dense(&dense());
+        assert_eq!(a.matmul(&b).to_vec(), dense().matmul(&b).to_vec());
+        // An empty row gives a zero row
+        assert_eq!(a.matmul(&b).to_vec()[2..4], [0.0, 0.0]);
+    }
+
+    #[test]
+    fn test_gradients() {
+        let a = Csr::from_dense(&dense());
+        let b = Tensor::from_vec((0..8).map(|i| (i as f32 * 0.7).sin()).collect(), &[4, 2]);
+        check_grad(
+            |t| a.with_values(t[0].clone()).matmul(&t[1]).tanh(),
+            &[a.values().clone(), b],
+        );
+        check_grad(
+            |t| a.with_values(t[0].clone()).to_dense().powi(2),
+            &[a.values().clone()],
+        );
+        // The gradient of to_dense differentiates again
+        check_grad(
+            |t| {
+                let v = t[0].clone().requires_grad(true);
+                let y = a.with_values(v.clone()).to_dense().powi(3).sum();
+                grad(&y, std::slice::from_ref(&v), true).remove(0)
+            },
+            &[a.values().clone()],
+        );
+    }
+
+    #[test]
+    fn test_sampled_matmul_and_row_softmax() {
+        let pattern = Csr::from_pattern(3, 4, |r, c| c <= r + 1 && r != 1);
+        assert_eq!(pattern.row_ptr(), &[0, 2, 2, 6]);
+        let a = Tensor::from_vec((0..6).map(|i| i as f32 * 0.3 - 0.5).collect(), &[3, 2]);
+        let b = Tensor::from_vec((0..8).map(|i| (i as f32).cos()).collect(), &[4, 2]);
+        let full = a.matmul(&b.t()).to_vec();
+        let sampled = pattern.sampled_matmul(&a, &b).to_vec();
+        for (p, (r, c)) in [(0, 0), (0, 1), (2, 0), (2, 1), (2, 2), (2, 3)]
+            .iter()
+            .enumerate()
+        {
+            assert!((sampled[p] - full[r * 4 + c]).abs() < 1e-6);
+        }
+
+        let probs = pattern
+            .with_values(pattern.sampled_matmul(&a, &b))
+            .softmax_rows();
+        let dense = Tensor::from_vec(full, &[3, 4]).softmax(1).to_vec();
+        // The last row stores every column, so it matches a dense softmax
+        for (p, c) in (2..6).zip(0..4) {
+            assert!((probs.values().to_vec()[p] - dense[8 + c]).abs() < 1e-6);
+        }
+
+        check_grad(
+            |t| pattern.sampled_matmul(&t[0], &t[1]).tanh(),
+            &[a.clone(), b.clone()],
+        );
+        check_grad(
+            |t| {
+                let s = pattern.sampled_matmul(&t[0], &t[1]);
+                pattern.with_values(s).softmax_rows().values().powi(2)
+            },
+            &[a, b],
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "must have increasing columns below 2")]
+    fn test_rejects_bad_columns() {
+        Csr::new(
+            1,
+            2,
+            vec![0, 2],
+            vec![1, 0],
+            Tensor::from_vec(vec![1.0, 2.0], &[2]),
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "requires [4, n], got [3, 2]")]
+    fn test_matmul_shape_mismatch() {
+        Csr::from_dense(&dense()).matmul(&Tensor::from_vec(vec![0.0; 6], &[3, 2]));
+    }
+}
@@ -0,0 +1,365 @@
+//! Special functions: error function and gamma-family functions.
+//!
+//! Each one is available as a scalar function here and as an element-wise
+//! method on [`Tensor`]. They are evaluated in f64 internally so the f32
+//! results are accurate to within a few ULPs.
+
+use std::f32::consts::FRAC_2_SQRT_PI;
+use std::f64::consts::PI;
+
+use crate::autograd::{record, record_first_order};
+use crate::tensor::Tensor;
+
+/// Error function: erf(x) = 2/√π ∫₀ˣ e^(-t²) dt
+///
+/// # Example
+/// ```
+/// use delta::tensor::special::erf;
+/// assert!((erf(1.0) - 0.842_700_8).abs() < 1e-6);
+/// ```
+pub fn erf(x: f32) -> f32 {
+    let x = x as f64;
+    if x.abs() < 0.5 {
+        // Near zero, 1 - erfc(x) would cancel catastrophically. The Maclaurin
+        // series converges fast here:
+        //   erf(x) = 2/√π Σ (-1)^n x^(2n+1) / (n! (2n+1))
+        let x2 = x * x;
+        let mut term = x;
+        let mut sum = x;
+        for n in 1..20 {
+            term *= -x2 / n as f64;
+            sum += term / (2 * n + 1) as f64;
+        }
+        (sum * 2.0 / PI.sqrt()) as f32
+    } else {
+        (1.0 - erfc_f64(x)) as f32
+    }
+}
+
+/// Complementary error function: erfc(x) = 1 - erf(x)
+///
+/// Computed directly rather than as `1 - erf(x)`, so it keeps full relative
+/// precision in the tail: erfc(5) ≈ 1.5e-12 instead of rounding to 0.
+pub fn erfc(x: f32) -> f32 {
+    erfc_f64(x as f64) as f32
+}
+
+/// Chebyshev fit of erfc with fractional error below 1.2e-7 everywhere
+/// (Numerical Recipes, `erfcc`).
+fn erfc_f64(x: f64) -> f64 {
+    // Highest-order coefficient first, for Horner evaluation
+    const COEFFS: [f64; 10] = [
+        0.170_872_77,
+        -0.822_152_23,
+        1.488_515_87,
+        -1.135_203_98,
+        0.278_868_07,
+        -0.186_288_06,
+        0.096_784_18,
+        0.374_091_96,
+        1.000_023_68,
+        -1.265_512_23,
+    ];
+
+    let z = x.abs();
+    let t = 1.0 / (1.0 + 0.5 * z);
+    let poly = COEFFS.iter().fold(0.0, |acc, c| acc * t + c);
+    let ans = t * (-z * z + poly).exp();
+    if x >= 0.0 { ans } else { 2.0 - ans }
+}
+
+/// Log of the absolute value of the gamma function: ln|Γ(x)|
+///
+/// Γ grows faster than exponentially (Γ(35) already overflows f32), so
+/// likelihoods are computed with lgamma instead. Poles at 0, -1, -2, ...
+/// give +inf.
+///
+/// # Example
+/// ```
+/// use delta::tensor::special::lgamma;
+/// // Γ(5) = 4! = 24
+/// assert!((lgamma(5.0) - 24f32.ln()).abs() < 1e-6);
+/// ```
+pub fn lgamma(x: f32) -> f32 {
+    lgamma_f64(x as f64) as f32
+}
+
+/// Lanczos approximation (g = 7, 9 coefficients), with the reflection
+/// formula Γ(x)Γ(1-x) = π / sin(πx) for x < 0.5.
+fn lgamma_f64(x: f64) -> f64 {
+    const G: f64 = 7.0;
+    const COEFFS: [f64; 9] = [
+        0.999_999_999_999_809_9,
+        676.520_368_121_885_1,
+        -1_259.139_216_722_402_8,
+        771.323_428_777_653_1,
+        -176.615_029_162_140_6,
+        12.507_343_278_686_905,
+        -0.138_571_095_265_720_12,
+        9.984_369_578_019_572e-6,
+        1.505_632_735_149_311_6e-7,
+    ];
+
+    if x.is_nan() {
+        return f64::NAN;
+    }
+    if x <= 0.0 && x == x.floor() {
+        return f64::INFINITY;
+    }
+    if x < 0.5 {
+        return (PI / (PI * x).sin().abs()).ln() - lgamma_f64(1.0 - x);
+    }
+
+    let x = x - 1.0;
+    let mut a = COEFFS[0];
+    let t = x + G + 0.5;
+    for (i, c) in COEFFS.iter().enumerate().skip(1) {
+        a += c / (x + i as f64);
+    }
+    0.5 * (2.0 * PI).ln() + (x + 0.5) * t.ln() - t + a.ln()
+}
+
+/// Digamma function: ψ(x) = d/dx ln Γ(x)
+///
+/// Shows up as the gradient of [`lgamma`]. Gives -inf at 0 and NaN at the
+/// negative integers, where Γ has poles of alternating sign.
+///
+/// # Example
+/// ```
+/// use delta::tensor::special::digamma;
+/// // ψ(1) = -γ (Euler–Mascheroni constant)
+/// assert!((digamma(1.0) + 0.577_215_7).abs() < 1e-6);
+/// ```
+pub fn digamma(x: f32) -> f32 {
+    digamma_f64(x as f64) as f32
+}
+
+/// Shift x up with ψ(x) = ψ(x + 1) - 1/x, then use the asymptotic series.
+/// Negative arguments use the reflection ψ(1 - x) - ψ(x) = π / tan(πx).
+fn digamma_f64(x: f64) -> f64 {
+    if x.is_nan() {
+        return f64::NAN;
+    }
+    if x == 0.0 {
+        return f64::NEG_INFINITY;
+    }
+    if x < 0.0 {
+        if x == x.floor() {
+            return f64::NAN;
+        }
+        return digamma_f64(1.0 - x) - PI / (PI * x).tan();
+    }
+
+    let mut x = x;
+    let mut result = 0.0;
+    while x < 6.0 {
+        result -= 1.0 / x;
+        x += 1.0;
+    }
+
+    // ψ(x) ~ ln x - 1/2x - Σ B_2k / (2k x^2k), in powers of 1/x², highest first
+    const SERIES: [f64; 5] = [
+        -1.0 / 132.0,
+        1.0 / 240.0,
+        -1.0 / 252.0,
+        1.0 / 120.0,
+        -1.0 / 12.0,
+    ];
+    let inv2 = 1.0 / (x * x);
+    let series = -inv2 * SERIES.iter().fold(0.0, |acc, c| acc * inv2 + c);
+    result + x.ln() - 0.5 / x - series
+}
+
+/// Trigamma function ψ₁(x) = d/dx ψ(x), the gradient of [`digamma`].
+///
+/// Same scheme as [`digamma_f64`]: recurrence ψ₁(x) = ψ₁(x + 1) + 1/x²,
+/// then the asymptotic series, with reflection
+/// ψ₁(1 - x) + ψ₁(x) = π² / sin²(πx) for negative x.
+fn trigamma_f64(x: f64) -> f64 {
+    if x.is_nan() {
+        return f64::NAN;
+    }
+    if x <= 0.0 && x == x.floor() {
+        return f64::INFINITY;
+    }
+    if x < 0.0 {
+        let s = (PI * x).sin();
+        return PI * PI / (s * s) - trigamma_f64(1.0 - x);
+    }
+
+    let mut x = x;
+    let mut result = 0.0;
+    while x < 6.0 {
+        result += 1.0 / (x * x);
+        x += 1.0;
+    }
+
+    // ψ₁(x) ~ 1/x + 1/2x² + Σ B_2k / x^(2k+1), in powers of 1/x², highest first
+    const SERIES: [f64; 5] = [5.0 / 66.0, -1.0 / 30.0, 1.0 / 42.0, -1.0 / 30.0, 1.0 / 6.0];
+    let inv2 = 1.0 / (x * x);
+    let series = inv2 * SERIES.iter().fold(0.0, |acc, c| acc * inv2 + c) / x;
+    result + 1.0 / x + 0.5 * inv2 + series
+}
+
+impl Tensor {
+    /// Element-wise error function, see [`erf`].
+    pub fn erf(&self) -> Tensor {
+        let out = self.unary_op(erf);
+        let x = self.clone();
+        record(out, "erf", &[self], move |g| {
+            // erf'(x) = 2/√π e^(-x²)
+            let d = x.powi(2).neg().exp().scalar_mul(FRAC_2_SQRT_PI);
+            vec![g.mul(&d)]
+        })
+    }
+
+    /// Element-wise complementary error function, see [`erfc`].
+    pub fn erfc(&self) -> Tensor {
+        let out = self.unary_op(erfc);
+        let x = self.clone();
+        record(out, "erfc", &[self], move |g| {
+            let d = x.powi(2).neg().exp().scalar_mul(-FRAC_2_SQRT_PI);
+            vec![g.mul(&d)]
+        })
+    }
+
+    /// Element-wise log-gamma, see [`lgamma`].
+    pub fn lgamma(&self) -> Tensor {
+        let out = self.unary_op(lgamma);
+        let x = self.clone();
+        record(out, "lgamma", &[self], move |g| vec![g.mul(&x.digamma())])
+    }
+
+    /// Element-wise digamma, see [`digamma`].
+    pub fn digamma(&self) -> Tensor {
+        let out = self.unary_op(digamma);
+        let x = self.clone();
+        record_first_order(out, "digamma", &[self], move |g| {
+            let d = x.unary_op(|v| trigamma_f64(v as f64) as f32);
+            vec![g.mul(&d)]
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::autograd::testing::{assert_close, check_grad};
+
+    /// [`assert_close`] against a reference computed in f64, relative to
+    /// its magnitude beyond 1.
+    fn assert_matches(actual: f32, expected: f64) {
+        let tol = 1e-6 * expected.abs().max(1.0);
+        assert_close(&[actual], &[expected as f32], tol as f32);
+    }
+
+    #[test]
+    fn test_erf() {
+        assert_eq!(erf(0.0), 0.0);
+        assert_matches(erf(0.1), 0.112_462_916_018_284_9);
+        assert_matches(erf(0.5), 0.520_499_877_813_046_5);
+        assert_matches(erf(1.0), 0.842_700_792_949_714_9);
+        assert_matches(erf(2.0), 0.995_322_265_018_952_7);
+        assert_matches(erf(-1.0), -0.842_700_792_949_714_9);
+        assert_eq!(erf(10.0), 1.0);
+    }
+
+    #[test]
+    fn test_erf_small_relative_precision() {
+        // Series branch keeps relative precision for tiny inputs
+        let x = 1e-6;
+        let expected = 2.0 / PI.sqrt() * x;
+        assert!(((erf(x as f32) as f64 - expected) / expected).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_erfc() {
+        assert_matches(erfc(0.0), 1.0);
+        assert_matches(erfc(-1.0), 1.842_700_792_949_715);
+        // Tail values: check relative error
+        for (x, expected) in [
+            (3.0, 2.209_049_699_858_544e-5),
+            (5.0, 1.537_459_794_428_035e-12),
+        ] {
+            let rel = (erfc(x) as f64 - expected) / expected;
+            assert!(rel.abs() < 1e-6, "erfc({}) rel error {}", x, rel);
+        }
+    }
+
+    #[test]
+    fn test_lgamma() {
+        assert_matches(lgamma(1.0), 0.0);
+        assert_matches(lgamma(2.0), 0.0);
+        assert_matches(lgamma(0.5), 0.572_364_942_924_700_1); // ln √π
+        assert_matches(lgamma(10.0), 12.801_827_480_081_469); // ln 9!
+        assert_matches(lgamma(100.0), 359.134_205_369_575_4);
+        assert_matches(lgamma(-0.5), 1.265_512_123_484_645_4); // ln 2√π
+    }
+
+    #[test]
+    fn test_lgamma_poles() {
+        assert_eq!(lgamma(0.0), f32::INFINITY);
+        assert_eq!(lgamma(-3.0), f32::INFINITY);
+        assert!(lgamma(f32::NAN).is_nan());
+    }
+
+    #[test]
+    fn test_digamma() {
+        assert_matches(digamma(1.0), -0.577_215_664_901_532_9);
+        assert_matches(digamma(0.5), -1.963_510_026_021_423_5);
+        assert_matches(digamma(10.0), 2.251_752_589_066_721);
+        assert_matches(digamma(-0.5), 0.036_489_973_978_576_52);
+    }
+
+    #[test]
+    fn test_digamma_poles() {
+        assert_eq!(digamma(0.0), f32::NEG_INFINITY);
+        assert!(digamma(-2.0).is_nan());
+    }
+
+    #[test]
+    fn test_digamma_is_lgamma_derivative() {
+        let h = 1e-5;
+        for x in [0.3, 1.7, 4.2, 12.5] {
+            let numeric = (lgamma_f64(x + h) - lgamma_f64(x - h)) / (2.0 * h);
+            assert!((digamma_f64(x) - numeric).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_tensor_methods() {
+        let t = Tensor::from_vec(vec![0.5, 1.0, 10.0], &[3]);
+        assert_eq!(t.erf().to_vec(), vec![erf(0.5), erf(1.0), erf(10.0)]);
+        assert_eq!(t.erfc().to_vec(), vec![erfc(0.5), erfc(1.0), erfc(10.0)]);
+        assert_eq!(
+            t.lgamma().to_vec(),
+            vec![lgamma(0.5), lgamma(1.0), lgamma(10.0)]
+        );
+        assert_eq!(
+            t.digamma().to_vec(),
+            vec![digamma(0.5), digamma(1.0), digamma(10.0)]
+        );
+        assert_eq!(t.erf().shape(), &[3]);
+    }
+
+    #[test]
+    fn test_trigamma() {
+        // ψ₁(1) = π²/6, ψ₁(1/2) = π²/2
+        assert!((trigamma_f64(1.0) - PI * PI / 6.0).abs() < 1e-9);
+        assert!((trigamma_f64(0.5) - PI * PI / 2.0).abs() < 1e-9);
+        let h = 1e-5;
+        for x in [-1.5, 0.3, 2.5, 9.0] {
+            let numeric = (digamma_f64(x + h) - digamma_f64(x - h)) / (2.0 * h);
+            assert!((trigamma_f64(x) - numeric).abs() < 1e-5 * numeric.abs().max(1.0));
+        }
+    }
+
+    #[test]
+    fn test_grad() {
+        let x = Tensor::from_vec(vec![0.3, 1.2, 2.5], &[3]);
+        check_grad(|x| x[0].erf(), std::slice::from_ref(&x));
+        check_grad(|x| x[0].erfc(), std::slice::from_ref(&x));
+        check_grad(|x| x[0].lgamma(), std::slice::from_ref(&x));
+        check_grad(|x| x[0].digamma(), std::slice::from_ref(&x));
+    }
+}
@@ -2,7 +2,7 @@
 ///
 /// Storage is a simple wrapper around a flat Vec<f32>.
 /// The interpretation of this data (shape, strides) is handled by Tensor.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct Storage {
     data: Vec<f32>,
 }
@@ -22,6 +22,11 @@ impl Storage {
         Self { data }
     }
 
+    /// Give back the underlying vector (no copy).
+    pub fn into_vec(self) -> Vec<f32> {
+        self.data
+    }
+
     /// Returns an immutable slice of the underlying data.
     pub fn as_slice(&self) -> &[f32] {
         &self.data
@@ -60,6 +65,14 @@ mod tests {
         assert_eq!(storage.as_slice(), &[1.0, 2.0, 3.0]);
     }
 
+    #[test]
+    fn test_into_vec() {
+        let data = vec![1.0, 2.0];
+        let ptr = data.as_ptr();
+        let back = Storage::from_vec(data).into_vec();
+        assert_eq!(back.as_ptr(), ptr);
+    }
+
     #[test]
     fn test_as_mut_slice() {
         let mut storage = Storage::zeros(3);
@@ -1,6 +1,9 @@
 use std::ops::{Add, Div, Mul, Neg, Sub};
+use std::rc::Rc;
 
-use crate::tensor::{Shape, Storage};
+use crate::autograd::{Node, record};
+use crate::tensor::shape::{attempted_broadcast, broadcast_shapes, broadcast_strides, next_index};
+use crate::tensor::{Shape, Storage, matmul};
 
 /// A multi-dimensional array with automatic differentiation support.
 ///
@@ -9,12 +12,18 @@ use crate::tensor::{Shape, Storage};
 /// - `shape`: The logical dimensions
 /// - `strides`: How to navigate memory for each dimension
 /// - `offset`: Starting position in storage (for views)
+/// - `node`: Its place in the computation graph, if it has one
+///
+/// Storage is shared between clones and copied on the first write, so
+/// cloning a tensor (as backward functions do to keep their inputs) is
+/// cheap.
 #[derive(Debug, Clone)]
 pub struct Tensor {
-    storage: Storage,
+    storage: Rc<Storage>,
     shape: Shape,
     strides: Vec<usize>,
     offset: usize,
+    node: Option<Rc<Node>>,
 }
 
 impl Tensor {
@@ -28,17 +37,22 @@ impl Tensor {
     pub fn zeros(shape: &[usize]) -> Self {
         let shape = Shape::new(shape);
         let strides = shape.strides();
-        let storage = Storage::zeros(shape.nelems());
+        let storage = Rc::new(Storage::zeros(shape.nelems()));
         Self {
             storage,
             shape,
             strides,
             offset: 0,
+            node: None,
         }
     }
 
     /// Create a tensor from a vector of data.
     ///
+    /// The tensor takes ownership of `data` as its storage: the values are
+    /// never copied, and [`Tensor::into_vec`] can hand the same allocation
+    /// back.
+    ///
     /// # Panics
     /// Panics if data length doesn't match shape.
     ///
@@ -58,12 +72,13 @@ impl Tensor {
             shape.nelems()
         );
         let strides = shape.strides();
-        let storage = Storage::from_vec(data);
+        let storage = Rc::new(Storage::from_vec(data));
         Self {
             storage,
             shape,
             strides,
             offset: 0,
+            node: None,
         }
     }
 
@@ -112,11 +127,154 @@ impl Tensor {
 
     /// Set element at the given indices.
     ///
+    /// The write is not recorded in the graph: if this tensor is the output
+    /// of an op, a backward pass through anything computed from it
+    /// afterwards panics (see [`Tensor::add_`] for recorded writes).
+    ///
     /// # Panics
     /// Panics if indices are out of bounds or wrong number of indices.
     pub fn set(&mut self, indices: &[usize], value: f32) {
         let idx = self.linear_index(indices);
-        self.storage.as_mut_slice()[idx] = value;
+        Rc::make_mut(&mut self.storage).as_mut_slice()[idx] = value;
+        self.bump_version();
+    }
+
+    /// Turn the tensor into a flat vector in logical (row-major) order,
+    /// reusing its storage when possible.
+    ///
+    /// If no other tensor or graph shares the storage and the tensor covers
+    /// all of it in row-major order (not a view), the storage vector is
+    /// returned as is, without copying. Otherwise this is
+    /// [`Tensor::to_vec`].
+    ///
+    /// # Example
+    /// ```
+    /// use delta::tensor::Tensor;
+    /// let data = vec![1.0, 2.0, 3.0, 4.0];
+    /// let ptr = data.as_ptr();
+    /// let back = Tensor::from_vec(data, &[2, 2]).into_vec();
+    /// assert_eq!(back.as_ptr(), ptr);
+    ///
+    /// // A transposed view is copied into logical order
+    /// let t = Tensor::from_vec(back, &[2, 2]).t();
+    /// assert_eq!(t.into_vec(), vec![1.0, 3.0, 2.0, 4.0]);
+    /// ```
+    pub fn into_vec(self) -> Vec<f32> {
+        let whole = self.offset == 0
+            && self.strides == self.shape.strides()
+            && self.storage.len() == self.nelems();
+        if !whole {
+            return self.to_vec();
+        }
+        match Rc::try_unwrap(self.storage) {
+            Ok(storage) => storage.into_vec(),
+            Err(shared) => shared.as_slice().to_vec(),
+        }
+    }
+
+    /// Copy the elements into a flat vector in logical (row-major) order.
+    ///
+    /// Walks the tensor through its strides, so the result is always
+    /// contiguous regardless of how the underlying storage is laid out.
+    ///
+    /// # Example
+    /// ```
+    /// use delta::tensor::Tensor;
+    /// let t = Tensor::from_vec(vec![1.0, 2.0, 3.0, 4.0], &[2, 2]);
+    /// assert_eq!(t.to_vec(), vec![1.0, 2.0, 3.0, 4.0]);
+    /// ```
+    pub fn to_vec(&self) -> Vec<f32> {
+        let data = self.storage.as_slice();
+        let mut out = Vec::with_capacity(self.nelems());
+        if self.nelems() == 0 {
+            return out;
+        }
+
+        let mut indices = vec![0; self.ndim()];
+        loop {
+            out.push(data[self.linear_index(&indices)]);
+            if !next_index(&mut indices, self.shape()) {
+                return out;
+            }
+        }
+    }
+
+    /// Apply a closure to every element, returning a new tensor.
+    ///
+    /// An escape hatch for element-wise ops the crate doesn't provide. The
+    /// elements are visited through the strides, so this works on any
+    /// layout, and the result has the same shape as `self`.
+    ///
+    /// The closure is opaque to autograd, so the result is never part of a
+    /// graph.
+    ///
+    /// # Example
+    /// ```
+    /// use delta::tensor::Tensor;
+    /// let t = Tensor::from_vec(vec![-2.0, 0.5, 3.0], &[3]);
+    /// let clipped = t.map(|x| x.clamp(-1.0, 1.0));
+    /// assert_eq!(clipped.to_vec(), vec![-1.0, 0.5, 1.0]);
+    /// ```
+    pub fn map(&self, f: impl Fn(f32) -> f32) -> Tensor {
+        self.unary_op(f)
+    }
+
+    /// Combine two tensors element by element with a closure.
+    ///
+    /// The binary counterpart of [`Tensor::map`]. Shapes are broadcast
+    /// against each other (see [`Tensor::eq`] for the rules), and the
+    /// result has the broadcast shape. Like `map`, the result is never part
+    /// of a graph.
+    ///
+    /// # Panics
+    /// Panics if the shapes are not broadcast-compatible.
+    ///
+    /// # Example
+    /// ```
+    /// use delta::tensor::Tensor;
+    /// // Smooth L1 (beta = 1) between predictions and a target row
+    /// let pred = Tensor::from_vec(vec![0.5, 3.0,
+    ///                                  -2.0, 1.0], &[2, 2]);
+    /// let target = Tensor::from_vec(vec![0.0, 1.0], &[2]);
+    /// let loss = pred.zip_map(&target, |p, t| {
+    ///     let d = (p - t).abs();
+    ///     if d < 1.0 { 0.5 * d * d } else { d - 0.5 }
+    /// });
+    /// assert_eq!(loss.to_vec(), vec![0.125, 1.5, 1.5, 0.0]);
+    /// ```
+    pub fn zip_map(&self, other: &Tensor, f: impl Fn(f32, f32) -> f32) -> Tensor {
+        self.broadcast_op(other, f)
+    }
+
+    /// Apply a closure to every element in place.
+    ///
+    /// Only the elements this tensor views are touched: the walk follows
+    /// `shape`, `strides` and `offset`, never the raw storage order, and
+    /// visits them in logical (row-major) order. Like [`Tensor::set`], the
+    /// write is not recorded in the graph.
+    ///
+    /// # Example
+    /// ```
+    /// use delta::tensor::Tensor;
+    /// let mut t = Tensor::from_vec(vec![1.0, 2.0, 3.0], &[3]);
+    /// t.map_inplace(|x| x * x);
+    /// assert_eq!(t.to_vec(), vec![1.0, 4.0, 9.0]);
+    /// ```
+    pub fn map_inplace(&mut self, mut f: impl FnMut(f32) -> f32) {
+        if self.nelems() == 0 {
+            return;
+        }
+        self.bump_version();
+
+        let mut indices = vec![0; self.ndim()];
+        loop {
+            let idx = self.linear_index(&indices);
+            let data = Rc::make_mut(&mut self.storage).as_mut_slice();
+            data[idx] = f(data[idx]);
+            if !next_index(&mut indices, self.shape.dims()) {
+                return;
+            }
+        }
     }
 
     /// Element-wise addition: self + other
@@ -124,69 +282,238 @@ impl Tensor {
     /// # Panics
     /// Panics if shapes do not match.
     pub fn add(&self, other: &Tensor) -> Tensor {
-        assert_eq!(
-            self.shape(),
-            other.shape(),
-            "Shape mismatch: {:?} vs {:?}",
-            self.shape(),
-            other.shape()
-        );
-
-        let data: Vec<f32> = self
-            .storage
-            .as_slice()
-            .iter()
-            .zip(other.storage.as_slice())
-            .map(|(a, b)| a + b)
-            .collect();
-
-        Tensor::from_vec(data, self.shape())
+        let out = self.binary_op(other, |a, b| a + b);
+        record(out, "add", &[self, other], |g| vec![g.clone(), g.clone()])
     }
 
     /// Element-wise subtraction: self - other
     pub fn sub(&self, other: &Tensor) -> Tensor {
-        assert_eq!(
-            self.shape(),
-            other.shape(),
-            "Shape mismatch: {:?} vs {:?}",
-            self.shape(),
-            other.shape()
-        );
-
-        let data: Vec<f32> = self
-            .storage
-            .as_slice()
-            .iter()
-            .zip(other.storage.as_slice())
-            .map(|(a, b)| a - b)
-            .collect();
-
-        Tensor::from_vec(data, self.shape())
+        let out = self.binary_op(other, |a, b| a - b);
+        record(out, "sub", &[self, other], |g| vec![g.clone(), g.neg()])
     }
 
     /// Element-wise multiplication: self * other (Hadamard product)
     pub fn mul(&self, other: &Tensor) -> Tensor {
-        assert_eq!(
-            self.shape(),
-            other.shape(),
-            "Shape mismatch: {:?} vs {:?}",
-            self.shape(),
-            other.shape()
-        );
+        let out = self.binary_op(other, |a, b| a * b);
+        let (a, b) = (self.clone(), other.clone());
+        record(out, "mul", &[self, other], move |g| {
+            vec![g.mul(&b), g.mul(&a)]
+        })
+    }
 
-        let data: Vec<f32> = self
-            .storage
-            .as_slice()
-            .iter()
-            .zip(other.storage.as_slice())
-            .map(|(a, b)| a * b)
-            .collect();
+    /// Element-wise division: self / other
+    pub fn div(&self, other: &Tensor) -> Tensor {
+        let out = self.binary_op(other, |a, b| a / b);
+        let (a, b) = (self.clone(), other.clone());
+        record(out, "div", &[self, other], move |g| {
+            // d(a/b)/db = -a / b²
+            vec![g.div(&b), g.mul(&a).div(&b.powi(2)).neg()]
+        })
+    }
+
+    /// Turn gradient tracking on or off for this tensor.
+    ///
+    /// A tensor with tracking on is a leaf of the computation graph: every
+    /// op that uses it records itself, and after `backward()` its gradient
+    /// is available from [`Tensor::grad`]. Parameters are created this way.
+    ///
+    /// Turning tracking on for a tensor that is already part of a graph
+    /// leaves it unchanged.
+    ///
+    /// # Panics
+    /// Panics when turning tracking off on a non-leaf tensor, since that
+    /// would silently cut it out of the graph it was computed in.
+    ///
+    /// # Example
+    /// ```
+    /// use delta::tensor::Tensor;
+    /// let w = Tensor::from_vec(vec![2.0, 3.0], &[2]).requires_grad(true);
+    /// let loss = w.mul(&w).sum();
+    /// loss.backward();
+    /// assert_eq!(w.grad().unwrap().to_vec(), vec![4.0, 6.0]);
+    /// ```
+    pub fn requires_grad(mut self, requires_grad: bool) -> Tensor {
+        match (&self.node, requires_grad) {
+            (None, true) => self.node = Some(Node::leaf(self.shape())),
+            (Some(node), false) => {
+                assert!(
+                    node.is_leaf(),
+                    "requires_grad(false) is only allowed on leaf tensors, this one was produced by {}",
+                    node.op()
+                );
+                self.node = None;
+            }
+            _ => {}
+        }
+        self
+    }
+
+    /// Switch gradient tracking of a leaf on or off in place, for every
+    /// clone of it: ops on a tensor switched off treat it as a constant
+    /// and backward passes leave its gradient alone. Unlike
+    /// [`Tensor::requires_grad`], this works through a shared reference,
+    /// so it reaches the parameters a model owns (see
+    /// [`Module::freeze`](crate::nn::Module::freeze)).
+    ///
+    /// Graphs recorded before the switch are not changed.
+    ///
+    /// # Panics
+    /// - Panics if `requires_grad` is false and the tensor was produced by
+    ///   a recorded op
+    /// - Panics if `requires_grad` is true and the tensor was never
+    ///   tracked; create tracked tensors with [`Tensor::requires_grad`]
+    ///
+    /// # Example
+    /// ```
+    /// use delta::tensor::Tensor;
+    /// let w = Tensor::from_vec(vec![2.0], &[1]).requires_grad(true);
+    /// let frozen = w.clone();
+    /// frozen.requires_grad_(false);
+    /// assert!(!w.tracks_grad());
+    /// assert!(w.mul(&w).is_leaf());
+    ///
+    /// w.requires_grad_(true);
+    /// w.mul(&w).sum().backward();
+    /// assert_eq!(w.grad().unwrap().to_vec(), vec![4.0]);
+    /// ```
+    pub fn requires_grad_(&self, requires_grad: bool) {
+        match &self.node {
+            Some(node) if node.is_leaf() => node.set_frozen(!requires_grad),
+            Some(node) => assert!(
+                requires_grad,
+                "requires_grad_(false) is only allowed on leaf tensors, this one was produced by {}",
+                node.op()
+            ),
+            None => assert!(
+                !requires_grad,
+                "requires_grad_(true) needs a tensor created with requires_grad(true)"
+            ),
+        }
+    }
+
+    /// Whether ops on this tensor are recorded for gradients: it was
+    /// created with [`Tensor::requires_grad`] or computed from such a
+    /// tensor, and not switched off by [`Tensor::requires_grad_`].
+    pub fn tracks_grad(&self) -> bool {
+        self.node().is_some()
+    }
+
+    /// True unless this tensor was produced by a recorded op.
+    ///
+    /// Tensors created directly (whether or not they track gradients) are
+    /// leaves; results of ops on tracked tensors are not.
+    pub fn is_leaf(&self) -> bool {
+        self.node.as_ref().is_none_or(|node| node.is_leaf())
+    }
+
+    /// The gradient accumulated for this tensor by `backward()` calls since
+    /// the last [`Tensor::zero_grad`].
+    ///
+    /// Shaped like the tensor. `None` before any backward pass reached it,
+    /// and always for tensors outside the graph. Intermediate results keep
+    /// their gradients too, not just leaves.
+    pub fn grad(&self) -> Option<Tensor> {
+        self.node.as_ref().and_then(|node| node.grad())
+    }
+
+    /// Drop the accumulated gradient, so `grad()` returns `None` until the
+    /// next backward pass.
+    ///
+    /// The gradient lives in the graph node, which clones of a tensor
+    /// share: resetting it through one clone resets it for all. Does
+    /// nothing for tensors outside the graph.
+    ///
+    /// # Example
+    /// ```
+    /// use delta::tensor::Tensor;
+    /// let w = Tensor::from_vec(vec![1.0], &[1]).requires_grad(true);
+    /// for _ in 0..4 {
+    ///     w.scalar_mul(2.0).sum().backward();
+    /// }
+    /// assert_eq!(w.grad().unwrap().to_vec(), vec![8.0]);
+    /// w.zero_grad();
+    /// assert!(w.grad().is_none());
+    /// ```
+    pub fn zero_grad(&self) {
+        if let Some(node) = &self.node {
+            node.clear_grad();
+        }
+    }
+
+    /// The same values, cut from the graph.
+    ///
+    /// The result shares storage with `self` (no copy, and writes through
+    /// either stay private thanks to copy-on-write) but has no graph node:
+    /// gradients do not flow through it, and holding on to it does not
+    /// keep the graph alive. Use it to stop gradients (target networks,
+    /// straight-through tricks) and to log values.
+    ///
+    /// # Example
+    /// ```
+    /// use delta::tensor::Tensor;
+    /// let x = Tensor::from_vec(vec![3.0], &[1]).requires_grad(true);
+    /// // d/dx (x * stop_gradient(x)) = x, not 2x
+    /// x.mul(&x.detach()).sum().backward();
+    /// assert_eq!(x.grad().unwrap().to_vec(), vec![3.0]);
+    /// ```
+    pub fn detach(&self) -> Tensor {
+        Tensor {
+            node: None,
+            ..self.clone()
+        }
+    }
 
+    /// The graph node of this tensor, if it is part of a graph and not a
+    /// frozen leaf.
+    pub(crate) fn node(&self) -> Option<&Rc<Node>> {
+        self.node.as_ref().filter(|node| !node.is_frozen())
+    }
+
+    /// Count a write to the values that the graph did not record.
+    fn bump_version(&self) {
+        if let Some(node) = &self.node {
+            node.bump_version();
+        }
+    }
+
+    /// The storage buffer, strides and offset, for [`crate::kernel`].
+    pub(crate) fn raw_parts(&self) -> (&[f32], &[usize], usize) {
+        (self.storage.as_slice(), &self.strides, self.offset)
+    }
+
+    /// A view of `storage` with the given shape and strides, for buffers
+    /// the crate grows itself (see [`KvCache`](crate::nn::attention::KvCache)).
+    pub(crate) fn from_storage(
+        storage: Rc<Storage>,
+        shape: &[usize],
+        strides: Vec<usize>,
+    ) -> Tensor {
+        Tensor {
+            storage,
+            shape: Shape::new(shape),
+            strides,
+            offset: 0,
+            node: None,
+        }
+    }
+
+    /// Attach this tensor to the graph through `node`.
+    pub(crate) fn with_node(mut self, node: Rc<Node>) -> Tensor {
+        self.node = Some(node);
+        self
+    }
+
+    /// Apply `f` to every element, producing a new tensor of the same shape.
+    pub(crate) fn unary_op(&self, f: impl Fn(f32) -> f32) -> Tensor {
+        let data: Vec<f32> = self.to_vec().into_iter().map(f).collect();
         Tensor::from_vec(data, self.shape())
     }
 
-    /// Element-wise division: self / other
-    pub fn div(&self, other: &Tensor) -> Tensor {
+    /// Combine two same-shaped tensors element by element with `f`.
+    ///
+    /// # Panics
+    /// Panics if shapes do not match.
+    pub(crate) fn binary_op(&self, other: &Tensor, f: impl Fn(f32, f32) -> f32) -> Tensor {
         assert_eq!(
             self.shape(),
             other.shape(),
@@ -196,37 +523,86 @@ impl Tensor {
         );
 
         let data: Vec<f32> = self
-            .storage
-            .as_slice()
-            .iter()
-            .zip(other.storage.as_slice())
-            .map(|(a, b)| a / b)
+            .to_vec()
+            .into_iter()
+            .zip(other.to_vec())
+            .map(|(a, b)| f(a, b))
             .collect();
 
         Tensor::from_vec(data, self.shape())
     }
 
+    /// Combine two tensors element by element with `f`, broadcasting their
+    /// shapes against each other.
+    ///
+    /// Shapes are aligned from the right; each pair of dimensions must be
+    /// equal or contain a 1, which is stretched to match the other:
+    /// ```text
+    ///   [2, 3] with [3]    -> [2, 3]     (row vector reused for each row)
+    ///   [2, 1] with [1, 3] -> [2, 3]     (outer-product style)
+    ///   [2, 3] with [2]    -> error      (3 vs 2)
+    /// ```
+    ///
+    /// # Panics
+    /// Panics if the shapes are not broadcast-compatible.
+    pub(crate) fn broadcast_op(&self, other: &Tensor, f: impl Fn(f32, f32) -> f32) -> Tensor {
+        let out_shape = broadcast_shapes(self.shape(), other.shape()).unwrap_or_else(|| {
+            panic!(
+                "Cannot broadcast shapes {:?} and {:?}",
+                self.shape(),
+                other.shape()
+            )
+        });
+
+        let (a, b) = (self.to_vec(), other.to_vec());
+        let a_strides = broadcast_strides(self.shape(), &out_shape);
+        let b_strides = broadcast_strides(other.shape(), &out_shape);
+
+        let n: usize = out_shape.iter().product();
+        let mut out = Vec::with_capacity(n);
+        if n > 0 {
+            let mut indices = vec![0; out_shape.len()];
+            loop {
+                let offset = |strides: &[usize]| -> usize {
+                    indices.iter().zip(strides).map(|(i, s)| i * s).sum()
+                };
+                out.push(f(a[offset(&a_strides)], b[offset(&b_strides)]));
+                if !next_index(&mut indices, &out_shape) {
+                    break;
+                }
+            }
+        }
+
+        Tensor::from_vec(out, &out_shape)
+    }
+
     /// Add a scalar to all elements
     pub fn scalar_add(&self, scalar: f32) -> Tensor {
-        let data: Vec<f32> = self.storage.as_slice().iter().map(|x| x + scalar).collect();
-        Tensor::from_vec(data, self.shape())
+        let out = self.unary_op(|x| x + scalar);
+        record(out, "scalar_add", &[self], |g| vec![g.clone()])
     }
 
     /// Multiply all elements by a scalar
     pub fn scalar_mul(&self, scalar: f32) -> Tensor {
-        let data: Vec<f32> = self.storage.as_slice().iter().map(|x| x * scalar).collect();
-        Tensor::from_vec(data, self.shape())
+        let out = self.unary_op(|x| x * scalar);
+        record(out, "scalar_mul", &[self], move |g| {
+            vec![g.scalar_mul(scalar)]
+        })
     }
 
     /// Negate all elements: -self
     pub fn neg(&self) -> Tensor {
-        let data: Vec<f32> = self.storage.as_slice().iter().map(|x| -x).collect();
-        Tensor::from_vec(data, self.shape())
+        let out = self.unary_op(|x| -x);
+        record(out, "neg", &[self], |g| vec![g.neg()])
     }
 
     /// Matrix multiplication: (M, K) @ (K, N) -> (M, N)
     ///
-    /// Uses the naive O(n³) algorithm. Correctness over performance.
+    /// Uses the naive O(n³) algorithm. Correctness over performance, except
+    /// that kernels are chosen automatically by size: tiny products (every
+    /// dimension at most 8, e.g. 4x4 transforms) are fully unrolled, and
+    /// large ones (every dimension at least 128) are split recursively into
+    /// cache-sized blocks. All give the same result.
     ///
     /// # Panics
     /// - Panics if tensors are not 2D
@@ -266,17 +642,14 @@ impl Tensor {
             m, k1, k2, n
         );
 
-        let mut result = Tensor::zeros(&[m, n]);
-        for i in 0..m {
-            for j in 0..n {
-                let mut sum = 0.0;
-                for k in 0..k1 {
-                    sum += self.get(&[i, k]) * other.get(&[k, j]);
-                }
-                result.set(&[i, j], sum);
-            }
-        }
-        result
+        let data = matmul::matmul(&self.to_vec(), &other.to_vec(), m, k1, n);
+        let result = Tensor::from_vec(data, &[m, n]);
+
+        let (a, b) = (self.clone(), other.clone());
+        record(result, "matmul", &[self, other], move |g| {
+            // dA = G @ Bᵀ, dB = Aᵀ @ G
+            vec![g.matmul(&b.t()), a.t().matmul(g)]
+        })
     }
 
     /// Transpose a 2D tensor (swap rows and columns).
@@ -312,7 +685,7 @@ impl Tensor {
                 result.set(&[j, i], self.get(&[i, j]));
             }
         }
-        result
+        record(result, "transpose", &[self], |g| vec![g.t()])
     }
 
     /// Shorthand for transpose (common notation).
@@ -320,17 +693,142 @@ impl Tensor {
         self.transpose()
     }
 
+    /// Reorder the dimensions: dimension `i` of the result is dimension
+    /// `order[i]` of `self`. The result is a strided view sharing the
+    /// storage, like [`Tensor::reshape`] of a contiguous tensor.
+    ///
+    /// # Panics
+    /// Panics if `order` is not a permutation of `0..ndim`.
+    ///
+    /// # Example
+    /// ```
+    /// use delta::tensor::Tensor;
+    /// let t = Tensor::from_vec((0..24).map(|i| i as f32).collect(), &[2, 3, 4]);
+    /// let p = t.permute(&[2, 0, 1]);
+    /// assert_eq!(p.shape(), &[4, 2, 3]);
+    /// assert_eq!(p.get(&[3, 1, 2]), t.get(&[1, 2, 3]));
+    /// ```
+    pub fn permute(&self, order: &[usize]) -> Tensor {
+        let shape = self.shape.permuted(order);
+        let out = Tensor {
+            storage: Rc::clone(&self.storage),
+            shape,
+            strides: order.iter().map(|&a| self.strides[a]).collect(),
+            offset: self.offset,
+            node: None,
+        };
+        let mut inverse = vec![0; order.len()];
+        for (i, &a) in order.iter().enumerate() {
+            inverse[a] = i;
+        }
+        record(out, "permute", &[self], move |g| vec![g.permute(&inverse)])
+    }
+
+    /// View the same elements with a different shape.
+    ///
+    /// Elements keep their row-major order. A contiguous tensor shares its
+    /// storage with the result; anything else is copied first.
+    ///
+    /// # Panics
+    /// Panics if the element counts differ.
+    ///
+    /// # Example
+    /// ```
+    /// use delta::tensor::Tensor;
+    /// let t = Tensor::from_vec(vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0], &[2, 3]);
+    /// let r = t.reshape(&[3, 2]);
+    /// assert_eq!(r.get(&[1, 0]), 3.0);
+    /// ```
+    pub fn reshape(&self, shape: &[usize]) -> Tensor {
+        let new_shape = Shape::new(shape);
+        assert_eq!(
+            new_shape.nelems(),
+            self.nelems(),
+            "Cannot reshape {:?} ({} elements) into {:?} ({} elements)",
+            self.shape(),
+            self.nelems(),
+            shape,
+            new_shape.nelems()
+        );
+
+        let out = if self.strides == self.shape.strides() {
+            Tensor {
+                storage: Rc::clone(&self.storage),
+                strides: new_shape.strides(),
+                shape: new_shape,
+                offset: self.offset,
+                node: None,
+            }
+        } else {
+            Tensor::from_vec(self.to_vec(), shape)
+        };
+
+        let in_shape = self.shape().to_vec();
+        record(out, "reshape", &[self], move |g| vec![g.reshape(&in_shape)])
+    }
+
+    /// Repeat the tensor along size-1 (or missing leading) dimensions to
+    /// make it `shape`, following the broadcasting rules of
+    /// [`Tensor::eq`].
+    ///
+    /// # Gradient
+    /// Every copy of an element contributes to its gradient, so the
+    /// backward pass sums the gradient over the broadcast dimensions.
+    ///
+    /// # Panics
+    /// Panics if the tensor cannot be broadcast to `shape`.
+    ///
+    /// # Example
+    /// ```
+    /// use delta::tensor::Tensor;
+    /// let row = Tensor::from_vec(vec![1.0, 2.0], &[2]);
+    /// let m = row.broadcast_to(&[3, 2]);
+    /// assert_eq!(m.to_vec(), vec![1.0, 2.0, 1.0, 2.0, 1.0, 2.0]);
+    /// ```
+    pub fn broadcast_to(&self, shape: &[usize]) -> Tensor {
+        assert!(
+            broadcast_shapes(self.shape(), shape).as_deref() == Some(shape),
+            "Cannot broadcast shape {:?} to {:?}",
+            self.shape(),
+            shape
+        );
+
+        let data = self.to_vec();
+        let strides = broadcast_strides(self.shape(), shape);
+        let n: usize = shape.iter().product();
+        let mut out = Vec::with_capacity(n);
+        if n > 0 {
+            let mut indices = vec![0; shape.len()];
+            loop {
+                let idx: usize = indices.iter().zip(&strides).map(|(i, s)| i * s).sum();
+                out.push(data[idx]);
+                if !next_index(&mut indices, shape) {
+                    break;
+                }
+            }
+        }
+
+        let out = Tensor::from_vec(out, shape);
+        let in_shape = self.shape().to_vec();
+        record(out, "broadcast_to", &[self], move |g| {
+            vec![g.sum_to(&in_shape)]
+        })
+    }
+
     /// Helper for recursive tensor formatting
     fn fmt_recursive(
         &self,
         f: &mut std::fmt::Formatter<'_>,
-        dim: usize,
-        offset: &mut usize,
+        indices: &mut Vec<usize>,
     ) -> std::fmt::Result {
+        let dim = indices.len();
         if dim == self.ndim() {
-            // Base case: print single element
-            write!(f, "{:.4}", self.storage.as_slice()[*offset])?;
-            *offset += 1;
+            // Base case: print single element, read through the view
+            write!(
+                f,
+                "{:.4}",
+                self.storage.as_slice()[self.linear_index(indices)]
+            )?;
             return Ok(());
         }
 
@@ -344,8 +842,6 @@ impl Tensor {
         for i in 0..size {
             if truncated && i == max_items / 2 {
                 // Skip middle elements
-                let skip = size - max_items;
-                *offset += skip * self.strides[dim];
                 write!(f, "..., ")?;
                 continue;
             }
@@ -354,7 +850,9 @@ impl Tensor {
                 continue;
             }
 
-            self.fmt_recursive(f, dim + 1, offset)?;
+            indices.push(i);
+            self.fmt_recursive(f, indices)?;
+            indices.pop();
 
             if i < size - 1 {
                 write!(f, ", ")?;
@@ -376,7 +874,7 @@ impl Tensor {
 impl std::fmt::Display for Tensor {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "Tensor(")?;
-        self.fmt_recursive(f, 0, &mut 0)?;
+        self.fmt_recursive(f, &mut Vec::with_capacity(self.ndim()))?;
         write!(f, ", shape={:?})", self.shape())
     }
 }
@@ -397,33 +895,72 @@ impl Neg for &Tensor {
     }
 }
 
+// ----- Binary operators -----
+//
+// Unlike the methods they stand for, the operators broadcast: `&m + &row`
+// works for a matrix and a row vector. A call site like `a * b + c` gives
+// no hint of which operand has which shape, so a failure names the
+// operator, both shapes and the conflicting dimensions.
+
+/// `op(lhs, rhs)` after broadcasting both to their common shape.
+///
+/// # Panics
+/// Panics if the shapes are not broadcast-compatible.
+fn broadcast_binary(
+    lhs: &Tensor,
+    rhs: &Tensor,
+    symbol: &str,
+    op: fn(&Tensor, &Tensor) -> Tensor,
+) -> Tensor {
+    if lhs.shape() == rhs.shape() {
+        return op(lhs, rhs);
+    }
+    let shape = broadcast_shapes(lhs.shape(), rhs.shape()).unwrap_or_else(|| {
+        panic!(
+            "Cannot broadcast {:?} {} {:?}: attempted shape {}",
+            lhs.shape(),
+            symbol,
+            rhs.shape(),
+            attempted_broadcast(lhs.shape(), rhs.shape())
+        )
+    });
+    let expand = |t: &Tensor| {
+        if t.shape() == shape.as_slice() {
+            t.clone()
+        } else {
+            t.broadcast_to(&shape)
+        }
+    };
+    op(&expand(lhs), &expand(rhs))
+}
+
 // ----- Add -----
 
 impl Add<Tensor> for Tensor {
     type Output = Tensor;
     fn add(self, rhs: Tensor) -> Self::Output {
-        Tensor::add(&self, &rhs)
+        broadcast_binary(&self, &rhs, "+", Tensor::add)
     }
 }
 
 impl Add<&Tensor> for Tensor {
     type Output = Tensor;
     fn add(self, rhs: &Tensor) -> Self::Output {
-        Tensor::add(&self, rhs)
+        broadcast_binary(&self, rhs, "+", Tensor::add)
     }
 }
 
 impl Add<Tensor> for &Tensor {
     type Output = Tensor;
     fn add(self, rhs: Tensor) -> Self::Output {
-        Tensor::add(self, &rhs)
+        broadcast_binary(self, &rhs, "+", Tensor::add)
     }
 }
 
 impl Add<&Tensor> for &Tensor {
     type Output = Tensor;
     fn add(self, rhs: &Tensor) -> Self::Output {
-        Tensor::add(self, rhs)
+        broadcast_binary(self, rhs, "+", Tensor::add)
     }
 }
 
@@ -432,28 +969,28 @@ impl Add<&Tensor> for &Tensor {
 impl Sub<Tensor> for Tensor {
     type Output = Tensor;
     fn sub(self, rhs: Tensor) -> Self::Output {
-        Tensor::sub(&self, &rhs)
+        broadcast_binary(&self, &rhs, "-", Tensor::sub)
     }
 }
 
 impl Sub<&Tensor> for Tensor {
     type Output = Tensor;
     fn sub(self, rhs: &Tensor) -> Self::Output {
-        Tensor::sub(&self, rhs)
+        broadcast_binary(&self, rhs, "-", Tensor::sub)
     }
 }
 
 impl Sub<Tensor> for &Tensor {
     type Output = Tensor;
     fn sub(self, rhs: Tensor) -> Self::Output {
-        Tensor::sub(self, &rhs)
+        broadcast_binary(self, &rhs, "-", Tensor::sub)
     }
 }
 
 impl Sub<&Tensor> for &Tensor {
     type Output = Tensor;
     fn sub(self, rhs: &Tensor) -> Self::Output {
-        Tensor::sub(self, rhs)
+        broadcast_binary(self, rhs, "-", Tensor::sub)
     }
 }
 
@@ -462,28 +999,28 @@ impl Sub<&Tensor> for &Tensor {
 impl Mul<Tensor> for Tensor {
     type Output = Tensor;
     fn mul(self, rhs: Tensor) -> Tensor {
-        Tensor::mul(&self, &rhs)
+        broadcast_binary(&self, &rhs, "*", Tensor::mul)
     }
 }
 
 impl Mul<&Tensor> for Tensor {
     type Output = Tensor;
     fn mul(self, rhs: &Tensor) -> Tensor {
-        Tensor::mul(&self, rhs)
+        broadcast_binary(&self, rhs, "*", Tensor::mul)
     }
 }
 
 impl Mul<Tensor> for &Tensor {
     type Output = Tensor;
     fn mul(self, rhs: Tensor) -> Tensor {
-        Tensor::mul(self, &rhs)
+        broadcast_binary(self, &rhs, "*", Tensor::mul)
     }
 }
 
 impl Mul<&Tensor> for &Tensor {
     type Output = Tensor;
     fn mul(self, rhs: &Tensor) -> Tensor {
-        Tensor::mul(self, rhs)
+        broadcast_binary(self, rhs, "*", Tensor::mul)
     }
 }
 
@@ -492,28 +1029,28 @@ impl Mul<&Tensor> for &Tensor {
 impl Div<Tensor> for Tensor {
     type Output = Tensor;
     fn div(self, rhs: Tensor) -> Tensor {
-        Tensor::div(&self, &rhs)
+        broadcast_binary(&self, &rhs, "/", Tensor::div)
     }
 }
 
 impl Div<&Tensor> for Tensor {
     type Output = Tensor;
     fn div(self, rhs: &Tensor) -> Tensor {
-        Tensor::div(&self, rhs)
+        broadcast_binary(&self, rhs, "/", Tensor::div)
     }
 }
 
 impl Div<Tensor> for &Tensor {
     type Output = Tensor;
     fn div(self, rhs: Tensor) -> Tensor {
-        Tensor::div(self, &rhs)
+        broadcast_binary(self, &rhs, "/", Tensor::div)
     }
 }
 
 impl Div<&Tensor> for &Tensor {
     type Output = Tensor;
     fn div(self, rhs: &Tensor) -> Tensor {
-        Tensor::div(self, rhs)
+        broadcast_binary(self, rhs, "/", Tensor::div)
     }
 }
 
@@ -551,6 +1088,7 @@ impl Mul<&Tensor> for f32 {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::autograd::testing::check_grad;
 
     #[test]
     fn test_zeros() {
@@ -591,6 +1129,123 @@ mod tests {
         assert_eq!(t.linear_index(&[1, 2]), 5);
     }
 
+    #[test]
+    fn test_to_vec() {
+        let t = Tensor::from_vec(vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0], &[2, 3]);
+        assert_eq!(t.to_vec(), vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+
+        // Scalar (0-d) tensor holds exactly one element
+        let s = Tensor::from_vec(vec![7.0], &[]);
+        assert_eq!(s.to_vec(), vec![7.0]);
+
+        assert!(Tensor::zeros(&[0, 3]).to_vec().is_empty());
+    }
+
+    #[test]
+    fn test_into_vec() {
+        let data = vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0];
+        let ptr = data.as_ptr();
+        let t = Tensor::from_vec(data, &[2, 3]).reshape(&[3, 2]);
+        let v = t.into_vec();
+        assert_eq!(v.as_ptr(), ptr);
+
+        // Shared storage is copied, leaving the other tensor intact
+        let a = Tensor::from_vec(vec![1.0, 2.0], &[2]);
+        let b = a.clone();
+        let ptr = a.storage.as_slice().as_ptr();
+        let v = a.into_vec();
+        assert_ne!(v.as_ptr(), ptr);
+        assert_eq!((v, b.to_vec()), (vec![1.0, 2.0], vec![1.0, 2.0]));
+
+        // Broadcast views come out in logical order
+        let row = Tensor::from_vec(vec![1.0, 2.0], &[1, 2]).broadcast_to(&[2, 2]);
+        assert_eq!(row.into_vec(), vec![1.0, 2.0, 1.0, 2.0]);
+    }
+
+    #[test]
+    fn test_permute() {
+        let t = Tensor::from_vec((0..6).map(|i| i as f32).collect(), &[1, 2, 3]);
+        let p = t.permute(&[2, 0, 1]);
+        assert_eq!(p.shape(), &[3, 1, 2]);
+        assert_eq!(p.to_vec(), vec![0.0, 3.0, 1.0, 4.0, 2.0, 5.0]);
+        // Reshaping a permuted view copies it into row-major order
+        assert_eq!(p.reshape(&[6]).to_vec(), p.to_vec());
+        check_grad(
+            |t| t[0].permute(&[1, 2, 0]).tanh(),
+            &[Tensor::from_vec(
+                vec![0.1, -0.4, 0.7, 1.2, -0.9, 0.3],
+                &[1, 2, 3],
+            )],
+        );
+    }
+
+    #[test]
+    fn test_map() {
+        let t = Tensor::from_vec(vec![1.0, 2.0, 3.0, 4.0], &[2, 2]);
+        let m = t.map(|x| x * 10.0 + 1.0);
+        assert_eq!(m.shape(), &[2, 2]);
+        assert_eq!(m.to_vec(), vec![11.0, 21.0, 31.0, 41.0]);
+        // Original untouched
+        assert_eq!(t.get(&[0, 0]), 1.0);
+    }
+
+    #[test]
+    fn test_zip_map() {
+        let a = Tensor::from_vec(vec![1.0, 2.0, 3.0], &[3]);
+        let b = Tensor::from_vec(vec![4.0, 5.0, 6.0], &[3]);
+        let c = a.zip_map(&b, |x, y| x * y - 1.0);
+        assert_eq!(c.to_vec(), vec![3.0, 9.0, 17.0]);
+    }
+
+    #[test]
+    fn test_zip_map_broadcast() {
+        // [2, 1] with [3] -> [2, 3]
+        let col = Tensor::from_vec(vec![10.0, 20.0], &[2, 1]);
+        let row = Tensor::from_vec(vec![1.0, 2.0, 3.0], &[3]);
+        let c = col.zip_map(&row, |x, y| x + y);
+        assert_eq!(c.shape(), &[2, 3]);
+        assert_eq!(c.to_vec(), vec![11.0, 12.0, 13.0, 21.0, 22.0, 23.0]);
+
+        // Scalar (0-d) operand broadcasts to anything
+        let s = Tensor::from_vec(vec![2.0], &[]);
+        assert_eq!(row.zip_map(&s, f32::powf).to_vec(), vec![1.0, 4.0, 9.0]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot broadcast")]
+    fn test_zip_map_incompatible() {
+        let a = Tensor::zeros(&[2, 3]);
+        let b = Tensor::zeros(&[4]);
+        a.zip_map(&b, |x, y| x + y);
+    }
+
+    #[test]
+    fn test_map_inplace() {
+        let mut t = Tensor::from_vec(vec![1.0, -2.0, 3.0, -4.0], &[2, 2]);
+        t.map_inplace(f32::abs);
+        assert_eq!(t.to_vec(), vec![1.0, 2.0, 3.0, 4.0]);
+        assert_eq!(t.shape(), &[2, 2]);
+    }
+
+    #[test]
+    fn test_map_inplace_strided() {
+        // A view onto the second column of a 2x3 buffer: offset 1, stride 3
+        let mut t = Tensor {
+            storage: Rc::new(Storage::from_vec(vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0])),
+            shape: Shape::new(&[2]),
+            strides: vec![3],
+            offset: 1,
+            node: None,
+        };
+        assert_eq!(t.to_vec(), vec![2.0, 5.0]);
+        assert_eq!(t.map(|x| -x).to_vec(), vec![-2.0, -5.0]);
+
+        t.map_inplace(|x| x * 100.0);
+        assert_eq!(t.to_vec(), vec![200.0, 500.0]);
+        // Elements outside the view are left alone
+        assert_eq!(t.storage.as_slice(), &[1.0, 200.0, 3.0, 4.0, 500.0, 6.0]);
+    }
+
     #[test]
     #[should_panic(expected = "Data length")]
     fn test_from_vec_shape_mismatch() {
@@ -669,7 +1324,8 @@ mod tests {
     fn test_add_shape_mismatch() {
         let a = Tensor::from_vec(vec![1.0, 2.0], &[2]);
         let b = Tensor::from_vec(vec![1.0, 2.0, 3.0], &[3]);
-        let _ = a.add(&b);
+        // The method, not the broadcasting operator
+        let _ = Tensor::add(&a, &b);
     }
 
     #[test]
@@ -680,6 +1336,22 @@ mod tests {
         assert!(s.contains("shape=[2, 3]"));
     }
 
+    #[test]
+    fn test_display_reads_views() {
+        let t = Tensor::from_vec((0..6).map(|v| v as f32).collect(), &[2, 3]);
+        let s = format!("{}", t.permute(&[1, 0]));
+        assert!(s.starts_with("Tensor([[0.0000, 3.0000], \n [1.0000, 4.0000]"));
+        // The second column, a view starting past the front of its storage
+        let column = Tensor {
+            storage: t.storage.clone(),
+            shape: Shape::new(&[2]),
+            strides: vec![3],
+            offset: 1,
+            node: None,
+        };
+        assert_eq!(format!("{}", column), "Tensor([1.0000, 4.0000], shape=[2])");
+    }
+
     #[test]
     fn test_operator_add() {
         let a = Tensor::from_vec(vec![1.0, 2.0], &[2]);
@@ -697,6 +1369,41 @@ mod tests {
         assert_eq!(c4.get(&[0]), 4.0);
     }
 
+    #[test]
+    fn test_operators_broadcast() {
+        let m = Tensor::from_vec(vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0], &[2, 3]);
+        let row = Tensor::from_vec(vec![10.0, 20.0, 30.0], &[3]);
+        let col = Tensor::from_vec(vec![2.0, 4.0], &[2, 1]);
+
+        assert_eq!(
+            (&m + &row).to_vec(),
+            vec![11.0, 22.0, 33.0, 14.0, 25.0, 36.0]
+        );
+        assert_eq!((&row - &m).to_vec(), vec![9.0, 18.0, 27.0, 6.0, 15.0, 24.0]);
+        assert_eq!((&m * &col).to_vec(), vec![2.0, 4.0, 6.0, 16.0, 20.0, 24.0]);
+        assert_eq!((&m / col).to_vec(), vec![0.5, 1.0, 1.5, 1.0, 1.25, 1.5]);
+        let outer = Tensor::from_vec(vec![1.0, 2.0], &[2, 1]) * row;
+        assert_eq!(outer.shape(), &[2, 3]);
+    }
+
+    #[test]
+    fn test_grad_operators_broadcast() {
+        check_grad(
+            |t| (&t[0] * &t[1] - &t[2]) / &t[1],
+            &[
+                Tensor::from_vec(vec![1.0, -2.0, 0.5, 3.0, 1.5, -1.0], &[2, 3]),
+                Tensor::from_vec(vec![2.0, -1.5, 3.0], &[3]),
+                Tensor::from_vec(vec![0.3, -0.7], &[2, 1]),
+            ],
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot broadcast [2, 3] * [2]: attempted shape [2, 3|2]")]
+    fn test_operator_broadcast_mismatch() {
+        let _ = Tensor::zeros(&[2, 3]) * Tensor::zeros(&[2]);
+    }
+
     #[test]
     fn test_operator_neg() {
         let a = Tensor::from_vec(vec![1.0, -2.0], &[2]);
@@ -811,4 +1518,169 @@ mod tests {
         let a = Tensor::zeros(&[2, 3, 4]);
         a.transpose();
     }
+
+    #[test]
+    fn test_clone_is_copy_on_write() {
+        let a = Tensor::from_vec(vec![1.0, 2.0], &[2]);
+        let mut b = a.clone();
+        b.set(&[0], 10.0);
+        assert_eq!(a.to_vec(), vec![1.0, 2.0]);
+        assert_eq!(b.to_vec(), vec![10.0, 2.0]);
+    }
+
+    #[test]
+    fn test_reshape() {
+        let t = Tensor::from_vec(vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0], &[2, 3]);
+        let r = t.reshape(&[3, 2]);
+        assert_eq!(r.shape(), &[3, 2]);
+        assert_eq!(r.to_vec(), t.to_vec());
+        assert_eq!(t.reshape(&[6]).get(&[4]), 5.0);
+        assert_eq!(
+            Tensor::from_vec(vec![7.0], &[1, 1]).reshape(&[]).get(&[]),
+            7.0
+        );
+    }
+
+    #[test]
+    fn test_reshape_strided() {
+        let t = Tensor {
+            storage: Rc::new(Storage::from_vec(vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0])),
+            shape: Shape::new(&[2, 2]),
+            strides: vec![1, 3],
+            offset: 0,
+            node: None,
+        };
+        assert_eq!(t.reshape(&[4]).to_vec(), vec![1.0, 4.0, 2.0, 5.0]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot reshape")]
+    fn test_reshape_mismatch() {
+        Tensor::zeros(&[2, 3]).reshape(&[4]);
+    }
+
+    #[test]
+    fn test_broadcast_to() {
+        let col = Tensor::from_vec(vec![1.0, 2.0], &[2, 1]);
+        let m = col.broadcast_to(&[2, 3]);
+        assert_eq!(m.to_vec(), vec![1.0, 1.0, 1.0, 2.0, 2.0, 2.0]);
+        let s = Tensor::from_vec(vec![5.0], &[]);
+        assert_eq!(s.broadcast_to(&[2, 2]).to_vec(), vec![5.0; 4]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot broadcast")]
+    fn test_broadcast_to_incompatible() {
+        Tensor::zeros(&[2, 3]).broadcast_to(&[3, 3]);
+    }
+
+    #[test]
+    fn test_detach() {
+        let x = Tensor::from_vec(vec![1.0, 2.0], &[2]).requires_grad(true);
+        let y = x.scalar_mul(2.0);
+        let d = y.detach();
+        assert!(d.node().is_none() && d.is_leaf());
+        assert!(Rc::ptr_eq(&d.storage, &y.storage));
+        assert_eq!(d.to_vec(), vec![2.0, 4.0]);
+
+        // Views keep their layout
+        let t = Tensor::from_vec(vec![1.0, 2.0, 3.0, 4.0], &[2, 2]).t();
+        assert_eq!(t.detach().to_vec(), t.to_vec());
+
+        // Ops on a detached tensor are constants to the graph
+        let loss = y.mul(&d).sum();
+        loss.backward();
+        assert_eq!(x.grad().unwrap().to_vec(), vec![4.0, 8.0]);
+    }
+
+    #[test]
+    fn test_requires_grad() {
+        let w = Tensor::from_vec(vec![1.0, 2.0], &[2]).requires_grad(true);
+        assert!(w.is_leaf());
+        assert!(w.grad().is_none());
+
+        let y = w.scalar_mul(3.0);
+        assert!(!y.is_leaf());
+        y.sum().backward();
+        assert_eq!(w.grad().unwrap().to_vec(), vec![3.0, 3.0]);
+        assert_eq!(y.grad().unwrap().to_vec(), vec![1.0, 1.0]);
+    }
+
+    #[test]
+    fn test_untracked_tensor() {
+        let t = Tensor::from_vec(vec![1.0], &[1]);
+        assert!(t.is_leaf());
+        assert!(t.scalar_mul(2.0).is_leaf());
+        assert!(t.grad().is_none());
+    }
+
+    #[test]
+    fn test_requires_grad_off() {
+        let w = Tensor::from_vec(vec![1.0], &[1])
+            .requires_grad(true)
+            .requires_grad(false);
+        assert!(w.scalar_mul(2.0).is_leaf());
+    }
+
+    #[test]
+    #[should_panic(expected = "only allowed on leaf tensors")]
+    fn test_requires_grad_off_non_leaf() {
+        let w = Tensor::from_vec(vec![1.0], &[1]).requires_grad(true);
+        w.scalar_mul(2.0).requires_grad(false);
+    }
+
+    #[test]
+    fn test_requires_grad_in_place() {
+        let w = Tensor::from_vec(vec![1.0], &[1]).requires_grad(true);
+        let x = Tensor::from_vec(vec![3.0], &[1]).requires_grad(true);
+        w.clone().requires_grad_(false);
+        assert!(!w.tracks_grad() && w.is_leaf());
+        Tensor::mul(&w, &x).sum().backward();
+        assert!(w.grad().is_none());
+        assert_eq!(x.grad().unwrap().to_vec(), vec![1.0]);
+
+        w.requires_grad_(true);
+        Tensor::mul(&w, &x).sum().backward();
+        assert_eq!(w.grad().unwrap().to_vec(), vec![3.0]);
+        // Switching off an untracked tensor is a no-op
+        Tensor::zeros(&[1]).requires_grad_(false);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "requires_grad_(true) needs a tensor created with requires_grad(true)"
+    )]
+    fn test_requires_grad_in_place_untracked() {
+        Tensor::zeros(&[1]).requires_grad_(true);
+    }
+
+    #[test]
+    fn test_grad_arithmetic() {
+        let a = Tensor::from_vec(vec![1.0, -2.0, 3.0, 0.5], &[2, 2]);
+        let b = Tensor::from_vec(vec![0.5, 4.0, -1.5, 2.0], &[2, 2]);
+        let inputs = [a, b];
+        check_grad(|t| Tensor::add(&t[0], &t[1]), &inputs);
+        check_grad(|t| Tensor::sub(&t[0], &t[1]), &inputs);
+        check_grad(|t| Tensor::mul(&t[0], &t[1]), &inputs);
+        check_grad(|t| Tensor::div(&t[0], &t[1]), &inputs);
+        check_grad(|t| -(&t[0] * 2.0).scalar_add(1.0), &inputs[..1]);
+    }
+
+    #[test]
+    fn test_grad_matmul() {
+        let a = Tensor::from_vec(vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0], &[2, 3]);
+        let b = Tensor::from_vec(vec![0.5, -1.0, 2.0, 0.0, 1.5, 1.0], &[3, 2]);
+        check_grad(|t| t[0].matmul(&t[1]), &[a.clone(), b]);
+        check_grad(|t| t[0].t(), &[a]);
+    }
+
+    #[test]
+    fn test_grad_shape_ops() {
+        let x = Tensor::from_vec(vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0], &[2, 3]);
+        check_grad(|t| t[0].reshape(&[3, 2]), std::slice::from_ref(&x));
+        let row = Tensor::from_vec(vec![1.0, 2.0, 3.0], &[3]);
+        check_grad(|t| t[0].broadcast_to(&[2, 2, 3]), &[row]);
+        let col = Tensor::from_vec(vec![1.0, 2.0], &[2, 1]);
+        check_grad(|t| t[0].broadcast_to(&[2, 4]), &[col]);
+    }
 }